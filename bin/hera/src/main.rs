@@ -4,12 +4,463 @@
 #![doc(issue_tracker_base_url = "https://github.com/paradigmxyz/op-rs/issues/")]
 #![cfg_attr(docsrs, feature(doc_cfg, doc_auto_cfg))]
 
-use eyre::Result;
+use std::{
+    net::Ipv4Addr,
+    path::{Path, PathBuf},
+};
 
-fn main() -> Result<()> {
-    rollup::init_telemetry_stack(8090)?;
+use clap::{Args, Parser, Subcommand};
+use eyre::{bail, Context, Result};
+use op_net::{
+    discovery::builder::DiscoveryBuilder, keys::load_keypair, types::address::NetworkAddress,
+};
+use rollup::{
+    BatchSubmitter, ChainsConfig, ConfigReloader, Driver, HeraAdminRpc, HeraArgsExt, HeraRpc,
+    LogConfig, LogFormat, LogRotation, MetricsStyle, MultiDriver, OtlpConfig, OutputFetcher,
+    OutputProposer, SequencerDriver, SupervisorRpc, DEFAULT_OTLP_FILTER, DEFAULT_OTLP_SERVICE_NAME,
+};
+use url::Url;
+
+/// The default port to serve Prometheus metrics on.
+const DEFAULT_METRICS_PORT: u16 = 8090;
+
+/// The Hera CLI arguments.
+#[derive(Debug, Clone, Parser)]
+#[command(about = "Hera OP Stack Rollup node")]
+struct HeraArgs {
+    /// The port to serve Prometheus metrics on.
+    #[clap(long = "metrics.port", default_value_t = DEFAULT_METRICS_PORT)]
+    metrics_port: u16,
+
+    /// The naming scheme of the exported metrics.
+    ///
+    /// - hera: export metrics under Hera's own names (`hera_*`).
+    /// - op-node: export metrics under op-node's names and histogram buckets
+    ///   (`op_node_default_*`), so existing op-node dashboards and alerts keep working.
+    #[clap(long = "metrics.style", default_value = "hera")]
+    metrics_style: MetricsStyle,
+
+    /// The format of the log lines: pretty or json.
+    #[clap(long = "log.format", default_value = "pretty")]
+    log_format: LogFormat,
+
+    /// The log filter directives, e.g. `hera=debug,op_net=info`.
+    /// Defaults to `RUST_LOG`, or `hera=info` if unset.
+    #[clap(long = "log.filter")]
+    log_filter: Option<String>,
+
+    /// A file to also write the logs to, suffixed with the date once rotated.
+    #[clap(long = "log.file")]
+    log_file: Option<PathBuf>,
+
+    /// How often the log file is rotated: minutely, hourly, daily or never.
+    #[clap(long = "log.rotation", default_value = "daily")]
+    log_rotation: LogRotation,
+
+    /// The number of rotated log files to keep. All of them are kept if unset.
+    #[clap(long = "log.max-files")]
+    log_max_files: Option<usize>,
+
+    /// The gRPC endpoint of an OTLP collector to export traces to, e.g.
+    /// `http://localhost:4317`. Disabled if unset.
+    #[clap(long = "otlp.endpoint")]
+    otlp_endpoint: Option<Url>,
+
+    /// The ratio of the traces exported to the OTLP collector, between 0 and 1.
+    #[clap(long = "otlp.sample-ratio", default_value_t = 1.0)]
+    otlp_sample_ratio: f64,
+
+    /// The service name the traces are exported under.
+    #[clap(long = "otlp.service-name", default_value = DEFAULT_OTLP_SERVICE_NAME)]
+    otlp_service_name: String,
+
+    /// The filter directives of the spans exported to the OTLP collector.
+    #[clap(long = "otlp.filter", default_value = DEFAULT_OTLP_FILTER)]
+    otlp_filter: String,
+
+    /// The subcommand to run.
+    #[command(subcommand)]
+    command: Option<HeraCommand>,
+}
+
+/// The Hera subcommands.
+#[derive(Debug, Clone, Subcommand)]
+enum HeraCommand {
+    /// Run a standalone rollup node, deriving from an external L1 RPC and beacon
+    /// endpoint and validating against an external L2 execution client.
+    Node(Box<HeraArgsExt>),
+    /// Fault proof tooling.
+    #[command(subcommand)]
+    Prove(ProveCommand),
+    /// Run the derivation pipeline offline over a range of L1 blocks, and write the
+    /// derived payload attributes to JSON files, e.g. to debug a bad batch.
+    Derive(DeriveArgs),
+    /// Print the peer ID and ENR of a node key file.
+    P2pId(P2pIdArgs),
+    /// Check that the L2 execution client was initialized with the genesis block of the
+    /// rollup config.
+    GenesisCheck(Box<HeraArgsExt>),
+}
+
+/// The arguments of the `derive` command.
+#[derive(Debug, Clone, Args)]
+struct DeriveArgs {
+    /// The first L1 block of the range.
+    #[clap(long = "from")]
+    from: u64,
+
+    /// The last L1 block of the range.
+    #[clap(long = "to")]
+    to: u64,
+
+    /// The directory to write the `<l2_block>.json` attributes files to.
+    #[clap(long = "output", short = 'o')]
+    output: PathBuf,
+
+    /// The rollup node configuration.
+    #[clap(flatten)]
+    hera: HeraArgsExt,
+}
+
+/// The arguments of the `p2p-id` command.
+#[derive(Debug, Clone, Args)]
+struct P2pIdArgs {
+    /// The file of the hex-encoded secp256k1 node key, e.g. the `--p2p.priv-key-path`
+    /// of the node.
+    #[clap(long = "p2p.priv-key-path")]
+    priv_key_path: PathBuf,
+
+    /// The L2 chain ID advertised in the `opstack` entry of the ENR.
+    #[clap(long = "l2-chain-id", default_value_t = 10)]
+    l2_chain_id: u64,
+
+    /// The public IP address advertised in the ENR. The ENR has no IP address if unset.
+    #[clap(long = "p2p.advertise-ip")]
+    advertise_ip: Option<Ipv4Addr>,
+
+    /// The TCP and UDP port advertised in the ENR.
+    #[clap(long = "p2p.advertise-port", default_value_t = 9222)]
+    advertise_port: u16,
+}
+
+/// The fault proof subcommands.
+#[derive(Debug, Clone, Subcommand)]
+enum ProveCommand {
+    /// Derive a single L2 block and export the preimage witness and boot info
+    /// needed to re-execute its derivation inside the fault proof program.
+    Prestate(PrestateArgs),
+}
+
+/// The arguments of the `prove prestate` command.
+#[derive(Debug, Clone, Args)]
+struct PrestateArgs {
+    /// The number of the L2 block to derive.
+    #[clap(long = "l2-block")]
+    l2_block: u64,
+
+    /// The file to write the JSON encoded witness to.
+    #[clap(long = "output", short = 'o')]
+    output: PathBuf,
+
+    /// The rollup node configuration.
+    #[clap(flatten)]
+    hera: HeraArgsExt,
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args = HeraArgs::parse();
+    let log = LogConfig {
+        format: args.log_format,
+        filter: args.log_filter.clone(),
+        file: args.log_file.clone(),
+        rotation: args.log_rotation,
+        max_files: args.log_max_files,
+    };
+    let otlp = args.otlp_endpoint.clone().map(|endpoint| OtlpConfig {
+        sample_ratio: args.otlp_sample_ratio.clamp(0.0, 1.0),
+        service_name: args.otlp_service_name.clone(),
+        filter: args.otlp_filter.clone(),
+        ..OtlpConfig::new(endpoint)
+    });
+    rollup::init_telemetry_stack(args.metrics_port, args.metrics_style, &log, otlp.as_ref())?;
 
     tracing::info!("Hera OP Stack Rollup node");
 
+    let result = run(args.command).await;
+    rollup::shutdown_telemetry();
+    result
+}
+
+/// Runs the given subcommand, if any.
+async fn run(command: Option<HeraCommand>) -> Result<()> {
+    match command {
+        Some(HeraCommand::Node(hera)) => run_node(*hera).await?,
+        Some(HeraCommand::Prove(ProveCommand::Prestate(prestate))) => {
+            let cfg = prestate.hera.rollup_config()?;
+            let witness = rollup::generate_prestate(&prestate.hera, cfg, prestate.l2_block).await?;
+            let json = serde_json::to_vec(&witness)?;
+            std::fs::write(&prestate.output, json).wrap_err("Failed to write witness")?;
+            tracing::info!("Wrote prestate witness to {:?}", prestate.output);
+        }
+        Some(HeraCommand::Derive(derive)) => {
+            let cfg = derive.hera.rollup_config()?;
+            rollup::dump_derivation(&derive.hera, cfg, derive.from, derive.to, &derive.output)
+                .await?;
+        }
+        Some(HeraCommand::P2pId(args)) => print_p2p_id(&args)?,
+        Some(HeraCommand::GenesisCheck(hera)) => {
+            let cfg = hera.rollup_config()?;
+            rollup::check_l2_genesis(&cfg, hera.l2_rpc_url.clone())
+                .await
+                .wrap_err("L2 genesis check failed")?;
+            tracing::info!(
+                "L2 execution client at {} has the genesis block {} of chain ID {}",
+                hera.l2_rpc_url,
+                cfg.genesis.l2.hash,
+                cfg.l2_chain_id
+            );
+        }
+        None => {}
+    }
+
     Ok(())
 }
+
+/// Prints the peer ID and ENR of the node key file of the given arguments.
+fn print_p2p_id(args: &P2pIdArgs) -> Result<()> {
+    if !args.priv_key_path.exists() {
+        bail!("Node key file {:?} does not exist", args.priv_key_path);
+    }
+    let keypair = load_keypair(&args.priv_key_path)?;
+    let peer_id = keypair.public().to_peer_id();
+    let ip = args.advertise_ip.unwrap_or(Ipv4Addr::UNSPECIFIED);
+    let mut discovery = DiscoveryBuilder::new()
+        .with_address(NetworkAddress { ip, port: args.advertise_port })
+        .with_chain_id(args.l2_chain_id)
+        .with_keypair(keypair);
+    if let Some(ip) = args.advertise_ip {
+        discovery = discovery.with_advertised_ip(ip.into());
+    }
+    let enr = discovery.local_enr()?;
+    println!("Peer ID: {}", peer_id);
+    println!("ENR: {}", enr.to_base64());
+    Ok(())
+}
+
+/// Runs the standalone rollup node until the L1 head notifications are closed, ctrl-C
+/// is received or networking fails.
+async fn run_node(hera: HeraArgsExt) -> Result<()> {
+    if let Some(path) = &hera.chains_file {
+        return run_multi_node(&hera, path).await;
+    }
+    let cfg = hera.rollup_config()?;
+    let network = hera.p2p.start_network(&cfg)?;
+    if let (Some(network), Some(watcher)) = (&network, hera.unsafe_signer_watcher(&cfg)) {
+        watcher.spawn(network.unsafe_block_signer.clone());
+    }
+
+    let mut driver = Driver::standalone(hera.clone(), cfg.clone()).await?;
+    if let Some(network) = &network {
+        driver.events().forward_unsafe_blocks(network.subscribe_unsafe_blocks());
+    }
+    let rpc = match hera.rpc_socket() {
+        Some(addr) => {
+            let mut rpc = HeraRpc::new(network.as_ref().map(|n| n.node_info.clone()))
+                .with_sync_status(driver.sync_status())
+                .with_events(driver.events())
+                .with_rollup_config(cfg.clone())
+                .with_outputs(OutputFetcher::new_http(hera.l2_rpc_url.clone(), cfg.clone()));
+            if let Some(system_config) = driver.system_config() {
+                rpc = rpc.with_system_config(system_config);
+            }
+            if let Some(tracker) = hera.cross_safety_tracker(&cfg)? {
+                let supervisor = SupervisorRpc::new(tracker.validator());
+                let cross_safety = tracker.spawn(driver.sync_status());
+                rpc = rpc.with_cross_safety(cross_safety.clone()).with_supervisor(
+                    supervisor.with_chain(
+                        cfg.l2_chain_id,
+                        driver.sync_status(),
+                        Some(cross_safety),
+                    ),
+                );
+            }
+            Some(rpc.serve(addr).await?)
+        }
+        None => None,
+    };
+    let mut sequencer = match hera.sequencer_enabled {
+        true => {
+            let mut sequencer = SequencerDriver::standalone(&hera, cfg.clone())
+                .await?
+                .with_sync_status(driver.sync_status());
+            if let Some(network) = &network {
+                sequencer = sequencer.with_publisher(network.sequencer.clone());
+            }
+            Some(sequencer)
+        }
+        false => None,
+    };
+    let ingress = match (hera.p2p.ingress, &network) {
+        (Some(addr), Some(network)) => {
+            let mut rpc = HeraAdminRpc::new(Some(network.injector.clone()))
+                .with_republish(hera.p2p.republish)
+                .with_peers(network.peers.clone(), network.node_info.clone());
+            if let Some(sequencer) = &mut sequencer {
+                rpc = rpc.with_sequencer(sequencer.control());
+            }
+            Some(rpc.serve(addr).await?)
+        }
+        _ => None,
+    };
+    let batcher = match hera.batcher_enabled {
+        true => Some(BatchSubmitter::standalone(&hera, cfg.clone(), driver.sync_status())?),
+        false => None,
+    };
+    let proposer = match hera.proposer_enabled {
+        true => Some(OutputProposer::standalone(&hera, cfg.clone(), driver.sync_status())?),
+        false => None,
+    };
+    if let Some(path) = &hera.config_file {
+        let mut reloader = ConfigReloader::new(path.clone(), hera.clone(), cfg, driver.events())?;
+        if let Some(network) = &network {
+            reloader = reloader.with_peers(network.peers.clone());
+        }
+        driver = driver.with_validator(reloader.validator());
+        reloader.spawn()?;
+    }
+
+    tracing::info!("Starting standalone rollup node");
+    let result = tokio::select! {
+        result = driver.start() => result,
+        // The sequencer only stops on an unrecoverable error.
+        result = async {
+            match sequencer {
+                Some(sequencer) => sequencer.start().await,
+                None => std::future::pending().await,
+            }
+        } => result,
+        // Likewise for the batcher and the proposer.
+        result = async {
+            match batcher {
+                Some(batcher) => batcher.start().await,
+                None => std::future::pending().await,
+            }
+        } => result,
+        result = async {
+            match proposer {
+                Some(proposer) => proposer.start().await,
+                None => std::future::pending().await,
+            }
+        } => result,
+        _ = tokio::signal::ctrl_c() => {
+            tracing::info!("Received ctrl-C, shutting down");
+            Ok(())
+        }
+        // Networking is disabled if there is no handle, so it never exits.
+        status = async {
+            match &network {
+                Some(network) => network.wait_for_exit().await,
+                None => std::future::pending().await,
+            }
+        } => {
+            Err(eyre::eyre!("Networking stopped: {}", status))
+        }
+    };
+    if let Some(network) = network {
+        network.shutdown();
+        if let Err(err) = network.join().await {
+            tracing::warn!("Networking did not shut down cleanly: {:?}", err);
+        }
+    }
+    if let Some(ingress) = ingress {
+        _ = ingress.stop();
+    }
+    if let Some(rpc) = rpc {
+        _ = rpc.stop();
+    }
+    result
+}
+
+/// Runs the standalone rollup nodes of the chains listed in the given file, sharing the
+/// L1 chain tracking and the p2p networking, until a driver stops, ctrl-C is received or
+/// networking fails.
+async fn run_multi_node(hera: &HeraArgsExt, path: &Path) -> Result<()> {
+    let chains = ChainsConfig::load(path)?.resolve(hera)?;
+    // The first chain owns the network, which gossips the blocks of the others too.
+    let (first, others) = chains.split_first().expect("resolved chains are not empty");
+    let others: Vec<_> = others
+        .iter()
+        .filter_map(|chain| Some((chain.cfg.l2_chain_id, chain.args.p2p.unsafe_block_signer?)))
+        .collect();
+    let network = first.args.p2p.start_shared_network(&first.cfg, &others)?;
+
+    let driver = MultiDriver::standalone(hera, chains.clone()).await?;
+    // The supervisor namespace of every chain serves the heads of all of them.
+    let mut supervisor = None;
+    let mut cross_safety = Vec::new();
+    for chain in &chains {
+        let (Some(tracker), Some(status)) =
+            (chain.args.cross_safety_tracker(&chain.cfg)?, driver.sync_status(&chain.name))
+        else {
+            cross_safety.push(None);
+            continue;
+        };
+        let validator = tracker.validator();
+        let recv = tracker.spawn(status.clone());
+        supervisor = Some(supervisor.unwrap_or_else(|| SupervisorRpc::new(validator)).with_chain(
+            chain.cfg.l2_chain_id,
+            status,
+            Some(recv.clone()),
+        ));
+        cross_safety.push(Some(recv));
+    }
+
+    let mut rpcs = Vec::new();
+    for (chain, cross_safety) in chains.iter().zip(cross_safety) {
+        let (Some(addr), Some(status), Some(events)) =
+            (chain.args.rpc_socket(), driver.sync_status(&chain.name), driver.events(&chain.name))
+        else {
+            continue;
+        };
+        let mut rpc = HeraRpc::new(network.as_ref().map(|n| n.node_info.clone()))
+            .with_sync_status(status)
+            .with_events(events)
+            .with_rollup_config(chain.cfg.clone())
+            .with_outputs(OutputFetcher::new_http(
+                chain.args.l2_rpc_url.clone(),
+                chain.cfg.clone(),
+            ));
+        if let (Some(cross_safety), Some(supervisor)) = (cross_safety, &supervisor) {
+            rpc = rpc.with_cross_safety(cross_safety).with_supervisor(supervisor.clone());
+        }
+        rpcs.push(rpc.serve(addr).await?);
+    }
+
+    tracing::info!("Starting standalone rollup node for {} chains", chains.len());
+    let result = tokio::select! {
+        result = driver.start() => result,
+        _ = tokio::signal::ctrl_c() => {
+            tracing::info!("Received ctrl-C, shutting down");
+            Ok(())
+        }
+        status = async {
+            match &network {
+                Some(network) => network.wait_for_exit().await,
+                None => std::future::pending().await,
+            }
+        } => {
+            Err(eyre::eyre!("Networking stopped: {}", status))
+        }
+    };
+    if let Some(network) = network {
+        network.shutdown();
+        if let Err(err) = network.join().await {
+            tracing::warn!("Networking did not shut down cleanly: {:?}", err);
+        }
+    }
+    for rpc in rpcs {
+        _ = rpc.stop();
+    }
+    result
+}
@@ -5,17 +5,17 @@
 #![cfg_attr(docsrs, feature(doc_cfg, doc_auto_cfg))]
 #![cfg_attr(not(test), warn(unused_crate_dependencies))]
 
-use std::{fs::File, sync::Arc};
-
 use clap::Parser;
-use eyre::{bail, Context, Result};
+use eyre::{bail, Result};
 use reth::cli::Cli;
 use reth_node_ethereum::EthereumNode;
-use serde_json::from_reader;
-use superchain_registry::ROLLUP_CONFIGS;
-use tracing::{debug, info, warn};
+use tokio::sync::watch;
+use tracing::{error, info, warn};
 
-use rollup::{Driver, HeraArgsExt, HERA_EXEX_ID};
+use rollup::{
+    ConfigReloader, Driver, DriverEvents, HeraAdminApiServer, HeraAdminRpc, HeraArgsExt, HeraRpc,
+    OutputFetcher, SequencerDriver, SupervisorRpc, SyncStatus, HERA_EXEX_ID,
+};
 
 /// The Reth CLI arguments with optional Hera Execution Extension support.
 #[derive(Debug, Clone, Parser)]
@@ -40,25 +40,119 @@ fn main() -> Result<()> {
                 bail!("Hera Execution Extension configuration is required when the `hera` flag is set");
             };
 
-            let cfg = match &hera_args.l2_config_file {
-                Some(path) => {
-                    info!("Loading l2 config from file: {:?}", path);
-                    let file = File::open(path).wrap_err("Failed to open l2 config file")?;
-                    Arc::new(from_reader(file).wrap_err("Failed to read l2 config file")?)
+            if hera_args.chains_file.is_some() {
+                bail!("Multiple chains are only supported by the standalone node");
+            }
+            let cfg = hera_args.rollup_config()?;
+
+            // Keep the network handle to shut networking down once the node exits.
+            let network = hera_args.p2p.start_network(&cfg)?;
+            // The driver is created with the ExEx, so it publishes its status on this channel.
+            let (status, status_recv) = watch::channel(SyncStatus::default());
+            // Likewise for its events.
+            let events = DriverEvents::default();
+            if let Some(network) = &network {
+                events.forward_unsafe_blocks(network.subscribe_unsafe_blocks());
+            }
+            let mut rpc = HeraRpc::new(network.as_ref().map(|n| n.node_info.clone()))
+                .with_sync_status(status_recv.clone())
+                .with_events(events.clone())
+                .with_rollup_config(cfg.clone())
+                .with_outputs(OutputFetcher::new_http(hera_args.l2_rpc_url.clone(), cfg.clone()));
+            if let Some(tracker) = hera_args.cross_safety_tracker(&cfg)? {
+                let supervisor = SupervisorRpc::new(tracker.validator());
+                let cross_safety = tracker.spawn(status_recv.clone());
+                rpc = rpc.with_cross_safety(cross_safety.clone()).with_supervisor(
+                    supervisor.with_chain(cfg.l2_chain_id, status_recv.clone(), Some(cross_safety)),
+                );
+            }
+            let rpc_server = match hera_args.rpc_socket() {
+                Some(addr) => Some(rpc.clone().serve(addr).await?),
+                None => None,
+            };
+            let mut admin_rpc = HeraAdminRpc::new(network.as_ref().map(|n| n.injector.clone()))
+                .with_republish(hera_args.p2p.republish);
+            if let Some(network) = &network {
+                admin_rpc = admin_rpc.with_peers(network.peers.clone(), network.node_info.clone());
+            }
+            let sequencer = match hera_args.sequencer_enabled {
+                true => {
+                    let mut sequencer = SequencerDriver::standalone(&hera_args, cfg.clone())
+                        .await?
+                        .with_sync_status(status_recv);
+                    if let Some(network) = &network {
+                        sequencer = sequencer.with_publisher(network.sequencer.clone());
+                    }
+                    admin_rpc = admin_rpc.with_sequencer(sequencer.control());
+                    Some(sequencer)
                 }
-                None => {
-                    debug!("Loading l2 config from superchain registry");
-                    let Some(cfg) = ROLLUP_CONFIGS.get(&hera_args.l2_chain_id).cloned() else {
-                        bail!("Failed to find l2 config for chain ID {}", hera_args.l2_chain_id);
-                    };
-                    Arc::new(cfg)
+                false => None,
+            };
+            let ingress = match hera_args.p2p.ingress {
+                Some(addr) => Some(admin_rpc.clone().serve(addr).await?),
+                None => None,
+            };
+
+            // Reload the settings of the config file on SIGHUP, if any.
+            let validator = match &hera_args.config_file {
+                Some(path) => {
+                    let mut reloader =
+                        ConfigReloader::new(path.clone(), hera_args.clone(), cfg.clone(), events.clone())?;
+                    if let Some(network) = &network {
+                        reloader = reloader.with_peers(network.peers.clone());
+                    }
+                    let validator = reloader.validator();
+                    reloader.spawn()?;
+                    Some(validator)
                 }
+                None => None,
             };
 
             let node = EthereumNode::default();
-            let hera = move |ctx| async { Ok(Driver::exex(ctx, hera_args, cfg).start()) };
-            let handle = builder.node(node).install_exex(HERA_EXEX_ID, hera).launch().await?;
-            handle.wait_for_node_exit().await
+            let hera = move |ctx| async move {
+                let mut driver =
+                    Driver::exex(ctx, hera_args, cfg).await?.with_sync_status(status).with_events(events);
+                if let Some(validator) = validator {
+                    driver = driver.with_validator(validator);
+                }
+                Ok(driver.start())
+            };
+            let handle = builder
+                .node(node)
+                .extend_rpc_modules(move |ctx| {
+                    ctx.modules.merge_configured(rpc.into_module()?)?;
+                    // Payload injection is only served on the authenticated endpoint.
+                    ctx.auth_module.merge_auth_methods(admin_rpc.into_rpc())?;
+                    Ok(())
+                })
+                .install_exex(HERA_EXEX_ID, hera)
+                .launch()
+                .await?;
+            // The sequencer only stops on an unrecoverable error, or with the node.
+            let sequencer = sequencer.map(|sequencer| {
+                tokio::spawn(async move {
+                    if let Err(err) = sequencer.start().await {
+                        error!("Sequencer stopped: {:?}", err);
+                    }
+                })
+            });
+            let exit = handle.wait_for_node_exit().await;
+            if let Some(sequencer) = sequencer {
+                sequencer.abort();
+            }
+            if let Some(network) = network {
+                network.shutdown();
+                if let Err(err) = network.join().await {
+                    warn!("Networking did not shut down cleanly: {:?}", err);
+                }
+            }
+            if let Some(ingress) = ingress {
+                _ = ingress.stop();
+            }
+            if let Some(rpc_server) = rpc_server {
+                _ = rpc_server.stop();
+            }
+            exit
         } else {
             warn!("Running Reth without the Hera Execution Extension");
             let node = EthereumNode::default();
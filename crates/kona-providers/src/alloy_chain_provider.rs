@@ -0,0 +1,294 @@
+//! Online Chain Provider
+
+use alloc::{collections::VecDeque, sync::Arc, vec::Vec};
+use core::{hash::Hash, marker::PhantomData};
+
+use alloy::{
+    consensus::{Header, Receipt, ReceiptEnvelope, TxEnvelope},
+    eips::eip2718::Decodable2718,
+    primitives::{Bytes, B256},
+    providers::{network::primitives::BlockTransactionsKind, Provider, ReqwestProvider},
+    rpc::types::BlockTransactions,
+    transports::{
+        http::{Client, Http},
+        Transport,
+    },
+};
+use alloy_rlp::Decodable;
+use async_trait::async_trait;
+use hashbrown::HashMap;
+use kona_derive::traits::ChainProvider;
+use kona_primitives::BlockInfo;
+use parking_lot::Mutex;
+use url::Url;
+
+/// The default number of entries kept in each cache of an [AlloyChainProvider].
+pub const DEFAULT_CHAIN_PROVIDER_CACHE_SIZE: usize = 256;
+
+/// A [ChainProvider] that fetches L1 chain data over RPC from any alloy [Provider].
+///
+/// Headers, receipts and block transactions are cached by block hash, and the caches
+/// are shared between clones of the provider. Headers and receipts are fetched in
+/// their raw encoding with the `debug_getRawHeader` and `debug_getRawReceipts` methods.
+#[derive(Debug)]
+pub struct AlloyChainProvider<P = ReqwestProvider, T = Http<Client>> {
+    /// The inner alloy provider.
+    inner: P,
+    /// The caches of fetched chain data.
+    caches: Arc<Mutex<Caches>>,
+    /// The transport of the inner provider.
+    _transport: PhantomData<fn() -> T>,
+}
+
+impl<P: Clone, T> Clone for AlloyChainProvider<P, T> {
+    fn clone(&self) -> Self {
+        Self { inner: self.inner.clone(), caches: self.caches.clone(), _transport: PhantomData }
+    }
+}
+
+impl AlloyChainProvider<ReqwestProvider, Http<Client>> {
+    /// Creates a new [AlloyChainProvider] over HTTP.
+    pub fn new_http(url: Url) -> Self {
+        Self::new(ReqwestProvider::new_http(url), DEFAULT_CHAIN_PROVIDER_CACHE_SIZE)
+    }
+}
+
+impl<P, T> AlloyChainProvider<P, T> {
+    /// Creates a new [AlloyChainProvider] over the given provider, caching up to `cap`
+    /// entries of each kind of chain data.
+    pub fn new(inner: P, cap: usize) -> Self {
+        Self { inner, caches: Arc::new(Mutex::new(Caches::new(cap))), _transport: PhantomData }
+    }
+
+    /// Returns the inner alloy provider.
+    pub const fn inner(&self) -> &P {
+        &self.inner
+    }
+}
+
+impl<P, T> AlloyChainProvider<P, T>
+where
+    P: Provider<T> + Send + Sync,
+    T: Transport + Clone,
+{
+    /// Fetches and decodes the raw header of the given block.
+    async fn raw_header(&self, block: impl Into<RawBlockId>) -> anyhow::Result<Header> {
+        let raw: Bytes = self
+            .inner
+            .raw_request("debug_getRawHeader".into(), [block.into().0])
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to fetch raw header: {:?}", e))?;
+        Header::decode(&mut raw.as_ref())
+            .map_err(|e| anyhow::anyhow!("Failed to decode raw header: {:?}", e))
+    }
+}
+
+#[async_trait]
+impl<P, T> ChainProvider for AlloyChainProvider<P, T>
+where
+    P: Provider<T> + Send + Sync,
+    T: Transport + Clone,
+{
+    async fn header_by_hash(&mut self, hash: B256) -> anyhow::Result<Header> {
+        if let Some(header) = self.caches.lock().headers.get(&hash) {
+            return Ok(header);
+        }
+
+        let header = self.raw_header(hash).await?;
+        self.caches.lock().headers.insert(hash, header.clone());
+        Ok(header)
+    }
+
+    async fn block_info_by_number(&mut self, number: u64) -> anyhow::Result<BlockInfo> {
+        // The block at a given number may be reorged, so only the header is cached by hash.
+        let header = self.raw_header(number).await?;
+        let hash = header.hash_slow();
+        let info = block_info(hash, &header);
+        self.caches.lock().headers.insert(hash, header);
+        Ok(info)
+    }
+
+    async fn receipts_by_hash(&mut self, hash: B256) -> anyhow::Result<Vec<Receipt>> {
+        if let Some(receipts) = self.caches.lock().receipts.get(&hash) {
+            return Ok(receipts);
+        }
+
+        let raw: Vec<Bytes> =
+            self.inner
+                .raw_request("debug_getRawReceipts".into(), [hash])
+                .await
+                .map_err(|e| anyhow::anyhow!("Failed to fetch raw receipts: {:?}", e))?;
+        let receipts = raw
+            .iter()
+            .map(|raw| {
+                let envelope = ReceiptEnvelope::decode_2718(&mut raw.as_ref())
+                    .map_err(|e| anyhow::anyhow!("Failed to decode receipt: {:?}", e))?;
+                envelope
+                    .as_receipt()
+                    .cloned()
+                    .ok_or_else(|| anyhow::anyhow!("Unsupported receipt type"))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        self.caches.lock().receipts.insert(hash, receipts.clone());
+        Ok(receipts)
+    }
+
+    async fn block_info_and_transactions_by_hash(
+        &mut self,
+        hash: B256,
+    ) -> anyhow::Result<(BlockInfo, Vec<TxEnvelope>)> {
+        if let Some(block) = self.caches.lock().blocks.get(&hash) {
+            return Ok(block);
+        }
+
+        let block = self
+            .inner
+            .get_block(hash.into(), BlockTransactionsKind::Full)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to fetch block: {:?}", e))?
+            .ok_or_else(|| anyhow::anyhow!("Block not found"))?;
+        let BlockTransactions::Full(txs) = block.transactions else {
+            anyhow::bail!("Block transactions are not hydrated");
+        };
+        let txs = txs
+            .into_iter()
+            .map(|tx| {
+                TxEnvelope::try_from(tx)
+                    .map_err(|e| anyhow::anyhow!("Failed to convert transaction: {:?}", e))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let info = BlockInfo {
+            hash,
+            number: block.header.number.ok_or_else(|| anyhow::anyhow!("Missing block number"))?,
+            parent_hash: block.header.parent_hash,
+            timestamp: block.header.timestamp,
+        };
+
+        self.caches.lock().blocks.insert(hash, (info, txs.clone()));
+        Ok((info, txs))
+    }
+}
+
+/// A block identifier for the `debug_getRaw*` methods, as a hash or a hex-encoded number.
+#[derive(Debug)]
+struct RawBlockId(alloc::string::String);
+
+impl From<B256> for RawBlockId {
+    fn from(hash: B256) -> Self {
+        Self(alloc::format!("{:?}", hash))
+    }
+}
+
+impl From<u64> for RawBlockId {
+    fn from(number: u64) -> Self {
+        Self(alloc::format!("{:#x}", number))
+    }
+}
+
+/// Returns the [BlockInfo] of the block with the given hash and header.
+fn block_info(hash: B256, header: &Header) -> BlockInfo {
+    BlockInfo {
+        hash,
+        number: header.number,
+        parent_hash: header.parent_hash,
+        timestamp: header.timestamp,
+    }
+}
+
+/// The caches of an [AlloyChainProvider] or an
+/// [OnlineChainProvider](crate::OnlineChainProvider).
+#[derive(Debug)]
+pub(crate) struct Caches {
+    /// Headers by block hash.
+    pub(crate) headers: BoundedCache<B256, Header>,
+    /// Receipts by block hash.
+    pub(crate) receipts: BoundedCache<B256, Vec<Receipt>>,
+    /// Block info and transactions by block hash.
+    pub(crate) blocks: BoundedCache<B256, (BlockInfo, Vec<TxEnvelope>)>,
+}
+
+impl Caches {
+    /// Creates new caches holding up to `cap` entries each.
+    pub(crate) fn new(cap: usize) -> Self {
+        Self {
+            headers: BoundedCache::new(cap),
+            receipts: BoundedCache::new(cap),
+            blocks: BoundedCache::new(cap),
+        }
+    }
+}
+
+/// A cache evicting its oldest entries once it reaches its capacity.
+#[derive(Debug)]
+pub(crate) struct BoundedCache<K, V> {
+    /// The maximum number of entries.
+    cap: usize,
+    /// The cached entries.
+    entries: HashMap<K, V>,
+    /// The keys in insertion order.
+    order: VecDeque<K>,
+}
+
+impl<K: Hash + Eq + Clone, V: Clone> BoundedCache<K, V> {
+    /// Creates a new [BoundedCache] holding up to `cap` entries.
+    pub(crate) fn new(cap: usize) -> Self {
+        Self { cap, entries: HashMap::with_capacity(cap), order: VecDeque::with_capacity(cap) }
+    }
+
+    /// Returns a copy of the cached value for the given key.
+    pub(crate) fn get(&self, key: &K) -> Option<V> {
+        self.entries.get(key).cloned()
+    }
+
+    /// Inserts the given value, evicting the oldest entry if the cache is full.
+    pub(crate) fn insert(&mut self, key: K, value: V) {
+        if self.cap == 0 {
+            return;
+        }
+        if self.entries.insert(key.clone(), value).is_some() {
+            return;
+        }
+        self.order.push_back(key);
+        if self.order.len() > self.cap {
+            if let Some(oldest) = self.order.pop_front() {
+                self.entries.remove(&oldest);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bounded_cache_eviction() {
+        let mut cache = BoundedCache::new(2);
+        cache.insert(1, "a");
+        cache.insert(2, "b");
+        cache.insert(1, "c");
+        assert_eq!(cache.get(&1), Some("c"));
+
+        cache.insert(3, "d");
+        assert_eq!(cache.get(&1), None);
+        assert_eq!(cache.get(&2), Some("b"));
+        assert_eq!(cache.get(&3), Some("d"));
+    }
+
+    #[test]
+    fn test_raw_block_id() {
+        assert_eq!(RawBlockId::from(255u64).0, "0xff");
+        assert_eq!(RawBlockId::from(B256::ZERO).0, format!("{:?}", B256::ZERO));
+    }
+
+    #[test]
+    fn test_block_info() {
+        let header = Header { number: 7, timestamp: 42, ..Default::default() };
+        let hash = header.hash_slow();
+        let info = block_info(hash, &header);
+        assert_eq!(info.hash, hash);
+        assert_eq!(info.number, 7);
+        assert_eq!(info.timestamp, 42);
+    }
+}
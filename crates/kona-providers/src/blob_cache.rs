@@ -0,0 +1,291 @@
+//! On-disk blob cache
+
+use alloc::{collections::BTreeMap, sync::Arc, vec::Vec};
+use std::{fs, path::PathBuf, str::FromStr};
+
+use alloy::primitives::B256;
+use eyre::{eyre, Result};
+use kona_primitives::{Blob, BlockInfo, IndexedBlobHash};
+use parking_lot::Mutex;
+use tracing::{debug, warn};
+
+use crate::blob_provider::BLOB_RETENTION_EPOCHS;
+
+/// The default maximum size of the on-disk blob cache, in bytes (4 GiB).
+pub const DEFAULT_BLOB_CACHE_MAX_SIZE: u64 = 4 << 30;
+
+/// The size of an entry of a blob cache file: a versioned hash followed by its blob.
+const ENTRY_SIZE: usize = 32 + BLOB_SIZE;
+
+/// The size of a blob, in bytes.
+const BLOB_SIZE: usize = 131072;
+
+/// A [DiskBlobCache] shared between the blob provider and the driver.
+pub type SharedDiskBlobCache = Arc<Mutex<DiskBlobCache>>;
+
+/// The default number of beacon slots the blobs are kept for, matching the retention
+/// window of beacon nodes (about 18 days with 12 second slots).
+pub const DEFAULT_BLOB_CACHE_RETENTION_SLOTS: u64 = BLOB_RETENTION_EPOCHS * 32;
+
+/// A cache of blobs on disk, with one file per L1 block, named after the beacon slot
+/// of the block so that the directory lists them in chain order.
+///
+/// Blobs of blocks older than the retention window, counted in slots from the newest
+/// cached block, are pruned. Once the cache grows over its maximum size, the blobs of
+/// the oldest L1 blocks are pruned first as well. Blobs of the safe head's L1 origin and
+/// newer blocks are never pruned, since derivation may need them again after a restart.
+///
+/// An archive cache, without retention window nor maximum size, keeps all the blobs.
+#[derive(Debug)]
+pub struct DiskBlobCache {
+    /// The directory of the cache files.
+    dir: PathBuf,
+    /// The maximum size of the cache, in bytes.
+    max_size: u64,
+    /// The number of slots the blobs are kept for, or `None` to keep them indefinitely.
+    retention: Option<u64>,
+    /// The slot and file size of each cached block, by block number and hash.
+    files: BTreeMap<(u64, B256), CachedBlock>,
+    /// The slot of the newest cached block.
+    latest_slot: u64,
+    /// The total size of the cache files, in bytes.
+    size: u64,
+    /// The L1 origin of the safe head. Blocks from this number on are never pruned.
+    safe_origin: u64,
+}
+
+impl DiskBlobCache {
+    /// Opens the blob cache in the given directory, creating it if needed.
+    ///
+    /// The blobs are kept for [DEFAULT_BLOB_CACHE_RETENTION_SLOTS], see [Self::with_retention].
+    pub fn open(dir: impl Into<PathBuf>, max_size: u64) -> Result<Self> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir)?;
+
+        let mut files = BTreeMap::new();
+        let mut size = 0;
+        let mut latest_slot = 0;
+        for entry in fs::read_dir(&dir)? {
+            let entry = entry?;
+            let Some((slot, number, hash)) = entry.file_name().to_str().and_then(parse_file_name)
+            else {
+                warn!("Ignoring unknown file in blob cache: {:?}", entry.path());
+                continue;
+            };
+            let len = entry.metadata()?.len();
+            files.insert((number, hash), CachedBlock { slot, len });
+            size += len;
+            latest_slot = latest_slot.max(slot);
+        }
+        debug!(blocks = files.len(), size, latest_slot, "Opened blob cache at {:?}", dir);
+
+        Ok(Self {
+            dir,
+            max_size,
+            retention: Some(DEFAULT_BLOB_CACHE_RETENTION_SLOTS),
+            files,
+            latest_slot,
+            size,
+            safe_origin: 0,
+        })
+    }
+
+    /// Keeps the blobs for the given number of slots, or indefinitely if `None`.
+    pub const fn with_retention(mut self, slots: Option<u64>) -> Self {
+        self.retention = slots;
+        self
+    }
+
+    /// Keeps all the blobs, regardless of their age and of the size of the cache.
+    pub const fn archive(mut self) -> Self {
+        self.retention = None;
+        self.max_size = u64::MAX;
+        self
+    }
+
+    /// Wraps the cache to share it between the blob provider and the driver.
+    pub fn into_shared(self) -> SharedDiskBlobCache {
+        Arc::new(Mutex::new(self))
+    }
+
+    /// Returns the total size of the cached blobs, in bytes.
+    pub const fn size(&self) -> u64 {
+        self.size
+    }
+
+    /// Returns the blobs with the given hashes in the given block, if they are all cached.
+    pub fn get(&self, block: &BlockInfo, hashes: &[IndexedBlobHash]) -> Option<Vec<Blob>> {
+        let cached = self.files.get(&(block.number, block.hash))?;
+        let data = fs::read(self.path(cached.slot, block.number, block.hash)).ok()?;
+        let cached: BTreeMap<B256, Blob> = data
+            .chunks_exact(ENTRY_SIZE)
+            .map(|entry| (B256::from_slice(&entry[..32]), Blob::from_slice(&entry[32..])))
+            .collect();
+        hashes.iter().map(|h| cached.get(&h.hash).copied()).collect()
+    }
+
+    /// Caches the given blobs of the given block at the given beacon slot, replacing any
+    /// previously cached ones, then prunes the blocks out of the retention window and the
+    /// oldest blocks if the cache is over its maximum size.
+    pub fn insert(&mut self, block: &BlockInfo, slot: u64, blobs: &[(B256, Blob)]) -> Result<()> {
+        let mut data = Vec::with_capacity(blobs.len() * ENTRY_SIZE);
+        for (hash, blob) in blobs {
+            data.extend_from_slice(hash.as_slice());
+            data.extend_from_slice(blob.as_slice());
+        }
+        let key = (block.number, block.hash);
+        if let Some(previous) = self.files.get(&key).copied() {
+            if previous.slot != slot {
+                fs::remove_file(self.path(previous.slot, block.number, block.hash))?;
+            }
+        }
+        fs::write(self.path(slot, block.number, block.hash), &data)?;
+
+        let len = data.len() as u64;
+        if let Some(previous) = self.files.insert(key, CachedBlock { slot, len }) {
+            self.size -= previous.len;
+        }
+        self.size += len;
+        self.latest_slot = self.latest_slot.max(slot);
+        self.prune()
+    }
+
+    /// Sets the L1 origin of the safe head, allowing older blocks to be pruned.
+    pub fn set_safe_origin(&mut self, number: u64) -> Result<()> {
+        self.safe_origin = self.safe_origin.max(number);
+        self.prune()
+    }
+
+    /// Removes the blobs of the oldest blocks while they are out of the retention window
+    /// or the cache doesn't fit in its maximum size, without pruning the safe head's L1
+    /// origin or newer blocks.
+    pub fn prune(&mut self) -> Result<()> {
+        let min_slot = self.retention.map_or(0, |slots| self.latest_slot.saturating_sub(slots));
+        while let Some((&(number, hash), &block)) = self.files.first_key_value() {
+            let expired = block.slot < min_slot;
+            if number >= self.safe_origin || (!expired && self.size <= self.max_size) {
+                break;
+            }
+            fs::remove_file(self.path(block.slot, number, hash))
+                .map_err(|e| eyre!("Failed to prune blobs of block {}: {}", number, e))?;
+            self.files.remove(&(number, hash));
+            self.size -= block.len;
+            let reason = if expired { "retention" } else { "size" };
+            metrics::counter!("hera_blob_cache_pruned_blocks_total", "reason" => reason)
+                .increment(1);
+        }
+        metrics::gauge!("hera_blob_cache_size_bytes").set(self.size as f64);
+        Ok(())
+    }
+
+    /// Returns the path of the cache file of the given block.
+    fn path(&self, slot: u64, number: u64, hash: B256) -> PathBuf {
+        self.dir.join(alloc::format!("{:016x}-{:016x}-{:x}.blobs", slot, number, hash))
+    }
+}
+
+/// A block cached on disk.
+#[derive(Debug, Clone, Copy)]
+struct CachedBlock {
+    /// The beacon slot of the block.
+    slot: u64,
+    /// The size of its cache file, in bytes.
+    len: u64,
+}
+
+/// Parses the slot, block number and hash from the name of a cache file.
+///
+/// Files written before the cache was indexed by slot are named after the block number
+/// and hash only: they get slot 0, so that they are the first out of the retention window.
+fn parse_file_name(name: &str) -> Option<(u64, u64, B256)> {
+    let parts: Vec<&str> = name.strip_suffix(".blobs")?.split('-').collect();
+    let (slot, number, hash) = match parts[..] {
+        [slot, number, hash] => (u64::from_str_radix(slot, 16).ok()?, number, hash),
+        [number, hash] => (0, number, hash),
+        _ => return None,
+    };
+    Some((slot, u64::from_str_radix(number, 16).ok()?, B256::from_str(hash).ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block(number: u64) -> BlockInfo {
+        BlockInfo { number, hash: B256::with_last_byte(number as u8), ..Default::default() }
+    }
+
+    fn blob(byte: u8) -> (B256, Blob) {
+        (B256::repeat_byte(byte), Blob::repeat_byte(byte))
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(alloc::format!("hera-{}-{}", name, std::process::id()));
+        _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn test_get_and_reopen() {
+        let dir = temp_dir("blob-cache-reopen");
+        let mut cache = DiskBlobCache::open(&dir, u64::MAX).unwrap();
+        cache.insert(&block(1), 10, &[blob(1), blob(2)]).unwrap();
+
+        let hashes = [IndexedBlobHash { index: 0, hash: B256::repeat_byte(2) }];
+        assert_eq!(cache.get(&block(1), &hashes), Some(vec![Blob::repeat_byte(2)]));
+        let missing = [IndexedBlobHash { index: 0, hash: B256::repeat_byte(3) }];
+        assert_eq!(cache.get(&block(1), &missing), None);
+
+        let reopened = DiskBlobCache::open(&dir, u64::MAX).unwrap();
+        assert_eq!(reopened.size(), 2 * ENTRY_SIZE as u64);
+        assert_eq!(reopened.get(&block(1), &hashes), Some(vec![Blob::repeat_byte(2)]));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_prune_oldest_before_safe_origin() {
+        let dir = temp_dir("blob-cache-prune");
+        let mut cache = DiskBlobCache::open(&dir, 2 * ENTRY_SIZE as u64).unwrap();
+        for number in 1..=3 {
+            cache.insert(&block(number), number, &[blob(number as u8)]).unwrap();
+        }
+        // Nothing is older than the safe head's origin yet.
+        assert_eq!(cache.size(), 3 * ENTRY_SIZE as u64);
+
+        cache.set_safe_origin(3).unwrap();
+        assert_eq!(cache.size(), 2 * ENTRY_SIZE as u64);
+        assert!(!cache.files.contains_key(&(1, block(1).hash)));
+        assert!(cache.files.contains_key(&(2, block(2).hash)));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_prune_out_of_retention_window() {
+        let dir = temp_dir("blob-cache-retention");
+        let mut cache = DiskBlobCache::open(&dir, u64::MAX).unwrap().with_retention(Some(10));
+        cache.set_safe_origin(u64::MAX).unwrap();
+        cache.insert(&block(1), 100, &[blob(1)]).unwrap();
+        cache.insert(&block(2), 105, &[blob(2)]).unwrap();
+        assert_eq!(cache.files.len(), 2);
+
+        cache.insert(&block(3), 111, &[blob(3)]).unwrap();
+        assert!(!cache.files.contains_key(&(1, block(1).hash)));
+        assert_eq!(cache.files.len(), 2);
+
+        let mut archive = DiskBlobCache::open(&dir, 0).unwrap().archive();
+        archive.set_safe_origin(u64::MAX).unwrap();
+        archive.insert(&block(4), 10_000, &[blob(4)]).unwrap();
+        assert_eq!(archive.files.len(), 3);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_parse_file_name() {
+        let hash = B256::repeat_byte(0xab);
+        let name = alloc::format!("{:016x}-{:016x}-{:x}.blobs", 7, 42, hash);
+        assert_eq!(parse_file_name(&name), Some((7, 42, hash)));
+        let legacy = alloc::format!("{:016x}-{:x}.blobs", 42, hash);
+        assert_eq!(parse_file_name(&legacy), Some((0, 42, hash)));
+        assert_eq!(parse_file_name("42.tmp"), None);
+    }
+}
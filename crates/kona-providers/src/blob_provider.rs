@@ -1,5 +1,6 @@
 use alloc::{collections::VecDeque, sync::Arc};
 use hashbrown::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use alloy::primitives::B256;
 use async_trait::async_trait;
@@ -7,30 +8,192 @@ use eyre::{eyre, Result};
 use kona_derive::{
     errors::BlobProviderError,
     online::{
-        OnlineBeaconClient, OnlineBlobProviderBuilder, OnlineBlobProviderWithFallback,
-        SimpleSlotDerivation,
+        OnlineBeaconClient, OnlineBlobProvider as KonaOnlineBlobProvider, SimpleSlotDerivation,
     },
-    traits::BlobProvider,
+    traits::{BeaconClient, BlobProvider},
 };
 use kona_primitives::{Blob, BlockInfo, IndexedBlobHash};
 use parking_lot::Mutex;
 use reth::primitives::BlobTransactionSidecar;
-use tracing::warn;
+use tracing::{debug, warn};
 use url::Url;
 
-/// A blob provider that first attempts to fetch blobs from a primary beacon client and
-/// falls back to a secondary blob archiver if the primary fails.
-///
-/// Any blob archiver just needs to implement the beacon
+use crate::{blob_cache::SharedDiskBlobCache, kzg::verify_blobs};
+
+/// The number of epochs beacon nodes serve blob sidecars for after their block
+/// (`MIN_EPOCHS_FOR_BLOB_SIDECARS_REQUESTS`).
+pub const BLOB_RETENTION_EPOCHS: u64 = 4096;
+
+/// The number of slots per beacon chain epoch.
+const SLOTS_PER_EPOCH: u64 = 32;
+
+/// The beacon slot interval assumed until it is loaded from the beacon node.
+const DEFAULT_SLOT_INTERVAL: u64 = 12;
+
+/// The number of blocks whose blobs fetched online are kept in memory.
+const FETCHED_BLOBS_CAPACITY: usize = 64;
+
+/// A kona blob provider over the `blob_sidecars` API of a single beacon node or archive.
+type BeaconBlobProvider = KonaOnlineBlobProvider<OnlineBeaconClient, SimpleSlotDerivation>;
+
+/// A [BlobProvider] that fetches blobs from the
 /// [`blob_sidecars` API](https://ethereum.github.io/beacon-APIs/#/Beacon/getBlobSidecars)
-pub type DurableBlobProvider =
-    OnlineBlobProviderWithFallback<OnlineBeaconClient, OnlineBeaconClient, SimpleSlotDerivation>;
+/// of a beacon node, and falls back to a blob archive serving the same API once the beacon
+/// node has pruned them.
+///
+/// Blobs older than the beacon node's retention window are fetched from the archive
+/// directly, rather than with a request to the beacon node that is bound to fail.
+/// Newer blobs are only fetched from the archive if the beacon node fails to serve them.
+#[derive(Debug, Clone)]
+pub struct OnlineBlobProvider {
+    /// The blob provider of the beacon node.
+    beacon: BeaconBlobProvider,
+    /// The blob provider of the blob archive, if any.
+    archive: Option<BeaconBlobProvider>,
+    /// The age in seconds after which the beacon node no longer serves blobs.
+    retention: u64,
+    /// The beacon genesis time, or 0 until loaded from the beacon node.
+    genesis_time: u64,
+    /// The beacon slot interval, in seconds.
+    slot_interval: u64,
+    /// Whether fetched blobs are checked against their versioned hashes.
+    verify: bool,
+}
+
+impl OnlineBlobProvider {
+    /// Creates a new [OnlineBlobProvider] from a beacon node and an optional blob archive.
+    ///
+    /// The beacon genesis time and slot interval are loaded lazily by each client,
+    /// see [online_blob_provider] to load them upfront from the beacon node.
+    pub fn new(beacon_client_url: Url, blob_archive_url: Option<Url>) -> Self {
+        let provider = |url: Url| {
+            BeaconBlobProvider::new(OnlineBeaconClient::new_http(url.to_string()), None, None)
+        };
+        Self {
+            beacon: provider(beacon_client_url),
+            archive: blob_archive_url.map(provider),
+            retention: retention(DEFAULT_SLOT_INTERVAL),
+            genesis_time: 0,
+            slot_interval: DEFAULT_SLOT_INTERVAL,
+            verify: false,
+        }
+    }
+
+    /// Returns the beacon slot of the given block.
+    ///
+    /// Until the beacon genesis time is loaded, slots are counted from the unix epoch,
+    /// which keeps the distance between two slots right.
+    pub const fn slot(&self, block_ref: &BlockInfo) -> u64 {
+        block_ref.timestamp.saturating_sub(self.genesis_time) / self.slot_interval
+    }
+
+    /// Checks every fetched blob against its versioned hash with KZG,
+    /// see [verify_blobs].
+    pub const fn with_verification(mut self) -> Self {
+        self.verify = true;
+        self
+    }
+
+    /// Returns `true` if the beacon node has likely pruned the blobs of the given block.
+    fn is_expired(&self, block_ref: &BlockInfo) -> bool {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        now.saturating_sub(block_ref.timestamp) > self.retention
+    }
+}
+
+#[async_trait]
+impl BlobProvider for OnlineBlobProvider {
+    async fn get_blobs(
+        &mut self,
+        block_ref: &BlockInfo,
+        blob_hashes: &[IndexedBlobHash],
+    ) -> Result<Vec<Blob>, BlobProviderError> {
+        let expired = self.is_expired(block_ref);
+        let verify = self.verify;
+        let Some(archive) = &mut self.archive else {
+            let blobs = self.beacon.get_blobs(block_ref, blob_hashes).await?;
+            return checked(verify, blob_hashes, blobs);
+        };
+
+        if !expired {
+            let result = self.beacon.get_blobs(block_ref, blob_hashes).await;
+            match result.and_then(|blobs| checked(verify, blob_hashes, blobs)) {
+                Ok(blobs) => return Ok(blobs),
+                Err(err) => warn!(?err, "Failed to fetch blobs from beacon node, trying archive"),
+            }
+        }
+        metrics::counter!("hera_blob_archive_requests_total").increment(1);
+        let blobs = archive.get_blobs(block_ref, blob_hashes).await?;
+        checked(verify, blob_hashes, blobs)
+    }
+}
+
+/// Returns the given blobs, after checking them against their versioned hashes
+/// if `verify` is set.
+fn checked(
+    verify: bool,
+    hashes: &[IndexedBlobHash],
+    blobs: Vec<Blob>,
+) -> Result<Vec<Blob>, BlobProviderError> {
+    if verify {
+        verify_blobs(hashes, &blobs)?;
+    }
+    Ok(blobs)
+}
+
+/// Returns the age in seconds after which beacon nodes prune blobs, for the given
+/// slot interval.
+const fn retention(slot_interval: u64) -> u64 {
+    BLOB_RETENTION_EPOCHS * SLOTS_PER_EPOCH * slot_interval
+}
+
+/// Creates a new [OnlineBlobProvider] from a beacon node and an optional blob archive.
+///
+/// The beacon genesis time and seconds-per-slot are fetched from the beacon node upfront,
+/// so that a misconfigured or unreachable beacon node is reported at startup rather than
+/// when the first blob transaction is derived. They are shared with the blob archive,
+/// which only needs to serve the `blob_sidecars` API.
+pub async fn online_blob_provider(
+    beacon_client_url: Url,
+    blob_archive_url: Option<Url>,
+) -> Result<OnlineBlobProvider> {
+    let beacon = OnlineBeaconClient::new_http(beacon_client_url.to_string());
+
+    let genesis_time = beacon
+        .beacon_genesis()
+        .await
+        .map_err(|e| eyre!("Failed to fetch beacon genesis: {:?}", e))?
+        .data
+        .genesis_time;
+    let slot_interval = beacon
+        .config_spec()
+        .await
+        .map_err(|e| eyre!("Failed to fetch beacon config spec: {:?}", e))?
+        .data
+        .seconds_per_slot;
+    debug!(genesis_time, slot_interval, "Loaded beacon client configuration");
+
+    let provider =
+        |client| BeaconBlobProvider::new(client, Some(genesis_time), Some(slot_interval));
+    Ok(OnlineBlobProvider {
+        verify: false,
+        beacon: provider(beacon),
+        archive: blob_archive_url
+            .map(|url| provider(OnlineBeaconClient::new_http(url.to_string()))),
+        retention: retention(slot_interval),
+        genesis_time,
+        slot_interval,
+    })
+}
 
 /// Layered [BlobProvider] for the Kona derivation pipeline.
 ///
 /// This provider wraps different blob sources in an ordered manner:
-/// - First, it attempts to fetch blobs from an in-memory store.
-/// - If the blobs are not found, it then attempts to fetch them from an online beacon client.
+/// - First, it attempts to fetch blobs from an in-memory store, holding the sidecars of live blocks
+///   and the blobs recently fetched online, e.g. ahead of derivation by a prefetcher.
+/// - If the blobs are not found, it then attempts to fetch them from an on-disk cache (if set).
+/// - If the blobs are not found, it then attempts to fetch them from an online beacon client, and
+///   caches them on disk, indexed by their beacon slot.
 /// - If the blobs are still not found, it tries to fetch them from a blob archiver (if set).
 /// - If all sources fail, the provider will return a [BlobProviderError].
 #[derive(Debug, Clone)]
@@ -43,9 +206,13 @@ pub struct LayeredBlobProvider {
     /// This is used primarily during sync when archived blobs
     /// aren't provided by reth since they'll be too old.
     ///
-    /// The `Durable` setup allows to specify two different
-    /// endpoints for a primary and a fallback blob provider.
-    online: DurableBlobProvider,
+    /// It fetches blobs from a beacon node, or from a blob archive
+    /// once the beacon node has pruned them.
+    online: OnlineBlobProvider,
+
+    /// On-disk blob cache, used to avoid refetching blobs from the
+    /// online provider across restarts.
+    disk: Option<SharedDiskBlobCache>,
 }
 
 /// A blob provider that hold blobs in memory.
@@ -57,6 +224,10 @@ pub struct InnerBlobProvider {
     key_order: VecDeque<B256>,
     /// Maps block hashes to blob hashes to blob sidecars.
     blocks_to_blob_sidecars: HashMap<B256, Vec<BlobTransactionSidecar>>,
+    /// Order of insertion of the fetched blobs, for oldest entry eviction.
+    fetched_order: VecDeque<B256>,
+    /// Maps block hashes to the blobs fetched online, with their versioned hashes.
+    fetched_blobs: HashMap<B256, Vec<(B256, Blob)>>,
 }
 
 impl InnerBlobProvider {
@@ -66,9 +237,35 @@ impl InnerBlobProvider {
             capacity: cap,
             blocks_to_blob_sidecars: HashMap::with_capacity(cap),
             key_order: VecDeque::with_capacity(cap),
+            fetched_order: VecDeque::with_capacity(FETCHED_BLOBS_CAPACITY),
+            fetched_blobs: HashMap::with_capacity(FETCHED_BLOBS_CAPACITY),
         }
     }
 
+    /// Inserts the blobs of a block fetched online, evicting the oldest fetched ones
+    /// once over capacity.
+    pub fn insert_fetched_blobs(&mut self, block_hash: B256, blobs: Vec<(B256, Blob)>) {
+        if self.fetched_blobs.insert(block_hash, blobs).is_some() {
+            return;
+        }
+        self.fetched_order.push_back(block_hash);
+        if self.fetched_order.len() > FETCHED_BLOBS_CAPACITY {
+            if let Some(oldest) = self.fetched_order.pop_front() {
+                self.fetched_blobs.remove(&oldest);
+            }
+        }
+    }
+
+    /// Returns the fetched blobs with the given hashes in the given block, if they are
+    /// all in memory.
+    fn fetched(&self, block_hash: &B256, blob_hashes: &[IndexedBlobHash]) -> Option<Vec<Blob>> {
+        let fetched = self.fetched_blobs.get(block_hash)?;
+        blob_hashes
+            .iter()
+            .map(|h| fetched.iter().find(|(hash, _)| *hash == h.hash).map(|(_, blob)| *blob))
+            .collect()
+    }
+
     /// Inserts multiple blob sidecars nto the provider.
     pub fn insert_blob_sidecars(
         &mut self,
@@ -92,14 +289,20 @@ impl LayeredBlobProvider {
     /// Creates a new [LayeredBlobProvider] with a local blob store, an online primary beacon
     /// client and an optional fallback blob archiver for fetching blobs.
     pub fn new(beacon_client_url: Url, blob_archiver_url: Option<Url>) -> Self {
-        let memory = Arc::new(Mutex::new(InnerBlobProvider::with_capacity(512)));
+        Self::with_online(OnlineBlobProvider::new(beacon_client_url, blob_archiver_url))
+    }
 
-        let online = OnlineBlobProviderBuilder::new()
-            .with_primary(beacon_client_url.to_string())
-            .with_fallback(blob_archiver_url.map(|url| url.to_string()))
-            .build();
+    /// Creates a new [LayeredBlobProvider] with a local blob store on top of
+    /// the given [OnlineBlobProvider].
+    pub fn with_online(online: OnlineBlobProvider) -> Self {
+        let memory = Arc::new(Mutex::new(InnerBlobProvider::with_capacity(512)));
+        Self { memory, online, disk: None }
+    }
 
-        Self { memory, online }
+    /// Caches the blobs fetched from the online provider in the given on-disk cache.
+    pub fn with_disk_cache(mut self, disk: SharedDiskBlobCache) -> Self {
+        self.disk = Some(disk);
+        self
     }
 
     /// Inserts multiple blob sidecars into the in-memory provider.
@@ -120,6 +323,9 @@ impl LayeredBlobProvider {
         blob_hashes: &[IndexedBlobHash],
     ) -> Result<Vec<Blob>> {
         let locked = self.memory.lock();
+        if let Some(blobs) = locked.fetched(&block_ref.hash, blob_hashes) {
+            return Ok(blobs);
+        }
 
         let sidecars_for_block = locked
             .blocks_to_blob_sidecars
@@ -163,9 +369,62 @@ impl BlobProvider for LayeredBlobProvider {
     ) -> Result<Vec<Blob>, BlobProviderError> {
         if let Ok(b) = self.memory_blob_load(block_ref, blob_hashes).await {
             return Ok(b);
-        } else {
-            warn!("Blob provider falling back to online provider");
-            self.online_blob_load(block_ref, blob_hashes).await
         }
+        if let Some(blobs) = self.disk.as_ref().and_then(|d| d.lock().get(block_ref, blob_hashes)) {
+            // Cached blobs may have been written before verification was enabled.
+            return checked(self.online.verify, blob_hashes, blobs);
+        }
+
+        warn!("Blob provider falling back to online provider");
+        let blobs = self.online_blob_load(block_ref, blob_hashes).await?;
+        let entries: Vec<_> =
+            blob_hashes.iter().map(|h| h.hash).zip(blobs.iter().copied()).collect();
+        if let Some(disk) = &self.disk {
+            let slot = self.online.slot(block_ref);
+            if let Err(err) = disk.lock().insert(block_ref, slot, &entries) {
+                warn!(?err, "Failed to cache blobs of block {} on disk", block_ref.number);
+            }
+        }
+        self.memory.lock().insert_fetched_blobs(block_ref.hash, entries);
+        Ok(blobs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expired_blobs_skip_beacon_node() {
+        let url = Url::parse("http://localhost:5052").unwrap();
+        let provider = OnlineBlobProvider::new(url, None);
+        // About 18 days with 12 second slots.
+        assert_eq!(provider.retention, 1_572_864);
+
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+        let recent = BlockInfo { timestamp: now - 3600, ..Default::default() };
+        assert!(!provider.is_expired(&recent));
+        let old = BlockInfo { timestamp: now - provider.retention - 60, ..Default::default() };
+        assert!(provider.is_expired(&old));
+    }
+
+    #[test]
+    fn test_fetched_blobs_in_memory() {
+        let mut memory = InnerBlobProvider::with_capacity(8);
+        let hash = |n: u8| IndexedBlobHash { index: n as usize, hash: B256::repeat_byte(n) };
+        memory.insert_fetched_blobs(
+            B256::ZERO,
+            vec![
+                (B256::repeat_byte(1), Blob::repeat_byte(1)),
+                (B256::repeat_byte(2), Blob::repeat_byte(2)),
+            ],
+        );
+        assert_eq!(memory.fetched(&B256::ZERO, &[hash(2)]), Some(vec![Blob::repeat_byte(2)]));
+        assert_eq!(memory.fetched(&B256::ZERO, &[hash(1), hash(3)]), None);
+
+        for n in 1..=FETCHED_BLOBS_CAPACITY as u64 {
+            memory.insert_fetched_blobs(B256::from(alloy::primitives::U256::from(n)), vec![]);
+        }
+        assert_eq!(memory.fetched(&B256::ZERO, &[hash(2)]), None);
     }
 }
@@ -2,7 +2,7 @@
 
 use alloc::{collections::vec_deque::VecDeque, sync::Arc};
 use alloy_rlp::Decodable;
-use hashbrown::HashMap;
+use hashbrown::{HashMap, HashSet};
 
 use alloy::{
     consensus::{
@@ -16,13 +16,34 @@ use async_trait::async_trait;
 use kona_derive::traits::ChainProvider;
 use kona_primitives::{BlockID, BlockInfo};
 use parking_lot::RwLock;
-use reth::{primitives::Transaction, providers::Chain};
+use reth::{
+    primitives::{Transaction, TransactionSigned},
+    providers::Chain,
+};
+use reth_exex::ExExNotification;
+use tokio::{sync::mpsc, time::Instant};
+
+#[cfg(feature = "online")]
+use crate::DiskChainProvider;
+
+/// The default number of blocks kept in memory by an [InMemoryChainProvider].
+pub const DEFAULT_IN_MEMORY_CAPACITY: usize = 1024;
+
+/// The number of applied ExEx notifications that can be buffered before the
+/// provider stops consuming new ones, see [InMemoryChainProvider::spawn_from_exex].
+pub const EXEX_NOTIFICATION_BUFFER: usize = 128;
 
 /// An in-memory [ChainProvider] that stores chain data,
 /// meant to be shared between multiple readers.
 ///
-/// This provider uses a ring buffer to limit capacity
-/// to avoid storing an unbounded amount of data in memory.
+/// This provider keeps at most `capacity` blocks in memory, evicting the least
+/// recently used ones first, and the blocks below the L1 finalized head once
+/// [InMemoryChainProvider::finalize] is called.
+/// Evicted blocks, or blocks committed before a restart,
+/// can be kept on disk with [InMemoryChainProvider::with_disk].
+///
+/// Lookups are counted in `hera_l1_cache_lookups_total`, by method and by whether
+/// they were served from memory, from disk, or missed.
 #[derive(Debug, Clone)]
 pub struct InMemoryChainProvider(Arc<RwLock<InMemoryChainProviderInner>>);
 
@@ -32,15 +53,135 @@ impl InMemoryChainProvider {
         Self(Arc::new(RwLock::new(InMemoryChainProviderInner::with_capacity(cap))))
     }
 
+    /// Also stores the committed chain data in the given [DiskChainProvider],
+    /// which lookups fall back to when the data is not in memory.
+    #[cfg(feature = "online")]
+    pub fn with_disk(self, disk: DiskChainProvider) -> Self {
+        self.0.write().disk = Some(disk);
+        self
+    }
+
+    /// Creates a new [InMemoryChainProvider] with the given capacity, fed by
+    /// the given ExEx notifications.
+    ///
+    /// A background task owns the subscription: committed chains are added to the
+    /// provider, and reverted chains are removed from it. Each notification is then
+    /// forwarded to the returned receiver, so consumers only observe it once the
+    /// provider reflects it.
+    pub fn spawn_from_exex(
+        cap: usize,
+        notifications: mpsc::Receiver<ExExNotification>,
+    ) -> (Self, mpsc::Receiver<ExExNotification>) {
+        Self::with_capacity(cap).spawn_exex(notifications)
+    }
+
+    /// Feeds the provider with the given ExEx notifications,
+    /// see [InMemoryChainProvider::spawn_from_exex].
+    pub fn spawn_exex(
+        self,
+        mut notifications: mpsc::Receiver<ExExNotification>,
+    ) -> (Self, mpsc::Receiver<ExExNotification>) {
+        let (sender, recv) = mpsc::channel(EXEX_NOTIFICATION_BUFFER);
+
+        let mut inner = self.clone();
+        tokio::spawn(async move {
+            while let Some(notification) = notifications.recv().await {
+                inner.apply(&notification);
+                if sender.send(notification).await.is_err() {
+                    tracing::debug!("ExEx notification receiver dropped, stopping");
+                    break;
+                }
+            }
+        });
+
+        (self, recv)
+    }
+
+    /// Applies an ExEx notification, unwinding the reverted chain (if any)
+    /// before committing the new one (if any).
+    pub fn apply(&mut self, notification: &ExExNotification) {
+        if let Some(reverted) = notification.reverted_chain() {
+            self.revert(reverted);
+        }
+        if let Some(committed) = notification.committed_chain() {
+            self.commit(committed);
+        }
+    }
+
     /// Commits Chain state to the provider.
     pub fn commit(&mut self, chain: Arc<Chain>) {
+        #[cfg(feature = "online")]
+        if let Some(mut disk) = self.disk() {
+            if let Err(err) = disk.commit(&chain) {
+                tracing::warn!(?err, "Failed to store committed chain on disk");
+            }
+        }
         self.0.write().commit(chain);
     }
 
+    /// Removes the blocks of a reverted Chain from the provider.
+    pub fn revert(&mut self, chain: Arc<Chain>) {
+        #[cfg(feature = "online")]
+        if let Some(mut disk) = self.disk() {
+            if let Err(err) = disk.revert(&chain) {
+                tracing::warn!(?err, "Failed to remove reverted chain from disk");
+            }
+        }
+        self.0.write().revert(&chain);
+    }
+
+    /// Removes the blocks with the given hashes from memory, e.g. blocks found to be
+    /// no longer canonical without a revert notification.
+    ///
+    /// The tip is left untouched unless it is removed, in which case it is cleared.
+    pub fn unwind(&mut self, hashes: &[B256]) {
+        self.0.write().remove(&hashes.iter().copied().collect());
+    }
+
+    /// Returns the [DiskChainProvider] backing the provider, if any.
+    #[cfg(feature = "online")]
+    fn disk(&self) -> Option<DiskChainProvider> {
+        self.0.read().disk.clone()
+    }
+
+    /// Returns the [BlockInfo] of the latest committed block, if any.
+    pub fn tip(&self) -> Option<BlockInfo> {
+        self.0.read().tip
+    }
+
+    /// Returns the time at which the block with the given hash was committed, if known.
+    pub fn committed_at(&self, hash: &B256) -> Option<Instant> {
+        self.0.read().hash_to_committed_at.get(hash).copied()
+    }
+
     /// Inserts the L2 genesis [BlockID] into the provider.
     pub fn insert_l2_genesis_block(&mut self, block: BlockID) {
         self.0.write().insert_l2_genesis_block(block);
     }
+
+    /// Evicts the blocks below the given number from memory, e.g. once they are
+    /// finalized on L1 and no longer read by the derivation pipeline.
+    ///
+    /// They can still be read from disk if a [DiskChainProvider] is configured.
+    pub fn finalize(&mut self, number: u64) {
+        self.0.write().finalize(number);
+    }
+
+    /// Returns the number of blocks held in memory.
+    pub fn len(&self) -> usize {
+        self.0.read().key_order.len()
+    }
+
+    /// Returns `true` if no block is held in memory.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Counts a lookup of the given method, served from memory, from disk, or missed.
+fn record_lookup(method: &'static str, result: &'static str) {
+    metrics::counter!("hera_l1_cache_lookups_total", "method" => method, "result" => result)
+        .increment(1);
 }
 
 /// The inner state of an [InMemoryChainProvider].
@@ -50,8 +191,8 @@ struct InMemoryChainProviderInner {
     /// This is used to prevent unbounded memory usage.
     capacity: usize,
 
-    /// The order in which keys were inserted into the provider.
-    /// This is used to evict the oldest items when the provider
+    /// The order in which keys were last used, from least to most recently.
+    /// This is used to evict the least recently used items when the provider
     /// reaches its capacity.
     key_order: VecDeque<B256>,
 
@@ -66,6 +207,16 @@ struct InMemoryChainProviderInner {
 
     /// Maps a [B256] hash to a [Vec]<[TxEnvelope]>.
     hash_to_txs: HashMap<B256, Vec<TxEnvelope>>,
+
+    /// Maps a [B256] hash to the [Instant] the block was committed at.
+    hash_to_committed_at: HashMap<B256, Instant>,
+
+    /// The [BlockInfo] of the latest committed block.
+    tip: Option<BlockInfo>,
+
+    /// The on-disk provider storing the committed blocks, if any.
+    #[cfg(feature = "online")]
+    disk: Option<DiskChainProvider>,
 }
 
 impl InMemoryChainProviderInner {
@@ -77,12 +228,16 @@ impl InMemoryChainProviderInner {
             hash_to_block_info: HashMap::with_capacity(cap),
             hash_to_receipts: HashMap::with_capacity(cap),
             hash_to_txs: HashMap::with_capacity(cap),
+            hash_to_committed_at: HashMap::with_capacity(cap),
+            tip: None,
+            #[cfg(feature = "online")]
+            disk: None,
         }
     }
 
     /// Commits Chain state to the provider.
     fn commit(&mut self, chain: Arc<Chain>) {
-        // Remove the oldest items if the provider is at capacity.
+        // Remove the least recently used items if the provider is at capacity.
         self.key_order.extend(chain.headers().map(|h| h.hash()));
         if self.key_order.len() > self.capacity {
             let to_remove = self.key_order.len() - self.capacity;
@@ -92,46 +247,78 @@ impl InMemoryChainProviderInner {
                     self.hash_to_block_info.remove(&key);
                     self.hash_to_receipts.remove(&key);
                     self.hash_to_txs.remove(&key);
+                    self.hash_to_committed_at.remove(&key);
                 }
             }
+            metrics::counter!("hera_l1_cache_evictions_total", "reason" => "capacity")
+                .increment(to_remove as u64);
         }
 
+        let now = Instant::now();
+        self.hash_to_committed_at.extend(chain.headers().map(|h| (h.hash(), now)));
+
         self.commit_headers(&chain);
         self.commit_block_infos(&chain);
         self.commit_receipts(&chain);
         self.commit_txs(&chain);
+        self.tip = self.hash_to_block_info.get(&chain.tip().hash()).copied();
+        metrics::gauge!("hera_l1_cache_blocks").set(self.key_order.len() as f64);
+    }
+
+    /// Marks the block with the given hash as the most recently used.
+    fn touch(&mut self, hash: &B256) {
+        if let Some(position) = self.key_order.iter().position(|key| key == hash) {
+            if let Some(key) = self.key_order.remove(position) {
+                self.key_order.push_back(key);
+            }
+        }
+    }
+
+    /// Removes all data of the blocks below the given number.
+    fn finalize(&mut self, number: u64) {
+        let finalized: HashSet<B256> = self
+            .hash_to_block_info
+            .values()
+            .filter(|info| info.number < number)
+            .map(|info| info.hash)
+            .collect();
+        if finalized.is_empty() {
+            return;
+        }
+        metrics::counter!("hera_l1_cache_evictions_total", "reason" => "finalized")
+            .increment(finalized.len() as u64);
+        self.remove(&finalized);
+        metrics::gauge!("hera_l1_cache_blocks").set(self.key_order.len() as f64);
+    }
+
+    /// Removes all data of the blocks in the given Chain.
+    fn revert(&mut self, chain: &Chain) {
+        self.remove(&chain.headers().map(|h| h.hash()).collect());
+
+        // The tip moves back to the parent of the reverted chain, if it is still known.
+        let parent = chain.first().parent_hash;
+        self.tip = self.hash_to_block_info.get(&parent).copied();
+    }
+
+    /// Removes all data of the blocks with the given hashes.
+    fn remove(&mut self, hashes: &HashSet<B256>) {
+        self.key_order.retain(|key| !hashes.contains(key));
+        for key in hashes {
+            self.hash_to_header.remove(key);
+            self.hash_to_block_info.remove(key);
+            self.hash_to_receipts.remove(key);
+            self.hash_to_txs.remove(key);
+            self.hash_to_committed_at.remove(key);
+        }
+        if self.tip.map_or(false, |tip| hashes.contains(&tip.hash)) {
+            self.tip = None;
+        }
     }
 
     /// Commits [Header]s to the provider.
     fn commit_headers(&mut self, chain: &Arc<Chain>) {
         for header in chain.headers() {
-            // TODO: won't need to coerce once reth uses alloy types
-            self.hash_to_header.insert(
-                header.hash(),
-                Header {
-                    parent_hash: header.parent_hash,
-                    ommers_hash: header.ommers_hash,
-                    beneficiary: header.beneficiary,
-                    state_root: header.state_root,
-                    requests_root: header.requests_root,
-                    transactions_root: header.transactions_root,
-                    receipts_root: header.receipts_root,
-                    withdrawals_root: header.withdrawals_root,
-                    logs_bloom: header.logs_bloom,
-                    difficulty: header.difficulty,
-                    number: header.number,
-                    gas_limit: header.gas_limit as u128,
-                    gas_used: header.gas_used as u128,
-                    timestamp: header.timestamp,
-                    mix_hash: header.mix_hash,
-                    nonce: header.nonce.into(),
-                    base_fee_per_gas: header.base_fee_per_gas.map(|b| b as u128),
-                    blob_gas_used: header.blob_gas_used.map(|b| b as u128),
-                    excess_blob_gas: header.excess_blob_gas.map(|b| b as u128),
-                    parent_beacon_block_root: header.parent_beacon_block_root,
-                    extra_data: header.extra_data.clone(),
-                },
-            );
+            self.hash_to_header.insert(header.hash(), convert_header(&header));
         }
     }
 
@@ -165,128 +352,15 @@ impl InMemoryChainProviderInner {
 
     /// Commits [Receipt]s to the provider.
     fn commit_receipts(&mut self, chain: &Arc<Chain>) {
-        for (b, receipt) in chain.blocks_and_receipts() {
-            self.hash_to_receipts.insert(
-                b.hash(),
-                receipt
-                    .iter()
-                    .flat_map(|r| {
-                        r.as_ref().map(|r| Receipt {
-                            cumulative_gas_used: r.cumulative_gas_used as u128,
-                            logs: r.logs.clone(),
-                            status: alloy::consensus::Eip658Value::Eip658(r.success),
-                        })
-                    })
-                    .collect(),
-            );
+        for (b, receipts) in chain.blocks_and_receipts() {
+            self.hash_to_receipts.insert(b.hash(), convert_receipts(receipts));
         }
     }
 
     /// Commits [TxEnvelope]s to the provider.
     fn commit_txs(&mut self, chain: &Arc<Chain>) {
         for b in chain.blocks_iter() {
-            let txs = b
-                .transactions()
-                .flat_map(|tx| {
-                    let mut buf = Vec::new();
-                    tx.signature.encode(&mut buf);
-                    let sig = Signature::decode(&mut buf.as_slice()).ok()?;
-                    let new = match &tx.transaction {
-                        Transaction::Legacy(l) => {
-                            let legacy_tx = TxLegacy {
-                                chain_id: l.chain_id,
-                                nonce: l.nonce,
-                                gas_price: l.gas_price,
-                                gas_limit: l.gas_limit as u128,
-                                to: l.to,
-                                value: l.value,
-                                input: l.input.clone(),
-                            };
-                            TxEnvelope::Legacy(Signed::new_unchecked(legacy_tx, sig, tx.hash))
-                        }
-                        Transaction::Eip2930(e) => {
-                            let eip_tx = TxEip2930 {
-                                chain_id: e.chain_id,
-                                nonce: e.nonce,
-                                gas_price: e.gas_price,
-                                gas_limit: e.gas_limit as u128,
-                                to: e.to,
-                                value: e.value,
-                                input: e.input.clone(),
-                                access_list: alloy::eips::eip2930::AccessList(
-                                    e.access_list
-                                        .0
-                                        .clone()
-                                        .into_iter()
-                                        .map(|item| alloy::eips::eip2930::AccessListItem {
-                                            address: item.address,
-                                            storage_keys: item.storage_keys.clone(),
-                                        })
-                                        .collect(),
-                                ),
-                            };
-                            TxEnvelope::Eip2930(Signed::new_unchecked(eip_tx, sig, tx.hash))
-                        }
-                        Transaction::Eip1559(e) => {
-                            let eip_tx = TxEip1559 {
-                                chain_id: e.chain_id,
-                                nonce: e.nonce,
-                                max_priority_fee_per_gas: e.max_priority_fee_per_gas,
-                                max_fee_per_gas: e.max_fee_per_gas,
-                                gas_limit: e.gas_limit as u128,
-                                to: e.to,
-                                value: e.value,
-                                input: e.input.clone(),
-                                access_list: alloy::eips::eip2930::AccessList(
-                                    e.access_list
-                                        .0
-                                        .clone()
-                                        .into_iter()
-                                        .map(|item| alloy::eips::eip2930::AccessListItem {
-                                            address: item.address,
-                                            storage_keys: item.storage_keys.clone(),
-                                        })
-                                        .collect(),
-                                ),
-                            };
-                            TxEnvelope::Eip1559(Signed::new_unchecked(eip_tx, sig, tx.hash))
-                        }
-                        Transaction::Eip4844(e) => {
-                            let eip_tx = TxEip4844 {
-                                chain_id: e.chain_id,
-                                nonce: e.nonce,
-                                max_fee_per_gas: e.max_fee_per_gas,
-                                max_priority_fee_per_gas: e.max_priority_fee_per_gas,
-                                max_fee_per_blob_gas: e.max_fee_per_blob_gas,
-                                blob_versioned_hashes: e.blob_versioned_hashes.clone(),
-                                gas_limit: e.gas_limit as u128,
-                                to: e.to,
-                                value: e.value,
-                                input: e.input.clone(),
-                                access_list: alloy::eips::eip2930::AccessList(
-                                    e.access_list
-                                        .0
-                                        .clone()
-                                        .into_iter()
-                                        .map(|item| alloy::eips::eip2930::AccessListItem {
-                                            address: item.address,
-                                            storage_keys: item.storage_keys.clone(),
-                                        })
-                                        .collect(),
-                                ),
-                            };
-                            TxEnvelope::Eip4844(Signed::new_unchecked(
-                                TxEip4844Variant::TxEip4844(eip_tx),
-                                sig,
-                                tx.hash,
-                            ))
-                        }
-                        Transaction::Eip7702(_) => unimplemented!("EIP-7702 not implemented"),
-                    };
-                    Some(new)
-                })
-                .collect();
-            self.hash_to_txs.insert(b.hash(), txs);
+            self.hash_to_txs.insert(b.hash(), b.transactions().flat_map(convert_tx).collect());
         }
     }
 }
@@ -295,35 +369,57 @@ impl InMemoryChainProviderInner {
 impl ChainProvider for InMemoryChainProvider {
     /// Fetch the L1 [Header] for the given [B256] hash.
     async fn header_by_hash(&mut self, hash: B256) -> anyhow::Result<Header> {
-        self.0
-            .read()
-            .hash_to_header
-            .get(&hash)
-            .cloned()
-            .ok_or_else(|| anyhow::anyhow!("Header not found"))
+        let mut inner = self.0.write();
+        if let Some(header) = inner.hash_to_header.get(&hash).cloned() {
+            inner.touch(&hash);
+            record_lookup("header_by_hash", "memory");
+            return Ok(header);
+        }
+        #[cfg(feature = "online")]
+        if let Some(block) = inner.disk.as_ref().and_then(|disk| disk.block(hash)) {
+            record_lookup("header_by_hash", "disk");
+            return Ok(block.header);
+        }
+        record_lookup("header_by_hash", "miss");
+        Err(anyhow::anyhow!("Header not found"))
     }
 
     /// Returns the block at the given number, or an error if the block does not exist in the data
     /// source.
     async fn block_info_by_number(&mut self, number: u64) -> anyhow::Result<BlockInfo> {
-        self.0
-            .read()
-            .hash_to_block_info
-            .values()
-            .find(|bi| bi.number == number)
-            .cloned()
-            .ok_or_else(|| anyhow::anyhow!("Block not found"))
+        let mut inner = self.0.write();
+        if let Some(info) =
+            inner.hash_to_block_info.values().find(|bi| bi.number == number).copied()
+        {
+            inner.touch(&info.hash);
+            record_lookup("block_info_by_number", "memory");
+            return Ok(info);
+        }
+        #[cfg(feature = "online")]
+        if let Some(block) = inner.disk.as_ref().and_then(|disk| disk.block_by_number(number)) {
+            record_lookup("block_info_by_number", "disk");
+            return Ok(block.info);
+        }
+        record_lookup("block_info_by_number", "miss");
+        Err(anyhow::anyhow!("Block not found"))
     }
 
     /// Returns all receipts in the block with the given hash, or an error if the block does not
     /// exist in the data source.
     async fn receipts_by_hash(&mut self, hash: B256) -> anyhow::Result<Vec<Receipt>> {
-        self.0
-            .read()
-            .hash_to_receipts
-            .get(&hash)
-            .cloned()
-            .ok_or_else(|| anyhow::anyhow!("Receipts not found"))
+        let mut inner = self.0.write();
+        if let Some(receipts) = inner.hash_to_receipts.get(&hash).cloned() {
+            inner.touch(&hash);
+            record_lookup("receipts_by_hash", "memory");
+            return Ok(receipts);
+        }
+        #[cfg(feature = "online")]
+        if let Some(block) = inner.disk.as_ref().and_then(|disk| disk.block(hash)) {
+            record_lookup("receipts_by_hash", "disk");
+            return Ok(block.receipts);
+        }
+        record_lookup("receipts_by_hash", "miss");
+        Err(anyhow::anyhow!("Receipts not found"))
     }
 
     /// Returns block info and transactions for the given block hash.
@@ -331,22 +427,278 @@ impl ChainProvider for InMemoryChainProvider {
         &mut self,
         hash: B256,
     ) -> anyhow::Result<(BlockInfo, Vec<TxEnvelope>)> {
-        let block_info = self
-            .0
-            .read()
-            .hash_to_block_info
-            .get(&hash)
-            .cloned()
-            .ok_or_else(|| anyhow::anyhow!("Block not found"))?;
-
-        let txs = self
-            .0
-            .read()
-            .hash_to_txs
-            .get(&hash)
-            .cloned()
-            .ok_or_else(|| anyhow::anyhow!("Tx not found"))?;
-
-        Ok((block_info, txs))
+        let mut inner = self.0.write();
+        if let Some(block_info) = inner.hash_to_block_info.get(&hash).copied() {
+            let txs = inner
+                .hash_to_txs
+                .get(&hash)
+                .cloned()
+                .ok_or_else(|| anyhow::anyhow!("Tx not found"))?;
+            inner.touch(&hash);
+            record_lookup("block_info_and_transactions_by_hash", "memory");
+            return Ok((block_info, txs));
+        }
+        #[cfg(feature = "online")]
+        if let Some(block) = inner.disk.as_ref().and_then(|disk| disk.block(hash)) {
+            record_lookup("block_info_and_transactions_by_hash", "disk");
+            return Ok((block.info, block.txs));
+        }
+        record_lookup("block_info_and_transactions_by_hash", "miss");
+        Err(anyhow::anyhow!("Block not found"))
+    }
+}
+
+/// Converts a reth [Header](reth::primitives::Header) to an alloy [Header].
+// TODO: won't need to coerce once reth uses alloy types
+pub(crate) fn convert_header(header: &reth::primitives::Header) -> Header {
+    Header {
+        parent_hash: header.parent_hash,
+        ommers_hash: header.ommers_hash,
+        beneficiary: header.beneficiary,
+        state_root: header.state_root,
+        requests_root: header.requests_root,
+        transactions_root: header.transactions_root,
+        receipts_root: header.receipts_root,
+        withdrawals_root: header.withdrawals_root,
+        logs_bloom: header.logs_bloom,
+        difficulty: header.difficulty,
+        number: header.number,
+        gas_limit: header.gas_limit as u128,
+        gas_used: header.gas_used as u128,
+        timestamp: header.timestamp,
+        mix_hash: header.mix_hash,
+        nonce: header.nonce.into(),
+        base_fee_per_gas: header.base_fee_per_gas.map(|b| b as u128),
+        blob_gas_used: header.blob_gas_used.map(|b| b as u128),
+        excess_blob_gas: header.excess_blob_gas.map(|b| b as u128),
+        parent_beacon_block_root: header.parent_beacon_block_root,
+        extra_data: header.extra_data.clone(),
+    }
+}
+
+/// Converts the reth receipts of a block to alloy [Receipt]s, skipping pruned ones.
+pub(crate) fn convert_receipts(receipts: &[Option<reth::primitives::Receipt>]) -> Vec<Receipt> {
+    receipts
+        .iter()
+        .flat_map(|r| {
+            r.as_ref().map(|r| Receipt {
+                cumulative_gas_used: r.cumulative_gas_used as u128,
+                logs: r.logs.clone(),
+                status: alloy::consensus::Eip658Value::Eip658(r.success),
+            })
+        })
+        .collect()
+}
+
+/// Converts a reth [TransactionSigned] to an alloy [TxEnvelope].
+///
+/// Returns `None` if the signature cannot be converted, or for EIP-7702 transactions,
+/// which are skipped since they can't carry batcher data.
+pub(crate) fn convert_tx(tx: &TransactionSigned) -> Option<TxEnvelope> {
+    let mut buf = Vec::new();
+    tx.signature.encode(&mut buf);
+    let sig = Signature::decode(&mut buf.as_slice()).ok()?;
+    let new = match &tx.transaction {
+        Transaction::Legacy(l) => {
+            let legacy_tx = TxLegacy {
+                chain_id: l.chain_id,
+                nonce: l.nonce,
+                gas_price: l.gas_price,
+                gas_limit: l.gas_limit as u128,
+                to: l.to,
+                value: l.value,
+                input: l.input.clone(),
+            };
+            TxEnvelope::Legacy(Signed::new_unchecked(legacy_tx, sig, tx.hash))
+        }
+        Transaction::Eip2930(e) => {
+            let eip_tx = TxEip2930 {
+                chain_id: e.chain_id,
+                nonce: e.nonce,
+                gas_price: e.gas_price,
+                gas_limit: e.gas_limit as u128,
+                to: e.to,
+                value: e.value,
+                input: e.input.clone(),
+                access_list: alloy::eips::eip2930::AccessList(
+                    e.access_list
+                        .0
+                        .clone()
+                        .into_iter()
+                        .map(|item| alloy::eips::eip2930::AccessListItem {
+                            address: item.address,
+                            storage_keys: item.storage_keys.clone(),
+                        })
+                        .collect(),
+                ),
+            };
+            TxEnvelope::Eip2930(Signed::new_unchecked(eip_tx, sig, tx.hash))
+        }
+        Transaction::Eip1559(e) => {
+            let eip_tx = TxEip1559 {
+                chain_id: e.chain_id,
+                nonce: e.nonce,
+                max_priority_fee_per_gas: e.max_priority_fee_per_gas,
+                max_fee_per_gas: e.max_fee_per_gas,
+                gas_limit: e.gas_limit as u128,
+                to: e.to,
+                value: e.value,
+                input: e.input.clone(),
+                access_list: alloy::eips::eip2930::AccessList(
+                    e.access_list
+                        .0
+                        .clone()
+                        .into_iter()
+                        .map(|item| alloy::eips::eip2930::AccessListItem {
+                            address: item.address,
+                            storage_keys: item.storage_keys.clone(),
+                        })
+                        .collect(),
+                ),
+            };
+            TxEnvelope::Eip1559(Signed::new_unchecked(eip_tx, sig, tx.hash))
+        }
+        Transaction::Eip4844(e) => {
+            let eip_tx = TxEip4844 {
+                chain_id: e.chain_id,
+                nonce: e.nonce,
+                max_fee_per_gas: e.max_fee_per_gas,
+                max_priority_fee_per_gas: e.max_priority_fee_per_gas,
+                max_fee_per_blob_gas: e.max_fee_per_blob_gas,
+                blob_versioned_hashes: e.blob_versioned_hashes.clone(),
+                gas_limit: e.gas_limit as u128,
+                to: e.to,
+                value: e.value,
+                input: e.input.clone(),
+                access_list: alloy::eips::eip2930::AccessList(
+                    e.access_list
+                        .0
+                        .clone()
+                        .into_iter()
+                        .map(|item| alloy::eips::eip2930::AccessListItem {
+                            address: item.address,
+                            storage_keys: item.storage_keys.clone(),
+                        })
+                        .collect(),
+                ),
+            };
+            TxEnvelope::Eip4844(Signed::new_unchecked(
+                TxEip4844Variant::TxEip4844(eip_tx),
+                sig,
+                tx.hash,
+            ))
+        }
+        Transaction::Eip7702(_) => return None,
+    };
+    Some(new)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reth::{
+        primitives::{Header as RethHeader, SealedBlock, SealedBlockWithSenders},
+        providers::ExecutionOutcome,
+    };
+
+    fn chain(number: u64) -> Arc<Chain> {
+        let header = RethHeader { number, ..Default::default() }.seal_slow();
+        let block = SealedBlockWithSenders {
+            block: SealedBlock { header, ..Default::default() },
+            senders: vec![],
+        };
+        Arc::new(Chain::from_block(block, ExecutionOutcome::default(), None))
+    }
+
+    #[test]
+    fn test_convert_tx_skips_eip7702() {
+        let tx = TransactionSigned::from_transaction_and_signature(
+            Transaction::Eip7702(Default::default()),
+            Default::default(),
+        );
+        assert_eq!(convert_tx(&tx), None);
+    }
+
+    #[tokio::test]
+    async fn test_apply_revert_and_commit() {
+        let mut provider = InMemoryChainProvider::with_capacity(16);
+        let old = chain(1);
+        provider.apply(&ExExNotification::ChainCommitted { new: old.clone() });
+        assert!(provider.block_info_by_number(1).await.is_ok());
+
+        let new = chain(2);
+        provider.apply(&ExExNotification::ChainReorged { old, new: new.clone() });
+        assert!(provider.block_info_by_number(1).await.is_err());
+        assert!(provider.block_info_by_number(2).await.is_ok());
+        assert_eq!(provider.0.read().key_order.len(), 1);
+        assert_eq!(provider.tip().map(|tip| tip.number), Some(2));
+        assert!(provider.committed_at(&new.tip().hash()).is_some());
+    }
+
+    #[tokio::test]
+    async fn test_unwind() {
+        let mut provider = InMemoryChainProvider::with_capacity(16);
+        let (first, second) = (chain(1), chain(2));
+        provider.commit(first.clone());
+        provider.commit(second.clone());
+
+        provider.unwind(&[first.tip().hash()]);
+        assert!(provider.block_info_by_number(1).await.is_err());
+        assert_eq!(provider.tip().map(|tip| tip.number), Some(2));
+        provider.unwind(&[second.tip().hash()]);
+        assert_eq!(provider.tip(), None);
+        assert!(provider.0.read().key_order.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_evicts_least_recently_used_and_finalized() {
+        let mut provider = InMemoryChainProvider::with_capacity(2);
+        let first = chain(1);
+        provider.commit(first.clone());
+        provider.commit(chain(2));
+        // Reading block 1 makes block 2 the least recently used one.
+        assert!(provider.header_by_hash(first.tip().hash()).await.is_ok());
+        provider.commit(chain(3));
+        assert!(provider.block_info_by_number(1).await.is_ok());
+        assert!(provider.block_info_by_number(2).await.is_err());
+
+        provider.finalize(3);
+        assert_eq!(provider.len(), 1);
+        assert!(provider.block_info_by_number(1).await.is_err());
+        assert_eq!(provider.tip().map(|tip| tip.number), Some(3));
+    }
+
+    #[tokio::test]
+    async fn test_spawn_from_exex_forwards_applied_notifications() {
+        let (sender, notifications) = mpsc::channel(1);
+        let (mut provider, mut forwarded) =
+            InMemoryChainProvider::spawn_from_exex(16, notifications);
+
+        sender.send(ExExNotification::ChainCommitted { new: chain(7) }).await.unwrap();
+        let notification = forwarded.recv().await.unwrap();
+        assert_eq!(notification.committed_chain().unwrap().tip().block.header().number, 7);
+        assert!(provider.block_info_by_number(7).await.is_ok());
+    }
+
+    #[cfg(feature = "online")]
+    #[tokio::test]
+    async fn test_disk_fallback_survives_restart() {
+        let dir = std::env::temp_dir().join(format!("hera-chain-fallback-{}", std::process::id()));
+        _ = std::fs::remove_dir_all(&dir);
+        let disk = DiskChainProvider::open(&dir, u64::MAX).unwrap();
+        let mut provider = InMemoryChainProvider::with_capacity(1).with_disk(disk);
+        provider.commit(chain(1));
+        provider.commit(chain(2));
+        // Block 1 was evicted from memory, but is still on disk.
+        assert!(provider.0.read().hash_to_block_info.values().all(|bi| bi.number != 1));
+        assert!(provider.block_info_by_number(1).await.is_ok());
+
+        provider.revert(chain(2));
+        let disk = DiskChainProvider::open(&dir, u64::MAX).unwrap();
+        let mut restarted = InMemoryChainProvider::with_capacity(1).with_disk(disk);
+        let info = restarted.block_info_by_number(1).await.unwrap();
+        assert!(restarted.header_by_hash(info.hash).await.is_ok());
+        assert!(restarted.block_info_by_number(2).await.is_err());
+        std::fs::remove_dir_all(dir).unwrap();
     }
 }
@@ -0,0 +1,417 @@
+//! Disk-backed Chain Provider
+
+use alloc::{collections::BTreeMap, sync::Arc, vec::Vec};
+use std::{fs, io, path::PathBuf, str::FromStr};
+
+use alloy::{
+    consensus::{Header, Receipt, ReceiptEnvelope, TxEnvelope},
+    eips::eip2718::{Decodable2718, Encodable2718},
+    primitives::{Bytes, B256},
+};
+use alloy_rlp::{Decodable, Encodable};
+use async_trait::async_trait;
+use eyre::{eyre, Result};
+use hashbrown::HashMap;
+use kona_derive::traits::ChainProvider;
+use kona_primitives::BlockInfo;
+use parking_lot::Mutex;
+use reth::providers::Chain;
+use tracing::{debug, warn};
+
+use crate::chain_provider::{convert_header, convert_receipts, convert_tx};
+
+/// The default maximum number of L1 blocks kept by a [DiskChainProvider]
+/// (about two weeks of L1 blocks).
+pub const DEFAULT_CHAIN_STORE_MAX_BLOCKS: u64 = 100_000;
+
+/// The L1 chain data of a block, as stored by a [DiskChainProvider].
+#[derive(Debug, Clone, PartialEq)]
+pub struct StoredBlock {
+    /// The block info.
+    pub info: BlockInfo,
+    /// The block header.
+    pub header: Header,
+    /// The receipts of the block transactions.
+    pub receipts: Vec<Receipt>,
+    /// The block transactions.
+    pub txs: Vec<TxEnvelope>,
+}
+
+impl StoredBlock {
+    /// Returns the stored blocks of the given Chain.
+    pub fn from_chain(chain: &Chain) -> Vec<Self> {
+        chain
+            .blocks_and_receipts()
+            .map(|(block, receipts)| Self {
+                info: BlockInfo {
+                    hash: block.hash(),
+                    number: block.number,
+                    parent_hash: block.parent_hash,
+                    timestamp: block.timestamp,
+                },
+                header: convert_header(&block.header),
+                receipts: convert_receipts(receipts),
+                txs: block.transactions().flat_map(convert_tx).collect(),
+            })
+            .collect()
+    }
+
+    /// Encodes the block as its RLP header, followed by the RLP lists of its
+    /// EIP-2718 encoded receipts and transactions.
+    ///
+    /// Receipts are stored as legacy receipts: derivation only reads their status
+    /// and logs, which do not depend on the receipt type.
+    fn encode(&self) -> Vec<u8> {
+        let receipts: Vec<Bytes> = self
+            .receipts
+            .iter()
+            .map(|r| ReceiptEnvelope::Legacy(r.clone().with_bloom()).encoded_2718().into())
+            .collect();
+        let txs: Vec<Bytes> = self.txs.iter().map(|tx| tx.encoded_2718().into()).collect();
+
+        let mut out = Vec::new();
+        self.header.encode(&mut out);
+        receipts.encode(&mut out);
+        txs.encode(&mut out);
+        out
+    }
+
+    /// Decodes a block with the given hash, encoded by [StoredBlock::encode].
+    fn decode(hash: B256, mut data: &[u8]) -> Result<Self> {
+        let header = Header::decode(&mut data).map_err(|e| eyre!("Invalid header: {:?}", e))?;
+        let receipts = Vec::<Bytes>::decode(&mut data)
+            .map_err(|e| eyre!("Invalid receipts: {:?}", e))?
+            .iter()
+            .map(|raw| {
+                let envelope = ReceiptEnvelope::decode_2718(&mut raw.as_ref())
+                    .map_err(|e| eyre!("Invalid receipt: {:?}", e))?;
+                envelope.as_receipt().cloned().ok_or_else(|| eyre!("Unsupported receipt type"))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let txs = Vec::<Bytes>::decode(&mut data)
+            .map_err(|e| eyre!("Invalid transactions: {:?}", e))?
+            .iter()
+            .map(|raw| {
+                TxEnvelope::decode_2718(&mut raw.as_ref())
+                    .map_err(|e| eyre!("Invalid transaction: {:?}", e))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let info = BlockInfo {
+            hash,
+            number: header.number,
+            parent_hash: header.parent_hash,
+            timestamp: header.timestamp,
+        };
+        Ok(Self { info, header, receipts, txs })
+    }
+}
+
+/// A [ChainProvider] that stores L1 chain data on disk, with one file per block,
+/// so that it survives restarts of the node.
+///
+/// Only the canonical chain is kept: reverted blocks are removed. Once the provider
+/// holds more than its maximum number of blocks, the oldest ones are pruned first,
+/// but never the safe head's L1 origin or newer blocks, since derivation restarts
+/// from there.
+///
+/// Lookups read from disk, so the provider is meant to back an in-memory cache,
+/// see [InMemoryChainProvider::with_disk](crate::InMemoryChainProvider::with_disk).
+#[derive(Debug, Clone)]
+pub struct DiskChainProvider(Arc<Mutex<DiskChainProviderInner>>);
+
+impl DiskChainProvider {
+    /// Opens the provider in the given directory, creating it if needed.
+    pub fn open(dir: impl Into<PathBuf>, max_blocks: u64) -> Result<Self> {
+        let inner = DiskChainProviderInner::open(dir.into(), max_blocks)?;
+        Ok(Self(Arc::new(Mutex::new(inner))))
+    }
+
+    /// Returns the number of stored blocks.
+    pub fn len(&self) -> usize {
+        self.0.lock().blocks.len()
+    }
+
+    /// Returns `true` if no block is stored.
+    pub fn is_empty(&self) -> bool {
+        self.0.lock().blocks.is_empty()
+    }
+
+    /// Returns the [BlockInfo] of the latest stored block, if any.
+    pub fn tip(&self) -> Option<BlockInfo> {
+        let inner = self.0.lock();
+        let (_, hash) = inner.blocks.last_key_value()?;
+        inner.block(*hash).map(|block| block.info)
+    }
+
+    /// Returns the stored block with the given hash, if any.
+    pub fn block(&self, hash: B256) -> Option<StoredBlock> {
+        self.0.lock().block(hash)
+    }
+
+    /// Returns the stored block with the given number, if any.
+    pub fn block_by_number(&self, number: u64) -> Option<StoredBlock> {
+        let inner = self.0.lock();
+        inner.blocks.get(&number).and_then(|hash| inner.block(*hash))
+    }
+
+    /// Stores the blocks of the given Chain, then prunes the oldest blocks
+    /// if the provider is over its maximum number of blocks.
+    pub fn commit(&mut self, chain: &Chain) -> Result<()> {
+        let mut inner = self.0.lock();
+        for block in StoredBlock::from_chain(chain) {
+            inner.insert(&block)?;
+        }
+        inner.prune()
+    }
+
+    /// Removes the blocks of a reverted Chain.
+    pub fn revert(&mut self, chain: &Chain) -> Result<()> {
+        let mut inner = self.0.lock();
+        for header in chain.headers() {
+            inner.remove(header.hash())?;
+        }
+        Ok(())
+    }
+
+    /// Sets the L1 origin of the safe head, allowing older blocks to be pruned.
+    pub fn set_safe_origin(&mut self, number: u64) -> Result<()> {
+        let mut inner = self.0.lock();
+        inner.safe_origin = inner.safe_origin.max(number);
+        inner.prune()
+    }
+}
+
+#[async_trait]
+impl ChainProvider for DiskChainProvider {
+    async fn header_by_hash(&mut self, hash: B256) -> anyhow::Result<Header> {
+        self.block(hash)
+            .map(|block| block.header)
+            .ok_or_else(|| anyhow::anyhow!("Header not found"))
+    }
+
+    async fn block_info_by_number(&mut self, number: u64) -> anyhow::Result<BlockInfo> {
+        self.block_by_number(number)
+            .map(|block| block.info)
+            .ok_or_else(|| anyhow::anyhow!("Block not found"))
+    }
+
+    async fn receipts_by_hash(&mut self, hash: B256) -> anyhow::Result<Vec<Receipt>> {
+        self.block(hash)
+            .map(|block| block.receipts)
+            .ok_or_else(|| anyhow::anyhow!("Receipts not found"))
+    }
+
+    async fn block_info_and_transactions_by_hash(
+        &mut self,
+        hash: B256,
+    ) -> anyhow::Result<(BlockInfo, Vec<TxEnvelope>)> {
+        self.block(hash)
+            .map(|block| (block.info, block.txs))
+            .ok_or_else(|| anyhow::anyhow!("Block not found"))
+    }
+}
+
+/// The inner state of a [DiskChainProvider].
+#[derive(Debug)]
+struct DiskChainProviderInner {
+    /// The directory of the block files.
+    dir: PathBuf,
+    /// The maximum number of stored blocks.
+    max_blocks: u64,
+    /// The hash of the stored block at each number.
+    blocks: BTreeMap<u64, B256>,
+    /// The number of each stored block, by hash.
+    numbers: HashMap<B256, u64>,
+    /// The L1 origin of the safe head. Blocks from this number on are never pruned.
+    safe_origin: u64,
+}
+
+impl DiskChainProviderInner {
+    /// Indexes the block files in the given directory, creating it if needed.
+    fn open(dir: PathBuf, max_blocks: u64) -> Result<Self> {
+        fs::create_dir_all(&dir)?;
+
+        let mut inner = Self {
+            dir,
+            max_blocks,
+            blocks: BTreeMap::new(),
+            numbers: HashMap::new(),
+            safe_origin: 0,
+        };
+        for entry in fs::read_dir(&inner.dir)? {
+            let entry = entry?;
+            let Some((number, hash)) = entry.file_name().to_str().and_then(parse_file_name) else {
+                warn!("Ignoring unknown file in chain store: {:?}", entry.path());
+                continue;
+            };
+            inner.index(number, hash)?;
+        }
+        debug!(blocks = inner.blocks.len(), "Opened chain store at {:?}", inner.dir);
+
+        Ok(inner)
+    }
+
+    /// Reads the stored block with the given hash.
+    fn block(&self, hash: B256) -> Option<StoredBlock> {
+        let number = *self.numbers.get(&hash)?;
+        let data = fs::read(self.path(number, hash)).ok()?;
+        match StoredBlock::decode(hash, &data) {
+            Ok(block) => Some(block),
+            Err(err) => {
+                warn!(?err, "Failed to decode stored block {}", number);
+                None
+            }
+        }
+    }
+
+    /// Stores the given block, replacing any other block at the same number.
+    fn insert(&mut self, block: &StoredBlock) -> Result<()> {
+        let BlockInfo { number, hash, .. } = block.info;
+        // Write to a temporary file first, so that a crash never leaves a partial block.
+        let tmp = self.dir.join(alloc::format!("{:016x}-{:x}.tmp", number, hash));
+        fs::write(&tmp, block.encode())?;
+        fs::rename(&tmp, self.path(number, hash))?;
+        self.index(number, hash)
+    }
+
+    /// Indexes the block with the given number and hash, removing any other
+    /// block at the same number.
+    fn index(&mut self, number: u64, hash: B256) -> Result<()> {
+        if let Some(replaced) = self.blocks.insert(number, hash).filter(|h| *h != hash) {
+            self.numbers.remove(&replaced);
+            self.remove_file(number, replaced)?;
+        }
+        self.numbers.insert(hash, number);
+        Ok(())
+    }
+
+    /// Removes the block with the given hash, if it is stored.
+    fn remove(&mut self, hash: B256) -> Result<()> {
+        let Some(number) = self.numbers.remove(&hash) else {
+            return Ok(());
+        };
+        self.blocks.remove(&number);
+        self.remove_file(number, hash)
+    }
+
+    /// Removes the oldest blocks until the provider holds at most its maximum number
+    /// of blocks, without pruning the safe head's L1 origin or newer blocks.
+    fn prune(&mut self) -> Result<()> {
+        while self.blocks.len() as u64 > self.max_blocks {
+            let Some((&number, &hash)) = self.blocks.first_key_value() else {
+                break;
+            };
+            if number >= self.safe_origin {
+                break;
+            }
+            self.remove(hash)?;
+            metrics::counter!("hera_chain_store_pruned_blocks_total").increment(1);
+        }
+        metrics::gauge!("hera_chain_store_blocks").set(self.blocks.len() as f64);
+        Ok(())
+    }
+
+    /// Removes the file of the given block, if it exists.
+    fn remove_file(&self, number: u64, hash: B256) -> Result<()> {
+        match fs::remove_file(self.path(number, hash)) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => {
+                Err(eyre!("Failed to remove stored block {}: {}", number, e))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns the path of the file of the given block.
+    fn path(&self, number: u64, hash: B256) -> PathBuf {
+        self.dir.join(alloc::format!("{:016x}-{:x}.block", number, hash))
+    }
+}
+
+/// Parses the block number and hash from the name of a block file.
+fn parse_file_name(name: &str) -> Option<(u64, B256)> {
+    let (number, hash) = name.strip_suffix(".block")?.split_once('-')?;
+    Some((u64::from_str_radix(number, 16).ok()?, B256::from_str(hash).ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::{consensus::Eip658Value, primitives::Log};
+
+    fn block(number: u64) -> StoredBlock {
+        let header = Header { number, ..Default::default() };
+        let hash = header.hash_slow();
+        let receipt = Receipt {
+            status: Eip658Value::Eip658(true),
+            cumulative_gas_used: 21_000 * number as u128,
+            logs: vec![Log::empty()],
+        };
+        StoredBlock {
+            info: BlockInfo { hash, number, ..Default::default() },
+            header,
+            receipts: vec![receipt],
+            txs: vec![],
+        }
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(alloc::format!("hera-{}-{}", name, std::process::id()));
+        _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[tokio::test]
+    async fn test_lookup_and_reopen() {
+        let dir = temp_dir("chain-store-reopen");
+        let provider = DiskChainProvider::open(&dir, u64::MAX).unwrap();
+        let stored = block(1);
+        provider.0.lock().insert(&stored).unwrap();
+        drop(provider);
+
+        let mut reopened = DiskChainProvider::open(&dir, u64::MAX).unwrap();
+        assert_eq!(reopened.block(stored.info.hash), Some(stored.clone()));
+        assert_eq!(reopened.tip(), Some(stored.info));
+        assert_eq!(reopened.block_info_by_number(1).await.unwrap(), stored.info);
+        assert_eq!(reopened.receipts_by_hash(stored.info.hash).await.unwrap(), stored.receipts);
+        assert!(reopened.block_info_by_number(2).await.is_err());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_insert_replaces_block_at_same_number() {
+        let dir = temp_dir("chain-store-replace");
+        let provider = DiskChainProvider::open(&dir, u64::MAX).unwrap();
+        let old = block(1);
+        let mut new = block(1);
+        new.header.timestamp = 12;
+        new.info.hash = new.header.hash_slow();
+
+        provider.0.lock().insert(&old).unwrap();
+        provider.0.lock().insert(&new).unwrap();
+        assert_eq!(provider.len(), 1);
+        assert_eq!(provider.block(old.info.hash), None);
+        assert!(provider.block(new.info.hash).is_some());
+        assert!(!provider.0.lock().path(1, old.info.hash).exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_prune_oldest_before_safe_origin() {
+        let dir = temp_dir("chain-store-prune");
+        let mut provider = DiskChainProvider::open(&dir, 2).unwrap();
+        for number in 1..=3 {
+            let mut inner = provider.0.lock();
+            inner.insert(&block(number)).unwrap();
+            inner.prune().unwrap();
+        }
+        // Nothing is older than the safe head's origin yet.
+        assert_eq!(provider.len(), 3);
+
+        provider.set_safe_origin(3).unwrap();
+        assert_eq!(provider.len(), 2);
+        assert_eq!(provider.block(block(1).info.hash), None);
+        assert!(provider.block(block(2).info.hash).is_some());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,82 @@
+//! Chain Provider of a derivation pipeline running as an ExEx
+
+use alloc::vec::Vec;
+use core::fmt;
+
+use alloy::{
+    consensus::{Header, Receipt, TxEnvelope},
+    primitives::B256,
+};
+use async_trait::async_trait;
+use kona_derive::traits::ChainProvider;
+use kona_primitives::BlockInfo;
+use reth::providers::BlockReader;
+
+use crate::{InMemoryChainProvider, RethDbChainProvider};
+
+/// The [ChainProvider] of a derivation pipeline running as an ExEx, which reads the L1
+/// chain data either from a copy fed by the ExEx notifications, or directly from the
+/// database of the host node.
+#[derive(Clone)]
+pub enum ExExChainProvider<P> {
+    /// Reads from the blocks kept in memory, see [InMemoryChainProvider].
+    InMemory(InMemoryChainProvider),
+    /// Reads from the host node's database, see [RethDbChainProvider].
+    RethDb(RethDbChainProvider<P>),
+}
+
+impl<P> fmt::Debug for ExExChainProvider<P> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InMemory(provider) => f.debug_tuple("InMemory").field(provider).finish(),
+            Self::RethDb(provider) => f.debug_tuple("RethDb").field(provider).finish(),
+        }
+    }
+}
+
+impl<P> ExExChainProvider<P> {
+    /// Returns the [InMemoryChainProvider] the chain data is read from, if any.
+    pub const fn in_memory(&self) -> Option<&InMemoryChainProvider> {
+        match self {
+            Self::InMemory(provider) => Some(provider),
+            Self::RethDb(_) => None,
+        }
+    }
+}
+
+#[async_trait]
+impl<P> ChainProvider for ExExChainProvider<P>
+where
+    P: BlockReader + Clone + Send + Sync,
+{
+    async fn header_by_hash(&mut self, hash: B256) -> anyhow::Result<Header> {
+        match self {
+            Self::InMemory(provider) => provider.header_by_hash(hash).await,
+            Self::RethDb(provider) => provider.header_by_hash(hash).await,
+        }
+    }
+
+    async fn block_info_by_number(&mut self, number: u64) -> anyhow::Result<BlockInfo> {
+        match self {
+            Self::InMemory(provider) => provider.block_info_by_number(number).await,
+            Self::RethDb(provider) => provider.block_info_by_number(number).await,
+        }
+    }
+
+    async fn receipts_by_hash(&mut self, hash: B256) -> anyhow::Result<Vec<Receipt>> {
+        match self {
+            Self::InMemory(provider) => provider.receipts_by_hash(hash).await,
+            Self::RethDb(provider) => provider.receipts_by_hash(hash).await,
+        }
+    }
+
+    async fn block_info_and_transactions_by_hash(
+        &mut self,
+        hash: B256,
+    ) -> anyhow::Result<(BlockInfo, Vec<TxEnvelope>)> {
+        match self {
+            Self::InMemory(provider) => provider.block_info_and_transactions_by_hash(hash).await,
+            Self::RethDb(provider) => provider.block_info_and_transactions_by_hash(hash).await,
+        }
+    }
+}
@@ -0,0 +1,71 @@
+//! KZG verification of blobs
+
+use alloy::primitives::B256;
+use kona_derive::errors::BlobProviderError;
+use kona_primitives::{Blob, IndexedBlobHash};
+use reth::primitives::{
+    constants::eip4844::MAINNET_KZG_TRUSTED_SETUP,
+    kzg::{Blob as KzgBlob, KzgCommitment},
+    kzg_to_versioned_hash,
+};
+
+/// Checks that the given blobs match the given versioned hashes, in order.
+///
+/// The KZG commitment of each blob is computed and hashed into its versioned hash.
+/// Since a commitment is fully determined by its blob, this binds each blob to the
+/// hash of the batcher transaction without trusting any commitment or proof served
+/// along with it.
+pub fn verify_blobs(hashes: &[IndexedBlobHash], blobs: &[Blob]) -> Result<(), BlobProviderError> {
+    if hashes.len() != blobs.len() {
+        return Err(verification_error(anyhow::anyhow!(
+            "Expected {} blobs, got {}",
+            hashes.len(),
+            blobs.len()
+        )));
+    }
+
+    for (hash, blob) in hashes.iter().zip(blobs) {
+        let versioned_hash = versioned_hash(blob).map_err(verification_error)?;
+        if versioned_hash != hash.hash {
+            return Err(verification_error(anyhow::anyhow!(
+                "Blob {} does not match its versioned hash: expected {}, got {}",
+                hash.index,
+                hash.hash,
+                versioned_hash
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Returns the versioned hash of the KZG commitment of the given blob.
+fn versioned_hash(blob: &Blob) -> anyhow::Result<B256> {
+    let kzg_blob = KzgBlob::from_bytes(blob.as_slice())
+        .map_err(|e| anyhow::anyhow!("Invalid blob: {:?}", e))?;
+    let commitment = KzgCommitment::blob_to_kzg_commitment(&kzg_blob, &MAINNET_KZG_TRUSTED_SETUP)
+        .map_err(|e| anyhow::anyhow!("Failed to commit blob: {:?}", e))?;
+    Ok(kzg_to_versioned_hash(commitment.to_bytes().as_slice()))
+}
+
+/// Counts a failed blob verification, and wraps its cause in a [BlobProviderError].
+fn verification_error(err: anyhow::Error) -> BlobProviderError {
+    metrics::counter!("hera_blob_verification_failures_total").increment(1);
+    BlobProviderError::Custom(err)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verify_blobs() {
+        let blob = Blob::repeat_byte(0);
+        let hash = IndexedBlobHash { index: 0, hash: versioned_hash(&blob).unwrap() };
+        assert_eq!(hash.hash[0], 0x01);
+        assert!(verify_blobs(&[hash.clone()], &[blob]).is_ok());
+
+        let wrong = IndexedBlobHash { index: 0, hash: B256::repeat_byte(1) };
+        assert!(verify_blobs(&[wrong], &[blob]).is_err());
+        assert!(verify_blobs(&[hash], &[]).is_err());
+    }
+}
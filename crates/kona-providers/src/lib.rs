@@ -3,15 +3,60 @@
 #![doc(issue_tracker_base_url = "https://github.com/paradigmxyz/op-rs/issues/")]
 #![cfg_attr(docsrs, feature(doc_cfg, doc_auto_cfg))]
 #![cfg_attr(not(test), warn(unused_crate_dependencies))]
-#![cfg_attr(not(any(test, feature = "online")), no_std)]
+#![cfg_attr(not(any(test, feature = "online", feature = "reth")), no_std)]
 
 extern crate alloc;
 
 /// Re-export kona's derivation traits
 pub use kona_derive::traits::*;
 
+#[cfg(feature = "reth")]
 pub mod chain_provider;
+#[cfg(feature = "reth")]
 pub use chain_provider::InMemoryChainProvider;
 
+#[cfg(feature = "online")]
+pub mod alloy_chain_provider;
+#[cfg(feature = "online")]
+pub use alloy_chain_provider::AlloyChainProvider;
+
+#[cfg(feature = "online")]
+pub mod online_chain_provider;
+#[cfg(feature = "online")]
+pub use online_chain_provider::OnlineChainProvider;
+
+#[cfg(feature = "reth")]
+pub mod reth_db_chain_provider;
+#[cfg(feature = "reth")]
+pub use reth_db_chain_provider::RethDbChainProvider;
+
+#[cfg(feature = "reth")]
+pub mod exex_chain_provider;
+#[cfg(feature = "reth")]
+pub use exex_chain_provider::ExExChainProvider;
+
+#[cfg(all(feature = "online", feature = "reth"))]
+pub mod blob_cache;
+#[cfg(all(feature = "online", feature = "reth"))]
+pub use blob_cache::{DiskBlobCache, SharedDiskBlobCache};
+
+#[cfg(all(feature = "online", feature = "reth"))]
+pub mod disk_chain_provider;
+#[cfg(all(feature = "online", feature = "reth"))]
+pub use disk_chain_provider::DiskChainProvider;
+
+#[cfg(all(feature = "online", feature = "reth"))]
 pub mod blob_provider;
-pub use blob_provider::LayeredBlobProvider;
+#[cfg(all(feature = "online", feature = "reth"))]
+pub use blob_provider::{LayeredBlobProvider, OnlineBlobProvider};
+
+#[cfg(feature = "reth")]
+pub mod kzg;
+
+pub mod witness;
+pub use witness::{WitnessBundle, WitnessStore};
+
+#[cfg(feature = "reth")]
+pub mod recording;
+#[cfg(feature = "reth")]
+pub use recording::{RecordingBlobProvider, RecordingChainProvider};
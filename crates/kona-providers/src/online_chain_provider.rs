@@ -0,0 +1,336 @@
+//! Online Chain Provider with JSON-RPC batching and retries
+
+use alloc::{sync::Arc, vec::Vec};
+use core::{
+    future::Future,
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+
+use alloy::{
+    consensus::{Header, Receipt, TxEnvelope},
+    primitives::B256,
+    providers::{Provider, ReqwestProvider},
+    rpc::types::{Block, BlockTransactions, BlockTransactionsKind, TransactionReceipt},
+};
+use async_trait::async_trait;
+use kona_derive::traits::ChainProvider;
+use kona_primitives::BlockInfo;
+use parking_lot::Mutex;
+use tracing::{debug, warn};
+use url::Url;
+
+use crate::alloy_chain_provider::{Caches, DEFAULT_CHAIN_PROVIDER_CACHE_SIZE};
+
+/// The default number of times a failed request is retried.
+pub const DEFAULT_MAX_RETRIES: u32 = 5;
+
+/// The default delay before the first retry, doubled on each following one.
+pub const DEFAULT_INITIAL_RETRY_DELAY: Duration = Duration::from_millis(200);
+
+/// The max delay between two retries.
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(10);
+
+/// The default max number of receipts requested in a single JSON-RPC batch.
+pub const DEFAULT_RECEIPTS_BATCH_SIZE: usize = 100;
+
+/// A [ChainProvider] that fetches L1 chain data over HTTP with the standard `eth_*`
+/// methods, for L1 RPC endpoints that don't expose the `debug_*` namespace.
+///
+/// The receipts of a block are fetched with `eth_getBlockReceipts`, or if the endpoint
+/// doesn't support it, with `eth_getTransactionReceipt` for all its transactions, in
+/// JSON-RPC batches sent concurrently. Failed requests are retried with an exponential backoff,
+/// and the fetched data is cached by block hash, shared between clones.
+#[derive(Debug, Clone)]
+pub struct OnlineChainProvider {
+    /// The inner alloy provider.
+    inner: ReqwestProvider,
+    /// The caches of fetched chain data.
+    caches: Arc<Mutex<Caches>>,
+    /// The number of times a failed request is retried.
+    max_retries: u32,
+    /// The delay before the first retry.
+    initial_retry_delay: Duration,
+    /// The max number of receipts requested in a single batch.
+    batch_size: usize,
+    /// Whether `eth_getBlockReceipts` is supported, until it fails.
+    block_receipts: Arc<AtomicBool>,
+}
+
+impl OnlineChainProvider {
+    /// Creates a new [OnlineChainProvider] over HTTP.
+    pub fn new_http(url: Url) -> Self {
+        Self {
+            inner: ReqwestProvider::new_http(url),
+            caches: Arc::new(Mutex::new(Caches::new(DEFAULT_CHAIN_PROVIDER_CACHE_SIZE))),
+            max_retries: DEFAULT_MAX_RETRIES,
+            initial_retry_delay: DEFAULT_INITIAL_RETRY_DELAY,
+            batch_size: DEFAULT_RECEIPTS_BATCH_SIZE,
+            block_receipts: Arc::new(AtomicBool::new(true)),
+        }
+    }
+
+    /// Sets the number of times a failed request is retried, and the delay before the
+    /// first retry.
+    pub const fn with_retries(mut self, max_retries: u32, initial_delay: Duration) -> Self {
+        self.max_retries = max_retries;
+        self.initial_retry_delay = initial_delay;
+        self
+    }
+
+    /// Sets the max number of receipts requested in a single JSON-RPC batch.
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Runs the given request, retrying it with an exponential backoff on failure.
+    async fn retry<F, Fut, R>(&self, method: &'static str, request: F) -> anyhow::Result<R>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = anyhow::Result<R>>,
+    {
+        let mut delay = self.initial_retry_delay;
+        let mut attempt = 0;
+        loop {
+            match request().await {
+                Ok(result) => return Ok(result),
+                Err(err) if attempt < self.max_retries => {
+                    attempt += 1;
+                    debug!(?err, "Retrying {} in {:?} (attempt {})", method, delay, attempt);
+                    metrics::counter!("hera_l1_rpc_retries_total", "method" => method).increment(1);
+                    tokio::time::sleep(delay).await;
+                    delay = (delay * 2).min(MAX_RETRY_DELAY);
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    /// Fetches the block with the given hash or number, without its transactions.
+    async fn block(&self, id: alloy::eips::BlockId) -> anyhow::Result<Block> {
+        self.retry("eth_getBlock", || async {
+            self.inner
+                .get_block(id, BlockTransactionsKind::Hashes)
+                .await
+                .map_err(|e| anyhow::anyhow!("Failed to fetch block: {:?}", e))?
+                .ok_or_else(|| anyhow::anyhow!("Block not found"))
+        })
+        .await
+    }
+
+    /// Fetches the receipts of the given block with `eth_getBlockReceipts`.
+    async fn block_receipts(&self, hash: B256) -> anyhow::Result<Vec<TransactionReceipt>> {
+        self.retry("eth_getBlockReceipts", || async {
+            self.inner
+                .client()
+                .request::<_, Option<Vec<TransactionReceipt>>>("eth_getBlockReceipts", (hash,))
+                .await
+                .map_err(|e| anyhow::anyhow!("Failed to fetch block receipts: {:?}", e))?
+                .ok_or_else(|| anyhow::anyhow!("Receipts not found"))
+        })
+        .await
+    }
+
+    /// Fetches the receipts of the given block one transaction at a time, in batches.
+    async fn batched_receipts(&self, hash: B256) -> anyhow::Result<Vec<TransactionReceipt>> {
+        let block = self.block(hash.into()).await?;
+        let hashes: Vec<B256> = block.transactions.hashes().copied().collect();
+        let batches = hashes.chunks(self.batch_size).map(|chunk| {
+            self.retry("eth_getTransactionReceipt", || async {
+                let mut batch = self.inner.client().new_batch();
+                let waiters = chunk
+                    .iter()
+                    .map(|hash| {
+                        batch.add_call::<_, Option<TransactionReceipt>>(
+                            "eth_getTransactionReceipt",
+                            &(hash,),
+                        )
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|e| anyhow::anyhow!("Failed to batch receipts: {:?}", e))?;
+                batch.send().await.map_err(|e| anyhow::anyhow!("Failed to send batch: {:?}", e))?;
+                let mut fetched = Vec::with_capacity(waiters.len());
+                for waiter in waiters {
+                    let receipt = waiter
+                        .await
+                        .map_err(|e| anyhow::anyhow!("Failed to fetch receipt: {:?}", e))?
+                        .ok_or_else(|| anyhow::anyhow!("Receipt not found"))?;
+                    fetched.push(receipt);
+                }
+                Ok(fetched)
+            })
+        });
+        // The batches are sent concurrently, and their receipts kept in order.
+        let receipts = futures::future::try_join_all(batches).await?;
+        Ok(receipts.into_iter().flatten().collect())
+    }
+}
+
+#[async_trait]
+impl ChainProvider for OnlineChainProvider {
+    async fn header_by_hash(&mut self, hash: B256) -> anyhow::Result<Header> {
+        if let Some(header) = self.caches.lock().headers.get(&hash) {
+            return Ok(header);
+        }
+
+        let header = convert_header(&self.block(hash.into()).await?)?;
+        self.caches.lock().headers.insert(hash, header.clone());
+        Ok(header)
+    }
+
+    async fn block_info_by_number(&mut self, number: u64) -> anyhow::Result<BlockInfo> {
+        // The block at a given number may be reorged, so only the header is cached by hash.
+        let block = self.block(number.into()).await?;
+        let hash = block.header.hash.ok_or_else(|| anyhow::anyhow!("Missing block hash"))?;
+        let header = convert_header(&block)?;
+        let info = BlockInfo {
+            hash,
+            number: header.number,
+            parent_hash: header.parent_hash,
+            timestamp: header.timestamp,
+        };
+        self.caches.lock().headers.insert(hash, header);
+        Ok(info)
+    }
+
+    async fn receipts_by_hash(&mut self, hash: B256) -> anyhow::Result<Vec<Receipt>> {
+        if let Some(receipts) = self.caches.lock().receipts.get(&hash) {
+            return Ok(receipts);
+        }
+
+        let receipts = if self.block_receipts.load(Ordering::Relaxed) {
+            match self.block_receipts(hash).await {
+                Ok(receipts) => receipts,
+                Err(err) => {
+                    warn!(?err, "eth_getBlockReceipts failed, fetching receipts in batches");
+                    self.block_receipts.store(false, Ordering::Relaxed);
+                    self.batched_receipts(hash).await?
+                }
+            }
+        } else {
+            self.batched_receipts(hash).await?
+        };
+        let receipts = receipts.iter().map(convert_receipt).collect::<anyhow::Result<Vec<_>>>()?;
+
+        self.caches.lock().receipts.insert(hash, receipts.clone());
+        Ok(receipts)
+    }
+
+    async fn block_info_and_transactions_by_hash(
+        &mut self,
+        hash: B256,
+    ) -> anyhow::Result<(BlockInfo, Vec<TxEnvelope>)> {
+        if let Some(block) = self.caches.lock().blocks.get(&hash) {
+            return Ok(block);
+        }
+
+        let block = self
+            .retry("eth_getBlockByHash", || async {
+                self.inner
+                    .get_block(hash.into(), BlockTransactionsKind::Full)
+                    .await
+                    .map_err(|e| anyhow::anyhow!("Failed to fetch block: {:?}", e))?
+                    .ok_or_else(|| anyhow::anyhow!("Block not found"))
+            })
+            .await?;
+        let BlockTransactions::Full(txs) = block.transactions else {
+            anyhow::bail!("Block transactions are not hydrated");
+        };
+        let txs = txs
+            .into_iter()
+            .map(|tx| {
+                TxEnvelope::try_from(tx)
+                    .map_err(|e| anyhow::anyhow!("Failed to convert transaction: {:?}", e))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let info = BlockInfo {
+            hash,
+            number: block.header.number.ok_or_else(|| anyhow::anyhow!("Missing block number"))?,
+            parent_hash: block.header.parent_hash,
+            timestamp: block.header.timestamp,
+        };
+
+        self.caches.lock().blocks.insert(hash, (info, txs.clone()));
+        Ok((info, txs))
+    }
+}
+
+/// Converts the header of an RPC block to a consensus [Header].
+fn convert_header(block: &Block) -> anyhow::Result<Header> {
+    let header = &block.header;
+    Ok(Header {
+        parent_hash: header.parent_hash,
+        ommers_hash: header.uncles_hash,
+        beneficiary: header.miner,
+        state_root: header.state_root,
+        transactions_root: header.transactions_root,
+        receipts_root: header.receipts_root,
+        withdrawals_root: header.withdrawals_root,
+        requests_root: header.requests_root,
+        logs_bloom: header.logs_bloom,
+        difficulty: header.difficulty,
+        number: header.number.ok_or_else(|| anyhow::anyhow!("Missing block number"))?,
+        gas_limit: header.gas_limit,
+        gas_used: header.gas_used,
+        timestamp: header.timestamp,
+        mix_hash: header.mix_hash.unwrap_or_default(),
+        nonce: header.nonce.unwrap_or_default(),
+        base_fee_per_gas: header.base_fee_per_gas,
+        blob_gas_used: header.blob_gas_used,
+        excess_blob_gas: header.excess_blob_gas,
+        parent_beacon_block_root: header.parent_beacon_block_root,
+        extra_data: header.extra_data.clone(),
+    })
+}
+
+/// Converts an RPC receipt to a consensus [Receipt].
+fn convert_receipt(receipt: &TransactionReceipt) -> anyhow::Result<Receipt> {
+    let inner =
+        receipt.inner.as_receipt().ok_or_else(|| anyhow::anyhow!("Unsupported receipt type"))?;
+    Ok(Receipt {
+        status: inner.status,
+        cumulative_gas_used: inner.cumulative_gas_used,
+        logs: inner.logs.iter().map(|log| log.inner.clone()).collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_convert_header_hashes_like_the_block() {
+        let header =
+            Header { number: 7, timestamp: 42, gas_limit: 30_000_000, ..Default::default() };
+        let hash = header.hash_slow();
+        let mut block = Block::default();
+        block.header.hash = Some(hash);
+        block.header.number = Some(7);
+        block.header.timestamp = 42;
+        block.header.gas_limit = 30_000_000;
+        block.header.uncles_hash = header.ommers_hash;
+        block.header.mix_hash = Some(header.mix_hash);
+        block.header.nonce = Some(header.nonce);
+        assert_eq!(convert_header(&block).unwrap().hash_slow(), hash);
+    }
+
+    #[tokio::test]
+    async fn test_retry_with_backoff() {
+        let provider = OnlineChainProvider::new_http("http://127.0.0.1:8545".parse().unwrap())
+            .with_retries(2, Duration::from_millis(1));
+        let attempts = core::sync::atomic::AtomicU32::new(0);
+        let flaky = || async {
+            match attempts.fetch_add(1, Ordering::Relaxed) {
+                0 | 1 => anyhow::bail!("Unavailable"),
+                n => Ok(n),
+            }
+        };
+        assert_eq!(provider.retry("test", flaky).await.unwrap(), 2);
+
+        attempts.store(0, Ordering::Relaxed);
+        let provider = provider.with_retries(1, Duration::from_millis(1));
+        assert!(provider.retry("test", flaky).await.is_err());
+        assert_eq!(attempts.load(Ordering::Relaxed), 2);
+    }
+}
@@ -0,0 +1,201 @@
+//! Recording providers for fault proof witness generation
+
+use alloc::{boxed::Box, vec::Vec};
+
+use alloy::{
+    consensus::{Header, Receipt, ReceiptEnvelope, TxEnvelope},
+    eips::eip2718::Encodable2718,
+    primitives::{keccak256, Bytes, B256},
+};
+use async_trait::async_trait;
+use kona_derive::{
+    errors::BlobProviderError,
+    traits::{BlobProvider, ChainProvider},
+};
+use kona_primitives::{Blob, BlockInfo, IndexedBlobHash, FIELD_ELEMENTS_PER_BLOB};
+use reth::primitives::{
+    constants::eip4844::MAINNET_KZG_TRUSTED_SETUP,
+    kzg::{Blob as KzgBlob, KzgCommitment},
+};
+
+use crate::witness::{preimage_key, PreimageKeyType, WitnessStore};
+
+/// A [ChainProvider] decorator that records every fetched header, transaction
+/// and receipt as preimages in a [WitnessStore].
+///
+/// Transactions and receipts are recorded as the nodes of their block's ordered tries,
+/// along with the block header committing to the trie roots, which is how the
+/// fault proof program retrieves them.
+#[derive(Debug, Clone)]
+pub struct RecordingChainProvider<P> {
+    /// The inner chain provider.
+    inner: P,
+    /// The store of recorded preimages.
+    witness: WitnessStore,
+}
+
+impl<P> RecordingChainProvider<P> {
+    /// Creates a new [RecordingChainProvider] recording into the given [WitnessStore].
+    pub const fn new(inner: P, witness: WitnessStore) -> Self {
+        Self { inner, witness }
+    }
+
+    /// Returns the [WitnessStore] the preimages are recorded into.
+    pub const fn witness(&self) -> &WitnessStore {
+        &self.witness
+    }
+
+    /// Returns the inner chain provider.
+    pub fn into_inner(self) -> P {
+        self.inner
+    }
+}
+
+impl<P: ChainProvider + Send> RecordingChainProvider<P> {
+    /// Fetches the header of the given block and records its RLP encoding.
+    async fn record_header(&mut self, hash: B256) -> anyhow::Result<Header> {
+        let header = self.inner.header_by_hash(hash).await?;
+        self.witness.insert_rlp(&header);
+        Ok(header)
+    }
+}
+
+#[async_trait]
+impl<P: ChainProvider + Send> ChainProvider for RecordingChainProvider<P> {
+    async fn header_by_hash(&mut self, hash: B256) -> anyhow::Result<Header> {
+        self.record_header(hash).await
+    }
+
+    async fn block_info_by_number(&mut self, number: u64) -> anyhow::Result<BlockInfo> {
+        let info = self.inner.block_info_by_number(number).await?;
+        // The fault proof program walks the chain by header hash.
+        self.record_header(info.hash).await?;
+        Ok(info)
+    }
+
+    async fn receipts_by_hash(&mut self, hash: B256) -> anyhow::Result<Vec<Receipt>> {
+        let receipts = self.inner.receipts_by_hash(hash).await?;
+        self.record_header(hash).await?;
+
+        // The receipts don't carry their type, which is needed for their
+        // trie encoding, so take it from the block's transactions.
+        let (_, txs) = self.inner.block_info_and_transactions_by_hash(hash).await?;
+        if txs.len() != receipts.len() {
+            anyhow::bail!("Receipt count mismatch for block {}", hash);
+        }
+        let encoded = receipts
+            .iter()
+            .zip(&txs)
+            .map(|(receipt, tx)| encode_receipt(receipt.clone(), tx))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        self.witness.insert_ordered_trie(&encoded);
+
+        Ok(receipts)
+    }
+
+    async fn block_info_and_transactions_by_hash(
+        &mut self,
+        hash: B256,
+    ) -> anyhow::Result<(BlockInfo, Vec<TxEnvelope>)> {
+        let (info, txs) = self.inner.block_info_and_transactions_by_hash(hash).await?;
+        self.record_header(hash).await?;
+
+        let encoded = txs.iter().map(|tx| tx.encoded_2718()).collect::<Vec<_>>();
+        self.witness.insert_ordered_trie(&encoded);
+
+        Ok((info, txs))
+    }
+}
+
+/// Returns the EIP-2718 encoding of the given receipt, typed after its transaction.
+fn encode_receipt(receipt: Receipt, tx: &TxEnvelope) -> anyhow::Result<Vec<u8>> {
+    let receipt = receipt.with_bloom();
+    let envelope = match tx {
+        TxEnvelope::Legacy(_) => ReceiptEnvelope::Legacy(receipt),
+        TxEnvelope::Eip2930(_) => ReceiptEnvelope::Eip2930(receipt),
+        TxEnvelope::Eip1559(_) => ReceiptEnvelope::Eip1559(receipt),
+        TxEnvelope::Eip4844(_) => ReceiptEnvelope::Eip4844(receipt),
+        _ => anyhow::bail!("Unsupported transaction type: {:?}", tx.tx_type()),
+    };
+    Ok(envelope.encoded_2718())
+}
+
+/// A [BlobProvider] decorator that records every fetched blob as preimages
+/// in a [WitnessStore].
+///
+/// Each blob is recorded as its KZG commitment, keyed by the sha256 versioned hash,
+/// and as its individual field elements, keyed by the commitment and element index.
+#[derive(Debug, Clone)]
+pub struct RecordingBlobProvider<P> {
+    /// The inner blob provider.
+    inner: P,
+    /// The store of recorded preimages.
+    witness: WitnessStore,
+}
+
+impl<P> RecordingBlobProvider<P> {
+    /// Creates a new [RecordingBlobProvider] recording into the given [WitnessStore].
+    pub const fn new(inner: P, witness: WitnessStore) -> Self {
+        Self { inner, witness }
+    }
+
+    /// Returns the [WitnessStore] the preimages are recorded into.
+    pub const fn witness(&self) -> &WitnessStore {
+        &self.witness
+    }
+
+    /// Returns the inner blob provider.
+    pub fn into_inner(self) -> P {
+        self.inner
+    }
+
+    /// Records the commitment and field elements of the given blob.
+    fn record_blob(&self, hash: &IndexedBlobHash, blob: &Blob) -> Result<(), BlobProviderError> {
+        let kzg_blob = KzgBlob::from_bytes(blob.as_slice())
+            .map_err(|e| BlobProviderError::Custom(anyhow::anyhow!("Invalid blob: {:?}", e)))?;
+        let commitment =
+            KzgCommitment::blob_to_kzg_commitment(&kzg_blob, &MAINNET_KZG_TRUSTED_SETUP).map_err(
+                |e| BlobProviderError::Custom(anyhow::anyhow!("Failed to commit blob: {:?}", e)),
+            )?;
+        let commitment = commitment.to_bytes();
+
+        self.witness.insert(
+            preimage_key(hash.hash, PreimageKeyType::Sha256),
+            Bytes::copy_from_slice(commitment.as_slice()),
+        );
+
+        let mut element_key = [0u8; 80];
+        element_key[..48].copy_from_slice(commitment.as_slice());
+        for i in 0..FIELD_ELEMENTS_PER_BLOB {
+            element_key[72..].copy_from_slice(&i.to_be_bytes());
+            let key_hash = keccak256(element_key);
+            let element = &blob[(i as usize) << 5..(i as usize + 1) << 5];
+
+            self.witness.insert(
+                preimage_key(key_hash, PreimageKeyType::Keccak256),
+                Bytes::copy_from_slice(&element_key),
+            );
+            self.witness.insert(
+                preimage_key(key_hash, PreimageKeyType::Blob),
+                Bytes::copy_from_slice(element),
+            );
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<P: BlobProvider + Send> BlobProvider for RecordingBlobProvider<P> {
+    async fn get_blobs(
+        &mut self,
+        block_ref: &BlockInfo,
+        blob_hashes: &[IndexedBlobHash],
+    ) -> Result<Vec<Blob>, BlobProviderError> {
+        let blobs = self.inner.get_blobs(block_ref, blob_hashes).await?;
+        for (hash, blob) in blob_hashes.iter().zip(&blobs) {
+            self.record_blob(hash, blob)?;
+        }
+        Ok(blobs)
+    }
+}
@@ -0,0 +1,198 @@
+//! Chain Provider reading from the database of the host reth node
+
+use alloc::vec::Vec;
+use core::fmt;
+
+use alloy::{
+    consensus::{Header, Receipt, TxEnvelope},
+    primitives::B256,
+};
+use async_trait::async_trait;
+use kona_derive::traits::ChainProvider;
+use kona_primitives::BlockInfo;
+use reth::providers::BlockReader;
+
+use crate::chain_provider::{convert_header, convert_tx};
+
+/// A [ChainProvider] reading the L1 chain data directly from the database of the
+/// host reth node, through its provider, e.g. the one of the ExEx context.
+///
+/// Unlike the [InMemoryChainProvider](crate::InMemoryChainProvider), no copy of the
+/// chain data is kept: every lookup reads the headers, receipts and transactions from
+/// the node's database and static files, so any block the node has not pruned can be
+/// read, at the cost of converting it to the alloy types on each lookup.
+#[derive(Clone)]
+pub struct RethDbChainProvider<P> {
+    /// The provider of the host node's database.
+    provider: P,
+}
+
+impl<P> fmt::Debug for RethDbChainProvider<P> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RethDbChainProvider").finish_non_exhaustive()
+    }
+}
+
+impl<P> RethDbChainProvider<P> {
+    /// Creates a new [RethDbChainProvider] reading from the given provider.
+    pub const fn new(provider: P) -> Self {
+        Self { provider }
+    }
+
+    /// Returns the inner provider.
+    pub fn into_inner(self) -> P {
+        self.provider
+    }
+}
+
+#[async_trait]
+impl<P> ChainProvider for RethDbChainProvider<P>
+where
+    P: BlockReader + Clone + Send + Sync,
+{
+    async fn header_by_hash(&mut self, hash: B256) -> anyhow::Result<Header> {
+        let header = self
+            .provider
+            .header(&hash)
+            .map_err(|e| anyhow::anyhow!("Failed to read header {}: {}", hash, e))?
+            .ok_or_else(|| anyhow::anyhow!("Header not found"))?;
+        Ok(convert_header(&header))
+    }
+
+    async fn block_info_by_number(&mut self, number: u64) -> anyhow::Result<BlockInfo> {
+        let header = self
+            .provider
+            .sealed_header(number)
+            .map_err(|e| anyhow::anyhow!("Failed to read header {}: {}", number, e))?
+            .ok_or_else(|| anyhow::anyhow!("Block not found"))?;
+        Ok(BlockInfo {
+            hash: header.hash(),
+            number: header.number,
+            timestamp: header.timestamp,
+            parent_hash: header.parent_hash,
+        })
+    }
+
+    async fn receipts_by_hash(&mut self, hash: B256) -> anyhow::Result<Vec<Receipt>> {
+        let receipts = self
+            .provider
+            .receipts_by_block(hash.into())
+            .map_err(|e| anyhow::anyhow!("Failed to read receipts of {}: {}", hash, e))?
+            .ok_or_else(|| anyhow::anyhow!("Receipts not found"))?;
+        Ok(receipts
+            .into_iter()
+            .map(|r| Receipt {
+                cumulative_gas_used: r.cumulative_gas_used as u128,
+                logs: r.logs,
+                status: alloy::consensus::Eip658Value::Eip658(r.success),
+            })
+            .collect())
+    }
+
+    async fn block_info_and_transactions_by_hash(
+        &mut self,
+        hash: B256,
+    ) -> anyhow::Result<(BlockInfo, Vec<TxEnvelope>)> {
+        let block = self
+            .provider
+            .block_by_hash(hash)
+            .map_err(|e| anyhow::anyhow!("Failed to read block {}: {}", hash, e))?
+            .ok_or_else(|| anyhow::anyhow!("Block not found"))?;
+        let info = BlockInfo {
+            hash,
+            number: block.header.number,
+            timestamp: block.header.timestamp,
+            parent_hash: block.header.parent_hash,
+        };
+        Ok((info, block.body.iter().flat_map(convert_tx).collect()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::{Address, Log};
+    use reth::{
+        primitives::{
+            Header as RethHeader, Receipt as RethReceipt, Receipts, SealedBlock,
+            SealedBlockWithSenders, TransactionSigned,
+        },
+        providers::ExecutionOutcome,
+    };
+    use reth_provider::{test_utils::create_test_provider_factory, BlockWriter};
+
+    /// Returns a provider of a test database holding a block with one transaction at the
+    /// given number, and the hash of the block.
+    fn provider_with_block(
+        number: u64,
+    ) -> (RethDbChainProvider<impl BlockReader + Clone + Send + Sync>, B256) {
+        let factory = create_test_provider_factory();
+        let header = RethHeader { number, timestamp: 12 * number, ..Default::default() };
+        let block = SealedBlockWithSenders {
+            block: SealedBlock {
+                header: header.seal_slow(),
+                body: vec![TransactionSigned::default()],
+                ..Default::default()
+            },
+            senders: vec![Address::ZERO],
+        };
+        let hash = block.hash();
+        let receipt = RethReceipt {
+            success: true,
+            cumulative_gas_used: 21_000,
+            logs: vec![Log::default()],
+            ..Default::default()
+        };
+        let outcome = ExecutionOutcome {
+            receipts: Receipts { receipt_vec: vec![vec![Some(receipt)]] },
+            first_block: number,
+            ..Default::default()
+        };
+
+        let provider = factory.provider_rw().unwrap();
+        provider
+            .append_blocks_with_state(vec![block], outcome, Default::default(), Default::default())
+            .unwrap();
+        provider.commit().unwrap();
+        (RethDbChainProvider::new(factory), hash)
+    }
+
+    #[tokio::test]
+    async fn test_header_by_hash() {
+        let (mut provider, hash) = provider_with_block(1);
+        let header = provider.header_by_hash(hash).await.unwrap();
+        assert_eq!(header.number, 1);
+        assert_eq!(header.timestamp, 12);
+        assert_eq!(header.hash_slow(), hash);
+    }
+
+    #[tokio::test]
+    async fn test_block_info_by_number() {
+        let (mut provider, hash) = provider_with_block(1);
+        let info = provider.block_info_by_number(1).await.unwrap();
+        assert_eq!(info, BlockInfo { hash, number: 1, timestamp: 12, parent_hash: B256::ZERO });
+
+        let (info, _) = provider.block_info_and_transactions_by_hash(hash).await.unwrap();
+        assert_eq!(info, BlockInfo { hash, number: 1, timestamp: 12, parent_hash: B256::ZERO });
+    }
+
+    #[tokio::test]
+    async fn test_receipts_by_hash() {
+        let (mut provider, hash) = provider_with_block(1);
+        let receipts = provider.receipts_by_hash(hash).await.unwrap();
+        assert_eq!(receipts.len(), 1);
+        assert_eq!(receipts[0].cumulative_gas_used, 21_000);
+        assert_eq!(receipts[0].logs, vec![Log::default()]);
+        assert_eq!(receipts[0].status, alloy::consensus::Eip658Value::Eip658(true));
+    }
+
+    #[tokio::test]
+    async fn test_missing_block() {
+        let (mut provider, _) = provider_with_block(1);
+        let missing = B256::repeat_byte(0xaa);
+        assert!(provider.header_by_hash(missing).await.is_err());
+        assert!(provider.block_info_by_number(2).await.is_err());
+        assert!(provider.receipts_by_hash(missing).await.is_err());
+        assert!(provider.block_info_and_transactions_by_hash(missing).await.is_err());
+    }
+}
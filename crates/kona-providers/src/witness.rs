@@ -0,0 +1,206 @@
+//! Preimage witness store
+
+use alloc::{collections::BTreeMap, sync::Arc, vec, vec::Vec};
+use hashbrown::HashMap;
+
+use alloy::primitives::{keccak256, Bytes, B256};
+use alloy_rlp::{Encodable, EMPTY_STRING_CODE};
+use alloy_trie::{
+    root::adjust_index_for_rlp, HashBuilder, Nibbles, ProofRetainer, EMPTY_ROOT_HASH,
+};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+
+/// The type of a preimage key, as defined by the
+/// [preimage oracle spec](https://specs.optimism.io/experimental/fault-proof/index.html#pre-image-key-types).
+///
+/// The type is stored in the first byte of the key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(u8)]
+pub enum PreimageKeyType {
+    /// Local key, specific to the program instance (e.g. boot info).
+    Local = 1,
+    /// Global key, the keccak256 hash of the preimage.
+    Keccak256 = 2,
+    /// Global generic key.
+    GlobalGeneric = 3,
+    /// Global key, the sha256 hash of the preimage.
+    Sha256 = 4,
+    /// Global key of a single blob field element.
+    Blob = 5,
+    /// Global key of a precompile call result.
+    Precompile = 6,
+}
+
+/// Returns the preimage key of the given type for the given hash.
+pub fn preimage_key(hash: B256, key_type: PreimageKeyType) -> B256 {
+    let mut key = hash;
+    key[0] = key_type as u8;
+    key
+}
+
+/// Returns the local preimage key with the given identifier.
+pub fn local_preimage_key(ident: u64) -> B256 {
+    let mut key = B256::ZERO;
+    key[24..].copy_from_slice(&ident.to_be_bytes());
+    preimage_key(key, PreimageKeyType::Local)
+}
+
+/// A serializable bundle of preimages, keyed by their typed preimage key.
+///
+/// The keys follow the same scheme as kona's host, so that the bundle can be
+/// loaded into its key-value store and served to the fault proof program.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WitnessBundle {
+    /// The preimages, by preimage key.
+    pub preimages: BTreeMap<B256, Bytes>,
+}
+
+impl WitnessBundle {
+    /// Serializes the bundle to JSON.
+    pub fn to_json(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string(self)
+    }
+
+    /// Deserializes a bundle from JSON.
+    pub fn from_json(json: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(json)
+    }
+}
+
+/// A shared, in-memory store of preimages recorded while deriving.
+///
+/// Clones share the same underlying store.
+#[derive(Debug, Clone, Default)]
+pub struct WitnessStore(Arc<RwLock<HashMap<B256, Bytes>>>);
+
+impl WitnessStore {
+    /// Inserts a preimage under the given key.
+    pub fn insert(&self, key: B256, value: Bytes) {
+        self.0.write().insert(key, value);
+    }
+
+    /// Inserts a preimage under its keccak256 preimage key and returns the key.
+    pub fn insert_keccak256(&self, value: Bytes) -> B256 {
+        let key = preimage_key(keccak256(&value), PreimageKeyType::Keccak256);
+        self.insert(key, value);
+        key
+    }
+
+    /// Inserts the RLP encoding of the given value under its keccak256 preimage key.
+    pub fn insert_rlp<T: Encodable>(&self, value: &T) -> B256 {
+        self.insert_keccak256(alloy_rlp::encode(value).into())
+    }
+
+    /// Inserts all the nodes of the ordered Merkle-Patricia trie of the given
+    /// (already encoded) items under their keccak256 preimage keys.
+    ///
+    /// This is how the fault proof program retrieves transactions and receipts,
+    /// by walking the trie from the root committed to in the block header.
+    pub fn insert_ordered_trie<T: AsRef<[u8]>>(&self, items: &[T]) {
+        // The hash builder doesn't retain the preimage of the empty root.
+        if items.is_empty() {
+            let key = preimage_key(EMPTY_ROOT_HASH, PreimageKeyType::Keccak256);
+            self.insert(key, Bytes::from(vec![EMPTY_STRING_CODE]));
+            return;
+        }
+
+        let mut hb = ordered_trie(items);
+        hb.root();
+        for node in hb.take_proofs().into_values() {
+            self.insert_keccak256(node);
+        }
+    }
+
+    /// Returns the preimage stored under the given key, if any.
+    pub fn get(&self, key: &B256) -> Option<Bytes> {
+        self.0.read().get(key).cloned()
+    }
+
+    /// Returns the number of recorded preimages.
+    pub fn len(&self) -> usize {
+        self.0.read().len()
+    }
+
+    /// Returns whether no preimage has been recorded.
+    pub fn is_empty(&self) -> bool {
+        self.0.read().is_empty()
+    }
+
+    /// Removes all recorded preimages.
+    pub fn clear(&self) {
+        self.0.write().clear();
+    }
+
+    /// Exports all recorded preimages as a [WitnessBundle].
+    pub fn bundle(&self) -> WitnessBundle {
+        WitnessBundle { preimages: self.0.read().iter().map(|(k, v)| (*k, v.clone())).collect() }
+    }
+}
+
+/// Builds the ordered trie of the given items, retaining the proofs of all leaves
+/// (i.e. every node of the trie).
+fn ordered_trie<T: AsRef<[u8]>>(items: &[T]) -> HashBuilder {
+    let len = items.len();
+    let key = |i: usize| {
+        let mut buf = Vec::new();
+        i.encode(&mut buf);
+        Nibbles::unpack(&buf)
+    };
+
+    let targets = (0..len).map(|i| key(adjust_index_for_rlp(i, len))).collect();
+    let mut hb = HashBuilder::default().with_proof_retainer(ProofRetainer::new(targets));
+    for i in 0..len {
+        let index = adjust_index_for_rlp(i, len);
+        hb.add_leaf(key(index), items[index].as_ref());
+    }
+    hb
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::b256;
+
+    #[test]
+    fn test_preimage_key() {
+        let hash = b256!("ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff");
+        let key = preimage_key(hash, PreimageKeyType::Keccak256);
+        assert_eq!(key[0], 2);
+        assert_eq!(key[1..], hash[1..]);
+    }
+
+    #[test]
+    fn test_local_preimage_key() {
+        let key = local_preimage_key(4);
+        assert_eq!(key, b256!("0100000000000000000000000000000000000000000000000000000000000004"));
+    }
+
+    #[test]
+    fn test_insert_ordered_trie_root_preimage() {
+        let store = WitnessStore::default();
+        let items = vec![vec![0x01u8; 40], vec![0x02u8; 40], vec![0x03u8; 40]];
+        store.insert_ordered_trie(&items);
+
+        // The root node must be retrievable by its hash, as the trie root.
+        let root = ordered_trie(&items).root();
+        assert!(store.get(&preimage_key(root, PreimageKeyType::Keccak256)).is_some());
+    }
+
+    #[test]
+    fn test_insert_empty_trie() {
+        let store = WitnessStore::default();
+        store.insert_ordered_trie::<Vec<u8>>(&[]);
+        let key = preimage_key(EMPTY_ROOT_HASH, PreimageKeyType::Keccak256);
+        assert_eq!(store.get(&key), Some(Bytes::from(vec![EMPTY_STRING_CODE])));
+    }
+
+    #[test]
+    fn test_bundle_json_roundtrip() {
+        let store = WitnessStore::default();
+        store.insert_keccak256(Bytes::from_static(b"hera"));
+        let bundle = store.bundle();
+        let json = bundle.to_json().unwrap();
+        assert_eq!(WitnessBundle::from_json(&json).unwrap(), bundle);
+    }
+}
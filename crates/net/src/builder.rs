@@ -1,23 +1,45 @@
 //! Network Builder Module.
 
-use alloy::primitives::Address;
+use alloy::{primitives::Address, signers::local::PrivateKeySigner};
 use eyre::Result;
-use std::net::SocketAddr;
+use std::{
+    collections::{BTreeSet, HashSet},
+    net::{IpAddr, SocketAddr},
+    path::PathBuf,
+    time::Duration,
+};
 use tokio::sync::watch::channel;
 
+use discv5::enr::{CombinedKey, Enr};
 use libp2p::{
-    gossipsub::Config as GossipConfig, noise::Config as NoiseConfig, tcp::Config as TcpConfig,
-    yamux::Config as YamuxConfig, Multiaddr, SwarmBuilder,
+    gossipsub::Config as GossipConfig, multiaddr::Protocol, noise::Config as NoiseConfig,
+    ping::Config as PingConfig, tcp::Config as TcpConfig, yamux::Config as YamuxConfig, Multiaddr,
+    PeerId, SwarmBuilder,
 };
 use libp2p_identity::Keypair;
 
+#[cfg(feature = "interop")]
+use crate::gossip::interop::InteropHandler;
 use crate::{
     discovery::builder::DiscoveryBuilder,
-    driver::NetworkDriver,
-    gossip::{behaviour::Behaviour, config, driver::GossipDriver, handler::BlockHandler},
+    driver::{ChainGossip, NetworkDriver},
+    gossip::{
+        behaviour::Behaviour,
+        channel::DEFAULT_UNSAFE_BLOCK_CAPACITY,
+        config::{self, GossipParams, MaxGossipSizes},
+        driver::GossipDriver,
+        gate::{ConnectionGate, ConnectionGater},
+        handler::{BlockHandler, Handler, LATEST_BLOCKS_VERSION},
+        scoring::PeerScoring,
+        sequencer::{ForkSchedule, SequencerSigner},
+    },
+    keys::load_or_generate_keypair,
     types::address::NetworkAddress,
 };
 
+/// The default duration a connection without any active stream is kept open.
+pub const DEFAULT_IDLE_CONNECTION_TIMEOUT: Duration = Duration::from_secs(60);
+
 /// Constructs a [NetworkDriver] for Optimism's consensus-layer.
 #[derive(Default)]
 pub struct NetworkDriverBuilder {
@@ -27,16 +49,74 @@ pub struct NetworkDriverBuilder {
     pub unsafe_block_signer: Option<Address>,
     /// The socket address that the service is listening on.
     pub socket: Option<SocketAddr>,
+    /// The other socket addresses the service is listening on, e.g. IPv6 ones.
+    pub additional_sockets: Vec<SocketAddr>,
+    /// The UDP port to listen on for QUIC connections, if any.
+    pub quic_port: Option<u16>,
+    /// The public IP address advertised to peers, if it differs from the listen one.
+    pub advertised_ip: Option<IpAddr>,
+    /// The public port advertised to peers, if it differs from the listen one.
+    pub advertised_port: Option<u16>,
+    /// Whether the public address is detected with identify and AutoNAT.
+    pub nat_traversal: bool,
+    /// Whether the listen ports are mapped on the gateway over UPnP.
+    pub upnp: bool,
     /// The [GossipConfig] constructs the config for `gossipsub`.
     pub gossip_config: Option<GossipConfig>,
+    /// The [GossipParams] the `gossipsub` config is built with, unless it is set.
+    pub gossip_params: Option<GossipParams>,
     /// The [Keypair] for the node.
     pub keypair: Option<Keypair>,
+    /// The file the [Keypair] of the node is loaded from, or stored to on first start.
+    pub keypair_path: Option<PathBuf>,
     /// The [TcpConfig] for the swarm.
     pub tcp_config: Option<TcpConfig>,
     /// The [NoiseConfig] for the swarm.
     pub noise_config: Option<NoiseConfig>,
     /// The [YamuxConfig] for the swarm.
     pub yamux_config: Option<YamuxConfig>,
+    /// Whether peer discovery is disabled.
+    pub discovery_disabled: bool,
+    /// Peers that are always dialed, regardless of discovery.
+    pub static_peers: Vec<Multiaddr>,
+    /// The discovery bootnodes, if the default ones are replaced.
+    pub bootnodes: Option<Vec<Enr<CombinedKey>>>,
+    /// Other chain IDs whose discovered peers are dialed as well.
+    pub additional_chain_ids: Vec<u64>,
+    /// Other chains whose blocks are gossiped as well, with their unsafe block signer.
+    pub additional_chains: Vec<(u64, Address)>,
+    /// The limits and filters of the connections of the swarm, if any.
+    pub connection_gate: Option<ConnectionGate>,
+    /// Whether connections are restricted to the static peers.
+    pub static_peers_only: bool,
+    /// The only peers allowed to connect, if the node runs in allowlist-only mode.
+    pub allowlist: Option<HashSet<PeerId>>,
+    /// The maximum decompressed size of a block on each blocks topic.
+    pub max_gossip_sizes: Option<MaxGossipSizes>,
+    /// The number of received unsafe blocks buffered until they are consumed.
+    pub unsafe_block_capacity: Option<usize>,
+    /// How long a connection without any active stream is kept open.
+    pub idle_connection_timeout: Option<Duration>,
+    /// The [PingConfig] used to keep connections alive and detect dead ones.
+    pub ping_config: Option<PingConfig>,
+    /// The gossipsub peer scoring configuration, if peers are scored.
+    pub peer_scoring: Option<PeerScoring>,
+    /// The score under which peers are banned and the duration of their ban, if any.
+    pub peer_bans: Option<(f64, Duration)>,
+    /// Whether to serve and request unsafe payloads over `payload_by_number`.
+    pub sync: bool,
+    /// The unsafe block signer key of the sequencer, if the node publishes its payloads.
+    pub sequencer_key: Option<PrivateKeySigner>,
+    /// The hardfork schedule deciding the blocks topic of published payloads, and of the
+    /// received ones once set.
+    pub fork_schedule: Option<ForkSchedule>,
+    /// The blocks topic versions subscribed to, if not all of them.
+    pub block_topics: Option<BTreeSet<u8>>,
+    /// Whether to unsubscribe from the blocks topics of past hardforks.
+    pub tip_only: bool,
+    /// Whether to subscribe to the interop executing messages topic.
+    #[cfg(feature = "interop")]
+    pub interop: bool,
 }
 
 impl NetworkDriverBuilder {
@@ -63,12 +143,72 @@ impl NetworkDriverBuilder {
         self
     }
 
+    /// Listens for TCP connections on the given socket address too, e.g. an IPv6 address
+    /// next to the IPv4 one of [NetworkDriverBuilder::with_socket].
+    ///
+    /// Discovery listens on the UDP port of the first IPv6 address too, and advertises
+    /// its TCP port in the local ENR.
+    pub fn with_additional_socket(&mut self, socket: SocketAddr) -> &mut Self {
+        self.additional_sockets.push(socket);
+        self
+    }
+
+    /// Advertises the given public IP address in the local ENR instead of the one
+    /// reported by peers, e.g. for a node behind a NAT.
+    pub fn with_advertised_ip(&mut self, ip: IpAddr) -> &mut Self {
+        self.advertised_ip = Some(ip);
+        self
+    }
+
+    /// Advertises the given public TCP and UDP port in the local ENR instead of the
+    /// listen ones, e.g. for a node behind a NAT forwarding it.
+    pub fn with_advertised_port(&mut self, port: u16) -> &mut Self {
+        self.advertised_port = Some(port);
+        self
+    }
+
+    /// Detects the public address of the node with identify and AutoNAT, and advertises
+    /// it in the local ENR once confirmed reachable.
+    ///
+    /// Conflicts with [Self::with_advertised_ip], which fixes the advertised address.
+    pub fn with_nat_traversal(&mut self) -> &mut Self {
+        self.nat_traversal = true;
+        self
+    }
+
+    /// Maps the listen ports on the gateway over UPnP, so that nodes behind a home router
+    /// are dialable without any manual port forwarding. Implies
+    /// [Self::with_nat_traversal].
+    pub fn with_upnp(&mut self) -> &mut Self {
+        self.nat_traversal = true;
+        self.upnp = true;
+        self
+    }
+
+    /// Listens for QUIC connections on the given UDP port too, on the IP addresses of
+    /// [NetworkDriverBuilder::with_socket] and [NetworkDriverBuilder::with_additional_socket].
+    /// The port is advertised in the local ENR.
+    ///
+    /// Peer discovery uses the UDP port of the socket, so it must differ from this one.
+    pub fn with_quic_port(&mut self, port: u16) -> &mut Self {
+        self.quic_port = Some(port);
+        self
+    }
+
     /// Specifies the keypair for the node.
     pub fn with_keypair(&mut self, keypair: Keypair) -> &mut Self {
         self.keypair = Some(keypair);
         self
     }
 
+    /// Loads the keypair of the node from the given file, or generates one and
+    /// stores it there on first start, so that the peer ID and ENR of the node
+    /// are stable across restarts. Ignored if a keypair is specified.
+    pub fn with_keypair_path(&mut self, path: impl Into<PathBuf>) -> &mut Self {
+        self.keypair_path = Some(path.into());
+        self
+    }
+
     /// Specifies the [TcpConfig] for the swarm.
     pub fn with_tcp_config(&mut self, tcp_config: TcpConfig) -> &mut Self {
         self.tcp_config = Some(tcp_config);
@@ -87,10 +227,205 @@ impl NetworkDriverBuilder {
         self
     }
 
+    /// Disables the `discv5` peer discovery service.
+    ///
+    /// When discovery is disabled the node only connects to the peers
+    /// specified via [NetworkDriverBuilder::with_static_peers].
+    pub fn with_discovery_disabled(&mut self) -> &mut Self {
+        self.discovery_disabled = true;
+        self
+    }
+
+    /// Specifies the static peers that are always dialed on startup.
+    ///
+    /// While discovery is enabled, they are also redialed on every lookup round
+    /// if they disconnected.
+    pub fn with_static_peers(&mut self, peers: Vec<Multiaddr>) -> &mut Self {
+        self.static_peers = peers;
+        self
+    }
+
+    /// Specifies the discovery bootnodes, replacing the default OP Mainnet and Base
+    /// bootnodes, e.g. for devnets and private chains.
+    pub fn with_bootnodes(&mut self, bootnodes: Vec<Enr<CombinedKey>>) -> &mut Self {
+        self.bootnodes = Some(bootnodes);
+        self
+    }
+
+    /// Enforces the given limits and filters on the connections of the swarm: maximum
+    /// peers, maximum connections per IP, banned subnets and denied or allowed peers.
+    pub fn with_connection_gate(&mut self, gate: ConnectionGate) -> &mut Self {
+        self.connection_gate = Some(gate);
+        self
+    }
+
+    /// Dials the discovered peers of the given chains as well. Discovered nodes whose
+    /// `opstack` ENR entry is for any other chain, or missing, are never dialed.
+    pub fn with_additional_chain_ids(&mut self, chain_ids: Vec<u64>) -> &mut Self {
+        self.additional_chain_ids = chain_ids;
+        self
+    }
+
+    /// Gossips the blocks of the given chain as well, signed by the given unsafe block
+    /// signer, e.g. to run the nodes of several chains in one process.
+    ///
+    /// The peers of the chain are discovered along with those of the network chain, and
+    /// its blocks are delivered through [NetworkDriver::additional_chains]. The other
+    /// gossip settings, e.g. the blocks topics and the tip-only mode, only apply to the
+    /// network chain.
+    pub fn with_additional_chain(
+        &mut self,
+        chain_id: u64,
+        unsafe_block_signer: Address,
+    ) -> &mut Self {
+        self.additional_chains.push((chain_id, unsafe_block_signer));
+        self
+    }
+
+    /// Restricts the node to the peers specified via [NetworkDriverBuilder::with_static_peers].
+    ///
+    /// This disables peer discovery, and refuses the connections of any other peer (see
+    /// [NetworkDriverBuilder::with_allowlist]). Every static peer address must therefore
+    /// include its peer ID (`/p2p/<peer id>`).
+    pub fn with_static_peers_only(&mut self) -> &mut Self {
+        self.static_peers_only = true;
+        self.discovery_disabled = true;
+        self
+    }
+
+    /// Restricts the node to a private mesh of the given peers.
+    ///
+    /// Connections to and from any other peer than these and the static peers are
+    /// refused by the connection gate before they are established, and gossip
+    /// subscriptions to topics the node doesn't handle are ignored. Can be called
+    /// several times to extend the allowlist.
+    pub fn with_allowlist(&mut self, peers: impl IntoIterator<Item = PeerId>) -> &mut Self {
+        self.allowlist.get_or_insert_with(HashSet::new).extend(peers);
+        self
+    }
+
+    /// Specifies the maximum decompressed size of a gossiped block on each blocks topic.
+    ///
+    /// Blocks exceeding the limit of their topic are rejected before being decoded.
+    /// If not set, [config::DEFAULT_MAX_GOSSIP_SIZES] is used.
+    pub fn with_max_gossip_sizes(&mut self, sizes: MaxGossipSizes) -> &mut Self {
+        self.max_gossip_sizes = Some(sizes);
+        self
+    }
+
+    /// Specifies the number of received unsafe blocks buffered in
+    /// [NetworkDriver::unsafe_block_recv] until they are consumed. Once full, the oldest
+    /// blocks are dropped.
+    /// If not set, [DEFAULT_UNSAFE_BLOCK_CAPACITY] is used.
+    pub fn with_unsafe_block_capacity(&mut self, capacity: usize) -> &mut Self {
+        self.unsafe_block_capacity = Some(capacity);
+        self
+    }
+
+    /// Specifies how long a connection without any active stream is kept open.
+    ///
+    /// Raising it keeps quiet connections to long-lived peers (e.g. static peers on
+    /// low-traffic networks) from being closed between messages.
+    /// If not set, [DEFAULT_IDLE_CONNECTION_TIMEOUT] is used.
+    pub fn with_idle_connection_timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.idle_connection_timeout = Some(timeout);
+        self
+    }
+
+    /// Specifies the keep-alive pings sent on every connection.
+    ///
+    /// A ping is sent every `interval`, and the connection is closed if it isn't
+    /// answered within `timeout`, so dead connections are reaped promptly.
+    pub fn with_keep_alive(&mut self, interval: Duration, timeout: Duration) -> &mut Self {
+        self.ping_config = Some(PingConfig::new().with_interval(interval).with_timeout(timeout));
+        self
+    }
+
+    /// Enables gossipsub peer scoring of the blocks topics with the given [PeerScoring].
+    ///
+    /// The [GossipParams::topic_scores] overrides are applied on top of it.
+    pub fn with_peer_scoring(&mut self, scoring: PeerScoring) -> &mut Self {
+        self.peer_scoring = Some(scoring);
+        self
+    }
+
+    /// Bans peers for `duration` once their score drops below `threshold`, e.g. after
+    /// flooding invalid unsafe payloads. Requires [Self::with_peer_scoring].
+    pub fn with_peer_bans(&mut self, threshold: f64, duration: Duration) -> &mut Self {
+        self.peer_bans = Some((threshold, duration));
+        self
+    }
+
+    /// Sets the sequencer key signing the payloads published with
+    /// [NetworkDriver::publish_payload]. It must match the unsafe block signer.
+    pub fn with_sequencer_key(&mut self, key: PrivateKeySigner) -> &mut Self {
+        self.sequencer_key = Some(key);
+        self
+    }
+
+    /// Sets the [ForkSchedule] deciding the blocks topic of the payloads published
+    /// with [NetworkDriver::publish_payload], and the obsolete topics in
+    /// [Self::with_tip_only] mode. Defaults to no hardfork being active.
+    ///
+    /// Once set, received blocks are also rejected if they are not on the blocks topic of
+    /// the hardfork active at their timestamp, e.g. pre-Isthmus blocks on `blocks/v4`.
+    pub fn with_fork_schedule(&mut self, forks: ForkSchedule) -> &mut Self {
+        self.fork_schedule = Some(forks);
+        self
+    }
+
+    /// Only subscribes to the given blocks topic versions (`0` for v1, `1` for v2, ...),
+    /// e.g. only to v3 once Ecotone is active. Payloads can still be published on the
+    /// other topics.
+    pub fn with_block_topics(&mut self, versions: impl IntoIterator<Item = u8>) -> &mut Self {
+        self.block_topics = Some(versions.into_iter().collect());
+        self
+    }
+
+    /// Unsubscribes from the blocks topics of the hardforks superseded according to the
+    /// [ForkSchedule] and the wall clock, once their last valid blocks are too old to be
+    /// gossiped, saving the bandwidth and validation work of the obsolete topics.
+    pub fn with_tip_only(&mut self) -> &mut Self {
+        self.tip_only = true;
+        self
+    }
+
+    /// Enables the `payload_by_number` request/response protocol.
+    ///
+    /// Recent valid unsafe payloads are served to peers, and missed payloads can be
+    /// requested from peers with the [crate::sync::client::SyncClient] of the
+    /// [crate::driver::NetworkHandle].
+    pub fn with_sync(&mut self) -> &mut Self {
+        self.sync = true;
+        self
+    }
+
+    /// Subscribes to the interop executing messages gossip topic.
+    ///
+    /// Received messages are forwarded to [NetworkDriver::interop_message_recv],
+    /// and can be published with [GossipDriver::publish_executing_messages].
+    #[cfg(feature = "interop")]
+    pub fn with_interop(&mut self) -> &mut Self {
+        self.interop = true;
+        self
+    }
+
+    /// Specifies the mesh, heartbeat, message size and message ID parameters the
+    /// `gossipsub` configuration is built with, and the scoring parameters of each
+    /// blocks topic.
+    ///
+    /// If not set, op-node's [GossipParams::default] are used, which the other nodes of
+    /// the network are expected to run with. Only the topic scores are used if a
+    /// [GossipConfig] is set with [NetworkDriverBuilder::with_gossip_config].
+    pub fn with_gossip_params(&mut self, params: GossipParams) -> &mut Self {
+        self.gossip_params = Some(params);
+        self
+    }
+
     /// Specifies the [GossipConfig] for the `gossipsub` configuration.
     ///
-    /// If not set, the [NetworkDriverBuilder] will use the default gossipsub
-    /// configuration defined in [config::default_config]. These defaults can
+    /// If not set, the [NetworkDriverBuilder] will use the gossipsub configuration of
+    /// the [GossipParams], see [NetworkDriverBuilder::with_gossip_params]. These defaults can
     /// be extended by using the [config::default_config_builder] method to
     /// build a custom [GossipConfig].
     ///
@@ -149,10 +484,15 @@ impl NetworkDriverBuilder {
     ///    .unwrap();
     /// ```
     pub fn build(&mut self) -> Result<NetworkDriver> {
-        // Build the config for gossipsub.
+        // Build the config for gossipsub, large enough to transmit the biggest allowed block.
+        let max_gossip_sizes = self.max_gossip_sizes.take().unwrap_or_default();
+        let mut params = self.gossip_params.take().unwrap_or_default();
         let config = match self.gossip_config.take() {
             Some(cfg) => cfg,
-            None => config::default_config()?,
+            None => {
+                params.max_message_size = params.max_message_size.max(max_gossip_sizes.max());
+                params.build()?
+            }
         };
         let unsafe_block_signer =
             self.unsafe_block_signer.ok_or_else(|| eyre::eyre!("unsafe block signer not set"))?;
@@ -160,14 +500,133 @@ impl NetworkDriverBuilder {
 
         // Create the block handler.
         let (unsafe_block_signer_sender, unsafe_block_signer_recv) = channel(unsafe_block_signer);
-        let (handler, unsafe_block_recv) = BlockHandler::new(chain_id, unsafe_block_signer_recv);
+        let (handler, unsafe_block_recv) = BlockHandler::with_capacity(
+            chain_id,
+            unsafe_block_signer_recv,
+            self.unsafe_block_capacity.unwrap_or(DEFAULT_UNSAFE_BLOCK_CAPACITY),
+        );
+        let mut handler = handler.with_max_sizes(max_gossip_sizes);
+        if let Some(forks) = self.fork_schedule {
+            handler = handler.with_fork_schedule(forks);
+        }
+
+        // Create the block handlers of the additional chains.
+        let mut additional_chains: Vec<ChainGossip> = Vec::new();
+        let mut additional_handlers = Vec::new();
+        for (id, signer) in std::mem::take(&mut self.additional_chains) {
+            if id == chain_id || additional_chains.iter().any(|chain| chain.chain_id == id) {
+                eyre::bail!("duplicate chain ID: {}", id);
+            }
+            let (signer_sender, signer_recv) = channel(signer);
+            let (handler, recv) = BlockHandler::with_capacity(
+                id,
+                signer_recv,
+                self.unsafe_block_capacity.unwrap_or(DEFAULT_UNSAFE_BLOCK_CAPACITY),
+            );
+            additional_handlers.push(handler.with_max_sizes(max_gossip_sizes));
+            additional_chains.push(ChainGossip {
+                chain_id: id,
+                unsafe_block_recv: recv,
+                unsafe_block_signer_sender: signer_sender,
+            });
+        }
+
+        // Create the interop handler, if enabled.
+        #[cfg(feature = "interop")]
+        let (interop, interop_message_recv) = if self.interop {
+            let (interop, recv) = InteropHandler::new(chain_id);
+            (Some(interop), Some(recv))
+        } else {
+            (None, None)
+        };
 
         // Construct the gossipsub behaviour.
-        let behaviour = Behaviour::new(config, &[Box::new(handler.clone())])?;
+        let mut handlers: Vec<Box<dyn Handler>> = vec![Box::new(handler.clone())];
+        handlers
+            .extend(additional_handlers.iter().map(|h| Box::new(h.clone()) as Box<dyn Handler>));
+        #[cfg(feature = "interop")]
+        handlers.extend(interop.clone().map(|i| Box::new(i) as Box<dyn Handler>));
+        // The static peers are always allowed by the connection gate.
+        let static_peers = if self.static_peers_only {
+            self.static_peers.iter().map(peer_id).collect::<Result<Vec<_>>>()?
+        } else {
+            self.static_peers.iter().filter_map(|addr| peer_id(addr).ok()).collect()
+        };
+        let allowlist = self.allowlist.take();
+        let restricted = allowlist.is_some() || self.static_peers_only;
+        let mut behaviour = if restricted {
+            Behaviour::restricted(config, &handlers)?
+        } else {
+            Behaviour::new(config, &handlers)?
+        };
+        if let Some(versions) = self.block_topics.take() {
+            if let Some(version) = versions.iter().find(|v| handler.topic_by_version(**v).is_none())
+            {
+                eyre::bail!("unknown blocks topic version: {}", version);
+            }
+            for version in (0..=LATEST_BLOCKS_VERSION).filter(|v| !versions.contains(v)) {
+                if let Some(topic) = handler.topic_by_version(version) {
+                    behaviour.unsubscribe(topic)?;
+                }
+            }
+        }
+        match self.peer_scoring.take() {
+            Some(scoring) => {
+                let topics = additional_handlers.iter().flat_map(|h| h.topics());
+                behaviour
+                    .enable_peer_scoring(&scoring, handler.topics().into_iter().chain(topics))?
+            }
+            None if self.peer_bans.is_some() => eyre::bail!("peer bans require peer scoring"),
+            None if !params.topic_scores.is_empty() => {
+                eyre::bail!("topic scores require peer scoring")
+            }
+            None => {}
+        }
+        let topic_params = std::mem::take(&mut params.topic_scores)
+            .into_iter()
+            .map(|(version, params)| {
+                let topic = handler
+                    .topic_by_version(version)
+                    .ok_or_else(|| eyre::eyre!("unknown blocks topic version: {}", version))?;
+                Ok((topic.clone(), params))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        behaviour.set_topic_params(topic_params)?;
+        if let Some(ping_config) = self.ping_config.take() {
+            behaviour.set_ping_config(ping_config);
+        }
+        if self.sync {
+            behaviour.enable_sync(chain_id);
+        }
+        let gate = match self.connection_gate.take() {
+            Some(gate) => Some(gate),
+            None if restricted || !static_peers.is_empty() => Some(ConnectionGate::new()),
+            None => None,
+        };
+        if let Some(gate) = gate {
+            let gate = gate
+                .with_allowed_peers(static_peers)
+                .with_allowed_peers(allowlist.unwrap_or_default());
+            let gate = if restricted { gate.with_allowlist_only() } else { gate };
+            behaviour.set_connection_gate(ConnectionGater::new(gate));
+        }
 
         // Build the swarm.
         let noise_config = self.noise_config.take();
-        let keypair = self.keypair.take().unwrap_or(Keypair::generate_secp256k1());
+        let keypair = match (self.keypair.take(), self.keypair_path.take()) {
+            (Some(keypair), _) => keypair,
+            (None, Some(path)) => load_or_generate_keypair(&path)?,
+            (None, None) => Keypair::generate_secp256k1(),
+        };
+        let discovery_keypair = keypair.clone();
+        if self.nat_traversal {
+            if self.advertised_ip.is_some() {
+                eyre::bail!("NAT traversal conflicts with the advertised IP address");
+            }
+            behaviour.enable_nat_traversal(keypair.public(), self.upnp);
+        }
+        let idle_connection_timeout =
+            self.idle_connection_timeout.take().unwrap_or(DEFAULT_IDLE_CONNECTION_TIMEOUT);
         let swarm = SwarmBuilder::with_existing_identity(keypair)
             .with_tokio()
             .with_tcp(
@@ -178,25 +637,127 @@ impl NetworkDriverBuilder {
                 },
                 || self.yamux_config.take().unwrap_or_default(),
             )?
+            // QUIC peers can be dialed even if the node doesn't listen on QUIC.
+            .with_quic()
             .with_behaviour(|_| behaviour)?
+            .with_swarm_config(|cfg| cfg.with_idle_connection_timeout(idle_connection_timeout))
             .build();
         let addr = self.socket.take().ok_or_else(|| eyre::eyre!("socket address not set"))?;
         let addr = NetworkAddress::try_from(addr)?;
         let swarm_addr = Multiaddr::from(addr);
-        let gossip = GossipDriver::new(swarm, swarm_addr, handler);
+        let additional_sockets = std::mem::take(&mut self.additional_sockets);
+        let mut gossip = GossipDriver::new(swarm, swarm_addr, handler)
+            .with_additional_addrs(
+                additional_sockets
+                    .iter()
+                    .map(|socket| tcp_addr(socket.ip(), socket.port()))
+                    .collect(),
+            )
+            .with_additional_handlers(additional_handlers);
+        if let Some(port) = self.quic_port {
+            if port == addr.port && !self.discovery_disabled {
+                eyre::bail!("QUIC port {} is already used by peer discovery", port);
+            }
+            gossip = gossip.with_quic_addr(quic_addr(addr.ip.into(), port));
+            for socket in &additional_sockets {
+                gossip = gossip.with_additional_addr(quic_addr(socket.ip(), port));
+            }
+        }
+        if let Some((threshold, duration)) = self.peer_bans.take() {
+            gossip = gossip.with_peer_bans(threshold, duration);
+        }
+        if self.tip_only {
+            gossip = gossip.with_tip_only(self.fork_schedule.unwrap_or_default());
+        }
+        if let Some(key) = self.sequencer_key.take() {
+            if key.address() != unsafe_block_signer {
+                eyre::bail!("sequencer key does not match the unsafe block signer");
+            }
+            let forks = self.fork_schedule.unwrap_or_default();
+            gossip = gossip.with_sequencer(SequencerSigner::new(key, chain_id, forks));
+        }
+        #[cfg(feature = "interop")]
+        let gossip = match interop {
+            Some(interop) => gossip.with_interop(interop),
+            None => gossip,
+        };
 
-        // Build the discovery service
-        let discovery =
-            DiscoveryBuilder::new().with_address(addr).with_chain_id(chain_id).build()?;
+        // Build the discovery service, unless it is disabled.
+        let static_peers = std::mem::take(&mut self.static_peers);
+        let discovery = if self.discovery_disabled {
+            None
+        } else {
+            let mut chain_ids = std::mem::take(&mut self.additional_chain_ids);
+            chain_ids.extend(additional_chains.iter().map(|chain| chain.chain_id));
+            let mut discovery = DiscoveryBuilder::new()
+                .with_address(addr)
+                .with_chain_id(chain_id)
+                .with_additional_chain_ids(chain_ids)
+                .with_keypair(discovery_keypair)
+                .with_static_peers(static_peers.clone());
+            if let Some(port) = self.quic_port {
+                discovery = discovery.with_quic_port(port);
+            }
+            let ipv6 = additional_sockets.iter().find_map(|socket| match socket {
+                SocketAddr::V6(socket) => Some(*socket),
+                SocketAddr::V4(_) => None,
+            });
+            if let Some(ipv6) = ipv6 {
+                if self.quic_port == Some(ipv6.port()) {
+                    eyre::bail!("QUIC port {} is already used by peer discovery", ipv6.port());
+                }
+                discovery = discovery.with_ipv6_address(ipv6);
+            }
+            if let Some(ip) = self.advertised_ip {
+                discovery = discovery.with_advertised_ip(ip);
+            }
+            if let Some(port) = self.advertised_port {
+                discovery = discovery.with_advertised_port(port);
+            }
+            if let Some(bootnodes) = self.bootnodes.take() {
+                discovery = discovery.with_bootnodes(bootnodes);
+            }
+            Some(discovery.build()?)
+        };
 
-        Ok(NetworkDriver { unsafe_block_recv, unsafe_block_signer_sender, gossip, discovery })
+        Ok(NetworkDriver {
+            unsafe_block_recv,
+            unsafe_block_signer_sender,
+            gossip,
+            discovery,
+            static_peers,
+            additional_chains,
+            #[cfg(feature = "interop")]
+            interop_message_recv,
+        })
     }
 }
 
+/// Returns the TCP [Multiaddr] of the given IP address and port.
+fn tcp_addr(ip: IpAddr, port: u16) -> Multiaddr {
+    Multiaddr::from(ip).with(Protocol::Tcp(port))
+}
+
+/// Returns the QUIC [Multiaddr] of the given IP address and UDP port.
+fn quic_addr(ip: IpAddr, port: u16) -> Multiaddr {
+    Multiaddr::from(ip).with(Protocol::Udp(port)).with(Protocol::QuicV1)
+}
+
+/// Extracts the [PeerId] from the `/p2p/` component of the given [Multiaddr].
+fn peer_id(addr: &Multiaddr) -> Result<PeerId> {
+    addr.iter()
+        .find_map(|protocol| match protocol {
+            Protocol::P2p(id) => Some(id),
+            _ => None,
+        })
+        .ok_or_else(|| eyre::eyre!("static peer {} has no peer ID", addr))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use libp2p::gossipsub::IdentTopic;
+    use crate::types::enr::ENR_QUIC_KEY;
+    use libp2p::gossipsub::{IdentTopic, TopicScoreParams};
     use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 
     #[test]
@@ -245,7 +806,7 @@ mod tests {
 
         // Driver Assertions
         assert_eq!(driver.gossip.addr, signer_multiaddr);
-        assert_eq!(driver.discovery.chain_id, id);
+        assert_eq!(driver.discovery.as_ref().unwrap().chain_id, id);
 
         // Block Handler Assertions
         assert_eq!(driver.gossip.handler.chain_id, id);
@@ -257,6 +818,458 @@ mod tests {
         assert_eq!(driver.gossip.handler.blocks_v3_topic.hash(), v3.hash());
     }
 
+    #[test]
+    fn test_build_gossip_params() {
+        let socket = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 9099);
+        let params = GossipParams { mesh_d: 4, mesh_dlo: 2, mesh_dhi: 6, ..Default::default() };
+        let driver = NetworkDriverBuilder::new()
+            .with_unsafe_block_signer(Address::random())
+            .with_chain_id(10)
+            .with_socket(socket)
+            .with_gossip_params(params.clone())
+            .with_max_gossip_sizes(MaxGossipSizes::uniform(20 << 20))
+            .build();
+        assert!(driver.is_ok());
+
+        let params = GossipParams { mesh_dhi: 2, ..params };
+        let driver = NetworkDriverBuilder::new()
+            .with_unsafe_block_signer(Address::random())
+            .with_chain_id(10)
+            .with_socket(socket)
+            .with_gossip_params(params)
+            .build();
+        assert!(driver.is_err());
+    }
+
+    #[test]
+    fn test_build_topic_scores() {
+        let socket = SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 9099);
+        let score = TopicScoreParams { topic_weight: 0.2, ..Default::default() };
+        let params =
+            GossipParams { topic_scores: [(2, score)].into_iter().collect(), ..Default::default() };
+        let mut builder = NetworkDriverBuilder::new();
+        builder
+            .with_unsafe_block_signer(Address::random())
+            .with_chain_id(10)
+            .with_socket(socket)
+            .with_gossip_params(params.clone());
+        let Err(err) = builder.build() else {
+            panic!("expected error when topic scores are set without peer scoring");
+        };
+        assert_eq!(err.to_string(), "topic scores require peer scoring");
+
+        let driver = builder
+            .with_socket(socket)
+            .with_gossip_params(params)
+            .with_peer_scoring(PeerScoring::default())
+            .build()
+            .unwrap();
+        let v3 = driver.gossip.handler.blocks_v3_topic.clone();
+        let v3_params = driver.gossip.swarm.behaviour().gossipsub.get_topic_params(&v3).unwrap();
+        assert_eq!(v3_params.topic_weight, 0.2);
+    }
+
+    #[test]
+    fn test_build_discovery_disabled() {
+        let socket = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 9099);
+        let peer = Multiaddr::from(NetworkAddress { ip: Ipv4Addr::new(10, 0, 0, 1), port: 9222 });
+        let driver = NetworkDriverBuilder::new()
+            .with_unsafe_block_signer(Address::random())
+            .with_chain_id(10)
+            .with_socket(socket)
+            .with_discovery_disabled()
+            .with_static_peers(vec![peer.clone()])
+            .build()
+            .unwrap();
+
+        assert!(driver.discovery.is_none());
+        assert_eq!(driver.static_peers, vec![peer]);
+    }
+
+    #[test]
+    fn test_build_discovery_bootnodes() {
+        let socket = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 9099);
+        let peer = Multiaddr::from(NetworkAddress { ip: Ipv4Addr::new(10, 0, 0, 1), port: 9222 });
+        let bootnode = crate::discovery::bootnodes::BOOTNODES[0].clone();
+        let driver = NetworkDriverBuilder::new()
+            .with_unsafe_block_signer(Address::random())
+            .with_chain_id(10)
+            .with_socket(socket)
+            .with_bootnodes(vec![bootnode.clone()])
+            .with_static_peers(vec![peer.clone()])
+            .with_additional_chain_ids(vec![8453])
+            .build()
+            .unwrap();
+
+        let discovery = driver.discovery.unwrap();
+        assert_eq!(discovery.bootnodes, vec![bootnode]);
+        assert_eq!(discovery.additional_chain_ids, vec![8453]);
+        assert_eq!(discovery.static_peers, vec![peer.clone()]);
+        assert_eq!(driver.static_peers, vec![peer]);
+    }
+
+    #[test]
+    fn test_build_additional_chains() {
+        let socket = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 9099);
+        let signer = Address::random();
+        let driver = NetworkDriverBuilder::new()
+            .with_unsafe_block_signer(Address::random())
+            .with_chain_id(10)
+            .with_socket(socket)
+            .with_additional_chain_ids(vec![7777777])
+            .with_additional_chain(8453, signer)
+            .build()
+            .unwrap();
+
+        assert_eq!(driver.additional_chains.len(), 1);
+        let chain = &driver.additional_chains[0];
+        assert_eq!(chain.chain_id, 8453);
+        assert_eq!(*chain.unsafe_block_signer_sender.borrow(), signer);
+        let handler = &driver.gossip.additional_handlers[0];
+        assert_eq!(handler.chain_id, 8453);
+        let topics =
+            driver.gossip.swarm.behaviour().gossipsub.topics().cloned().collect::<Vec<_>>();
+        assert!(handler.topics().iter().all(|topic| topics.contains(topic)));
+        assert_eq!(driver.discovery.unwrap().additional_chain_ids, vec![7777777, 8453]);
+
+        let Err(err) = NetworkDriverBuilder::new()
+            .with_unsafe_block_signer(Address::random())
+            .with_chain_id(10)
+            .with_socket(socket)
+            .with_additional_chain(10, signer)
+            .build()
+        else {
+            panic!("expected a duplicate chain ID error");
+        };
+        assert_eq!(err.to_string(), "duplicate chain ID: 10");
+    }
+
+    #[test]
+    fn test_build_quic() {
+        let socket = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 9099);
+        let driver = NetworkDriverBuilder::new()
+            .with_unsafe_block_signer(Address::random())
+            .with_chain_id(10)
+            .with_socket(socket)
+            .with_quic_port(9100)
+            .build()
+            .unwrap();
+        assert_eq!(
+            driver.gossip.quic_addr,
+            Some("/ip4/127.0.0.1/udp/9100/quic-v1".parse().unwrap())
+        );
+        let enr = driver.discovery.unwrap().disc.local_enr();
+        assert_eq!(enr.tcp4(), Some(9099));
+        assert_eq!(enr.get_decodable::<u16>(ENR_QUIC_KEY).unwrap().unwrap(), 9100);
+
+        let err = NetworkDriverBuilder::new()
+            .with_unsafe_block_signer(Address::random())
+            .with_chain_id(10)
+            .with_socket(socket)
+            .with_quic_port(9099)
+            .build()
+            .err()
+            .unwrap();
+        assert_eq!(err.to_string(), "QUIC port 9099 is already used by peer discovery");
+    }
+
+    #[test]
+    fn test_build_additional_sockets() {
+        let socket = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 9099);
+        let ipv6: SocketAddr = "[::1]:9199".parse().unwrap();
+        let driver = NetworkDriverBuilder::new()
+            .with_unsafe_block_signer(Address::random())
+            .with_chain_id(10)
+            .with_socket(socket)
+            .with_additional_socket(ipv6)
+            .with_quic_port(9100)
+            .with_advertised_ip(IpAddr::V4(Ipv4Addr::new(203, 0, 113, 1)))
+            .with_advertised_port(30303)
+            .build()
+            .unwrap();
+        assert_eq!(
+            driver.gossip.additional_addrs,
+            vec![
+                "/ip6/::1/tcp/9199".parse::<Multiaddr>().unwrap(),
+                "/ip6/::1/udp/9100/quic-v1".parse().unwrap(),
+            ]
+        );
+        assert_eq!(driver.node_info().listen_addrs.len(), 4);
+        let enr = driver.discovery.unwrap().disc.local_enr();
+        assert_eq!(enr.ip4(), Some(Ipv4Addr::new(203, 0, 113, 1)));
+        assert_eq!(enr.tcp4(), Some(30303));
+        assert_eq!(enr.udp4(), Some(30303));
+        assert_eq!(enr.tcp6(), Some(30303));
+    }
+
+    #[test]
+    fn test_build_block_topics() {
+        let socket = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 9099);
+        let driver = NetworkDriverBuilder::new()
+            .with_unsafe_block_signer(Address::random())
+            .with_chain_id(10)
+            .with_socket(socket)
+            .with_discovery_disabled()
+            .with_block_topics([2])
+            .build()
+            .unwrap();
+        let topics =
+            driver.gossip.swarm.behaviour().gossipsub.topics().cloned().collect::<Vec<_>>();
+        assert_eq!(topics, vec![IdentTopic::new("/optimism/10/2/blocks").hash()]);
+
+        let err = NetworkDriverBuilder::new()
+            .with_unsafe_block_signer(Address::random())
+            .with_chain_id(10)
+            .with_socket(socket)
+            .with_discovery_disabled()
+            .with_block_topics([4])
+            .build()
+            .err()
+            .unwrap();
+        assert_eq!(err.to_string(), "unknown blocks topic version: 4");
+    }
+
+    #[test]
+    fn test_build_tip_only() {
+        let socket = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 9099);
+        let mut driver = NetworkDriverBuilder::new()
+            .with_unsafe_block_signer(Address::random())
+            .with_chain_id(10)
+            .with_socket(socket)
+            .with_discovery_disabled()
+            .with_fork_schedule(ForkSchedule {
+                canyon_time: Some(0),
+                ecotone_time: Some(100),
+                isthmus_time: Some(300),
+            })
+            .with_tip_only()
+            .build()
+            .unwrap();
+        assert!(driver.node_info().features.contains(&"tip-only".to_string()));
+        let subscribed = |driver: &NetworkDriver| {
+            let mut topics =
+                driver.gossip.swarm.behaviour().gossipsub.topics().cloned().collect::<Vec<_>>();
+            topics.sort();
+            topics
+        };
+
+        // The last pre-Ecotone blocks stay valid on v2 for a minute after the activation.
+        driver.gossip.unsubscribe_obsolete_topics(130);
+        assert_eq!(
+            subscribed(&driver),
+            vec![
+                IdentTopic::new("/optimism/10/1/blocks").hash(),
+                IdentTopic::new("/optimism/10/2/blocks").hash(),
+                IdentTopic::new("/optimism/10/3/blocks").hash(),
+            ]
+        );
+        driver.gossip.unsubscribe_obsolete_topics(200);
+        assert_eq!(
+            subscribed(&driver),
+            vec![
+                IdentTopic::new("/optimism/10/2/blocks").hash(),
+                IdentTopic::new("/optimism/10/3/blocks").hash(),
+            ]
+        );
+        driver.gossip.unsubscribe_obsolete_topics(400);
+        assert_eq!(subscribed(&driver), vec![IdentTopic::new("/optimism/10/3/blocks").hash()]);
+    }
+
+    #[test]
+    fn test_build_nat_traversal() {
+        let socket = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 9099);
+        let driver = NetworkDriverBuilder::new()
+            .with_unsafe_block_signer(Address::random())
+            .with_chain_id(10)
+            .with_socket(socket)
+            .with_nat_traversal()
+            .build()
+            .unwrap();
+        let behaviour = driver.gossip.swarm.behaviour();
+        assert!(behaviour.identify.is_enabled());
+        assert!(behaviour.autonat.is_enabled());
+        assert!(!behaviour.upnp.is_enabled());
+
+        let err = NetworkDriverBuilder::new()
+            .with_unsafe_block_signer(Address::random())
+            .with_chain_id(10)
+            .with_socket(socket)
+            .with_nat_traversal()
+            .with_advertised_ip(IpAddr::V4(Ipv4Addr::new(203, 0, 113, 1)))
+            .build()
+            .err()
+            .unwrap();
+        assert_eq!(err.to_string(), "NAT traversal conflicts with the advertised IP address");
+    }
+
+    #[test]
+    fn test_build_keypair_path() {
+        let socket = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 9099);
+        let dir = std::env::temp_dir().join(format!("hera-builder-key-{}", std::process::id()));
+        _ = std::fs::remove_dir_all(&dir);
+        let build = || {
+            NetworkDriverBuilder::new()
+                .with_unsafe_block_signer(Address::random())
+                .with_chain_id(10)
+                .with_socket(socket)
+                .with_keypair_path(dir.join("p2p.key"))
+                .build()
+                .unwrap()
+        };
+
+        let first = build();
+        let second = build();
+        assert_eq!(first.node_info().peer_id, second.node_info().peer_id);
+        assert_eq!(first.node_info().enr, second.node_info().enr);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_build_static_peers_only() {
+        let socket = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 9099);
+        let id = PeerId::random();
+        let mut peer =
+            Multiaddr::from(NetworkAddress { ip: Ipv4Addr::new(10, 0, 0, 1), port: 9222 });
+        peer.push(Protocol::P2p(id));
+        let driver = NetworkDriverBuilder::new()
+            .with_unsafe_block_signer(Address::random())
+            .with_chain_id(10)
+            .with_socket(socket)
+            .with_static_peers(vec![peer])
+            .with_static_peers_only()
+            .build()
+            .unwrap();
+
+        assert!(driver.discovery.is_none());
+        let gate = driver.gossip.swarm.behaviour().gate.as_ref().unwrap().gate();
+        assert_eq!(gate.allowed_peers, HashSet::from([id]));
+        assert!(gate.allowlist_only);
+    }
+
+    #[test]
+    fn test_build_allowlist() {
+        let socket = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 9099);
+        let (a, b, c) = (PeerId::random(), PeerId::random(), PeerId::random());
+        let mut peer =
+            Multiaddr::from(NetworkAddress { ip: Ipv4Addr::new(10, 0, 0, 1), port: 9222 });
+        peer.push(Protocol::P2p(c));
+        let driver = NetworkDriverBuilder::new()
+            .with_unsafe_block_signer(Address::random())
+            .with_chain_id(10)
+            .with_socket(socket)
+            .with_static_peers(vec![peer])
+            .with_allowlist([a])
+            .with_allowlist([b])
+            .build()
+            .unwrap();
+
+        assert!(driver.discovery.is_some());
+        let gate = driver.gossip.swarm.behaviour().gate.as_ref().unwrap().gate();
+        // The static peers are allowed too.
+        assert_eq!(gate.allowed_peers, HashSet::from([a, b, c]));
+        assert!(gate.allowlist_only);
+    }
+
+    #[test]
+    fn test_build_static_peers_only_missing_peer_id() {
+        let socket = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 9099);
+        let peer = Multiaddr::from(NetworkAddress { ip: Ipv4Addr::new(10, 0, 0, 1), port: 9222 });
+        let Err(err) = NetworkDriverBuilder::new()
+            .with_unsafe_block_signer(Address::random())
+            .with_chain_id(10)
+            .with_socket(socket)
+            .with_static_peers(vec![peer.clone()])
+            .with_static_peers_only()
+            .build()
+        else {
+            panic!("expected error when a static peer has no peer ID");
+        };
+        assert_eq!(err.to_string(), format!("static peer {} has no peer ID", peer));
+    }
+
+    #[test]
+    fn test_build_keep_alive() {
+        let socket = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 9099);
+        let mut builder = NetworkDriverBuilder::new();
+        builder
+            .with_unsafe_block_signer(Address::random())
+            .with_chain_id(10)
+            .with_socket(socket)
+            .with_idle_connection_timeout(Duration::from_secs(600))
+            .with_keep_alive(Duration::from_secs(10), Duration::from_secs(5));
+        assert_eq!(builder.idle_connection_timeout, Some(Duration::from_secs(600)));
+        assert!(builder.ping_config.is_some());
+
+        assert!(builder.build().is_ok());
+    }
+
+    #[test]
+    fn test_build_peer_bans() {
+        let socket = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 9099);
+        let mut builder = NetworkDriverBuilder::new();
+        builder
+            .with_unsafe_block_signer(Address::random())
+            .with_chain_id(10)
+            .with_socket(socket)
+            .with_discovery_disabled()
+            .with_peer_bans(-100.0, Duration::from_secs(3600));
+        let Err(err) = builder.build() else {
+            panic!("expected error when banning peers without peer scoring");
+        };
+        assert_eq!(err.to_string(), "peer bans require peer scoring");
+
+        let driver = builder
+            .with_peer_scoring(PeerScoring::default())
+            .with_peer_bans(-100.0, Duration::from_secs(3600))
+            .build()
+            .unwrap();
+        assert_eq!(driver.gossip.ban_policy, Some((-100.0, Duration::from_secs(3600))));
+    }
+
+    #[test]
+    fn test_build_sequencer_key() {
+        let socket = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 9099);
+        let key = PrivateKeySigner::random();
+        let mut builder = NetworkDriverBuilder::new();
+        builder
+            .with_unsafe_block_signer(Address::random())
+            .with_chain_id(10)
+            .with_socket(socket)
+            .with_discovery_disabled()
+            .with_sequencer_key(key.clone());
+        let Err(err) = builder.build() else {
+            panic!("expected error when the sequencer key is not the unsafe block signer");
+        };
+        assert_eq!(err.to_string(), "sequencer key does not match the unsafe block signer");
+
+        let driver = builder
+            .with_unsafe_block_signer(key.address())
+            .with_socket(socket)
+            .with_sequencer_key(key.clone())
+            .build()
+            .unwrap();
+        assert_eq!(driver.gossip.sequencer.unwrap().address(), key.address());
+    }
+
+    #[test]
+    fn test_node_info() {
+        let socket = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 9099);
+        let driver = NetworkDriverBuilder::new()
+            .with_unsafe_block_signer(Address::random())
+            .with_chain_id(10)
+            .with_socket(socket)
+            .with_allowlist([PeerId::random()])
+            .build()
+            .unwrap();
+        let info = driver.node_info();
+
+        assert_eq!(info.peer_id, driver.gossip.swarm.local_peer_id().to_string());
+        assert!(info.enr.is_some());
+        assert_eq!(info.listen_addrs, vec!["/ip4/127.0.0.1/tcp/9099".to_string()]);
+        assert_eq!(info.chain_id, 10);
+        assert_eq!(info.features, vec!["discovery".to_string(), "allowlist".to_string()]);
+    }
+
     #[test]
     fn test_build_default_network_driver() {
         let id = 10;
@@ -273,7 +1286,7 @@ mod tests {
 
         // Driver Assertions
         assert_eq!(driver.gossip.addr, signer_multiaddr);
-        assert_eq!(driver.discovery.chain_id, id);
+        assert_eq!(driver.discovery.as_ref().unwrap().chain_id, id);
 
         // Block Handler Assertions
         assert_eq!(driver.gossip.handler.chain_id, id);
@@ -1,9 +1,15 @@
 //! Bootnodes for consensus network discovery.
 
 use discv5::enr::{CombinedKey, Enr};
+use eyre::{eyre, Result};
 use lazy_static::lazy_static;
 use std::str::FromStr;
 
+/// Parses a base64 encoded bootnode ENR, e.g. from the command line.
+pub fn parse_bootnode(enr: &str) -> Result<Enr<CombinedKey>> {
+    Enr::from_str(enr).map_err(|e| eyre!("invalid bootnode ENR {}: {}", enr, e))
+}
+
 lazy_static! {
     /// Default bootnodes to use.
     pub static ref BOOTNODES: Vec<Enr<CombinedKey>> = [
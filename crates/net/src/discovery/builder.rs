@@ -1,7 +1,7 @@
 //! Contains a builder for the discovery service.
 
 use crate::{
-    discovery::driver::DiscoveryDriver,
+    discovery::{cache::NegativeCache, driver::DiscoveryDriver},
     types::{address::NetworkAddress, enr::OpStackEnr},
 };
 use discv5::{
@@ -9,16 +9,40 @@ use discv5::{
     ConfigBuilder, Discv5, ListenConfig,
 };
 use eyre::Result;
+use libp2p::Multiaddr;
+use libp2p_identity::Keypair;
+use std::{
+    net::{IpAddr, SocketAddrV4, SocketAddrV6},
+    time::Duration,
+};
 
-use crate::types::enr::OP_CL_KEY;
+use crate::types::enr::{ENR_QUIC_KEY, OP_CL_KEY};
 
 /// Discovery service builder.
 #[derive(Debug, Default, Clone)]
 pub struct DiscoveryBuilder {
     /// The discovery service address.
     address: Option<NetworkAddress>,
+    /// The IPv6 address the discovery service listens on too, if any.
+    ipv6_address: Option<SocketAddrV6>,
+    /// The public IP address advertised in the local ENR, e.g. behind a NAT.
+    advertised_ip: Option<IpAddr>,
+    /// The public TCP and UDP port advertised in the local ENR, e.g. behind a NAT.
+    advertised_port: Option<u16>,
     /// The chain ID of the network.
     chain_id: Option<u64>,
+    /// Other chain IDs whose peers are handed out as well.
+    additional_chain_ids: Vec<u64>,
+    /// How long nodes that failed ENR validation are ignored for.
+    negative_cache_ttl: Option<Duration>,
+    /// The bootnodes to seed the routing table with, instead of the default ones.
+    bootnodes: Option<Vec<Enr<CombinedKey>>>,
+    /// The peers handed out on every lookup round, whether discovered or not.
+    static_peers: Vec<Multiaddr>,
+    /// The secp256k1 node key signing the local ENR.
+    keypair: Option<Keypair>,
+    /// The QUIC port of the gossip service advertised in the local ENR, if any.
+    quic_port: Option<u16>,
 }
 
 impl DiscoveryBuilder {
@@ -33,27 +57,151 @@ impl DiscoveryBuilder {
         self
     }
 
+    /// Listens on the given IPv6 address too, next to the IPv4 one. Its port is
+    /// advertised in the local ENR as the IPv6 TCP port of the gossip service.
+    pub fn with_ipv6_address(mut self, address: SocketAddrV6) -> Self {
+        self.ipv6_address = Some(address);
+        self
+    }
+
+    /// Advertises the given public IP address in the local ENR, instead of the one
+    /// reported by peers, e.g. for a node behind a NAT.
+    pub fn with_advertised_ip(mut self, ip: IpAddr) -> Self {
+        self.advertised_ip = Some(ip);
+        self
+    }
+
+    /// Advertises the given public port in the local ENR, for both the gossip service
+    /// and discovery, instead of the ports listened on, e.g. for a node behind a NAT.
+    pub fn with_advertised_port(mut self, port: u16) -> Self {
+        self.advertised_port = Some(port);
+        self
+    }
+
     /// Sets the chain ID of the network.
     pub fn with_chain_id(mut self, chain_id: u64) -> Self {
         self.chain_id = Some(chain_id);
         self
     }
 
+    /// Hands out the discovered peers of the given chains as well, besides the peers of
+    /// the chain set with [DiscoveryBuilder::with_chain_id].
+    pub fn with_additional_chain_ids(mut self, chain_ids: Vec<u64>) -> Self {
+        self.additional_chain_ids = chain_ids;
+        self
+    }
+
+    /// Sets how long nodes whose ENR failed chain-id or fork validation are ignored for.
+    ///
+    /// Defaults to [crate::discovery::cache::DEFAULT_NEGATIVE_CACHE_TTL].
+    pub fn with_negative_cache_ttl(mut self, ttl: Duration) -> Self {
+        self.negative_cache_ttl = Some(ttl);
+        self
+    }
+
+    /// Seeds the routing table with the given bootnodes, instead of the default
+    /// OP Mainnet and Base bootnodes, e.g. for devnets and private chains.
+    pub fn with_bootnodes(mut self, bootnodes: Vec<Enr<CombinedKey>>) -> Self {
+        self.bootnodes = Some(bootnodes);
+        self
+    }
+
+    /// Sets the peers handed out on every lookup round, so that they are always
+    /// dialed, and redialed if they disconnect.
+    pub fn with_static_peers(mut self, peers: Vec<Multiaddr>) -> Self {
+        self.static_peers = peers;
+        self
+    }
+
+    /// Sets the secp256k1 node key signing the local ENR, so that the node ID matches
+    /// the peer ID of the gossip service. A random key is generated if unset.
+    pub fn with_keypair(mut self, keypair: Keypair) -> Self {
+        self.keypair = Some(keypair);
+        self
+    }
+
+    /// Advertises the given QUIC port of the gossip service in the local ENR, under the
+    /// `quic` key, next to its TCP port.
+    pub fn with_quic_port(mut self, port: u16) -> Self {
+        self.quic_port = Some(port);
+        self
+    }
+
     /// Builds a [DiscoveryDriver].
     pub fn build(&mut self) -> Result<DiscoveryDriver> {
         let addr = self.address.ok_or_else(|| eyre::eyre!("address not set"))?;
         let chain_id = self.chain_id.ok_or_else(|| eyre::eyre!("chain ID not set"))?;
-        let opstack = OpStackEnr::new(chain_id, 0);
-        let opstack_data: Vec<u8> = opstack.into();
-
-        let key = CombinedKey::generate_secp256k1();
-        let enr = Enr::builder().add_value_rlp(OP_CL_KEY, opstack_data.into()).build(&key)?;
-        let listen_config = ListenConfig::from_ip(addr.ip.into(), addr.port);
-        let config = ConfigBuilder::new(listen_config).build();
+        let (enr, key) = self.enr_and_key()?;
+        let listen_config = ListenConfig::from_two_sockets(
+            Some(SocketAddrV4::new(addr.ip, addr.port)),
+            self.ipv6_address,
+        );
+        let mut config = ConfigBuilder::new(listen_config);
+        if self.advertised_ip.is_some() {
+            // Keep the advertised address instead of the one reported by peers.
+            config.disable_enr_update();
+        }
+        let config = config.build();
 
         let disc = Discv5::new(enr, key, config)
             .map_err(|_| eyre::eyre!("could not create disc service"))?;
 
-        Ok(DiscoveryDriver::new(disc, chain_id))
+        let rejected = self.negative_cache_ttl.map(NegativeCache::new).unwrap_or_default();
+
+        let mut driver = DiscoveryDriver::new(disc, chain_id)
+            .with_rejected_cache(rejected)
+            .with_additional_chain_ids(std::mem::take(&mut self.additional_chain_ids))
+            .with_static_peers(std::mem::take(&mut self.static_peers));
+        if let Some(bootnodes) = self.bootnodes.take() {
+            driver = driver.with_bootnodes(bootnodes);
+        }
+        Ok(driver)
+    }
+
+    /// Returns the local ENR the discovery service would advertise, without starting it,
+    /// e.g. to list the node as a bootnode.
+    pub fn local_enr(&self) -> Result<Enr<CombinedKey>> {
+        Ok(self.enr_and_key()?.0)
+    }
+
+    /// Returns the local ENR and its signing key, generating a key if none is set.
+    fn enr_and_key(&self) -> Result<(Enr<CombinedKey>, CombinedKey)> {
+        let addr = self.address.ok_or_else(|| eyre::eyre!("address not set"))?;
+        let chain_id = self.chain_id.ok_or_else(|| eyre::eyre!("chain ID not set"))?;
+        let opstack = OpStackEnr::new(chain_id, 0);
+        let opstack_data: Vec<u8> = opstack.into();
+
+        let key = match self.keypair.clone() {
+            Some(keypair) => {
+                let keypair = keypair
+                    .try_into_secp256k1()
+                    .map_err(|_| eyre::eyre!("discovery requires a secp256k1 node key"))?;
+                CombinedKey::secp256k1_from_bytes(&mut keypair.secret().to_bytes())
+                    .map_err(|e| eyre::eyre!("invalid node key: {:?}", e))?
+            }
+            None => CombinedKey::generate_secp256k1(),
+        };
+        // The gossip service listens on the TCP port of the discovery address.
+        let mut enr = Enr::builder();
+        enr.add_value_rlp(OP_CL_KEY, opstack_data.into())
+            .tcp4(self.advertised_port.unwrap_or(addr.port));
+        if let Some(ipv6) = self.ipv6_address {
+            enr.tcp6(self.advertised_port.unwrap_or(ipv6.port()));
+        }
+        match self.advertised_ip {
+            Some(IpAddr::V4(ip)) => {
+                enr.ip4(ip).udp4(self.advertised_port.unwrap_or(addr.port));
+            }
+            Some(IpAddr::V6(ip)) => {
+                let port = self.ipv6_address.map_or(addr.port, |ipv6| ipv6.port());
+                enr.ip6(ip).udp6(self.advertised_port.unwrap_or(port));
+            }
+            None => {}
+        }
+        if let Some(port) = self.quic_port {
+            enr.add_value(ENR_QUIC_KEY, &port);
+        }
+        let enr = enr.build(&key)?;
+        Ok((enr, key))
     }
 }
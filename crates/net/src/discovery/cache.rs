@@ -0,0 +1,84 @@
+//! Negative cache for peers that failed validation.
+
+use std::{
+    collections::HashMap,
+    hash::Hash,
+    time::{Duration, Instant},
+};
+
+/// The default duration that a rejected node is remembered for.
+pub const DEFAULT_NEGATIVE_CACHE_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// A cache of keys (typically node IDs) whose ENRs failed chain-id or fork validation.
+///
+/// Entries expire after a fixed TTL, so that a node which later joins the
+/// correct network (or fixes its ENR) is eventually reconsidered.
+#[derive(Debug, Clone)]
+pub struct NegativeCache<K> {
+    /// The time-to-live of each entry.
+    ttl: Duration,
+    /// Maps each rejected key to the instant it expires at.
+    entries: HashMap<K, Instant>,
+}
+
+impl<K: Hash + Eq> NegativeCache<K> {
+    /// Creates a new [NegativeCache] with the given TTL.
+    pub fn new(ttl: Duration) -> Self {
+        Self { ttl, entries: HashMap::new() }
+    }
+
+    /// Marks the given key as rejected.
+    pub fn insert(&mut self, key: K) {
+        self.entries.insert(key, Instant::now() + self.ttl);
+    }
+
+    /// Returns `true` if the key was rejected and its entry has not yet expired.
+    pub fn contains(&self, key: &K) -> bool {
+        self.entries.get(key).is_some_and(|expiry| *expiry > Instant::now())
+    }
+
+    /// Removes all expired entries from the cache.
+    pub fn prune(&mut self) {
+        let now = Instant::now();
+        self.entries.retain(|_, expiry| *expiry > now);
+    }
+
+    /// Returns the number of entries in the cache, including expired ones not yet pruned.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `true` if the cache is empty.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+impl<K: Hash + Eq> Default for NegativeCache<K> {
+    fn default() -> Self {
+        Self::new(DEFAULT_NEGATIVE_CACHE_TTL)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_negative_cache_contains() {
+        let mut cache = NegativeCache::new(Duration::from_secs(60));
+        cache.insert(1u64);
+        assert!(cache.contains(&1));
+        assert!(!cache.contains(&2));
+    }
+
+    #[test]
+    fn test_negative_cache_expiry() {
+        let mut cache = NegativeCache::new(Duration::ZERO);
+        cache.insert(1u64);
+        assert!(!cache.contains(&1));
+        assert_eq!(cache.len(), 1);
+        cache.prune();
+        assert!(cache.is_empty());
+    }
+}
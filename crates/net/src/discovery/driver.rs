@@ -1,29 +1,111 @@
 //! Discovery Module.
 
-use eyre::Result;
-use std::time::Duration;
+use eyre::{eyre, Result};
+use std::{collections::HashSet, net::SocketAddr, time::Duration};
 use tokio::{
-    sync::mpsc::{channel, Receiver},
-    time::sleep,
+    select,
+    sync::{
+        broadcast,
+        mpsc::{self, channel, unbounded_channel, Receiver, UnboundedReceiver, UnboundedSender},
+        oneshot, watch,
+    },
+    task::JoinHandle,
+    time::{interval_at, sleep, timeout, Instant},
 };
-use tracing::{trace, warn};
+use tracing::{debug, error, info, trace, warn};
 
-use discv5::{enr::NodeId, Discv5};
+use discv5::{
+    enr::{CombinedKey, Enr, NodeId},
+    Discv5, Event,
+};
+use libp2p::Multiaddr;
 
 use crate::{
-    discovery::{bootnodes::BOOTNODES, builder::DiscoveryBuilder},
-    types::{address::Peer, enr::OpStackEnr},
+    discovery::{
+        bootnodes::BOOTNODES,
+        builder::DiscoveryBuilder,
+        cache::NegativeCache,
+        events::{DiscoveryEvent, DISCOVERY_EVENT_CHANNEL_SIZE},
+        schedule::LookupSchedule,
+        stats::DiscoveryStats,
+    },
+    types::{
+        address::Peer,
+        enr::{EnrRejection, OpStackEnr, OP_CL_KEY},
+    },
 };
 
 /// The number of peers to buffer in the channel.
 const DISCOVERY_PEER_CHANNEL_SIZE: usize = 256;
 
+/// The default interval at which an updated local ENR is re-published to the peers of
+/// the routing table.
+pub const DEFAULT_ENR_REFRESH_INTERVAL: Duration = Duration::from_secs(300);
+
+/// The default duration after which a lookup is abandoned.
+pub const DEFAULT_LOOKUP_TIMEOUT: Duration = Duration::from_secs(60);
+
 /// The discovery driver handles running the discovery service.
 pub struct DiscoveryDriver {
     /// The [Discv5] discovery service.
     pub disc: Discv5,
     /// The chain ID of the network.
     pub chain_id: u64,
+    /// Other chain IDs whose peers are handed out as well.
+    pub additional_chain_ids: Vec<u64>,
+    /// Node IDs whose ENRs recently failed chain-id or fork validation.
+    pub rejected: NegativeCache<NodeId>,
+    /// Receives the ENR updates to apply while the service is running.
+    pub enr_updates: Option<UnboundedReceiver<EnrUpdate>>,
+    /// Receives the commands to answer while the service is running.
+    pub commands: Option<UnboundedReceiver<DiscoveryCommand>>,
+    /// The bootnodes the routing table is seeded with.
+    pub bootnodes: Vec<Enr<CombinedKey>>,
+    /// The peers handed out on every lookup round.
+    pub static_peers: Vec<Multiaddr>,
+    /// Signals the service to shut down, if set.
+    pub shutdown: Option<watch::Receiver<bool>>,
+    /// Schedules the lookups of the service.
+    pub schedule: LookupSchedule,
+    /// The interval at which an updated local ENR is re-published.
+    pub enr_refresh_interval: Duration,
+    /// Publishes the statistics of the service after every lookup.
+    pub stats: watch::Sender<DiscoveryStats>,
+    /// Publishes the [DiscoveryEvent]s of the service.
+    pub events: broadcast::Sender<DiscoveryEvent>,
+    /// The duration after which a lookup is abandoned.
+    pub lookup_timeout: Duration,
+}
+
+/// An update of a field of the local ENR.
+#[derive(Debug, Clone)]
+pub enum EnrUpdate {
+    /// Advertise the given UDP socket address, e.g. after a NAT port mapping.
+    Udp(SocketAddr),
+    /// Advertise the given TCP socket address of the gossip service.
+    Tcp(SocketAddr),
+    /// Replace the `opstack` entry, e.g. after a fork transition.
+    OpStack(OpStackEnr),
+    /// Set a custom entry to the given value, which is RLP encoded as a byte string.
+    Custom(String, Vec<u8>),
+}
+
+/// A command to a running [DiscoveryDriver], e.g. from the admin RPC.
+#[derive(Debug)]
+pub enum DiscoveryCommand {
+    /// Sends back the base64 encoded local ENR.
+    LocalEnr(oneshot::Sender<String>),
+}
+
+/// Sends [EnrUpdate]s to a running [DiscoveryDriver].
+#[derive(Debug, Clone)]
+pub struct EnrUpdater(UnboundedSender<EnrUpdate>);
+
+impl EnrUpdater {
+    /// Updates the local ENR of the running discovery service.
+    pub fn update(&self, update: EnrUpdate) -> Result<()> {
+        self.0.send(update).map_err(|_| eyre!("discovery service is not running"))
+    }
 }
 
 impl DiscoveryDriver {
@@ -34,12 +116,191 @@ impl DiscoveryDriver {
 
     /// Instantiates a new [DiscoveryDriver].
     pub fn new(disc: Discv5, chain_id: u64) -> Self {
-        Self { disc, chain_id }
+        Self {
+            disc,
+            chain_id,
+            additional_chain_ids: Vec::new(),
+            rejected: NegativeCache::default(),
+            enr_updates: None,
+            commands: None,
+            bootnodes: BOOTNODES.clone(),
+            static_peers: Vec::new(),
+            shutdown: None,
+            schedule: LookupSchedule::default(),
+            enr_refresh_interval: DEFAULT_ENR_REFRESH_INTERVAL,
+            stats: watch::channel(DiscoveryStats::default()).0,
+            events: broadcast::channel(DISCOVERY_EVENT_CHANNEL_SIZE).0,
+            lookup_timeout: DEFAULT_LOOKUP_TIMEOUT,
+        }
+    }
+
+    /// Replaces the default bootnodes the routing table is seeded with.
+    pub fn with_bootnodes(mut self, bootnodes: Vec<Enr<CombinedKey>>) -> Self {
+        self.bootnodes = bootnodes;
+        self
+    }
+
+    /// Sets the peers handed out on every lookup round, whether discovered or not.
+    pub fn with_static_peers(mut self, peers: Vec<Multiaddr>) -> Self {
+        self.static_peers = peers;
+        self
+    }
+
+    /// Shuts the spawned service down and closes its socket once `true` is sent on the
+    /// given channel, or the channel is closed.
+    pub fn with_shutdown(mut self, shutdown: watch::Receiver<bool>) -> Self {
+        self.shutdown = Some(shutdown);
+        self
+    }
+
+    /// Hands out the peers of the given chains as well, whose `opstack` ENR entries are
+    /// otherwise rejected, e.g. for chains sharing their gossip network.
+    pub fn with_additional_chain_ids(mut self, chain_ids: Vec<u64>) -> Self {
+        self.additional_chain_ids = chain_ids;
+        self
+    }
+
+    /// Checks that a node [Enr] has a valid `opstack` entry for one of the chains of the
+    /// service, so that Ethereum L1 nodes and the peers of other chains aren't dialed.
+    pub fn check_node(&self, node: &Enr<CombinedKey>) -> Result<OpStackEnr, EnrRejection> {
+        OpStackEnr::check_node(node, |chain_id| {
+            chain_id == self.chain_id || self.additional_chain_ids.contains(&chain_id)
+        })
+    }
+
+    /// Replaces the default [LookupSchedule] of the `FIND_NODE` lookups.
+    pub fn with_lookup_schedule(mut self, schedule: LookupSchedule) -> Self {
+        self.schedule = schedule;
+        self
+    }
+
+    /// Sets the interval at which an updated local ENR is re-published to the peers of
+    /// the routing table.
+    pub fn with_enr_refresh_interval(mut self, interval: Duration) -> Self {
+        self.enr_refresh_interval = interval;
+        self
+    }
+
+    /// Returns a receiver of the [DiscoveryStats] published after every lookup of the
+    /// spawned service.
+    pub fn subscribe_stats(&self) -> watch::Receiver<DiscoveryStats> {
+        self.stats.subscribe()
+    }
+
+    /// Returns a receiver of the [DiscoveryEvent]s of the spawned service from now on.
+    ///
+    /// Subscribers falling behind by more than [DISCOVERY_EVENT_CHANNEL_SIZE] events skip
+    /// the oldest ones.
+    pub fn events(&self) -> broadcast::Receiver<DiscoveryEvent> {
+        self.events.subscribe()
+    }
+
+    /// Publishes the given event, if anyone is subscribed.
+    fn emit(&self, event: impl FnOnce() -> DiscoveryEvent) {
+        if self.events.receiver_count() > 0 {
+            _ = self.events.send(event());
+        }
+    }
+
+    /// Sets the duration after which a lookup is abandoned.
+    pub fn with_lookup_timeout(mut self, timeout: Duration) -> Self {
+        self.lookup_timeout = timeout;
+        self
+    }
+
+    /// Returns the current statistics of the routing table.
+    pub fn table_stats(&self) -> DiscoveryStats {
+        let table = self.disc.table_entries_enr();
+        let metrics = self.disc.metrics();
+        DiscoveryStats {
+            table_size: table.len(),
+            opstack_peers: table.iter().filter(|enr| self.check_node(enr).is_ok()).count(),
+            connected_peers: self.disc.connected_peers(),
+            rejected_peers: self.rejected.len(),
+            lookup_interval_secs: self.schedule.interval().as_secs(),
+            enr_seq: self.disc.local_enr().seq(),
+            bytes_sent: metrics.bytes_sent,
+            bytes_received: metrics.bytes_recv,
+            ..Default::default()
+        }
+    }
+
+    /// Re-publishes the local ENR by pinging the peers of the routing table, which
+    /// request the ENR when they see its new sequence number.
+    async fn publish_enr(&self) {
+        let table = self.disc.table_entries_enr();
+        let total = table.len();
+        let pings = table.into_iter().map(|enr| self.disc.send_ping(enr));
+        let reached = futures::future::join_all(pings).await.iter().filter(|r| r.is_ok()).count();
+        metrics::counter!("hera_discovery_enr_publishes_total").increment(1);
+        debug!(
+            "Published local ENR with sequence number {} to {}/{} peers",
+            self.disc.local_enr().seq(),
+            reached,
+            total
+        );
+    }
+
+    /// Returns an [EnrUpdater] to update the local ENR once the service is spawned.
+    ///
+    /// Replaces any previously returned updater.
+    pub fn enr_updater(&mut self) -> EnrUpdater {
+        let (sender, recv) = unbounded_channel();
+        self.enr_updates = Some(recv);
+        EnrUpdater(sender)
+    }
+
+    /// Returns a sender of [DiscoveryCommand]s to the service once it is spawned.
+    ///
+    /// Replaces any previously returned sender.
+    pub fn commander(&mut self) -> UnboundedSender<DiscoveryCommand> {
+        let (sender, recv) = unbounded_channel();
+        self.commands = Some(recv);
+        sender
+    }
+
+    /// Answers a [DiscoveryCommand].
+    fn handle_command(&self, command: DiscoveryCommand) {
+        match command {
+            DiscoveryCommand::LocalEnr(reply) => {
+                _ = reply.send(self.disc.local_enr().to_base64());
+            }
+        }
+    }
+
+    /// Updates a field of the local ENR, which is re-signed with a bumped sequence number.
+    pub fn update_enr(&self, update: EnrUpdate) -> Result<()> {
+        match update {
+            EnrUpdate::Udp(addr) => {
+                self.disc.update_local_enr_socket(addr, false);
+            }
+            EnrUpdate::Tcp(addr) => {
+                self.disc.update_local_enr_socket(addr, true);
+            }
+            EnrUpdate::OpStack(opstack) => {
+                self.disc
+                    .enr_insert(OP_CL_KEY, &opstack.value_bytes())
+                    .map_err(|e| eyre!("failed to update opstack ENR entry: {:?}", e))?;
+            }
+            EnrUpdate::Custom(key, value) => {
+                self.disc
+                    .enr_insert(&key, &value)
+                    .map_err(|e| eyre!("failed to update ENR entry {}: {:?}", key, e))?;
+            }
+        }
+        debug!("Updated local ENR to sequence number {}", self.disc.local_enr().seq());
+        Ok(())
+    }
+
+    /// Replaces the [NegativeCache] of rejected node IDs.
+    pub fn with_rejected_cache(mut self, rejected: NegativeCache<NodeId>) -> Self {
+        self.rejected = rejected;
+        self
     }
 
     /// Spawns a new [Discv5] discovery service in a new tokio task.
     ///
-    /// Returns a [Receiver] to receive [Peer] structs.
+    /// Returns a [Receiver] to receive the addresses of the static and discovered peers.
     ///
     /// ## Errors
     ///
@@ -69,42 +330,300 @@ impl DiscoveryDriver {
     ///     }
     /// }
     /// ```
-    pub fn start(mut self) -> Result<Receiver<Peer>> {
-        // Clone the bootnodes since the spawned thread takes mutable ownership.
-        let bootnodes = BOOTNODES.clone();
+    pub fn start(self) -> Result<Receiver<Multiaddr>> {
+        self.spawn().map(|(recv, _)| recv)
+    }
+
+    /// Spawns a new [Discv5] discovery service in a new tokio task.
+    ///
+    /// Returns a [Receiver] to receive the addresses of the static and discovered
+    /// peers, along with the [JoinHandle] of the spawned task so callers can supervise it.
+    /// The receiver is closed once the task exits.
+    pub fn spawn(mut self) -> Result<(Receiver<Multiaddr>, JoinHandle<()>)> {
+        let bootnodes = std::mem::take(&mut self.bootnodes);
 
         // Create a multi-producer, single-consumer (mpsc) channel to receive
         // peers bounded by `DISCOVERY_PEER_CHANNEL_SIZE`.
-        let (sender, recv) = channel::<Peer>(DISCOVERY_PEER_CHANNEL_SIZE);
+        let (sender, recv) = channel::<Multiaddr>(DISCOVERY_PEER_CHANNEL_SIZE);
 
-        tokio::spawn(async move {
+        let mut updates = self.enr_updates.take();
+        let mut commands = self.commands.take();
+        let mut shutdown = self.shutdown.take();
+        let handle = tokio::spawn(async move {
             bootnodes.into_iter().for_each(|enr| _ = self.disc.add_enr(enr));
-            self.disc.start().await.unwrap();
+            if let Err(err) = self.disc.start().await {
+                error!("Failed to start peer discovery: {:?}", err);
+                return;
+            }
 
             trace!("Started peer discovery");
 
-            loop {
+            // Discv5 updates the local ENR on its own once its peers agree on a new
+            // external address, which is then re-published on the next refresh.
+            let mut disc_events = match self.disc.event_stream().await {
+                Ok(events) => Some(events),
+                Err(err) => {
+                    warn!("Failed to subscribe to discovery events: {:?}", err);
+                    None
+                }
+            };
+            let period = self.enr_refresh_interval;
+            let mut refresh = interval_at(Instant::now() + period, period);
+            let mut published_seq = self.disc.local_enr().seq();
+            let mut lookups = 0;
+
+            'discovery: loop {
+                // Static peers that are still connected are skipped by the dialer.
+                for peer in &self.static_peers {
+                    _ = sender.send(peer.clone()).await;
+                }
+
+                let known: HashSet<_> = self.disc.table_entries_id().into_iter().collect();
+                let mut new_peers = 0;
                 let target = NodeId::random();
-                match self.disc.find_node(target).await {
-                    Ok(nodes) => {
-                        let peers = nodes
-                            .iter()
-                            .filter(|node| OpStackEnr::is_valid_node(node, self.chain_id))
-                            .flat_map(Peer::try_from);
+                let lookup = select! {
+                    lookup = timeout(self.lookup_timeout, self.disc.find_node(target)) => lookup,
+                    _ = recv_shutdown(&mut shutdown) => break 'discovery,
+                };
+                match lookup {
+                    Ok(Ok(nodes)) => {
+                        self.rejected.prune();
+                        let mut peers = Vec::with_capacity(nodes.len());
+                        for node in &nodes {
+                            if self.rejected.contains(&node.node_id()) {
+                                continue;
+                            }
+                            if let Err(reason) = self.check_node(node) {
+                                trace!(
+                                    "Rejecting node {} with invalid opstack ENR: {:?}",
+                                    node.node_id(),
+                                    reason
+                                );
+                                metrics::counter!(
+                                    "hera_discovery_rejected_nodes_total",
+                                    "reason" => reason.as_str()
+                                )
+                                .increment(1);
+                                self.rejected.insert(node.node_id());
+                                self.emit(|| DiscoveryEvent::PeerRejected {
+                                    node_id: node.node_id(),
+                                    reason,
+                                });
+                                continue;
+                            }
+                            if !known.contains(&node.node_id()) {
+                                new_peers += 1;
+                            }
+                            self.emit(|| DiscoveryEvent::PeerDiscovered(node.clone()));
+                            peers.extend(Peer::try_from(node));
+                        }
 
+                        metrics::counter!("hera_discovery_peers_found_total")
+                            .increment(peers.len() as u64);
                         for peer in peers {
-                            _ = sender.send(peer).await;
+                            _ = sender.send(peer.into()).await;
                         }
                     }
-                    Err(err) => {
+                    Ok(Err(err)) => {
                         warn!("discovery error: {:?}", err);
                     }
+                    Err(_) => {
+                        warn!("Discovery lookup timed out after {:?}", self.lookup_timeout);
+                        metrics::counter!("hera_discovery_lookup_timeouts_total").increment(1);
+                        self.emit(|| DiscoveryEvent::QueryTimeout {
+                            target,
+                            timeout: self.lookup_timeout,
+                        });
+                    }
                 }
+                lookups += 1;
+                metrics::counter!("hera_discovery_lookups_total").increment(1);
 
-                sleep(Duration::from_secs(10)).await;
+                let mut stats = self.table_stats();
+                let interval = self.schedule.next(new_peers, stats.opstack_peers);
+                stats.lookups = lookups;
+                stats.last_lookup_new_peers = new_peers;
+                stats.lookup_interval_secs = interval.as_secs();
+                stats.record();
+                debug!(
+                    "Discovery table has {} nodes, {} OP-stack peers, next lookup in {:?}",
+                    stats.table_size, stats.opstack_peers, interval
+                );
+                self.emit(|| DiscoveryEvent::LookupCompleted(stats.clone()));
+                self.stats.send_replace(stats);
+
+                // Apply the ENR updates received until the next lookup.
+                let pause = sleep(interval);
+                tokio::pin!(pause);
+                loop {
+                    select! {
+                        _ = &mut pause => break,
+                        Some(update) = recv_update(&mut updates) => {
+                            if let Err(err) = self.update_enr(update) {
+                                warn!("Failed to update local ENR: {:?}", err);
+                            }
+                        }
+                        Some(command) = recv_update(&mut commands) => {
+                            self.handle_command(command);
+                        }
+                        Some(event) = recv_event(&mut disc_events) => match event {
+                            Event::SocketUpdated(addr) => {
+                                info!(
+                                    "Local ENR updated to the external address {} (seq {})",
+                                    addr,
+                                    self.disc.local_enr().seq()
+                                );
+                                metrics::counter!("hera_discovery_enr_updates_total").increment(1);
+                                self.emit(|| DiscoveryEvent::SocketUpdated(addr));
+                            }
+                            Event::SessionEstablished(enr, addr) => {
+                                trace!("Established session with {} at {}", enr.node_id(), addr);
+                                metrics::counter!("hera_discovery_sessions_total").increment(1);
+                                self.emit(|| DiscoveryEvent::SessionEstablished { enr, addr });
+                            }
+                            _ => {}
+                        },
+                        _ = refresh.tick() => {
+                            let seq = self.disc.local_enr().seq();
+                            if seq != published_seq {
+                                self.publish_enr().await;
+                                published_seq = seq;
+                            }
+                        }
+                        _ = recv_shutdown(&mut shutdown) => break 'discovery,
+                    }
+                }
             }
+
+            self.disc.shutdown();
+            debug!("Stopped peer discovery");
         });
 
-        Ok(recv)
+        Ok((recv, handle))
+    }
+}
+
+/// Waits for the shutdown signal, or forever if there is none.
+async fn recv_shutdown(shutdown: &mut Option<watch::Receiver<bool>>) {
+    match shutdown {
+        Some(recv) => {
+            _ = recv.wait_for(|stop| *stop).await;
+        }
+        None => std::future::pending().await,
+    }
+}
+
+/// Receives the next discv5 [Event], or waits forever if there is no event stream.
+async fn recv_event(events: &mut Option<mpsc::Receiver<Event>>) -> Option<Event> {
+    match events {
+        Some(recv) => recv.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Receives the next [EnrUpdate] or [DiscoveryCommand], or waits forever if there is no
+/// sender.
+async fn recv_update<T>(updates: &mut Option<UnboundedReceiver<T>>) -> Option<T> {
+    match updates {
+        Some(recv) => recv.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::address::NetworkAddress;
+    use std::net::Ipv4Addr;
+
+    #[test]
+    fn test_build_with_bootnodes_and_static_peers() {
+        let addr = NetworkAddress { ip: Ipv4Addr::new(127, 0, 0, 1), port: 9001 };
+        let driver =
+            DiscoveryDriver::builder().with_address(addr).with_chain_id(10).build().unwrap();
+        assert_eq!(driver.bootnodes.len(), BOOTNODES.len());
+        assert!(driver.static_peers.is_empty());
+
+        let bootnode = BOOTNODES[0].clone();
+        let peer: Multiaddr = "/ip4/10.0.0.1/tcp/9222".parse().unwrap();
+        let driver = DiscoveryDriver::builder()
+            .with_address(addr)
+            .with_chain_id(10)
+            .with_bootnodes(vec![bootnode.clone()])
+            .with_static_peers(vec![peer.clone()])
+            .build()
+            .unwrap();
+        assert_eq!(driver.bootnodes, vec![bootnode]);
+        assert_eq!(driver.static_peers, vec![peer]);
+    }
+
+    #[test]
+    fn test_local_enr() {
+        let addr = NetworkAddress { ip: Ipv4Addr::new(127, 0, 0, 1), port: 9003 };
+        let keypair = libp2p_identity::Keypair::generate_secp256k1();
+        let builder = DiscoveryDriver::builder()
+            .with_address(addr)
+            .with_chain_id(10)
+            .with_keypair(keypair)
+            .with_advertised_ip(Ipv4Addr::new(10, 0, 0, 1).into());
+        let enr = builder.local_enr().unwrap();
+        assert!(OpStackEnr::is_valid_node(&enr, 10));
+        assert_eq!(enr.ip4(), Some(Ipv4Addr::new(10, 0, 0, 1)));
+        assert_eq!(enr.udp4(), Some(9003));
+
+        // The ENR is signed with the node key, so it keeps the node ID of the service.
+        let driver = builder.clone().build().unwrap();
+        assert_eq!(driver.disc.local_enr().node_id(), enr.node_id());
+    }
+
+    #[test]
+    fn test_update_enr() {
+        let addr = NetworkAddress { ip: Ipv4Addr::new(127, 0, 0, 1), port: 9000 };
+        let driver =
+            DiscoveryDriver::builder().with_address(addr).with_chain_id(10).build().unwrap();
+        let seq = driver.disc.local_enr().seq();
+
+        driver.update_enr(EnrUpdate::OpStack(OpStackEnr::new(8453, 0))).unwrap();
+        let enr = driver.disc.local_enr();
+        assert!(enr.seq() > seq);
+        assert!(OpStackEnr::is_valid_node(&enr, 8453));
+        assert!(!OpStackEnr::is_valid_node(&enr, 10));
+
+        let tcp = SocketAddr::from((Ipv4Addr::new(10, 0, 0, 1), 9222));
+        driver.update_enr(EnrUpdate::Tcp(tcp)).unwrap();
+        assert_eq!(driver.disc.local_enr().tcp4(), Some(9222));
+    }
+
+    #[test]
+    fn test_table_stats() {
+        let addr = NetworkAddress { ip: Ipv4Addr::new(127, 0, 0, 1), port: 9002 };
+        let driver =
+            DiscoveryDriver::builder().with_address(addr).with_chain_id(10).build().unwrap();
+        let stats = driver.table_stats();
+        assert_eq!(stats.table_size, 0);
+        assert_eq!(stats.opstack_peers, 0);
+        assert_eq!(stats.lookup_interval_secs, 5);
+        assert_eq!(stats.enr_seq, driver.disc.local_enr().seq());
+        assert_eq!(*driver.subscribe_stats().borrow(), DiscoveryStats::default());
+    }
+
+    #[test]
+    fn test_events() {
+        let addr = NetworkAddress { ip: Ipv4Addr::new(127, 0, 0, 1), port: 9003 };
+        let driver =
+            DiscoveryDriver::builder().with_address(addr).with_chain_id(10).build().unwrap();
+        // Events without subscribers are dropped.
+        driver.emit(|| DiscoveryEvent::LookupCompleted(DiscoveryStats::default()));
+
+        let mut events = driver.events();
+        let node_id = NodeId::random();
+        driver
+            .emit(|| DiscoveryEvent::PeerRejected { node_id, reason: EnrRejection::MissingEntry });
+        assert!(matches!(
+            events.try_recv().unwrap(),
+            DiscoveryEvent::PeerRejected { node_id: id, reason: EnrRejection::MissingEntry }
+                if id == node_id
+        ));
+        assert!(events.try_recv().is_err());
     }
 }
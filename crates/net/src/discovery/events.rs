@@ -0,0 +1,45 @@
+//! Events of the discovery service, for embedders building their own peer management
+//! and debugging tools.
+
+use std::{net::SocketAddr, time::Duration};
+
+use discv5::enr::{CombinedKey, Enr, NodeId};
+
+use crate::{discovery::stats::DiscoveryStats, types::enr::EnrRejection};
+
+/// The number of events buffered for each subscriber, past which lagging subscribers
+/// skip the oldest ones.
+pub const DISCOVERY_EVENT_CHANNEL_SIZE: usize = 256;
+
+/// An event of a running [crate::discovery::driver::DiscoveryDriver], see
+/// [crate::discovery::driver::DiscoveryDriver::events].
+#[derive(Debug, Clone)]
+pub enum DiscoveryEvent {
+    /// A lookup found a node with a valid `opstack` ENR entry for one of the chains.
+    PeerDiscovered(Enr<CombinedKey>),
+    /// A lookup found a node which is not an OP-stack peer of the chains.
+    PeerRejected {
+        /// The ID of the rejected node.
+        node_id: NodeId,
+        /// Why the node was rejected.
+        reason: EnrRejection,
+    },
+    /// A discv5 session was established with a node.
+    SessionEstablished {
+        /// The ENR of the node.
+        enr: Enr<CombinedKey>,
+        /// The socket address of the node.
+        addr: SocketAddr,
+    },
+    /// A lookup was abandoned after the lookup timeout.
+    QueryTimeout {
+        /// The target node ID of the lookup.
+        target: NodeId,
+        /// How long the lookup ran.
+        timeout: Duration,
+    },
+    /// The local ENR was updated to the external address agreed on by the peers.
+    SocketUpdated(SocketAddr),
+    /// A lookup completed, with the resulting statistics of the routing table.
+    LookupCompleted(DiscoveryStats),
+}
@@ -2,4 +2,8 @@
 
 pub mod bootnodes;
 pub mod builder;
+pub mod cache;
 pub mod driver;
+pub mod events;
+pub mod schedule;
+pub mod stats;
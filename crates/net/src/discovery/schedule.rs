@@ -0,0 +1,85 @@
+//! Adaptive scheduling of discovery lookups.
+
+use std::time::Duration;
+
+/// The default shortest interval between two lookups.
+pub const DEFAULT_MIN_LOOKUP_INTERVAL: Duration = Duration::from_secs(5);
+
+/// The default longest interval between two lookups.
+pub const DEFAULT_MAX_LOOKUP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The default number of OP-stack peers in the routing table below which lookups run at
+/// the shortest interval.
+pub const DEFAULT_TARGET_PEERS: usize = 32;
+
+/// Schedules the `FIND_NODE` lookups of the discovery service.
+///
+/// Lookups run at the shortest interval while the routing table holds fewer OP-stack
+/// peers than the target. Past the target, the interval doubles after every lookup that
+/// finds no new peer, up to the longest interval, and halves after one that does.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LookupSchedule {
+    /// The shortest interval between two lookups.
+    min: Duration,
+    /// The longest interval between two lookups.
+    max: Duration,
+    /// The number of OP-stack peers past which lookups slow down.
+    target_peers: usize,
+    /// The current interval between two lookups.
+    interval: Duration,
+}
+
+impl Default for LookupSchedule {
+    fn default() -> Self {
+        Self::new(DEFAULT_MIN_LOOKUP_INTERVAL, DEFAULT_MAX_LOOKUP_INTERVAL)
+    }
+}
+
+impl LookupSchedule {
+    /// Creates a new [LookupSchedule] between the given shortest and longest intervals.
+    pub fn new(min: Duration, max: Duration) -> Self {
+        Self { min, max: max.max(min), target_peers: DEFAULT_TARGET_PEERS, interval: min }
+    }
+
+    /// Sets the number of OP-stack peers past which lookups slow down.
+    pub fn with_target_peers(mut self, target_peers: usize) -> Self {
+        self.target_peers = target_peers;
+        self
+    }
+
+    /// Returns the current interval between two lookups.
+    pub const fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// Returns the interval until the next lookup, given the number of new OP-stack
+    /// peers found by the last lookup and the number of OP-stack peers in the table.
+    pub fn next(&mut self, new_peers: usize, opstack_peers: usize) -> Duration {
+        self.interval = if opstack_peers < self.target_peers {
+            self.min
+        } else if new_peers > 0 {
+            (self.interval / 2).max(self.min)
+        } else {
+            (self.interval * 2).min(self.max)
+        };
+        self.interval
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_next_interval() {
+        let secs = Duration::from_secs;
+        let mut schedule = LookupSchedule::new(secs(5), secs(30)).with_target_peers(10);
+        assert_eq!(schedule.next(0, 3), secs(5));
+        assert_eq!(schedule.next(0, 10), secs(10));
+        assert_eq!(schedule.next(0, 10), secs(20));
+        assert_eq!(schedule.next(0, 10), secs(30));
+        assert_eq!(schedule.next(0, 10), secs(30));
+        assert_eq!(schedule.next(2, 10), secs(15));
+        assert_eq!(schedule.next(0, 9), secs(5));
+    }
+}
@@ -0,0 +1,48 @@
+//! Routing table statistics of the discovery service.
+
+use serde::{Deserialize, Serialize};
+
+/// A snapshot of the routing table and lookup statistics of a running
+/// [crate::discovery::driver::DiscoveryDriver], to tell whether discovery is actually
+/// finding OP-stack peers.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiscoveryStats {
+    /// The number of nodes in the routing table.
+    pub table_size: usize,
+    /// The number of nodes in the routing table with a valid `opstack` ENR entry for
+    /// the chain.
+    pub opstack_peers: usize,
+    /// The number of nodes with an active discv5 session.
+    pub connected_peers: usize,
+    /// The number of node IDs recently rejected for an invalid `opstack` ENR entry.
+    pub rejected_peers: usize,
+    /// The number of lookups run since the service started.
+    pub lookups: u64,
+    /// The number of new OP-stack peers found by the last lookup.
+    pub last_lookup_new_peers: usize,
+    /// The interval until the next lookup, in seconds.
+    pub lookup_interval_secs: u64,
+    /// The sequence number of the local ENR.
+    pub enr_seq: u64,
+    /// The number of bytes sent by discv5 since the service started.
+    pub bytes_sent: usize,
+    /// The number of bytes received by discv5 since the service started.
+    pub bytes_received: usize,
+}
+
+impl DiscoveryStats {
+    /// Records the statistics as metrics.
+    pub fn record(&self) {
+        metrics::gauge!("hera_discovery_table_size").set(self.table_size as f64);
+        metrics::gauge!("hera_discovery_opstack_peers").set(self.opstack_peers as f64);
+        metrics::gauge!("hera_discovery_connected_peers").set(self.connected_peers as f64);
+        metrics::gauge!("hera_discovery_rejected_peers").set(self.rejected_peers as f64);
+        metrics::gauge!("hera_discovery_lookup_interval_seconds")
+            .set(self.lookup_interval_secs as f64);
+        metrics::gauge!("hera_discovery_enr_seq").set(self.enr_seq as f64);
+        metrics::counter!("hera_discovery_bytes_sent_total").absolute(self.bytes_sent as u64);
+        metrics::counter!("hera_discovery_bytes_received_total")
+            .absolute(self.bytes_received as u64);
+    }
+}
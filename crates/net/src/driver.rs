@@ -1,29 +1,67 @@
 //! Driver for network services.
 
 use crate::{
-    builder::NetworkDriverBuilder, discovery::driver::DiscoveryDriver,
-    gossip::driver::GossipDriver, types::envelope::ExecutionPayloadEnvelope,
+    builder::NetworkDriverBuilder,
+    discovery::{
+        driver::{DiscoveryDriver, EnrUpdater},
+        stats::DiscoveryStats,
+    },
+    gossip::{
+        channel::{UnsafeBlockReceiver, UnsafeBlockSender},
+        driver::{GossipDriver, TIP_ONLY_CHECK_INTERVAL},
+        handler::ReceivedBlock,
+        injector::{PayloadInjector, PublishRequest},
+        peers::{PeerCommand, PeerManager},
+    },
+    sync::client::{SyncClient, SyncRequest},
+    types::{envelope::ExecutionPayloadEnvelope, node_info::NodeInfo},
 };
 use alloy::primitives::Address;
 use eyre::Result;
-use std::sync::mpsc::Receiver;
-use tokio::{select, sync::watch};
+use futures::FutureExt;
+use libp2p::{gossipsub::MessageId, Multiaddr};
+use std::{
+    any::Any,
+    fmt,
+    panic::AssertUnwindSafe,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tokio::{
+    select,
+    sync::{broadcast, mpsc, oneshot, watch},
+    task::JoinHandle,
+};
 
 /// NetworkDriver
 ///
 /// Contains the logic to run Optimism's consensus-layer networking stack.
 /// There are two core services that are run by the driver:
 /// - Block gossip through Gossipsub.
-/// - Peer discovery with `discv5`.
+/// - Peer discovery with `discv5` (optional, see [NetworkDriverBuilder::with_discovery_disabled]
+///   and [NetworkDriverBuilder::with_static_peers_only]).
 pub struct NetworkDriver {
-    /// Channel to receive unsafe blocks.
-    pub unsafe_block_recv: Receiver<ExecutionPayloadEnvelope>,
+    /// Channel to receive unsafe blocks, in gossip order.
+    ///
+    /// It is bounded, see [NetworkDriverBuilder::with_unsafe_block_capacity], and
+    /// [NetworkDriver::subscribe_unsafe_blocks] delivers the blocks to more consumers.
+    /// See [crate::gossip::buffer::UnsafeBlockBuffer] to turn them into a contiguous stream.
+    pub unsafe_block_recv: UnsafeBlockReceiver,
     /// Channel to send unsafe signer updates.
     pub unsafe_block_signer_sender: watch::Sender<Address>,
     /// The swarm instance.
     pub gossip: GossipDriver,
-    /// The discovery service driver.
-    pub discovery: DiscoveryDriver,
+    /// The discovery service driver, if discovery is enabled.
+    pub discovery: Option<DiscoveryDriver>,
+    /// Peers that are always dialed on startup, and redialed by discovery if enabled.
+    pub static_peers: Vec<Multiaddr>,
+    /// The other chains gossiped by the node, see
+    /// [NetworkDriverBuilder::with_additional_chain].
+    ///
+    /// Take them before starting the driver to receive their blocks.
+    pub additional_chains: Vec<ChainGossip>,
+    /// Channel to receive interop executing messages, if interop gossip is enabled.
+    #[cfg(feature = "interop")]
+    pub interop_message_recv: Option<std::sync::mpsc::Receiver<alloy::primitives::Bytes>>,
 }
 
 impl NetworkDriver {
@@ -32,24 +70,378 @@ impl NetworkDriver {
         NetworkDriverBuilder::new()
     }
 
+    /// Returns the identity and network configuration of the node.
+    pub fn node_info(&self) -> NodeInfo {
+        let mut features = Vec::new();
+        if self.discovery.is_some() {
+            features.push("discovery".to_string());
+        }
+        if !self.static_peers.is_empty() {
+            features.push("static-peers".to_string());
+        }
+        if self
+            .gossip
+            .swarm
+            .behaviour()
+            .gate
+            .as_ref()
+            .is_some_and(|gater| gater.gate().allowlist_only)
+        {
+            features.push("allowlist".to_string());
+        }
+        if self.gossip.quic_addr.is_some() {
+            features.push("quic".to_string());
+        }
+        if self.gossip.swarm.behaviour().sync.is_enabled() {
+            features.push("sync".to_string());
+        }
+        if self.gossip.tip_only.is_some() {
+            features.push("tip-only".to_string());
+        }
+        if self.gossip.swarm.behaviour().autonat.is_enabled() {
+            features.push("nat".to_string());
+        }
+        if self.gossip.swarm.behaviour().upnp.is_enabled() {
+            features.push("upnp".to_string());
+        }
+        if !self.gossip.additional_handlers.is_empty() {
+            features.push("multi-chain".to_string());
+        }
+        #[cfg(feature = "interop")]
+        if self.gossip.interop.is_some() {
+            features.push("interop".to_string());
+        }
+
+        NodeInfo {
+            peer_id: self.gossip.swarm.local_peer_id().to_string(),
+            enr: self.discovery.as_ref().map(|d| d.disc.local_enr().to_base64()),
+            listen_addrs: std::iter::once(&self.gossip.addr)
+                .chain(&self.gossip.quic_addr)
+                .chain(&self.gossip.additional_addrs)
+                .map(ToString::to_string)
+                .collect(),
+            chain_id: self.gossip.handler.chain_id,
+            features,
+        }
+    }
+
+    /// Returns a new receiver of the unsafe blocks received from now on, in addition to
+    /// [NetworkDriver::unsafe_block_recv].
+    pub fn subscribe_unsafe_blocks(&self) -> broadcast::Receiver<ReceivedBlock> {
+        self.gossip.handler.block_sender.subscribe()
+    }
+
+    /// Signs the given payload with the sequencer key and publishes it on the blocks
+    /// topic of the hardfork active at its timestamp.
+    ///
+    /// Once the driver is started, use [NetworkHandle::publish_payload] instead.
+    pub fn publish_payload(&mut self, envelope: ExecutionPayloadEnvelope) -> Result<MessageId> {
+        self.gossip.publish_payload(envelope)
+    }
+
     /// Starts the Discv5 peer discovery & libp2p services
-    /// and continually listens for new peers and messages to handle
-    pub fn start(mut self) -> Result<()> {
-        let mut peer_recv = self.discovery.start()?;
+    /// and continually listens for new peers and messages to handle.
+    ///
+    /// Returns a [NetworkHandle] that can be used to supervise the
+    /// spawned tasks and to shut them down.
+    pub fn start(mut self) -> Result<NetworkHandle> {
+        let node_info = self.node_info();
+        let (shutdown, mut shutdown_recv) = watch::channel(false);
+        let (status_sender, status) = watch::channel(NetworkStatus::Running);
+        let (mut peer_recv, discovery, enr_updater, discovery_stats, discovery_commands) =
+            match self.discovery.take() {
+                Some(d) => {
+                    let mut d = d.with_shutdown(shutdown.subscribe());
+                    let updater = d.enr_updater();
+                    let commands = d.commander();
+                    let stats = d.subscribe_stats();
+                    let (recv, handle) = d.spawn()?;
+                    (Some(recv), Some(handle), Some(updater), Some(stats), Some(commands))
+                }
+                None => (None, None, None, None, None),
+            };
+        self.gossip.enr_updater = enr_updater.clone();
         self.gossip.listen()?;
-        tokio::spawn(async move {
+        let (dialer, mut dial_recv) = mpsc::unbounded_channel();
+        let (publisher, mut publish_recv) = mpsc::unbounded_channel::<PublishRequest>();
+        let injector = PayloadInjector::new(self.gossip.handler.clone(), publisher);
+        let (sequencer, mut sequencer_recv) = mpsc::unbounded_channel::<PublishRequest>();
+        let (sync_requests, mut sync_recv) = mpsc::unbounded_channel::<SyncRequest>();
+        let sync = SyncClient::new(sync_requests);
+        let unsafe_block_signer = self.unsafe_block_signer_sender.clone();
+        let unsafe_blocks = self.gossip.handler.block_sender.clone();
+        let (peer_commands, mut peer_command_recv) = mpsc::unbounded_channel::<PeerCommand>();
+        let peers = match (discovery_commands, discovery_stats.clone()) {
+            (Some(commands), Some(stats)) => {
+                PeerManager::new(peer_commands).with_discovery(commands, stats)
+            }
+            _ => PeerManager::new(peer_commands),
+        };
+        // Discovery hands out the static peers on its own, on every lookup round.
+        let static_peers =
+            if peer_recv.is_none() { std::mem::take(&mut self.static_peers) } else { Vec::new() };
+        let discovery_status = status_sender.clone();
+        let event_loop = async move {
+            for peer in static_peers {
+                self.gossip.dial_opt(Some(peer)).await;
+            }
+            let mut topic_check = tokio::time::interval(TIP_ONLY_CHECK_INTERVAL);
+
             loop {
                 select! {
-                    peer = peer_recv.recv() => {
+                    peer = recv_peer(&mut peer_recv) => {
+                        if peer.is_none() {
+                            // Discovery closes the channel when its task exits.
+                            peer_recv = None;
+                            if !*shutdown_recv.borrow() {
+                                tracing::error!("Peer discovery exited unexpectedly");
+                                _ = discovery_status.send(NetworkStatus::Failed(
+                                    "peer discovery exited".to_string(),
+                                ));
+                            }
+                        }
                         self.gossip.dial_opt(peer).await;
                     },
-                    event = self.gossip.select_next_some() => {
-                        self.gossip.handle_event(event);
+                    Some(addr) = dial_recv.recv() => {
+                        self.gossip.dial_opt(Some(addr)).await;
+                    },
+                    Some((envelope, result)) = publish_recv.recv() => {
+                        _ = result.send(self.gossip.publish(&envelope));
+                    },
+                    Some((envelope, result)) = sequencer_recv.recv() => {
+                        _ = result.send(self.gossip.publish_payload(envelope));
+                    },
+                    Some((peer, number, result)) = sync_recv.recv() => {
+                        self.gossip.request_payload(peer, number, result);
+                    },
+                    Some(command) = peer_command_recv.recv() => {
+                        self.gossip.handle_peer_command(command).await;
+                    },
+                    _ = topic_check.tick(), if self.gossip.tip_only.is_some() => {
+                        self.gossip.unsubscribe_obsolete_topics(unix_time());
+                    },
+                    events = self.gossip.next_events() => {
+                        self.gossip.handle_events(events);
+                    },
+                    _ = shutdown_recv.changed() => {
+                        tracing::info!("Shutting down network driver");
+                        break;
                     },
                 }
             }
+            // The swarm and its listeners are closed as the driver is dropped.
+        };
+        let gossip = tokio::spawn(async move {
+            match AssertUnwindSafe(event_loop).catch_unwind().await {
+                Ok(()) => {
+                    status_sender.send_if_modified(|status| {
+                        let running = *status == NetworkStatus::Running;
+                        if running {
+                            *status = NetworkStatus::Stopped;
+                        }
+                        running
+                    });
+                }
+                Err(panic) => {
+                    let message = panic_message(&*panic);
+                    tracing::error!("Network driver panicked: {}", message);
+                    _ = status_sender.send(NetworkStatus::Failed(message));
+                }
+            }
         });
 
-        Ok(())
+        Ok(NetworkHandle {
+            gossip,
+            discovery,
+            shutdown,
+            dialer,
+            injector,
+            sequencer,
+            sync,
+            peers,
+            unsafe_block_signer,
+            unsafe_blocks,
+            enr_updater,
+            discovery_stats,
+            node_info,
+            status,
+        })
+    }
+}
+
+/// The gossip of a chain served by a [NetworkDriver] besides its own.
+#[derive(Debug)]
+pub struct ChainGossip {
+    /// The chain ID.
+    pub chain_id: u64,
+    /// Channel to receive the unsafe blocks of the chain, in gossip order.
+    pub unsafe_block_recv: UnsafeBlockReceiver,
+    /// Channel to send the unsafe signer updates of the chain.
+    pub unsafe_block_signer_sender: watch::Sender<Address>,
+}
+
+/// The status of the networking tasks spawned by [NetworkDriver::start].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NetworkStatus {
+    /// The tasks are running.
+    Running,
+    /// The tasks were shut down through [NetworkHandle::shutdown].
+    Stopped,
+    /// A task panicked or exited unexpectedly, with the reason.
+    Failed(String),
+}
+
+impl fmt::Display for NetworkStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Running => write!(f, "running"),
+            Self::Stopped => write!(f, "stopped"),
+            Self::Failed(reason) => write!(f, "failed: {}", reason),
+        }
+    }
+}
+
+/// A handle to the tasks spawned by [NetworkDriver::start].
+///
+/// Dropping the handle shuts the tasks down too, but without waiting for them to exit;
+/// call [NetworkHandle::shutdown] then [NetworkHandle::join] instead.
+#[derive(Debug)]
+pub struct NetworkHandle {
+    /// The gossip event loop task.
+    pub gossip: JoinHandle<()>,
+    /// The peer discovery task, if discovery is enabled.
+    pub discovery: Option<JoinHandle<()>>,
+    /// Signals the gossip event loop to shut down.
+    pub shutdown: watch::Sender<bool>,
+    /// Requests the gossip event loop to dial a peer.
+    pub dialer: mpsc::UnboundedSender<Multiaddr>,
+    /// Injects unsafe payloads received out-of-band.
+    pub injector: PayloadInjector,
+    /// Requests the gossip event loop to sign and publish a payload as the sequencer.
+    pub sequencer: mpsc::UnboundedSender<PublishRequest>,
+    /// Requests unsafe payloads from peers, if `payload_by_number` is enabled.
+    pub sync: SyncClient,
+    /// Manages the peers of the gossip event loop, e.g. from the admin RPC.
+    pub peers: PeerManager,
+    /// Updates the unsafe block signer of the gossip handler, e.g. on a key rotation.
+    pub unsafe_block_signer: watch::Sender<Address>,
+    /// Delivers the received unsafe blocks to subscribers, see
+    /// [NetworkHandle::subscribe_unsafe_blocks].
+    pub unsafe_blocks: UnsafeBlockSender,
+    /// Updates the local ENR advertised by discovery, if discovery is enabled.
+    pub enr_updater: Option<EnrUpdater>,
+    /// The routing table statistics published by discovery, if discovery is enabled.
+    pub discovery_stats: Option<watch::Receiver<DiscoveryStats>>,
+    /// The identity and network configuration of the node.
+    pub node_info: NodeInfo,
+    /// The status of the networking tasks, updated when they exit.
+    pub status: watch::Receiver<NetworkStatus>,
+}
+
+impl NetworkHandle {
+    /// Signals the networking tasks to shut down, closing the swarm and the discovery
+    /// socket. Use [NetworkHandle::join] to wait for them to exit.
+    pub fn shutdown(&self) {
+        _ = self.shutdown.send(true);
+    }
+
+    /// Returns a new receiver of the unsafe blocks received from now on.
+    pub fn subscribe_unsafe_blocks(&self) -> broadcast::Receiver<ReceivedBlock> {
+        self.unsafe_blocks.subscribe()
+    }
+
+    /// Returns the current status of the networking tasks.
+    pub fn status(&self) -> NetworkStatus {
+        self.status.borrow().clone()
+    }
+
+    /// Waits until the networking tasks stop running, and returns their final status,
+    /// e.g. to shut the node down when networking fails.
+    pub async fn wait_for_exit(&self) -> NetworkStatus {
+        let mut status = self.status.clone();
+        match status.wait_for(|status| *status != NetworkStatus::Running).await {
+            Ok(status) => status.clone(),
+            Err(_) => NetworkStatus::Failed("network driver is not running".to_string()),
+        }
+    }
+
+    /// Dials the given peer from the running gossip event loop, e.g. a static peer
+    /// added while the node is running.
+    pub fn dial(&self, addr: Multiaddr) -> Result<()> {
+        self.dialer.send(addr).map_err(|_| eyre::eyre!("network driver is not running"))
+    }
+
+    /// Signs the given payload with the sequencer key and publishes it from the
+    /// running gossip event loop, see [NetworkDriver::publish_payload].
+    pub async fn publish_payload(&self, envelope: ExecutionPayloadEnvelope) -> Result<MessageId> {
+        let (sender, recv) = oneshot::channel();
+        self.sequencer
+            .send((envelope, sender))
+            .map_err(|_| eyre::eyre!("network driver is not running"))?;
+        recv.await.map_err(|_| eyre::eyre!("network driver is not running"))?
+    }
+
+    /// Returns `true` if any of the networking tasks has exited.
+    pub fn is_finished(&self) -> bool {
+        self.gossip.is_finished() || self.discovery.as_ref().is_some_and(|d| d.is_finished())
+    }
+
+    /// Waits for the networking tasks to exit, e.g. after [NetworkHandle::shutdown].
+    ///
+    /// Returns an error if a task panicked, was cancelled or failed.
+    pub async fn join(self) -> Result<()> {
+        self.gossip.await.map_err(|e| eyre::eyre!("network task failed: {}", e))?;
+        if let Some(discovery) = self.discovery {
+            discovery.await.map_err(|e| eyre::eyre!("discovery task failed: {}", e))?;
+        }
+        let status = self.status.borrow().clone();
+        match status {
+            NetworkStatus::Failed(reason) => Err(eyre::eyre!("network driver failed: {}", reason)),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Returns the message of a caught panic.
+fn panic_message(panic: &(dyn Any + Send)) -> String {
+    panic
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| panic.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic".to_string())
+}
+
+/// Returns the current unix time in seconds.
+fn unix_time() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_secs())
+}
+
+/// Receives the address of the next peer to dial from discovery, or waits forever
+/// if discovery is disabled.
+async fn recv_peer(recv: &mut Option<mpsc::Receiver<Multiaddr>>) -> Option<Multiaddr> {
+    match recv {
+        Some(recv) => recv.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_panic_message() {
+        let panic = std::panic::catch_unwind(|| panic!("boom {}", 1)).unwrap_err();
+        assert_eq!(panic_message(&*panic), "boom 1");
+        let panic = std::panic::catch_unwind(|| panic!("boom")).unwrap_err();
+        assert_eq!(panic_message(&*panic), "boom");
+    }
+
+    #[test]
+    fn test_network_status_display() {
+        assert_eq!(
+            NetworkStatus::Failed("peer discovery exited".into()).to_string(),
+            "failed: peer discovery exited"
+        );
     }
 }
@@ -1,31 +1,93 @@
 //! Network Behaviour Module.
 
+use std::collections::HashSet;
+
 use eyre::Result;
 use libp2p::{
-    gossipsub::{Config, IdentTopic, MessageAuthenticity},
-    swarm::NetworkBehaviour,
+    autonat,
+    gossipsub::{
+        Config, IdentTopic, IdentityTransform, MessageAuthenticity, TopicHash, TopicScoreParams,
+        TopicSubscriptionFilter,
+    },
+    identify,
+    identity::PublicKey,
+    request_response::{self, ProtocolSupport},
+    swarm::{behaviour::toggle::Toggle, NetworkBehaviour},
+    upnp,
 };
 
-use super::{event::Event, handler::Handler};
+use super::{event::Event, gate::ConnectionGater, handler::Handler, scoring::PeerScoring};
+use crate::sync::codec::{self, PayloadByNumberCodec};
 
 /// Specifies the [NetworkBehaviour] of the node
 #[derive(NetworkBehaviour)]
 #[behaviour(out_event = "Event")]
 pub struct Behaviour {
+    /// Enforces the connection limits and filters, if any.
+    pub gate: Toggle<ConnectionGater>,
     /// Responds to inbound pings and send outbound pings.
     pub ping: libp2p::ping::Behaviour,
     /// Enables gossipsub as the routing layer.
-    pub gossipsub: libp2p::gossipsub::Behaviour,
+    pub gossipsub: libp2p::gossipsub::Behaviour<IdentityTransform, TopicFilter>,
+    /// Serves and requests unsafe payloads over `payload_by_number`, if enabled.
+    pub sync: Toggle<request_response::Behaviour<PayloadByNumberCodec>>,
+    /// Exchanges listen and observed addresses with peers, if NAT traversal is enabled.
+    pub identify: Toggle<identify::Behaviour>,
+    /// Confirms the observed addresses are publicly reachable, if NAT traversal is enabled.
+    pub autonat: Toggle<autonat::Behaviour>,
+    /// Maps the listen ports on the gateway over UPnP, if enabled.
+    pub upnp: Toggle<upnp::tokio::Behaviour>,
+}
+
+/// The protocol version advertised over identify.
+pub const IDENTIFY_PROTOCOL_VERSION: &str = "/opstack/1.0.0";
+
+/// A gossipsub subscription filter that, when restricted, only tracks
+/// subscriptions to the topics the node handles.
+#[derive(Debug, Default, Clone)]
+pub struct TopicFilter(Option<HashSet<TopicHash>>);
+
+impl TopicFilter {
+    /// Creates a [TopicFilter] only allowing the given topics.
+    pub fn restricted(topics: HashSet<TopicHash>) -> Self {
+        Self(Some(topics))
+    }
+}
+
+impl TopicSubscriptionFilter for TopicFilter {
+    fn can_subscribe(&mut self, topic_hash: &TopicHash) -> bool {
+        self.0.as_ref().map_or(true, |topics| topics.contains(topic_hash))
+    }
 }
 
 impl Behaviour {
     /// Configures the swarm behaviors, subscribes to the gossip topics, and returns a new
     /// [Behaviour].
     pub fn new(cfg: Config, handlers: &[Box<dyn Handler>]) -> Result<Self> {
+        Self::build(cfg, handlers, TopicFilter::default())
+    }
+
+    /// Creates a new [Behaviour] for a private mesh, ignoring the subscriptions to
+    /// topics the node doesn't handle.
+    ///
+    /// The connections of the peers outside of the mesh are refused by the
+    /// [ConnectionGater], see [Behaviour::set_connection_gate].
+    pub fn restricted(cfg: Config, handlers: &[Box<dyn Handler>]) -> Result<Self> {
+        let topics = handlers.iter().flat_map(|handler| handler.topics()).collect();
+        Self::build(cfg, handlers, TopicFilter::restricted(topics))
+    }
+
+    /// Builds the [Behaviour], tracking the subscriptions allowed by the given filter.
+    fn build(cfg: Config, handlers: &[Box<dyn Handler>], filter: TopicFilter) -> Result<Self> {
         let ping = libp2p::ping::Behaviour::default();
 
-        let mut gossipsub = libp2p::gossipsub::Behaviour::new(MessageAuthenticity::Anonymous, cfg)
-            .map_err(|_| eyre::eyre!("gossipsub behaviour creation failed"))?;
+        let mut gossipsub = libp2p::gossipsub::Behaviour::new_with_subscription_filter(
+            MessageAuthenticity::Anonymous,
+            cfg,
+            None,
+            filter,
+        )
+        .map_err(|_| eyre::eyre!("gossipsub behaviour creation failed"))?;
 
         handlers
             .iter()
@@ -41,7 +103,87 @@ impl Behaviour {
             })
             .collect::<Result<Vec<bool>>>()?;
 
-        Ok(Self { ping, gossipsub })
+        Ok(Self {
+            gate: None.into(),
+            ping,
+            gossipsub,
+            sync: None.into(),
+            identify: None.into(),
+            autonat: None.into(),
+            upnp: None.into(),
+        })
+    }
+
+    /// Replaces the ping behaviour with one using the given [libp2p::ping::Config].
+    pub fn set_ping_config(&mut self, cfg: libp2p::ping::Config) {
+        self.ping = libp2p::ping::Behaviour::new(cfg);
+    }
+
+    /// Enforces the given connection limits and filters on the connections of the swarm.
+    pub fn set_connection_gate(&mut self, gate: ConnectionGater) {
+        self.gate = Some(gate).into();
+    }
+
+    /// Enables the `payload_by_number` protocol of the given chain, both to serve
+    /// payloads to peers and to request payloads from them.
+    pub fn enable_sync(&mut self, chain_id: u64) {
+        let protocols = [(codec::protocol(chain_id), ProtocolSupport::Full)];
+        let sync = request_response::Behaviour::new(protocols, request_response::Config::default());
+        self.sync = Some(sync).into();
+    }
+
+    /// Enables the detection of the public address of the node.
+    ///
+    /// Identify learns the addresses peers observe the node at, and AutoNAT asks peers to
+    /// dial them back, confirming the reachable ones as external addresses of the swarm.
+    /// If `upnp` is set, the listen ports are also mapped on the gateway, whose public
+    /// address is then confirmed directly.
+    pub fn enable_nat_traversal(&mut self, public_key: PublicKey, upnp: bool) {
+        let peer_id = public_key.to_peer_id();
+        let identify = identify::Config::new(IDENTIFY_PROTOCOL_VERSION.to_string(), public_key);
+        self.identify = Some(identify::Behaviour::new(identify)).into();
+        self.autonat = Some(autonat::Behaviour::new(peer_id, autonat::Config::default())).into();
+        if upnp {
+            self.upnp = Some(upnp::tokio::Behaviour::default()).into();
+        }
+    }
+
+    /// Unsubscribes from the given topic, returning `true` if the node was subscribed.
+    pub fn unsubscribe(&mut self, topic: &IdentTopic) -> Result<bool> {
+        self.gossipsub
+            .unsubscribe(topic)
+            .map_err(|e| eyre::eyre!("failed to unsubscribe from {}: {:?}", topic, e))
+    }
+
+    /// Activates gossipsub peer scoring of the given blocks topics.
+    ///
+    /// Must be called before [Self::set_topic_params], so that the per-topic
+    /// overrides apply on top of the scoring parameters.
+    pub fn enable_peer_scoring(
+        &mut self,
+        scoring: &PeerScoring,
+        topics: impl IntoIterator<Item = TopicHash>,
+    ) -> Result<()> {
+        self.gossipsub
+            .with_peer_score(scoring.peer_score_params(topics), scoring.thresholds())
+            .map_err(|e| eyre::eyre!("failed to enable peer scoring: {}", e))
+    }
+
+    /// Overrides the scoring parameters of the given topics.
+    ///
+    /// Gossipsub only supports per-topic scoring parameters (mesh message delivery
+    /// expectations, invalid message penalties, topic weights); mesh degree and
+    /// heartbeat settings remain global to the [Config].
+    ///
+    /// Fails if peer scoring is not enabled, see [Self::enable_peer_scoring].
+    pub fn set_topic_params(&mut self, params: Vec<(IdentTopic, TopicScoreParams)>) -> Result<()> {
+        for (topic, topic_params) in params {
+            self.gossipsub
+                .set_topic_params(topic, topic_params)
+                .map_err(|e| eyre::eyre!("failed to set topic params: {}", e))?;
+        }
+
+        Ok(())
     }
 }
 
@@ -50,13 +192,14 @@ mod tests {
     use super::*;
     use crate::gossip::{config, handler::BlockHandler};
     use alloy::primitives::Address;
-    use libp2p::gossipsub::{IdentTopic, TopicHash};
+    use libp2p::gossipsub::IdentTopic;
 
     fn zero_topics() -> Vec<TopicHash> {
         vec![
             IdentTopic::new("/optimism/0/0/blocks").hash(),
             IdentTopic::new("/optimism/0/1/blocks").hash(),
             IdentTopic::new("/optimism/0/2/blocks").hash(),
+            IdentTopic::new("/optimism/0/3/blocks").hash(),
         ]
     }
 
@@ -78,4 +221,72 @@ mod tests {
         topics.sort();
         assert_eq!(topics, zero_topics());
     }
+
+    #[test]
+    fn test_behaviour_restricted() {
+        let cfg = config::default_config_builder().build().expect("Failed to build default config");
+        let (_, recv) = tokio::sync::watch::channel(Address::default());
+        let (block_handler, _) = BlockHandler::new(0, recv);
+        let handlers: Vec<Box<dyn Handler>> = vec![Box::new(block_handler)];
+        let behaviour = Behaviour::restricted(cfg, &handlers).unwrap();
+        let mut topics = behaviour.gossipsub.topics().cloned().collect::<Vec<TopicHash>>();
+        topics.sort();
+        assert_eq!(topics, zero_topics());
+    }
+
+    #[test]
+    fn test_behaviour_peer_scoring() {
+        let cfg = config::default_config_builder().build().expect("Failed to build default config");
+        let mut behaviour = Behaviour::new(cfg, &[]).unwrap();
+        let v3 = IdentTopic::new("/optimism/0/2/blocks");
+        behaviour.enable_peer_scoring(&PeerScoring::default(), [v3.hash()]).unwrap();
+        assert_eq!(behaviour.gossipsub.get_topic_params(&v3).unwrap().topic_weight, 0.8);
+        assert!(behaviour.enable_peer_scoring(&PeerScoring::default(), []).is_err());
+    }
+
+    #[test]
+    fn test_behaviour_enable_sync() {
+        let cfg = config::default_config_builder().build().expect("Failed to build default config");
+        let mut behaviour = Behaviour::new(cfg, &[]).unwrap();
+        assert!(!behaviour.sync.is_enabled());
+        behaviour.enable_sync(10);
+        assert!(behaviour.sync.is_enabled());
+    }
+
+    #[test]
+    fn test_behaviour_enable_nat_traversal() {
+        let cfg = config::default_config_builder().build().expect("Failed to build default config");
+        let mut behaviour = Behaviour::new(cfg, &[]).unwrap();
+        let keypair = libp2p::identity::Keypair::generate_secp256k1();
+        behaviour.enable_nat_traversal(keypair.public(), false);
+        assert!(behaviour.identify.is_enabled());
+        assert!(behaviour.autonat.is_enabled());
+        assert!(!behaviour.upnp.is_enabled());
+    }
+
+    #[test]
+    fn test_topic_filter() {
+        let allowed = IdentTopic::new("/optimism/0/0/blocks").hash();
+        let other = IdentTopic::new("/optimism/1/0/blocks").hash();
+        let mut filter = TopicFilter::restricted(HashSet::from([allowed.clone()]));
+        assert!(filter.can_subscribe(&allowed));
+        assert!(!filter.can_subscribe(&other));
+        assert!(TopicFilter::default().can_subscribe(&other));
+    }
+
+    #[test]
+    fn test_behaviour_topic_params() {
+        let cfg = config::default_config_builder().build().expect("Failed to build default config");
+        let mut behaviour = Behaviour::new(cfg, &[]).unwrap();
+        let v1 = IdentTopic::new("/optimism/0/0/blocks");
+        let v3 = IdentTopic::new("/optimism/0/2/blocks");
+        let params = TopicScoreParams { topic_weight: 0.2, ..Default::default() };
+        // Peer scoring is not enabled implicitly.
+        assert!(behaviour.set_topic_params(vec![(v3.clone(), params.clone())]).is_err());
+
+        behaviour.enable_peer_scoring(&PeerScoring::default(), [v1.hash()]).unwrap();
+        behaviour.set_topic_params(vec![(v3.clone(), params)]).unwrap();
+        assert_eq!(behaviour.gossipsub.get_topic_params(&v3).unwrap().topic_weight, 0.2);
+        assert_eq!(behaviour.gossipsub.get_topic_params(&v1).unwrap().topic_weight, 0.8);
+    }
 }
@@ -0,0 +1,232 @@
+//! Ordering and deduplication of unsafe blocks received through gossip.
+
+use crate::gossip::{channel::UnsafeBlockReceiver, handler::ReceivedBlock};
+use std::{collections::BTreeMap, ops::RangeInclusive};
+use tokio::sync::mpsc;
+use tracing::{debug, warn};
+
+/// The default number of out-of-order blocks held by an [UnsafeBlockBuffer].
+pub const DEFAULT_UNSAFE_BUFFER_DEPTH: usize = 64;
+
+/// Turns the unsafe blocks received through gossip, in gossip order and with possible
+/// duplicates and gaps, into a stream of contiguous blocks in ascending order.
+///
+/// Blocks ahead of the next expected block are held until the gap before them is
+/// filled, e.g. by a `payload_by_number` request for the [UnsafeBlockBuffer::missing]
+/// blocks. If more than `depth` blocks are held, the furthest ahead are dropped.
+#[derive(Debug)]
+pub struct UnsafeBlockBuffer {
+    /// The number of the next block to emit, once the first block is received.
+    next: Option<u64>,
+    /// The blocks received ahead of the next block, by number.
+    pending: BTreeMap<u64, ReceivedBlock>,
+    /// The maximum number of held blocks.
+    depth: usize,
+}
+
+impl Default for UnsafeBlockBuffer {
+    fn default() -> Self {
+        Self::new(DEFAULT_UNSAFE_BUFFER_DEPTH)
+    }
+}
+
+impl UnsafeBlockBuffer {
+    /// Creates a new [UnsafeBlockBuffer], holding at most `depth` out-of-order blocks.
+    ///
+    /// The stream starts at the first received block.
+    pub fn new(depth: usize) -> Self {
+        Self { next: None, pending: BTreeMap::new(), depth: depth.max(1) }
+    }
+
+    /// Starts the stream at the given block number, e.g. the block after the current
+    /// unsafe head.
+    pub fn with_next(mut self, number: u64) -> Self {
+        self.next = Some(number);
+        self
+    }
+
+    /// Returns the number of the next block to emit, if known.
+    pub const fn next_number(&self) -> Option<u64> {
+        self.next
+    }
+
+    /// Returns the number of held out-of-order blocks.
+    pub fn len(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Returns `true` if no out-of-order blocks are held.
+    pub fn is_empty(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Returns the range of blocks missing before the first held block, if any.
+    pub fn missing(&self) -> Option<RangeInclusive<u64>> {
+        let next = self.next?;
+        let first = *self.pending.keys().next()?;
+        Some(next..=first - 1)
+    }
+
+    /// Restarts the stream at the given block number, e.g. after the unsafe chain was
+    /// reorged or resynced. Held blocks before it are dropped.
+    pub fn reset(&mut self, number: u64) {
+        self.pending = self.pending.split_off(&number);
+        self.next = Some(number);
+    }
+
+    /// Adds a received block to the buffer, and returns the blocks that are now
+    /// contiguous with the previously emitted ones, in ascending order.
+    ///
+    /// Blocks that were already emitted or are already held are dropped. A held block
+    /// is replaced by a different block with the same number, as the sequencer may
+    /// have reorged the unsafe chain.
+    pub fn insert(&mut self, block: ReceivedBlock) -> Vec<ReceivedBlock> {
+        let payload = &block.envelope.payload;
+        let (number, hash) = (payload.block_number, payload.block_hash);
+        let next = *self.next.get_or_insert(number);
+        if number < next {
+            debug!("Dropping unsafe block {} ({}), already emitted", number, hash);
+            metrics::counter!("hera_unsafe_buffer_dropped_total", "reason" => "stale").increment(1);
+            return Vec::new();
+        }
+
+        match self.pending.get(&number).map(|held| held.envelope.payload.block_hash) {
+            Some(held) if held == hash => {
+                metrics::counter!("hera_unsafe_buffer_dropped_total", "reason" => "duplicate")
+                    .increment(1);
+                return Vec::new();
+            }
+            Some(held) => {
+                warn!("Replacing held unsafe block {} ({}) with {}", number, held, hash);
+                metrics::counter!("hera_unsafe_buffer_dropped_total", "reason" => "replaced")
+                    .increment(1);
+            }
+            None => {}
+        }
+        self.pending.insert(number, block);
+
+        let mut ready = Vec::new();
+        self.drain_ready(&mut ready);
+        while self.pending.len() > self.depth {
+            if let Some((number, _)) = self.pending.pop_last() {
+                debug!("Dropping unsafe block {}, the buffer is full", number);
+                metrics::counter!("hera_unsafe_buffer_dropped_total", "reason" => "full")
+                    .increment(1);
+            }
+        }
+        metrics::gauge!("hera_unsafe_buffer_size").set(self.pending.len() as f64);
+        ready
+    }
+
+    /// Moves the held blocks that are contiguous with the emitted ones to `ready`.
+    fn drain_ready(&mut self, ready: &mut Vec<ReceivedBlock>) {
+        let Some(next) = self.next.as_mut() else {
+            return;
+        };
+        while let Some(block) = self.pending.remove(next) {
+            ready.push(block);
+            *next += 1;
+        }
+    }
+
+    /// Spawns a task that orders the blocks of the given receiver, e.g.
+    /// [crate::driver::NetworkDriver::unsafe_block_recv], and returns the receiver of
+    /// the contiguous stream.
+    ///
+    /// The task exits once either channel is closed.
+    pub fn spawn(
+        mut self,
+        mut blocks: UnsafeBlockReceiver,
+    ) -> mpsc::UnboundedReceiver<ReceivedBlock> {
+        let (sender, recv) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            while let Some(block) = blocks.recv().await {
+                for block in self.insert(block) {
+                    if sender.send(block).is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+        recv
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{
+        envelope::ExecutionPayloadEnvelope,
+        payload::{ExecutionPayloadV1SSZ, PayloadHash},
+    };
+    use alloy::primitives::{Signature, B256, U256};
+    use kona_primitives::L2ExecutionPayload;
+    use std::time::Instant;
+    use tracing::Span;
+
+    fn block(number: u64, fork: u8) -> ReceivedBlock {
+        let mut payload = L2ExecutionPayload::from(ExecutionPayloadV1SSZ {
+            block_number: number,
+            ..Default::default()
+        });
+        payload.block_hash = B256::with_last_byte(number as u8 + fork);
+        let envelope = ExecutionPayloadEnvelope {
+            payload,
+            signature: Signature::from_rs_and_parity(U256::from(1), U256::from(2), false).unwrap(),
+            hash: PayloadHash::default(),
+            parent_beacon_block_root: None,
+            withdrawals_root: None,
+        };
+        ReceivedBlock { envelope, span: Span::none(), received_at: Instant::now() }
+    }
+
+    fn numbers(blocks: Vec<ReceivedBlock>) -> Vec<u64> {
+        blocks.iter().map(|b| b.envelope.payload.block_number).collect()
+    }
+
+    #[test]
+    fn test_orders_and_dedups() {
+        let mut buffer = UnsafeBlockBuffer::default();
+        assert_eq!(numbers(buffer.insert(block(10, 0))), vec![10]);
+        assert!(buffer.insert(block(10, 0)).is_empty());
+        assert!(buffer.insert(block(12, 0)).is_empty());
+        assert!(buffer.insert(block(13, 0)).is_empty());
+        assert!(buffer.insert(block(13, 0)).is_empty());
+        assert_eq!(buffer.len(), 2);
+        assert_eq!(buffer.missing(), Some(11..=11));
+
+        assert_eq!(numbers(buffer.insert(block(11, 0))), vec![11, 12, 13]);
+        assert!(buffer.is_empty());
+        assert_eq!(buffer.next_number(), Some(14));
+        assert!(buffer.insert(block(9, 0)).is_empty());
+    }
+
+    #[test]
+    fn test_replaces_held_block() {
+        let mut buffer = UnsafeBlockBuffer::default().with_next(1);
+        buffer.insert(block(2, 0));
+        buffer.insert(block(2, 100));
+        let ready = buffer.insert(block(1, 0));
+        assert_eq!(ready[1].envelope.payload.block_hash, B256::with_last_byte(102));
+    }
+
+    #[test]
+    fn test_drops_furthest_blocks_when_full() {
+        let mut buffer = UnsafeBlockBuffer::new(2).with_next(1);
+        for number in [4, 3, 5, 2] {
+            assert!(buffer.insert(block(number, 0)).is_empty());
+        }
+        assert_eq!(buffer.len(), 2);
+        assert_eq!(numbers(buffer.insert(block(1, 0))), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn test_reset() {
+        let mut buffer = UnsafeBlockBuffer::default().with_next(1);
+        buffer.insert(block(3, 0));
+        buffer.insert(block(6, 0));
+        buffer.reset(5);
+        assert_eq!(buffer.len(), 1);
+        assert_eq!(numbers(buffer.insert(block(5, 0))), vec![5, 6]);
+    }
+}
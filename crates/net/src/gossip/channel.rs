@@ -0,0 +1,171 @@
+//! Bounded delivery of the received unsafe blocks to their consumers.
+
+use crate::gossip::handler::ReceivedBlock;
+use std::{
+    future::poll_fn,
+    sync::{Arc, Mutex, MutexGuard, PoisonError, Weak},
+};
+use tokio::sync::{
+    broadcast,
+    mpsc::{
+        self,
+        error::{TryRecvError, TrySendError},
+    },
+};
+
+/// The default number of received blocks buffered until they are consumed.
+pub const DEFAULT_UNSAFE_BLOCK_CAPACITY: usize = 256;
+
+/// Creates a bounded channel of unsafe blocks, buffering up to `capacity` blocks.
+///
+/// When the consumer falls behind, the oldest buffered block is dropped to make room for
+/// the newest one, since a fresher unsafe head is more useful than a stale one. The
+/// blocks can also be broadcast to any number of subscribers, see
+/// [UnsafeBlockSender::subscribe].
+pub fn unsafe_block_channel(capacity: usize) -> (UnsafeBlockSender, UnsafeBlockReceiver) {
+    let capacity = capacity.max(1);
+    let (sender, receiver) = mpsc::channel(capacity);
+    let receiver = Arc::new(Mutex::new(receiver));
+    let (broadcast, _) = broadcast::channel(capacity);
+    let sender = UnsafeBlockSender { sender, receiver: Arc::downgrade(&receiver), broadcast };
+    (sender, UnsafeBlockReceiver { receiver })
+}
+
+/// The sending half of an [unsafe_block_channel], shared by the clones of the block
+/// handler.
+#[derive(Debug, Clone)]
+pub struct UnsafeBlockSender {
+    /// The bounded channel to the [UnsafeBlockReceiver].
+    sender: mpsc::Sender<ReceivedBlock>,
+    /// The receiving end of the channel, to drop the oldest block from when it is full.
+    /// Closed once the [UnsafeBlockReceiver] is dropped.
+    receiver: Weak<Mutex<mpsc::Receiver<ReceivedBlock>>>,
+    /// The broadcast channel to the subscribers.
+    broadcast: broadcast::Sender<ReceivedBlock>,
+}
+
+impl UnsafeBlockSender {
+    /// Sends the given block to the receiver and the subscribers, dropping the oldest
+    /// buffered block if the receiver fell behind.
+    pub fn send(&self, block: ReceivedBlock) {
+        if self.broadcast.receiver_count() > 0 {
+            // Lagging subscribers skip the oldest blocks on their own.
+            _ = self.broadcast.send(block.clone());
+        }
+        let block = match self.sender.try_send(block) {
+            Err(TrySendError::Full(block)) => block,
+            // Sent, or nobody is receiving the blocks.
+            _ => return,
+        };
+        let dropped = match self.receiver.upgrade() {
+            Some(receiver) => lock(&receiver).try_recv().is_ok(),
+            None => return,
+        };
+        if dropped && self.sender.try_send(block).is_ok() {
+            tracing::debug!("unsafe block channel full, dropped the oldest block");
+            metrics::counter!("hera_unsafe_blocks_dropped_total", "block" => "oldest").increment(1);
+        } else {
+            tracing::debug!("unsafe block channel full, dropped the newest block");
+            metrics::counter!("hera_unsafe_blocks_dropped_total", "block" => "newest").increment(1);
+        }
+    }
+
+    /// Returns a new receiver of the blocks sent from now on.
+    ///
+    /// Subscribers that fall behind by more than the capacity of the channel skip the
+    /// oldest blocks, and are told how many with [broadcast::error::RecvError::Lagged].
+    pub fn subscribe(&self) -> broadcast::Receiver<ReceivedBlock> {
+        self.broadcast.subscribe()
+    }
+}
+
+/// The receiving half of an [unsafe_block_channel].
+#[derive(Debug)]
+pub struct UnsafeBlockReceiver {
+    /// The bounded channel from the [UnsafeBlockSender], shared with it to drop the
+    /// oldest blocks.
+    receiver: Arc<Mutex<mpsc::Receiver<ReceivedBlock>>>,
+}
+
+impl UnsafeBlockReceiver {
+    /// Waits for the next block.
+    ///
+    /// Returns `None` once all the senders are dropped and the buffered blocks consumed.
+    pub async fn recv(&mut self) -> Option<ReceivedBlock> {
+        // The lock is only held while polling, so that senders can still drop the
+        // oldest block while the receiver waits.
+        poll_fn(|cx| lock(&self.receiver).poll_recv(cx)).await
+    }
+
+    /// Returns the next block if one is buffered, without waiting.
+    pub fn try_recv(&mut self) -> Result<ReceivedBlock, TryRecvError> {
+        lock(&self.receiver).try_recv()
+    }
+
+    /// Returns the number of buffered blocks.
+    pub fn len(&self) -> usize {
+        lock(&self.receiver).len()
+    }
+
+    /// Returns `true` if no block is buffered.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Locks the given receiver, which stays consistent even if a holder of the lock panicked.
+fn lock<T>(receiver: &Mutex<T>) -> MutexGuard<'_, T> {
+    receiver.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{
+        envelope::ExecutionPayloadEnvelope,
+        payload::{ExecutionPayloadV1SSZ, PayloadHash},
+    };
+    use alloy::primitives::{Signature, U256};
+    use kona_primitives::L2ExecutionPayload;
+    use std::time::Instant;
+    use tracing::Span;
+
+    fn block(number: u64) -> ReceivedBlock {
+        let payload = L2ExecutionPayload::from(ExecutionPayloadV1SSZ {
+            block_number: number,
+            ..Default::default()
+        });
+        let envelope = ExecutionPayloadEnvelope {
+            payload,
+            signature: Signature::from_rs_and_parity(U256::from(1), U256::from(2), false).unwrap(),
+            hash: PayloadHash::default(),
+            parent_beacon_block_root: None,
+            withdrawals_root: None,
+        };
+        ReceivedBlock { envelope, span: Span::none(), received_at: Instant::now() }
+    }
+
+    #[tokio::test]
+    async fn test_drop_oldest() {
+        let (sender, mut receiver) = unsafe_block_channel(2);
+        for number in 1..=3 {
+            sender.send(block(number));
+        }
+        assert_eq!(receiver.len(), 2);
+        assert_eq!(receiver.recv().await.unwrap().envelope.payload.block_number, 2);
+        assert_eq!(receiver.recv().await.unwrap().envelope.payload.block_number, 3);
+        assert!(receiver.try_recv().is_err());
+
+        drop(sender);
+        assert!(receiver.recv().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_subscribe() {
+        let (sender, receiver) = unsafe_block_channel(2);
+        let mut subscriber = sender.subscribe();
+        drop(receiver);
+        sender.send(block(1));
+        assert_eq!(subscriber.recv().await.unwrap().envelope.payload.block_number, 1);
+    }
+}
@@ -1,10 +1,12 @@
 //! Gossipsub Configuration
 
 use lazy_static::lazy_static;
-use libp2p::gossipsub::{Config, ConfigBuilder, ConfigBuilderError, Message, MessageId};
+use libp2p::gossipsub::{
+    Config, ConfigBuilder, ConfigBuilderError, Message, MessageId, TopicScoreParams,
+};
 use openssl::sha::sha256;
 use snap::raw::Decoder;
-use std::time::Duration;
+use std::{collections::BTreeMap, time::Duration};
 
 ////////////////////////////////////////////////////////////////////////////////////////////////
 // GossipSub Constants
@@ -14,6 +16,33 @@ use std::time::Duration;
 /// Limits the total size of gossip RPC containers as well as decompressed individual messages.
 pub const MAX_GOSSIP_SIZE: usize = 10 * (1 << 20);
 
+/// The maximum gossip size of the `blocks/v1` topic (pre-Canyon), the spec's limit.
+pub const MAX_GOSSIP_SIZE_V1: usize = MAX_GOSSIP_SIZE;
+
+/// The maximum gossip size of the `blocks/v2` topic (Canyon and Delta), whose payloads
+/// carry their withdrawals within the spec's limit.
+pub const MAX_GOSSIP_SIZE_V2: usize = MAX_GOSSIP_SIZE;
+
+/// The maximum gossip size of the `blocks/v3` topic (Ecotone to Holocene): the spec's limit
+/// plus the parent beacon block root which the envelope carries beside the payload.
+pub const MAX_GOSSIP_SIZE_V3: usize = MAX_GOSSIP_SIZE + 32;
+
+/// The maximum gossip size of the `blocks/v4` topic (Isthmus onwards): the `blocks/v3`
+/// limit plus the withdrawals root which the Isthmus payloads carry.
+pub const MAX_GOSSIP_SIZE_V4: usize = MAX_GOSSIP_SIZE_V3 + 32;
+
+/// The maximum gossip size of each blocks topic, by topic version.
+///
+/// Each blocks topic version corresponds to a hardfork, so the limits can be raised for
+/// newer forks as block gas limits and payloads grow, without loosening them for the
+/// legacy topics.
+pub const DEFAULT_MAX_GOSSIP_SIZES: MaxGossipSizes = MaxGossipSizes {
+    v1: MAX_GOSSIP_SIZE_V1,
+    v2: MAX_GOSSIP_SIZE_V2,
+    v3: MAX_GOSSIP_SIZE_V3,
+    v4: MAX_GOSSIP_SIZE_V4,
+};
+
 /// The minimum gossip size.
 /// Used to make sure that there is at least some data to validate the signature against.
 pub const MIN_GOSSIP_SIZE: usize = 66;
@@ -39,17 +68,89 @@ pub const DEFAULT_MESH_DHI: usize = 12;
 /// The default mesh D lazy.
 pub const DEFAULT_MESH_DLAZY: usize = 6;
 
+/// The default gossip heartbeat interval.
+pub const DEFAULT_GOSSIP_HEARTBEAT: Duration = Duration::from_millis(500);
+
+/// The number of heartbeats the message IDs are remembered for, to deduplicate messages.
+pub const SEEN_MESSAGES_HEARTBEATS: u32 = 130;
+
+/// The domain of the message IDs of the messages with a valid snappy compression.
+pub const MESSAGE_DOMAIN_VALID_SNAPPY: [u8; 4] = [1, 0, 0, 0];
+
+/// The domain of the message IDs of the messages with an invalid snappy compression.
+pub const MESSAGE_DOMAIN_INVALID_SNAPPY: [u8; 4] = [0, 0, 0, 0];
+
+/// The maximum decompressed size of a gossiped block, for each blocks topic version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MaxGossipSizes {
+    /// The maximum size on the `blocks/v1` topic.
+    pub v1: usize,
+    /// The maximum size on the `blocks/v2` topic.
+    pub v2: usize,
+    /// The maximum size on the `blocks/v3` topic.
+    pub v3: usize,
+    /// The maximum size on the `blocks/v4` topic.
+    pub v4: usize,
+}
+
+impl Default for MaxGossipSizes {
+    fn default() -> Self {
+        DEFAULT_MAX_GOSSIP_SIZES
+    }
+}
+
+impl MaxGossipSizes {
+    /// Returns the same maximum size for every topic version.
+    pub const fn uniform(size: usize) -> Self {
+        Self { v1: size, v2: size, v3: size, v4: size }
+    }
+
+    /// Sets the maximum size of the given topic version (`0` for v1, `1` for v2, ...).
+    /// Unknown versions are ignored.
+    pub fn set_version(&mut self, version: u8, size: usize) {
+        match version {
+            0 => self.v1 = size,
+            1 => self.v2 = size,
+            2 => self.v3 = size,
+            3 => self.v4 = size,
+            _ => {}
+        }
+    }
+
+    /// Returns the maximum size for the given topic version (`0` for v1, `1` for v2, ...).
+    pub const fn by_version(&self, version: u8) -> Option<usize> {
+        match version {
+            0 => Some(self.v1),
+            1 => Some(self.v2),
+            2 => Some(self.v3),
+            3 => Some(self.v4),
+            _ => None,
+        }
+    }
+
+    /// Returns the largest maximum size across all topic versions.
+    pub fn max(&self) -> usize {
+        self.v1.max(self.v2).max(self.v3).max(self.v4)
+    }
+
+    /// Returns the maximum size of a snappy-compressed gossip message,
+    /// used to bound the gossipsub transmit size.
+    pub fn max_transmit_size(&self) -> usize {
+        snap::raw::max_compress_len(self.max())
+    }
+}
+
 ////////////////////////////////////////////////////////////////////////////////////////////////
 // Duration Constants
 ////////////////////////////////////////////////////////////////////////////////////////////////
 
 lazy_static! {
     /// The gossip heartbeat.
-    pub static ref GOSSIP_HEARTBEAT: Duration = Duration::from_millis(500);
+    pub static ref GOSSIP_HEARTBEAT: Duration = DEFAULT_GOSSIP_HEARTBEAT;
 
     /// The seen messages TTL.
     /// Limits the duration that message IDs are remembered for gossip deduplication purposes.
-    pub static ref SEEN_MESSAGES_TTL: Duration = 130 * *GOSSIP_HEARTBEAT;
+    pub static ref SEEN_MESSAGES_TTL: Duration = SEEN_MESSAGES_HEARTBEATS * *GOSSIP_HEARTBEAT;
 
     /// The pper score inspect frequency.
     /// The frequency at which peer scores are inspected.
@@ -60,35 +161,93 @@ lazy_static! {
 // Config Building
 ////////////////////////////////////////////////////////////////////////////////////////////////
 
-/// Builds the default gossipsub configuration.
+/// The gossipsub parameters which must match the other OP-stack nodes for the mesh to
+/// form, defaulting to op-node's.
 ///
-/// Notable defaults:
-/// - flood_publish: false (call `.flood_publish(true)` on the [ConfigBuilder] to enable)
-/// - backoff_slack: 1
-/// - peer exchange is disabled
-/// - maximum byte size for gossip messages: 2048 bytes
+/// Gossipsub has no per-topic mesh or heartbeat settings, so the mesh degrees and the
+/// heartbeat apply to all the topics: only the scoring of each topic can be tuned, with
+/// [GossipParams::topic_scores].
+#[derive(Debug, Clone)]
+pub struct GossipParams {
+    /// The target number of peers in the mesh of each topic (`D`).
+    pub mesh_d: usize,
+    /// The number of mesh peers under which more are grafted (`D_lo`).
+    pub mesh_dlo: usize,
+    /// The number of mesh peers over which some are pruned (`D_hi`).
+    pub mesh_dhi: usize,
+    /// The number of peers gossiped to outside the mesh (`D_lazy`).
+    pub mesh_dlazy: usize,
+    /// The interval of the gossipsub heartbeat, from which the seen messages TTL derives.
+    pub heartbeat_interval: Duration,
+    /// The maximum size of a gossipsub message, as compressed on the wire.
+    pub max_message_size: usize,
+    /// Whether published messages are sent to all the peers of the topic, rather than to
+    /// the mesh peers only.
+    pub flood_publish: bool,
+    /// Computes the ID of a message, [compute_message_id] by default.
+    pub message_id_fn: fn(&Message) -> MessageId,
+    /// The scoring parameters of the blocks topics, by topic version (`0` for v1), which
+    /// override the ones of the peer scoring. Requires peer scoring.
+    pub topic_scores: BTreeMap<u8, TopicScoreParams>,
+}
+
+impl Default for GossipParams {
+    fn default() -> Self {
+        Self {
+            mesh_d: DEFAULT_MESH_D,
+            mesh_dlo: DEFAULT_MESH_DLO,
+            mesh_dhi: DEFAULT_MESH_DHI,
+            mesh_dlazy: DEFAULT_MESH_DLAZY,
+            heartbeat_interval: DEFAULT_GOSSIP_HEARTBEAT,
+            max_message_size: MAX_GOSSIP_SIZE,
+            flood_publish: false,
+            message_id_fn: compute_message_id,
+            topic_scores: BTreeMap::new(),
+        }
+    }
+}
+
+impl GossipParams {
+    /// Returns a [ConfigBuilder] with the parameters set, on top of op-node's other
+    /// settings:
+    /// - backoff_slack: 1
+    /// - peer exchange is disabled
+    /// - messages must be unsigned and without author, sequence number or key
+    pub fn config_builder(&self) -> ConfigBuilder {
+        let mut builder = ConfigBuilder::default();
+        builder
+            .mesh_n(self.mesh_d)
+            .mesh_n_low(self.mesh_dlo)
+            .mesh_n_high(self.mesh_dhi)
+            .gossip_lazy(self.mesh_dlazy)
+            .heartbeat_interval(self.heartbeat_interval)
+            .fanout_ttl(Duration::from_secs(24))
+            .history_length(12)
+            .history_gossip(3)
+            .duplicate_cache_time(SEEN_MESSAGES_HEARTBEATS * self.heartbeat_interval)
+            .max_transmit_size(self.max_message_size)
+            .flood_publish(self.flood_publish)
+            .validation_mode(libp2p::gossipsub::ValidationMode::Anonymous)
+            .validate_messages()
+            .message_id_fn(self.message_id_fn);
+
+        builder
+    }
+
+    /// Builds the gossipsub [Config] with the parameters.
+    pub fn build(&self) -> Result<Config, ConfigBuilderError> {
+        self.config_builder().build()
+    }
+}
+
+/// Builds the default gossipsub configuration, see [GossipParams::config_builder].
 ///
 /// # Returns
 ///
 /// A [`ConfigBuilder`] with the default gossipsub configuration already set.
 /// Call `.build()` on the returned builder to get the final [libp2p::gossipsub::Config].
 pub fn default_config_builder() -> ConfigBuilder {
-    let mut builder = ConfigBuilder::default();
-    builder
-        .mesh_n(DEFAULT_MESH_D)
-        .mesh_n_low(DEFAULT_MESH_DLO)
-        .mesh_n_high(DEFAULT_MESH_DHI)
-        .gossip_lazy(DEFAULT_MESH_DLAZY)
-        .heartbeat_interval(*GOSSIP_HEARTBEAT)
-        .fanout_ttl(Duration::from_secs(24))
-        .history_length(12)
-        .history_gossip(3)
-        .duplicate_cache_time(Duration::from_secs(65))
-        .validation_mode(libp2p::gossipsub::ValidationMode::None)
-        .validate_messages()
-        .message_id_fn(compute_message_id);
-
-    builder
+    GossipParams::default().config_builder()
 }
 
 /// Returns the default [Config] for gossipsub.
@@ -96,22 +255,83 @@ pub fn default_config() -> Result<Config, ConfigBuilderError> {
     default_config_builder().build()
 }
 
-/// Computes the [MessageId] of a `gossipsub` message.
-fn compute_message_id(msg: &Message) -> MessageId {
-    let mut decoder = Decoder::new();
-    let id = match decoder.decompress_vec(&msg.data) {
-        Ok(data) => {
-            let domain_valid_snappy: Vec<u8> = vec![0x1, 0x0, 0x0, 0x0];
-            sha256([domain_valid_snappy.as_slice(), data.as_slice()].concat().as_slice())[..20]
-                .to_vec()
-        }
-        Err(_) => {
-            let domain_invalid_snappy: Vec<u8> = vec![0x0, 0x0, 0x0, 0x0];
-            sha256([domain_invalid_snappy.as_slice(), msg.data.as_slice()].concat().as_slice())
-                [..20]
-                .to_vec()
-        }
+/// Computes the [MessageId] of a `gossipsub` message, see [message_id].
+pub fn compute_message_id(msg: &Message) -> MessageId {
+    message_id(&msg.data)
+}
+
+/// Computes the [MessageId] of the given message data, as specified by the
+/// [p2p spec](https://specs.optimism.io/protocol/rollup-node-p2p.html#gossipsub-message-id):
+/// the first 20 bytes of the SHA256 hash of [MESSAGE_DOMAIN_VALID_SNAPPY] and the
+/// decompressed data, or of [MESSAGE_DOMAIN_INVALID_SNAPPY] and the raw data if it isn't
+/// valid snappy or would decompress past [MAX_GOSSIP_SIZE].
+pub fn message_id(data: &[u8]) -> MessageId {
+    let decompressed = snap::raw::decompress_len(data)
+        .ok()
+        .filter(|len| *len <= MAX_GOSSIP_SIZE)
+        .and_then(|_| Decoder::new().decompress_vec(data).ok());
+    let hash = match decompressed {
+        Some(data) => sha256(&[MESSAGE_DOMAIN_VALID_SNAPPY.as_slice(), data.as_slice()].concat()),
+        None => sha256(&[MESSAGE_DOMAIN_INVALID_SNAPPY.as_slice(), data].concat()),
     };
+    MessageId(hash[..20].to_vec())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_max_gossip_sizes_by_version() {
+        let sizes = MaxGossipSizes { v1: 1, v2: 2, v3: 3, v4: 4 };
+        assert_eq!(sizes.by_version(0), Some(1));
+        assert_eq!(sizes.by_version(2), Some(3));
+        assert_eq!(sizes.by_version(3), Some(4));
+        assert_eq!(sizes.by_version(4), None);
+        assert_eq!(sizes.max(), 4);
+
+        let mut sizes = MaxGossipSizes::uniform(1);
+        sizes.set_version(3, 5);
+        assert_eq!(sizes, MaxGossipSizes { v1: 1, v2: 1, v3: 1, v4: 5 });
+        assert_eq!(DEFAULT_MAX_GOSSIP_SIZES.max(), MAX_GOSSIP_SIZE + 64);
+    }
+
+    #[test]
+    fn test_default_config_transmit_size() {
+        let config = default_config().unwrap();
+        assert_eq!(config.max_transmit_size(), MAX_GOSSIP_SIZE);
+    }
+
+    #[test]
+    fn test_gossip_params() {
+        let params = GossipParams {
+            mesh_d: 4,
+            mesh_dlo: 3,
+            mesh_dhi: 6,
+            heartbeat_interval: Duration::from_secs(1),
+            ..Default::default()
+        };
+        let config = params.build().unwrap();
+        assert_eq!(config.mesh_n(), 4);
+        assert_eq!(config.mesh_n_low(), 3);
+        assert_eq!(config.mesh_n_high(), 6);
+        assert_eq!(config.heartbeat_interval(), Duration::from_secs(1));
+        assert_eq!(config.duplicate_cache_time(), Duration::from_secs(130));
+
+        let params = GossipParams { mesh_dlo: 9, ..Default::default() };
+        assert!(params.build().is_err());
+    }
+
+    #[test]
+    fn test_message_id() {
+        let data = b"hello";
+        let compressed = snap::raw::Encoder::new().compress_vec(data).unwrap();
+        let expected = sha256(&[MESSAGE_DOMAIN_VALID_SNAPPY.as_slice(), data.as_slice()].concat());
+        assert_eq!(message_id(&compressed), MessageId(expected[..20].to_vec()));
 
-    MessageId(id)
+        let invalid = [0xff, 0xff, 0xff, 0xff, 0xff];
+        let expected =
+            sha256(&[MESSAGE_DOMAIN_INVALID_SNAPPY.as_slice(), invalid.as_slice()].concat());
+        assert_eq!(message_id(&invalid), MessageId(expected[..20].to_vec()));
+    }
 }
@@ -1,14 +1,51 @@
 //! Consensus-layer gossipsub driver for Optimism.
 
-use crate::gossip::{
-    behaviour::Behaviour,
-    event::Event,
-    handler::{BlockHandler, Handler},
+#[cfg(feature = "interop")]
+use crate::gossip::interop::InteropHandler;
+use crate::{
+    discovery::driver::{EnrUpdate, EnrUpdater},
+    gossip::{
+        behaviour::Behaviour,
+        event::Event,
+        handler::{BlockHandler, Handler, LATEST_BLOCKS_VERSION},
+        peers::{Connectedness, Direction, PeerCommand, PeerDump, PeerInfo, PeerStats},
+        sequencer::{ForkSchedule, SequencerSigner},
+        validation::MAX_PAST_DRIFT,
+    },
+    sync::codec::{SyncResponse, SyncedPayload, RESULT_NOT_FOUND},
+    types::envelope::ExecutionPayloadEnvelope,
 };
-use eyre::Result;
-use futures::stream::StreamExt;
-use libp2p::{swarm::SwarmEvent, Multiaddr, Swarm};
-use tracing::{debug, error, info};
+use eyre::{eyre, Result};
+use futures::{stream::StreamExt, FutureExt};
+use libp2p::{
+    autonat,
+    gossipsub::{IdentTopic, Message, MessageAcceptance, MessageId, TopicHash},
+    multiaddr::Protocol,
+    request_response::{self, OutboundRequestId},
+    swarm::SwarmEvent,
+    upnp, Multiaddr, PeerId, Swarm,
+};
+use lru::LruCache;
+use std::{
+    collections::{BTreeMap, HashMap},
+    net::{IpAddr, SocketAddr},
+    num::NonZeroUsize,
+    time::{Duration, Instant},
+};
+use tokio::sync::oneshot;
+use tracing::{debug, error, field, info, info_span, trace, warn};
+
+/// How long peers blocked through [PeerCommand::Block] stay banned, i.e. until restart.
+pub const BLOCK_DURATION: Duration = Duration::from_secs(10 * 365 * 24 * 60 * 60);
+
+/// The interval at which obsolete blocks topics are checked for in tip-only mode.
+pub const TIP_ONLY_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// The maximum number of swarm events handled together by [GossipDriver::handle_events].
+pub const MAX_EVENT_BATCH: usize = 64;
+
+/// The number of gossip message validation results to remember, by message ID.
+pub const VALIDATION_CACHE_SIZE: usize = 4096;
 
 /// A [libp2p::Swarm] instance with an associated address to listen on.
 pub struct GossipDriver {
@@ -16,20 +53,225 @@ pub struct GossipDriver {
     pub swarm: Swarm<Behaviour>,
     /// The address to listen on.
     pub addr: Multiaddr,
+    /// The QUIC address to listen on, if any.
+    pub quic_addr: Option<Multiaddr>,
+    /// The other addresses to listen on, e.g. IPv6 ones.
+    pub additional_addrs: Vec<Multiaddr>,
     /// Block handler.
     pub handler: BlockHandler,
+    /// The block handlers of the other chains gossiped by the node, if any.
+    pub additional_handlers: Vec<BlockHandler>,
+    /// Interop executing messages handler, if interop gossip is enabled.
+    #[cfg(feature = "interop")]
+    pub interop: Option<InteropHandler>,
+    /// Signs the payloads published with [Self::publish_payload], if the node sequences.
+    pub sequencer: Option<SequencerSigner>,
+    /// The score under which peers are banned and the duration of their ban,
+    /// if peers are banned automatically.
+    pub ban_policy: Option<(f64, Duration)>,
+    /// The banned peers, with the time their ban expires.
+    pub banned_peers: HashMap<PeerId, Instant>,
+    /// The remote address and direction of the connected peers.
+    pub connections: HashMap<PeerId, (Multiaddr, Direction)>,
+    /// The in-flight `payload_by_number` requests, with their block number and
+    /// the channel to send their result back on.
+    pub pending_syncs: HashMap<OutboundRequestId, (u64, oneshot::Sender<Result<SyncedPayload>>)>,
+    /// The number of `payload_by_number` requests sent, used to spread them across peers.
+    sync_requests: usize,
+    /// Advertises the confirmed external addresses in the local ENR, if discovery runs.
+    pub enr_updater: Option<EnrUpdater>,
+    /// The hardfork schedule whose superseded blocks topics are unsubscribed from, in
+    /// tip-only mode.
+    pub tip_only: Option<ForkSchedule>,
+    /// The validation results of the recent gossip messages, reported again for messages
+    /// received again instead of handling them twice.
+    validations: LruCache<MessageId, MessageAcceptance>,
 }
 
 impl GossipDriver {
     /// Creates a new [GossipDriver] instance.
     pub fn new(swarm: Swarm<Behaviour>, addr: Multiaddr, handler: BlockHandler) -> Self {
-        Self { swarm, addr, handler }
+        Self {
+            swarm,
+            addr,
+            quic_addr: None,
+            additional_addrs: Vec::new(),
+            handler,
+            additional_handlers: Vec::new(),
+            #[cfg(feature = "interop")]
+            interop: None,
+            sequencer: None,
+            ban_policy: None,
+            banned_peers: HashMap::new(),
+            connections: HashMap::new(),
+            pending_syncs: HashMap::new(),
+            sync_requests: 0,
+            enr_updater: None,
+            tip_only: None,
+            validations: LruCache::new(
+                NonZeroUsize::new(VALIDATION_CACHE_SIZE).expect("non-zero cache size"),
+            ),
+        }
+    }
+
+    /// Listens for QUIC connections on the given address too.
+    pub fn with_quic_addr(mut self, addr: Multiaddr) -> Self {
+        self.quic_addr = Some(addr);
+        self
+    }
+
+    /// Listens on the given addresses too.
+    pub fn with_additional_addrs(mut self, addrs: Vec<Multiaddr>) -> Self {
+        self.additional_addrs = addrs;
+        self
+    }
+
+    /// Listens on the given address too.
+    pub fn with_additional_addr(mut self, addr: Multiaddr) -> Self {
+        self.additional_addrs.push(addr);
+        self
+    }
+
+    /// Handles the blocks of other chains with the given handlers too.
+    ///
+    /// The handlers' topics must already be subscribed to by the [Behaviour].
+    pub fn with_additional_handlers(mut self, handlers: Vec<BlockHandler>) -> Self {
+        self.additional_handlers = handlers;
+        self
+    }
+
+    /// Advertises the external addresses confirmed by NAT traversal in the local ENR
+    /// through the given [EnrUpdater].
+    pub fn with_enr_updater(mut self, updater: EnrUpdater) -> Self {
+        self.enr_updater = Some(updater);
+        self
+    }
+
+    /// Unsubscribes from the blocks topics superseded according to the given
+    /// [ForkSchedule], see [Self::unsubscribe_obsolete_topics].
+    pub fn with_tip_only(mut self, forks: ForkSchedule) -> Self {
+        self.tip_only = Some(forks);
+        self
+    }
+
+    /// Unsubscribes from the blocks topics of the hardforks superseded before the given
+    /// unix time, in tip-only mode.
+    ///
+    /// A topic stays subscribed until the blocks of its hardfork are older than
+    /// [MAX_PAST_DRIFT], after which they would be rejected anyway.
+    pub fn unsubscribe_obsolete_topics(&mut self, now: u64) {
+        let Some(forks) = self.tip_only else {
+            return;
+        };
+        let current = forks.blocks_version(now.saturating_sub(MAX_PAST_DRIFT));
+        for version in 0..current {
+            let Some(topic) = self.handler.topic_by_version(version).cloned() else {
+                continue;
+            };
+            match self.swarm.behaviour_mut().unsubscribe(&topic) {
+                Ok(true) => info!("Unsubscribed from the obsolete blocks topic {}", topic),
+                Ok(false) => {}
+                Err(err) => warn!("{}", err),
+            }
+        }
+    }
+
+    /// Sets the [SequencerSigner] signing the payloads published with
+    /// [Self::publish_payload].
+    pub fn with_sequencer(mut self, sequencer: SequencerSigner) -> Self {
+        self.sequencer = Some(sequencer);
+        self
+    }
+
+    /// Bans peers for `duration` once their gossipsub score drops below `threshold`.
+    ///
+    /// Requires peer scoring to be enabled on the [Behaviour].
+    pub fn with_peer_bans(mut self, threshold: f64, duration: Duration) -> Self {
+        self.ban_policy = Some((threshold, duration));
+        self
+    }
+
+    /// Returns `true` if the given peer is currently banned.
+    pub fn is_banned(&self, peer: &PeerId) -> bool {
+        self.banned_peers.get(peer).is_some_and(|until| *until > Instant::now())
+    }
+
+    /// Bans the given peer for the given duration.
+    ///
+    /// Its connections are closed, its messages are dropped by gossipsub, and new
+    /// connections from it are refused until the ban expires.
+    pub fn ban_peer(&mut self, peer: PeerId, duration: Duration) {
+        info!("Banning peer {} for {:?}", peer, duration);
+        self.banned_peers.insert(peer, Instant::now() + duration);
+        self.swarm.behaviour_mut().gossipsub.blacklist_peer(&peer);
+        _ = self.swarm.disconnect_peer_id(peer);
+        metrics::counter!("hera_gossip_banned_peers_total").increment(1);
+    }
+
+    /// Lifts the ban of the given peer, if any.
+    pub fn unban_peer(&mut self, peer: &PeerId) {
+        if self.banned_peers.remove(peer).is_some() {
+            debug!("Unbanning peer {}", peer);
+            self.swarm.behaviour_mut().gossipsub.remove_blacklisted_peer(peer);
+        }
+    }
+
+    /// Bans the given peer if its gossipsub score dropped below the ban threshold.
+    fn check_peer_score(&mut self, peer: &PeerId) {
+        let Some((threshold, duration)) = self.ban_policy else {
+            return;
+        };
+        let Some(score) = self.swarm.behaviour().gossipsub.peer_score(peer) else {
+            return;
+        };
+        if score < threshold && !self.is_banned(peer) {
+            warn!("Peer {} scored {:.2}, under the ban threshold of {}", peer, score, threshold);
+            self.ban_peer(*peer, duration);
+        }
+    }
+
+    /// Sets the [InteropHandler] for the executing messages topic.
+    ///
+    /// The handler's topics must already be subscribed to by the [Behaviour].
+    #[cfg(feature = "interop")]
+    pub fn with_interop(mut self, interop: InteropHandler) -> Self {
+        self.interop = Some(interop);
+        self
+    }
+
+    /// Publishes an executing messages payload on the interop topic.
+    #[cfg(feature = "interop")]
+    pub fn publish_executing_messages(&mut self, data: &[u8]) -> Result<MessageId> {
+        let interop = self.interop.as_ref().ok_or_else(|| eyre::eyre!("interop not enabled"))?;
+        let topic = interop.executing_messages_topic.clone();
+        let data = InteropHandler::encode(data)?;
+
+        let id = self
+            .swarm
+            .behaviour_mut()
+            .gossipsub
+            .publish(topic, data)
+            .map_err(|e| eyre::eyre!("publish failed: {:?}", e))?;
+        debug!("Published executing messages with message id: {}", id);
+        Ok(id)
     }
 
-    /// Listens on the address.
+    /// Listens on the addresses.
     pub fn listen(&mut self) -> Result<()> {
         self.swarm.listen_on(self.addr.clone()).map_err(|_| eyre::eyre!("swarm listen failed"))?;
         info!("Swarm listening on: {:?}", self.addr);
+        if let Some(addr) = &self.quic_addr {
+            self.swarm
+                .listen_on(addr.clone())
+                .map_err(|_| eyre::eyre!("swarm QUIC listen failed"))?;
+            info!("Swarm listening on: {:?}", addr);
+        }
+        for addr in &self.additional_addrs {
+            self.swarm
+                .listen_on(addr.clone())
+                .map_err(|_| eyre::eyre!("swarm listen on {} failed", addr))?;
+            info!("Swarm listening on: {:?}", addr);
+        }
         Ok(())
     }
 
@@ -38,11 +280,156 @@ impl GossipDriver {
         self.swarm.behaviour_mut()
     }
 
+    /// Returns the [PeerId]s of all currently connected peers.
+    pub fn connected_peers(&self) -> Vec<PeerId> {
+        self.swarm.connected_peers().copied().collect()
+    }
+
+    /// Returns the [PeerId]s of the peers in our gossipsub mesh for the given topic.
+    pub fn mesh_peers(&self, topic: &TopicHash) -> Vec<PeerId> {
+        self.swarm.behaviour().gossipsub.mesh_peers(topic).copied().collect()
+    }
+
+    /// Returns the number of known peers subscribed to each topic.
+    ///
+    /// Topics that we are subscribed to but that have no subscribed
+    /// peers are included with a count of zero.
+    pub fn topic_subscription_counts(&self) -> HashMap<TopicHash, usize> {
+        let gossipsub = &self.swarm.behaviour().gossipsub;
+        let mut counts: HashMap<TopicHash, usize> =
+            gossipsub.topics().map(|topic| (topic.clone(), 0)).collect();
+        for (_, topics) in gossipsub.all_peers() {
+            for topic in topics {
+                *counts.entry(topic.clone()).or_default() += 1;
+            }
+        }
+        counts
+    }
+
+    /// Returns the connected peers, with the other peers known to gossipsub unless
+    /// `connected` is set.
+    pub fn peer_dump(&self, connected: bool) -> PeerDump {
+        let gossipsub = &self.swarm.behaviour().gossipsub;
+        let blocks: Vec<_> = (0..=LATEST_BLOCKS_VERSION)
+            .filter_map(|v| self.handler.topic_by_version(v))
+            .map(|topic| topic.hash())
+            .collect();
+        let mut peers = BTreeMap::new();
+        for (peer, topics) in gossipsub.all_peers() {
+            let connection = self.connections.get(peer);
+            if connected && connection.is_none() {
+                continue;
+            }
+            let info = PeerInfo {
+                peer_id: peer.to_string(),
+                addresses: connection.map(|(addr, _)| addr.to_string()).into_iter().collect(),
+                connectedness: if connection.is_some() {
+                    Connectedness::Connected
+                } else {
+                    Connectedness::NotConnected
+                },
+                direction: connection.map_or(Direction::Unknown, |(_, direction)| *direction),
+                chain_id: self.handler.chain_id,
+                gossip_blocks: topics.iter().any(|topic| blocks.contains(topic)),
+                gossip_score: gossipsub.peer_score(peer),
+                ..Default::default()
+            };
+            peers.insert(peer.to_string(), info);
+        }
+        // Peers that haven't opened a gossipsub stream yet.
+        for (peer, (addr, direction)) in &self.connections {
+            peers.entry(peer.to_string()).or_insert_with(|| PeerInfo {
+                peer_id: peer.to_string(),
+                addresses: vec![addr.to_string()],
+                connectedness: Connectedness::Connected,
+                direction: *direction,
+                chain_id: self.handler.chain_id,
+                ..Default::default()
+            });
+        }
+        PeerDump {
+            total_connected: self.connections.len(),
+            peers,
+            banned_peers: self.banned().map(ToString::to_string).collect(),
+        }
+    }
+
+    /// Returns the peer statistics, without the size of the discovery routing table.
+    pub fn peer_stats(&self) -> PeerStats {
+        let counts = self.topic_subscription_counts();
+        let count = |version| {
+            self.handler
+                .topic_by_version(version)
+                .and_then(|topic| counts.get(&topic.hash()))
+                .copied()
+                .unwrap_or_default()
+        };
+        PeerStats {
+            connected: self.connections.len(),
+            table: 0,
+            blocks_topic: count(0),
+            blocks_topic_v2: count(1),
+            blocks_topic_v3: count(2),
+            blocks_topic_v4: count(3),
+            banned: self.banned().count(),
+            known: self.swarm.behaviour().gossipsub.all_peers().count(),
+        }
+    }
+
+    /// Returns the peers whose ban hasn't expired yet.
+    fn banned(&self) -> impl Iterator<Item = &PeerId> {
+        let now = Instant::now();
+        self.banned_peers.iter().filter(move |(_, until)| **until > now).map(|(peer, _)| peer)
+    }
+
+    /// Runs a [PeerCommand] and sends its result back.
+    pub async fn handle_peer_command(&mut self, command: PeerCommand) {
+        match command {
+            PeerCommand::Peers { connected, reply } => {
+                _ = reply.send(self.peer_dump(connected));
+            }
+            PeerCommand::Stats(reply) => {
+                _ = reply.send(self.peer_stats());
+            }
+            PeerCommand::Connect(addr, reply) => {
+                _ = reply.send(self.dial(addr).await);
+            }
+            PeerCommand::Disconnect(peer, reply) => {
+                let result = self
+                    .swarm
+                    .disconnect_peer_id(peer)
+                    .map_err(|_| eyre!("peer {} is not connected", peer));
+                _ = reply.send(result);
+            }
+            PeerCommand::Block(peer, reply) => {
+                if peer == *self.swarm.local_peer_id() {
+                    _ = reply.send(Err(eyre!("cannot block the local peer")));
+                    return;
+                }
+                self.ban_peer(peer, BLOCK_DURATION);
+                _ = reply.send(Ok(()));
+            }
+        }
+    }
+
     /// Attempts to select the next event from the Swarm.
     pub async fn select_next_some(&mut self) -> SwarmEvent<Event> {
         self.swarm.select_next_some().await
     }
 
+    /// Waits for the next event from the Swarm, returning it with the events that are
+    /// ready right after it, up to [MAX_EVENT_BATCH] events.
+    pub async fn next_events(&mut self) -> Vec<SwarmEvent<Event>> {
+        let mut events = vec![self.swarm.select_next_some().await];
+        while events.len() < MAX_EVENT_BATCH {
+            match self.swarm.select_next_some().now_or_never() {
+                Some(event) => events.push(event),
+                None => break,
+            }
+        }
+        events
+    }
+
     /// Dials the given [`Option<Multiaddr>`].
     pub async fn dial_opt(&mut self, peer: Option<impl Into<Multiaddr>>) {
         let Some(addr) = peer else {
@@ -54,31 +441,472 @@ impl GossipDriver {
     }
 
     /// Dials the given [Multiaddr].
+    ///
+    /// Addresses of already connected peers, as told by their `/p2p/` component,
+    /// are not dialed again.
     pub async fn dial(&mut self, peer: impl Into<Multiaddr>) -> Result<()> {
         let addr: Multiaddr = peer.into();
+        let connected =
+            addr.iter().any(|p| matches!(p, Protocol::P2p(id) if self.swarm.is_connected(&id)));
+        if connected {
+            trace!("Skipping dial of connected peer {}", addr);
+            return Ok(());
+        }
         self.swarm.dial(addr).map_err(|e| eyre::eyre!("dial failed: {:?}", e))?;
         Ok(())
     }
 
+    /// Publishes an [ExecutionPayloadEnvelope] on the blocks topic matching its version.
+    ///
+    /// The payload is marked as published so that copies reflected back
+    /// by mesh peers are ignored instead of being re-validated and re-delivered.
+    pub fn publish(&mut self, envelope: &ExecutionPayloadEnvelope) -> Result<MessageId> {
+        let topic = self.handler.topic_for(envelope).clone();
+        self.publish_on(topic, envelope)
+    }
+
+    /// Signs a payload built by this node with the sequencer key, and publishes it
+    /// on the blocks topic of the hardfork active at its timestamp.
+    ///
+    /// The hash and signature of the given envelope are replaced.
+    pub fn publish_payload(&mut self, envelope: ExecutionPayloadEnvelope) -> Result<MessageId> {
+        let sequencer = self.sequencer.as_ref().ok_or_else(|| eyre!("no sequencer key set"))?;
+        let version = sequencer.version(&envelope);
+        let envelope = sequencer.sign(envelope)?;
+        let topic = self
+            .handler
+            .topic_by_version(version)
+            .ok_or_else(|| eyre!("unknown blocks topic version: {}", version))?
+            .clone();
+        self.publish_on(topic, &envelope)
+    }
+
+    /// Publishes an [ExecutionPayloadEnvelope] on the given blocks topic.
+    fn publish_on(
+        &mut self,
+        topic: IdentTopic,
+        envelope: &ExecutionPayloadEnvelope,
+    ) -> Result<MessageId> {
+        let data = if topic.hash() == self.handler.blocks_v4_topic.hash() {
+            envelope.encode_v4()?
+        } else if topic.hash() == self.handler.blocks_v3_topic.hash() {
+            envelope.encode_v3()?
+        } else if topic.hash() == self.handler.blocks_v2_topic.hash() {
+            envelope.encode_v2()?
+        } else {
+            envelope.encode_v1()?
+        };
+
+        self.handler.mark_published(envelope.hash);
+        self.handler.payloads.insert(envelope.into());
+        let id = self
+            .swarm
+            .behaviour_mut()
+            .gossipsub
+            .publish(topic, data)
+            .map_err(|e| eyre::eyre!("publish failed: {:?}", e))?;
+        debug!("Published block {} with message id: {}", envelope.payload.block_number, id);
+        Ok(id)
+    }
+
+    /// Requests the payload with the given number over `payload_by_number`, from the
+    /// given peer or else from the next connected peer in turn.
+    ///
+    /// The result is sent on `result` once the peer responded.
+    pub fn request_payload(
+        &mut self,
+        peer: Option<PeerId>,
+        number: u64,
+        result: oneshot::Sender<Result<SyncedPayload>>,
+    ) {
+        let peer = peer.or_else(|| {
+            let mut peers = self.connected_peers();
+            peers.sort();
+            self.sync_requests += 1;
+            peers.get(self.sync_requests % peers.len().max(1)).copied()
+        });
+        let Some(peer) = peer else {
+            _ = result.send(Err(eyre!("no connected peer to sync from")));
+            return;
+        };
+        let Some(sync) = self.swarm.behaviour_mut().sync.as_mut() else {
+            _ = result.send(Err(eyre!("payload sync not enabled")));
+            return;
+        };
+
+        debug!("Requesting payload {} from {}", number, peer);
+        let id = sync.send_request(&peer, number);
+        self.pending_syncs.insert(id, (number, result));
+    }
+
+    /// Handles a [request_response::Event] of the `payload_by_number` protocol.
+    fn handle_sync_event(&mut self, event: request_response::Event<u64, SyncResponse>) {
+        match event {
+            request_response::Event::Message {
+                peer,
+                message: request_response::Message::Request { request: number, channel, .. },
+            } => {
+                let response = match self.handler.payloads.get(number) {
+                    Some(payload) => SyncResponse::Payload(Box::new(payload)),
+                    None => SyncResponse::Error(RESULT_NOT_FOUND),
+                };
+                let found = matches!(response, SyncResponse::Payload(_));
+                debug!("Serving payload {} to {} (found: {})", number, peer, found);
+                metrics::counter!("hera_sync_requests_served_total", "found" => found.to_string())
+                    .increment(1);
+                if let Some(sync) = self.swarm.behaviour_mut().sync.as_mut() {
+                    _ = sync.send_response(channel, response);
+                }
+            }
+            request_response::Event::Message {
+                peer,
+                message: request_response::Message::Response { request_id, response },
+            } => {
+                let Some((number, result)) = self.pending_syncs.remove(&request_id) else {
+                    return;
+                };
+                let payload = match response {
+                    SyncResponse::Payload(payload) if payload.payload.block_number == number => {
+                        Ok(*payload)
+                    }
+                    SyncResponse::Payload(payload) => Err(eyre!(
+                        "peer {} sent payload {} instead of {}",
+                        peer,
+                        payload.payload.block_number,
+                        number
+                    )),
+                    SyncResponse::Error(code) => Err(eyre!(
+                        "peer {} failed to serve payload {}: code {}",
+                        peer,
+                        number,
+                        code
+                    )),
+                };
+                _ = result.send(payload);
+            }
+            request_response::Event::OutboundFailure { peer, request_id, error } => {
+                if let Some((number, result)) = self.pending_syncs.remove(&request_id) {
+                    _ = result.send(Err(eyre!(
+                        "request for payload {} to {} failed: {}",
+                        number,
+                        peer,
+                        error
+                    )));
+                }
+            }
+            request_response::Event::InboundFailure { peer, error, .. } => {
+                debug!("Failed to serve payload to {}: {}", peer, error);
+            }
+            request_response::Event::ResponseSent { .. } => {}
+        }
+    }
+
+    /// Returns the [Handler] responsible for the given topic, if any.
+    fn handler_for(&self, topic: &TopicHash) -> Option<&dyn Handler> {
+        if self.handler.topics().contains(topic) {
+            return Some(&self.handler);
+        }
+        if let Some(handler) = self.additional_handlers.iter().find(|h| h.topics().contains(topic))
+        {
+            return Some(handler);
+        }
+        #[cfg(feature = "interop")]
+        if let Some(interop) = &self.interop {
+            if interop.topics().contains(topic) {
+                return Some(interop);
+            }
+        }
+        None
+    }
+
+    /// Exports the number of connected peers.
+    fn record_peer_count(&self) {
+        metrics::gauge!("hera_p2p_peers_connected")
+            .set(self.swarm.connected_peers().count() as f64);
+    }
+
+    /// Advertises the given confirmed external address in the local ENR, if it is a TCP
+    /// one.
+    fn advertise_external_addr(&self, addr: &Multiaddr) {
+        let Some(socket) = tcp_socket(addr) else {
+            return;
+        };
+        if let Some(updater) = &self.enr_updater {
+            if let Err(err) = updater.update(EnrUpdate::Tcp(socket)) {
+                warn!("Failed to advertise the external address {}: {}", addr, err);
+            }
+        }
+    }
+
+    /// Handles the NAT traversal events of AutoNAT and UPnP.
+    fn handle_nat_event(&mut self, event: Event) {
+        match event {
+            Event::Autonat(autonat::Event::StatusChanged { old, new }) => {
+                info!("NAT status changed from {:?} to {:?}", old, new);
+                let public = matches!(new, autonat::NatStatus::Public(_));
+                metrics::gauge!("hera_p2p_nat_public").set(if public { 1.0 } else { 0.0 });
+            }
+            Event::Upnp(upnp::Event::NewExternalAddr(addr)) => {
+                info!("Mapped the external address {} over UPnP", addr);
+            }
+            Event::Upnp(upnp::Event::ExpiredExternalAddr(addr)) => {
+                debug!("UPnP mapping of {} expired", addr);
+            }
+            Event::Upnp(upnp::Event::GatewayNotFound) => {
+                warn!("No UPnP gateway found, the p2p ports are not mapped");
+            }
+            Event::Upnp(upnp::Event::NonRoutableGateway) => {
+                warn!("The UPnP gateway is not exposed to the public network");
+            }
+            _ => {}
+        }
+    }
+
+    /// Handles the given [`SwarmEvent<Event>`]s, in order.
+    ///
+    /// The block handlers are first given the new gossip messages of the batch together,
+    /// see [Handler::prepare], so that their signatures are checked at once.
+    pub fn handle_events(&mut self, events: Vec<SwarmEvent<Event>>) {
+        let messages: Vec<&Message> =
+            events
+                .iter()
+                .filter_map(|event| match event {
+                    SwarmEvent::Behaviour(Event::Gossipsub(
+                        libp2p::gossipsub::Event::Message { message_id, message, .. },
+                    )) if !self.validations.contains(message_id) => Some(message),
+                    _ => None,
+                })
+                .collect();
+        if messages.len() > 1 {
+            for handler in std::iter::once(&self.handler).chain(&self.additional_handlers) {
+                handler.prepare(&messages);
+            }
+        }
+        for event in events {
+            self.handle_event(event);
+        }
+    }
+
     /// Handles the [`SwarmEvent<Event>`].
     pub fn handle_event(&mut self, event: SwarmEvent<Event>) {
-        if let SwarmEvent::Behaviour(Event::Gossipsub(libp2p::gossipsub::Event::Message {
-            propagation_source: src,
-            message_id: id,
-            message,
-        })) = event
-        {
-            debug!("Received message with topic: {}", message.topic);
-            if self.handler.topics().contains(&message.topic) {
-                debug!("Handling message with topic: {}", message.topic);
-                let status = self.handler.handle(message);
-                debug!("Reporting message validation result: {:?}", status);
-                _ = self
-                    .swarm
-                    .behaviour_mut()
-                    .gossipsub
-                    .report_message_validation_result(&id, &src, status);
+        match event {
+            SwarmEvent::ConnectionEstablished { peer_id, connection_id, endpoint, .. } => {
+                if self.is_banned(&peer_id) {
+                    debug!("Refusing connection from banned peer {} at {:?}", peer_id, endpoint);
+                    self.swarm.close_connection(connection_id);
+                } else {
+                    // The ban of the peer expired, if it was banned at all.
+                    self.unban_peer(&peer_id);
+                    let direction =
+                        if endpoint.is_dialer() { Direction::Outbound } else { Direction::Inbound };
+                    self.connections
+                        .insert(peer_id, (endpoint.get_remote_address().clone(), direction));
+                }
+                self.record_peer_count();
+            }
+            SwarmEvent::ConnectionClosed { peer_id, num_established, .. } => {
+                if num_established == 0 {
+                    self.connections.remove(&peer_id);
+                }
+                self.record_peer_count();
             }
+            SwarmEvent::OutgoingConnectionError { peer_id, error, .. } => {
+                debug!("Failed to dial peer {:?}: {}", peer_id, error);
+                metrics::counter!("hera_p2p_dial_failures_total").increment(1);
+            }
+            SwarmEvent::Behaviour(Event::Ping(libp2p::ping::Event {
+                peer,
+                connection,
+                result: Err(err),
+            })) => {
+                // Pings don't close failed connections by themselves.
+                debug!("Closing connection to {} after failed ping: {}", peer, err);
+                self.swarm.close_connection(connection);
+            }
+            SwarmEvent::Behaviour(Event::Gossipsub(libp2p::gossipsub::Event::Message {
+                propagation_source: src,
+                message_id: id,
+                message,
+            })) => {
+                debug!("Received message with topic: {}", message.topic);
+                if let Some(status) = self.validations.get(&id).map(replayed) {
+                    debug!("Reporting cached validation result of message {}: {:?}", id, status);
+                    metrics::counter!("hera_gossip_validation_cache_hits_total").increment(1);
+                    let rejected = matches!(status, MessageAcceptance::Reject);
+                    _ = self
+                        .swarm
+                        .behaviour_mut()
+                        .gossipsub
+                        .report_message_validation_result(&id, &src, status);
+                    if rejected {
+                        self.check_peer_score(&src);
+                    }
+                } else if let Some(handler) = self.handler_for(&message.topic) {
+                    // The span follows the block into its consumers, see `ReceivedBlock`.
+                    let span = info_span!(
+                        "gossip_message",
+                        peer = %src,
+                        topic = %message.topic,
+                        payload_hash = field::Empty,
+                        block_number = field::Empty,
+                        outcome = field::Empty,
+                    );
+                    let status = span.in_scope(|| {
+                        debug!("Handling message with topic: {}", message.topic);
+                        handler.handle(message)
+                    });
+                    span.record("outcome", field::debug(&status));
+                    debug!(parent: &span, "Reporting message validation result: {:?}", status);
+                    self.validations.put(id.clone(), replayed(&status));
+                    let rejected = matches!(status, MessageAcceptance::Reject);
+                    _ = self
+                        .swarm
+                        .behaviour_mut()
+                        .gossipsub
+                        .report_message_validation_result(&id, &src, status);
+                    if rejected {
+                        self.check_peer_score(&src);
+                    }
+                }
+            }
+            SwarmEvent::Behaviour(Event::Sync(event)) => self.handle_sync_event(event),
+            SwarmEvent::Behaviour(event @ (Event::Autonat(_) | Event::Upnp(_))) => {
+                self.handle_nat_event(event)
+            }
+            SwarmEvent::ExternalAddrConfirmed { address } => {
+                info!("External address confirmed: {}", address);
+                self.advertise_external_addr(&address);
+            }
+            SwarmEvent::ExternalAddrExpired { address } => {
+                debug!("External address expired: {}", address);
+            }
+            _ => {}
         }
     }
 }
+
+/// Returns the validation result to report for a message received again after being
+/// validated with the given result.
+///
+/// Accepted messages are ignored the second time, since their block was already
+/// delivered and propagated.
+const fn replayed(status: &MessageAcceptance) -> MessageAcceptance {
+    match status {
+        MessageAcceptance::Accept | MessageAcceptance::Ignore => MessageAcceptance::Ignore,
+        MessageAcceptance::Reject => MessageAcceptance::Reject,
+    }
+}
+
+/// Returns the socket address of the given TCP [Multiaddr], if it is one.
+fn tcp_socket(addr: &Multiaddr) -> Option<SocketAddr> {
+    let mut protocols = addr.iter();
+    let ip = match protocols.next()? {
+        Protocol::Ip4(ip) => IpAddr::V4(ip),
+        Protocol::Ip6(ip) => IpAddr::V6(ip),
+        _ => return None,
+    };
+    match protocols.next()? {
+        Protocol::Tcp(port) => Some(SocketAddr::new(ip, port)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{builder::NetworkDriverBuilder, gossip::peers::PeerCommand};
+    use alloy::primitives::Address;
+    use libp2p::PeerId;
+    use std::{
+        net::{IpAddr, Ipv4Addr, SocketAddr},
+        time::Duration,
+    };
+    use tokio::sync::oneshot;
+
+    #[test]
+    fn test_introspection_without_peers() {
+        let socket = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 9099);
+        let driver = NetworkDriverBuilder::new()
+            .with_unsafe_block_signer(Address::random())
+            .with_chain_id(10)
+            .with_socket(socket)
+            .with_discovery_disabled()
+            .build()
+            .unwrap();
+        let gossip = driver.gossip;
+
+        assert!(gossip.connected_peers().is_empty());
+        assert!(gossip.mesh_peers(&gossip.handler.blocks_v3_topic.hash()).is_empty());
+
+        let counts = gossip.topic_subscription_counts();
+        assert_eq!(counts.len(), 4);
+        assert!(counts.values().all(|count| *count == 0));
+    }
+
+    #[test]
+    fn test_tcp_socket() {
+        let addr = "/ip4/203.0.113.1/tcp/9222".parse().unwrap();
+        assert_eq!(super::tcp_socket(&addr), Some("203.0.113.1:9222".parse().unwrap()));
+        let addr = "/ip6/::1/tcp/9222".parse().unwrap();
+        assert_eq!(super::tcp_socket(&addr), Some("[::1]:9222".parse().unwrap()));
+        let addr = "/ip4/203.0.113.1/udp/9222/quic-v1".parse().unwrap();
+        assert_eq!(super::tcp_socket(&addr), None);
+    }
+
+    #[test]
+    fn test_ban_peer() {
+        let socket = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 9099);
+        let mut gossip = NetworkDriverBuilder::new()
+            .with_unsafe_block_signer(Address::random())
+            .with_chain_id(10)
+            .with_socket(socket)
+            .with_discovery_disabled()
+            .build()
+            .unwrap()
+            .gossip;
+        let peer = PeerId::random();
+
+        gossip.ban_peer(peer, Duration::from_secs(3600));
+        assert!(gossip.is_banned(&peer));
+        gossip.unban_peer(&peer);
+        assert!(!gossip.is_banned(&peer));
+
+        // Expired bans are no longer enforced.
+        gossip.ban_peer(peer, Duration::ZERO);
+        assert!(!gossip.is_banned(&peer));
+    }
+
+    #[tokio::test]
+    async fn test_peer_commands() {
+        let socket = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 9099);
+        let mut gossip = NetworkDriverBuilder::new()
+            .with_unsafe_block_signer(Address::random())
+            .with_chain_id(10)
+            .with_socket(socket)
+            .with_discovery_disabled()
+            .build()
+            .unwrap()
+            .gossip;
+        let peer = PeerId::random();
+
+        let (reply, recv) = oneshot::channel();
+        gossip.handle_peer_command(PeerCommand::Block(peer, reply)).await;
+        recv.await.unwrap().unwrap();
+        assert!(gossip.is_banned(&peer));
+
+        let (reply, recv) = oneshot::channel();
+        gossip.handle_peer_command(PeerCommand::Stats(reply)).await;
+        let stats = recv.await.unwrap();
+        assert_eq!(stats.connected, 0);
+        assert_eq!(stats.banned, 1);
+
+        let (reply, recv) = oneshot::channel();
+        gossip.handle_peer_command(PeerCommand::Peers { connected: false, reply }).await;
+        let dump = recv.await.unwrap();
+        assert!(dump.peers.is_empty());
+        assert_eq!(dump.banned_peers, vec![peer.to_string()]);
+
+        let (reply, recv) = oneshot::channel();
+        gossip.handle_peer_command(PeerCommand::Disconnect(peer, reply)).await;
+        assert!(recv.await.unwrap().is_err());
+    }
+}
@@ -1,15 +1,26 @@
 //! Event Handling Module.
 
-use libp2p::{gossipsub, ping};
+use std::convert::Infallible;
+
+use libp2p::{autonat, gossipsub, identify, ping, request_response, upnp};
+
+use crate::sync::codec::SyncResponse;
 
 /// The type of message received
 #[derive(Debug)]
 pub enum Event {
     /// Represents a [ping::Event]
-    #[allow(dead_code)]
     Ping(ping::Event),
     /// Represents a [gossipsub::Event]
     Gossipsub(gossipsub::Event),
+    /// Represents a [request_response::Event] of the `payload_by_number` protocol
+    Sync(request_response::Event<u64, SyncResponse>),
+    /// Represents an [identify::Event]
+    Identify(Box<identify::Event>),
+    /// Represents an [autonat::Event]
+    Autonat(autonat::Event),
+    /// Represents an [upnp::Event]
+    Upnp(upnp::Event),
 }
 
 impl From<ping::Event> for Event {
@@ -25,3 +36,38 @@ impl From<gossipsub::Event> for Event {
         Event::Gossipsub(value)
     }
 }
+
+impl From<request_response::Event<u64, SyncResponse>> for Event {
+    /// Converts [request_response::Event] to [Event]
+    fn from(value: request_response::Event<u64, SyncResponse>) -> Self {
+        Event::Sync(value)
+    }
+}
+
+impl From<identify::Event> for Event {
+    /// Converts [identify::Event] to [Event]
+    fn from(value: identify::Event) -> Self {
+        Event::Identify(Box::new(value))
+    }
+}
+
+impl From<autonat::Event> for Event {
+    /// Converts [autonat::Event] to [Event]
+    fn from(value: autonat::Event) -> Self {
+        Event::Autonat(value)
+    }
+}
+
+impl From<upnp::Event> for Event {
+    /// Converts [upnp::Event] to [Event]
+    fn from(value: upnp::Event) -> Self {
+        Event::Upnp(value)
+    }
+}
+
+impl From<Infallible> for Event {
+    /// Connection gating behaviours never emit events.
+    fn from(value: Infallible) -> Self {
+        match value {}
+    }
+}
@@ -0,0 +1,442 @@
+//! Connection gating of the swarm.
+
+use std::{
+    collections::{HashMap, HashSet},
+    convert::Infallible,
+    fmt,
+    net::IpAddr,
+    str::FromStr,
+    task::{Context, Poll},
+};
+
+use libp2p::{
+    core::{transport::PortUse, Endpoint},
+    multiaddr::Protocol,
+    swarm::{
+        dummy, ConnectionClosed, ConnectionDenied, ConnectionId, FromSwarm, NetworkBehaviour,
+        THandler, THandlerInEvent, THandlerOutEvent, ToSwarm,
+    },
+    Multiaddr, PeerId,
+};
+use tracing::debug;
+
+/// An IP subnet in CIDR notation, e.g. `10.0.0.0/8` or `fd00::/8`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Subnet {
+    /// The address of the subnet.
+    addr: IpAddr,
+    /// The length of the network prefix, in bits.
+    prefix: u8,
+}
+
+impl Subnet {
+    /// Returns `true` if the given IP address is in the subnet.
+    pub fn contains(&self, ip: &IpAddr) -> bool {
+        match (self.addr, ip) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix as u32).unwrap_or(0);
+                u32::from(net) & mask == u32::from(*ip) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix as u32).unwrap_or(0);
+                u128::from(net) & mask == u128::from(*ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for Subnet {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (addr, prefix) = s.split_once('/').ok_or(format!("missing prefix length: {}", s))?;
+        let addr: IpAddr = addr.parse().map_err(|e| format!("invalid subnet {}: {}", s, e))?;
+        let prefix: u8 = prefix.parse().map_err(|e| format!("invalid subnet {}: {}", s, e))?;
+        let max = if addr.is_ipv4() { 32 } else { 128 };
+        if prefix > max {
+            return Err(format!("invalid subnet {}: prefix longer than {} bits", s, max));
+        }
+        Ok(Self { addr, prefix })
+    }
+}
+
+/// The limits and filters applied to the connections of the swarm, to prevent resource
+/// exhaustion on public nodes.
+///
+/// The allowed peers, e.g. the static peers and the allowlist, bypass the other checks,
+/// while denied peers are always refused. In allowlist-only mode, the connections of
+/// any other peer are refused before they are established.
+#[derive(Debug, Clone, Default)]
+pub struct ConnectionGate {
+    /// The maximum number of connected peers.
+    pub max_peers: Option<usize>,
+    /// The maximum number of inbound connections from a single IP address.
+    pub max_inbound_per_ip: Option<usize>,
+    /// The maximum number of outbound connections to a single IP address.
+    pub max_outbound_per_ip: Option<usize>,
+    /// The subnets whose connections are refused.
+    pub banned_subnets: Vec<Subnet>,
+    /// The peers whose connections are refused.
+    pub denied_peers: HashSet<PeerId>,
+    /// The peers whose connections are always accepted.
+    pub allowed_peers: HashSet<PeerId>,
+    /// Whether the connections of peers outside of the allowed peers are refused.
+    pub allowlist_only: bool,
+}
+
+impl ConnectionGate {
+    /// Creates a new [ConnectionGate], accepting every connection.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the maximum number of connected peers.
+    pub fn with_max_peers(mut self, max_peers: usize) -> Self {
+        self.max_peers = Some(max_peers);
+        self
+    }
+
+    /// Sets the maximum number of inbound connections from a single IP address.
+    pub fn with_max_inbound_per_ip(mut self, max: usize) -> Self {
+        self.max_inbound_per_ip = Some(max);
+        self
+    }
+
+    /// Sets the maximum number of outbound connections to a single IP address.
+    pub fn with_max_outbound_per_ip(mut self, max: usize) -> Self {
+        self.max_outbound_per_ip = Some(max);
+        self
+    }
+
+    /// Refuses the connections of the given subnets.
+    pub fn with_banned_subnets(mut self, subnets: Vec<Subnet>) -> Self {
+        self.banned_subnets = subnets;
+        self
+    }
+
+    /// Refuses the connections of the given peers.
+    pub fn with_denied_peers(mut self, peers: impl IntoIterator<Item = PeerId>) -> Self {
+        self.denied_peers.extend(peers);
+        self
+    }
+
+    /// Always accepts the connections of the given peers, regardless of the limits.
+    pub fn with_allowed_peers(mut self, peers: impl IntoIterator<Item = PeerId>) -> Self {
+        self.allowed_peers.extend(peers);
+        self
+    }
+
+    /// Refuses the connections of any peer outside of the allowed peers, e.g. for a
+    /// private mesh.
+    pub fn with_allowlist_only(mut self) -> Self {
+        self.allowlist_only = true;
+        self
+    }
+
+    /// Returns why the connections of the given peer are refused regardless of the
+    /// limits, if they are: it is denied, or it is not allowed in allowlist-only mode.
+    fn refuses(&self, peer: &PeerId) -> Option<GateDenial> {
+        if self.allowed_peers.contains(peer) {
+            return None;
+        }
+        if self.denied_peers.contains(peer) {
+            return Some(GateDenial::DeniedPeer);
+        }
+        self.allowlist_only.then_some(GateDenial::NotAllowed)
+    }
+}
+
+/// The reason a connection was refused by a [ConnectionGater].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GateDenial {
+    /// The peer is denied.
+    DeniedPeer,
+    /// The peer is not allowed, in allowlist-only mode.
+    NotAllowed,
+    /// The IP address is in a banned subnet.
+    BannedSubnet,
+    /// The maximum number of peers is reached.
+    MaxPeers,
+    /// The maximum number of connections from or to the IP address is reached.
+    MaxPerIp,
+}
+
+impl GateDenial {
+    /// Returns the label of the denial reason, for metrics.
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::DeniedPeer => "denied_peer",
+            Self::NotAllowed => "not_allowed",
+            Self::BannedSubnet => "banned_subnet",
+            Self::MaxPeers => "max_peers",
+            Self::MaxPerIp => "max_per_ip",
+        }
+    }
+}
+
+impl fmt::Display for GateDenial {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "connection refused by the gate: {}", self.as_str())
+    }
+}
+
+impl std::error::Error for GateDenial {}
+
+/// A [NetworkBehaviour] enforcing a [ConnectionGate] on the connections of the swarm.
+#[derive(Debug, Default)]
+pub struct ConnectionGater {
+    /// The enforced limits and filters.
+    gate: ConnectionGate,
+    /// The established connections, with their peer, remote IP and direction.
+    connections: HashMap<ConnectionId, (PeerId, Option<IpAddr>, Endpoint)>,
+}
+
+impl ConnectionGater {
+    /// Creates a new [ConnectionGater] enforcing the given [ConnectionGate].
+    pub fn new(gate: ConnectionGate) -> Self {
+        Self { gate, connections: HashMap::new() }
+    }
+
+    /// Returns the enforced [ConnectionGate].
+    pub const fn gate(&self) -> &ConnectionGate {
+        &self.gate
+    }
+
+    /// Returns the number of connected peers.
+    fn peer_count(&self) -> usize {
+        self.connections.values().map(|(peer, _, _)| peer).collect::<HashSet<_>>().len()
+    }
+
+    /// Returns the number of established connections with the given IP and direction.
+    fn ip_count(&self, ip: &IpAddr, endpoint: Endpoint) -> usize {
+        self.connections
+            .values()
+            .filter(|(_, addr, role)| addr.as_ref() == Some(ip) && *role == endpoint)
+            .count()
+    }
+
+    /// Checks the connection of a peer with the given remote address.
+    ///
+    /// Only the subnet bans apply to pending inbound connections, whose peer is unknown
+    /// until the handshake completes.
+    fn check(
+        &self,
+        peer: Option<&PeerId>,
+        remote: &Multiaddr,
+        endpoint: Endpoint,
+    ) -> Result<(), GateDenial> {
+        if let Some(peer) = peer {
+            if self.gate.allowed_peers.contains(peer) {
+                return Ok(());
+            }
+            if let Some(denial) = self.gate.refuses(peer) {
+                return Err(denial);
+            }
+            let connected = self.connections.values().any(|(p, _, _)| p == peer);
+            if !connected && self.gate.max_peers.is_some_and(|max| self.peer_count() >= max) {
+                return Err(GateDenial::MaxPeers);
+            }
+        }
+
+        let Some(ip) = ip_of(remote) else {
+            return Ok(());
+        };
+        if self.gate.banned_subnets.iter().any(|subnet| subnet.contains(&ip)) {
+            return Err(GateDenial::BannedSubnet);
+        }
+        if peer.is_none() {
+            return Ok(());
+        }
+        let max_per_ip = match endpoint {
+            Endpoint::Listener => self.gate.max_inbound_per_ip,
+            Endpoint::Dialer => self.gate.max_outbound_per_ip,
+        };
+        if max_per_ip.is_some_and(|max| self.ip_count(&ip, endpoint) >= max) {
+            return Err(GateDenial::MaxPerIp);
+        }
+        Ok(())
+    }
+
+    /// Checks a connection, and records the denial if it is refused.
+    fn gate(
+        &self,
+        peer: Option<&PeerId>,
+        remote: &Multiaddr,
+        endpoint: Endpoint,
+    ) -> Result<(), ConnectionDenied> {
+        self.check(peer, remote, endpoint).map_err(|denial| {
+            debug!("Refusing connection with {:?} at {}: {}", peer, remote, denial);
+            metrics::counter!("hera_p2p_connections_denied_total", "reason" => denial.as_str())
+                .increment(1);
+            ConnectionDenied::new(denial)
+        })
+    }
+}
+
+impl NetworkBehaviour for ConnectionGater {
+    type ConnectionHandler = dummy::ConnectionHandler;
+    type ToSwarm = Infallible;
+
+    fn handle_pending_inbound_connection(
+        &mut self,
+        _connection_id: ConnectionId,
+        _local_addr: &Multiaddr,
+        remote_addr: &Multiaddr,
+    ) -> Result<(), ConnectionDenied> {
+        self.gate(None, remote_addr, Endpoint::Listener)
+    }
+
+    fn handle_pending_outbound_connection(
+        &mut self,
+        _connection_id: ConnectionId,
+        maybe_peer: Option<PeerId>,
+        _addresses: &[Multiaddr],
+        _effective_role: Endpoint,
+    ) -> Result<Vec<Multiaddr>, ConnectionDenied> {
+        match maybe_peer.and_then(|peer| self.gate.refuses(&peer)) {
+            Some(denial) => Err(ConnectionDenied::new(denial)),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    fn handle_established_inbound_connection(
+        &mut self,
+        connection_id: ConnectionId,
+        peer: PeerId,
+        _local_addr: &Multiaddr,
+        remote_addr: &Multiaddr,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        self.gate(Some(&peer), remote_addr, Endpoint::Listener)?;
+        self.connections.insert(connection_id, (peer, ip_of(remote_addr), Endpoint::Listener));
+        Ok(dummy::ConnectionHandler)
+    }
+
+    fn handle_established_outbound_connection(
+        &mut self,
+        connection_id: ConnectionId,
+        peer: PeerId,
+        addr: &Multiaddr,
+        _role_override: Endpoint,
+        _port_use: PortUse,
+    ) -> Result<THandler<Self>, ConnectionDenied> {
+        self.gate(Some(&peer), addr, Endpoint::Dialer)?;
+        self.connections.insert(connection_id, (peer, ip_of(addr), Endpoint::Dialer));
+        Ok(dummy::ConnectionHandler)
+    }
+
+    fn on_swarm_event(&mut self, event: FromSwarm) {
+        if let FromSwarm::ConnectionClosed(ConnectionClosed { connection_id, .. }) = event {
+            self.connections.remove(&connection_id);
+        }
+    }
+
+    fn on_connection_handler_event(
+        &mut self,
+        _peer_id: PeerId,
+        _connection_id: ConnectionId,
+        event: THandlerOutEvent<Self>,
+    ) {
+        match event {}
+    }
+
+    fn poll(
+        &mut self,
+        _cx: &mut Context<'_>,
+    ) -> Poll<ToSwarm<Self::ToSwarm, THandlerInEvent<Self>>> {
+        Poll::Pending
+    }
+}
+
+/// Returns the IP address of a [Multiaddr], if any.
+fn ip_of(addr: &Multiaddr) -> Option<IpAddr> {
+    addr.iter().find_map(|protocol| match protocol {
+        Protocol::Ip4(ip) => Some(IpAddr::V4(ip)),
+        Protocol::Ip6(ip) => Some(IpAddr::V6(ip)),
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addr(ip: &str) -> Multiaddr {
+        format!("/ip4/{}/tcp/9222", ip).parse().unwrap()
+    }
+
+    #[test]
+    fn test_subnet() {
+        let subnet: Subnet = "10.1.0.0/16".parse().unwrap();
+        assert!(subnet.contains(&"10.1.2.3".parse().unwrap()));
+        assert!(!subnet.contains(&"10.2.0.1".parse().unwrap()));
+        assert!(!subnet.contains(&"::1".parse().unwrap()));
+        let all: Subnet = "0.0.0.0/0".parse().unwrap();
+        assert!(all.contains(&"192.168.0.1".parse().unwrap()));
+        let v6: Subnet = "fd00::/8".parse().unwrap();
+        assert!(v6.contains(&"fd12::1".parse().unwrap()));
+        assert!("10.0.0.0".parse::<Subnet>().is_err());
+        assert!("10.0.0.0/33".parse::<Subnet>().is_err());
+    }
+
+    #[test]
+    fn test_gate_limits() {
+        let allowed = PeerId::random();
+        let denied = PeerId::random();
+        let gate = ConnectionGate::new()
+            .with_max_peers(2)
+            .with_max_inbound_per_ip(1)
+            .with_banned_subnets(vec!["192.168.0.0/16".parse().unwrap()])
+            .with_denied_peers([denied])
+            .with_allowed_peers([allowed]);
+        let mut gater = ConnectionGater::new(gate);
+
+        let id = ConnectionId::new_unchecked;
+        assert_eq!(
+            gater.check(Some(&denied), &addr("10.0.0.1"), Endpoint::Dialer),
+            Err(GateDenial::DeniedPeer)
+        );
+        assert_eq!(
+            gater.check(None, &addr("192.168.1.1"), Endpoint::Listener),
+            Err(GateDenial::BannedSubnet)
+        );
+
+        let peer = (PeerId::random(), ip_of(&addr("10.0.0.1")), Endpoint::Listener);
+        gater.connections.insert(id(1), peer);
+        assert!(gater.check(None, &addr("10.0.0.1"), Endpoint::Listener).is_ok());
+        assert_eq!(
+            gater.check(Some(&PeerId::random()), &addr("10.0.0.1"), Endpoint::Listener),
+            Err(GateDenial::MaxPerIp)
+        );
+        assert!(gater.check(Some(&PeerId::random()), &addr("10.0.0.1"), Endpoint::Dialer).is_ok());
+
+        gater.connections.insert(id(2), (PeerId::random(), None, Endpoint::Dialer));
+        assert_eq!(
+            gater.check(Some(&PeerId::random()), &addr("10.0.0.2"), Endpoint::Dialer),
+            Err(GateDenial::MaxPeers)
+        );
+        assert!(gater.check(Some(&allowed), &addr("192.168.1.1"), Endpoint::Listener).is_ok());
+    }
+
+    #[test]
+    fn test_gate_allowlist_only() {
+        let (allowed, denied) = (PeerId::random(), PeerId::random());
+        let gate = ConnectionGate::new()
+            .with_allowed_peers([allowed])
+            .with_denied_peers([denied, allowed])
+            .with_allowlist_only();
+        let gater = ConnectionGater::new(gate);
+
+        assert!(gater.check(Some(&allowed), &addr("10.0.0.1"), Endpoint::Dialer).is_ok());
+        assert_eq!(
+            gater.check(Some(&PeerId::random()), &addr("10.0.0.1"), Endpoint::Listener),
+            Err(GateDenial::NotAllowed)
+        );
+        assert_eq!(
+            gater.check(Some(&denied), &addr("10.0.0.1"), Endpoint::Dialer),
+            Err(GateDenial::DeniedPeer)
+        );
+        // The peer of a pending inbound connection is not known yet.
+        assert!(gater.check(None, &addr("10.0.0.1"), Endpoint::Listener).is_ok());
+    }
+}
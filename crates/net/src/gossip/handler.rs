@@ -1,13 +1,33 @@
 //! Block Handler
 
-use crate::types::envelope::ExecutionPayloadEnvelope;
+use crate::{
+    gossip::{
+        channel::{
+            unsafe_block_channel, UnsafeBlockReceiver, UnsafeBlockSender,
+            DEFAULT_UNSAFE_BLOCK_CAPACITY,
+        },
+        config::{MaxGossipSizes, MAX_GOSSIP_SIZE},
+        sequencer::ForkSchedule,
+        validation::{self, Seen, SeenBlocks, SignatureCache},
+    },
+    sync::store::PayloadStore,
+    types::{envelope::ExecutionPayloadEnvelope, payload::PayloadHash},
+};
 use alloy::primitives::Address;
 use libp2p::gossipsub::{IdentTopic, Message, MessageAcceptance, TopicHash};
 use std::{
-    sync::mpsc::{channel, Receiver, Sender},
-    time::SystemTime,
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+    time::{Instant, SystemTime},
 };
 use tokio::sync::watch;
+use tracing::{field, Span};
+
+/// The number of self-published payload hashes to remember.
+const PUBLISHED_CACHE_SIZE: usize = 256;
+
+/// The latest blocks topic version, `3` for `blocks/v4`.
+pub const LATEST_BLOCKS_VERSION: u8 = 3;
 
 /// This trait defines the functionality required to process incoming messages
 /// and determine their acceptance within the network.
@@ -18,10 +38,29 @@ pub trait Handler: Send {
     /// Manages validation and further processing of messages
     fn handle(&self, msg: Message) -> MessageAcceptance;
 
+    /// Prepares the handling of messages received together, e.g. by checking their
+    /// signatures at once. Does nothing by default.
+    fn prepare(&self, _msgs: &[&Message]) {}
+
     /// Specifies which topics the handler is interested in
     fn topics(&self) -> Vec<TopicHash>;
 }
 
+/// A block received from the network, with the tracing span of the message it came in.
+#[derive(Debug, Clone)]
+pub struct ReceivedBlock {
+    /// The received payload.
+    pub envelope: ExecutionPayloadEnvelope,
+    /// The span of the message the block was received in.
+    ///
+    /// Consumers should enter it while processing the block, so that the end-to-end
+    /// latency of the block is visible in traces. The span closes once every copy of
+    /// it is dropped.
+    pub span: Span,
+    /// When the block was received.
+    pub received_at: Instant,
+}
+
 /// Responsible for managing blocks received via p2p gossip
 #[derive(Debug, Clone)]
 pub struct BlockHandler {
@@ -29,8 +68,8 @@ pub struct BlockHandler {
     /// blockchains.
     pub chain_id: u64,
     /// A channel sender to forward new blocks to other modules
-    pub block_sender: Sender<ExecutionPayloadEnvelope>,
-    /// A [Receiver] to monitor changes to the unsafe block signer.
+    pub block_sender: UnsafeBlockSender,
+    /// A [watch::Receiver] to monitor changes to the unsafe block signer.
     pub unsafe_signer_recv: watch::Receiver<Address>,
     /// The libp2p topic for pre Canyon/Shangai blocks.
     pub blocks_v1_topic: IdentTopic,
@@ -38,54 +77,109 @@ pub struct BlockHandler {
     pub blocks_v2_topic: IdentTopic,
     /// The libp2p topic for Ecotone V3 blocks.
     pub blocks_v3_topic: IdentTopic,
+    /// The libp2p topic for Isthmus V4 blocks.
+    pub blocks_v4_topic: IdentTopic,
+    /// Hashes of the payloads published by this node.
+    /// Shared between clones so that our own payloads reflected back
+    /// by mesh peers are not re-validated and re-delivered.
+    pub published: Arc<Mutex<VecDeque<PayloadHash>>>,
+    /// The maximum decompressed size of a block on each topic.
+    pub max_sizes: MaxGossipSizes,
+    /// The recent valid payloads, served to syncing peers.
+    pub payloads: PayloadStore,
+    /// The recently seen blocks, shared between clones to ignore duplicates and detect
+    /// equivocations.
+    pub seen: Arc<Mutex<SeenBlocks>>,
+    /// The signers recovered from the recent block signatures, shared between clones.
+    pub signatures: Arc<Mutex<SignatureCache>>,
+    /// The hardfork schedule of the chain, if known, against which the topic version of
+    /// the blocks is checked.
+    pub forks: Option<ForkSchedule>,
 }
 
 impl Handler for BlockHandler {
     /// Checks validity of a block received via p2p gossip, and sends to the block update channel if
     /// valid.
+    ///
+    /// The payload hash and block number are recorded on the current span.
     fn handle(&self, msg: Message) -> MessageAcceptance {
         tracing::debug!("received block");
+        let received_at = Instant::now();
+        let topic = msg.topic.clone();
 
-        let decoded = if msg.topic == self.blocks_v1_topic.hash() {
-            ExecutionPayloadEnvelope::decode_v1(&msg.data)
-        } else if msg.topic == self.blocks_v2_topic.hash() {
-            ExecutionPayloadEnvelope::decode_v2(&msg.data)
-        } else if msg.topic == self.blocks_v3_topic.hash() {
-            ExecutionPayloadEnvelope::decode_v3(&msg.data)
-        } else {
+        if let Some((version, size)) = self.oversized(&msg) {
+            tracing::warn!(
+                "rejecting oversized block of {} bytes on topic version {}",
+                size,
+                version
+            );
+            metrics::counter!("hera_gossip_oversized_messages_total", "version" => version.to_string())
+                .increment(1);
             return MessageAcceptance::Reject;
-        };
+        }
 
-        match decoded {
-            Ok(envelope) => {
-                if self.block_valid(&envelope) {
-                    _ = self.block_sender.send(envelope);
-                    MessageAcceptance::Accept
-                } else {
-                    tracing::warn!("invalid unsafe block");
-                    MessageAcceptance::Reject
-                }
-            }
-            Err(err) => {
-                tracing::warn!("unsafe block decode failed: {}", err);
-                MessageAcceptance::Reject
-            }
+        let status = self.handle_block(msg, received_at);
+        if let Some(version) = self.version_of(&topic) {
+            metrics::counter!(
+                "hera_gossip_blocks_received_total",
+                "version" => version.to_string(),
+                "outcome" => outcome(&status),
+            )
+            .increment(1);
+        }
+        status
+    }
+
+    /// Recovers the signers of the blocks of the given messages at once, so that their
+    /// validation hits the signature cache.
+    ///
+    /// Messages of other topics, oversized or malformed messages are left to [Self::handle].
+    fn prepare(&self, msgs: &[&Message]) {
+        let signatures: Vec<_> = msgs
+            .iter()
+            .filter_map(|msg| {
+                let version = self.version_of(&msg.topic)?;
+                let max_size = self.max_sizes.by_version(version).unwrap_or(MAX_GOSSIP_SIZE);
+                let (signature, hash) =
+                    ExecutionPayloadEnvelope::decode_signature(version, &msg.data, max_size)
+                        .ok()?;
+                Some((signature, hash.signature_message(self.chain_id)))
+            })
+            .collect();
+        if signatures.len() > 1 {
+            self.signatures.lock().unwrap_or_else(|e| e.into_inner()).recover_batch(&signatures);
         }
     }
 
     /// The gossip topics accepted for new blocks
     fn topics(&self) -> Vec<TopicHash> {
-        vec![self.blocks_v1_topic.hash(), self.blocks_v2_topic.hash(), self.blocks_v3_topic.hash()]
+        vec![
+            self.blocks_v1_topic.hash(),
+            self.blocks_v2_topic.hash(),
+            self.blocks_v3_topic.hash(),
+            self.blocks_v4_topic.hash(),
+        ]
     }
 }
 
 impl BlockHandler {
-    /// Creates a new [BlockHandler] and opens a channel
+    /// Creates a new [BlockHandler] and opens a channel buffering up to
+    /// [DEFAULT_UNSAFE_BLOCK_CAPACITY] blocks.
     pub fn new(
         chain_id: u64,
         unsafe_recv: watch::Receiver<Address>,
-    ) -> (Self, Receiver<ExecutionPayloadEnvelope>) {
-        let (sender, recv) = channel();
+    ) -> (Self, UnsafeBlockReceiver) {
+        Self::with_capacity(chain_id, unsafe_recv, DEFAULT_UNSAFE_BLOCK_CAPACITY)
+    }
+
+    /// Creates a new [BlockHandler] and opens a channel buffering up to `capacity`
+    /// blocks, dropping the oldest ones when full.
+    pub fn with_capacity(
+        chain_id: u64,
+        unsafe_recv: watch::Receiver<Address>,
+        capacity: usize,
+    ) -> (Self, UnsafeBlockReceiver) {
+        let (sender, recv) = unsafe_block_channel(capacity);
 
         let handler = Self {
             chain_id,
@@ -94,30 +188,377 @@ impl BlockHandler {
             blocks_v1_topic: IdentTopic::new(format!("/optimism/{}/0/blocks", chain_id)),
             blocks_v2_topic: IdentTopic::new(format!("/optimism/{}/1/blocks", chain_id)),
             blocks_v3_topic: IdentTopic::new(format!("/optimism/{}/2/blocks", chain_id)),
+            blocks_v4_topic: IdentTopic::new(format!("/optimism/{}/3/blocks", chain_id)),
+            published: Arc::new(Mutex::new(VecDeque::with_capacity(PUBLISHED_CACHE_SIZE))),
+            max_sizes: MaxGossipSizes::default(),
+            payloads: PayloadStore::default(),
+            seen: Arc::default(),
+            signatures: Arc::default(),
+            forks: None,
         };
 
         (handler, recv)
     }
 
-    /// Determines if a block is valid.
+    /// Returns the blocks topic for the given topic version (`0` for v1, `1` for v2, ...).
+    pub fn topic_by_version(&self, version: u8) -> Option<&IdentTopic> {
+        match version {
+            0 => Some(&self.blocks_v1_topic),
+            1 => Some(&self.blocks_v2_topic),
+            2 => Some(&self.blocks_v3_topic),
+            3 => Some(&self.blocks_v4_topic),
+            _ => None,
+        }
+    }
+
+    /// Sets the maximum decompressed size of a block on each topic.
+    pub fn with_max_sizes(mut self, max_sizes: MaxGossipSizes) -> Self {
+        self.max_sizes = max_sizes;
+        self
+    }
+
+    /// Checks the topic version of the blocks against the given hardfork schedule, see
+    /// [validation::check_hardfork].
+    pub fn with_fork_schedule(mut self, forks: ForkSchedule) -> Self {
+        self.forks = Some(forks);
+        self
+    }
+
+    /// Returns the topic version and decompressed size of the given message
+    /// if it exceeds the maximum size of its topic.
     ///
-    /// True if the block is less than 1 minute old, and correctly signed by the unsafe block
-    /// signer.
-    fn block_valid(&self, envelope: &ExecutionPayloadEnvelope) -> bool {
-        let current_timestamp =
-            SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_secs();
+    /// The size is read from the snappy header, so oversized messages
+    /// are rejected without being decompressed.
+    fn oversized(&self, msg: &Message) -> Option<(u8, usize)> {
+        let version = self.version_of(&msg.topic)?;
+        let max = self.max_sizes.by_version(version)?;
+        let size = snap::raw::decompress_len(&msg.data).ok()?;
+        (size > max).then_some((version, size))
+    }
+
+    /// Returns the version of the given blocks topic, if it is one.
+    fn version_of(&self, topic: &TopicHash) -> Option<u8> {
+        (0..=LATEST_BLOCKS_VERSION)
+            .find(|v| self.topic_by_version(*v).is_some_and(|t| t.hash() == *topic))
+    }
+
+    /// Decodes a message of the given blocks topic version, within the maximum size of
+    /// the topic.
+    pub fn decode(&self, version: u8, data: &[u8]) -> eyre::Result<ExecutionPayloadEnvelope> {
+        let max_size = self.max_sizes.by_version(version).unwrap_or(MAX_GOSSIP_SIZE);
+        ExecutionPayloadEnvelope::decode_with_max_size(version, data, max_size)
+    }
+
+    /// Returns the topic that the given [ExecutionPayloadEnvelope] must be published on.
+    pub fn topic_for(&self, envelope: &ExecutionPayloadEnvelope) -> &IdentTopic {
+        if envelope.withdrawals_root.is_some() {
+            &self.blocks_v4_topic
+        } else if envelope.parent_beacon_block_root.is_some() {
+            &self.blocks_v3_topic
+        } else if envelope.payload.withdrawals.is_some() {
+            &self.blocks_v2_topic
+        } else {
+            &self.blocks_v1_topic
+        }
+    }
+
+    /// Marks the payload with the given [PayloadHash] as published by this node.
+    pub fn mark_published(&self, hash: PayloadHash) {
+        let mut published = self.published.lock().unwrap_or_else(|e| e.into_inner());
+        if published.contains(&hash) {
+            return;
+        }
+        if published.len() >= PUBLISHED_CACHE_SIZE {
+            published.pop_front();
+        }
+        published.push_back(hash);
+    }
+
+    /// Returns `true` if the payload with the given [PayloadHash] was published by this node.
+    pub fn is_published(&self, hash: &PayloadHash) -> bool {
+        self.published.lock().unwrap_or_else(|e| e.into_inner()).contains(hash)
+    }
 
-        let is_future = envelope.payload.timestamp > current_timestamp + 5;
-        let is_past = envelope.payload.timestamp < current_timestamp - 60;
-        let time_valid = !(is_future || is_past);
+    /// Decodes and validates a block of a message that is within its size limit.
+    fn handle_block(&self, msg: Message, received_at: Instant) -> MessageAcceptance {
+        let Some(version) = self.version_of(&msg.topic) else {
+            return MessageAcceptance::Reject;
+        };
+        let decoded = self.decode(version, &msg.data);
+
+        if let Ok(envelope) = &decoded {
+            let span = Span::current();
+            span.record("payload_hash", field::debug(&envelope.hash));
+            span.record("block_number", envelope.payload.block_number);
+        }
+
+        match decoded {
+            Ok(envelope) if self.is_published(&envelope.hash) => {
+                tracing::debug!("ignoring self-published block");
+                MessageAcceptance::Ignore
+            }
+            Ok(envelope) => {
+                let status = self.validate(&envelope, version);
+                if matches!(status, MessageAcceptance::Accept) {
+                    self.payloads.insert((&envelope).into());
+                    self.block_sender.send(ReceivedBlock {
+                        envelope,
+                        span: Span::current(),
+                        received_at,
+                    });
+                }
+                status
+            }
+            Err(err) => {
+                tracing::warn!("unsafe block decode failed: {}", err);
+                metrics::counter!("hera_gossip_decode_failures_total", "version" => version.to_string())
+                    .increment(1);
+                MessageAcceptance::Reject
+            }
+        }
+    }
+
+    /// Validates a block received on the blocks topic of the given version.
+    ///
+    /// Blocks are rejected if their timestamp is off by more than [validation::MAX_PAST_DRIFT]
+    /// or [validation::MAX_FUTURE_DRIFT] seconds, if their hash or fork specific fields
+    /// don't match their contents and topic, if their topic isn't the one of the hardfork
+    /// active at their timestamp when the [ForkSchedule] is known, if they are not signed by
+    /// the unsafe block signer, or if the signer equivocated at their height. Blocks seen
+    /// before are ignored.
+    fn validate(&self, envelope: &ExecutionPayloadEnvelope, version: u8) -> MessageAcceptance {
+        let now = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_secs();
+        let checked = validation::check_payload(envelope, version, now).and_then(|()| {
+            self.forks.as_ref().map_or(Ok(()), |forks| {
+                validation::check_hardfork(envelope.payload.timestamp, version, forks)
+            })
+        });
+        if let Err(err) = checked {
+            tracing::warn!("invalid unsafe block: {}", err);
+            metrics::counter!("hera_gossip_invalid_payloads_total", "reason" => err.label())
+                .increment(1);
+            return MessageAcceptance::Reject;
+        }
 
         let msg = envelope.hash.signature_message(self.chain_id);
         let block_signer = *self.unsafe_signer_recv.borrow();
-        let Ok(msg_signer) = envelope.signature.recover_address_from_msg(msg) else {
-            // TODO: add telemetry here if this happens.
-            return false;
+        let msg_signer = self
+            .signatures
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .recover(&envelope.signature, &msg);
+        let signed = msg_signer == Some(block_signer);
+        if !signed {
+            tracing::warn!("invalid unsafe block signature");
+            metrics::counter!("hera_gossip_invalid_signatures_total").increment(1);
+            return MessageAcceptance::Reject;
+        }
+
+        let payload = &envelope.payload;
+        let seen = self
+            .seen
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .observe(payload.block_number, payload.block_hash);
+        match seen {
+            Seen::New => MessageAcceptance::Accept,
+            Seen::Duplicate => {
+                tracing::debug!("ignoring already seen block");
+                MessageAcceptance::Ignore
+            }
+            Seen::Equivocation => {
+                tracing::warn!(
+                    "rejecting block {}, too many blocks seen at height {}",
+                    payload.block_hash,
+                    payload.block_number
+                );
+                metrics::counter!("hera_gossip_equivocations_total").increment(1);
+                MessageAcceptance::Reject
+            }
+        }
+    }
+}
+
+/// Returns the label of the given validation outcome.
+const fn outcome(status: &MessageAcceptance) -> &'static str {
+    match status {
+        MessageAcceptance::Accept => "accept",
+        MessageAcceptance::Ignore => "ignore",
+        MessageAcceptance::Reject => "reject",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        gossip::sequencer::{ForkSchedule, SequencerSigner},
+        types::payload::{ExecutionPayloadV2SSZ, ExecutionPayloadV3SSZ},
+    };
+    use alloy::{
+        primitives::{Signature, B256, U256},
+        signers::local::PrivateKeySigner,
+    };
+    use kona_primitives::L2ExecutionPayload;
+
+    #[test]
+    fn test_published_cache_is_bounded() {
+        let (_, recv) = watch::channel(Address::default());
+        let (handler, _) = BlockHandler::new(10, recv);
+        let first = PayloadHash::from([0u8].as_slice());
+
+        handler.mark_published(first);
+        assert!(handler.clone().is_published(&first));
+
+        for i in 1..=PUBLISHED_CACHE_SIZE as u64 {
+            handler.mark_published(PayloadHash::from(i.to_be_bytes().as_slice()));
+        }
+        assert!(!handler.is_published(&first));
+        assert_eq!(handler.published.lock().unwrap().len(), PUBLISHED_CACHE_SIZE);
+    }
+
+    #[test]
+    fn test_reject_oversized_block() {
+        let (_, recv) = watch::channel(Address::default());
+        let (handler, _) = BlockHandler::new(10, recv);
+        let handler =
+            handler.with_max_sizes(MaxGossipSizes { v1: 1024, v2: 1024, v3: 128, v4: 1024 });
+        let data = snap::raw::Encoder::new().compress_vec(&[0u8; 256]).unwrap();
+        let msg = |topic: &IdentTopic| Message {
+            source: None,
+            data: data.clone(),
+            sequence_number: None,
+            topic: topic.hash(),
+        };
+
+        assert_eq!(handler.version_of(&handler.blocks_v3_topic.hash()), Some(2));
+        assert_eq!(handler.oversized(&msg(&handler.blocks_v3_topic)), Some((2, 256)));
+        assert_eq!(handler.oversized(&msg(&handler.blocks_v2_topic)), None);
+        assert!(matches!(handler.handle(msg(&handler.blocks_v3_topic)), MessageAcceptance::Reject));
+    }
+
+    #[test]
+    fn test_validate_block() {
+        let forks = ForkSchedule { canyon_time: Some(0), ecotone_time: None, isthmus_time: None };
+        let signer = SequencerSigner::new(PrivateKeySigner::random(), 10, forks);
+        let (_, recv) = watch::channel(signer.address());
+        let (handler, mut blocks) = BlockHandler::new(10, recv);
+
+        let now = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_secs();
+        let mut envelope = ExecutionPayloadEnvelope {
+            payload: L2ExecutionPayload::from(ExecutionPayloadV2SSZ {
+                timestamp: now,
+                ..Default::default()
+            }),
+            signature: Signature::from_rs_and_parity(U256::from(1), U256::from(2), false).unwrap(),
+            hash: PayloadHash::default(),
+            parent_beacon_block_root: None,
+            withdrawals_root: None,
+        };
+        envelope.payload.block_hash = validation::block_hash(&envelope);
+        let envelope = signer.sign(envelope).unwrap();
+        let msg = Message {
+            source: None,
+            data: envelope.encode_v2().unwrap(),
+            sequence_number: None,
+            topic: handler.blocks_v2_topic.hash(),
         };
 
-        time_valid && msg_signer == block_signer
+        assert!(matches!(handler.handle(msg.clone()), MessageAcceptance::Accept));
+        assert!(matches!(handler.handle(msg.clone()), MessageAcceptance::Ignore));
+        assert_eq!(blocks.try_recv().unwrap().envelope.hash, envelope.hash);
+
+        // The same message is invalid on the v1 topic.
+        let msg = Message { topic: handler.blocks_v1_topic.hash(), ..msg };
+        assert!(matches!(handler.handle(msg), MessageAcceptance::Reject));
+    }
+
+    #[test]
+    fn test_validate_block_v4() {
+        let forks =
+            ForkSchedule { canyon_time: Some(0), ecotone_time: Some(0), isthmus_time: Some(0) };
+        let signer = SequencerSigner::new(PrivateKeySigner::random(), 10, forks);
+        let (_, recv) = watch::channel(signer.address());
+        let (handler, mut blocks) = BlockHandler::new(10, recv);
+        let handler = handler.with_fork_schedule(forks);
+
+        let now = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_secs();
+        let mut envelope = ExecutionPayloadEnvelope {
+            payload: L2ExecutionPayload::from(ExecutionPayloadV3SSZ {
+                timestamp: now,
+                ..Default::default()
+            }),
+            signature: Signature::from_rs_and_parity(U256::from(1), U256::from(2), false).unwrap(),
+            hash: PayloadHash::default(),
+            parent_beacon_block_root: Some(B256::repeat_byte(0xaa)),
+            withdrawals_root: Some(B256::repeat_byte(0xbb)),
+        };
+        envelope.payload.block_hash = validation::block_hash(&envelope);
+        let envelope = signer.sign(envelope).unwrap();
+        assert_eq!(handler.topic_for(&envelope).hash(), handler.blocks_v4_topic.hash());
+        let msg = Message {
+            source: None,
+            data: envelope.encode_v4().unwrap(),
+            sequence_number: None,
+            topic: handler.blocks_v4_topic.hash(),
+        };
+
+        // Blocks are only accepted on the v4 topic once Isthmus is active.
+        let later = ForkSchedule { isthmus_time: Some(now + 10), ..forks };
+        let pre_isthmus = handler.clone().with_fork_schedule(later);
+        assert!(matches!(pre_isthmus.handle(msg.clone()), MessageAcceptance::Reject));
+
+        assert!(matches!(handler.handle(msg), MessageAcceptance::Accept));
+        let received = blocks.try_recv().unwrap().envelope;
+        assert_eq!(received.withdrawals_root, envelope.withdrawals_root);
+    }
+
+    #[test]
+    fn test_prepare_signatures() {
+        let forks = ForkSchedule { canyon_time: Some(0), ecotone_time: None, isthmus_time: None };
+        let signer = SequencerSigner::new(PrivateKeySigner::random(), 10, forks);
+        let (_, recv) = watch::channel(signer.address());
+        let (handler, _) = BlockHandler::new(10, recv);
+
+        let now = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).unwrap().as_secs();
+        let msgs: Vec<_> = (0..2)
+            .map(|number| {
+                let mut envelope = ExecutionPayloadEnvelope {
+                    payload: L2ExecutionPayload::from(ExecutionPayloadV2SSZ {
+                        block_number: number,
+                        timestamp: now,
+                        ..Default::default()
+                    }),
+                    signature: Signature::from_rs_and_parity(U256::from(1), U256::from(2), false)
+                        .unwrap(),
+                    hash: PayloadHash::default(),
+                    parent_beacon_block_root: None,
+                    withdrawals_root: None,
+                };
+                envelope.payload.block_hash = validation::block_hash(&envelope);
+                Message {
+                    source: None,
+                    data: signer.sign(envelope).unwrap().encode_v2().unwrap(),
+                    sequence_number: None,
+                    topic: handler.blocks_v2_topic.hash(),
+                }
+            })
+            .collect();
+
+        let cached = |msg: &Message| {
+            let (signature, hash) =
+                ExecutionPayloadEnvelope::decode_signature(1, &msg.data, MAX_GOSSIP_SIZE).unwrap();
+            let msg = hash.signature_message(10);
+            handler.signatures.lock().unwrap().contains(&signature, &msg)
+        };
+        // Lone messages are left to the handling itself.
+        handler.prepare(&[&msgs[0]]);
+        assert!(!cached(&msgs[0]));
+
+        handler.prepare(&[&msgs[0], &msgs[1]]);
+        assert!(msgs.iter().all(cached));
+        for msg in msgs {
+            assert!(matches!(handler.handle(msg), MessageAcceptance::Accept));
+        }
     }
 }
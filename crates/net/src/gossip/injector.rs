@@ -0,0 +1,104 @@
+//! Out-of-band injection of unsafe payloads
+
+use crate::{
+    gossip::handler::{BlockHandler, Handler},
+    types::envelope::ExecutionPayloadEnvelope,
+};
+use eyre::{bail, eyre, Result};
+use libp2p::gossipsub::{Message, MessageAcceptance, MessageId};
+use tokio::sync::{mpsc, oneshot};
+use tracing::{field, info_span};
+
+/// A request to publish a payload from the gossip event loop, with a channel
+/// to send the result of the publication back on.
+pub type PublishRequest = (ExecutionPayloadEnvelope, oneshot::Sender<Result<MessageId>>);
+
+/// Injects signed unsafe payloads received out-of-band, e.g. from a conductor
+/// or from a directly peered sequencer, into the same path as gossiped blocks.
+///
+/// Payloads are validated by the [BlockHandler] exactly like gossip messages,
+/// and can optionally be republished on the blocks topic of their version.
+#[derive(Debug, Clone)]
+pub struct PayloadInjector {
+    /// The handler validating and forwarding the payloads.
+    handler: BlockHandler,
+    /// Requests the gossip event loop to publish a payload.
+    publisher: mpsc::UnboundedSender<PublishRequest>,
+}
+
+impl PayloadInjector {
+    /// Creates a new [PayloadInjector].
+    pub const fn new(
+        handler: BlockHandler,
+        publisher: mpsc::UnboundedSender<PublishRequest>,
+    ) -> Self {
+        Self { handler, publisher }
+    }
+
+    /// Injects the given payload, encoded as a gossip message of the given topic
+    /// version (`0` for v1, `1` for v2, ...).
+    ///
+    /// Returns the id of the gossip message if the payload was republished.
+    pub async fn inject(
+        &self,
+        version: u8,
+        data: Vec<u8>,
+        republish: bool,
+    ) -> Result<Option<MessageId>> {
+        let topic = self
+            .handler
+            .topic_by_version(version)
+            .ok_or_else(|| eyre!("unknown payload version {}", version))?;
+        let msg = Message { source: None, data, sequence_number: None, topic: topic.hash() };
+        let envelope =
+            if republish { Some(self.handler.decode(version, &msg.data)?) } else { None };
+
+        let span = info_span!(
+            "injected_payload",
+            topic = %msg.topic,
+            payload_hash = field::Empty,
+            block_number = field::Empty,
+            outcome = field::Empty,
+        );
+        let status = span.in_scope(|| self.handler.handle(msg));
+        span.record("outcome", field::debug(&status));
+        match status {
+            MessageAcceptance::Accept => {}
+            MessageAcceptance::Ignore => bail!("payload was already received or published"),
+            MessageAcceptance::Reject => bail!("invalid unsafe payload"),
+        }
+
+        let Some(envelope) = envelope else {
+            return Ok(None);
+        };
+        let (sender, recv) = oneshot::channel();
+        self.publisher
+            .send((envelope, sender))
+            .map_err(|_| eyre!("network driver is not running"))?;
+        let id = recv.await.map_err(|_| eyre!("network driver is not running"))??;
+        Ok(Some(id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::Address;
+    use tokio::sync::watch;
+
+    #[tokio::test]
+    async fn test_inject_rejects_invalid_payloads() {
+        let (_, signer) = watch::channel(Address::default());
+        let (handler, mut blocks) = BlockHandler::new(10, signer);
+        let (publisher, _requests) = mpsc::unbounded_channel();
+        let injector = PayloadInjector::new(handler, publisher);
+
+        let err = injector.inject(3, vec![], false).await.unwrap_err();
+        assert_eq!(err.to_string(), "unknown payload version 3");
+
+        let data = snap::raw::Encoder::new().compress_vec(&[0u8; 64]).unwrap();
+        assert!(injector.inject(2, data.clone(), false).await.is_err());
+        assert!(injector.inject(2, data, true).await.is_err());
+        assert!(blocks.try_recv().is_err());
+    }
+}
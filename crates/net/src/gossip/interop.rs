@@ -0,0 +1,114 @@
+//! Interop Handler
+
+use crate::gossip::handler::Handler;
+use alloy::primitives::Bytes;
+use eyre::Result;
+use libp2p::gossipsub::{IdentTopic, Message, MessageAcceptance, TopicHash};
+use snap::raw::{Decoder, Encoder};
+use std::sync::mpsc::{channel, Receiver, Sender};
+
+/// The maximum size of an uncompressed executing messages gossip payload.
+pub const MAX_EXECUTING_MESSAGES_SIZE: usize = 1024 * 1024;
+
+/// Responsible for managing executing messages received via the interop gossip mesh.
+///
+/// Executing messages are shared between the nodes of the chains in an interop
+/// dependency set, so that each supervisor can check cross-chain message validity
+/// before the referenced blocks are derived from L1.
+#[derive(Debug, Clone)]
+pub struct InteropHandler {
+    /// Chain ID of the L2 blockchain.
+    pub chain_id: u64,
+    /// A channel sender to forward new executing messages to other modules.
+    pub message_sender: Sender<Bytes>,
+    /// The libp2p topic for executing messages.
+    pub executing_messages_topic: IdentTopic,
+}
+
+impl Handler for InteropHandler {
+    /// Decompresses the executing messages payload, and forwards it to the message channel.
+    ///
+    /// The payload itself is validated by the supervisor it is forwarded to.
+    fn handle(&self, msg: Message) -> MessageAcceptance {
+        tracing::debug!("received executing messages");
+
+        if msg.topic != self.executing_messages_topic.hash() {
+            return MessageAcceptance::Reject;
+        }
+
+        match Self::decode(&msg.data) {
+            Ok(data) => {
+                _ = self.message_sender.send(data);
+                MessageAcceptance::Accept
+            }
+            Err(err) => {
+                tracing::warn!("executing messages decode failed: {}", err);
+                MessageAcceptance::Reject
+            }
+        }
+    }
+
+    /// The gossip topics accepted for executing messages
+    fn topics(&self) -> Vec<TopicHash> {
+        vec![self.executing_messages_topic.hash()]
+    }
+}
+
+impl InteropHandler {
+    /// Creates a new [InteropHandler] and opens a channel
+    pub fn new(chain_id: u64) -> (Self, Receiver<Bytes>) {
+        let (sender, recv) = channel();
+
+        let handler = Self {
+            chain_id,
+            message_sender: sender,
+            executing_messages_topic: IdentTopic::new(format!(
+                "/optimism/{}/interop/executing-messages",
+                chain_id
+            )),
+        };
+
+        (handler, recv)
+    }
+
+    /// Encodes an executing messages payload for gossip.
+    pub fn encode(data: &[u8]) -> Result<Vec<u8>> {
+        if data.is_empty() || data.len() > MAX_EXECUTING_MESSAGES_SIZE {
+            eyre::bail!("invalid executing messages size: {}", data.len());
+        }
+        Ok(Encoder::new().compress_vec(data)?)
+    }
+
+    /// Decodes an executing messages payload received via gossip.
+    pub fn decode(data: &[u8]) -> Result<Bytes> {
+        let len = snap::raw::decompress_len(data)?;
+        if len == 0 || len > MAX_EXECUTING_MESSAGES_SIZE {
+            eyre::bail!("invalid executing messages size: {}", len);
+        }
+        Ok(Decoder::new().decompress_vec(data)?.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_decode_roundtrip() {
+        let data = b"executing messages".to_vec();
+        let encoded = InteropHandler::encode(&data).unwrap();
+        assert_eq!(InteropHandler::decode(&encoded).unwrap(), Bytes::from(data));
+    }
+
+    #[test]
+    fn test_encode_empty() {
+        assert!(InteropHandler::encode(&[]).is_err());
+    }
+
+    #[test]
+    fn test_topics() {
+        let (handler, _) = InteropHandler::new(10);
+        let topic = IdentTopic::new("/optimism/10/interop/executing-messages");
+        assert_eq!(handler.topics(), vec![topic.hash()]);
+    }
+}
@@ -1,7 +1,17 @@
 //! Module containing consensus-layer gossipsub for optimism.
 
 pub mod behaviour;
+pub mod buffer;
+pub mod channel;
 pub mod config;
 pub mod driver;
 pub mod event;
+pub mod gate;
 pub mod handler;
+pub mod injector;
+#[cfg(feature = "interop")]
+pub mod interop;
+pub mod peers;
+pub mod scoring;
+pub mod sequencer;
+pub mod validation;
@@ -0,0 +1,254 @@
+//! Peer management of the running gossip driver, for op-node's `opp2p` admin API.
+
+use crate::discovery::{driver::DiscoveryCommand, stats::DiscoveryStats};
+use eyre::{eyre, Result};
+use libp2p::{Multiaddr, PeerId};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use tokio::sync::{mpsc, oneshot, watch};
+
+/// Whether a peer is connected, with op-node's `network.Connectedness` values.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(into = "u8", from = "u8")]
+pub enum Connectedness {
+    /// The peer is not connected.
+    #[default]
+    NotConnected,
+    /// The peer is connected.
+    Connected,
+}
+
+impl From<Connectedness> for u8 {
+    fn from(connectedness: Connectedness) -> Self {
+        connectedness as u8
+    }
+}
+
+impl From<u8> for Connectedness {
+    fn from(value: u8) -> Self {
+        if value == 1 {
+            Self::Connected
+        } else {
+            Self::NotConnected
+        }
+    }
+}
+
+/// The direction of the connection to a peer, with op-node's `network.Direction` values.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(into = "u8", from = "u8")]
+pub enum Direction {
+    /// The direction is unknown, e.g. the peer is not connected.
+    #[default]
+    Unknown,
+    /// The peer dialed the node.
+    Inbound,
+    /// The node dialed the peer.
+    Outbound,
+}
+
+impl From<Direction> for u8 {
+    fn from(direction: Direction) -> Self {
+        direction as u8
+    }
+}
+
+impl From<u8> for Direction {
+    fn from(value: u8) -> Self {
+        match value {
+            1 => Self::Inbound,
+            2 => Self::Outbound,
+            _ => Self::Unknown,
+        }
+    }
+}
+
+/// A peer of the node, as returned by op-node's `opp2p_peers` and `opp2p_self`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PeerInfo {
+    /// The libp2p peer ID.
+    #[serde(rename = "peerID")]
+    pub peer_id: String,
+    /// The base64 encoded ENR, if known.
+    #[serde(rename = "ENR", default)]
+    pub enr: String,
+    /// The addresses of the peer.
+    pub addresses: Vec<String>,
+    /// Whether the peer is connected.
+    pub connectedness: Connectedness,
+    /// The direction of the connection to the peer.
+    pub direction: Direction,
+    /// The chain ID of the peer, if known.
+    #[serde(rename = "chainID")]
+    pub chain_id: u64,
+    /// Whether the peer is subscribed to a blocks topic.
+    pub gossip_blocks: bool,
+    /// The gossipsub score of the peer, if peer scoring is enabled.
+    pub gossip_score: Option<f64>,
+}
+
+/// The peers of the node, as returned by op-node's `opp2p_peers`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PeerDump {
+    /// The number of connected peers.
+    pub total_connected: usize,
+    /// The peers, by peer ID.
+    pub peers: BTreeMap<String, PeerInfo>,
+    /// The IDs of the banned or blocked peers.
+    pub banned_peers: Vec<String>,
+}
+
+/// The peer statistics of the node, as returned by op-node's `opp2p_peerStats`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PeerStats {
+    /// The number of connected peers.
+    pub connected: usize,
+    /// The number of nodes in the discovery routing table.
+    pub table: usize,
+    /// The number of peers subscribed to the v1 blocks topic.
+    pub blocks_topic: usize,
+    /// The number of peers subscribed to the v2 blocks topic.
+    pub blocks_topic_v2: usize,
+    /// The number of peers subscribed to the v3 blocks topic.
+    pub blocks_topic_v3: usize,
+    /// The number of peers subscribed to the v4 blocks topic.
+    pub blocks_topic_v4: usize,
+    /// The number of banned or blocked peers.
+    pub banned: usize,
+    /// The number of peers known to gossipsub.
+    pub known: usize,
+}
+
+/// A command to the running gossip driver, with a channel to send its result back on.
+#[derive(Debug)]
+pub enum PeerCommand {
+    /// Lists the known peers, or only the connected ones.
+    Peers {
+        /// Whether to only list the connected peers.
+        connected: bool,
+        /// The channel to send the peers back on.
+        reply: oneshot::Sender<PeerDump>,
+    },
+    /// Returns the peer statistics.
+    Stats(oneshot::Sender<PeerStats>),
+    /// Dials the given address.
+    Connect(Multiaddr, oneshot::Sender<Result<()>>),
+    /// Closes the connections to the given peer.
+    Disconnect(PeerId, oneshot::Sender<Result<()>>),
+    /// Disconnects the given peer and refuses its connections from now on.
+    Block(PeerId, oneshot::Sender<Result<()>>),
+}
+
+/// Manages the peers of the running network driver, e.g. from the admin RPC.
+///
+/// The commands are run from the gossip event loop, and the local ENR is read from the
+/// discovery service, if discovery is enabled.
+#[derive(Debug, Clone)]
+pub struct PeerManager {
+    /// Sends commands to the gossip event loop.
+    commands: mpsc::UnboundedSender<PeerCommand>,
+    /// Sends commands to the discovery service, if discovery is enabled.
+    discovery: Option<mpsc::UnboundedSender<DiscoveryCommand>>,
+    /// The routing table statistics published by discovery, if discovery is enabled.
+    discovery_stats: Option<watch::Receiver<DiscoveryStats>>,
+}
+
+impl PeerManager {
+    /// Creates a new [PeerManager] sending commands to the gossip event loop.
+    pub const fn new(commands: mpsc::UnboundedSender<PeerCommand>) -> Self {
+        Self { commands, discovery: None, discovery_stats: None }
+    }
+
+    /// Reads the local ENR and the routing table size from the discovery service.
+    pub fn with_discovery(
+        mut self,
+        commands: mpsc::UnboundedSender<DiscoveryCommand>,
+        stats: watch::Receiver<DiscoveryStats>,
+    ) -> Self {
+        self.discovery = Some(commands);
+        self.discovery_stats = Some(stats);
+        self
+    }
+
+    /// Returns the known peers, or only the connected ones.
+    pub async fn peers(&self, connected: bool) -> Result<PeerDump> {
+        self.request(|reply| PeerCommand::Peers { connected, reply }).await
+    }
+
+    /// Returns the peer statistics, with the size of the discovery routing table.
+    pub async fn stats(&self) -> Result<PeerStats> {
+        let mut stats = self.request(PeerCommand::Stats).await?;
+        stats.table = self.discovery_stats.as_ref().map_or(0, |s| s.borrow().table_size);
+        Ok(stats)
+    }
+
+    /// Dials the given address.
+    pub async fn connect(&self, addr: Multiaddr) -> Result<()> {
+        self.request(|reply| PeerCommand::Connect(addr, reply)).await?
+    }
+
+    /// Closes the connections to the given peer.
+    pub async fn disconnect(&self, peer: PeerId) -> Result<()> {
+        self.request(|reply| PeerCommand::Disconnect(peer, reply)).await?
+    }
+
+    /// Disconnects the given peer and refuses its connections from now on.
+    pub async fn block(&self, peer: PeerId) -> Result<()> {
+        self.request(|reply| PeerCommand::Block(peer, reply)).await?
+    }
+
+    /// Returns the current local ENR, if discovery is enabled.
+    pub async fn local_enr(&self) -> Result<Option<String>> {
+        let Some(discovery) = &self.discovery else {
+            return Ok(None);
+        };
+        let (sender, recv) = oneshot::channel();
+        discovery
+            .send(DiscoveryCommand::LocalEnr(sender))
+            .map_err(|_| eyre!("discovery service is not running"))?;
+        Ok(Some(recv.await.map_err(|_| eyre!("discovery service is not running"))?))
+    }
+
+    /// Sends a command to the gossip event loop and waits for its result.
+    async fn request<T>(
+        &self,
+        command: impl FnOnce(oneshot::Sender<T>) -> PeerCommand,
+    ) -> Result<T> {
+        let (sender, recv) = oneshot::channel();
+        self.commands.send(command(sender)).map_err(|_| eyre!("network driver is not running"))?;
+        recv.await.map_err(|_| eyre!("network driver is not running"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_peer_info_serde() {
+        let info = PeerInfo {
+            peer_id: "16Uiu2HAm".to_string(),
+            connectedness: Connectedness::Connected,
+            direction: Direction::Outbound,
+            chain_id: 10,
+            ..Default::default()
+        };
+        let json = serde_json::to_value(&info).unwrap();
+        assert_eq!(json["peerID"], "16Uiu2HAm");
+        assert_eq!(json["connectedness"], 1);
+        assert_eq!(json["direction"], 2);
+        assert_eq!(json["chainID"], 10);
+        assert_eq!(serde_json::from_value::<PeerInfo>(json).unwrap(), info);
+    }
+
+    #[tokio::test]
+    async fn test_stats_without_driver() {
+        let (commands, recv) = mpsc::unbounded_channel();
+        drop(recv);
+        let err = PeerManager::new(commands).stats().await.unwrap_err();
+        assert_eq!(err.to_string(), "network driver is not running");
+    }
+}
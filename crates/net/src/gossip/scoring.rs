@@ -0,0 +1,162 @@
+//! Gossipsub Peer Scoring
+
+use libp2p::gossipsub::{PeerScoreParams, PeerScoreThresholds, TopicHash, TopicScoreParams};
+use std::{collections::HashSet, net::IpAddr, time::Duration};
+
+/// The value below which a decayed score is reset to zero.
+pub const DECAY_TO_ZERO: f64 = 0.01;
+
+/// The default block time of OP Stack chains, used as the scoring slot.
+pub const DEFAULT_BLOCK_TIME: Duration = Duration::from_secs(2);
+
+/// The default score under which peers are banned.
+pub const DEFAULT_BAN_THRESHOLD: f64 = -100.0;
+
+/// The default duration of a peer ban.
+pub const DEFAULT_BAN_DURATION: Duration = Duration::from_secs(60 * 60);
+
+/// The maximum score a peer can get from its time in the mesh of a topic.
+const MAX_IN_MESH_SCORE: f64 = 10.0;
+
+/// The number of epochs over which mesh delivery and failure penalties decay.
+const DECAY_EPOCHS: u32 = 5;
+
+/// Configures gossipsub peer scoring, using the parameters of op-node's `light`
+/// scoring by default.
+///
+/// Peers delivering invalid unsafe payloads quickly drop below the graylist
+/// threshold, after which their messages are ignored and they are pruned from
+/// the mesh.
+#[derive(Debug, Clone)]
+pub struct PeerScoring {
+    /// The block time of the chain. Scores decay once per block.
+    pub block_time: Duration,
+    /// The weight of the score of each blocks topic.
+    pub topic_weight: f64,
+    /// The penalty for each invalid message delivered on a blocks topic.
+    pub invalid_message_deliveries_weight: f64,
+    /// The penalty for each peer over the threshold sharing the same IP address.
+    pub ip_colocation_factor_weight: f64,
+    /// The number of peers allowed to share an IP address before being penalized.
+    pub ip_colocation_factor_threshold: f64,
+    /// IP addresses exempt from the IP colocation penalty.
+    pub ip_colocation_factor_whitelist: HashSet<IpAddr>,
+}
+
+impl Default for PeerScoring {
+    fn default() -> Self {
+        Self {
+            block_time: DEFAULT_BLOCK_TIME,
+            topic_weight: 0.8,
+            invalid_message_deliveries_weight: -140.4475,
+            ip_colocation_factor_weight: -35.0,
+            ip_colocation_factor_threshold: 10.0,
+            ip_colocation_factor_whitelist: HashSet::new(),
+        }
+    }
+}
+
+impl PeerScoring {
+    /// Returns the block time, or the default block time if it is zero.
+    fn slot(&self) -> Duration {
+        if self.block_time.is_zero() {
+            DEFAULT_BLOCK_TIME
+        } else {
+            self.block_time
+        }
+    }
+
+    /// Returns a scoring epoch, spanning 6 blocks.
+    fn epoch(&self) -> Duration {
+        6 * self.slot()
+    }
+
+    /// Returns the decay factor applied each block for a score to decay to zero
+    /// over the given duration.
+    fn decay(&self, duration: Duration) -> f64 {
+        let times = duration.as_secs_f64() / self.slot().as_secs_f64();
+        DECAY_TO_ZERO.powf(1.0 / times)
+    }
+
+    /// Returns the [PeerScoreParams] scoring the given blocks topics.
+    pub fn peer_score_params(
+        &self,
+        topics: impl IntoIterator<Item = TopicHash>,
+    ) -> PeerScoreParams {
+        let topic_params = self.topic_score_params();
+        PeerScoreParams {
+            topics: topics.into_iter().map(|topic| (topic, topic_params.clone())).collect(),
+            topic_score_cap: 34.0,
+            app_specific_weight: 1.0,
+            ip_colocation_factor_weight: self.ip_colocation_factor_weight,
+            ip_colocation_factor_threshold: self.ip_colocation_factor_threshold,
+            ip_colocation_factor_whitelist: self.ip_colocation_factor_whitelist.clone(),
+            behaviour_penalty_weight: -16.0,
+            behaviour_penalty_threshold: 6.0,
+            behaviour_penalty_decay: self.decay(50 * self.epoch()),
+            decay_interval: self.slot(),
+            decay_to_zero: DECAY_TO_ZERO,
+            retain_score: 100 * self.epoch(),
+        }
+    }
+
+    /// Returns the [TopicScoreParams] of a blocks topic.
+    pub fn topic_score_params(&self) -> TopicScoreParams {
+        let (slot, epoch) = (self.slot(), self.epoch());
+        let in_mesh_cap = 3600.0 / slot.as_secs_f64();
+        TopicScoreParams {
+            topic_weight: self.topic_weight,
+            time_in_mesh_weight: MAX_IN_MESH_SCORE / in_mesh_cap,
+            time_in_mesh_quantum: slot,
+            time_in_mesh_cap: in_mesh_cap,
+            first_message_deliveries_weight: 1.0,
+            first_message_deliveries_decay: self.decay(20 * epoch),
+            first_message_deliveries_cap: 23.0,
+            mesh_message_deliveries_weight: -0.7,
+            mesh_message_deliveries_decay: self.decay(DECAY_EPOCHS * epoch),
+            mesh_message_deliveries_cap: 2.0,
+            mesh_message_deliveries_threshold: 1.0,
+            mesh_message_deliveries_window: Duration::from_secs(2),
+            mesh_message_deliveries_activation: 4 * epoch,
+            mesh_failure_penalty_weight: -0.7,
+            mesh_failure_penalty_decay: self.decay(DECAY_EPOCHS * epoch),
+            invalid_message_deliveries_weight: self.invalid_message_deliveries_weight,
+            invalid_message_deliveries_decay: self.decay(50 * epoch),
+        }
+    }
+
+    /// Returns the [PeerScoreThresholds] used by op-node.
+    pub fn thresholds(&self) -> PeerScoreThresholds {
+        PeerScoreThresholds {
+            gossip_threshold: -10.0,
+            publish_threshold: -40.0,
+            graylist_threshold: -40.0,
+            accept_px_threshold: 20.0,
+            opportunistic_graft_threshold: 0.05,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libp2p::gossipsub::IdentTopic;
+
+    #[test]
+    fn test_default_params_are_valid() {
+        let scoring = PeerScoring::default();
+        let topic = IdentTopic::new("/optimism/10/2/blocks").hash();
+        let params = scoring.peer_score_params([topic.clone()]);
+        assert!(params.validate().is_ok());
+        assert!(scoring.thresholds().validate().is_ok());
+        assert_eq!(params.topics[&topic].time_in_mesh_cap, 1800.0);
+    }
+
+    #[test]
+    fn test_decay() {
+        let scoring = PeerScoring::default();
+        // A score decays to zero after as many blocks as fit in the duration.
+        let decay = scoring.decay(Duration::from_secs(20));
+        assert!((decay.powi(10) - DECAY_TO_ZERO).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,121 @@
+//! Unsafe payload signing for sequencers
+
+use crate::types::envelope::ExecutionPayloadEnvelope;
+use alloy::{
+    primitives::Address,
+    signers::{local::PrivateKeySigner, SignerSync},
+};
+use eyre::{bail, Result};
+
+/// The activation timestamps of the hardforks that change the blocks topic.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ForkSchedule {
+    /// The Canyon activation timestamp, from which blocks are published on `blocks/v2`.
+    pub canyon_time: Option<u64>,
+    /// The Ecotone activation timestamp, from which blocks are published on `blocks/v3`.
+    pub ecotone_time: Option<u64>,
+    /// The Isthmus activation timestamp, from which blocks are published on `blocks/v4`.
+    pub isthmus_time: Option<u64>,
+}
+
+impl ForkSchedule {
+    /// Returns the blocks topic version (`0` for v1, `1` for v2, ...) of a block with
+    /// the given timestamp.
+    pub fn blocks_version(&self, timestamp: u64) -> u8 {
+        let active = |time: Option<u64>| time.is_some_and(|time| timestamp >= time);
+        if active(self.isthmus_time) {
+            3
+        } else if active(self.ecotone_time) {
+            2
+        } else if active(self.canyon_time) {
+            1
+        } else {
+            0
+        }
+    }
+}
+
+/// Signs the unsafe payloads of a sequencer with its unsafe block signer key.
+#[derive(Debug, Clone)]
+pub struct SequencerSigner {
+    /// The unsafe block signer key.
+    signer: PrivateKeySigner,
+    /// The chain ID of the L2 chain, part of the signed message.
+    chain_id: u64,
+    /// The hardfork schedule of the L2 chain.
+    forks: ForkSchedule,
+}
+
+impl SequencerSigner {
+    /// Creates a new [SequencerSigner].
+    pub const fn new(signer: PrivateKeySigner, chain_id: u64, forks: ForkSchedule) -> Self {
+        Self { signer, chain_id, forks }
+    }
+
+    /// Returns the address of the unsafe block signer.
+    pub fn address(&self) -> Address {
+        self.signer.address()
+    }
+
+    /// Returns the blocks topic version of the given payload.
+    pub fn version(&self, envelope: &ExecutionPayloadEnvelope) -> u8 {
+        self.forks.blocks_version(envelope.payload.timestamp)
+    }
+
+    /// Signs the payload of the given envelope, replacing its hash and signature.
+    ///
+    /// The payload hash is computed over the SSZ encoding of the blocks topic version
+    /// active at the payload's timestamp.
+    pub fn sign(&self, mut envelope: ExecutionPayloadEnvelope) -> Result<ExecutionPayloadEnvelope> {
+        let version = self.version(&envelope);
+        if version >= 2 && envelope.parent_beacon_block_root.is_none() {
+            bail!("missing parent beacon block root of Ecotone payload");
+        }
+        if version >= 3 && envelope.withdrawals_root.is_none() {
+            bail!("missing withdrawals root of Isthmus payload");
+        }
+
+        envelope.hash = envelope.hash_for_version(version)?;
+        let msg = envelope.hash.signature_message(self.chain_id);
+        envelope.signature = self.signer.sign_hash_sync(&msg)?;
+        Ok(envelope)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::payload::{ExecutionPayloadV2SSZ, PayloadHash};
+    use alloy::primitives::{Signature, U256};
+    use kona_primitives::L2ExecutionPayload;
+
+    #[test]
+    fn test_blocks_version() {
+        let forks =
+            ForkSchedule { canyon_time: Some(10), ecotone_time: Some(20), isthmus_time: Some(30) };
+        assert_eq!(forks.blocks_version(9), 0);
+        assert_eq!(forks.blocks_version(10), 1);
+        assert_eq!(forks.blocks_version(20), 2);
+        assert_eq!(forks.blocks_version(30), 3);
+        assert_eq!(ForkSchedule::default().blocks_version(u64::MAX), 0);
+    }
+
+    #[test]
+    fn test_sign() {
+        let forks = ForkSchedule { canyon_time: Some(0), ecotone_time: None, isthmus_time: None };
+        let signer = SequencerSigner::new(PrivateKeySigner::random(), 10, forks);
+        let payload = L2ExecutionPayload::from(ExecutionPayloadV2SSZ::default());
+        let envelope = ExecutionPayloadEnvelope {
+            payload: payload.clone(),
+            signature: Signature::from_rs_and_parity(U256::from(1), U256::from(2), false).unwrap(),
+            hash: PayloadHash::default(),
+            parent_beacon_block_root: None,
+            withdrawals_root: None,
+        };
+
+        let signed = signer.sign(envelope).unwrap();
+        assert_eq!(signed.hash, ExecutionPayloadEnvelope::payload_hash(&payload, 1).unwrap());
+        let msg = signed.hash.signature_message(10);
+        assert_eq!(signed.signature.recover_address_from_prehash(&msg).unwrap(), signer.address());
+    }
+}
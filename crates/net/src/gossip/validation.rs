@@ -0,0 +1,425 @@
+//! Semantic validation of the gossiped payloads, as mandated by the
+//! [p2p spec](https://specs.optimism.io/protocol/rollup-node-p2p.html#block-validation).
+
+use crate::{gossip::sequencer::ForkSchedule, types::envelope::ExecutionPayloadEnvelope};
+use alloy::{
+    consensus::{constants::EMPTY_OMMER_ROOT_HASH, Header},
+    primitives::{Address, Signature, B256},
+};
+use alloy_trie::{root::ordered_trie_root_with_encoder, EMPTY_ROOT_HASH};
+use lru::LruCache;
+use rayon::prelude::*;
+use std::{
+    collections::{BTreeMap, HashSet},
+    fmt,
+    num::NonZeroUsize,
+};
+
+/// How far in the future the timestamp of a payload may be, in seconds.
+pub const MAX_FUTURE_DRIFT: u64 = 5;
+
+/// How far in the past the timestamp of a payload may be, in seconds.
+pub const MAX_PAST_DRIFT: u64 = 60;
+
+/// The maximum number of different blocks accepted at the same height.
+///
+/// More blocks at a height mean the unsafe block signer equivocates, and are rejected.
+pub const MAX_BLOCKS_PER_HEIGHT: usize = 5;
+
+/// The number of block heights whose seen blocks are remembered.
+const SEEN_HEIGHTS: usize = 1024;
+
+/// The number of recovered unsafe block signers to remember.
+pub const SIGNATURE_CACHE_SIZE: usize = 1024;
+
+/// The number of signatures from which [recover_signers] spreads them across the rayon
+/// thread pool. A recovery takes tens of microseconds, well over the cost of handing
+/// work to the pool.
+const PARALLEL_RECOVERY_THRESHOLD: usize = 4;
+
+/// A violation of the gossip validation rules by a payload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PayloadError {
+    /// The timestamp is more than [MAX_FUTURE_DRIFT] seconds in the future.
+    FutureTimestamp,
+    /// The timestamp is more than [MAX_PAST_DRIFT] seconds in the past.
+    PastTimestamp,
+    /// The block hash doesn't match the contents of the payload.
+    BlockHash,
+    /// The withdrawals are present before Canyon, or not empty after it.
+    Withdrawals,
+    /// The blob gas fields are present before Ecotone, or not zero after it.
+    BlobGas,
+    /// The parent beacon block root is missing after Ecotone.
+    ParentBeaconBlockRoot,
+    /// The withdrawals root is present before Isthmus, or missing after it.
+    WithdrawalsRoot,
+    /// The blocks topic version doesn't match the hardfork active at the timestamp.
+    Hardfork,
+}
+
+impl PayloadError {
+    /// Returns the metrics label of the error.
+    pub const fn label(&self) -> &'static str {
+        match self {
+            Self::FutureTimestamp => "future_timestamp",
+            Self::PastTimestamp => "past_timestamp",
+            Self::BlockHash => "block_hash",
+            Self::Withdrawals => "withdrawals",
+            Self::BlobGas => "blob_gas",
+            Self::ParentBeaconBlockRoot => "parent_beacon_block_root",
+            Self::WithdrawalsRoot => "withdrawals_root",
+            Self::Hardfork => "hardfork",
+        }
+    }
+}
+
+impl fmt::Display for PayloadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::FutureTimestamp => write!(f, "timestamp too far in the future"),
+            Self::PastTimestamp => write!(f, "timestamp too far in the past"),
+            Self::BlockHash => write!(f, "block hash doesn't match the payload"),
+            Self::Withdrawals => write!(f, "unexpected withdrawals for the topic version"),
+            Self::BlobGas => write!(f, "unexpected blob gas fields for the topic version"),
+            Self::ParentBeaconBlockRoot => write!(f, "missing parent beacon block root"),
+            Self::WithdrawalsRoot => write!(f, "unexpected withdrawals root for the topic version"),
+            Self::Hardfork => write!(f, "topic version doesn't match the active hardfork"),
+        }
+    }
+}
+
+/// Checks the given payload, received on the blocks topic of the given version (`0` for
+/// v1, `1` for v2, ...), against the validation rules that don't depend on the signer or
+/// on the other received payloads, at the given unix time in seconds.
+pub fn check_payload(
+    envelope: &ExecutionPayloadEnvelope,
+    version: u8,
+    now: u64,
+) -> Result<(), PayloadError> {
+    check_timestamp(envelope.payload.timestamp, now)?;
+    check_fields(envelope, version)?;
+    if block_hash(envelope) != envelope.payload.block_hash {
+        return Err(PayloadError::BlockHash);
+    }
+    Ok(())
+}
+
+/// Checks that the given payload timestamp is close enough to the given unix time.
+pub const fn check_timestamp(timestamp: u64, now: u64) -> Result<(), PayloadError> {
+    if timestamp > now.saturating_add(MAX_FUTURE_DRIFT) {
+        return Err(PayloadError::FutureTimestamp);
+    }
+    if timestamp < now.saturating_sub(MAX_PAST_DRIFT) {
+        return Err(PayloadError::PastTimestamp);
+    }
+    Ok(())
+}
+
+/// Checks that the fork specific fields of the given payload match the topic version.
+pub fn check_fields(envelope: &ExecutionPayloadEnvelope, version: u8) -> Result<(), PayloadError> {
+    let payload = &envelope.payload;
+    match (version, &payload.withdrawals) {
+        (0, None) => {}
+        (0, Some(_)) | (_, None) => return Err(PayloadError::Withdrawals),
+        (_, Some(withdrawals)) if !withdrawals.is_empty() => return Err(PayloadError::Withdrawals),
+        _ => {}
+    }
+    match (version, payload.blob_gas_used, payload.excess_blob_gas) {
+        (0 | 1, None, None) | (2 | 3, Some(0), Some(0)) => {}
+        _ => return Err(PayloadError::BlobGas),
+    }
+    if version >= 2 && envelope.parent_beacon_block_root.is_none() {
+        return Err(PayloadError::ParentBeaconBlockRoot);
+    }
+    if (version >= 3) != envelope.withdrawals_root.is_some() {
+        return Err(PayloadError::WithdrawalsRoot);
+    }
+    Ok(())
+}
+
+/// Checks that a payload with the given timestamp is on the `blocks/v4` topic if and only
+/// if Isthmus is active at that timestamp according to the given [ForkSchedule].
+///
+/// The earlier topics need no such check, since the fields of another hardfork change
+/// the block hash, but a pre-Isthmus block could be relayed on `blocks/v4` with the empty
+/// withdrawals root.
+pub fn check_hardfork(
+    timestamp: u64,
+    version: u8,
+    forks: &ForkSchedule,
+) -> Result<(), PayloadError> {
+    if (forks.blocks_version(timestamp) >= 3) != (version >= 3) {
+        return Err(PayloadError::Hardfork);
+    }
+    Ok(())
+}
+
+/// Returns the hash of the block header committing to the given payload.
+pub fn block_hash(envelope: &ExecutionPayloadEnvelope) -> B256 {
+    let payload = &envelope.payload;
+    let transactions_root =
+        ordered_trie_root_with_encoder(&payload.transactions, |tx, buf| buf.extend_from_slice(tx));
+    // Withdrawals are always empty on L2, see [check_fields], and from Isthmus the header
+    // commits to the storage root of the `L2ToL1MessagePasser` instead.
+    let withdrawals_root =
+        envelope.withdrawals_root.or_else(|| payload.withdrawals.as_ref().map(|_| EMPTY_ROOT_HASH));
+    let header = Header {
+        parent_hash: payload.parent_hash,
+        ommers_hash: EMPTY_OMMER_ROOT_HASH,
+        beneficiary: payload.fee_recipient,
+        state_root: payload.state_root,
+        transactions_root,
+        receipts_root: payload.receipts_root,
+        withdrawals_root,
+        logs_bloom: payload.logs_bloom,
+        number: payload.block_number,
+        gas_limit: payload.gas_limit,
+        gas_used: payload.gas_used,
+        timestamp: payload.timestamp,
+        mix_hash: payload.prev_randao,
+        base_fee_per_gas: payload.base_fee_per_gas,
+        blob_gas_used: payload.blob_gas_used,
+        excess_blob_gas: payload.excess_blob_gas,
+        parent_beacon_block_root: envelope.parent_beacon_block_root,
+        extra_data: payload.extra_data.clone(),
+        ..Default::default()
+    };
+    header.hash_slow()
+}
+
+/// Whether a block was seen before, see [SeenBlocks::observe].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Seen {
+    /// The block is new.
+    New,
+    /// The block was already seen.
+    Duplicate,
+    /// Too many other blocks were seen at the same height.
+    Equivocation,
+}
+
+/// The hashes of the recently seen blocks, by height, to ignore duplicates and detect
+/// equivocating signers.
+#[derive(Debug, Default)]
+pub struct SeenBlocks {
+    /// The hashes of the blocks seen at each height.
+    heights: BTreeMap<u64, Vec<B256>>,
+}
+
+impl SeenBlocks {
+    /// Records the given validly signed block, returning whether it was seen before.
+    ///
+    /// Blocks past the [MAX_BLOCKS_PER_HEIGHT] different ones at their height are not
+    /// recorded.
+    pub fn observe(&mut self, number: u64, hash: B256) -> Seen {
+        let hashes = self.heights.entry(number).or_default();
+        if hashes.contains(&hash) {
+            return Seen::Duplicate;
+        }
+        if hashes.len() >= MAX_BLOCKS_PER_HEIGHT {
+            return Seen::Equivocation;
+        }
+        hashes.push(hash);
+        if self.heights.len() > SEEN_HEIGHTS {
+            self.heights.pop_first();
+        }
+        Seen::New
+    }
+}
+
+/// The signers recovered from the recent unsafe block signatures, by signed message.
+///
+/// Signature recovery is the most expensive step of the block validation, and a well
+/// connected node receives each block from many peers, possibly encoded differently.
+#[derive(Debug)]
+pub struct SignatureCache {
+    /// The recovered signers, `None` for invalid signatures.
+    signers: LruCache<(B256, [u8; 65]), Option<Address>>,
+}
+
+impl Default for SignatureCache {
+    fn default() -> Self {
+        Self::new(NonZeroUsize::new(SIGNATURE_CACHE_SIZE).expect("non-zero cache size"))
+    }
+}
+
+impl SignatureCache {
+    /// Creates a [SignatureCache] remembering up to `capacity` signers.
+    pub fn new(capacity: NonZeroUsize) -> Self {
+        Self { signers: LruCache::new(capacity) }
+    }
+
+    /// Returns the signer of the given message, recovering it from the signature unless
+    /// it was recovered before, or `None` if the signature is invalid.
+    pub fn recover(&mut self, signature: &Signature, msg: &B256) -> Option<Address> {
+        let key = (*msg, signature.as_bytes());
+        if let Some(signer) = self.signers.get(&key) {
+            metrics::counter!("hera_gossip_signature_cache_hits_total").increment(1);
+            return *signer;
+        }
+        metrics::counter!("hera_gossip_signature_cache_misses_total").increment(1);
+        let signer = signature.recover_address_from_prehash(msg).ok();
+        self.signers.put(key, signer);
+        signer
+    }
+
+    /// Returns `true` if the signer of the given signature and message is cached.
+    pub fn contains(&self, signature: &Signature, msg: &B256) -> bool {
+        self.signers.contains(&(*msg, signature.as_bytes()))
+    }
+
+    /// Recovers the signers of the given signatures and messages that aren't cached yet,
+    /// at once, so that the following [Self::recover] calls hit the cache.
+    pub fn recover_batch(&mut self, signatures: &[(Signature, B256)]) {
+        let mut keys = HashSet::new();
+        let pending: Vec<_> = signatures
+            .iter()
+            .filter(|(signature, msg)| {
+                let key = (*msg, signature.as_bytes());
+                !self.signers.contains(&key) && keys.insert(key)
+            })
+            .copied()
+            .collect();
+        if pending.is_empty() {
+            return;
+        }
+        metrics::histogram!("hera_gossip_signature_batch_size").record(pending.len() as f64);
+        for ((signature, msg), signer) in pending.iter().zip(recover_signers(&pending)) {
+            self.signers.put((*msg, signature.as_bytes()), signer);
+        }
+    }
+}
+
+/// Recovers the signers of the given signatures and messages, in order, `None` for the
+/// invalid signatures.
+///
+/// Batches of at least [PARALLEL_RECOVERY_THRESHOLD] signatures are split across the
+/// global rayon thread pool, instead of spawning threads for each batch.
+pub fn recover_signers(signatures: &[(Signature, B256)]) -> Vec<Option<Address>> {
+    let recover = |(signature, msg): &(Signature, B256)| -> Option<Address> {
+        signature.recover_address_from_prehash(msg).ok()
+    };
+    if signatures.len() < PARALLEL_RECOVERY_THRESHOLD {
+        return signatures.iter().map(recover).collect();
+    }
+    signatures.par_iter().map(recover).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::payload::{ExecutionPayloadV3SSZ, PayloadHash};
+    use alloy::{
+        primitives::U256,
+        signers::{local::PrivateKeySigner, SignerSync},
+    };
+    use kona_primitives::L2ExecutionPayload;
+
+    fn envelope() -> ExecutionPayloadEnvelope {
+        let mut envelope = ExecutionPayloadEnvelope {
+            payload: L2ExecutionPayload::from(ExecutionPayloadV3SSZ {
+                timestamp: 100,
+                ..Default::default()
+            }),
+            signature: Signature::from_rs_and_parity(U256::from(1), U256::from(2), false).unwrap(),
+            hash: PayloadHash::default(),
+            parent_beacon_block_root: Some(B256::repeat_byte(0xaa)),
+            withdrawals_root: None,
+        };
+        envelope.payload.block_hash = block_hash(&envelope);
+        envelope
+    }
+
+    #[test]
+    fn test_check_timestamp() {
+        assert_eq!(check_timestamp(100, 100), Ok(()));
+        assert_eq!(check_timestamp(105, 100), Ok(()));
+        assert_eq!(check_timestamp(106, 100), Err(PayloadError::FutureTimestamp));
+        assert_eq!(check_timestamp(40, 100), Ok(()));
+        assert_eq!(check_timestamp(39, 100), Err(PayloadError::PastTimestamp));
+        assert_eq!(check_timestamp(0, 30), Ok(()));
+    }
+
+    #[test]
+    fn test_check_payload() {
+        let mut envelope = envelope();
+        assert_eq!(check_payload(&envelope, 2, 100), Ok(()));
+        assert_eq!(check_payload(&envelope, 1, 100), Err(PayloadError::BlobGas));
+        assert_eq!(check_payload(&envelope, 0, 100), Err(PayloadError::Withdrawals));
+
+        envelope.payload.excess_blob_gas = Some(1);
+        assert_eq!(check_payload(&envelope, 2, 100), Err(PayloadError::BlobGas));
+        envelope.payload.excess_blob_gas = Some(0);
+
+        envelope.parent_beacon_block_root = Some(B256::repeat_byte(0xbb));
+        assert_eq!(check_payload(&envelope, 2, 100), Err(PayloadError::BlockHash));
+        envelope.parent_beacon_block_root = None;
+        assert_eq!(check_payload(&envelope, 2, 100), Err(PayloadError::ParentBeaconBlockRoot));
+    }
+
+    #[test]
+    fn test_check_payload_isthmus() {
+        let mut envelope = envelope();
+        assert_eq!(check_payload(&envelope, 3, 100), Err(PayloadError::WithdrawalsRoot));
+
+        envelope.withdrawals_root = Some(B256::repeat_byte(0xcc));
+        assert_eq!(check_payload(&envelope, 3, 100), Err(PayloadError::BlockHash));
+        envelope.payload.block_hash = block_hash(&envelope);
+        assert_eq!(check_payload(&envelope, 3, 100), Ok(()));
+        assert_eq!(check_payload(&envelope, 2, 100), Err(PayloadError::WithdrawalsRoot));
+
+        let forks =
+            ForkSchedule { canyon_time: Some(0), ecotone_time: Some(0), isthmus_time: Some(100) };
+        assert_eq!(check_hardfork(100, 3, &forks), Ok(()));
+        assert_eq!(check_hardfork(99, 3, &forks), Err(PayloadError::Hardfork));
+        assert_eq!(check_hardfork(99, 2, &forks), Ok(()));
+        assert_eq!(check_hardfork(100, 2, &forks), Err(PayloadError::Hardfork));
+    }
+
+    #[test]
+    fn test_seen_blocks() {
+        let mut seen = SeenBlocks::default();
+        assert_eq!(seen.observe(1, B256::repeat_byte(0)), Seen::New);
+        assert_eq!(seen.observe(1, B256::repeat_byte(0)), Seen::Duplicate);
+        for byte in 1..MAX_BLOCKS_PER_HEIGHT as u8 {
+            assert_eq!(seen.observe(1, B256::repeat_byte(byte)), Seen::New);
+        }
+        assert_eq!(seen.observe(1, B256::repeat_byte(0xff)), Seen::Equivocation);
+        assert_eq!(seen.observe(2, B256::repeat_byte(0xff)), Seen::New);
+
+        for number in 3..SEEN_HEIGHTS as u64 + 2 {
+            seen.observe(number, B256::ZERO);
+        }
+        assert_eq!(seen.heights.len(), SEEN_HEIGHTS);
+        assert_eq!(seen.observe(1, B256::repeat_byte(0)), Seen::New);
+    }
+
+    #[test]
+    fn test_signature_cache() {
+        let signer = PrivateKeySigner::random();
+        let signatures: Vec<_> = (0..8u8)
+            .map(|byte| {
+                let msg = B256::repeat_byte(byte);
+                (signer.sign_hash_sync(&msg).unwrap(), msg)
+            })
+            .collect();
+        let signers = recover_signers(&signatures);
+        assert_eq!(signers, vec![Some(signer.address()); signatures.len()]);
+
+        let mut cache = SignatureCache::new(NonZeroUsize::new(4).unwrap());
+        cache.recover_batch(&[signatures[0], signatures[1], signatures[0]]);
+        assert_eq!(cache.signers.len(), 2);
+        let (signature, msg) = signatures[1];
+        assert!(cache.contains(&signature, &msg));
+        assert_eq!(cache.recover(&signature, &msg), Some(signer.address()));
+
+        // A signature of another message recovers another signer.
+        let other = B256::repeat_byte(0xff);
+        assert_ne!(cache.recover(&signature, &other), Some(signer.address()));
+        assert_eq!(cache.signers.len(), 3);
+
+        cache.recover_batch(&signatures);
+        assert_eq!(cache.signers.len(), 4);
+    }
+}
@@ -0,0 +1,111 @@
+//! Node key derivation.
+
+use alloy::{
+    primitives::hex,
+    signers::local::{coins_bip39::English, MnemonicBuilder},
+};
+use eyre::{Result, WrapErr};
+use libp2p_identity::{secp256k1, Keypair};
+use std::{fs, path::Path};
+use tracing::info;
+
+/// The default BIP-32 derivation path used to derive the node key from a mnemonic.
+pub const DEFAULT_DERIVATION_PATH: &str = "m/44'/60'/0'/0/0";
+
+/// Deterministically derives a secp256k1 [Keypair] from a BIP-39 mnemonic phrase
+/// and a BIP-32 derivation path.
+///
+/// This allows fleets of nodes to manage their p2p identities from a single seed,
+/// using a different derivation path for each node.
+pub fn keypair_from_mnemonic(phrase: &str, derivation_path: &str) -> Result<Keypair> {
+    let signer = MnemonicBuilder::<English>::default()
+        .phrase(phrase)
+        .derivation_path(derivation_path)?
+        .build()?;
+    keypair_from_secret(signer.credential().to_bytes().as_slice())
+}
+
+/// Creates a secp256k1 [Keypair] from the raw bytes of a private key.
+pub fn keypair_from_secret(secret: &[u8]) -> Result<Keypair> {
+    let mut secret = secret.to_vec();
+    let secret = secp256k1::SecretKey::try_from_bytes(&mut secret)
+        .map_err(|e| eyre::eyre!("invalid secp256k1 secret key: {}", e))?;
+    Ok(secp256k1::Keypair::from(secret).into())
+}
+
+/// Loads the hex-encoded secp256k1 node key stored at the given path.
+pub fn load_keypair(path: &Path) -> Result<Keypair> {
+    let hex = fs::read_to_string(path).wrap_err("Failed to read the node key")?;
+    let secret = hex::decode(hex.trim()).wrap_err("Invalid hex node key")?;
+    keypair_from_secret(&secret)
+}
+
+/// Loads the hex-encoded secp256k1 node key stored at the given path, or generates
+/// a new one and stores it there if the file doesn't exist.
+///
+/// Persisting the node key keeps the peer ID and ENR of the node stable across
+/// restarts, which peer scores and bootnode listings rely on.
+pub fn load_or_generate_keypair(path: &Path) -> Result<Keypair> {
+    if path.exists() {
+        return load_keypair(path);
+    }
+
+    let keypair = secp256k1::Keypair::generate();
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(path, hex::encode(keypair.secret().to_bytes()))
+        .wrap_err("Failed to write the node key")?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+    }
+    info!("Generated a new node key at {:?}", path);
+    Ok(keypair.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::hex;
+
+    const TEST_MNEMONIC: &str = "test test test test test test test test test test test junk";
+
+    #[test]
+    fn test_keypair_from_mnemonic() {
+        let derived = keypair_from_mnemonic(TEST_MNEMONIC, DEFAULT_DERIVATION_PATH).unwrap();
+        let secret = hex!("ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80");
+        let expected = keypair_from_secret(&secret).unwrap();
+        assert_eq!(derived.public(), expected.public());
+    }
+
+    #[test]
+    fn test_keypair_from_mnemonic_different_paths() {
+        let first = keypair_from_mnemonic(TEST_MNEMONIC, "m/44'/60'/0'/0/0").unwrap();
+        let second = keypair_from_mnemonic(TEST_MNEMONIC, "m/44'/60'/0'/0/1").unwrap();
+        assert_ne!(first.public(), second.public());
+    }
+
+    #[test]
+    fn test_keypair_from_invalid_mnemonic() {
+        assert!(keypair_from_mnemonic("not a mnemonic", DEFAULT_DERIVATION_PATH).is_err());
+    }
+
+    #[test]
+    fn test_load_or_generate_keypair() {
+        let dir = std::env::temp_dir().join(format!("hera-node-key-{}", std::process::id()));
+        _ = fs::remove_dir_all(&dir);
+        let path = dir.join("p2p.key");
+
+        let generated = load_or_generate_keypair(&path).unwrap();
+        let loaded = load_or_generate_keypair(&path).unwrap();
+        assert_eq!(generated.public(), loaded.public());
+        assert_eq!(load_keypair(&path).unwrap().public(), generated.public());
+        assert!(load_keypair(&dir.join("missing.key")).is_err());
+
+        fs::write(&path, "not hex").unwrap();
+        assert!(load_or_generate_keypair(&path).is_err());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -5,7 +5,10 @@
 
 pub mod discovery;
 pub mod gossip;
+pub mod sync;
 pub mod types;
 
 pub mod builder;
 pub mod driver;
+pub mod keys;
+pub mod telemetry;
@@ -0,0 +1,113 @@
+//! Payload Sync Client
+
+use crate::sync::codec::SyncedPayload;
+use eyre::{eyre, Result};
+use libp2p::PeerId;
+use std::ops::RangeInclusive;
+use tokio::sync::{mpsc, oneshot};
+use tracing::debug;
+
+/// The number of peers a payload is requested from before giving up on it.
+pub const DEFAULT_SYNC_ATTEMPTS: usize = 3;
+
+/// A request for the payload with the given number, from the given peer or any
+/// connected peer, with a channel to send the result back on.
+pub type SyncRequest = (Option<PeerId>, u64, oneshot::Sender<Result<SyncedPayload>>);
+
+/// Requests unsafe payloads by number from peers over the `payload_by_number`
+/// protocol, e.g. to backfill the payloads a freshly started node missed on gossip.
+///
+/// Requests are sent from the gossip event loop. Synced payloads are not signed,
+/// so they must be validated by the execution engine before being trusted.
+#[derive(Debug, Clone)]
+pub struct SyncClient {
+    /// Sends requests to the gossip event loop.
+    requests: mpsc::UnboundedSender<SyncRequest>,
+    /// The number of peers a payload is requested from before giving up on it.
+    attempts: usize,
+}
+
+impl SyncClient {
+    /// Creates a new [SyncClient].
+    pub const fn new(requests: mpsc::UnboundedSender<SyncRequest>) -> Self {
+        Self { requests, attempts: DEFAULT_SYNC_ATTEMPTS }
+    }
+
+    /// Sets the number of peers a payload is requested from before giving up on it.
+    pub fn with_attempts(mut self, attempts: usize) -> Self {
+        self.attempts = attempts.max(1);
+        self
+    }
+
+    /// Requests the payload with the given number from the next connected peer.
+    pub async fn payload_by_number(&self, number: u64) -> Result<SyncedPayload> {
+        self.request(None, number).await
+    }
+
+    /// Requests the payload with the given number from the given peer.
+    pub async fn payload_by_number_from(&self, peer: PeerId, number: u64) -> Result<SyncedPayload> {
+        self.request(Some(peer), number).await
+    }
+
+    /// Requests the payloads in the given range of numbers, in order.
+    ///
+    /// Each payload is requested from up to [Self::with_attempts] connected peers
+    /// in turn. Returns an error if any of them could not be synced.
+    pub async fn payloads_by_range(
+        &self,
+        range: RangeInclusive<u64>,
+    ) -> Result<Vec<SyncedPayload>> {
+        let mut payloads = Vec::new();
+        for number in range {
+            let mut attempt = 1;
+            let payload = loop {
+                match self.payload_by_number(number).await {
+                    Ok(payload) => break payload,
+                    Err(e) if attempt < self.attempts => {
+                        debug!("Failed to sync payload {} (attempt {}): {}", number, attempt, e);
+                        attempt += 1;
+                    }
+                    Err(e) => return Err(e.wrap_err(format!("failed to sync payload {}", number))),
+                }
+            };
+            payloads.push(payload);
+        }
+        Ok(payloads)
+    }
+
+    /// Sends a request to the gossip event loop and waits for its result.
+    async fn request(&self, peer: Option<PeerId>, number: u64) -> Result<SyncedPayload> {
+        let (sender, recv) = oneshot::channel();
+        self.requests
+            .send((peer, number, sender))
+            .map_err(|_| eyre!("network driver is not running"))?;
+        recv.await.map_err(|_| eyre!("network driver is not running"))?
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_payloads_by_range_retries() {
+        let (requests, mut recv) = mpsc::unbounded_channel::<SyncRequest>();
+        let client = SyncClient::new(requests).with_attempts(2);
+        tokio::spawn(async move {
+            while let Some((_, number, result)) = recv.recv().await {
+                _ = result.send(Err(eyre!("payload {} not found", number)));
+            }
+        });
+
+        let err = client.payloads_by_range(1..=2).await.unwrap_err();
+        assert_eq!(err.to_string(), "failed to sync payload 1");
+    }
+
+    #[tokio::test]
+    async fn test_request_without_driver() {
+        let (requests, recv) = mpsc::unbounded_channel();
+        drop(recv);
+        let err = SyncClient::new(requests).payload_by_number(1).await.unwrap_err();
+        assert_eq!(err.to_string(), "network driver is not running");
+    }
+}
@@ -0,0 +1,316 @@
+//! Payload By Number Codec
+
+use crate::types::{
+    envelope::ExecutionPayloadEnvelope,
+    payload::{
+        ExecutionPayloadV1SSZ, ExecutionPayloadV2SSZ, ExecutionPayloadV3SSZ, ExecutionPayloadV4SSZ,
+    },
+};
+use alloy::primitives::B256;
+use async_trait::async_trait;
+use eyre::{bail, Result};
+use futures::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use kona_primitives::L2ExecutionPayload;
+use libp2p::{request_response::Codec, StreamProtocol};
+use ssz_rs::prelude::*;
+use std::io;
+
+/// The maximum size of an uncompressed payload, as enforced by op-node.
+pub const MAX_PAYLOAD_SIZE: usize = 10 * 1024 * 1024;
+
+/// The maximum size of a compressed payload, leaving room for incompressible
+/// data and snappy frame headers.
+const MAX_COMPRESSED_SIZE: usize = MAX_PAYLOAD_SIZE + MAX_PAYLOAD_SIZE / 4;
+
+/// The result code of a response carrying the requested payload.
+pub const RESULT_SUCCESS: u8 = 0;
+/// The result code of a response to a request for an unknown payload.
+pub const RESULT_NOT_FOUND: u8 = 1;
+/// The result code of a response to an invalid request.
+pub const RESULT_INVALID_REQUEST: u8 = 2;
+/// The result code of a response to a request that failed on the serving peer.
+pub const RESULT_ERROR: u8 = 3;
+
+/// The response version of a pre-Ecotone payload: `ssz(payload)`.
+const VERSION_PAYLOAD: u32 = 0;
+/// The response version of an Ecotone or Isthmus payload:
+/// `parent_beacon_block_root ++ ssz(payload)`.
+const VERSION_ENVELOPE: u32 = 1;
+
+/// Returns the `payload_by_number` protocol of the given chain.
+pub fn protocol(chain_id: u64) -> StreamProtocol {
+    StreamProtocol::try_from_owned(format!("/opstack/req/payload_by_number/{}/0", chain_id))
+        .expect("protocol name starts with a slash")
+}
+
+/// An unsafe payload served over the `payload_by_number` protocol.
+///
+/// Unlike gossiped payloads, synced payloads are not signed by the unsafe block
+/// signer, so they must only be trusted once the execution engine validated them.
+#[derive(Debug, Clone)]
+pub struct SyncedPayload {
+    /// The execution payload.
+    pub payload: L2ExecutionPayload,
+    /// The parent beacon block root, for Ecotone payloads.
+    pub parent_beacon_block_root: Option<B256>,
+    /// The withdrawals root of the block header, for Isthmus payloads.
+    pub withdrawals_root: Option<B256>,
+}
+
+impl From<&ExecutionPayloadEnvelope> for SyncedPayload {
+    fn from(envelope: &ExecutionPayloadEnvelope) -> Self {
+        Self {
+            payload: envelope.payload.clone(),
+            parent_beacon_block_root: envelope.parent_beacon_block_root,
+            withdrawals_root: envelope.withdrawals_root,
+        }
+    }
+}
+
+impl SyncedPayload {
+    /// Encodes the payload as the version and body of a successful response.
+    fn encode(&self) -> Result<(u32, Vec<u8>)> {
+        if let Some(root) = self.parent_beacon_block_root {
+            let payload = match self.withdrawals_root {
+                Some(withdrawals_root) => ssz_rs::serialize(
+                    &ExecutionPayloadV4SSZ::try_from_payload(&self.payload, withdrawals_root)?,
+                )?,
+                None => ssz_rs::serialize(&ExecutionPayloadV3SSZ::try_from(&self.payload)?)?,
+            };
+            return Ok((VERSION_ENVELOPE, [root.as_slice(), payload.as_slice()].concat()));
+        }
+        let payload = if self.payload.withdrawals.is_some() {
+            ssz_rs::serialize(&ExecutionPayloadV2SSZ::try_from(&self.payload)?)?
+        } else {
+            ssz_rs::serialize(&ExecutionPayloadV1SSZ::try_from(&self.payload)?)?
+        };
+        Ok((VERSION_PAYLOAD, payload))
+    }
+
+    /// Decodes a payload from the version and body of a successful response.
+    ///
+    /// Pre-Ecotone responses don't tell Bedrock and Canyon payloads apart, nor do the
+    /// later ones tell Ecotone and Isthmus payloads apart, but the fixed-size part of
+    /// their SSZ encodings differ, so only one of them decodes.
+    fn decode(version: u32, data: &[u8]) -> Result<Self> {
+        match version {
+            VERSION_PAYLOAD => {
+                let payload = match ExecutionPayloadV1SSZ::deserialize(data) {
+                    Ok(payload) => L2ExecutionPayload::from(payload),
+                    Err(_) => L2ExecutionPayload::from(ExecutionPayloadV2SSZ::deserialize(data)?),
+                };
+                Ok(Self { payload, parent_beacon_block_root: None, withdrawals_root: None })
+            }
+            VERSION_ENVELOPE => {
+                if data.len() < 32 {
+                    bail!("payload envelope too short: {} bytes", data.len());
+                }
+                let parent_beacon_block_root = Some(B256::from_slice(&data[..32]));
+                let (payload, withdrawals_root) =
+                    match ExecutionPayloadV3SSZ::deserialize(&data[32..]) {
+                        Ok(payload) => (L2ExecutionPayload::from(payload), None),
+                        Err(_) => {
+                            let payload = ExecutionPayloadV4SSZ::deserialize(&data[32..])?;
+                            let root = payload.withdrawals_root();
+                            (L2ExecutionPayload::from(payload), Some(root))
+                        }
+                    };
+                Ok(Self { payload, parent_beacon_block_root, withdrawals_root })
+            }
+            _ => bail!("unknown payload version: {}", version),
+        }
+    }
+}
+
+/// A response to a `payload_by_number` request.
+#[derive(Debug, Clone)]
+pub enum SyncResponse {
+    /// The requested payload.
+    Payload(Box<SyncedPayload>),
+    /// The request failed with the given result code, e.g. [RESULT_NOT_FOUND].
+    Error(u8),
+}
+
+/// The [Codec] of the `payload_by_number` protocol.
+///
+/// Requests are the little-endian `u64` number of the requested block. Responses
+/// start with a result code; successful ones follow it with the little-endian
+/// `u32` version of the payload and its snappy-framed encoding.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct PayloadByNumberCodec;
+
+#[async_trait]
+impl Codec for PayloadByNumberCodec {
+    type Protocol = StreamProtocol;
+    type Request = u64;
+    type Response = SyncResponse;
+
+    async fn read_request<T>(&mut self, _: &StreamProtocol, io: &mut T) -> io::Result<u64>
+    where
+        T: AsyncRead + Unpin + Send,
+    {
+        let mut number = [0u8; 8];
+        io.read_exact(&mut number).await?;
+        Ok(u64::from_le_bytes(number))
+    }
+
+    async fn read_response<T>(&mut self, _: &StreamProtocol, io: &mut T) -> io::Result<SyncResponse>
+    where
+        T: AsyncRead + Unpin + Send,
+    {
+        let mut code = [0u8; 1];
+        io.read_exact(&mut code).await?;
+        if code[0] != RESULT_SUCCESS {
+            return Ok(SyncResponse::Error(code[0]));
+        }
+
+        let mut version = [0u8; 4];
+        io.read_exact(&mut version).await?;
+        let mut compressed = Vec::new();
+        io.take(MAX_COMPRESSED_SIZE as u64).read_to_end(&mut compressed).await?;
+
+        let data = decompress(&compressed)?;
+        let payload = SyncedPayload::decode(u32::from_le_bytes(version), &data)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+        Ok(SyncResponse::Payload(Box::new(payload)))
+    }
+
+    async fn write_request<T>(
+        &mut self,
+        _: &StreamProtocol,
+        io: &mut T,
+        number: u64,
+    ) -> io::Result<()>
+    where
+        T: AsyncWrite + Unpin + Send,
+    {
+        io.write_all(&number.to_le_bytes()).await
+    }
+
+    async fn write_response<T>(
+        &mut self,
+        _: &StreamProtocol,
+        io: &mut T,
+        response: SyncResponse,
+    ) -> io::Result<()>
+    where
+        T: AsyncWrite + Unpin + Send,
+    {
+        let payload = match response {
+            SyncResponse::Payload(payload) => payload,
+            SyncResponse::Error(code) => return io.write_all(&[code]).await,
+        };
+        let (version, data) = payload
+            .encode()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e.to_string()))?;
+
+        io.write_all(&[RESULT_SUCCESS]).await?;
+        io.write_all(&version.to_le_bytes()).await?;
+        io.write_all(&compress(&data)?).await
+    }
+}
+
+/// Compresses the given data in the snappy framing format.
+fn compress(data: &[u8]) -> io::Result<Vec<u8>> {
+    use std::io::Read;
+
+    let mut compressed = Vec::new();
+    snap::read::FrameEncoder::new(data).read_to_end(&mut compressed)?;
+    Ok(compressed)
+}
+
+/// Decompresses the given snappy-framed data, up to [MAX_PAYLOAD_SIZE] bytes.
+fn decompress(data: &[u8]) -> io::Result<Vec<u8>> {
+    use std::io::Read;
+
+    let mut decompressed = Vec::new();
+    snap::read::FrameDecoder::new(data)
+        .take(MAX_PAYLOAD_SIZE as u64 + 1)
+        .read_to_end(&mut decompressed)?;
+    if decompressed.len() > MAX_PAYLOAD_SIZE {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "payload too large"));
+    }
+    Ok(decompressed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::io::Cursor;
+
+    async fn roundtrip(response: SyncResponse) -> SyncResponse {
+        let mut codec = PayloadByNumberCodec;
+        let protocol = protocol(10);
+        let mut io = Cursor::new(Vec::new());
+        codec.write_response(&protocol, &mut io, response).await.unwrap();
+        io.set_position(0);
+        codec.read_response(&protocol, &mut io).await.unwrap()
+    }
+
+    #[test]
+    fn test_protocol() {
+        assert_eq!(protocol(10).as_ref(), "/opstack/req/payload_by_number/10/0");
+    }
+
+    #[tokio::test]
+    async fn test_request_roundtrip() {
+        let mut codec = PayloadByNumberCodec;
+        let mut io = Cursor::new(Vec::new());
+        codec.write_request(&protocol(10), &mut io, 0x0102).await.unwrap();
+        assert_eq!(io.get_ref(), &[2, 1, 0, 0, 0, 0, 0, 0]);
+        io.set_position(0);
+        assert_eq!(codec.read_request(&protocol(10), &mut io).await.unwrap(), 0x0102);
+    }
+
+    #[tokio::test]
+    async fn test_response_roundtrip() {
+        let payload = ExecutionPayloadV3SSZ { block_number: 7, ..Default::default() };
+        let synced = SyncedPayload {
+            payload: L2ExecutionPayload::from(payload),
+            parent_beacon_block_root: Some(B256::repeat_byte(0xaa)),
+            withdrawals_root: None,
+        };
+        let SyncResponse::Payload(decoded) =
+            roundtrip(SyncResponse::Payload(Box::new(synced))).await
+        else {
+            panic!("expected a payload");
+        };
+        assert_eq!(decoded.payload.block_number, 7);
+        assert_eq!(decoded.parent_beacon_block_root, Some(B256::repeat_byte(0xaa)));
+        assert_eq!(decoded.withdrawals_root, None);
+
+        let payload = ExecutionPayloadV3SSZ { block_number: 9, ..Default::default() };
+        let synced = SyncedPayload {
+            payload: L2ExecutionPayload::from(payload),
+            parent_beacon_block_root: Some(B256::repeat_byte(0xaa)),
+            withdrawals_root: Some(B256::repeat_byte(0xbb)),
+        };
+        let SyncResponse::Payload(decoded) =
+            roundtrip(SyncResponse::Payload(Box::new(synced))).await
+        else {
+            panic!("expected a payload");
+        };
+        assert_eq!(decoded.payload.block_number, 9);
+        assert_eq!(decoded.withdrawals_root, Some(B256::repeat_byte(0xbb)));
+
+        let payload = ExecutionPayloadV2SSZ { block_number: 8, ..Default::default() };
+        let synced = SyncedPayload {
+            payload: L2ExecutionPayload::from(payload),
+            parent_beacon_block_root: None,
+            withdrawals_root: None,
+        };
+        let SyncResponse::Payload(decoded) =
+            roundtrip(SyncResponse::Payload(Box::new(synced))).await
+        else {
+            panic!("expected a payload");
+        };
+        assert_eq!(decoded.payload.block_number, 8);
+        assert_eq!(decoded.payload.withdrawals, Some(Vec::new()));
+
+        let SyncResponse::Error(code) = roundtrip(SyncResponse::Error(RESULT_NOT_FOUND)).await
+        else {
+            panic!("expected an error");
+        };
+        assert_eq!(code, RESULT_NOT_FOUND);
+    }
+}
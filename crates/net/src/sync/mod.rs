@@ -0,0 +1,6 @@
+//! Module containing the `payload_by_number` request/response protocol, used to
+//! backfill unsafe payloads missed on gossip from peers.
+
+pub mod client;
+pub mod codec;
+pub mod store;
@@ -0,0 +1,87 @@
+//! Recent Payload Store
+
+use crate::sync::codec::SyncedPayload;
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, Mutex},
+};
+
+/// The default number of recent payloads served to syncing peers.
+pub const DEFAULT_PAYLOAD_STORE_CAPACITY: usize = 1024;
+
+/// The most recent unsafe payloads seen by the node, by block number, served to
+/// peers over the `payload_by_number` protocol.
+///
+/// A payload replaces any previously stored one with the same number, so that
+/// the store follows unsafe reorgs. The store is shared between clones.
+#[derive(Debug, Clone)]
+pub struct PayloadStore {
+    /// The stored payloads, by block number.
+    payloads: Arc<Mutex<BTreeMap<u64, SyncedPayload>>>,
+    /// The maximum number of stored payloads.
+    capacity: usize,
+}
+
+impl Default for PayloadStore {
+    fn default() -> Self {
+        Self::with_capacity(DEFAULT_PAYLOAD_STORE_CAPACITY)
+    }
+}
+
+impl PayloadStore {
+    /// Creates a new [PayloadStore] holding up to `capacity` payloads.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self { payloads: Arc::new(Mutex::new(BTreeMap::new())), capacity: capacity.max(1) }
+    }
+
+    /// Stores the given payload, evicting the oldest ones if the store is full.
+    pub fn insert(&self, payload: SyncedPayload) {
+        let mut payloads = self.payloads.lock().unwrap_or_else(|e| e.into_inner());
+        payloads.insert(payload.payload.block_number, payload);
+        while payloads.len() > self.capacity {
+            payloads.pop_first();
+        }
+    }
+
+    /// Returns the stored payload with the given block number, if any.
+    pub fn get(&self, number: u64) -> Option<SyncedPayload> {
+        self.payloads.lock().unwrap_or_else(|e| e.into_inner()).get(&number).cloned()
+    }
+
+    /// Returns the number of stored payloads.
+    pub fn len(&self) -> usize {
+        self.payloads.lock().unwrap_or_else(|e| e.into_inner()).len()
+    }
+
+    /// Returns `true` if no payload is stored.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::payload::ExecutionPayloadV1SSZ;
+    use kona_primitives::L2ExecutionPayload;
+
+    fn payload(number: u64) -> SyncedPayload {
+        let payload = ExecutionPayloadV1SSZ { block_number: number, ..Default::default() };
+        SyncedPayload {
+            payload: L2ExecutionPayload::from(payload),
+            parent_beacon_block_root: None,
+            withdrawals_root: None,
+        }
+    }
+
+    #[test]
+    fn test_store_evicts_oldest() {
+        let store = PayloadStore::with_capacity(2);
+        for number in [3, 1, 2] {
+            store.insert(payload(number));
+        }
+        assert_eq!(store.len(), 2);
+        assert!(store.get(1).is_none());
+        assert_eq!(store.get(3).unwrap().payload.block_number, 3);
+    }
+}
@@ -0,0 +1,41 @@
+//! Networking metrics
+
+/// Describes the metrics of the networking stack to the installed recorder,
+/// so that they are exported with their help text.
+///
+/// Must be called after the global metrics recorder is installed.
+pub fn describe_metrics() {
+    metrics::describe_gauge!("hera_p2p_peers_connected", "The number of connected peers");
+    metrics::describe_counter!(
+        "hera_p2p_dial_failures_total",
+        "The number of failed outgoing connections"
+    );
+    metrics::describe_counter!(
+        "hera_gossip_blocks_received_total",
+        "The number of gossiped blocks received, by topic version and validation outcome"
+    );
+    metrics::describe_counter!(
+        "hera_gossip_invalid_signatures_total",
+        "The number of gossiped blocks not signed by the unsafe block signer"
+    );
+    metrics::describe_counter!(
+        "hera_gossip_oversized_messages_total",
+        "The number of gossiped blocks over the maximum size of their topic"
+    );
+    metrics::describe_counter!(
+        "hera_gossip_banned_peers_total",
+        "The number of peers banned for their gossip score"
+    );
+    metrics::describe_counter!(
+        "hera_sync_requests_served_total",
+        "The number of payload_by_number requests served to peers"
+    );
+    metrics::describe_gauge!(
+        "hera_discovery_table_size",
+        "The number of nodes in the discovery routing table"
+    );
+    metrics::describe_counter!(
+        "hera_discovery_peers_found_total",
+        "The number of valid peers found by discovery lookups"
+    );
+}
@@ -7,6 +7,9 @@ use unsigned_varint::{decode, encode};
 /// The ENR key literal string for the consensus layer.
 pub const OP_CL_KEY: &str = "opstack";
 
+/// The ENR key of the QUIC port of the gossip service.
+pub const ENR_QUIC_KEY: &str = "quic";
+
 /// The unique L2 network identifier
 #[derive(Debug, Clone, Copy, Default)]
 pub struct OpStackEnr {
@@ -22,15 +25,62 @@ impl OpStackEnr {
         Self { chain_id, version }
     }
 
+    /// Returns the value of the `opstack` ENR entry, before RLP encoding.
+    pub fn value_bytes(&self) -> Vec<u8> {
+        let mut chain_id_buf = encode::u128_buffer();
+        let chain_id_slice = encode::u128(self.chain_id as u128, &mut chain_id_buf);
+
+        let mut version_buf = encode::u128_buffer();
+        let version_slice = encode::u128(self.version as u128, &mut version_buf);
+
+        [chain_id_slice, version_slice].concat()
+    }
+
     /// Returns `true` if a node [Enr] contains an `opstack` key and is on the same network.
     pub fn is_valid_node(node: &Enr<CombinedKey>, chain_id: u64) -> bool {
-        node.get_raw_rlp(OP_CL_KEY)
-            .map(|opstack| {
-                OpStackEnr::try_from(opstack)
-                    .map(|opstack| opstack.chain_id == chain_id && opstack.version == 0)
-                    .unwrap_or_default()
-            })
-            .unwrap_or_default()
+        Self::check_node(node, |id| id == chain_id).is_ok()
+    }
+
+    /// Decodes the `opstack` entry of a node [Enr], and checks that it has version 0
+    /// and a chain ID accepted by `accepts_chain`.
+    pub fn check_node(
+        node: &Enr<CombinedKey>,
+        accepts_chain: impl Fn(u64) -> bool,
+    ) -> Result<Self, EnrRejection> {
+        let opstack = node.get_raw_rlp(OP_CL_KEY).ok_or(EnrRejection::MissingEntry)?;
+        let opstack = OpStackEnr::try_from(opstack).map_err(|_| EnrRejection::Malformed)?;
+        if opstack.version != 0 {
+            return Err(EnrRejection::UnsupportedVersion(opstack.version));
+        }
+        if !accepts_chain(opstack.chain_id) {
+            return Err(EnrRejection::OtherChain(opstack.chain_id));
+        }
+        Ok(opstack)
+    }
+}
+
+/// The reason a node [Enr] is not an OP Stack peer of the network.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EnrRejection {
+    /// The ENR has no `opstack` entry, e.g. an Ethereum L1 node.
+    MissingEntry,
+    /// The `opstack` entry could not be decoded.
+    Malformed,
+    /// The `opstack` entry has an unsupported version.
+    UnsupportedVersion(u64),
+    /// The `opstack` entry is for another chain.
+    OtherChain(u64),
+}
+
+impl EnrRejection {
+    /// Returns the label of the rejection reason, for metrics.
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            Self::MissingEntry => "missing_entry",
+            Self::Malformed => "malformed",
+            Self::UnsupportedVersion(_) => "unsupported_version",
+            Self::OtherChain(_) => "other_chain",
+        }
     }
 }
 
@@ -55,14 +105,40 @@ impl TryFrom<&[u8]> for OpStackEnr {
 impl From<OpStackEnr> for Vec<u8> {
     /// Converts Op Stack Enr data to a vector of bytes.
     fn from(value: OpStackEnr) -> Vec<u8> {
-        let mut chain_id_buf = encode::u128_buffer();
-        let chain_id_slice = encode::u128(value.chain_id as u128, &mut chain_id_buf);
+        alloy_rlp::encode(&value.value_bytes()).to_vec()
+    }
+}
 
-        let mut version_buf = encode::u128_buffer();
-        let version_slice = encode::u128(value.version as u128, &mut version_buf);
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        let opstack = [chain_id_slice, version_slice].concat();
+    fn node(opstack: Option<OpStackEnr>) -> Enr<CombinedKey> {
+        let key = CombinedKey::generate_secp256k1();
+        let mut builder = Enr::builder();
+        if let Some(opstack) = opstack {
+            let data: Vec<u8> = opstack.into();
+            builder.add_value_rlp(OP_CL_KEY, data.into());
+        }
+        builder.build(&key).unwrap()
+    }
 
-        alloy_rlp::encode(&opstack).to_vec()
+    #[test]
+    fn test_check_node() {
+        let accepts = |id| id == 10 || id == 8453;
+        assert!(OpStackEnr::check_node(&node(Some(OpStackEnr::new(10, 0))), accepts).is_ok());
+        assert!(OpStackEnr::check_node(&node(Some(OpStackEnr::new(8453, 0))), accepts).is_ok());
+        assert_eq!(
+            OpStackEnr::check_node(&node(Some(OpStackEnr::new(1, 0))), accepts).unwrap_err(),
+            EnrRejection::OtherChain(1)
+        );
+        assert_eq!(
+            OpStackEnr::check_node(&node(Some(OpStackEnr::new(10, 1))), accepts).unwrap_err(),
+            EnrRejection::UnsupportedVersion(1)
+        );
+        assert_eq!(
+            OpStackEnr::check_node(&node(None), accepts).unwrap_err(),
+            EnrRejection::MissingEntry
+        );
     }
 }
@@ -1,13 +1,15 @@
 //! Execution Payload Envelope Type
 
 use alloy::primitives::{Signature, B256};
-use eyre::Result;
+use eyre::{eyre, Result};
 use kona_primitives::L2ExecutionPayload;
 use ssz_rs::prelude::*;
 
 use super::payload::{
-    ExecutionPayloadV1SSZ, ExecutionPayloadV2SSZ, ExecutionPayloadV3SSZ, PayloadHash,
+    ExecutionPayloadV1SSZ, ExecutionPayloadV2SSZ, ExecutionPayloadV3SSZ, ExecutionPayloadV4SSZ,
+    PayloadHash,
 };
+use crate::gossip::config::MAX_GOSSIP_SIZE;
 
 /// An envelope around the execution payload for L2.
 #[derive(Debug, Clone)]
@@ -20,60 +22,418 @@ pub struct ExecutionPayloadEnvelope {
     pub hash: PayloadHash,
     /// The parent beacon block root.
     pub parent_beacon_block_root: Option<B256>,
+    /// The withdrawals root of the block header, i.e. the storage root of the
+    /// `L2ToL1MessagePasser` predeploy, for Isthmus payloads.
+    pub withdrawals_root: Option<B256>,
 }
 
 impl ExecutionPayloadEnvelope {
+    /// Decodes a gossip message of the given blocks topic version (`0` for v1, `1` for
+    /// v2, ...), decompressing to at most [MAX_GOSSIP_SIZE] bytes.
+    pub fn decode(version: u8, data: &[u8]) -> Result<Self> {
+        Self::decode_with_max_size(version, data, MAX_GOSSIP_SIZE)
+    }
+
+    /// Decodes a gossip message of the given blocks topic version, decompressing to at
+    /// most `max_size` bytes.
+    ///
+    /// Malformed messages are rejected without panicking, and oversized ones before
+    /// being decompressed, so that a peer can't exhaust the memory of the node.
+    pub fn decode_with_max_size(version: u8, data: &[u8], max_size: usize) -> Result<Self> {
+        let size = snap::raw::decompress_len(data)?;
+        if size > max_size {
+            eyre::bail!("message of {} bytes exceeds the maximum size of {}", size, max_size);
+        }
+        let decompressed = snap::raw::Decoder::new().decompress_vec(data)?;
+        Self::from_decompressed(version, &decompressed)
+    }
+
+    /// Returns the signature and the [PayloadHash] of a gossip message of the given blocks
+    /// topic version, decompressing to at most `max_size` bytes but without decoding the
+    /// payload.
+    pub fn decode_signature(
+        version: u8,
+        data: &[u8],
+        max_size: usize,
+    ) -> Result<(Signature, PayloadHash)> {
+        let size = snap::raw::decompress_len(data)?;
+        if size > max_size {
+            eyre::bail!("message of {} bytes exceeds the maximum size of {}", size, max_size);
+        }
+        let decompressed = snap::raw::Decoder::new().decompress_vec(data)?;
+        let prefix = Self::prefix_len(version, &decompressed)?;
+        let signature = Signature::try_from(&decompressed[..65])?;
+        Ok((signature, PayloadHash::from(&decompressed[prefix..])))
+    }
+
     /// Decode V1
     pub fn decode_v1(data: &[u8]) -> Result<Self> {
-        let mut decoder = snap::raw::Decoder::new();
-        let decompressed = decoder.decompress_vec(data)?;
-        let sig_data = &decompressed[..65];
-        let block_data = &decompressed[65..];
+        Self::decode(0, data)
+    }
 
-        let signature = Signature::try_from(sig_data)?;
+    /// Decode V2
+    pub fn decode_v2(data: &[u8]) -> Result<Self> {
+        Self::decode(1, data)
+    }
 
-        let payload = ExecutionPayloadV1SSZ::deserialize(block_data)?;
-        let payload = L2ExecutionPayload::from(payload);
+    /// Decode V3
+    pub fn decode_v3(data: &[u8]) -> Result<Self> {
+        Self::decode(2, data)
+    }
+
+    /// Decode V4
+    pub fn decode_v4(data: &[u8]) -> Result<Self> {
+        Self::decode(3, data)
+    }
 
+    /// Decodes a decompressed gossip message of the given blocks topic version:
+    /// `signature (65 bytes) ++ [parent_beacon_block_root (32 bytes)] ++ ssz(payload)`.
+    ///
+    /// Payloads with withdrawals are rejected, since they are always empty on L2.
+    fn from_decompressed(version: u8, decompressed: &[u8]) -> Result<Self> {
+        let prefix = Self::prefix_len(version, decompressed)?;
+        let signature = Signature::try_from(&decompressed[..65])?;
+        let parent_beacon_block_root =
+            (version >= 2).then(|| B256::from_slice(&decompressed[65..97]));
+        let block_data = &decompressed[prefix..];
+
+        let mut withdrawals_root = None;
+        let payload = match version {
+            0 => L2ExecutionPayload::from(deserialize::<ExecutionPayloadV1SSZ>(block_data)?),
+            1 => {
+                let payload = deserialize::<ExecutionPayloadV2SSZ>(block_data)?;
+                if !payload.withdrawals.is_empty() {
+                    eyre::bail!("non-empty withdrawals");
+                }
+                L2ExecutionPayload::from(payload)
+            }
+            2 => {
+                let payload = deserialize::<ExecutionPayloadV3SSZ>(block_data)?;
+                if !payload.withdrawals.is_empty() {
+                    eyre::bail!("non-empty withdrawals");
+                }
+                L2ExecutionPayload::from(payload)
+            }
+            _ => {
+                let payload = deserialize::<ExecutionPayloadV4SSZ>(block_data)?;
+                if !payload.withdrawals.is_empty() {
+                    eyre::bail!("non-empty withdrawals");
+                }
+                withdrawals_root = Some(payload.withdrawals_root());
+                L2ExecutionPayload::from(payload)
+            }
+        };
         let hash = PayloadHash::from(block_data);
 
-        Ok(ExecutionPayloadEnvelope { parent_beacon_block_root: None, signature, payload, hash })
+        Ok(ExecutionPayloadEnvelope {
+            parent_beacon_block_root,
+            signature,
+            payload,
+            hash,
+            withdrawals_root,
+        })
     }
 
-    /// Decode V2
-    pub fn decode_v2(data: &[u8]) -> Result<Self> {
-        let mut decoder = snap::raw::Decoder::new();
-        let decompressed = decoder.decompress_vec(data)?;
-        let sig_data = &decompressed[..65];
-        let block_data = &decompressed[65..];
+    /// Returns the length of the prefix of a decompressed gossip message of the given
+    /// blocks topic version, before the payload.
+    fn prefix_len(version: u8, decompressed: &[u8]) -> Result<usize> {
+        let prefix = match version {
+            0 | 1 => 65,
+            2 | 3 => 97,
+            _ => eyre::bail!("unsupported payload version: {}", version),
+        };
+        if decompressed.len() < prefix {
+            eyre::bail!("message of {} bytes is too short", decompressed.len());
+        }
+        Ok(prefix)
+    }
 
-        let signature = Signature::try_from(sig_data)?;
+    /// Encode V1
+    ///
+    /// Produces the snappy-compressed gossip message for the `blocks/v1` topic:
+    /// `signature (65 bytes) ++ ssz(payload)`.
+    pub fn encode_v1(&self) -> Result<Vec<u8>> {
+        let payload = ExecutionPayloadV1SSZ::try_from(&self.payload)?;
+        let block_data = ssz_rs::serialize(&payload)?;
+        Self::compress(&[&signature_bytes(&self.signature), block_data.as_slice()])
+    }
 
-        let payload = ExecutionPayloadV2SSZ::deserialize(block_data)?;
-        let payload = L2ExecutionPayload::from(payload);
+    /// Encode V2
+    ///
+    /// Produces the snappy-compressed gossip message for the `blocks/v2` topic:
+    /// `signature (65 bytes) ++ ssz(payload)`.
+    pub fn encode_v2(&self) -> Result<Vec<u8>> {
+        let payload = ExecutionPayloadV2SSZ::try_from(&self.payload)?;
+        let block_data = ssz_rs::serialize(&payload)?;
+        Self::compress(&[&signature_bytes(&self.signature), block_data.as_slice()])
+    }
 
-        let hash = PayloadHash::from(block_data);
+    /// Encode V3
+    ///
+    /// Produces the snappy-compressed gossip message for the `blocks/v3` topic:
+    /// `signature (65 bytes) ++ parent_beacon_block_root (32 bytes) ++ ssz(payload)`.
+    ///
+    /// Returns an error if the envelope has no parent beacon block root.
+    pub fn encode_v3(&self) -> Result<Vec<u8>> {
+        let parent_beacon_block_root = self
+            .parent_beacon_block_root
+            .ok_or_else(|| eyre!("missing parent beacon block root"))?;
+        let payload = ExecutionPayloadV3SSZ::try_from(&self.payload)?;
+        let block_data = ssz_rs::serialize(&payload)?;
+        Self::compress(&[
+            &signature_bytes(&self.signature),
+            parent_beacon_block_root.as_slice(),
+            block_data.as_slice(),
+        ])
+    }
 
-        Ok(ExecutionPayloadEnvelope { parent_beacon_block_root: None, signature, payload, hash })
+    /// Encode V4
+    ///
+    /// Produces the snappy-compressed gossip message for the `blocks/v4` topic:
+    /// `signature (65 bytes) ++ parent_beacon_block_root (32 bytes) ++ ssz(payload)`,
+    /// the payload including the withdrawals root.
+    ///
+    /// Returns an error if the envelope has no parent beacon block root or withdrawals
+    /// root.
+    pub fn encode_v4(&self) -> Result<Vec<u8>> {
+        let parent_beacon_block_root = self
+            .parent_beacon_block_root
+            .ok_or_else(|| eyre!("missing parent beacon block root"))?;
+        let block_data = self.block_data_v4()?;
+        Self::compress(&[
+            &signature_bytes(&self.signature),
+            parent_beacon_block_root.as_slice(),
+            block_data.as_slice(),
+        ])
     }
 
-    /// Decode V3
-    pub fn decode_v3(data: &[u8]) -> Result<Self> {
-        let mut decoder = snap::raw::Decoder::new();
-        let decompressed = decoder.decompress_vec(data)?;
-        let sig_data = &decompressed[..65];
-        let parent_beacon_block_root = &decompressed[65..97];
-        let block_data = &decompressed[97..];
+    /// Returns the [PayloadHash] of the SSZ-encoded payload of the envelope for the given
+    /// topic version, including the withdrawals root from `blocks/v4`.
+    pub fn hash_for_version(&self, version: u8) -> Result<PayloadHash> {
+        if version == 3 {
+            return Ok(PayloadHash::from(self.block_data_v4()?.as_slice()));
+        }
+        Self::payload_hash(&self.payload, version)
+    }
+
+    /// Returns the SSZ encoding of the payload with its withdrawals root.
+    fn block_data_v4(&self) -> Result<Vec<u8>> {
+        let withdrawals_root =
+            self.withdrawals_root.ok_or_else(|| eyre!("missing withdrawals root"))?;
+        let payload = ExecutionPayloadV4SSZ::try_from_payload(&self.payload, withdrawals_root)?;
+        Ok(ssz_rs::serialize(&payload)?)
+    }
+
+    /// Returns the [PayloadHash] of the SSZ-encoded payload for the given topic version,
+    /// up to `blocks/v3`, see [Self::hash_for_version] for `blocks/v4` payloads.
+    ///
+    /// This is the hash that the unsafe block signer signs over.
+    pub fn payload_hash(payload: &L2ExecutionPayload, version: u8) -> Result<PayloadHash> {
+        let block_data = match version {
+            0 => ssz_rs::serialize(&ExecutionPayloadV1SSZ::try_from(payload)?)?,
+            1 => ssz_rs::serialize(&ExecutionPayloadV2SSZ::try_from(payload)?)?,
+            2 => ssz_rs::serialize(&ExecutionPayloadV3SSZ::try_from(payload)?)?,
+            _ => eyre::bail!("unsupported payload version: {}", version),
+        };
+        Ok(PayloadHash::from(block_data.as_slice()))
+    }
+
+    /// Concatenates the given parts and snappy-compresses the result.
+    fn compress(parts: &[&[u8]]) -> Result<Vec<u8>> {
+        let mut encoder = snap::raw::Encoder::new();
+        Ok(encoder.compress_vec(&parts.concat())?)
+    }
+}
 
-        let signature = Signature::try_from(sig_data)?;
+/// Deserializes the given SSZ payload, rejecting non-canonical encodings, e.g. with
+/// trailing bytes, which would otherwise give the same payload several hashes.
+fn deserialize<T: SimpleSerialize>(block_data: &[u8]) -> Result<T> {
+    let payload = T::deserialize(block_data)?;
+    if ssz_rs::serialize(&payload)? != block_data {
+        eyre::bail!("non-canonical SSZ encoding");
+    }
+    Ok(payload)
+}
 
-        let parent_beacon_block_root = Some(B256::from_slice(parent_beacon_block_root));
+/// Returns the 65 byte `r ++ s ++ v` representation of a [Signature],
+/// with `v` encoded as the y-parity (0 or 1) as expected by op-node.
+fn signature_bytes(signature: &Signature) -> [u8; 65] {
+    let mut bytes = signature.as_bytes();
+    bytes[64] = signature.v().y_parity_byte();
+    bytes
+}
 
-        let payload = ExecutionPayloadV3SSZ::deserialize(block_data)?;
-        let payload = L2ExecutionPayload::from(payload);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::{Bytes, U256};
+    use proptest::prelude::*;
 
-        let hash = PayloadHash::from(block_data);
+    fn test_signature() -> Signature {
+        Signature::from_rs_and_parity(U256::from(1), U256::from(2), false).unwrap()
+    }
+
+    #[test]
+    fn test_encode_decode_v1_roundtrip() {
+        let payload = L2ExecutionPayload::from(ExecutionPayloadV1SSZ::default());
+        let hash = ExecutionPayloadEnvelope::payload_hash(&payload, 0).unwrap();
+        let envelope = ExecutionPayloadEnvelope {
+            payload,
+            signature: test_signature(),
+            hash,
+            parent_beacon_block_root: None,
+            withdrawals_root: None,
+        };
+
+        let data = envelope.encode_v1().unwrap();
+        let decoded = ExecutionPayloadEnvelope::decode_v1(&data).unwrap();
+        assert_eq!(decoded.hash, envelope.hash);
+        assert_eq!(decoded.signature, envelope.signature);
+        assert_eq!(decoded.payload.block_hash, envelope.payload.block_hash);
+    }
+
+    #[test]
+    fn test_encode_decode_v3_roundtrip() {
+        let payload = L2ExecutionPayload::from(ExecutionPayloadV3SSZ::default());
+        let hash = ExecutionPayloadEnvelope::payload_hash(&payload, 2).unwrap();
+        let envelope = ExecutionPayloadEnvelope {
+            payload,
+            signature: test_signature(),
+            hash,
+            parent_beacon_block_root: Some(B256::repeat_byte(0xaa)),
+            withdrawals_root: None,
+        };
+
+        let data = envelope.encode_v3().unwrap();
+        let decoded = ExecutionPayloadEnvelope::decode_v3(&data).unwrap();
+        assert_eq!(decoded.hash, envelope.hash);
+        assert_eq!(decoded.parent_beacon_block_root, envelope.parent_beacon_block_root);
+        assert_eq!(decoded.payload.withdrawals, Some(Vec::new()));
+    }
+
+    #[test]
+    fn test_encode_decode_v4_roundtrip() {
+        let mut envelope = ExecutionPayloadEnvelope {
+            payload: L2ExecutionPayload::from(ExecutionPayloadV3SSZ::default()),
+            signature: test_signature(),
+            hash: PayloadHash::default(),
+            parent_beacon_block_root: Some(B256::repeat_byte(0xaa)),
+            withdrawals_root: None,
+        };
+        assert!(envelope.encode_v4().is_err());
+        assert!(envelope.hash_for_version(3).is_err());
+
+        envelope.withdrawals_root = Some(B256::repeat_byte(0xbb));
+        envelope.hash = envelope.hash_for_version(3).unwrap();
+        assert_ne!(envelope.hash, envelope.hash_for_version(2).unwrap());
+
+        let data = envelope.encode_v4().unwrap();
+        let decoded = ExecutionPayloadEnvelope::decode_v4(&data).unwrap();
+        assert_eq!(decoded.hash, envelope.hash);
+        assert_eq!(decoded.parent_beacon_block_root, envelope.parent_beacon_block_root);
+        assert_eq!(decoded.withdrawals_root, envelope.withdrawals_root);
+        assert_eq!(decoded.payload.blob_gas_used, Some(0));
+
+        // A v3 message is not a valid v4 message, and conversely.
+        assert!(ExecutionPayloadEnvelope::decode_v3(&data).is_err());
+        assert!(ExecutionPayloadEnvelope::decode_v4(&envelope.encode_v3().unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_encode_v3_missing_parent_beacon_block_root() {
+        let payload = L2ExecutionPayload::from(ExecutionPayloadV3SSZ::default());
+        let envelope = ExecutionPayloadEnvelope {
+            payload,
+            signature: test_signature(),
+            hash: PayloadHash::default(),
+            parent_beacon_block_root: None,
+            withdrawals_root: None,
+        };
+        assert!(envelope.encode_v3().is_err());
+    }
+
+    #[test]
+    fn test_decode_rejects_malformed_messages() {
+        let envelope = ExecutionPayloadEnvelope {
+            payload: L2ExecutionPayload::from(ExecutionPayloadV1SSZ::default()),
+            signature: test_signature(),
+            hash: PayloadHash::default(),
+            parent_beacon_block_root: None,
+            withdrawals_root: None,
+        };
+        let data = envelope.encode_v1().unwrap();
+        assert!(ExecutionPayloadEnvelope::decode(0, &data).is_ok());
+
+        let err = ExecutionPayloadEnvelope::decode_with_max_size(0, &data, 64).unwrap_err();
+        assert!(err.to_string().contains("exceeds the maximum size of 64"));
+
+        let mut decompressed = snap::raw::Decoder::new().decompress_vec(&data).unwrap();
+        decompressed.push(0);
+        let trailing = snap::raw::Encoder::new().compress_vec(&decompressed).unwrap();
+        assert!(ExecutionPayloadEnvelope::decode(0, &trailing).is_err());
+
+        let short = snap::raw::Encoder::new().compress_vec(&[0u8; 64]).unwrap();
+        for version in 0..=3 {
+            assert!(ExecutionPayloadEnvelope::decode(version, &short).is_err());
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn test_decode_arbitrary_messages(
+            version in 0u8..4,
+            data in proptest::collection::vec(any::<u8>(), 0..1024),
+        ) {
+            // Neither raw nor compressed garbage may panic the decoder.
+            _ = ExecutionPayloadEnvelope::decode(version, &data);
+            let compressed = snap::raw::Encoder::new().compress_vec(&data).unwrap();
+            _ = ExecutionPayloadEnvelope::decode(version, &compressed);
+        }
+
+        #[test]
+        fn test_encode_decode_roundtrip(
+            version in 0u8..4,
+            block_number: u64,
+            timestamp: u64,
+            extra_data in proptest::collection::vec(any::<u8>(), 0..=32),
+            transactions in proptest::collection::vec(
+                proptest::collection::vec(any::<u8>(), 0..256),
+                0..8,
+            ),
+        ) {
+            let mut payload = match version {
+                0 => L2ExecutionPayload::from(ExecutionPayloadV1SSZ::default()),
+                1 => L2ExecutionPayload::from(ExecutionPayloadV2SSZ::default()),
+                _ => L2ExecutionPayload::from(ExecutionPayloadV3SSZ::default()),
+            };
+            payload.block_number = block_number;
+            payload.timestamp = timestamp;
+            payload.extra_data = Bytes::from(extra_data);
+            payload.transactions = transactions.into_iter().map(Bytes::from).collect();
+            let mut envelope = ExecutionPayloadEnvelope {
+                hash: PayloadHash::default(),
+                payload,
+                signature: test_signature(),
+                parent_beacon_block_root: (version >= 2).then(|| B256::repeat_byte(0xaa)),
+                withdrawals_root: (version == 3).then(|| B256::repeat_byte(0xbb)),
+            };
+            envelope.hash = envelope.hash_for_version(version).unwrap();
 
-        Ok(ExecutionPayloadEnvelope { parent_beacon_block_root, signature, payload, hash })
+            let data = match version {
+                0 => envelope.encode_v1(),
+                1 => envelope.encode_v2(),
+                2 => envelope.encode_v3(),
+                _ => envelope.encode_v4(),
+            }
+            .unwrap();
+            let decoded = ExecutionPayloadEnvelope::decode(version, &data).unwrap();
+            prop_assert_eq!(decoded.hash, envelope.hash);
+            prop_assert_eq!(decoded.payload.block_number, block_number);
+            prop_assert_eq!(decoded.payload.timestamp, timestamp);
+            prop_assert_eq!(&decoded.payload.extra_data, &envelope.payload.extra_data);
+            prop_assert_eq!(&decoded.payload.transactions, &envelope.payload.transactions);
+            prop_assert_eq!(decoded.parent_beacon_block_root, envelope.parent_beacon_block_root);
+            prop_assert_eq!(decoded.withdrawals_root, envelope.withdrawals_root);
+        }
     }
 }
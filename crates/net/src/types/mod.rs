@@ -3,4 +3,5 @@
 pub mod address;
 pub mod enr;
 pub mod envelope;
+pub mod node_info;
 pub mod payload;
@@ -0,0 +1,19 @@
+//! Node information exposed to operators.
+
+use serde::{Deserialize, Serialize};
+
+/// Describes the identity and network configuration of a running node.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NodeInfo {
+    /// The libp2p peer ID of the node.
+    pub peer_id: String,
+    /// The base64 encoded ENR of the node, if discovery is enabled.
+    pub enr: Option<String>,
+    /// The multiaddrs the node listens on.
+    pub listen_addrs: Vec<String>,
+    /// The chain ID of the L2 network.
+    pub chain_id: u64,
+    /// The optional networking features enabled on the node.
+    pub features: Vec<String>,
+}
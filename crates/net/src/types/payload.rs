@@ -1,6 +1,7 @@
 //! Execution Payload Types
 
 use alloy::primitives::{keccak256, B256};
+use eyre::{eyre, Result};
 use kona_primitives::L2ExecutionPayload;
 use ssz_rs::{prelude::*, List, Vector, U256};
 
@@ -14,7 +15,7 @@ type VecAddress = Vector<u8, 20>;
 type Transaction = List<u8, 1073741824>;
 
 /// Represents the Keccak256 hash of the block
-#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
 pub struct PayloadHash(B256);
 
 impl From<&[u8]> for PayloadHash {
@@ -92,6 +93,30 @@ impl From<ExecutionPayloadV1SSZ> for L2ExecutionPayload {
     }
 }
 
+impl TryFrom<&L2ExecutionPayload> for ExecutionPayloadV1SSZ {
+    type Error = eyre::Report;
+
+    /// Converts an [L2ExecutionPayload] into its pre Canyon/Shanghai SSZ representation.
+    fn try_from(value: &L2ExecutionPayload) -> Result<Self> {
+        Ok(Self {
+            parent_hash: to_bytes32(value.parent_hash)?,
+            fee_recipient: to_vec_address(value.fee_recipient)?,
+            state_root: to_bytes32(value.state_root)?,
+            receipts_root: to_bytes32(value.receipts_root)?,
+            logs_bloom: to_bloom_vector(value.logs_bloom)?,
+            prev_randao: to_bytes32(value.prev_randao)?,
+            block_number: value.block_number,
+            gas_limit: to_u64(value.gas_limit)?,
+            gas_used: to_u64(value.gas_used)?,
+            timestamp: value.timestamp,
+            extra_data: to_byte_list(&value.extra_data)?,
+            base_fee_per_gas: to_uint(value.base_fee_per_gas)?,
+            block_hash: to_bytes32(value.block_hash)?,
+            transactions: to_tx_list(&value.transactions)?,
+        })
+    }
+}
+
 /// The Canyon/Shanghai [L2ExecutionPayload] - the withdrawals field should be an empty [List]
 #[derive(SimpleSerialize, Default)]
 pub struct ExecutionPayloadV2SSZ {
@@ -168,6 +193,31 @@ impl From<ExecutionPayloadV2SSZ> for L2ExecutionPayload {
     }
 }
 
+impl TryFrom<&L2ExecutionPayload> for ExecutionPayloadV2SSZ {
+    type Error = eyre::Report;
+
+    /// Converts an [L2ExecutionPayload] into its Canyon/Shanghai SSZ representation.
+    fn try_from(value: &L2ExecutionPayload) -> Result<Self> {
+        Ok(Self {
+            parent_hash: to_bytes32(value.parent_hash)?,
+            fee_recipient: to_vec_address(value.fee_recipient)?,
+            state_root: to_bytes32(value.state_root)?,
+            receipts_root: to_bytes32(value.receipts_root)?,
+            logs_bloom: to_bloom_vector(value.logs_bloom)?,
+            prev_randao: to_bytes32(value.prev_randao)?,
+            block_number: value.block_number,
+            gas_limit: to_u64(value.gas_limit)?,
+            gas_used: to_u64(value.gas_used)?,
+            timestamp: value.timestamp,
+            extra_data: to_byte_list(&value.extra_data)?,
+            base_fee_per_gas: to_uint(value.base_fee_per_gas)?,
+            block_hash: to_bytes32(value.block_hash)?,
+            transactions: to_tx_list(&value.transactions)?,
+            withdrawals: List::default(),
+        })
+    }
+}
+
 /// The Ecotone [L2ExecutionPayload] - Adds Eip 4844 fields to the payload
 /// - `blob_gas_used`
 /// - `excess_blob_gas`
@@ -234,6 +284,135 @@ impl From<ExecutionPayloadV3SSZ> for L2ExecutionPayload {
     }
 }
 
+impl TryFrom<&L2ExecutionPayload> for ExecutionPayloadV3SSZ {
+    type Error = eyre::Report;
+
+    /// Converts an [L2ExecutionPayload] into its Ecotone SSZ representation.
+    fn try_from(value: &L2ExecutionPayload) -> Result<Self> {
+        Ok(Self {
+            parent_hash: to_bytes32(value.parent_hash)?,
+            fee_recipient: to_vec_address(value.fee_recipient)?,
+            state_root: to_bytes32(value.state_root)?,
+            receipts_root: to_bytes32(value.receipts_root)?,
+            logs_bloom: to_bloom_vector(value.logs_bloom)?,
+            prev_randao: to_bytes32(value.prev_randao)?,
+            block_number: value.block_number,
+            gas_limit: to_u64(value.gas_limit)?,
+            gas_used: to_u64(value.gas_used)?,
+            timestamp: value.timestamp,
+            extra_data: to_byte_list(&value.extra_data)?,
+            base_fee_per_gas: to_uint(value.base_fee_per_gas)?,
+            block_hash: to_bytes32(value.block_hash)?,
+            transactions: to_tx_list(&value.transactions)?,
+            withdrawals: List::default(),
+            blob_gas_used: to_u64(value.blob_gas_used.unwrap_or_default())?,
+            excess_blob_gas: to_u64(value.excess_blob_gas.unwrap_or_default())?,
+        })
+    }
+}
+
+/// The Isthmus [L2ExecutionPayload] - Adds the withdrawals root to the payload
+/// - `withdrawals_root`, the storage root of the `L2ToL1MessagePasser` predeploy
+#[derive(SimpleSerialize, Default)]
+pub struct ExecutionPayloadV4SSZ {
+    /// Block hash of the parent block
+    pub parent_hash: Bytes32,
+    /// Fee recipient of the block. Set to the sequencer fee vault
+    pub fee_recipient: VecAddress,
+    /// State root of the block
+    pub state_root: Bytes32,
+    /// Receipts root of the block
+    pub receipts_root: Bytes32,
+    /// Logs bloom of the block
+    pub logs_bloom: Vector<u8, 256>,
+    /// The block mix_digest
+    pub prev_randao: Bytes32,
+    /// The block number
+    pub block_number: u64,
+    /// The block gas limit
+    pub gas_limit: u64,
+    /// Total gas used in the block
+    pub gas_used: u64,
+    /// Timestamp of the block
+    pub timestamp: u64,
+    /// Any extra data included in the block
+    pub extra_data: List<u8, 32>,
+    /// Base fee per gas of the block
+    pub base_fee_per_gas: U256,
+    /// Hash of the block
+    pub block_hash: Bytes32,
+    /// Transactions in the block
+    pub transactions: List<Transaction, 1048576>,
+    /// An empty list. This is unused and only exists for L1 compatibility.
+    pub withdrawals: List<Withdrawal, 16>,
+    /// The total gas used by the blob in the block
+    pub blob_gas_used: u64,
+    /// The excess gas used by the blob in the block
+    pub excess_blob_gas: u64,
+    /// The withdrawals root of the block header
+    pub withdrawals_root: Bytes32,
+}
+
+impl ExecutionPayloadV4SSZ {
+    /// Converts an [L2ExecutionPayload] and the withdrawals root of its block into their
+    /// Isthmus SSZ representation.
+    pub fn try_from_payload(value: &L2ExecutionPayload, withdrawals_root: B256) -> Result<Self> {
+        let v3 = ExecutionPayloadV3SSZ::try_from(value)?;
+        Ok(Self {
+            parent_hash: v3.parent_hash,
+            fee_recipient: v3.fee_recipient,
+            state_root: v3.state_root,
+            receipts_root: v3.receipts_root,
+            logs_bloom: v3.logs_bloom,
+            prev_randao: v3.prev_randao,
+            block_number: v3.block_number,
+            gas_limit: v3.gas_limit,
+            gas_used: v3.gas_used,
+            timestamp: v3.timestamp,
+            extra_data: v3.extra_data,
+            base_fee_per_gas: v3.base_fee_per_gas,
+            block_hash: v3.block_hash,
+            transactions: v3.transactions,
+            withdrawals: v3.withdrawals,
+            blob_gas_used: v3.blob_gas_used,
+            excess_blob_gas: v3.excess_blob_gas,
+            withdrawals_root: to_bytes32(withdrawals_root)?,
+        })
+    }
+
+    /// Returns the withdrawals root of the block header.
+    pub fn withdrawals_root(&self) -> B256 {
+        B256::from_slice(self.withdrawals_root.as_slice())
+    }
+}
+
+impl From<ExecutionPayloadV4SSZ> for L2ExecutionPayload {
+    /// Converts an ExecutionPayloadV4SSZ into an [L2ExecutionPayload], without the
+    /// withdrawals root which is carried next to the payload.
+    fn from(value: ExecutionPayloadV4SSZ) -> Self {
+        Self {
+            parent_hash: convert_hash(value.parent_hash),
+            fee_recipient: convert_address(value.fee_recipient),
+            state_root: convert_hash(value.state_root),
+            receipts_root: convert_hash(value.receipts_root),
+            logs_bloom: convert_bloom(value.logs_bloom),
+            prev_randao: convert_hash(value.prev_randao),
+            block_number: value.block_number,
+            gas_limit: value.gas_limit.into(),
+            gas_used: value.gas_used.into(),
+            timestamp: value.timestamp,
+            extra_data: convert_byte_list(value.extra_data),
+            base_fee_per_gas: convert_uint(value.base_fee_per_gas),
+            block_hash: convert_hash(value.block_hash),
+            transactions: convert_tx_list(value.transactions),
+            deserialized_transactions: Vec::default(),
+            withdrawals: Some(Vec::new()),
+            blob_gas_used: Some(value.blob_gas_used.into()),
+            excess_blob_gas: Some(value.excess_blob_gas.into()),
+        }
+    }
+}
+
 /// Converts an [ssz_rs::Vector] of bytes into [alloy::primitives::Bloom]
 fn convert_bloom(vector: Vector<u8, 256>) -> alloy::primitives::Bloom {
     let mut bloom = [0u8; 256];
@@ -256,11 +435,9 @@ fn convert_byte_list<const N: usize>(list: List<u8, N>) -> alloy::primitives::By
     alloy::primitives::Bytes::from(list.to_vec())
 }
 
-/// Converts a [U256] into [u128]
+/// Converts a [U256] into [u128], if it fits.
 fn convert_uint(value: U256) -> Option<u128> {
-    let bytes = value.to_bytes_le();
-    let bytes: [u8; 16] = bytes.try_into().ok()?;
-    Some(u128::from_le_bytes(bytes))
+    alloy::primitives::U256::from_le_slice(&value.to_bytes_le()).try_into().ok()
 }
 
 /// Converts [ssz_rs::List] of [Transaction] into a vector of [alloy::primitives::Bytes]
@@ -268,6 +445,43 @@ fn convert_tx_list(value: List<Transaction, 1048576>) -> Vec<alloy::primitives::
     value.iter().map(|tx| alloy::primitives::Bytes::from(tx.to_vec())).collect()
 }
 
+/// Converts [alloy::primitives::Bloom] into an [ssz_rs::Vector] of bytes
+fn to_bloom_vector(bloom: alloy::primitives::Bloom) -> Result<Vector<u8, 256>> {
+    Vector::try_from(bloom.to_vec()).map_err(|_| eyre!("invalid logs bloom"))
+}
+
+/// Converts [alloy::primitives::B256] into [Bytes32]
+fn to_bytes32(hash: B256) -> Result<Bytes32> {
+    Bytes32::try_from(hash.to_vec()).map_err(|_| eyre!("invalid hash"))
+}
+
+/// Converts [alloy::primitives::Address] into [VecAddress]
+fn to_vec_address(address: alloy::primitives::Address) -> Result<VecAddress> {
+    VecAddress::try_from(address.to_vec()).map_err(|_| eyre!("invalid address"))
+}
+
+/// Converts [alloy::primitives::Bytes] into an [ssz_rs::List] of bytes
+fn to_byte_list<const N: usize>(bytes: &alloy::primitives::Bytes) -> Result<List<u8, N>> {
+    List::try_from(bytes.to_vec()).map_err(|_| eyre!("byte list exceeds {} bytes", N))
+}
+
+/// Converts an optional [u128] into a little-endian [U256]
+fn to_uint(value: Option<u128>) -> Result<U256> {
+    let value = alloy::primitives::U256::from(value.unwrap_or_default());
+    U256::deserialize(&value.to_le_bytes::<32>()).map_err(|_| eyre!("invalid uint"))
+}
+
+/// Converts a [u128] quantity into a [u64]
+fn to_u64(value: u128) -> Result<u64> {
+    u64::try_from(value).map_err(|_| eyre!("quantity {} overflows u64", value))
+}
+
+/// Converts a vector of [alloy::primitives::Bytes] into an [ssz_rs::List] of [Transaction]
+fn to_tx_list(value: &[alloy::primitives::Bytes]) -> Result<List<Transaction, 1048576>> {
+    let txs = value.iter().map(to_byte_list).collect::<Result<Vec<Transaction>>>()?;
+    List::try_from(txs).map_err(|_| eyre!("too many transactions"))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
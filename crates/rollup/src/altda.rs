@@ -0,0 +1,377 @@
+//! Alt-DA (Plasma) inputs, referenced by commitments in the batcher transactions
+
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use alloy::{
+    consensus::Receipt,
+    primitives::{hex, keccak256, Address, Bytes, B256},
+    sol,
+    sol_types::SolEvent,
+};
+use eyre::{bail, eyre, Result};
+use reqwest::{Client, StatusCode};
+use tracing::{debug, warn};
+use url::Url;
+
+/// The derivation version of batcher data holding an alt-DA commitment instead of frames.
+pub const ALTDA_DERIVATION_VERSION: u8 = 0x01;
+
+/// The default number of L1 blocks after its inclusion during which a commitment can be
+/// challenged.
+pub const DEFAULT_CHALLENGE_WINDOW: u64 = 3600;
+
+/// The default number of L1 blocks after a challenge during which it can be resolved.
+pub const DEFAULT_RESOLVE_WINDOW: u64 = 3600;
+
+/// The default timeout of a request to the DA server.
+pub const DEFAULT_DA_SERVER_TIMEOUT: Duration = Duration::from_secs(10);
+
+sol! {
+    /// Emitted by the `DataAvailabilityChallenge` contract when the status of a
+    /// challenge changes.
+    event ChallengeStatusChanged(
+        uint256 indexed challengedBlockNumber,
+        bytes challengedCommitment,
+        uint8 status
+    );
+}
+
+/// A commitment to an alt-DA input, posted by the batcher instead of the input.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum DaCommitment {
+    /// The keccak256 hash of the input, which the DA server must serve.
+    Keccak256(B256),
+    /// A commitment specific to a DA layer, whose first byte identifies the layer.
+    Generic(Bytes),
+}
+
+impl DaCommitment {
+    /// Decodes a commitment from its type byte and data.
+    pub fn decode(data: &[u8]) -> Result<Self> {
+        match data.split_first() {
+            Some((0x00, hash)) if hash.len() == 32 => Ok(Self::Keccak256(B256::from_slice(hash))),
+            Some((0x00, hash)) => bail!("Invalid keccak256 commitment length: {}", hash.len()),
+            Some((0x01, data)) if !data.is_empty() => {
+                Ok(Self::Generic(Bytes::copy_from_slice(data)))
+            }
+            Some((kind, _)) => bail!("Unsupported commitment type: {}", kind),
+            None => bail!("Empty commitment"),
+        }
+    }
+
+    /// Encodes the commitment as its type byte and data.
+    pub fn encode(&self) -> Bytes {
+        let (kind, data) = match self {
+            Self::Keccak256(hash) => (0x00, hash.as_slice()),
+            Self::Generic(data) => (0x01, data.as_ref()),
+        };
+        [&[kind][..], data].concat().into()
+    }
+
+    /// Returns `true` if the given input matches the commitment.
+    ///
+    /// Generic commitments are verified by the DA server, so any input matches them.
+    pub fn verify(&self, input: &[u8]) -> bool {
+        match self {
+            Self::Keccak256(hash) => keccak256(input) == *hash,
+            Self::Generic(_) => true,
+        }
+    }
+}
+
+impl fmt::Display for DaCommitment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&hex::encode_prefixed(self.encode()))
+    }
+}
+
+/// A client of an alt-DA server, serving the inputs of the commitments.
+#[derive(Debug, Clone)]
+pub struct DaClient {
+    /// The DA server URL.
+    url: Url,
+    /// The reqwest client.
+    client: Client,
+    /// The timeout of a single request.
+    timeout: Duration,
+}
+
+impl DaClient {
+    /// Creates a new [DaClient] for the DA server at the given URL.
+    pub fn new(url: Url) -> Self {
+        Self { url, client: Client::new(), timeout: DEFAULT_DA_SERVER_TIMEOUT }
+    }
+
+    /// Fetches the input of the given commitment, returning `None` if the server
+    /// doesn't have it.
+    pub async fn get_input(&self, commitment: &DaCommitment) -> Result<Option<Bytes>> {
+        let url: Url =
+            format!("{}/get/{}", self.url.as_str().trim_end_matches('/'), commitment).parse()?;
+        let response = self.client.get(url).timeout(self.timeout).send().await?;
+        match response.status() {
+            StatusCode::OK => Ok(Some(response.bytes().await?.into())),
+            StatusCode::NOT_FOUND => Ok(None),
+            status => Err(eyre!("DA server returned status: {}", status)),
+        }
+    }
+}
+
+/// The status of a challenged commitment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChallengeStatus {
+    /// The commitment was challenged, and must be resolved before the given L1 block.
+    Active {
+        /// The last L1 block in which the challenge can be resolved.
+        resolve_by: u64,
+    },
+    /// The input was published on L1 in response to the challenge.
+    Resolved,
+    /// The challenge was not resolved in time: the input is skipped by derivation.
+    Expired,
+}
+
+/// A commitment tracked during its challenge window.
+#[derive(Debug, Clone, Copy)]
+struct TrackedCommitment {
+    /// The L1 block the commitment was included in.
+    inclusion: u64,
+    /// The status of its challenge, if it was challenged.
+    challenge: Option<ChallengeStatus>,
+}
+
+/// Tracks the commitments read by derivation through their challenge and resolve
+/// windows, following the challenge events of the `DataAvailabilityChallenge` contract.
+#[derive(Debug)]
+pub struct ChallengeTracker {
+    /// The number of L1 blocks during which a commitment can be challenged.
+    challenge_window: u64,
+    /// The number of L1 blocks during which a challenge can be resolved.
+    resolve_window: u64,
+    /// The tracked commitments.
+    commitments: HashMap<DaCommitment, TrackedCommitment>,
+}
+
+impl Default for ChallengeTracker {
+    fn default() -> Self {
+        Self::new(DEFAULT_CHALLENGE_WINDOW, DEFAULT_RESOLVE_WINDOW)
+    }
+}
+
+impl ChallengeTracker {
+    /// Creates a new [ChallengeTracker] with the given windows, in L1 blocks.
+    pub fn new(challenge_window: u64, resolve_window: u64) -> Self {
+        Self { challenge_window, resolve_window, commitments: HashMap::new() }
+    }
+
+    /// Starts tracking a commitment included in the given L1 block.
+    pub fn track(&mut self, commitment: DaCommitment, inclusion: u64) {
+        self.commitments
+            .entry(commitment)
+            .or_insert(TrackedCommitment { inclusion, challenge: None });
+    }
+
+    /// Returns the challenge status of the given commitment, if it was challenged.
+    pub fn status(&self, commitment: &DaCommitment) -> Option<ChallengeStatus> {
+        self.commitments.get(commitment).and_then(|tracked| tracked.challenge)
+    }
+
+    /// Records a challenge of the given commitment in the given L1 block.
+    pub fn challenge(&mut self, commitment: DaCommitment, inclusion: u64, block: u64) {
+        let tracked = self
+            .commitments
+            .entry(commitment)
+            .or_insert(TrackedCommitment { inclusion, challenge: None });
+        tracked.challenge =
+            Some(ChallengeStatus::Active { resolve_by: block + self.resolve_window });
+    }
+
+    /// Records the resolution of the challenge of the given commitment.
+    pub fn resolve(&mut self, commitment: &DaCommitment) {
+        if let Some(tracked) = self.commitments.get_mut(commitment) {
+            tracked.challenge = Some(ChallengeStatus::Resolved);
+        }
+    }
+
+    /// Updates the statuses at the given L1 block, and returns the commitments whose
+    /// challenge just expired.
+    ///
+    /// Commitments that can no longer be challenged or resolved stop being tracked,
+    /// except for the expired ones.
+    pub fn advance(&mut self, block: u64) -> Vec<DaCommitment> {
+        let mut expired = Vec::new();
+        let (challenge_window, resolve_window) = (self.challenge_window, self.resolve_window);
+        self.commitments.retain(|commitment, tracked| match tracked.challenge {
+            Some(ChallengeStatus::Active { resolve_by }) if block > resolve_by => {
+                tracked.challenge = Some(ChallengeStatus::Expired);
+                expired.push(commitment.clone());
+                true
+            }
+            Some(ChallengeStatus::Active { .. }) => true,
+            Some(ChallengeStatus::Expired) => {
+                block <= tracked.inclusion + challenge_window + 2 * resolve_window
+            }
+            _ => block <= tracked.inclusion + challenge_window,
+        });
+        expired
+    }
+
+    /// Applies the challenge events of the given receipts of an L1 block, emitted by
+    /// the given challenge contract.
+    pub fn apply_receipts(&mut self, contract: Address, block: u64, receipts: &[Receipt]) {
+        let logs = receipts.iter().flat_map(|receipt| &receipt.logs);
+        for log in logs.filter(|log| log.address == contract) {
+            let Ok(event) = ChallengeStatusChanged::decode_log_data(&log.data, true) else {
+                continue;
+            };
+            let Ok(commitment) = DaCommitment::decode(&event.challengedCommitment) else {
+                continue;
+            };
+            let inclusion = event.challengedBlockNumber.saturating_to::<u64>();
+            match event.status {
+                1 => {
+                    debug!(
+                        "Commitment {} included in block {} was challenged",
+                        commitment, inclusion
+                    );
+                    self.challenge(commitment, inclusion, block);
+                }
+                2 => self.resolve(&commitment),
+                _ => {}
+            }
+        }
+    }
+}
+
+/// The alt-DA configuration of the derivation pipeline: the DA server the inputs are
+/// fetched from, and the tracking of the commitment challenges.
+#[derive(Debug, Clone)]
+pub struct AltDa {
+    /// The DA server client
+    client: DaClient,
+    /// The `DataAvailabilityChallenge` contract, if challenges are tracked
+    challenge_contract: Option<Address>,
+    /// The tracker of the commitment challenges
+    tracker: Arc<Mutex<ChallengeTracker>>,
+}
+
+impl AltDa {
+    /// Creates a new [AltDa] fetching the inputs from the given DA server.
+    pub fn new(client: DaClient) -> Self {
+        Self { client, challenge_contract: None, tracker: Default::default() }
+    }
+
+    /// Tracks the challenges of the given `DataAvailabilityChallenge` contract, with
+    /// the given windows in L1 blocks.
+    pub fn with_challenges(
+        mut self,
+        contract: Address,
+        challenge_window: u64,
+        resolve_window: u64,
+    ) -> Self {
+        self.challenge_contract = Some(contract);
+        self.tracker =
+            Arc::new(Mutex::new(ChallengeTracker::new(challenge_window, resolve_window)));
+        self
+    }
+
+    /// Returns the challenge contract, if challenges are tracked.
+    pub const fn challenge_contract(&self) -> Option<Address> {
+        self.challenge_contract
+    }
+
+    /// Applies the challenge events of a new L1 block, before reading its batcher data.
+    pub fn on_l1_block(&self, block: u64, receipts: &[Receipt]) {
+        let mut tracker = self.tracker.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(contract) = self.challenge_contract {
+            tracker.apply_receipts(contract, block, receipts);
+        }
+        for commitment in tracker.advance(block) {
+            warn!("Challenge of commitment {} expired, skipping its input", commitment);
+        }
+    }
+
+    /// Resolves batcher data included in the given L1 block into the frames data.
+    ///
+    /// Data that is not an alt-DA commitment is returned as is. Returns `None` if the
+    /// data must be skipped, i.e. its commitment is invalid or its challenge expired,
+    /// and an error if the input is not available yet.
+    pub async fn resolve(&self, data: Bytes, inclusion: u64) -> Result<Option<Bytes>> {
+        if data.first() != Some(&ALTDA_DERIVATION_VERSION) {
+            return Ok(Some(data));
+        }
+        let commitment = match DaCommitment::decode(&data[1..]) {
+            Ok(commitment) => commitment,
+            Err(err) => {
+                warn!("Skipping invalid alt-DA commitment: {}", err);
+                return Ok(None);
+            }
+        };
+        self.tracker.lock().unwrap_or_else(|e| e.into_inner()).track(commitment.clone(), inclusion);
+
+        let fetched = self.client.get_input(&commitment).await;
+        let expired = || self.status(&commitment) == Some(ChallengeStatus::Expired);
+        match fetched {
+            Ok(Some(input)) if commitment.verify(&input) => Ok(Some(input)),
+            _ if expired() => Ok(None),
+            Ok(Some(_)) => bail!("DA server input does not match commitment {}", commitment),
+            Ok(None) => bail!("Input of commitment {} is not available", commitment),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Returns the challenge status of the given commitment, if it was challenged.
+    fn status(&self, commitment: &DaCommitment) -> Option<ChallengeStatus> {
+        self.tracker.lock().unwrap_or_else(|e| e.into_inner()).status(commitment)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_commitment_roundtrip() {
+        let hash = keccak256(b"input");
+        let commitment = DaCommitment::Keccak256(hash);
+        let encoded = commitment.encode();
+        assert_eq!(encoded[0], 0x00);
+        assert_eq!(DaCommitment::decode(&encoded).unwrap(), commitment);
+        assert!(commitment.verify(b"input"));
+        assert!(!commitment.verify(b"other"));
+
+        let generic = DaCommitment::Generic(Bytes::from_static(&[0x0c, 1, 2]));
+        assert_eq!(DaCommitment::decode(&generic.encode()).unwrap(), generic);
+        assert!(DaCommitment::decode(&[0x00, 1, 2]).is_err());
+        assert!(DaCommitment::decode(&[0x02, 1]).is_err());
+    }
+
+    #[test]
+    fn test_challenge_expiry() {
+        let mut tracker = ChallengeTracker::new(10, 5);
+        let commitment = DaCommitment::Keccak256(keccak256(b"input"));
+        let resolved = DaCommitment::Keccak256(keccak256(b"resolved"));
+        tracker.track(commitment.clone(), 100);
+        tracker.challenge(commitment.clone(), 100, 105);
+        tracker.challenge(resolved.clone(), 100, 105);
+        assert_eq!(tracker.status(&commitment), Some(ChallengeStatus::Active { resolve_by: 110 }));
+
+        tracker.resolve(&resolved);
+        assert!(tracker.advance(110).is_empty());
+        assert_eq!(tracker.advance(111), vec![commitment.clone()]);
+        assert_eq!(tracker.status(&commitment), Some(ChallengeStatus::Expired));
+        assert_eq!(tracker.status(&resolved), None);
+    }
+
+    #[tokio::test]
+    async fn test_resolve_passes_frames_through() {
+        let altda = AltDa::new(DaClient::new("http://localhost:1".parse().unwrap()));
+        let frames = Bytes::from_static(&[0x00, 1, 2, 3]);
+        assert_eq!(altda.resolve(frames.clone(), 1).await.unwrap(), Some(frames));
+        assert_eq!(altda.resolve(Bytes::from_static(&[0x01, 0x07]), 1).await.unwrap(), None);
+    }
+}
@@ -0,0 +1,416 @@
+//! Batch submission of the L2 blocks to L1
+
+use std::{
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use alloy::{
+    eips::eip4844::{Blob, BlobTransactionSidecar, Bytes48},
+    primitives::{Bytes, TxKind},
+    providers::{Provider, ReqwestProvider},
+    rpc::types::{TransactionInput, TransactionRequest},
+    signers::local::PrivateKeySigner,
+};
+use eyre::{eyre, Context, Result};
+use kona_derive::{batch::SingleBatch, online::AlloyL2ChainProvider, traits::L2ChainProvider};
+use kona_primitives::{BlockID, Frame, L2ExecutionPayload, RawTransaction};
+use reth::primitives::{
+    constants::eip4844::MAINNET_KZG_TRUSTED_SETUP,
+    kzg::{Blob as KzgBlob, KzgCommitment, KzgProof},
+};
+use ser::types::{
+    encode_blob, ChannelOut, ZlibCompressor, FRAME_V0_OVERHEAD_SIZE, MAX_BLOB_DATA_SIZE,
+};
+use superchain_registry::RollupConfig;
+use tokio::{sync::watch, time::sleep};
+use tracing::{debug, info, warn};
+
+use crate::{driver::DEPOSIT_TX_TYPE, DaMode, HeraArgsExt, SyncStatus, TxManager};
+
+/// The default interval at which the batcher checks for new L2 blocks.
+pub const DEFAULT_BATCHER_POLL_INTERVAL: Duration = Duration::from_secs(6);
+
+/// The default number of L1 blocks before a channel times out on L1 by which it must
+/// be submitted.
+pub const DEFAULT_SUB_SAFETY_MARGIN: u64 = 10;
+
+/// The default number of L1 blocks after which a channel that is not full is submitted.
+pub const DEFAULT_MAX_CHANNEL_DURATION: u64 = 10;
+
+/// The version byte prefixing the frames of a batcher transaction.
+const DERIVATION_VERSION_0: u8 = 0;
+
+/// The max number of blobs of a batcher transaction.
+const MAX_BLOBS_PER_TX: usize = 6;
+
+/// Returns the [SingleBatch] of the given L2 block, with the given L1 origin.
+///
+/// The deposit transactions are left out, since they are derived from L1.
+pub fn single_batch(payload: &L2ExecutionPayload, epoch: BlockID) -> SingleBatch {
+    SingleBatch {
+        parent_hash: payload.parent_hash,
+        epoch_num: epoch.number,
+        epoch_hash: epoch.hash,
+        timestamp: payload.timestamp,
+        transactions: payload
+            .transactions
+            .iter()
+            .filter(|tx| tx.first() != Some(&DEPOSIT_TX_TYPE))
+            .map(|tx| RawTransaction(tx.clone()))
+            .collect(),
+    }
+}
+
+/// Returns the batcher transaction data carrying the given frame, in calldata or in a
+/// blob.
+pub fn batcher_tx_data(frame: &Frame) -> Bytes {
+    let mut data = vec![DERIVATION_VERSION_0];
+    data.extend_from_slice(&frame.encode());
+    data.into()
+}
+
+/// Encodes the given batcher transaction data into blobs, along with their KZG
+/// commitments and proofs.
+fn blob_sidecar(data: &[Bytes]) -> Result<BlobTransactionSidecar> {
+    let mut sidecar = BlobTransactionSidecar::default();
+    for data in data {
+        let blob = encode_blob(data)?;
+        let kzg_blob = KzgBlob::from_bytes(&blob).map_err(|e| eyre!("Invalid blob: {:?}", e))?;
+        let commitment =
+            KzgCommitment::blob_to_kzg_commitment(&kzg_blob, &MAINNET_KZG_TRUSTED_SETUP)
+                .map_err(|e| eyre!("Failed to commit blob: {:?}", e))?
+                .to_bytes();
+        let proof =
+            KzgProof::compute_blob_kzg_proof(&kzg_blob, &commitment, &MAINNET_KZG_TRUSTED_SETUP)
+                .map_err(|e| eyre!("Failed to compute blob proof: {:?}", e))?
+                .to_bytes();
+        sidecar.blobs.push(Blob::from_slice(&blob));
+        sidecar.commitments.push(Bytes48::from_slice(commitment.as_slice()));
+        sidecar.proofs.push(Bytes48::from_slice(proof.as_slice()));
+    }
+    Ok(sidecar)
+}
+
+/// Returns `true` if the derivation pipeline has caught up with the L1 head, within the
+/// given number of blocks, so that its safe head reflects the submitted batches.
+fn derivation_synced(status: &SyncStatus, margin: u64) -> bool {
+    status.head_l1.number != 0 && status.current_l1.number + margin >= status.head_l1.number
+}
+
+/// Returns the current unix timestamp, in seconds.
+fn unix_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
+}
+
+/// A submitted channel, waiting for its blocks to become safe.
+#[derive(Debug, Clone, Copy)]
+struct SubmittedChannel {
+    /// The last L2 block of the channel
+    last_block: u64,
+    /// The L1 block the channel was fully included in
+    l1_block: u64,
+}
+
+/// BatchSubmitter
+///
+/// The batcher of a sequencer. Encodes the L2 blocks past the safe head into channels,
+/// compressed with zlib, and submits their frames to the batch inbox on L1, in calldata
+/// or in blobs.
+///
+/// The safe head is followed through the sync status of the rollup driver. Once it has
+/// caught up with L1, the blocks after the safe head are batched into a channel until
+/// it is full or has been open for the max channel duration. Its frames are then
+/// submitted one transaction at a time, or six blobs per transaction. If the blocks of
+/// a submitted channel don't become safe before it times out on L1, or a submission
+/// fails, batching restarts from the safe head.
+#[derive(Debug)]
+pub struct BatchSubmitter {
+    /// The rollup configuration
+    cfg: Arc<RollupConfig>,
+    /// The L2 chain provider, for the blocks to batch
+    l2_chain_provider: AlloyL2ChainProvider,
+    /// The L2 provider, for the latest block number
+    l2_provider: ReqwestProvider,
+    /// The manager of the batcher transactions
+    txs: TxManager,
+    /// The sync status of the rollup driver, for the safe head
+    sync_status: watch::Receiver<SyncStatus>,
+    /// Whether the frames are submitted in calldata or in blobs
+    da_mode: DaMode,
+    /// The max size of the calldata of a batcher transaction
+    max_frame_size: u64,
+    /// The number of L1 blocks after which a channel is submitted
+    max_channel_duration: u64,
+    /// The number of L1 blocks before a channel times out by which it is submitted
+    sub_safety_margin: u64,
+    /// The interval at which new L2 blocks are checked for
+    poll_interval: Duration,
+    /// The next L2 block to batch, once started
+    next_block: Option<u64>,
+    /// The channel being filled
+    channel: Option<ChannelOut<ZlibCompressor>>,
+    /// The last submitted channel, until its blocks become safe
+    submitted: Option<SubmittedChannel>,
+}
+
+impl BatchSubmitter {
+    /// Creates a new standalone [BatchSubmitter], following the safe head of the given
+    /// sync status.
+    pub fn standalone(
+        args: &HeraArgsExt,
+        cfg: Arc<RollupConfig>,
+        sync_status: watch::Receiver<SyncStatus>,
+    ) -> Result<Self> {
+        let key = args.batcher_private_key.ok_or(eyre!("Missing batcher private key"))?;
+        let signer = PrivateKeySigner::from_bytes(&key).wrap_err("Invalid batcher key")?;
+        if let Some(system_config) = &cfg.genesis.system_config {
+            if system_config.batcher_address != signer.address() {
+                warn!(
+                    "Batcher key {} does not match the genesis batcher address {}",
+                    signer.address(),
+                    system_config.batcher_address
+                );
+            }
+        }
+        let txs = TxManager::new(args.l1_rpc_url.clone(), signer, cfg.l1_chain_id)
+            .with_fee_bump_percent(args.batcher_fee_bump_percent)
+            .with_resubmission_timeout(Duration::from_secs(args.batcher_resubmission_timeout));
+        Ok(Self {
+            l2_chain_provider: AlloyL2ChainProvider::new_http(args.l2_rpc_url.clone(), cfg.clone()),
+            l2_provider: ReqwestProvider::new_http(args.l2_rpc_url.clone()),
+            cfg,
+            txs,
+            sync_status,
+            da_mode: args.batcher_data_type,
+            max_frame_size: args.batcher_max_frame_size,
+            max_channel_duration: args.batcher_max_channel_duration,
+            sub_safety_margin: args.batcher_sub_safety_margin,
+            poll_interval: Duration::from_secs(args.batcher_poll_interval),
+            next_block: None,
+            channel: None,
+            submitted: None,
+        })
+    }
+
+    /// Starts the batching loop. It only returns on an unrecoverable error.
+    pub async fn start(mut self) -> Result<()> {
+        info!("Starting batcher, submitting from {}", self.txs.address());
+        loop {
+            if let Err(err) = self.tick().await {
+                warn!("Batch submission failed, restarting from the safe head: {:?}", err);
+                metrics::counter!("hera_batcher_failures_total").increment(1);
+                self.reset();
+            }
+            sleep(self.poll_interval).await;
+        }
+    }
+
+    /// Batches the new L2 blocks, and submits the channel once it is ready.
+    async fn tick(&mut self) -> Result<()> {
+        let status = *self.sync_status.borrow();
+        if !derivation_synced(&status, self.sub_safety_margin) {
+            debug!("Waiting for the derivation to catch up with L1 before batching");
+            return Ok(());
+        }
+        let safe = status.safe_l2.block_info.number;
+        let l1_head = self.txs.l1_block_number().await?;
+        if let Some(submitted) = self.submitted {
+            if safe >= submitted.last_block {
+                self.submitted = None;
+            } else if l1_head > submitted.l1_block + self.cfg.channel_timeout {
+                warn!("Blocks up to {} did not become safe in time", submitted.last_block);
+                self.reset();
+            }
+        }
+        let next = match self.next_block {
+            Some(next) if next > safe => next,
+            _ => {
+                self.channel = None;
+                safe + 1
+            }
+        };
+
+        let latest = self.l2_provider.get_block_number().await?;
+        for number in next..=latest {
+            let batch = self.batch(number).await?;
+            let mut channel = match self.channel.take() {
+                Some(channel) => channel,
+                None => self.open_channel(batch.epoch_num, l1_head),
+            };
+            if channel.add_batch(batch.clone()).is_err() {
+                // The channel was closed without this batch, which opens the next one.
+                self.channel = Some(channel);
+                self.submit_channel().await?;
+                channel = self.open_channel(batch.epoch_num, l1_head);
+                channel.add_batch(batch)?;
+            }
+            let closed = channel.closed;
+            self.channel = Some(channel);
+            self.next_block = Some(number + 1);
+            metrics::counter!("hera_batcher_blocks_total").increment(1);
+            metrics::gauge!("hera_batcher_pending_blocks").set((latest - number) as f64);
+            if closed {
+                self.submit_channel().await?;
+            }
+        }
+
+        if let Some(channel) = self.channel.as_mut() {
+            channel.check_timed_out(l1_head);
+            if channel.closed {
+                self.submit_channel().await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Forgets the batched blocks, to restart from the safe head.
+    fn reset(&mut self) {
+        self.next_block = None;
+        self.channel = None;
+        self.submitted = None;
+    }
+
+    /// Returns the [SingleBatch] of the given L2 block.
+    async fn batch(&mut self, number: u64) -> Result<SingleBatch> {
+        let envelope = self
+            .l2_chain_provider
+            .payload_by_number(number)
+            .await
+            .map_err(|e| eyre!("Failed to fetch L2 block {}: {:?}", number, e))?;
+        let block = envelope
+            .to_l2_block_ref(&self.cfg)
+            .map_err(|e| eyre!("Failed to read L2 block {}: {:?}", number, e))?;
+        Ok(single_batch(&envelope.execution_payload, block.l1_origin))
+    }
+
+    /// Returns `true` if the frames are submitted in blobs.
+    fn use_blobs(&self) -> bool {
+        match self.da_mode {
+            DaMode::Calldata => false,
+            DaMode::Blobs => true,
+            DaMode::Auto => self.cfg.ecotone_time.is_some_and(|time| unix_now() >= time),
+        }
+    }
+
+    /// Opens a new channel starting at the given L1 origin, sized to fill one calldata
+    /// transaction or the blobs of one blob transaction.
+    fn open_channel(&self, epoch_num: u64, l1_head: u64) -> ChannelOut<ZlibCompressor> {
+        let (frame_size, frames) = match self.use_blobs() {
+            true => (MAX_BLOB_DATA_SIZE as u64, MAX_BLOBS_PER_TX as u64),
+            false => (self.max_frame_size, 1),
+        };
+        // Leave room for the version byte of the batcher transaction.
+        let frame_size = frame_size - 1;
+        let target = (frame_size.saturating_sub(FRAME_V0_OVERHEAD_SIZE) * frames) as usize;
+        let mut channel = ChannelOut::new(
+            self.cfg.clone(),
+            ZlibCompressor::new(target),
+            epoch_num,
+            self.sub_safety_margin,
+        )
+        .with_max_frame_size(frame_size);
+        channel.channel_timeout = channel.channel_timeout.min(l1_head + self.max_channel_duration);
+        channel
+    }
+
+    /// Closes the current channel and submits all its frames.
+    async fn submit_channel(&mut self) -> Result<()> {
+        let Some(mut channel) = self.channel.take() else {
+            return Ok(());
+        };
+        channel.close()?;
+        let mut data = Vec::new();
+        while let Some(frame) = channel.output_frame() {
+            data.push(batcher_tx_data(&frame));
+        }
+
+        let inbox = TxKind::Call(self.cfg.batch_inbox_address);
+        let mut l1_block = 0;
+        match self.use_blobs() {
+            true => {
+                for chunk in data.chunks(MAX_BLOBS_PER_TX) {
+                    let sidecar = blob_sidecar(chunk)?;
+                    let tx = TransactionRequest {
+                        to: Some(inbox),
+                        blob_versioned_hashes: Some(sidecar.versioned_hashes().collect()),
+                        sidecar: Some(sidecar),
+                        ..Default::default()
+                    };
+                    let receipt = self.txs.send(tx).await?;
+                    l1_block = receipt.block_number.unwrap_or_default();
+                    metrics::counter!("hera_batcher_txs_total", "da" => "blobs").increment(1);
+                    metrics::counter!("hera_batcher_blobs_total").increment(chunk.len() as u64);
+                }
+            }
+            false => {
+                for data in data.iter() {
+                    let tx = TransactionRequest {
+                        to: Some(inbox),
+                        input: TransactionInput::new(data.clone()),
+                        ..Default::default()
+                    };
+                    let receipt = self.txs.send(tx).await?;
+                    l1_block = receipt.block_number.unwrap_or_default();
+                    metrics::counter!("hera_batcher_txs_total", "da" => "calldata").increment(1);
+                }
+            }
+        }
+
+        let last_block = self.next_block.unwrap_or_default().saturating_sub(1);
+        metrics::counter!("hera_batcher_channels_total").increment(1);
+        metrics::counter!("hera_batcher_frames_total").increment(data.len() as u64);
+        info!(
+            "Submitted channel {} with blocks up to {} in {} frames",
+            Bytes::copy_from_slice(&channel.id),
+            last_block,
+            data.len()
+        );
+        self.submitted = Some(SubmittedChannel { last_block, l1_block });
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::B256;
+    use kona_primitives::BlockInfo;
+
+    #[test]
+    fn test_single_batch() {
+        let payload = L2ExecutionPayload {
+            parent_hash: B256::repeat_byte(1),
+            timestamp: 100,
+            transactions: vec![Bytes::from_static(&[0x7e, 1]), Bytes::from_static(&[0x02, 2])],
+            ..Default::default()
+        };
+        let epoch = BlockID { hash: B256::repeat_byte(2), number: 10 };
+        let batch = single_batch(&payload, epoch);
+        assert_eq!(batch.parent_hash, payload.parent_hash);
+        assert_eq!(batch.epoch_num, 10);
+        assert_eq!(batch.epoch_hash, epoch.hash);
+        assert_eq!(batch.transactions, vec![RawTransaction(Bytes::from_static(&[0x02, 2]))]);
+    }
+
+    #[test]
+    fn test_batcher_tx_data() {
+        let frame = Frame { data: vec![0xaa; 4], is_last: true, ..Default::default() };
+        let data = batcher_tx_data(&frame);
+        assert_eq!(data[0], DERIVATION_VERSION_0);
+        assert_eq!(data.len(), 1 + FRAME_V0_OVERHEAD_SIZE as usize + 4);
+        assert_eq!(data[data.len() - 1], 1);
+    }
+
+    #[test]
+    fn test_derivation_synced() {
+        let status = |current, head| SyncStatus {
+            current_l1: BlockInfo { number: current, ..Default::default() },
+            head_l1: BlockInfo { number: head, ..Default::default() },
+            ..Default::default()
+        };
+        assert!(!derivation_synced(&status(0, 0), 10));
+        assert!(!derivation_synced(&status(50, 100), 10));
+        assert!(derivation_synced(&status(90, 100), 10));
+    }
+}
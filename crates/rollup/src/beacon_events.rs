@@ -0,0 +1,303 @@
+//! L1 head and finality tracking from the event stream of a beacon node
+
+use std::{sync::Arc, time::Duration};
+
+use eyre::{bail, eyre, Result};
+use reqwest::{header::ACCEPT, Client, StatusCode};
+use serde::Deserialize;
+use tokio::sync::watch;
+use tracing::{debug, trace, warn};
+use url::Url;
+
+use crate::Supervisor;
+
+/// The timeout of the requests fetching the beacon blocks of the events.
+const BEACON_REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Tracks the head and the finalized block of the L1 chain from the
+/// [`head` and `finalized_checkpoint` events](https://ethereum.github.io/beacon-APIs/#/Events/eventstream)
+/// of a beacon node.
+///
+/// The events name beacon blocks, whose execution payload is fetched to learn the
+/// number of the L1 execution block. The beacon node only emits them once the
+/// execution client imported the block, so the L1 RPC can serve it right away.
+#[derive(Debug, Clone)]
+pub struct BeaconEventTracker {
+    /// The beacon node URL.
+    url: Url,
+    /// The reqwest client.
+    client: Client,
+}
+
+impl BeaconEventTracker {
+    /// Creates a new [BeaconEventTracker] for the beacon node at the given URL.
+    pub fn new(url: Url) -> Self {
+        Self { url, client: Client::new() }
+    }
+
+    /// Spawns the tracking task under the given [Supervisor], returning receivers of
+    /// the latest L1 head number and of the latest L1 finalized block number, once known.
+    ///
+    /// The event stream is reconnected by the supervisor when it fails or ends, and the
+    /// receivers keep the last known blocks across reconnections.
+    pub fn supervise(
+        self,
+        supervisor: &mut Supervisor,
+    ) -> (watch::Receiver<u64>, watch::Receiver<Option<u64>>) {
+        let heads = Arc::new(watch::channel(0).0);
+        let finalized = Arc::new(watch::channel(None).0);
+        let recvs = (heads.subscribe(), finalized.subscribe());
+        supervisor.spawn("beacon_event_tracker", move || {
+            let tracker = self.clone();
+            let (heads, finalized) = (heads.clone(), finalized.clone());
+            async move { tracker.run(&heads, &finalized).await }
+        });
+        recvs
+    }
+
+    /// Follows the event stream until it ends or both receivers are dropped.
+    async fn run(
+        &self,
+        heads: &watch::Sender<u64>,
+        finalized: &watch::Sender<Option<u64>>,
+    ) -> Result<()> {
+        let url = self.endpoint("eth/v1/events?topics=head,finalized_checkpoint")?;
+        let mut response = self.client.get(url).header(ACCEPT, "text/event-stream").send().await?;
+        if response.status() != StatusCode::OK {
+            bail!("beacon node returned status: {}", response.status());
+        }
+        debug!("Subscribed to the events of the beacon node at {}", self.url);
+
+        let mut decoder = SseDecoder::default();
+        while let Some(chunk) = response.chunk().await? {
+            for event in decoder.push(&chunk) {
+                if matches!(event, BeaconEvent::Head { .. }) && heads.is_closed() {
+                    continue;
+                }
+                metrics::counter!("hera_beacon_events_total", "topic" => event.topic())
+                    .increment(1);
+                let number = match self.execution_block_number(&event).await {
+                    Ok(Some(number)) => number,
+                    // Pre-merge blocks have no execution payload.
+                    Ok(None) => continue,
+                    Err(err) => {
+                        warn!(
+                            ?err,
+                            "Failed to fetch the beacon block of a {} event",
+                            event.topic()
+                        );
+                        continue;
+                    }
+                };
+                match event {
+                    BeaconEvent::Head { .. } => {
+                        trace!("L1 head from the beacon node: {}", number);
+                        heads.send_if_modified(|head| std::mem::replace(head, number) != number);
+                    }
+                    BeaconEvent::FinalizedCheckpoint { .. } => {
+                        debug!("L1 finalized block from the beacon node: {}", number);
+                        finalized.send_replace(Some(number));
+                    }
+                }
+            }
+            if heads.is_closed() && finalized.is_closed() {
+                return Ok(());
+            }
+        }
+        Err(eyre!("beacon event stream ended"))
+    }
+
+    /// Returns the number of the execution block of the beacon block of the given event,
+    /// if it has one.
+    async fn execution_block_number(&self, event: &BeaconEvent) -> Result<Option<u64>> {
+        let block_root = event.block_root()?;
+        let url = self.endpoint(&format!("eth/v2/beacon/blocks/{}", block_root))?;
+        let response = self.client.get(url).timeout(BEACON_REQUEST_TIMEOUT).send().await?;
+        if response.status() != StatusCode::OK {
+            bail!("beacon node returned status {} for block {}", response.status(), block_root);
+        }
+        let block: BeaconBlockResponse = serde_json::from_slice(&response.bytes().await?)?;
+        block
+            .data
+            .message
+            .body
+            .execution_payload
+            .map(|payload| payload.block_number.parse())
+            .transpose()
+            .map_err(|e| eyre!("invalid execution block number: {:?}", e))
+    }
+
+    /// Returns the URL of the given endpoint of the beacon node.
+    fn endpoint(&self, path: &str) -> Result<Url> {
+        Ok(format!("{}/{}", self.url.as_str().trim_end_matches('/'), path).parse()?)
+    }
+}
+
+/// An event of the beacon node event stream tracked by the [BeaconEventTracker].
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum BeaconEvent {
+    /// The head of the beacon chain changed.
+    Head {
+        /// The root of the new head block.
+        block: String,
+    },
+    /// A new checkpoint was finalized.
+    FinalizedCheckpoint {
+        /// The root of the finalized block.
+        block: String,
+    },
+}
+
+impl BeaconEvent {
+    /// Parses the event of the given topic from its JSON data, ignoring other topics.
+    fn parse(topic: &str, data: &str) -> Option<Self> {
+        /// The fields shared by the `head` and `finalized_checkpoint` events.
+        #[derive(Deserialize)]
+        struct EventData {
+            block: String,
+        }
+
+        let EventData { block } = serde_json::from_str(data).ok()?;
+        match topic {
+            "head" => Some(Self::Head { block }),
+            "finalized_checkpoint" => Some(Self::FinalizedCheckpoint { block }),
+            _ => None,
+        }
+    }
+
+    /// Returns the topic of the event.
+    const fn topic(&self) -> &'static str {
+        match self {
+            Self::Head { .. } => "head",
+            Self::FinalizedCheckpoint { .. } => "finalized_checkpoint",
+        }
+    }
+
+    /// Returns the root of the beacon block of the event.
+    fn block_root(&self) -> Result<&str> {
+        let (Self::Head { block } | Self::FinalizedCheckpoint { block }) = self;
+        let is_root = block
+            .strip_prefix("0x")
+            .is_some_and(|hex| hex.len() == 64 && hex.bytes().all(|byte| byte.is_ascii_hexdigit()));
+        if !is_root {
+            bail!("invalid beacon block root: {}", block);
+        }
+        Ok(block)
+    }
+}
+
+/// The parts of a `GET /eth/v2/beacon/blocks/{block_id}` response read by the tracker.
+#[derive(Deserialize)]
+struct BeaconBlockResponse {
+    data: SignedBeaconBlock,
+}
+
+#[derive(Deserialize)]
+struct SignedBeaconBlock {
+    message: BeaconBlock,
+}
+
+#[derive(Deserialize)]
+struct BeaconBlock {
+    body: BeaconBlockBody,
+}
+
+#[derive(Deserialize)]
+struct BeaconBlockBody {
+    execution_payload: Option<ExecutionPayload>,
+}
+
+#[derive(Deserialize)]
+struct ExecutionPayload {
+    /// The block number, as a decimal string.
+    block_number: String,
+}
+
+/// An incremental decoder of a
+/// [server-sent events](https://html.spec.whatwg.org/multipage/server-sent-events.html)
+/// stream, keeping the events of the [BeaconEvent] topics.
+#[derive(Debug, Default)]
+struct SseDecoder {
+    /// The bytes of the current incomplete line.
+    line: Vec<u8>,
+    /// The topic of the current event.
+    topic: String,
+    /// The data lines of the current event.
+    data: Vec<String>,
+}
+
+impl SseDecoder {
+    /// Decodes the given chunk of the stream, returning the events it completes.
+    fn push(&mut self, chunk: &[u8]) -> Vec<BeaconEvent> {
+        let mut events = Vec::new();
+        for &byte in chunk {
+            if byte != b'\n' {
+                self.line.push(byte);
+                continue;
+            }
+            let line = String::from_utf8_lossy(&self.line).trim_end_matches('\r').to_string();
+            self.line.clear();
+            if line.is_empty() {
+                let data = std::mem::take(&mut self.data).join("\n");
+                events.extend(BeaconEvent::parse(&std::mem::take(&mut self.topic), &data));
+                continue;
+            }
+            let (field, value) = line.split_once(':').unwrap_or((&line, ""));
+            let value = value.strip_prefix(' ').unwrap_or(value);
+            match field {
+                "event" => self.topic = value.to_string(),
+                "data" => self.data.push(value.to_string()),
+                // Comments, keep-alives, ids and retry delays.
+                _ => {}
+            }
+        }
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROOT: &str = "0x9a2fefd2fdb57f74993c7780ea5b9030d2897b615b89f808011ca5aebed54eaf";
+
+    #[test]
+    fn test_sse_decoder() {
+        let mut decoder = SseDecoder::default();
+        let head = format!("event: head\ndata: {{\"slot\":\"10\",\"block\":\"{}\"}}\n\n", ROOT);
+        let (first, second) = head.as_bytes().split_at(20);
+        assert!(decoder.push(first).is_empty());
+        assert_eq!(decoder.push(second), vec![BeaconEvent::Head { block: ROOT.to_string() }]);
+
+        let finalized = format!(
+            ": keep-alive\r\nevent:finalized_checkpoint\r\ndata:{{\"block\":\"{}\",\r\ndata:\"epoch\":\"2\"}}\r\n\r\n",
+            ROOT
+        );
+        assert_eq!(
+            decoder.push(finalized.as_bytes()),
+            vec![BeaconEvent::FinalizedCheckpoint { block: ROOT.to_string() }]
+        );
+
+        let other = "event: chain_reorg\ndata: {\"block\":\"0x00\"}\n\nevent: head\ndata: {}\n\n";
+        assert!(decoder.push(other.as_bytes()).is_empty());
+    }
+
+    #[test]
+    fn test_block_root() {
+        let event = BeaconEvent::Head { block: ROOT.to_string() };
+        assert_eq!(event.block_root().unwrap(), ROOT);
+        let event = BeaconEvent::Head { block: "0x00/../../eth/v1/node".to_string() };
+        assert!(event.block_root().is_err());
+    }
+
+    #[test]
+    fn test_execution_block_number() {
+        let json = r#"{"version":"deneb","data":{"message":{"slot":"1","body":{"execution_payload":{"block_number":"123","block_hash":"0x00"}}},"signature":"0x00"}}"#;
+        let block: BeaconBlockResponse = serde_json::from_str(json).unwrap();
+        assert_eq!(block.data.message.body.execution_payload.unwrap().block_number, "123");
+
+        let json = r#"{"version":"phase0","data":{"message":{"body":{}}}}"#;
+        let block: BeaconBlockResponse = serde_json::from_str(json).unwrap();
+        assert!(block.data.message.body.execution_payload.is_none());
+    }
+}
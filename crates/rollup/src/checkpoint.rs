@@ -0,0 +1,95 @@
+//! Persistence of the derivation progress
+
+use std::{fs, io::ErrorKind, path::PathBuf};
+
+use eyre::{bail, Result, WrapErr};
+use kona_primitives::L2BlockInfo;
+use serde::{Deserialize, Serialize};
+
+/// A checkpoint of the derivation progress, as stored on disk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Checkpoint {
+    /// The chain ID of the L2 network the checkpoint belongs to.
+    pub l2_chain_id: u64,
+    /// The latest validated L2 block, with its L1 origin.
+    pub safe_head: L2BlockInfo,
+}
+
+/// A file recording the latest validated L2 block, which derivation resumes from
+/// after a restart.
+///
+/// The file is replaced atomically on every save, so that a crash never leaves a
+/// partially written checkpoint behind.
+#[derive(Debug, Clone)]
+pub struct CheckpointStore {
+    /// The path of the checkpoint file.
+    path: PathBuf,
+    /// The chain ID of the L2 network being derived.
+    l2_chain_id: u64,
+}
+
+impl CheckpointStore {
+    /// Creates a new [CheckpointStore] for the given L2 network, stored at the given path.
+    pub fn new(path: impl Into<PathBuf>, l2_chain_id: u64) -> Self {
+        Self { path: path.into(), l2_chain_id }
+    }
+
+    /// Loads the checkpointed safe head, if a checkpoint was saved.
+    ///
+    /// Fails if the checkpoint can't be read, or belongs to another L2 network.
+    pub fn load(&self) -> Result<Option<L2BlockInfo>> {
+        let data = match fs::read(&self.path) {
+            Ok(data) => data,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err).wrap_err("Failed to read the checkpoint file"),
+        };
+        let checkpoint: Checkpoint =
+            serde_json::from_slice(&data).wrap_err("Failed to parse the checkpoint file")?;
+        if checkpoint.l2_chain_id != self.l2_chain_id {
+            bail!(
+                "Checkpoint {:?} belongs to chain {}, not {}",
+                self.path,
+                checkpoint.l2_chain_id,
+                self.l2_chain_id
+            );
+        }
+        Ok(Some(checkpoint.safe_head))
+    }
+
+    /// Saves the given safe head as the new checkpoint.
+    pub fn save(&self, safe_head: &L2BlockInfo) -> Result<()> {
+        let checkpoint = Checkpoint { l2_chain_id: self.l2_chain_id, safe_head: *safe_head };
+        let tmp = self.path.with_extension("tmp");
+        fs::write(&tmp, serde_json::to_vec_pretty(&checkpoint)?)?;
+        fs::rename(&tmp, &self.path)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use kona_primitives::{BlockID, BlockInfo};
+
+    #[test]
+    fn test_save_and_load() {
+        let path =
+            std::env::temp_dir().join(format!("hera-checkpoint-{}.json", std::process::id()));
+        _ = fs::remove_file(&path);
+        let store = CheckpointStore::new(&path, 10);
+        assert_eq!(store.load().unwrap(), None);
+
+        let head = L2BlockInfo {
+            block_info: BlockInfo { number: 42, ..Default::default() },
+            l1_origin: BlockID { number: 7, ..Default::default() },
+            seq_num: 1,
+        };
+        store.save(&head).unwrap();
+        assert_eq!(store.load().unwrap(), Some(head));
+
+        let other = CheckpointStore::new(&path, 8453);
+        assert!(other.load().unwrap_err().to_string().contains("belongs to chain 10"));
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,133 @@
+//! Circuit breaker for calls to an unreliable remote endpoint
+
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// The default number of consecutive failures after which the circuit opens.
+pub const DEFAULT_MAX_CONSECUTIVE_FAILURES: usize = 5;
+
+/// The default time to wait before probing the endpoint again once the circuit is open.
+pub const DEFAULT_CIRCUIT_BREAKER_COOLDOWN: Duration = Duration::from_secs(30);
+
+/// A circuit breaker that marks a remote endpoint as unhealthy after a number of
+/// consecutive failures.
+///
+/// While the circuit is open, requests are rejected immediately instead of waiting for
+/// the endpoint to time out. Once the cooldown has elapsed, a single request is let through
+/// to probe the endpoint: a success closes the circuit again, a failure re-opens it.
+///
+/// Clones share the same state.
+#[derive(Debug, Clone)]
+pub struct CircuitBreaker {
+    /// The number of consecutive failures after which the circuit opens.
+    max_failures: usize,
+    /// The time to wait before probing the endpoint again.
+    cooldown: Duration,
+    /// The shared state of the breaker.
+    state: Arc<Mutex<BreakerState>>,
+}
+
+/// The mutable state of a [CircuitBreaker].
+#[derive(Debug, Default)]
+struct BreakerState {
+    /// The number of consecutive failures.
+    failures: usize,
+    /// The time at which the circuit was last opened (or probed).
+    opened_at: Option<Instant>,
+}
+
+impl Default for CircuitBreaker {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_CONSECUTIVE_FAILURES, DEFAULT_CIRCUIT_BREAKER_COOLDOWN)
+    }
+}
+
+impl CircuitBreaker {
+    /// Creates a new [CircuitBreaker] opening after `max_failures` consecutive failures.
+    pub fn new(max_failures: usize, cooldown: Duration) -> Self {
+        Self { max_failures: max_failures.max(1), cooldown, state: Default::default() }
+    }
+
+    /// Returns whether a request should be sent to the endpoint.
+    ///
+    /// When the circuit is open and the cooldown has elapsed, this lets a single probe
+    /// request through and restarts the cooldown.
+    pub fn allow_request(&self) -> bool {
+        let mut state = self.state.lock().expect("circuit breaker lock poisoned");
+        match state.opened_at {
+            None => true,
+            Some(opened_at) if opened_at.elapsed() >= self.cooldown => {
+                state.opened_at = Some(Instant::now());
+                true
+            }
+            Some(_) => false,
+        }
+    }
+
+    /// Records a successful request, closing the circuit.
+    pub fn record_success(&self) {
+        let mut state = self.state.lock().expect("circuit breaker lock poisoned");
+        state.failures = 0;
+        state.opened_at = None;
+    }
+
+    /// Records a failed request, opening the circuit if the failure threshold is reached.
+    pub fn record_failure(&self) {
+        let mut state = self.state.lock().expect("circuit breaker lock poisoned");
+        state.failures = state.failures.saturating_add(1);
+        if state.failures >= self.max_failures {
+            state.opened_at = Some(Instant::now());
+        }
+    }
+
+    /// Returns whether the endpoint is considered healthy, i.e. the circuit is closed.
+    pub fn is_healthy(&self) -> bool {
+        self.state.lock().expect("circuit breaker lock poisoned").opened_at.is_none()
+    }
+
+    /// Returns the current number of consecutive failures.
+    pub fn consecutive_failures(&self) -> usize {
+        self.state.lock().expect("circuit breaker lock poisoned").failures
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_opens_after_consecutive_failures() {
+        let breaker = CircuitBreaker::new(2, Duration::from_secs(60));
+        breaker.record_failure();
+        assert!(breaker.is_healthy());
+        assert!(breaker.allow_request());
+
+        breaker.record_failure();
+        assert!(!breaker.is_healthy());
+        assert!(!breaker.allow_request());
+    }
+
+    #[test]
+    fn test_success_resets_failures() {
+        let breaker = CircuitBreaker::new(2, Duration::from_secs(60));
+        breaker.record_failure();
+        breaker.record_success();
+        breaker.record_failure();
+        assert!(breaker.is_healthy());
+        assert_eq!(breaker.consecutive_failures(), 1);
+    }
+
+    #[test]
+    fn test_probe_after_cooldown() {
+        let breaker = CircuitBreaker::new(1, Duration::ZERO);
+        breaker.record_failure();
+        assert!(!breaker.is_healthy());
+
+        // The cooldown has elapsed, so a probe is allowed through.
+        assert!(breaker.allow_request());
+        breaker.record_success();
+        assert!(breaker.is_healthy());
+    }
+}
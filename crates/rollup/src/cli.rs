@@ -1,10 +1,74 @@
 //! Module for the Hera Execution Extension CLI arguments.
 
-use std::path::PathBuf;
+use std::{
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
 
+use alloy::{
+    primitives::{Address, B256},
+    signers::local::PrivateKeySigner,
+};
 use clap::Args;
+use eyre::{eyre, Context, Result};
+use kona_primitives::BlockInfo;
+use kona_providers::{
+    blob_cache::{DEFAULT_BLOB_CACHE_MAX_SIZE, DEFAULT_BLOB_CACHE_RETENTION_SLOTS},
+    blob_provider::online_blob_provider,
+    chain_provider::DEFAULT_IN_MEMORY_CAPACITY,
+    disk_chain_provider::DEFAULT_CHAIN_STORE_MAX_BLOCKS,
+    BlobProvider, ChainProvider, DiskBlobCache, DiskChainProvider, OnlineBlobProvider,
+    SharedDiskBlobCache,
+};
+use libp2p::{Multiaddr, PeerId};
+use libp2p_identity::Keypair;
+use op_net::{
+    builder::{NetworkDriverBuilder, DEFAULT_IDLE_CONNECTION_TIMEOUT},
+    discovery::bootnodes::parse_bootnode,
+    driver::NetworkHandle,
+    gossip::{
+        config::{
+            GossipParams, MaxGossipSizes, DEFAULT_MESH_D, DEFAULT_MESH_DHI, DEFAULT_MESH_DLAZY,
+            DEFAULT_MESH_DLO,
+        },
+        gate::{ConnectionGate, Subnet},
+        scoring::{PeerScoring, DEFAULT_BAN_DURATION, DEFAULT_BAN_THRESHOLD},
+        sequencer::ForkSchedule,
+    },
+    keys::{keypair_from_mnemonic, DEFAULT_DERIVATION_PATH},
+};
+use reth::rpc::types::engine::JwtSecret;
+use ser::types::DEFAULT_MAX_FRAME_SIZE;
+use superchain_registry::{RollupConfig, ROLLUP_CONFIGS};
+use tracing::info;
 use url::Url;
 
+use crate::{
+    batcher::{
+        DEFAULT_BATCHER_POLL_INTERVAL, DEFAULT_MAX_CHANNEL_DURATION, DEFAULT_SUB_SAFETY_MARGIN,
+    },
+    circuit_breaker::{CircuitBreaker, DEFAULT_CIRCUIT_BREAKER_COOLDOWN},
+    engine::{
+        EngineClient, PayloadBodiesFetcher, DEFAULT_ENGINE_API_TIMEOUT,
+        DEFAULT_PAYLOAD_BODIES_BATCH_SIZE,
+    },
+    proposer::{DEFAULT_PROPOSAL_INTERVAL, DEFAULT_PROPOSER_POLL_INTERVAL},
+    rollup_config::load_rollup_config,
+    sequencer::DEFAULT_SEQUENCER_L1_CONFS,
+    superchain::{chain_name, resolve_chain_id},
+    tx_manager::{DEFAULT_FEE_BUMP_PERCENT, DEFAULT_RESUBMISSION_TIMEOUT},
+    validator::{
+        EngineApiValidator, MultiValidator, QuorumPolicy, SampledValidator, TrustedValidator,
+        DEFAULT_TRUSTED_RPC_TIMEOUT, DEFAULT_VALIDATION_DEADLINE,
+    },
+    AltDa, AttributesValidator, CheckpointStore, CrossSafetyTracker, DaClient, DaMode,
+    DependencySet, DriverEvents, EngineController, Prefetcher, ProposalTarget, SpanBatchConfig,
+    StepPacer, SystemConfigTracker, UnsafeSignerWatcher, DEFAULT_CHALLENGE_WINDOW,
+    DEFAULT_L1_POLL_INTERVAL, DEFAULT_PREFETCH_DEPTH, DEFAULT_RESOLVE_WINDOW, DEFAULT_STEP_BURST,
+};
+
 /// The default L2 chain ID to use. This corresponds to OP Mainnet.
 pub const DEFAULT_L2_CHAIN_ID: u64 = 10;
 
@@ -17,6 +81,14 @@ pub const DEFAULT_L2_RPC_URL: &str = "https://optimism.llamarpc.com/";
 /// The default L1 Beacon Client RPC URL to use.
 pub const DEFAULT_L1_BEACON_CLIENT_URL: &str = "http://localhost:5052/";
 
+/// The default maximum number of derived payloads that can be validated
+/// concurrently with the derivation of the next ones.
+pub const DEFAULT_VALIDATION_DEPTH: usize = 2;
+
+/// The default number of consecutive engine API failures after which
+/// the engine is marked unhealthy.
+pub const DEFAULT_ENGINE_MAX_FAILURES: usize = 5;
+
 /// The Hera Execution Extension CLI Arguments.
 #[derive(Debug, Clone, Args)]
 pub struct HeraArgsExt {
@@ -24,9 +96,18 @@ pub struct HeraArgsExt {
     #[clap(long = "hera.l2-chain-id", default_value_t = DEFAULT_L2_CHAIN_ID)]
     pub l2_chain_id: u64,
 
-    /// Path to a custom L2 rollup configuration file
+    /// Name or chain ID of an L2 network of the superchain registry, e.g. `op-mainnet`,
+    /// `base`, `zora` or `op-sepolia`, whose rollup config is loaded from the registry
+    /// data embedded in the binary.
+    #[clap(long = "hera.chain", conflicts_with_all = ["l2_chain_id", "l2_config_file"])]
+    pub chain: Option<String>,
+
+    /// Path to a custom op-node compatible rollup config JSON file
     /// (overrides the default rollup configuration from the registry)
-    #[clap(long = "hera.l2-config-file")]
+    ///
+    /// The config is validated before the node starts: genesis blocks, hardfork
+    /// ordering and the required addresses, e.g. the batcher's.
+    #[clap(long = "hera.l2-config-file", visible_alias = "hera.rollup-config")]
     pub l2_config_file: Option<PathBuf>,
 
     /// RPC URL of an L2 execution client
@@ -38,10 +119,36 @@ pub struct HeraArgsExt {
     #[clap(long = "hera.l1-rpc-url", default_value = DEFAULT_L1_RPC_URL)]
     pub l1_rpc_url: Url,
 
+    /// Websocket URL of an L1 execution client, used to subscribe to new L1 heads
+    /// instead of polling the L1 RPC URL
+    /// (This is only needed when running in Standalone mode)
+    #[clap(long = "hera.l1-ws-url")]
+    pub l1_ws_url: Option<Url>,
+
+    /// Interval in seconds at which the L1 head is polled, when no websocket
+    /// URL is set or the `eth_subscribe` subscription fails
+    /// (This is only needed when running in Standalone mode)
+    #[clap(long = "hera.l1-poll-interval", default_value_t = DEFAULT_L1_POLL_INTERVAL.as_secs())]
+    pub l1_poll_interval: u64,
+
     /// URL of an L1 beacon client to fetch blobs
-    #[clap(long = "hera.l1-beacon-client-url", default_value = DEFAULT_L1_BEACON_CLIENT_URL)]
+    ///
+    /// The beacon genesis time and seconds-per-slot are loaded from this client at startup.
+    #[clap(
+        long = "hera.l1-beacon-client-url",
+        visible_alias = "l1.beacon",
+        default_value = DEFAULT_L1_BEACON_CLIENT_URL
+    )]
     pub l1_beacon_client_url: Url,
 
+    /// Follow the `head` and `finalized_checkpoint` events of the L1 beacon client.
+    ///
+    /// In Standalone mode, the L1 head and finalized block are tracked from these events
+    /// instead of the L1 RPC. As an ExEx, the L1 reorgs of the host node are checked
+    /// against the finalized block of the beacon client.
+    #[clap(long = "hera.l1-beacon-events")]
+    pub l1_beacon_events: bool,
+
     /// URL of the blob archiver to fetch blobs that are expired on
     /// the beacon client but still needed for processing.
     ///
@@ -50,6 +157,108 @@ pub struct HeraArgsExt {
     #[clap(long = "hera.l1-blob-archiver-url")]
     pub l1_blob_archiver_url: Option<Url>,
 
+    /// Verify the blobs fetched from the beacon client or blob archiver against
+    /// the versioned hashes of the batcher transactions, by recomputing their
+    /// KZG commitments.
+    #[clap(long = "hera.verify-blobs")]
+    pub verify_blobs: bool,
+
+    /// Where to read the batcher data of the L1 blocks from: their `calldata`, their
+    /// `blobs`, or `auto` to read the calldata before Ecotone and the blobs after it.
+    #[clap(long = "hera.da-mode", default_value = "auto")]
+    pub da_mode: DaMode,
+
+    /// URL of an alt-DA server, to fetch the inputs of the alt-DA commitments posted by
+    /// the batcher, on chains using alt-DA (Plasma mode). Disabled if unset.
+    #[clap(long = "hera.altda-server", visible_alias = "altda-server")]
+    pub altda_server: Option<Url>,
+
+    /// Address of the `DataAvailabilityChallenge` contract on L1. If set, the inputs of
+    /// commitments challenged and not resolved in time are skipped.
+    #[clap(long = "hera.altda-challenge-contract", requires = "altda_server")]
+    pub altda_challenge_contract: Option<Address>,
+
+    /// Number of L1 blocks after its inclusion during which a commitment can be challenged.
+    #[clap(long = "hera.altda-challenge-window", default_value_t = DEFAULT_CHALLENGE_WINDOW)]
+    pub altda_challenge_window: u64,
+
+    /// Number of L1 blocks after a challenge during which it can be resolved.
+    #[clap(long = "hera.altda-resolve-window", default_value_t = DEFAULT_RESOLVE_WINDOW)]
+    pub altda_resolve_window: u64,
+
+    /// Directory of an on-disk cache of the blobs fetched from the beacon client
+    /// or blob archiver. Disabled if unset.
+    #[clap(long = "hera.blob-cache-dir")]
+    pub blob_cache_dir: Option<PathBuf>,
+
+    /// Maximum size in MiB of the on-disk blob cache.
+    ///
+    /// The blobs of the oldest L1 blocks are pruned first, but never those of the
+    /// safe head's L1 origin or newer blocks, so the cache may temporarily exceed it.
+    #[clap(long = "hera.blob-cache-max-size", default_value_t = DEFAULT_BLOB_CACHE_MAX_SIZE >> 20)]
+    pub blob_cache_max_size: u64,
+
+    /// Number of beacon slots the blobs are kept for in the on-disk blob cache, counted
+    /// from the newest cached block. Defaults to the retention window of beacon nodes.
+    #[clap(
+        long = "hera.blob-cache-retention",
+        default_value_t = DEFAULT_BLOB_CACHE_RETENTION_SLOTS,
+        conflicts_with = "blob_cache_archive"
+    )]
+    pub blob_cache_retention: u64,
+
+    /// Keep all the blobs in the on-disk blob cache indefinitely, regardless of their age
+    /// and of `--hera.blob-cache-max-size`, e.g. for archival nodes.
+    #[clap(long = "hera.blob-cache-archive")]
+    pub blob_cache_archive: bool,
+
+    /// Number of L1 blocks kept in memory for derivation, as an ExEx. The least recently
+    /// read blocks are evicted first, and the blocks finalized on L1 once derivation is
+    /// past them.
+    #[clap(long = "hera.l1-cache-size", default_value_t = DEFAULT_IN_MEMORY_CAPACITY)]
+    pub l1_cache_size: usize,
+
+    /// Read the L1 chain data from the database of the host node as an ExEx, instead of
+    /// keeping a copy of the notified blocks in memory. `--hera.l1-cache-size` and the
+    /// on-disk L1 store are then unused.
+    #[clap(long = "hera.l1-reth-db")]
+    pub l1_reth_db: bool,
+
+    /// Directory of an on-disk store of the L1 chain data received from the host node,
+    /// which survives restarts. Disabled if unset.
+    #[clap(long = "hera.l1-store-dir")]
+    pub l1_store_dir: Option<PathBuf>,
+
+    /// Maximum number of L1 blocks kept in the on-disk store.
+    ///
+    /// The oldest blocks are pruned first, but never the safe head's L1 origin
+    /// or newer blocks, so the store may temporarily exceed it.
+    #[clap(long = "hera.l1-store-max-blocks", default_value_t = DEFAULT_CHAIN_STORE_MAX_BLOCKS)]
+    pub l1_store_max_blocks: u64,
+
+    /// File recording the latest validated L2 block, which derivation resumes from
+    /// after a restart. Disabled if unset.
+    ///
+    /// In ExEx mode, the L1 blocks needed to resume must still be available, e.g. from
+    /// the on-disk L1 store.
+    #[clap(long = "hera.checkpoint-file")]
+    pub checkpoint_file: Option<PathBuf>,
+
+    /// Track the live L1 `SystemConfig` from its `ConfigUpdate` logs, serve it over RPC
+    /// as `optimism_systemConfig`, and cross-check the batcher address and the gas limit
+    /// of the derived payload attributes with it.
+    #[clap(long = "hera.track-system-config")]
+    pub track_system_config: bool,
+
+    /// JSON file of the interop dependency set: the chains whose messages can be executed,
+    /// with the activation time and the RPC URL of each.
+    ///
+    /// The executing messages of the derived L2 blocks are then checked against the
+    /// initiating chains, and the cross-unsafe and cross-safe heads are served in
+    /// `optimism_syncStatus`.
+    #[clap(long = "hera.interop-dependency-set")]
+    pub interop_dependency_set: Option<PathBuf>,
+
     /// The payload validation mode to use.
     ///
     /// - Trusted: rely on a trusted synced L2 execution client. Validation happens by fetching the
@@ -63,8 +272,21 @@ pub struct HeraArgsExt {
     )]
     pub validation_mode: ValidationMode,
 
+    /// Validation modes to run concurrently instead of the single validation mode,
+    /// e.g. `trusted,engine-api`. Their verdicts are combined with the validation quorum.
+    #[clap(long = "hera.validators", value_delimiter = ',')]
+    pub validators: Vec<ValidationMode>,
+
+    /// The policy combining the verdicts of multiple validators: `all` of them, `any`
+    /// of them, or a `majority` of them must find the payload valid.
+    #[clap(long = "hera.validation-quorum", default_value = "all", requires = "validators")]
+    pub validation_quorum: QuorumPolicy,
+
     /// If the mode is "engine api", we also need an URL for the engine API endpoint of
     /// the execution client to validate the payload.
+    ///
+    /// In "trusted" mode, the engine API is optionally used to fetch the bodies
+    /// of already known L2 blocks in bulk.
     #[clap(long = "hera.l2-engine-api-url")]
     pub l2_engine_api_url: Option<Url>,
 
@@ -72,6 +294,919 @@ pub struct HeraArgsExt {
     /// This MUST be a valid path to a file containing the hex-encoded JWT secret.
     #[clap(long = "hera.l2-engine-jwt-secret")]
     pub l2_engine_jwt_secret: Option<PathBuf>,
+
+    /// URL of a secondary engine API, in "engine api" mode.
+    ///
+    /// Every request is mirrored to it, and its response is used when the primary
+    /// engine API fails or is unhealthy, e.g. during execution client maintenance.
+    #[clap(long = "hera.l2-engine-api-url-secondary", requires = "l2_engine_api_url")]
+    pub l2_engine_api_url_secondary: Option<Url>,
+
+    /// Path to the JWT secret of the secondary engine API.
+    ///
+    /// Defaults to the JWT secret of the primary engine API.
+    #[clap(long = "hera.l2-engine-jwt-secret-secondary", requires = "l2_engine_api_url_secondary")]
+    pub l2_engine_jwt_secret_secondary: Option<PathBuf>,
+
+    /// Timeout in seconds of a single engine API request.
+    #[clap(long = "hera.l2-engine-timeout", default_value_t = DEFAULT_ENGINE_API_TIMEOUT.as_secs())]
+    pub l2_engine_timeout: u64,
+
+    /// Number of consecutive engine API failures after which the engine is marked
+    /// unhealthy and further requests fail fast until it recovers.
+    #[clap(long = "hera.l2-engine-max-failures", default_value_t = DEFAULT_ENGINE_MAX_FAILURES)]
+    pub l2_engine_max_failures: usize,
+
+    /// Insert the derived blocks into the L2 execution client through the engine API,
+    /// advancing its chain and forkchoice, instead of only validating them.
+    #[clap(
+        long = "hera.l2-engine-sync",
+        requires = "l2_engine_api_url",
+        requires = "l2_engine_jwt_secret"
+    )]
+    pub l2_engine_sync: bool,
+
+    /// Run as the sequencer: build a new L2 block every block time through the engine
+    /// API, and gossip it if `--p2p.sequencer.key` is set.
+    #[clap(
+        long = "sequencer.enabled",
+        requires = "l2_engine_api_url",
+        requires = "l2_engine_jwt_secret",
+        conflicts_with = "l2_engine_sync"
+    )]
+    pub sequencer_enabled: bool,
+
+    /// Number of L1 blocks a new L1 origin must be behind the L1 head to be adopted by
+    /// the sequencer, to avoid building on L1 blocks that are reorged out.
+    #[clap(long = "sequencer.l1-confs", default_value_t = DEFAULT_SEQUENCER_L1_CONFS)]
+    pub sequencer_l1_confs: u64,
+
+    /// Start the sequencer stopped, until `admin_startSequencer` is called, e.g. on the
+    /// standby instances of a highly available sequencer.
+    #[clap(long = "sequencer.stopped", requires = "sequencer_enabled")]
+    pub sequencer_stopped: bool,
+
+    /// Run the batcher: submit the L2 blocks past the safe head to the batch inbox on
+    /// L1, signed with `--batcher.private-key`.
+    #[clap(long = "batcher.enabled", requires = "batcher_private_key")]
+    pub batcher_enabled: bool,
+
+    /// Private key of the batcher account, which must match the batcher address of the
+    /// system config.
+    #[clap(long = "batcher.private-key")]
+    pub batcher_private_key: Option<B256>,
+
+    /// Whether the batcher submits the frames in `calldata`, in `blobs`, or `auto` to use
+    /// blobs after Ecotone.
+    #[clap(long = "batcher.data-availability-type", default_value = "auto")]
+    pub batcher_data_type: DaMode,
+
+    /// Max size in bytes of the calldata of a batcher transaction.
+    #[clap(long = "batcher.max-frame-size", default_value_t = DEFAULT_MAX_FRAME_SIZE)]
+    pub batcher_max_frame_size: u64,
+
+    /// Number of L1 blocks after which a channel that is not full is submitted.
+    #[clap(
+        long = "batcher.max-channel-duration",
+        default_value_t = DEFAULT_MAX_CHANNEL_DURATION
+    )]
+    pub batcher_max_channel_duration: u64,
+
+    /// Number of L1 blocks before a channel times out on L1 by which it is submitted.
+    #[clap(long = "batcher.sub-safety-margin", default_value_t = DEFAULT_SUB_SAFETY_MARGIN)]
+    pub batcher_sub_safety_margin: u64,
+
+    /// Interval in seconds at which the batcher checks for new L2 blocks.
+    #[clap(
+        long = "batcher.poll-interval",
+        default_value_t = DEFAULT_BATCHER_POLL_INTERVAL.as_secs()
+    )]
+    pub batcher_poll_interval: u64,
+
+    /// Percentage by which the fees of a batcher transaction are bumped when it is
+    /// resubmitted. Blob transactions are always bumped by at least 100%.
+    #[clap(long = "batcher.fee-bump-percent", default_value_t = DEFAULT_FEE_BUMP_PERCENT)]
+    pub batcher_fee_bump_percent: u64,
+
+    /// Time in seconds after which a batcher transaction that is not included is
+    /// resubmitted with bumped fees.
+    #[clap(
+        long = "batcher.resubmission-timeout",
+        default_value_t = DEFAULT_RESUBMISSION_TIMEOUT.as_secs()
+    )]
+    pub batcher_resubmission_timeout: u64,
+
+    /// Run the proposer: propose the output roots of the finalized L2 blocks to the
+    /// `L2OutputOracle` or `DisputeGameFactory` on L1, signed with
+    /// `--proposer.private-key`.
+    #[clap(long = "proposer.enabled", requires = "proposer_private_key")]
+    pub proposer_enabled: bool,
+
+    /// Private key of the proposer account.
+    #[clap(long = "proposer.private-key")]
+    pub proposer_private_key: Option<B256>,
+
+    /// Address of the `L2OutputOracle` on L1, to propose the outputs to.
+    #[clap(long = "proposer.l2oo-address", conflicts_with = "proposer_game_factory_address")]
+    pub proposer_l2oo_address: Option<Address>,
+
+    /// Address of the `DisputeGameFactory` on L1, to create dispute games with the outputs
+    /// as root claims.
+    #[clap(long = "proposer.game-factory-address")]
+    pub proposer_game_factory_address: Option<Address>,
+
+    /// Type of the dispute games created by the proposer.
+    #[clap(long = "proposer.game-type", default_value_t = 0)]
+    pub proposer_game_type: u32,
+
+    /// Number of L2 blocks between two dispute games created by the proposer.
+    #[clap(long = "proposer.proposal-interval", default_value_t = DEFAULT_PROPOSAL_INTERVAL)]
+    pub proposer_proposal_interval: u64,
+
+    /// Propose the outputs of the safe L2 blocks, without waiting for them to be
+    /// finalized. Only intended for test networks.
+    #[clap(long = "proposer.allow-non-finalized")]
+    pub proposer_allow_non_finalized: bool,
+
+    /// Interval in seconds at which the proposer checks for a new output to propose.
+    #[clap(
+        long = "proposer.poll-interval",
+        default_value_t = DEFAULT_PROPOSER_POLL_INTERVAL.as_secs()
+    )]
+    pub proposer_poll_interval: u64,
+
+    /// Timeout in seconds of a single trusted L2 RPC call, in trusted validation mode.
+    #[clap(long = "hera.l2-rpc-timeout", default_value_t = DEFAULT_TRUSTED_RPC_TIMEOUT.as_secs())]
+    pub l2_rpc_timeout: u64,
+
+    /// Deadline in seconds to validate a single block against the trusted L2 RPC,
+    /// across all of its calls, in trusted validation mode.
+    #[clap(
+        long = "hera.validation-deadline",
+        default_value_t = DEFAULT_VALIDATION_DEADLINE.as_secs()
+    )]
+    pub validation_deadline: u64,
+
+    /// Don't use the `debug_*` namespace of the trusted L2 RPC, in trusted validation mode.
+    ///
+    /// Raw transactions are re-encoded from hydrated blocks instead, which works with
+    /// hosted RPC providers that don't expose the `debug_*` namespace.
+    #[clap(long = "hera.l2-rpc-no-debug")]
+    pub l2_rpc_no_debug: bool,
+
+    /// Maximum number of L2 blocks of a span batch. Longer span batches are rejected,
+    /// halting derivation until the limit is raised. Unlimited if unset.
+    #[clap(long = "hera.max-span-batch-blocks", value_parser = clap::value_parser!(u64).range(1..))]
+    pub max_span_batch_blocks: Option<u64>,
+
+    /// Overrides the Delta activation timestamp of the rollup config, before which span
+    /// batches are rejected, for custom chains.
+    #[clap(long = "hera.span-batch-activation", conflicts_with = "disable_span_batches")]
+    pub span_batch_activation: Option<u64>,
+
+    /// Rejects all span batches, as if Delta was never activated.
+    #[clap(long = "hera.disable-span-batches")]
+    pub disable_span_batches: bool,
+
+    /// The maximum number of derived payloads that can be in-flight for validation
+    /// while the pipeline keeps deriving the next ones.
+    ///
+    /// A depth of 1 still overlaps the validation of a block with the derivation of
+    /// the next one, but waits for it before handing out further payloads.
+    #[clap(long = "hera.validation-depth", default_value_t = DEFAULT_VALIDATION_DEPTH)]
+    pub validation_depth: usize,
+
+    /// Only validate 1-in-N derived payloads, for chains where validation is the
+    /// sync bottleneck.
+    ///
+    /// Payloads are always validated after a failed validation, a reorg or a restart
+    /// of the pipeline. Defaults to validating every payload.
+    #[clap(
+        long = "hera.validation-sample-rate",
+        default_value_t = 1,
+        value_parser = clap::value_parser!(u64).range(1..)
+    )]
+    pub validation_sample_rate: u64,
+
+    /// The maximum number of derivation pipeline steps per second.
+    ///
+    /// Limits the RPC load on shared or rate-limited L1 infrastructure while catching up.
+    /// Unlimited if unset.
+    #[clap(long = "hera.max-steps-per-second", value_parser = clap::value_parser!(u32).range(1..))]
+    pub max_steps_per_second: Option<u32>,
+
+    /// The number of pipeline steps that can run back-to-back near the L1 tip,
+    /// when `--hera.max-steps-per-second` is set.
+    #[clap(long = "hera.step-burst", default_value_t = DEFAULT_STEP_BURST)]
+    pub step_burst: u32,
+
+    /// Number of L1 blocks whose headers, receipts and batcher blobs are fetched
+    /// concurrently ahead of the derivation pipeline origin. Disabled if 0.
+    #[clap(long = "hera.prefetch-depth", default_value_t = DEFAULT_PREFETCH_DEPTH)]
+    pub prefetch_depth: u64,
+
+    /// Path to a JSON file of settings that are reloaded on SIGHUP.
+    ///
+    /// The log level (`logLevel`), static peers (`staticPeers`) and validator endpoints
+    /// (`l2RpcUrl`, `l2EngineApiUrl`) are applied without restarting the node, on top
+    /// of the command line flags.
+    #[clap(long = "hera.config")]
+    pub config_file: Option<PathBuf>,
+
+    /// The address to serve the `optimism_*` RPC methods on, when `--hera.rpc.port` is set.
+    #[clap(long = "hera.rpc.addr", default_value_t = IpAddr::V4(Ipv4Addr::LOCALHOST))]
+    pub rpc_addr: IpAddr,
+
+    /// The port to serve the `optimism_*` RPC methods on, e.g. for the proposer
+    /// or monitoring. Not served if unset.
+    #[clap(long = "hera.rpc.port")]
+    pub rpc_port: Option<u16>,
+
+    /// TOML file listing the chains to run derivation for in one process, sharing the L1
+    /// chain tracking and the p2p discovery service.
+    ///
+    /// The settings of each chain (`chain`, `rollup-config`, `l2-rpc-url`,
+    /// `l2-engine-api-url`, `l2-engine-jwt-secret`, `unsafe-block-signer`, `rpc-port` and
+    /// `checkpoint-file`) replace the flags of the same name, and the other flags apply to
+    /// all of them. Only supported by the standalone node.
+    #[clap(
+        long = "hera.chains-file",
+        conflicts_with_all = ["chain", "l2_config_file", "sequencer_enabled", "batcher_enabled", "proposer_enabled"]
+    )]
+    pub chains_file: Option<PathBuf>,
+
+    /// The P2P networking configuration.
+    #[clap(flatten)]
+    pub p2p: P2PArgs,
+}
+
+impl HeraArgsExt {
+    /// Returns the chain ID of the configured L2 network.
+    pub fn chain_id(&self) -> Result<u64> {
+        match &self.chain {
+            Some(chain) => resolve_chain_id(chain),
+            None => Ok(self.l2_chain_id),
+        }
+    }
+
+    /// Loads the [RollupConfig] from the configured file, or from the
+    /// superchain registry for the configured L2 chain.
+    pub fn rollup_config(&self) -> Result<Arc<RollupConfig>> {
+        let mut cfg = match &self.l2_config_file {
+            Some(path) => {
+                info!("Loading l2 config from file: {:?}", path);
+                load_rollup_config(path)?
+            }
+            None => {
+                let chain_id = self.chain_id()?;
+                let Some(cfg) = ROLLUP_CONFIGS.get(&chain_id).cloned() else {
+                    eyre::bail!("Failed to find l2 config for chain ID {}", chain_id);
+                };
+                let name = chain_name(chain_id).unwrap_or_default();
+                info!(
+                    "Loading l2 config of {} (chain ID {}) from superchain registry",
+                    name, chain_id
+                );
+                if cfg.genesis.system_config.is_none() {
+                    eyre::bail!("Missing genesis system config for chain ID {}", chain_id);
+                }
+                cfg
+            }
+        };
+
+        if self.disable_span_batches {
+            info!("Span batches disabled, rejecting them regardless of Delta");
+            cfg.delta_time = None;
+        } else if let Some(time) = self.span_batch_activation {
+            info!("Overriding the span batch (Delta) activation with timestamp {}", time);
+            cfg.delta_time = Some(time);
+        }
+        Ok(Arc::new(cfg))
+    }
+
+    /// Returns the [SpanBatchConfig] of the driver.
+    pub const fn span_batch_config(&self) -> SpanBatchConfig {
+        SpanBatchConfig { max_blocks: self.max_span_batch_blocks }
+    }
+
+    /// Creates the [AttributesValidator] for the configured [ValidationMode], or the
+    /// [MultiValidator] of the configured validators, sampling the validated payloads
+    /// if a sample rate is configured.
+    ///
+    /// Disagreements between the validators are published on the given [DriverEvents].
+    pub fn validator(
+        &self,
+        cfg: &Arc<RollupConfig>,
+        events: &DriverEvents,
+    ) -> Result<Arc<dyn AttributesValidator + Send + Sync>> {
+        let validator = if self.validators.is_empty() {
+            self.mode_validator(&self.validation_mode, cfg, events)?
+        } else {
+            let mut multi = MultiValidator::new(self.validation_quorum).with_events(events.clone());
+            for mode in &self.validators {
+                multi = multi.with_validator(mode.name(), self.mode_validator(mode, cfg, events)?);
+            }
+            Arc::new(multi)
+        };
+        if self.validation_sample_rate > 1 {
+            return Ok(Arc::new(SampledValidator::new(validator, self.validation_sample_rate)));
+        }
+        Ok(validator)
+    }
+
+    /// Creates the [AttributesValidator] for the given [ValidationMode].
+    fn mode_validator(
+        &self,
+        mode: &ValidationMode,
+        cfg: &Arc<RollupConfig>,
+        events: &DriverEvents,
+    ) -> Result<Arc<dyn AttributesValidator + Send + Sync>> {
+        match mode {
+            ValidationMode::Trusted => {
+                let mut validator =
+                    TrustedValidator::new_http(self.l2_rpc_url.clone(), cfg.clone())
+                        .with_call_timeout(Duration::from_secs(self.l2_rpc_timeout))
+                        .with_deadline(Duration::from_secs(self.validation_deadline));
+                if self.l2_rpc_no_debug {
+                    validator = validator.without_debug_namespace();
+                }
+
+                // If the engine API is available, use it to fetch block bodies in bulk.
+                if let Some(engine) = self.engine_client()? {
+                    let bodies =
+                        PayloadBodiesFetcher::new(engine, DEFAULT_PAYLOAD_BODIES_BATCH_SIZE);
+                    validator = validator.with_payload_bodies(bodies);
+                }
+                Ok(Arc::new(validator))
+            }
+            ValidationMode::EngineApi => {
+                let url = self.l2_engine_api_url.clone().ok_or(eyre!("Missing engine API URL"))?;
+                let path = self.l2_engine_jwt_secret.as_ref().ok_or(eyre!("Missing JWT secret"))?;
+                let mut validator = self.engine_api_validator(url, path, cfg)?;
+                if let Some(url) = self.l2_engine_api_url_secondary.clone() {
+                    let path = self.l2_engine_jwt_secret_secondary.as_ref().unwrap_or(path);
+                    let secondary = self.engine_api_validator(url, path, cfg)?;
+                    validator = validator.with_secondary(secondary).with_events(events.clone());
+                }
+                Ok(Arc::new(validator))
+            }
+        }
+    }
+
+    /// Creates an [EngineApiValidator] for the given engine API, with its own circuit breaker.
+    fn engine_api_validator(
+        &self,
+        url: Url,
+        jwt_path: &Path,
+        cfg: &Arc<RollupConfig>,
+    ) -> Result<EngineApiValidator> {
+        let jwt = JwtSecret::from_file(jwt_path)?;
+        let breaker =
+            CircuitBreaker::new(self.l2_engine_max_failures, DEFAULT_CIRCUIT_BREAKER_COOLDOWN);
+        Ok(EngineApiValidator::new_http(url, jwt)
+            .with_rollup_config(cfg.clone())
+            .with_timeout(Duration::from_secs(self.l2_engine_timeout))
+            .with_circuit_breaker(breaker))
+    }
+
+    /// Creates the [OnlineBlobProvider] of the beacon client and blob archiver.
+    pub async fn online_blob_provider(&self) -> Result<OnlineBlobProvider> {
+        let provider = online_blob_provider(
+            self.l1_beacon_client_url.clone(),
+            self.l1_blob_archiver_url.clone(),
+        )
+        .await?;
+        Ok(if self.verify_blobs { provider.with_verification() } else { provider })
+    }
+
+    /// Opens the on-disk blob cache, if enabled.
+    pub fn blob_cache(&self) -> Result<Option<SharedDiskBlobCache>> {
+        let Some(dir) = &self.blob_cache_dir else {
+            return Ok(None);
+        };
+        let cache = DiskBlobCache::open(dir, self.blob_cache_max_size << 20)?;
+        let cache = if self.blob_cache_archive {
+            cache.archive()
+        } else {
+            cache.with_retention(Some(self.blob_cache_retention))
+        };
+        Ok(Some(cache.into_shared()))
+    }
+
+    /// Opens the on-disk L1 chain store, if enabled.
+    pub fn l1_store(&self) -> Result<Option<DiskChainProvider>> {
+        let Some(dir) = &self.l1_store_dir else {
+            return Ok(None);
+        };
+        Ok(Some(DiskChainProvider::open(dir, self.l1_store_max_blocks)?))
+    }
+
+    /// Creates the [CheckpointStore] of the derivation progress, if enabled.
+    pub fn checkpoint_store(&self, cfg: &RollupConfig) -> Option<CheckpointStore> {
+        let path = self.checkpoint_file.as_ref()?;
+        Some(CheckpointStore::new(path, cfg.l2_chain_id))
+    }
+
+    /// Returns the socket address to serve the `optimism_*` RPC methods on, if enabled.
+    pub fn rpc_socket(&self) -> Option<SocketAddr> {
+        self.rpc_port.map(|port| SocketAddr::new(self.rpc_addr, port))
+    }
+
+    /// Creates the [AltDa] fetcher of the alt-DA inputs, if enabled.
+    pub fn alt_da(&self) -> Option<AltDa> {
+        let altda = AltDa::new(DaClient::new(self.altda_server.clone()?));
+        Some(match self.altda_challenge_contract {
+            Some(contract) => altda.with_challenges(
+                contract,
+                self.altda_challenge_window,
+                self.altda_resolve_window,
+            ),
+            None => altda,
+        })
+    }
+
+    /// Returns the contract the proposer proposes the outputs to, if configured.
+    pub fn proposal_target(&self) -> Option<ProposalTarget> {
+        if let Some(address) = self.proposer_l2oo_address {
+            return Some(ProposalTarget::OutputOracle(address));
+        }
+        self.proposer_game_factory_address.map(|address| ProposalTarget::DisputeGameFactory {
+            address,
+            game_type: self.proposer_game_type,
+            interval: self.proposer_proposal_interval,
+        })
+    }
+
+    /// Creates the [StepPacer] limiting the derivation pipeline steps, if enabled.
+    pub fn step_pacer(&self) -> Option<StepPacer> {
+        self.max_steps_per_second.map(|rate| StepPacer::new(rate).with_burst(self.step_burst))
+    }
+
+    /// Returns the [UnsafeSignerWatcher] of the L1 `SystemConfig`, unless the unsafe block
+    /// signer is fixed or the rollup config has no `SystemConfig`.
+    pub fn unsafe_signer_watcher(&self, cfg: &RollupConfig) -> Option<UnsafeSignerWatcher> {
+        let system_config = cfg.l1_system_config_address;
+        (!self.p2p.fixed_unsafe_block_signer && system_config != Address::ZERO)
+            .then(|| UnsafeSignerWatcher::new_http(self.l1_rpc_url.clone(), system_config))
+    }
+
+    /// Returns the [SystemConfigTracker] of the L1 `SystemConfig`, if enabled and if the
+    /// rollup config has a `SystemConfig`.
+    pub fn system_config_tracker(&self, cfg: &RollupConfig) -> Option<SystemConfigTracker> {
+        if !self.track_system_config {
+            return None;
+        }
+        SystemConfigTracker::new_http(self.l1_rpc_url.clone(), cfg)
+    }
+
+    /// Returns the [CrossSafetyTracker] of the derived L2 blocks, if an interop
+    /// dependency set is configured.
+    pub fn cross_safety_tracker(
+        &self,
+        cfg: &Arc<RollupConfig>,
+    ) -> Result<Option<CrossSafetyTracker>> {
+        let Some(path) = &self.interop_dependency_set else {
+            return Ok(None);
+        };
+        let deps = DependencySet::load(path)?;
+        let tracker = CrossSafetyTracker::new_http(self.l2_rpc_url.clone(), cfg.clone(), deps)?;
+        Ok(Some(tracker))
+    }
+
+    /// Spawns the [Prefetcher] of the L1 data ahead of derivation, with clones of the
+    /// given providers, if enabled.
+    pub fn prefetcher<CP, BP>(
+        &self,
+        cfg: &Arc<RollupConfig>,
+        chain_provider: &CP,
+        blob_provider: &BP,
+    ) -> Option<Prefetcher>
+    where
+        CP: ChainProvider + Clone + Send + Sync + 'static,
+        BP: BlobProvider + Clone + Send + Sync + 'static,
+    {
+        (self.prefetch_depth > 0).then(|| {
+            Prefetcher::spawn(
+                cfg.clone(),
+                chain_provider.clone(),
+                blob_provider.clone(),
+                self.prefetch_depth,
+            )
+        })
+    }
+
+    /// Creates the [EngineController] inserting the derived blocks into the L2 execution
+    /// client, starting at the L2 genesis block, if engine sync is enabled.
+    pub fn engine_controller(&self, cfg: &RollupConfig) -> Result<Option<EngineController>> {
+        if !self.l2_engine_sync {
+            return Ok(None);
+        }
+        let engine = self.engine_client()?.ok_or(eyre!("Missing engine API URL or JWT secret"))?;
+        let genesis = BlockInfo {
+            hash: cfg.genesis.l2.hash,
+            number: cfg.genesis.l2.number,
+            timestamp: cfg.genesis.l2_time,
+            ..Default::default()
+        };
+        Ok(Some(EngineController::new(engine, genesis).with_ecotone_time(cfg.ecotone_time)))
+    }
+
+    /// Creates the [EngineClient] for the configured engine API, if any.
+    pub(crate) fn engine_client(&self) -> Result<Option<EngineClient>> {
+        let (Some(url), Some(path)) = (&self.l2_engine_api_url, &self.l2_engine_jwt_secret) else {
+            return Ok(None);
+        };
+        let jwt = JwtSecret::from_file(path)?;
+        let timeout = Duration::from_secs(self.l2_engine_timeout);
+        Ok(Some(EngineClient::new_http(url.clone(), jwt).with_timeout(timeout)))
+    }
+}
+
+/// The P2P networking CLI arguments.
+#[derive(Debug, Clone, Args)]
+pub struct P2PArgs {
+    /// The socket address to listen on for p2p connections, e.g. `0.0.0.0:9222`.
+    ///
+    /// P2P networking is only enabled if set.
+    #[clap(long = "p2p.listen", requires = "unsafe_block_signer")]
+    pub listen: Option<SocketAddr>,
+
+    /// The UDP port to listen on for QUIC p2p connections, on the IP address of
+    /// `--p2p.listen`. Must differ from the `--p2p.listen` port, used by discovery.
+    #[clap(long = "p2p.quic-port", requires = "listen")]
+    pub quic_port: Option<u16>,
+
+    /// Other socket addresses to listen on for p2p connections, e.g. `[::]:9222` to
+    /// accept IPv6 peers too. QUIC connections are accepted on their IP addresses too,
+    /// and discovery runs on the first IPv6 one as well. Can be repeated.
+    #[clap(long = "p2p.additional-listen", requires = "listen")]
+    pub additional_listen: Vec<SocketAddr>,
+
+    /// The public IP address advertised to peers in the local ENR, e.g. behind a NAT.
+    /// By default, the address reported by peers is advertised.
+    #[clap(long = "p2p.advertise-ip", requires = "listen")]
+    pub advertise_ip: Option<IpAddr>,
+
+    /// The public TCP and UDP port advertised to peers in the local ENR, e.g. behind a
+    /// NAT forwarding it to `--p2p.listen`. By default, the listen port is advertised.
+    #[clap(long = "p2p.advertise-port", requires = "listen")]
+    pub advertise_port: Option<u16>,
+
+    /// Detect the public address of the node with identify and AutoNAT, asking peers to
+    /// dial it back, and advertise it in the local ENR once confirmed reachable.
+    #[clap(long = "p2p.nat", requires = "listen", conflicts_with = "advertise_ip")]
+    pub nat: bool,
+
+    /// Map the p2p ports on the home router over UPnP, so that the node is dialable
+    /// without any manual port forwarding. Implies `--p2p.nat`.
+    #[clap(long = "p2p.nat.upnp", requires = "listen", conflicts_with = "advertise_ip")]
+    pub upnp: bool,
+
+    /// The address of the unsafe block signer, whose signature gossiped blocks must carry.
+    ///
+    /// It then follows the rotations of the L1 `SystemConfig`, if the rollup config has one.
+    #[clap(long = "p2p.unsafe-block-signer")]
+    pub unsafe_block_signer: Option<Address>,
+
+    /// Keep `--p2p.unsafe-block-signer` for the lifetime of the node instead of following
+    /// the signer rotations of the L1 `SystemConfig`.
+    #[clap(long = "p2p.fixed-unsafe-block-signer", requires = "unsafe_block_signer")]
+    pub fixed_unsafe_block_signer: bool,
+
+    /// BIP-39 mnemonic to deterministically derive the p2p node key from.
+    ///
+    /// If unset, the node key is loaded from `--p2p.priv-key-path`, or a random
+    /// one is generated on every start.
+    #[clap(long = "p2p.mnemonic")]
+    pub mnemonic: Option<String>,
+
+    /// BIP-32 derivation path of the p2p node key (requires `--p2p.mnemonic`).
+    ///
+    /// Defaults to `m/44'/60'/0'/0/0`.
+    #[clap(long = "p2p.derivation-path", requires = "mnemonic")]
+    pub derivation_path: Option<String>,
+
+    /// Path to the hex-encoded p2p node key, which is generated on first start if
+    /// missing. Keeps the peer ID and ENR of the node stable across restarts.
+    #[clap(long = "p2p.priv-key-path", conflicts_with = "mnemonic")]
+    pub priv_key_path: Option<PathBuf>,
+
+    /// Comma-separated list of peer multiaddrs that are always dialed on startup.
+    ///
+    /// e.g. `/ip4/10.0.0.1/tcp/9222/p2p/16Uiu2HAm...`
+    #[clap(long = "p2p.static", value_delimiter = ',')]
+    pub static_peers: Vec<Multiaddr>,
+
+    /// Disable discv5 peer discovery and only connect to the `--p2p.static` peers.
+    ///
+    /// Connections from any other peer are refused, so every static peer
+    /// must include its peer ID. Intended for private sequencer/verifier meshes.
+    #[clap(long = "p2p.no-discovery", requires = "static_peers")]
+    pub no_discovery: bool,
+
+    /// Comma-separated list of bootnode ENRs to seed discovery with, replacing the
+    /// default OP Mainnet and Base bootnodes, e.g. for devnets and private chains.
+    #[clap(long = "p2p.bootnodes", value_delimiter = ',', conflicts_with = "no_discovery")]
+    pub bootnodes: Vec<String>,
+
+    /// Comma-separated list of peer IDs allowed to connect, for private sequencer meshes.
+    ///
+    /// When set, connections to and from any other peer are denied, except for the
+    /// static peers whose address includes their peer ID.
+    #[clap(long = "p2p.allowlist", value_delimiter = ',')]
+    pub allowlist: Vec<PeerId>,
+
+    /// Maximum decompressed size in bytes of a gossiped block, on every blocks topic.
+    ///
+    /// Larger blocks are rejected before being decoded. Defaults to the spec's limit of
+    /// each topic: 10 MiB, plus the envelope fields beside the payload from `blocks/v3`.
+    #[clap(long = "p2p.max-gossip-size")]
+    pub max_gossip_size: Option<usize>,
+
+    /// Maximum decompressed size in bytes of a gossiped block on the `blocks/v1` topic,
+    /// overriding `--p2p.max-gossip-size`.
+    #[clap(long = "p2p.max-gossip-size.v1")]
+    pub max_gossip_size_v1: Option<usize>,
+
+    /// Maximum decompressed size in bytes of a gossiped block on the `blocks/v2` topic,
+    /// overriding `--p2p.max-gossip-size`.
+    #[clap(long = "p2p.max-gossip-size.v2")]
+    pub max_gossip_size_v2: Option<usize>,
+
+    /// Maximum decompressed size in bytes of a gossiped block on the `blocks/v3` topic,
+    /// overriding `--p2p.max-gossip-size`.
+    #[clap(long = "p2p.max-gossip-size.v3")]
+    pub max_gossip_size_v3: Option<usize>,
+
+    /// Maximum decompressed size in bytes of a gossiped block on the `blocks/v4` topic,
+    /// overriding `--p2p.max-gossip-size`.
+    #[clap(long = "p2p.max-gossip-size.v4")]
+    pub max_gossip_size_v4: Option<usize>,
+
+    /// The target number of peers in the gossip mesh of each topic (`D`).
+    #[clap(long = "p2p.gossip.mesh.d", default_value_t = DEFAULT_MESH_D)]
+    pub gossip_mesh_d: usize,
+
+    /// The number of mesh peers under which more are grafted (`D_lo`).
+    #[clap(long = "p2p.gossip.mesh.lo", default_value_t = DEFAULT_MESH_DLO)]
+    pub gossip_mesh_dlo: usize,
+
+    /// The number of mesh peers over which some are pruned (`D_hi`).
+    #[clap(long = "p2p.gossip.mesh.dhi", default_value_t = DEFAULT_MESH_DHI)]
+    pub gossip_mesh_dhi: usize,
+
+    /// The number of peers gossiped to outside the mesh (`D_lazy`).
+    #[clap(long = "p2p.gossip.mesh.dlazy", default_value_t = DEFAULT_MESH_DLAZY)]
+    pub gossip_mesh_dlazy: usize,
+
+    /// Publish blocks to all the peers of their topic rather than to the mesh peers only.
+    #[clap(long = "p2p.gossip.mesh.floodpublish")]
+    pub gossip_flood_publish: bool,
+
+    /// The blocks topics to subscribe to, by version, e.g. `3` for `blocks/v3` only.
+    /// Defaults to all of them.
+    #[clap(
+        long = "p2p.blocks-topics",
+        value_delimiter = ',',
+        value_parser = clap::value_parser!(u8).range(1..=4)
+    )]
+    pub blocks_topics: Vec<u8>,
+
+    /// The Isthmus activation timestamp of the chain, from which blocks are gossiped on
+    /// `blocks/v4`, since the rollup config doesn't schedule Isthmus yet.
+    #[clap(long = "p2p.isthmus-time")]
+    pub isthmus_time: Option<u64>,
+
+    /// Unsubscribe from the blocks topics of the hardforks superseded according to the
+    /// rollup config, once their last blocks are too old to be gossiped.
+    #[clap(long = "p2p.tip-only")]
+    pub tip_only: bool,
+
+    /// Maximum number of connected peers. Allowlisted peers are always accepted.
+    #[clap(long = "p2p.peers.max")]
+    pub max_peers: Option<usize>,
+
+    /// Maximum number of inbound and of outbound connections per IP address.
+    #[clap(long = "p2p.peers.max-per-ip")]
+    pub max_peers_per_ip: Option<usize>,
+
+    /// Comma-separated list of subnets whose connections are refused, in CIDR
+    /// notation, e.g. `10.0.0.0/8`.
+    #[clap(long = "p2p.ban.subnets", value_delimiter = ',')]
+    pub ban_subnets: Vec<Subnet>,
+
+    /// Seconds a connection without any active stream is kept open.
+    ///
+    /// Raise it to keep quiet connections, e.g. to static peers on low-traffic networks.
+    #[clap(long = "p2p.idle-timeout", default_value_t = DEFAULT_IDLE_CONNECTION_TIMEOUT.as_secs())]
+    pub idle_timeout: u64,
+
+    /// Seconds between keep-alive pings sent on every connection.
+    #[clap(long = "p2p.ping-interval", default_value_t = 15)]
+    pub ping_interval: u64,
+
+    /// Seconds to wait for a keep-alive ping reply before closing the connection.
+    #[clap(long = "p2p.ping-timeout", default_value_t = 20)]
+    pub ping_timeout: u64,
+
+    /// Republish every payload injected with `admin_postUnsafePayload` on its blocks
+    /// topic, unless the request says otherwise.
+    ///
+    /// Lets the node act as a gossip ingress node for a sequencer.
+    #[clap(long = "p2p.republish", requires = "listen")]
+    pub republish: bool,
+
+    /// The socket address of a trusted, unauthenticated HTTP endpoint serving
+    /// `admin_postUnsafePayload`, the `admin` sequencer controls and op-node's `opp2p`
+    /// peer management methods, e.g. `127.0.0.1:9545`.
+    ///
+    /// Only bind it to an interface reachable by the sequencer or conductor.
+    #[clap(long = "p2p.ingress", requires = "listen")]
+    pub ingress: Option<SocketAddr>,
+
+    /// Serve recent unsafe payloads to peers and request missed ones from them over
+    /// the `payload_by_number` request/response protocol.
+    #[clap(long = "p2p.sync.req-resp", requires = "listen")]
+    pub sync_req_resp: bool,
+
+    /// Score gossip peers with op-node's peer scoring parameters, pruning peers
+    /// that deliver invalid unsafe payloads from the mesh.
+    #[clap(long = "p2p.scoring")]
+    pub scoring: bool,
+
+    /// Ban peers whose score drops below `--p2p.ban.threshold`.
+    #[clap(long = "p2p.ban.peers", requires = "scoring")]
+    pub ban_peers: bool,
+
+    /// The score under which peers are banned.
+    #[clap(long = "p2p.ban.threshold", default_value_t = DEFAULT_BAN_THRESHOLD, allow_negative_numbers = true)]
+    pub ban_threshold: f64,
+
+    /// Seconds a peer stays banned for.
+    #[clap(long = "p2p.ban.duration", default_value_t = DEFAULT_BAN_DURATION.as_secs())]
+    pub ban_duration: u64,
+
+    /// Hex-encoded private key of the unsafe block signer, to sign and publish the
+    /// payloads of a sequencer. Must match `--p2p.unsafe-block-signer`.
+    #[clap(long = "p2p.sequencer.key", requires = "listen")]
+    pub sequencer_key: Option<B256>,
+}
+
+impl P2PArgs {
+    /// Returns the node [Keypair] derived from the configured mnemonic, if any.
+    pub fn keypair(&self) -> Result<Option<Keypair>> {
+        let Some(mnemonic) = &self.mnemonic else {
+            return Ok(None);
+        };
+        let path = self.derivation_path.as_deref().unwrap_or(DEFAULT_DERIVATION_PATH);
+        keypair_from_mnemonic(mnemonic, path).map(Some)
+    }
+
+    /// Builds and starts the p2p networking stack for the given L2 chain, if enabled
+    /// with `--p2p.listen`.
+    pub fn start_network(&self, cfg: &RollupConfig) -> Result<Option<NetworkHandle>> {
+        self.start_shared_network(cfg, &[])
+    }
+
+    /// Starts the p2p networking of the given chain, like [P2PArgs::start_network], also
+    /// gossiping the blocks of the given other chains, by chain ID and unsafe block signer.
+    pub fn start_shared_network(
+        &self,
+        cfg: &RollupConfig,
+        additional_chains: &[(u64, Address)],
+    ) -> Result<Option<NetworkHandle>> {
+        let Some(socket) = self.listen else {
+            return Ok(None);
+        };
+        let signer = self.unsafe_block_signer.ok_or(eyre!("Missing unsafe block signer"))?;
+
+        let mut builder = NetworkDriverBuilder::new();
+        builder
+            .with_chain_id(cfg.l2_chain_id)
+            .with_unsafe_block_signer(signer)
+            .with_socket(socket)
+            .with_fork_schedule(ForkSchedule {
+                canyon_time: cfg.canyon_time,
+                ecotone_time: cfg.ecotone_time,
+                isthmus_time: self.isthmus_time,
+            });
+        if let Some(key) = &self.sequencer_key {
+            let key = PrivateKeySigner::from_bytes(key).wrap_err("Invalid sequencer key")?;
+            builder.with_sequencer_key(key);
+        }
+        for (chain_id, signer) in additional_chains {
+            builder.with_additional_chain(*chain_id, *signer);
+        }
+        self.configure(&mut builder)?;
+        let network = builder.build()?.start()?;
+        info!("Started p2p networking with peer ID {}", network.node_info.peer_id);
+        Ok(Some(network))
+    }
+
+    /// Returns the maximum gossip size of each blocks topic, if any is set.
+    pub fn max_gossip_sizes(&self) -> Option<MaxGossipSizes> {
+        let by_version = [
+            self.max_gossip_size_v1,
+            self.max_gossip_size_v2,
+            self.max_gossip_size_v3,
+            self.max_gossip_size_v4,
+        ];
+        if self.max_gossip_size.is_none() && by_version.iter().all(Option::is_none) {
+            return None;
+        }
+        let mut sizes = self.max_gossip_size.map(MaxGossipSizes::uniform).unwrap_or_default();
+        for (version, size) in (0..).zip(by_version) {
+            if let Some(size) = size {
+                sizes.set_version(version, size);
+            }
+        }
+        Some(sizes)
+    }
+
+    /// Applies the node key, static peers, discovery and gossip settings to the given
+    /// [NetworkDriverBuilder].
+    pub fn configure(&self, builder: &mut NetworkDriverBuilder) -> Result<()> {
+        if let Some(keypair) = self.keypair()? {
+            builder.with_keypair(keypair);
+        } else if let Some(path) = &self.priv_key_path {
+            builder.with_keypair_path(path);
+        }
+        builder.with_static_peers(self.static_peers.clone());
+        if self.no_discovery {
+            builder.with_static_peers_only();
+        }
+        if !self.bootnodes.is_empty() {
+            let bootnodes = self.bootnodes.iter().map(|enr| parse_bootnode(enr));
+            builder.with_bootnodes(bootnodes.collect::<Result<_>>()?);
+        }
+        if !self.allowlist.is_empty() {
+            builder.with_allowlist(self.allowlist.iter().copied());
+        }
+        if let Some(port) = self.quic_port {
+            builder.with_quic_port(port);
+        }
+        for socket in &self.additional_listen {
+            builder.with_additional_socket(*socket);
+        }
+        if let Some(ip) = self.advertise_ip {
+            builder.with_advertised_ip(ip);
+        }
+        if let Some(port) = self.advertise_port {
+            builder.with_advertised_port(port);
+        }
+        if self.upnp {
+            builder.with_upnp();
+        } else if self.nat {
+            builder.with_nat_traversal();
+        }
+        if let Some(sizes) = self.max_gossip_sizes() {
+            builder.with_max_gossip_sizes(sizes);
+        }
+        builder.with_gossip_params(GossipParams {
+            mesh_d: self.gossip_mesh_d,
+            mesh_dlo: self.gossip_mesh_dlo,
+            mesh_dhi: self.gossip_mesh_dhi,
+            mesh_dlazy: self.gossip_mesh_dlazy,
+            flood_publish: self.gossip_flood_publish,
+            ..Default::default()
+        });
+        if !self.blocks_topics.is_empty() {
+            // Topic versions are zero-based, `blocks/v1` being version 0.
+            builder.with_block_topics(self.blocks_topics.iter().map(|version| version - 1));
+        }
+        if self.tip_only {
+            builder.with_tip_only();
+        }
+        if self.max_peers.is_some() ||
+            self.max_peers_per_ip.is_some() ||
+            !self.ban_subnets.is_empty()
+        {
+            let mut gate = ConnectionGate::new().with_banned_subnets(self.ban_subnets.clone());
+            if let Some(max) = self.max_peers {
+                gate = gate.with_max_peers(max);
+            }
+            if let Some(max) = self.max_peers_per_ip {
+                gate = gate.with_max_inbound_per_ip(max).with_max_outbound_per_ip(max);
+            }
+            builder.with_connection_gate(gate);
+        }
+        if self.sync_req_resp {
+            builder.with_sync();
+        }
+        if self.scoring {
+            builder.with_peer_scoring(PeerScoring::default());
+        }
+        if self.ban_peers {
+            builder.with_peer_bans(self.ban_threshold, Duration::from_secs(self.ban_duration));
+        }
+        builder
+            .with_idle_connection_timeout(Duration::from_secs(self.idle_timeout))
+            .with_keep_alive(
+                Duration::from_secs(self.ping_interval),
+                Duration::from_secs(self.ping_timeout),
+            );
+        Ok(())
+    }
 }
 
 /// The payload validation mode.
@@ -93,6 +1228,16 @@ pub enum ValidationMode {
     EngineApi,
 }
 
+impl ValidationMode {
+    /// Returns the name of the mode, as passed on the command line.
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Self::Trusted => "trusted",
+            Self::EngineApi => "engine-api",
+        }
+    }
+}
+
 impl std::str::FromStr for ValidationMode {
     type Err = String;
 
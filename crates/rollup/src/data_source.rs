@@ -0,0 +1,259 @@
+//! Selection of the L1 data availability source of the batcher data
+
+use std::{
+    fmt::Debug,
+    sync::{Arc, Mutex},
+};
+
+use alloy::primitives::{Address, Bytes};
+use async_trait::async_trait;
+use kona_derive::{
+    errors::{StageError, StageResult},
+    sources::{BlobSource, CalldataSource, EthereumDataSourceVariant},
+    traits::{AsyncIterator, BlobProvider, ChainProvider, DataAvailabilityProvider},
+};
+use kona_primitives::{BlockInfo, RollupConfig};
+use tracing::warn;
+
+use crate::{AltDa, ChannelTracker, SharedChannelTracker, SystemConfigHistory};
+
+/// Where the batcher data of the L1 blocks is read from.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DaMode {
+    /// Read the batcher transactions' calldata, as before Ecotone.
+    Calldata,
+    /// Read the blobs of the batcher transactions, along with their calldata.
+    Blobs,
+    /// Read the calldata before the Ecotone activation, and the blobs after it.
+    #[default]
+    Auto,
+}
+
+impl std::str::FromStr for DaMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "calldata" => Ok(DaMode::Calldata),
+            "blobs" | "blob" => Ok(DaMode::Blobs),
+            "auto" => Ok(DaMode::Auto),
+            _ => Err(format!("Invalid DA mode: {}", s)),
+        }
+    }
+}
+
+/// A [DataAvailabilityProvider] reading the batcher data of each L1 block from either
+/// its calldata or its blobs, depending on the [DaMode].
+///
+/// The calldata and blobs are read through the given L1 [ChainProvider] and
+/// [BlobProvider], e.g. an `InMemoryChainProvider` fed by the host node and a
+/// `LayeredBlobProvider`.
+///
+/// If alt-DA is enabled, the batcher data holding alt-DA commitments is replaced by
+/// the inputs fetched from the DA server.
+///
+/// The channels of the batcher data are tracked with a [ChannelTracker], to report the
+/// frames and channels dropped by the pipeline.
+#[derive(Debug, Clone)]
+pub struct HeraDataSource<CP, BP> {
+    /// The L1 chain provider, for the batcher transactions
+    chain_provider: CP,
+    /// The L1 blob provider, for the blobs of the batcher transactions
+    blob_provider: BP,
+    /// The address the batcher transactions are sent to
+    batch_inbox_address: Address,
+    /// The L1 timestamp from which blobs are read in [DaMode::Auto]
+    ecotone_timestamp: Option<u64>,
+    /// The selected DA mode
+    mode: DaMode,
+    /// The alt-DA inputs fetcher, if enabled
+    altda: Option<AltDa>,
+    /// The tracked `SystemConfig`, to cross-check the batcher address of the pipeline
+    system_config: Option<SystemConfigHistory>,
+    /// The tracker of the channels of the batcher data
+    channels: SharedChannelTracker,
+}
+
+impl<CP, BP> HeraDataSource<CP, BP> {
+    /// Creates a new [HeraDataSource] for the given rollup configuration.
+    pub fn new(chain_provider: CP, blob_provider: BP, cfg: &RollupConfig, mode: DaMode) -> Self {
+        Self {
+            chain_provider,
+            blob_provider,
+            batch_inbox_address: cfg.batch_inbox_address,
+            ecotone_timestamp: cfg.ecotone_time,
+            mode,
+            altda: None,
+            system_config: None,
+            channels: Arc::new(Mutex::new(ChannelTracker::new(cfg.channel_timeout))),
+        }
+    }
+
+    /// Resolves the alt-DA commitments of the batcher data with the given fetcher.
+    pub fn with_alt_da(mut self, altda: Option<AltDa>) -> Self {
+        self.altda = altda;
+        self
+    }
+
+    /// Cross-checks the batcher address of the pipeline with the given tracked
+    /// `SystemConfig`.
+    pub fn with_system_config(mut self, system_config: Option<SystemConfigHistory>) -> Self {
+        self.system_config = system_config;
+        self
+    }
+
+    /// Returns `true` if the batcher data of the given L1 block is read from blobs.
+    pub fn reads_blobs(&self, block: &BlockInfo) -> bool {
+        match self.mode {
+            DaMode::Calldata => false,
+            DaMode::Blobs => true,
+            DaMode::Auto => self.ecotone_timestamp.is_some_and(|time| block.timestamp >= time),
+        }
+    }
+}
+
+#[async_trait]
+impl<CP, BP> DataAvailabilityProvider for HeraDataSource<CP, BP>
+where
+    CP: ChainProvider + Send + Sync + Clone + Debug,
+    BP: BlobProvider + Send + Sync + Clone + Debug,
+{
+    type Item = Bytes;
+    type DataIter = HeraDataIter<CP, BP>;
+
+    async fn open_data(
+        &self,
+        block_ref: &BlockInfo,
+        batcher_address: Address,
+    ) -> anyhow::Result<Self::DataIter> {
+        let tracked = self.system_config.as_ref().and_then(|s| s.at(block_ref.number));
+        if let Some(tracked) = tracked.filter(|c| c.batcher_address != batcher_address) {
+            warn!(
+                "Batcher address {} of the pipeline at L1 block {} doesn't match the system \
+                 config {}",
+                batcher_address, block_ref.number, tracked.batcher_address
+            );
+            metrics::counter!("hera_system_config_mismatches_total", "field" => "batcher_address")
+                .increment(1);
+        }
+        let source = if self.reads_blobs(block_ref) {
+            EthereumDataSourceVariant::Blob(BlobSource::new(
+                self.chain_provider.clone(),
+                self.blob_provider.clone(),
+                self.batch_inbox_address,
+                *block_ref,
+                batcher_address,
+            ))
+        } else {
+            EthereumDataSourceVariant::Calldata(CalldataSource::new(
+                self.chain_provider.clone(),
+                self.batch_inbox_address,
+                *block_ref,
+                batcher_address,
+            ))
+        };
+
+        if let Some(altda) = &self.altda {
+            let receipts = match altda.challenge_contract() {
+                Some(_) => self.chain_provider.clone().receipts_by_hash(block_ref.hash).await?,
+                None => Vec::new(),
+            };
+            altda.on_l1_block(block_ref.number, &receipts);
+        }
+        self.channels.lock().expect("lock poisoned").advance(block_ref.number);
+        Ok(HeraDataIter {
+            source,
+            block: *block_ref,
+            altda: self.altda.clone(),
+            pending: None,
+            channels: self.channels.clone(),
+        })
+    }
+}
+
+/// The batcher data of an L1 block, opened by a [HeraDataSource].
+#[derive(Debug)]
+pub struct HeraDataIter<CP, BP>
+where
+    CP: ChainProvider + Send + Clone,
+    BP: BlobProvider + Send + Clone,
+{
+    /// The calldata or blob source of the L1 block
+    source: EthereumDataSourceVariant<CP, BP>,
+    /// The L1 block
+    block: BlockInfo,
+    /// The alt-DA inputs fetcher, if enabled
+    altda: Option<AltDa>,
+    /// The batcher data whose alt-DA input could not be fetched yet
+    pending: Option<Bytes>,
+    /// The tracker of the channels of the batcher data
+    channels: SharedChannelTracker,
+}
+
+impl<CP, BP> HeraDataIter<CP, BP>
+where
+    CP: ChainProvider + Send + Sync + Clone + Debug,
+    BP: BlobProvider + Send + Sync + Clone + Debug,
+{
+    /// Returns the next batcher data of the L1 block, with its alt-DA commitment
+    /// resolved.
+    async fn next_data(&mut self) -> Option<StageResult<Bytes>> {
+        let Some(altda) = &self.altda else {
+            return self.source.next().await;
+        };
+        loop {
+            let data = match self.pending.take() {
+                Some(data) => data,
+                None => match self.source.next().await? {
+                    Ok(data) => data,
+                    Err(err) => return Some(Err(err)),
+                },
+            };
+            match altda.resolve(data.clone(), self.block.number).await {
+                Ok(Some(input)) => return Some(Ok(input)),
+                Ok(None) => continue,
+                Err(err) => {
+                    // Retry the same data on the next call, until the challenge expires.
+                    warn!("Failed to fetch alt-DA input in block {}: {}", self.block.number, err);
+                    self.pending = Some(data);
+                    return Some(Err(StageError::NotEnoughData));
+                }
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl<CP, BP> AsyncIterator for HeraDataIter<CP, BP>
+where
+    CP: ChainProvider + Send + Sync + Clone + Debug,
+    BP: BlobProvider + Send + Sync + Clone + Debug,
+{
+    type Item = Bytes;
+
+    async fn next(&mut self) -> Option<StageResult<Self::Item>> {
+        let data = self.next_data().await;
+        if let Some(Ok(data)) = &data {
+            self.channels.lock().expect("lock poisoned").ingest(self.block.number, data);
+        }
+        data
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reads_blobs() {
+        let cfg = RollupConfig { ecotone_time: Some(100), ..Default::default() };
+        let block = |timestamp| BlockInfo { timestamp, ..Default::default() };
+        let source = |mode| HeraDataSource::new((), (), &cfg, mode);
+
+        assert!(!source(DaMode::Auto).reads_blobs(&block(99)));
+        assert!(source(DaMode::Auto).reads_blobs(&block(100)));
+        assert!(source(DaMode::Blobs).reads_blobs(&block(0)));
+        assert!(!source(DaMode::Calldata).reads_blobs(&block(200)));
+        assert_eq!("blobs".parse(), Ok(DaMode::Blobs));
+    }
+}
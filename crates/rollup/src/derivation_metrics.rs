@@ -0,0 +1,76 @@
+//! Metrics of the derivation pipeline health
+
+use std::time::{Duration, Instant};
+
+use kona_derive::{errors::StageError, traits::StepResult};
+use kona_primitives::{BlockInfo, L2AttributesWithParent};
+
+/// The window over which the attributes production rate is measured.
+const RATE_WINDOW: Duration = Duration::from_secs(10);
+
+/// Records the progress of the derivation pipeline, as seen by the driver stepping it.
+///
+/// Exports the L1 origin and its lag behind the L1 head, the step results, and the
+/// number and rate of the produced payload attributes and the batches they come from.
+#[derive(Debug)]
+pub(crate) struct DerivationMetrics {
+    /// The start of the current rate window
+    window_start: Instant,
+    /// The number of attributes produced in the current rate window
+    window_attributes: u64,
+}
+
+impl Default for DerivationMetrics {
+    fn default() -> Self {
+        Self { window_start: Instant::now(), window_attributes: 0 }
+    }
+}
+
+impl DerivationMetrics {
+    /// Records the result of a pipeline step.
+    pub(crate) fn record_step(&self, result: &StepResult) {
+        metrics::counter!("hera_derivation_steps_total", "result" => step_label(result))
+            .increment(1);
+    }
+
+    /// Records the current L1 origin of the pipeline, and its lag behind the L1 head.
+    pub(crate) fn record_origin(&self, origin: &BlockInfo, l1_head: Option<u64>) {
+        metrics::counter!("hera_derivation_origin_advances_total").increment(1);
+        metrics::gauge!("hera_derivation_l1_origin").set(origin.number as f64);
+        if let Some(head) = l1_head {
+            metrics::gauge!("hera_derivation_l1_origin_lag_blocks")
+                .set(head.saturating_sub(origin.number) as f64);
+        }
+    }
+
+    /// Records the given payload attributes, produced by the pipeline.
+    ///
+    /// The attributes that close a span, or the ones of a singular batch, each count
+    /// for one decoded batch.
+    pub(crate) fn record_attributes(&mut self, attributes: &L2AttributesWithParent) {
+        metrics::counter!("hera_derivation_attributes_total").increment(1);
+        if attributes.is_last_in_span {
+            metrics::counter!("hera_derivation_batches_total").increment(1);
+        }
+
+        self.window_attributes += 1;
+        let elapsed = self.window_start.elapsed();
+        if elapsed >= RATE_WINDOW {
+            metrics::gauge!("hera_derivation_attributes_per_second")
+                .set(self.window_attributes as f64 / elapsed.as_secs_f64());
+            self.window_start = Instant::now();
+            self.window_attributes = 0;
+        }
+    }
+}
+
+/// Returns the label of the result of a pipeline step, in metrics and traces.
+pub(crate) fn step_label(result: &StepResult) -> &'static str {
+    match result {
+        StepResult::PreparedAttributes => "prepared_attributes",
+        StepResult::AdvancedOrigin => "advanced_origin",
+        StepResult::OriginAdvanceErr(_) => "origin_advance_error",
+        StepResult::StepFailed(StageError::NotEnoughData) => "not_enough_data",
+        StepResult::StepFailed(_) => "failed",
+    }
+}
@@ -1,59 +1,295 @@
 //! Rollup Node Driver
 
-use std::{fmt::Debug, sync::Arc};
+use std::{
+    collections::VecDeque,
+    fmt::Debug,
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
+use alloy::{consensus::Header, primitives::B256};
 use async_trait::async_trait;
-use eyre::{bail, Result};
+use eyre::{bail, eyre, Result};
 use kona_derive::{
-    online::{AlloyChainProvider, AlloyL2ChainProvider, OnlineBlobProviderBuilder},
-    traits::{BlobProvider, ChainProvider, L2ChainProvider},
+    errors::StageError,
+    online::AlloyL2ChainProvider,
+    traits::{BlobProvider, ChainProvider, L2ChainProvider, OriginProvider, Pipeline, StepResult},
 };
-use kona_primitives::BlockInfo;
+use kona_primitives::{BlockInfo, L2AttributesWithParent, L2BlockInfo};
 use kona_providers::{
-    blob_provider::DurableBlobProvider, InMemoryChainProvider, LayeredBlobProvider,
+    DiskChainProvider, ExExChainProvider, InMemoryChainProvider, LayeredBlobProvider,
+    OnlineChainProvider, RethDbChainProvider, SharedDiskBlobCache,
 };
+use reth::providers::{BlockIdReader, BlockReader, Chain};
 use reth_exex::{ExExContext, ExExEvent, ExExNotification};
 use reth_node_api::FullNodeComponents;
 use superchain_registry::RollupConfig;
-use tokio::sync::mpsc::error::SendError;
-use tracing::{debug, info};
+use tokio::{
+    sync::{
+        mpsc::{self, error::SendError},
+        watch,
+    },
+    task::JoinHandle,
+    time::sleep,
+};
+use tracing::{debug, error, field, info, info_span, trace, warn, Instrument, Span};
+use url::Url;
 
-use crate::{new_rollup_pipeline, HeraArgsExt, RollupPipeline};
+use crate::{
+    derivation_metrics::step_label, new_rollup_pipeline, AltDa, AttributesValidator, Backoff,
+    BeaconEventTracker, CheckpointStore, DaMode, DerivationMetrics, DriverEvent, DriverEvents,
+    EngineController, Finalizer, HeraArgsExt, HeraDataSource, L1FinalizedTracker, L1HeadTracker,
+    L1Reorg, Prefetcher, ReorgWatcher, RollupPipeline, SpanBatchTracker, SpanPosition, StepPacer,
+    Supervisor, SyncStatus, SystemConfigHistory, SystemConfigValidator,
+    DEFAULT_L1_FINALIZED_POLL_INTERVAL, DEFAULT_STALL_TIMEOUT,
+};
 
+/// The context the [Driver] runs in, which notifies it of new L1 blocks.
 #[async_trait]
 pub trait DriverContext {
-    async fn recv_notification(&mut self) -> Option<ExExNotification>;
+    /// Waits for the L1 chain to advance, returning the number of the new L1 head.
+    ///
+    /// Returns `None` if no more notifications will be received.
+    async fn recv_new_l1_head(&mut self) -> Option<u64>;
 
+    /// Sends an event back to the host node.
     fn send_event(&mut self, event: ExExEvent) -> Result<(), SendError<ExExEvent>>;
+
+    /// Called when the derivation pipeline advances to a new L1 origin.
+    fn on_origin_advanced(&mut self, _origin: &BlockInfo) {}
+
+    /// Returns the L1 reorg detected since the last call, if any.
+    fn take_l1_reorg(&mut self) -> Option<L1Reorg> {
+        None
+    }
+
+    /// Returns the latest finalized L1 block number, once known.
+    fn l1_finalized(&self) -> Option<u64> {
+        None
+    }
+}
+
+/// The number of L1 blocks before the pipeline origin that are kept in memory past L1
+/// finality, since the pipeline may read them again when it is reset.
+const PIPELINE_RESET_LOOKBACK: u64 = 300;
+
+/// The number of the latest committed L1 blocks whose commit time is kept, to measure
+/// how long derivation takes to pick them up.
+const COMMITTED_AT_CAPACITY: usize = 256;
+
+/// The context of a [Driver] running as an Execution Extension.
+///
+/// Unless the L1 chain data is read from the host node's database, the ExEx
+/// notifications are received after they have been applied to the driver's
+/// [InMemoryChainProvider]. The blocks finalized on L1 by the host node are evicted
+/// from it, unless the pipeline may still read them.
+///
+/// The hashes of the committed L1 blocks are tracked by a [ReorgWatcher]: committed
+/// blocks that don't extend the tracked ones reveal a reorg, whose reverted blocks are
+/// unwound from the chain provider before the driver is notified of it.
+///
+/// If a beacon node is followed, its finalized block bounds the evicted blocks, and
+/// the reorgs of blocks it finalized are reported, as they reveal a host node that
+/// diverged from the consensus layer.
+#[derive(Debug)]
+pub struct ExExDriverContext {
+    /// The applied ExEx notifications.
+    notifications: mpsc::Receiver<ExExNotification>,
+    /// The sender of events to the host node.
+    events: mpsc::UnboundedSender<ExExEvent>,
+    /// The in-memory chain provider the notifications are applied to, unless the L1
+    /// chain data is read from the host node's database.
+    chain_provider: Option<InMemoryChainProvider>,
+    /// The latest committed L1 block.
+    tip: Option<BlockInfo>,
+    /// The hashes of the latest committed L1 blocks and when they were committed,
+    /// oldest first.
+    committed_at: VecDeque<(B256, Instant)>,
+    /// The watcher of the committed L1 block hashes.
+    reorgs: ReorgWatcher,
+    /// The L1 reorg detected since the driver last handled one.
+    pending_reorg: Option<L1Reorg>,
+    /// The L1 finalized block number of the host node, once known.
+    l1_finalized: watch::Receiver<Option<u64>>,
+    /// The L1 finalized block number of the followed beacon node, if any, once known.
+    beacon_finalized: Option<watch::Receiver<Option<u64>>>,
+    /// The supervisor of the beacon event tracker, which stops it when dropped.
+    _beacon_supervisor: Option<Supervisor>,
+    /// The latest origin of the derivation pipeline.
+    origin: Option<u64>,
+}
+
+impl ExExDriverContext {
+    /// Evicts the L1 blocks that are finalized and behind the pipeline from the
+    /// chain provider.
+    fn evict_finalized(&mut self) {
+        let (Some(mut finalized), Some(origin)) = (*self.l1_finalized.borrow(), self.origin) else {
+            return;
+        };
+        metrics::gauge!("hera_exex_l1_finalized").set(finalized as f64);
+        if let Some(beacon_finalized) = self.beacon_finalized() {
+            metrics::gauge!("hera_exex_beacon_l1_finalized").set(beacon_finalized as f64);
+            finalized = finalized.min(beacon_finalized);
+        }
+        if let Some(chain_provider) = &mut self.chain_provider {
+            chain_provider.finalize(finalized.min(origin.saturating_sub(PIPELINE_RESET_LOOKBACK)));
+        }
+    }
+
+    /// Tracks the blocks of the given committed chain, and records the reorg they
+    /// reveal, if any.
+    fn observe_committed(&mut self, chain: &Chain) {
+        let blocks: Vec<BlockInfo> = chain
+            .blocks_iter()
+            .map(|block| BlockInfo {
+                hash: block.hash(),
+                number: block.number,
+                parent_hash: block.parent_hash,
+                timestamp: block.timestamp,
+            })
+            .collect();
+        let now = Instant::now();
+        self.committed_at.extend(blocks.iter().map(|block| (block.hash, now)));
+        while self.committed_at.len() > COMMITTED_AT_CAPACITY {
+            self.committed_at.pop_front();
+        }
+        self.tip = blocks.last().copied().or(self.tip);
+        let Some(reorg) = self.reorgs.observe(&blocks) else {
+            return;
+        };
+        if let Some(finalized) = self.beacon_finalized().filter(|f| reorg.fork_number() <= *f) {
+            error!(
+                fork_number = reorg.fork_number(),
+                depth = reorg.depth(),
+                finalized,
+                "Host node reorged L1 blocks finalized by the beacon node"
+            );
+            metrics::counter!("hera_exex_finalized_l1_reorgs_total").increment(1);
+        }
+
+        let reverted: Vec<_> = reorg.reverted.iter().map(|block| block.hash).collect();
+        if let Some(chain_provider) = &mut self.chain_provider {
+            chain_provider.unwind(&reverted);
+        }
+        self.pending_reorg = Some(match self.pending_reorg.take() {
+            Some(earlier) => earlier.merge(reorg),
+            None => reorg,
+        });
+    }
+
+    /// Returns the L1 finalized block number of the followed beacon node, if known.
+    fn beacon_finalized(&self) -> Option<u64> {
+        self.beacon_finalized.as_ref().and_then(|finalized| *finalized.borrow())
+    }
 }
 
 #[async_trait]
-impl<N: FullNodeComponents> DriverContext for ExExContext<N> {
-    async fn recv_notification(&mut self) -> Option<ExExNotification> {
-        self.notifications.recv().await
+impl DriverContext for ExExDriverContext {
+    async fn recv_new_l1_head(&mut self) -> Option<u64> {
+        // Reverts are followed by a commit of the new canonical chain, so only
+        // committed chains advance the head.
+        loop {
+            let notification = self.notifications.recv().await?;
+            metrics::gauge!("hera_exex_notification_backlog").set(self.notifications.len() as f64);
+            if let Some(committed_chain) = notification.committed_chain() {
+                self.observe_committed(&committed_chain);
+                self.evict_finalized();
+                return Some(committed_chain.tip().block.header().number);
+            }
+        }
+    }
+
+    fn take_l1_reorg(&mut self) -> Option<L1Reorg> {
+        self.pending_reorg.take()
+    }
+
+    fn l1_finalized(&self) -> Option<u64> {
+        *self.l1_finalized.borrow()
     }
 
     fn send_event(&mut self, event: ExExEvent) -> Result<(), SendError<ExExEvent>> {
         self.events.send(event)
     }
+
+    /// Exports how far derivation lags behind the host node's canonical L1 tip,
+    /// both in blocks and in time since the new origin was committed.
+    fn on_origin_advanced(&mut self, origin: &BlockInfo) {
+        self.origin = Some(origin.number);
+        metrics::gauge!("hera_exex_notification_backlog").set(self.notifications.len() as f64);
+        if let Some(tip) = self.tip {
+            let lag = tip.number.saturating_sub(origin.number);
+            metrics::gauge!("hera_exex_l1_lag_blocks").set(lag as f64);
+        }
+        let committed_at = self.committed_at.iter().rev().find(|(hash, _)| *hash == origin.hash);
+        if let Some((_, committed_at)) = committed_at {
+            metrics::histogram!("hera_exex_l1_pickup_latency_seconds")
+                .record(committed_at.elapsed().as_secs_f64());
+        }
+    }
 }
 
-#[derive(Debug)]
-pub struct StandaloneContext;
+/// The context of a standalone [Driver], which tracks the L1 head and finalized block
+/// over RPC.
+///
+/// Clones share the same L1 trackers, e.g. to run the drivers of several chains.
+#[derive(Debug, Clone)]
+pub struct StandaloneContext {
+    /// The receiver of the latest L1 head number.
+    heads: watch::Receiver<u64>,
+    /// The receiver of the latest L1 finalized block number, once known.
+    finalized: watch::Receiver<Option<u64>>,
+    /// The supervisor of the L1 trackers, which stops them once every clone is dropped.
+    _supervisor: Arc<Supervisor>,
+}
+
+impl StandaloneContext {
+    /// Creates a new [StandaloneContext], tracking the L1 head of the given RPC endpoint,
+    /// and the L1 finalized block of the given HTTP endpoint.
+    ///
+    /// New heads are received via subscription for `ws://` endpoints, and by polling
+    /// the endpoint on the given interval otherwise.
+    pub fn new(head_url: Url, finalized_url: Url, poll_interval: Duration) -> Self {
+        let mut supervisor = Supervisor::default();
+        let heads = L1HeadTracker::new(head_url, poll_interval).supervise(&mut supervisor);
+        let finalized = L1FinalizedTracker::new(finalized_url, DEFAULT_L1_FINALIZED_POLL_INTERVAL)
+            .supervise(&mut supervisor);
+        Self { heads, finalized, _supervisor: Arc::new(supervisor) }
+    }
+
+    /// Creates a new [StandaloneContext], tracking the L1 head and finalized block from
+    /// the event stream of the beacon node at the given URL.
+    pub fn beacon(beacon_url: Url) -> Self {
+        let mut supervisor = Supervisor::default();
+        let (heads, finalized) = BeaconEventTracker::new(beacon_url).supervise(&mut supervisor);
+        Self { heads, finalized, _supervisor: Arc::new(supervisor) }
+    }
+
+    /// Creates the [StandaloneContext] of the given arguments: from the beacon client
+    /// events if enabled, or from the L1 websocket endpoint if one is configured, or by
+    /// polling the L1 RPC otherwise.
+    pub fn from_args(args: &HeraArgsExt) -> Self {
+        if args.l1_beacon_events {
+            return Self::beacon(args.l1_beacon_client_url.clone());
+        }
+        let poll_interval = Duration::from_secs(args.l1_poll_interval);
+        let head_url = args.l1_ws_url.clone().unwrap_or_else(|| args.l1_rpc_url.clone());
+        Self::new(head_url, args.l1_rpc_url.clone(), poll_interval)
+    }
+}
 
 #[async_trait]
 impl DriverContext for StandaloneContext {
-    async fn recv_notification(&mut self) -> Option<ExExNotification> {
-        // TODO: we will need a background task to listen for new blocks
-        // (either polling or via websocket), parse them into ExExNotifications
-        // and handle reorgs for the driver to react to.
-        todo!()
+    async fn recv_new_l1_head(&mut self) -> Option<u64> {
+        self.heads.changed().await.ok()?;
+        Some(*self.heads.borrow_and_update())
     }
 
+    fn l1_finalized(&self) -> Option<u64> {
+        *self.finalized.borrow()
+    }
+
+    /// There is no host node to report the progress of derivation to.
     fn send_event(&mut self, _event: ExExEvent) -> Result<(), SendError<ExExEvent>> {
-        // TODO: When we have a better background notifier abstraction, sending
-        // FinishedHeight events will be useful to make the pipeline advance
-        // safely through reorgs (as it is currently done for ExExContext).
         Ok(())
     }
 }
@@ -63,6 +299,10 @@ impl DriverContext for StandaloneContext {
 pub struct Driver<DC, CP, BP, L2CP> {
     /// The rollup configuration
     cfg: Arc<RollupConfig>,
+    /// Where the pipeline reads the batcher data from
+    da_mode: DaMode,
+    /// The alt-DA inputs fetcher, if enabled
+    altda: Option<AltDa>,
     /// The context of the node
     ctx: DC,
     /// The L1 chain provider
@@ -71,33 +311,332 @@ pub struct Driver<DC, CP, BP, L2CP> {
     blob_provider: BP,
     /// The L2 chain provider
     l2_chain_provider: L2CP,
+    /// The validator of the derived payload attributes
+    validator: Arc<dyn AttributesValidator + Send + Sync>,
+    /// The live `SystemConfig` tracked from the L1 logs, if enabled
+    system_config: Option<SystemConfigHistory>,
+    /// The maximum number of payload attributes that can be validated concurrently
+    /// with the derivation of the next ones
+    validation_depth: usize,
+    /// The L2 block the pipeline derives the next payload attributes on top of
+    cursor: L2BlockInfo,
+    /// Whether the chain is running in deposits-only mode, i.e. the sequencer
+    /// drift was exceeded and derived blocks only contain deposits
+    deposits_only: bool,
+    /// The rate limiter of the pipeline steps, if pacing is enabled
+    pacer: Option<StepPacer>,
+    /// The latest L1 head received from the context
+    l1_head: Option<u64>,
+    /// The on-disk blob cache, pruned up to the L1 origin of the validated blocks
+    blob_cache: Option<SharedDiskBlobCache>,
+    /// The on-disk L1 chain store, pruned up to the L1 origin of the validated blocks
+    l1_store: Option<DiskChainProvider>,
+    /// The controller inserting the derived blocks into the L2 execution client, if enabled
+    engine: Option<EngineController>,
+    /// The store of the latest validated block, which derivation resumes from on restart
+    checkpoint: Option<CheckpointStore>,
+    /// The sync status of the node, published to the RPC server
+    status: watch::Sender<SyncStatus>,
+    /// The bus the driver events are published on
+    events: DriverEvents,
+    /// How long the safe head can stay still before derivation is reported as stalled
+    stall_timeout: Duration,
+    /// When the safe head last advanced, or derivation started
+    last_progress: Instant,
+    /// Whether derivation was reported as stalled since the safe head last advanced
+    stalled: bool,
+    /// The metrics of the derivation pipeline
+    metrics: DerivationMetrics,
+    /// The tracker of the span batches the derived blocks come from
+    spans: SpanBatchTracker,
+    /// The tracker of the L2 blocks waiting for the finality of the L1 blocks they are
+    /// derived from
+    finalizer: Finalizer,
+    /// The prefetcher of the L1 data ahead of the pipeline origin, if enabled
+    prefetcher: Option<Prefetcher>,
+    /// The latest L1 block number reported to the host node as finished
+    finished_height: Option<u64>,
+}
+
+/// A payload attributes validation that is running in the background.
+#[derive(Debug)]
+struct PendingValidation {
+    /// The L2 block being validated
+    block: L2BlockInfo,
+    /// The parent of the L2 block being validated
+    parent: L2BlockInfo,
+    /// The L1 block the pipeline read up to when the L2 block was derived
+    derived_from: u64,
+    /// The position of the L2 block in its batch
+    span: SpanPosition,
+    /// The handle of the validation task
+    handle: JoinHandle<Result<bool>>,
 }
 
-impl<N> Driver<ExExContext<N>, InMemoryChainProvider, LayeredBlobProvider, AlloyL2ChainProvider>
+impl<P> Driver<ExExDriverContext, ExExChainProvider<P>, LayeredBlobProvider, AlloyL2ChainProvider>
 where
-    N: FullNodeComponents,
+    P: BlockReader + BlockIdReader + Clone + Send + Sync + 'static,
 {
     /// Create a new Hera Execution Extension Driver
-    pub fn exex(ctx: ExExContext<N>, args: HeraArgsExt, cfg: Arc<RollupConfig>) -> Self {
-        let cp = InMemoryChainProvider::with_capacity(1024);
-        let bp = LayeredBlobProvider::new(args.l1_beacon_client_url, args.l1_blob_archiver_url);
-        let l2_cp = AlloyL2ChainProvider::new_http(args.l2_rpc_url, cfg.clone());
+    ///
+    /// This connects to the L1 beacon client to load its genesis time and slot interval.
+    /// The L1 chain data is read from the database of the host node if
+    /// `--hera.l1-reth-db` is set. Otherwise, the L1 chain provider is fed from the ExEx
+    /// notifications of the host node, and backed by the on-disk L1 store if one is
+    /// configured.
+    pub async fn exex<N: FullNodeComponents<Provider = P>>(
+        ctx: ExExContext<N>,
+        args: HeraArgsExt,
+        cfg: Arc<RollupConfig>,
+    ) -> Result<Self> {
+        let events = DriverEvents::default();
+        let validator = args.validator(&cfg, &events)?;
+        let pacer = args.step_pacer();
+        let altda = args.alt_da();
+        let engine = args.engine_controller(&cfg)?;
+        let checkpoint = args.checkpoint_store(&cfg);
+        let blob_cache = args.blob_cache()?;
+        let l1_store = if args.l1_reth_db { None } else { args.l1_store()? };
+        let provider = ctx.provider().clone();
+        let l1_finalized = spawn_l1_finalized_poller(provider.clone());
+        let (beacon_finalized, beacon_supervisor) = if args.l1_beacon_events {
+            let mut supervisor = Supervisor::default();
+            let (_, finalized) = BeaconEventTracker::new(args.l1_beacon_client_url.clone())
+                .supervise(&mut supervisor);
+            (Some(finalized), Some(supervisor))
+        } else {
+            (None, None)
+        };
+        let ExExContext { notifications, events: exex_events, .. } = ctx;
+        let (cp, notifications) = if args.l1_reth_db {
+            (ExExChainProvider::RethDb(RethDbChainProvider::new(provider)), notifications)
+        } else {
+            let mut cp = InMemoryChainProvider::with_capacity(args.l1_cache_size);
+            if let Some(store) = &l1_store {
+                cp = cp.with_disk(store.clone());
+            }
+            let (cp, notifications) = cp.spawn_exex(notifications);
+            (ExExChainProvider::InMemory(cp), notifications)
+        };
+        let ctx = ExExDriverContext {
+            notifications,
+            events: exex_events,
+            chain_provider: cp.in_memory().cloned(),
+            tip: None,
+            committed_at: VecDeque::new(),
+            reorgs: ReorgWatcher::default(),
+            pending_reorg: None,
+            l1_finalized,
+            beacon_finalized,
+            _beacon_supervisor: beacon_supervisor,
+            origin: None,
+        };
+        let online = args.online_blob_provider().await?;
+        let mut bp = LayeredBlobProvider::with_online(online);
+        if let Some(cache) = &blob_cache {
+            bp = bp.with_disk_cache(cache.clone());
+        }
+        let prefetcher = args.prefetcher(&cfg, &cp, &bp);
+        let l2_cp = AlloyL2ChainProvider::new_http(args.l2_rpc_url.clone(), cfg.clone());
+        let system_config = args.system_config_tracker(&cfg).map(|t| t.spawn());
 
-        Self { cfg, ctx, chain_provider: cp, blob_provider: bp, l2_chain_provider: l2_cp }
+        let mut driver = Self::new(cfg, ctx, cp, bp, l2_cp, validator, args.validation_depth);
+        if let Some(history) = system_config {
+            driver = driver.with_system_config(history);
+        }
+        driver.pacer = pacer;
+        driver.da_mode = args.da_mode;
+        driver.altda = altda;
+        driver.blob_cache = blob_cache;
+        driver.l1_store = l1_store;
+        driver.prefetcher = prefetcher;
+        driver.engine = engine;
+        driver.checkpoint = checkpoint;
+        driver.events = events;
+        driver.spans = SpanBatchTracker::new(args.span_batch_config());
+        Ok(driver)
     }
 }
 
-impl Driver<StandaloneContext, AlloyChainProvider, DurableBlobProvider, AlloyL2ChainProvider> {
+impl Driver<StandaloneContext, OnlineChainProvider, LayeredBlobProvider, AlloyL2ChainProvider> {
     /// Create a new standalone Hera Driver
-    pub fn standalone(ctx: StandaloneContext, args: HeraArgsExt, cfg: Arc<RollupConfig>) -> Self {
-        let cp = AlloyChainProvider::new_http(args.l1_rpc_url);
-        let l2_cp = AlloyL2ChainProvider::new_http(args.l2_rpc_url, cfg.clone());
-        let bp = OnlineBlobProviderBuilder::new()
-            .with_primary(args.l1_beacon_client_url.to_string())
-            .with_fallback(args.l1_blob_archiver_url.map(|url| url.to_string()))
-            .build();
+    ///
+    /// This connects to the L1 beacon client to load its genesis time and slot interval,
+    /// and starts tracking the L1 head, from the beacon client events if enabled, or via
+    /// the L1 websocket endpoint if one is configured.
+    /// The fetched blobs are cached on disk if a blob cache is configured.
+    pub async fn standalone(args: HeraArgsExt, cfg: Arc<RollupConfig>) -> Result<Self> {
+        let ctx = StandaloneContext::from_args(&args);
+        Self::standalone_with_context(args, cfg, ctx).await
+    }
+
+    /// Create a new standalone Hera Driver following the L1 chain of the given context,
+    /// e.g. one shared with the drivers of other chains.
+    pub async fn standalone_with_context(
+        args: HeraArgsExt,
+        cfg: Arc<RollupConfig>,
+        ctx: StandaloneContext,
+    ) -> Result<Self> {
+        let events = DriverEvents::default();
+        let validator = args.validator(&cfg, &events)?;
+        let pacer = args.step_pacer();
+        let altda = args.alt_da();
+        let engine = args.engine_controller(&cfg)?;
+        let checkpoint = args.checkpoint_store(&cfg);
+        let blob_cache = args.blob_cache()?;
+        let mut bp = LayeredBlobProvider::with_online(args.online_blob_provider().await?);
+        if let Some(cache) = &blob_cache {
+            bp = bp.with_disk_cache(cache.clone());
+        }
+        let cp = OnlineChainProvider::new_http(args.l1_rpc_url.clone());
+        let l2_cp = AlloyL2ChainProvider::new_http(args.l2_rpc_url.clone(), cfg.clone());
+        let prefetcher = args.prefetcher(&cfg, &cp, &bp);
+        let system_config = args.system_config_tracker(&cfg).map(|t| t.spawn());
+
+        let mut driver = Self::new(cfg, ctx, cp, bp, l2_cp, validator, args.validation_depth);
+        if let Some(history) = system_config {
+            driver = driver.with_system_config(history);
+        }
+        driver.pacer = pacer;
+        driver.da_mode = args.da_mode;
+        driver.altda = altda;
+        driver.blob_cache = blob_cache;
+        driver.prefetcher = prefetcher;
+        driver.engine = engine;
+        driver.checkpoint = checkpoint;
+        driver.events = events;
+        driver.spans = SpanBatchTracker::new(args.span_batch_config());
+        Ok(driver)
+    }
+}
+
+impl<DC, CP, BP, L2CP> Driver<DC, CP, BP, L2CP> {
+    /// Replaces the validator of the derived payload attributes.
+    ///
+    /// If a `SystemConfig` is tracked, the attributes are checked against it first.
+    pub fn with_validator(mut self, validator: Arc<dyn AttributesValidator + Send + Sync>) -> Self {
+        self.validator = match &self.system_config {
+            Some(history) => Arc::new(SystemConfigValidator::new(validator, history.clone())),
+            None => validator,
+        };
+        self
+    }
+
+    /// Cross-checks the batcher address of the pipeline and the derived payload
+    /// attributes with the given tracked `SystemConfig`.
+    pub fn with_system_config(mut self, history: SystemConfigHistory) -> Self {
+        self.validator = Arc::new(SystemConfigValidator::new(self.validator, history.clone()));
+        self.system_config = Some(history);
+        self
+    }
+
+    /// Returns the tracked `SystemConfig`, if enabled.
+    pub fn system_config(&self) -> Option<SystemConfigHistory> {
+        self.system_config.clone()
+    }
+
+    /// Publishes the sync status of the node on the given channel, e.g. one created
+    /// before the driver to serve it over RPC.
+    pub fn with_sync_status(mut self, status: watch::Sender<SyncStatus>) -> Self {
+        status.send_replace(*self.status.borrow());
+        self.status = status;
+        self
+    }
+
+    /// Returns a receiver of the sync status of the node.
+    pub fn sync_status(&self) -> watch::Receiver<SyncStatus> {
+        self.status.subscribe()
+    }
+
+    /// Publishes the driver events on the given bus, e.g. one created before the driver
+    /// to serve them over RPC.
+    ///
+    /// The validators created with the driver publish their disagreements on it too.
+    pub fn with_events(self, events: DriverEvents) -> Self {
+        self.events.publish_to(&events);
+        self
+    }
+
+    /// Returns the bus the driver events are published on.
+    pub fn events(&self) -> DriverEvents {
+        self.events.clone()
+    }
+
+    /// Sets how long the safe head can stay still before a
+    /// [DriverEvent::DerivationStalled] is emitted, [DEFAULT_STALL_TIMEOUT] by default.
+    pub const fn with_stall_timeout(mut self, stall_timeout: Duration) -> Self {
+        self.stall_timeout = stall_timeout;
+        self
+    }
+
+    /// Create a new Hera Driver from its components, starting at the L2 genesis block.
+    fn new(
+        cfg: Arc<RollupConfig>,
+        ctx: DC,
+        chain_provider: CP,
+        blob_provider: BP,
+        l2_chain_provider: L2CP,
+        validator: Arc<dyn AttributesValidator + Send + Sync>,
+        validation_depth: usize,
+    ) -> Self {
+        let cursor = L2BlockInfo {
+            block_info: BlockInfo {
+                hash: cfg.genesis.l2.hash,
+                number: cfg.genesis.l2.number,
+                timestamp: cfg.genesis.l2_time,
+                ..Default::default()
+            },
+            l1_origin: cfg.genesis.l1,
+            seq_num: 0,
+        };
+        let (status, _) = watch::channel(SyncStatus::starting_at(cursor));
 
-        Self { cfg, ctx, chain_provider: cp, blob_provider: bp, l2_chain_provider: l2_cp }
+        Self {
+            cfg,
+            da_mode: DaMode::default(),
+            altda: None,
+            ctx,
+            chain_provider,
+            blob_provider,
+            l2_chain_provider,
+            validator,
+            system_config: None,
+            validation_depth: validation_depth.max(1),
+            cursor,
+            deposits_only: false,
+            pacer: None,
+            l1_head: None,
+            blob_cache: None,
+            l1_store: None,
+            engine: None,
+            checkpoint: None,
+            status,
+            events: DriverEvents::default(),
+            stall_timeout: DEFAULT_STALL_TIMEOUT,
+            last_progress: Instant::now(),
+            stalled: false,
+            metrics: DerivationMetrics::default(),
+            spans: SpanBatchTracker::default(),
+            finalizer: Finalizer::default(),
+            prefetcher: None,
+            finished_height: None,
+        }
+    }
+}
+
+impl<DC: DriverContext, CP, BP, L2CP> Driver<DC, CP, BP, L2CP> {
+    /// Reports the given L1 block number to the host node with an
+    /// [ExExEvent::FinishedHeight] if it is past the last reported one, so that the
+    /// host can prune its ExEx write-ahead log up to it.
+    fn report_finished_height(&mut self, height: u64) -> Result<()> {
+        if self.finished_height.is_some_and(|finished| finished >= height) {
+            return Ok(());
+        }
+        if let Err(err) = self.ctx.send_event(ExExEvent::FinishedHeight(height)) {
+            bail!("Critical: Failed to send ExEx event: {:?}", err);
+        }
+        self.finished_height = Some(height);
+        Ok(())
     }
 }
 
@@ -111,50 +650,617 @@ where
     /// Wait for the L2 genesis L1 block (aka "origin block") to be available in the L1 chain.
     async fn wait_for_l2_genesis_l1_block(&mut self) -> Result<()> {
         loop {
-            if let Some(notification) = self.ctx.recv_notification().await {
-                if let Some(committed_chain) = notification.committed_chain() {
-                    let tip = committed_chain.tip().block.header().number;
-                    // TODO: commit the chain to a local buffered provider
-                    // self.chain_provider.commit_chain(committed_chain);
-
-                    if let Err(err) = self.ctx.send_event(ExExEvent::FinishedHeight(tip)) {
-                        bail!("Critical: Failed to send ExEx event: {:?}", err);
-                    }
+            let Some(tip) = self.ctx.recv_new_l1_head().await else {
+                bail!("L1 head notifications closed before reaching the rollup genesis");
+            };
+            self.set_l1_head(tip).await;
+            // Nothing was derived yet, so there is nothing to reset on reorgs.
+            _ = self.ctx.take_l1_reorg();
+            self.report_finished_height(tip)?;
 
-                    if tip >= self.cfg.genesis.l1.number {
-                        break Ok(());
-                    } else {
-                        debug!("Chain not yet synced to rollup genesis. L1 block number: {}", tip);
-                    }
-                }
+            if tip >= self.cfg.genesis.l1.number {
+                break Ok(());
+            } else {
+                debug!("Chain not yet synced to rollup genesis. L1 block number: {}", tip);
+            }
+        }
+    }
+
+    /// Resumes derivation from the checkpointed safe head, if a checkpoint was saved.
+    fn resume_from_checkpoint(&mut self) -> Result<()> {
+        let Some(store) = &self.checkpoint else {
+            return Ok(());
+        };
+        let Some(head) = store.load()? else {
+            info!("No checkpoint found, starting from the rollup genesis");
+            return Ok(());
+        };
+        if head.block_info.number <= self.cursor.block_info.number {
+            return Ok(());
+        }
+
+        info!(
+            "Resuming derivation from checkpoint at L2 block {} (L1 origin {})",
+            head.block_info.number, head.l1_origin.number
+        );
+        self.cursor = head;
+        self.status.send_modify(|status| {
+            status.unsafe_l2 = head;
+            status.safe_l2 = head;
+        });
+        Ok(())
+    }
+
+    /// Saves the given safe head as the new checkpoint, if enabled.
+    fn save_checkpoint(&self, safe_head: &L2BlockInfo) {
+        if let Some(store) = &self.checkpoint {
+            if let Err(err) = store.save(safe_head) {
+                warn!(?err, "Failed to save the checkpoint");
             }
         }
     }
 
     /// Initialize the rollup pipeline from the driver's components.
-    fn init_pipeline(&mut self) -> RollupPipeline<CP, BP, L2CP> {
-        new_rollup_pipeline(
-            self.cfg.clone(),
+    ///
+    /// Past the rollup genesis, the pipeline starts far enough back on L1 to see every
+    /// channel that may contain the batch of the block following the cursor.
+    async fn init_pipeline(&mut self) -> Result<RollupPipeline<CP, BP, L2CP>> {
+        let origin = if self.cursor.block_info.number <= self.cfg.genesis.l2.number {
+            self.cursor.block_info
+        } else {
+            let number = self.cursor.l1_origin.number.saturating_sub(self.cfg.channel_timeout);
+            self.chain_provider
+                .block_info_by_number(number)
+                .await
+                .map_err(|e| eyre!("Failed to fetch L1 block {}: {:?}", number, e))?
+        };
+
+        let dap = HeraDataSource::new(
             self.chain_provider.clone(),
             self.blob_provider.clone(),
-            self.l2_chain_provider.clone(),
-            // TODO: use a dynamic "tip" block instead of genesis
-            BlockInfo {
-                hash: self.cfg.genesis.l2.hash,
-                number: self.cfg.genesis.l2.number,
-                ..Default::default()
-            },
+            &self.cfg,
+            self.da_mode,
         )
+        .with_alt_da(self.altda.clone())
+        .with_system_config(self.system_config.clone());
+        Ok(new_rollup_pipeline(
+            self.cfg.clone(),
+            self.chain_provider.clone(),
+            self.l2_chain_provider.clone(),
+            origin,
+            dap,
+        ))
+    }
+
+    /// Records the new L1 head, and publishes it in the sync status.
+    async fn set_l1_head(&mut self, head: u64) {
+        self.l1_head = Some(head);
+        match self.chain_provider.block_info_by_number(head).await {
+            Ok(block) => self.status.send_modify(|status| status.head_l1 = block),
+            Err(err) => debug!(?err, "Failed to fetch L1 head block {}", head),
+        }
+        self.update_finalized().await;
+    }
+
+    /// Finalizes the safe L2 blocks derived from the L1 blocks finalized since the last
+    /// call, publishing the finalized heads in the sync status, and sending them to the
+    /// execution client if an [EngineController] is configured.
+    async fn update_finalized(&mut self) {
+        let Some(l1_finalized) = self.ctx.l1_finalized() else {
+            return;
+        };
+        if self.status.borrow().finalized_l1.number != l1_finalized {
+            match self.chain_provider.block_info_by_number(l1_finalized).await {
+                Ok(block) => self.status.send_modify(|status| status.finalized_l1 = block),
+                Err(err) => debug!(?err, "Failed to fetch L1 finalized block {}", l1_finalized),
+            }
+        }
+        let Some(block) = self.finalizer.on_l1_finalized(l1_finalized) else {
+            return;
+        };
+        debug!("Finalized L2 block {} with L1 block {}", block.block_info.number, l1_finalized);
+        self.status.send_modify(|status| status.finalized_l2 = block);
+        metrics::gauge!("hera_finalized_l2_block").set(block.block_info.number as f64);
+        if let Some(engine) = &mut self.engine {
+            if let Err(err) = engine.finalize(block.block_info).await {
+                warn!(?err, "Failed to finalize L2 block {}", block.block_info.number);
+            }
+        }
+    }
+
+    /// Resets derivation after the given L1 reorg, to the latest validated L2 block whose
+    /// L1 origin is still canonical.
+    ///
+    /// The in-flight validations are cancelled, since their blocks may be derived from
+    /// reverted L1 data.
+    async fn reset_after_reorg(
+        &mut self,
+        reorg: &L1Reorg,
+        in_flight: &mut VecDeque<PendingValidation>,
+    ) -> Result<()> {
+        in_flight.drain(..).for_each(|pending| pending.handle.abort());
+
+        let fork_number = reorg.fork_number();
+        let mut target = self.status.borrow().safe_l2;
+        while target.l1_origin.number >= fork_number &&
+            target.block_info.number > self.cfg.genesis.l2.number
+        {
+            let number = target.block_info.number - 1;
+            target = self.l2_chain_provider.l2_block_info_by_number(number).await.map_err(|e| {
+                eyre!("Failed to fetch L2 block info for block {}: {:?}", number, e)
+            })?;
+        }
+
+        warn!(
+            fork_number,
+            depth = reorg.depth(),
+            old_tip = ?reorg.old_tip().map(|tip| tip.hash),
+            new_tip = %reorg.new_tip.hash,
+            new_tip_number = reorg.new_tip.number,
+            from_l2 = self.cursor.block_info.number,
+            to_l2 = target.block_info.number,
+            "L1 reorg detected, resetting derivation"
+        );
+        metrics::counter!("hera_l1_reorgs_total").increment(1);
+        metrics::histogram!("hera_l1_reorg_depth").record(reorg.depth() as f64);
+        let from_l2 = self.cursor;
+        self.events.emit(|| DriverEvent::ReorgDetected {
+            fork_number,
+            depth: reorg.depth(),
+            from_l2,
+            to_l2: target,
+        });
+
+        self.finalizer.on_l1_reorg(fork_number);
+        self.cursor = target;
+        self.status.send_modify(|status| {
+            status.unsafe_l2 = target;
+            status.safe_l2 = target;
+        });
+        self.save_checkpoint(&target);
+        Ok(())
+    }
+
+    /// Spawns the validation of the given payload attributes in the background.
+    ///
+    /// The cursor must already be advanced to the block built from the attributes.
+    fn spawn_validation(
+        &self,
+        attributes: L2AttributesWithParent,
+        span: SpanPosition,
+    ) -> PendingValidation {
+        let validator = self.validator.clone();
+        let block = self.cursor;
+        let parent = attributes.parent;
+        let derived_from = self.status.borrow().current_l1.number;
+        let span = info_span!(
+            "validate_attributes",
+            l2_block = attributes.parent.block_info.number + 1,
+            valid = field::Empty,
+        );
+        let handle = tokio::spawn(
+            async move {
+                let result = validator.validate(&attributes).await;
+                if let Ok(valid) = &result {
+                    Span::current().record("valid", valid);
+                }
+                result
+            }
+            .instrument(span),
+        );
+        PendingValidation { block, parent, derived_from, span, handle }
+    }
+
+    /// Waits for the given validation to complete and fails if the payload is invalid.
+    ///
+    /// On failure, the cursor is rewound to the parent of the block, so that derivation
+    /// restarts from the last validated block.
+    async fn finish_validation(&mut self, pending: PendingValidation) -> Result<()> {
+        let PendingValidation { block, parent, derived_from, span, handle } = pending;
+        let number = block.block_info.number;
+        let result = handle.await;
+        let valid = matches!(result, Ok(Ok(true)));
+        span.record_validation(valid);
+        if !valid {
+            self.cursor = parent;
+        }
+
+        match result.map_err(|e| eyre!("Validation task failed: {:?}", e))? {
+            Ok(true) => {
+                trace!("Validated payload attributes for block {}", number);
+                self.status.send_modify(|status| status.safe_l2 = block);
+                self.events
+                    .emit(|| DriverEvent::SafeHeadAdvanced { safe_head: block, derived_from });
+                self.last_progress = Instant::now();
+                self.stalled = false;
+                self.save_checkpoint(&block);
+                self.finalizer.on_safe(derived_from, block);
+                self.update_finalized().await;
+                self.report_finished_height(block.l1_origin.number)?;
+                if let Some(cache) = &self.blob_cache {
+                    if let Err(err) = cache.lock().set_safe_origin(parent.l1_origin.number) {
+                        warn!(?err, "Failed to prune the blob cache");
+                    }
+                }
+                if let Some(store) = &mut self.l1_store {
+                    if let Err(err) = store.set_safe_origin(parent.l1_origin.number) {
+                        warn!(?err, "Failed to prune the L1 store");
+                    }
+                }
+                Ok(())
+            }
+            Ok(false) => {
+                error!("Derived invalid payload attributes for block {}", number);
+                bail!("Invalid payload attributes for block {}", number);
+            }
+            Err(err) => {
+                error!(?err, "Failed to validate payload attributes for block {}", number);
+                bail!("Failed to validate payload attributes for block {}: {:?}", number, err);
+            }
+        }
+    }
+
+    /// Advances the cursor to the L2 block built from the given payload attributes.
+    ///
+    /// If an [EngineController] is configured, the block is first inserted into the
+    /// L2 execution client, advancing its chain.
+    async fn advance_cursor_with(&mut self, attributes: &L2AttributesWithParent) -> Result<()> {
+        let number = attributes.parent.block_info.number + 1;
+        if let Some(engine) = &mut self.engine {
+            engine
+                .insert_attributes(attributes)
+                .await
+                .map_err(|e| eyre!("Failed to insert block {}: {:?}", number, e))?;
+        }
+        self.cursor =
+            self.l2_chain_provider.l2_block_info_by_number(number).await.map_err(|e| {
+                eyre!("Failed to fetch L2 block info for block {}: {:?}", number, e)
+            })?;
+        let cursor = self.cursor;
+        self.status.send_modify(|status| status.unsafe_l2 = cursor);
+        Ok(())
+    }
+
+    /// Tracks whether the chain is running in deposits-only mode.
+    ///
+    /// When the sequencer is down for longer than the max sequencer drift, or doesn't
+    /// submit the batches of an epoch within the sequencing window, the protocol forces
+    /// the derivation of blocks that only contain deposits until batches are submitted
+    /// again. The cursor must already be advanced to the block built from the attributes,
+    /// whose L1 origin header was fetched by the pipeline, so it is read from the cache
+    /// of the chain provider.
+    async fn track_deposits_only(&mut self, attributes: &L2AttributesWithParent) -> Result<()> {
+        let forced = if is_deposits_only(attributes) {
+            let origin = self
+                .chain_provider
+                .header_by_hash(self.cursor.l1_origin.hash)
+                .await
+                .map_err(|e| eyre!("Failed to fetch L1 origin header: {:?}", e))?;
+            let derived_from = self.status.borrow().current_l1.number;
+            is_forced_deposits_only(&self.cfg, attributes, &origin, derived_from)
+        } else {
+            false
+        };
+
+        if !forced {
+            if self.deposits_only {
+                info!("Sequencer batches resumed, leaving deposits-only mode");
+                self.deposits_only = false;
+                metrics::gauge!("hera_deposits_only_mode").set(0.0);
+            }
+            return Ok(());
+        }
+        metrics::counter!("hera_deposits_only_blocks_total").increment(1);
+        if !self.deposits_only {
+            warn!(
+                "Sequencer outage detected at block {}, entering deposits-only mode",
+                attributes.parent.block_info.number + 1
+            );
+            self.deposits_only = true;
+            metrics::gauge!("hera_deposits_only_mode").set(1.0);
+        }
+        Ok(())
+    }
+
+    /// Emits a [DriverEvent::DerivationStalled] once the safe head did not advance for
+    /// longer than the stall timeout, until it advances again.
+    fn check_stalled(&mut self) {
+        let stalled_for = self.last_progress.elapsed();
+        if self.stalled || stalled_for < self.stall_timeout {
+            return;
+        }
+        let safe_head = self.status.borrow().safe_l2;
+        warn!(
+            safe_head = safe_head.block_info.number,
+            "Safe head did not advance for {:?}", stalled_for
+        );
+        metrics::counter!("hera_derivation_stalls_total").increment(1);
+        self.events.emit(|| DriverEvent::DerivationStalled { safe_head, stalled_for });
+        self.stalled = true;
     }
 
     /// Starts the Hera Execution Extension loop.
+    ///
+    /// Derivation resumes from the last checkpoint if one was saved, and runs under
+    /// supervision: if it fails, e.g. because a validation task
+    /// panicked or the payload attributes were invalid, the pipeline is restarted from
+    /// the last validated block after an exponential backoff. The loop only returns once
+    /// the L1 head notifications are closed.
     pub async fn start(mut self) -> Result<()> {
+        self.resume_from_checkpoint()?;
+
         // Step 1: Wait for the L2 origin block to be available
         self.wait_for_l2_genesis_l1_block().await?;
         info!("Chain synced to rollup genesis");
+        self.last_progress = Instant::now();
 
-        let _pipeline = self.init_pipeline();
+        // Step 2: Derive and validate payload attributes, restarting on failures
+        let mut backoff = Backoff::default();
+        loop {
+            let started = Instant::now();
+            let Err(err) = self.derive().await else {
+                info!("L1 head notifications closed, stopping derivation");
+                return Ok(());
+            };
+            metrics::counter!("hera_task_crashes_total", "task" => "derivation").increment(1);
 
-        todo!("start processing events");
+            self.check_stalled();
+            let delay = backoff.on_exit(started.elapsed());
+            error!(
+                ?err,
+                "Derivation failed, restarting from block {} in {:?}",
+                self.cursor.block_info.number,
+                delay
+            );
+            sleep(delay).await;
+        }
+    }
+
+    /// Runs the derivation pipeline from the current cursor.
+    ///
+    /// Validation is pipelined with derivation: while the attributes of block N are being
+    /// validated in the background, the pipeline keeps stepping to derive block N+1. At most
+    /// `validation_depth` validations can be in-flight at once, and results are checked in
+    /// block order.
+    ///
+    /// Returns `Ok` once the L1 head notifications are closed. On failure, the validations
+    /// still in-flight are cancelled and the cursor is rewound to the last validated block.
+    async fn derive(&mut self) -> Result<()> {
+        let mut in_flight = VecDeque::with_capacity(self.validation_depth);
+        self.spans.reset();
+        let result = self.derive_with(&mut in_flight).await;
+        if let Some(oldest) = in_flight.front() {
+            // A failed validation already rewound the cursor before the oldest pending one.
+            if oldest.parent.block_info.number < self.cursor.block_info.number {
+                self.cursor = oldest.parent;
+            }
+        }
+        in_flight.iter().for_each(|pending| pending.handle.abort());
+        if result.is_err() {
+            let cursor = self.cursor;
+            self.status.send_modify(|status| status.unsafe_l2 = cursor);
+        }
+        result
+    }
+
+    /// Steps the derivation pipeline, tracking the pending validations in `in_flight`.
+    async fn derive_with(&mut self, in_flight: &mut VecDeque<PendingValidation>) -> Result<()> {
+        let mut pipeline = self.init_pipeline().await?;
+
+        loop {
+            self.check_stalled();
+
+            // Check the results of the validations that completed in the meantime.
+            while let Some(pending) = in_flight.pop_front() {
+                if !pending.handle.is_finished() {
+                    in_flight.push_front(pending);
+                    break;
+                }
+                self.finish_validation(pending).await?;
+            }
+
+            if let Some(pacer) = &mut self.pacer {
+                let near_tip = pacer.is_near_tip(pipeline.origin().map(|o| o.number), self.l1_head);
+                pacer.pace(near_tip).await;
+            }
+
+            let span = info_span!(
+                "derivation_step",
+                l2_parent = self.cursor.block_info.number,
+                l1_origin = pipeline.origin().map(|o| o.number),
+                result = field::Empty,
+            );
+            let step = pipeline.step(self.cursor).instrument(span.clone()).await;
+            span.record("result", step_label(&step));
+            self.metrics.record_step(&step);
+            match step {
+                StepResult::PreparedAttributes => trace!("Prepared new attributes"),
+                StepResult::AdvancedOrigin => {
+                    trace!("Advanced origin");
+                    if let Some(origin) = pipeline.origin() {
+                        self.ctx.on_origin_advanced(&origin);
+                        if let Some(prefetcher) = &self.prefetcher {
+                            prefetcher.advance(&origin);
+                        }
+                        self.metrics.record_origin(&origin, self.l1_head);
+                        self.status.send_modify(|status| status.current_l1 = origin);
+                    }
+                }
+                StepResult::OriginAdvanceErr(err) => {
+                    // Usually the pipeline reached the L1 head: wait for the next block
+                    // instead of retrying in a loop.
+                    debug!("Could not advance origin, waiting for a new L1 head: {:?}", err);
+                    let Some(head) = self.ctx.recv_new_l1_head().await else {
+                        return Ok(());
+                    };
+                    trace!("New L1 head: {}", head);
+                    self.set_l1_head(head).await;
+                    if let Some(reorg) = self.ctx.take_l1_reorg() {
+                        self.reset_after_reorg(&reorg, in_flight).await?;
+                        pipeline = self.init_pipeline().await?;
+                        continue;
+                    }
+                }
+                StepResult::StepFailed(err) => match err {
+                    StageError::NotEnoughData => debug!("Not enough data to advance pipeline"),
+                    _ => error!("Error stepping derivation pipeline: {:?}", err),
+                },
+            }
+
+            let Some(attributes) = pipeline.next() else {
+                continue;
+            };
+            self.metrics.record_attributes(&attributes);
+            let span = self.spans.observe(&attributes)?;
+
+            // Apply backpressure: wait for the oldest validation before starting a new one.
+            if in_flight.len() >= self.validation_depth {
+                if let Some(pending) = in_flight.pop_front() {
+                    self.finish_validation(pending).await?;
+                }
+            }
+
+            self.advance_cursor_with(&attributes).await?;
+            self.track_deposits_only(&attributes).await?;
+            in_flight.push_back(self.spawn_validation(attributes, span));
+        }
+    }
+}
+
+/// Polls the L1 finalized block number of the host node, until the returned receiver
+/// is dropped.
+fn spawn_l1_finalized_poller<P>(provider: P) -> watch::Receiver<Option<u64>>
+where
+    P: BlockIdReader + Send + 'static,
+{
+    let (sender, recv) = watch::channel(None);
+    tokio::spawn(async move {
+        while !sender.is_closed() {
+            match provider.finalized_block_number() {
+                Ok(Some(number)) => _ = sender.send_replace(Some(number)),
+                Ok(None) => {}
+                Err(err) => warn!(?err, "Failed to read the L1 finalized block"),
+            }
+            sleep(DEFAULT_L1_FINALIZED_POLL_INTERVAL).await;
+        }
+    });
+    recv
+}
+
+/// The EIP-2718 type of deposit transactions.
+pub(crate) const DEPOSIT_TX_TYPE: u8 = 0x7E;
+
+/// Returns whether the given payload attributes only contain deposit transactions.
+fn is_deposits_only(attributes: &L2AttributesWithParent) -> bool {
+    attributes.attributes.transactions.iter().all(|tx| tx.0.first() == Some(&DEPOSIT_TX_TYPE))
+}
+
+/// Returns whether the given payload attributes, whose L1 origin is `origin`, were
+/// forced by the protocol to only contain deposits: their timestamp is past the max
+/// sequencer drift of the origin, or their batch was not submitted before the L1 block
+/// `derived_from` closed the sequencing window of the origin.
+///
+/// Blocks that are empty because nobody transacted, e.g. on a quiet chain, are not.
+fn is_forced_deposits_only(
+    cfg: &RollupConfig,
+    attributes: &L2AttributesWithParent,
+    origin: &Header,
+    derived_from: u64,
+) -> bool {
+    if !is_deposits_only(attributes) {
+        return false;
+    }
+    let drift_exceeded =
+        attributes.attributes.timestamp > origin.timestamp + cfg.max_sequencer_drift;
+    let window_expired = derived_from >= origin.number + cfg.seq_window_size;
+    drift_exceeded || window_expired
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::Bytes;
+    use kona_primitives::{L2PayloadAttributes, RawTransaction};
+
+    /// A [DriverContext] recording the events sent to the host node.
+    #[derive(Debug, Default)]
+    struct MockContext {
+        events: Vec<ExExEvent>,
+    }
+
+    #[async_trait]
+    impl DriverContext for MockContext {
+        async fn recv_new_l1_head(&mut self) -> Option<u64> {
+            None
+        }
+
+        fn send_event(&mut self, event: ExExEvent) -> Result<(), SendError<ExExEvent>> {
+            self.events.push(event);
+            Ok(())
+        }
+    }
+
+    /// A validator accepting every payload.
+    #[derive(Debug)]
+    struct AcceptAll;
+
+    #[async_trait]
+    impl AttributesValidator for AcceptAll {
+        async fn validate(&self, _: &L2AttributesWithParent) -> Result<bool> {
+            Ok(true)
+        }
+    }
+
+    #[test]
+    fn test_is_forced_deposits_only() {
+        let cfg =
+            RollupConfig { max_sequencer_drift: 600, seq_window_size: 3600, ..Default::default() };
+        let origin = Header { number: 100, timestamp: 1_000, ..Default::default() };
+        let attributes = |timestamp, transactions: &[&'static [u8]]| {
+            let attributes = L2PayloadAttributes {
+                timestamp,
+                transactions: transactions
+                    .iter()
+                    .map(|tx| RawTransaction(Bytes::from_static(tx)))
+                    .collect(),
+                ..Default::default()
+            };
+            L2AttributesWithParent::new(attributes, Default::default(), true)
+        };
+        let deposit: &[u8] = &[DEPOSIT_TX_TYPE, 1];
+
+        // An empty block within the drift and sequencing window, e.g. on a quiet chain.
+        assert!(!is_forced_deposits_only(&cfg, &attributes(1_600, &[deposit]), &origin, 3_699));
+        // The drift is exceeded one second past the max sequencer drift.
+        assert!(is_forced_deposits_only(&cfg, &attributes(1_601, &[deposit]), &origin, 101));
+        // The sequencing window of the origin expires once the L1 block at its end is read.
+        assert!(is_forced_deposits_only(&cfg, &attributes(1_002, &[deposit]), &origin, 3_700));
+        // Blocks with sequenced transactions are never forced.
+        let user_tx: &[u8] = &[0x02, 2];
+        assert!(!is_forced_deposits_only(
+            &cfg,
+            &attributes(1_601, &[deposit, user_tx]),
+            &origin,
+            3_700
+        ));
+    }
+
+    #[test]
+    fn test_report_finished_height() {
+        let cfg = Arc::new(RollupConfig::default());
+        let mut driver =
+            Driver::new(cfg, MockContext::default(), (), (), (), Arc::new(AcceptAll), 1);
+        for height in [5, 5, 4, 7, 6, 9] {
+            driver.report_finished_height(height).unwrap();
+        }
+        let heights: Vec<_> = driver
+            .ctx
+            .events
+            .iter()
+            .map(|event| match event {
+                ExExEvent::FinishedHeight(height) => *height,
+            })
+            .collect();
+        assert_eq!(heights, vec![5, 7, 9]);
     }
 }
@@ -0,0 +1,220 @@
+//! Metrics and structured logs for the frames and channels of the derivation pipeline
+//!
+//! The frames read, the channels opened and the drops are detected on the batcher data read by the
+//! pipeline, which is tracked by the [HeraDataSource](crate::HeraDataSource): the channels that are
+//! not complete within the channel timeout, the frames of channels that are already closed, and the
+//! batcher data that doesn't hold valid frames. The batches dropped by the batch queue
+//! are not reported by the pipeline, so they are not counted.
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use kona_primitives::Frame;
+use tracing::warn;
+
+/// The kind of derivation input that was dropped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DropKind {
+    /// A frame, dropped by the frame queue or the channel bank.
+    Frame,
+    /// A whole channel, dropped by the channel bank.
+    Channel,
+}
+
+impl DropKind {
+    /// Returns the metric label of the kind.
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            DropKind::Frame => "frame",
+            DropKind::Channel => "channel",
+        }
+    }
+}
+
+/// The reason a derivation input was dropped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DropReason {
+    /// The channel timed out before it was complete.
+    Timeout,
+    /// The frame arrived after its channel was closed.
+    OutOfOrder,
+    /// The batcher data doesn't hold valid frames.
+    Invalid,
+}
+
+impl DropReason {
+    /// Returns the metric label of the reason.
+    pub const fn as_str(&self) -> &'static str {
+        match self {
+            DropReason::Timeout => "timeout",
+            DropReason::OutOfOrder => "out_of_order",
+            DropReason::Invalid => "invalid",
+        }
+    }
+}
+
+/// Counts the given number of inputs dropped at the given L1 block in
+/// `hera_derivation_dropped_total{kind, reason}`, and logs them with machine-readable
+/// `kind` and `reason` fields.
+pub fn record_drop(kind: DropKind, reason: DropReason, count: usize, l1_block: u64) {
+    if count == 0 {
+        return;
+    }
+    metrics::counter!(
+        "hera_derivation_dropped_total",
+        "kind" => kind.as_str(),
+        "reason" => reason.as_str()
+    )
+    .increment(count as u64);
+    warn!(
+        target: "hera::derivation",
+        kind = kind.as_str(),
+        reason = reason.as_str(),
+        count,
+        l1_block,
+        "Dropped derivation input"
+    );
+}
+
+/// The state of a channel tracked by a [ChannelTracker].
+#[derive(Debug, Clone, Copy)]
+struct TrackedChannel {
+    /// The L1 block the first frame of the channel was read from.
+    opened_at: u64,
+    /// Whether the last frame of the channel was read.
+    closed: bool,
+}
+
+/// Tracks the channels of the batcher data read by the derivation pipeline, to count
+/// the frames and channels it reads and drops.
+///
+/// The frames are counted in `hera_derivation_frames_total`, and the channels in
+/// `hera_derivation_channels_opened_total` and `hera_derivation_channels_timed_out_total`.
+///
+/// A channel is tracked from its first frame until the channel timeout of the L1 block
+/// it was opened at, so that the frames arriving after it was closed are reported too.
+#[derive(Debug)]
+pub struct ChannelTracker {
+    /// The number of L1 blocks a channel can stay open for.
+    channel_timeout: u64,
+    /// The latest L1 block batcher data was read from.
+    l1_block: Option<u64>,
+    /// The tracked channels, by ID.
+    channels: HashMap<[u8; 16], TrackedChannel>,
+}
+
+/// A [ChannelTracker] shared by the clones of a data source and the data they open.
+pub(crate) type SharedChannelTracker = Arc<Mutex<ChannelTracker>>;
+
+impl ChannelTracker {
+    /// Creates a new [ChannelTracker] for the given channel timeout.
+    pub fn new(channel_timeout: u64) -> Self {
+        Self { channel_timeout, l1_block: None, channels: HashMap::new() }
+    }
+
+    /// Stops tracking the channels that timed out at the given L1 block, which the
+    /// batcher data is read from next. Returns the number of channels that were not
+    /// complete, which are dropped by the pipeline.
+    ///
+    /// If the L1 block is not past the previous one, the pipeline was reset and reads
+    /// the batcher data again, so all channels are forgotten.
+    pub fn advance(&mut self, l1_block: u64) -> usize {
+        if self.l1_block.is_some_and(|previous| l1_block <= previous) {
+            self.channels.clear();
+        }
+        self.l1_block = Some(l1_block);
+
+        let timeout = self.channel_timeout;
+        let mut timed_out = 0;
+        self.channels.retain(|_, channel| {
+            let live = channel.opened_at + timeout >= l1_block;
+            if !live && !channel.closed {
+                timed_out += 1;
+            }
+            live
+        });
+        metrics::counter!("hera_derivation_channels_timed_out_total").increment(timed_out as u64);
+        record_drop(DropKind::Channel, DropReason::Timeout, timed_out, l1_block);
+        timed_out
+    }
+
+    /// Tracks the frames of the given batcher data, read from the given L1 block.
+    /// Returns the number of dropped frames: the frames of closed channels, or all of
+    /// them if the data doesn't hold valid frames.
+    pub fn ingest(&mut self, l1_block: u64, data: &[u8]) -> usize {
+        let Ok(frames) = Frame::parse_frames(data) else {
+            record_drop(DropKind::Frame, DropReason::Invalid, 1, l1_block);
+            return 1;
+        };
+
+        metrics::counter!("hera_derivation_frames_total").increment(frames.len() as u64);
+        let mut dropped = 0;
+        for frame in &frames {
+            let channel = self.channels.entry(frame.id).or_insert_with(|| {
+                metrics::counter!("hera_derivation_channels_opened_total").increment(1);
+                TrackedChannel { opened_at: l1_block, closed: false }
+            });
+            if channel.closed {
+                dropped += 1;
+                continue;
+            }
+            channel.closed = frame.is_last;
+        }
+        record_drop(DropKind::Frame, DropReason::OutOfOrder, dropped, l1_block);
+        dropped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::batcher_tx_data;
+
+    fn frame(id: u8, number: u16, is_last: bool) -> Vec<u8> {
+        let frame = Frame { id: [id; 16], number, data: vec![0xaa; 4], is_last };
+        batcher_tx_data(&frame).to_vec()
+    }
+
+    #[test]
+    fn test_channel_timeout() {
+        let mut tracker = ChannelTracker::new(2);
+        tracker.advance(10);
+        assert_eq!(tracker.ingest(10, &frame(1, 0, false)), 0);
+        assert_eq!(tracker.ingest(10, &frame(2, 0, false)), 0);
+        assert_eq!(tracker.ingest(11, &frame(2, 1, true)), 0);
+
+        assert_eq!(tracker.advance(12), 0);
+        // Only the incomplete channel times out.
+        assert_eq!(tracker.advance(13), 1);
+        assert!(tracker.channels.is_empty());
+    }
+
+    #[test]
+    fn test_dropped_frames() {
+        let mut tracker = ChannelTracker::new(10);
+        tracker.advance(1);
+        assert_eq!(tracker.ingest(1, &frame(1, 0, true)), 0);
+        // The frames of a closed channel are dropped.
+        assert_eq!(tracker.ingest(1, &frame(1, 1, false)), 1);
+        assert_eq!(tracker.ingest(1, &[0x01, 0xff]), 1);
+
+        // The channels are read again after a pipeline reset.
+        tracker.advance(1);
+        assert_eq!(tracker.ingest(1, &frame(1, 0, true)), 0);
+    }
+
+    #[test]
+    fn test_multiple_frames() {
+        let mut tracker = ChannelTracker::new(10);
+        tracker.advance(1);
+        // All the frames of the batcher data are tracked.
+        let mut data = frame(1, 0, true);
+        data.extend_from_slice(&frame(2, 0, false)[1..]);
+        data.extend_from_slice(&frame(1, 1, false)[1..]);
+        assert_eq!(tracker.ingest(1, &data), 1);
+        assert_eq!(tracker.channels.len(), 2);
+        assert!(!tracker.channels[&[2; 16]].closed);
+    }
+}
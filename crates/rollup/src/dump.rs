@@ -0,0 +1,352 @@
+//! Offline derivation of a fixed L1 range, dumping the derived payload attributes
+
+use std::{fs, future::Future, path::Path, sync::Arc};
+
+use alloy::providers::{Provider, ReqwestProvider};
+use eyre::{bail, eyre, Result, WrapErr};
+use kona_derive::{
+    errors::StageError,
+    online::AlloyL2ChainProvider,
+    traits::{ChainProvider, L2ChainProvider, OriginProvider, Pipeline, StepResult},
+};
+use kona_primitives::{BlockInfo, L2BlockInfo, L2PayloadAttributes};
+use kona_providers::AlloyChainProvider;
+use serde::Serialize;
+use superchain_registry::RollupConfig;
+use tracing::{debug, info, trace};
+
+use crate::{new_rollup_pipeline, HeraArgsExt, HeraDataSource};
+
+/// The maximum number of pipeline steps without deriving attributes, past which the
+/// dump is considered stuck.
+const MAX_IDLE_STEPS: usize = 100_000;
+
+/// The payload attributes of an L2 block, as dumped to its JSON file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DumpedAttributes {
+    /// The number of the L2 block built from the attributes.
+    pub l2_block: u64,
+    /// The L1 block the pipeline read up to when the attributes were derived.
+    pub derived_from: BlockInfo,
+    /// The parent of the L2 block.
+    pub parent: L2BlockInfo,
+    /// Whether the block is the last one of its span batch.
+    pub is_last_in_span: bool,
+    /// The derived payload attributes.
+    pub attributes: L2PayloadAttributes,
+}
+
+/// Runs the derivation pipeline over the L1 blocks `from..=to`, and writes the payload
+/// attributes derived from them to `<l2_block>.json` files in the given directory.
+/// Returns the number of written files, or an error if the range is reversed or no
+/// attributes were derived from it.
+///
+/// Derivation starts from the last canonical L2 block whose L1 origin is before `from`,
+/// and the pipeline reads the L1 chain from a channel timeout earlier, like the driver
+/// does, so that channels started before the range are complete. The derived blocks are
+/// not validated: the canonical L2 chain is followed, so that a bad batch only affects
+/// its own attributes.
+pub async fn dump_derivation(
+    args: &HeraArgsExt,
+    cfg: Arc<RollupConfig>,
+    from: u64,
+    to: u64,
+    output: &Path,
+) -> Result<usize> {
+    if from > to {
+        bail!("Invalid L1 range: {} is after {}", from, to);
+    }
+    fs::create_dir_all(output).wrap_err("Failed to create the output directory")?;
+
+    let mut l2_cp = AlloyL2ChainProvider::new_http(args.l2_rpc_url.clone(), cfg.clone());
+    let latest = ReqwestProvider::new_http(args.l2_rpc_url.clone())
+        .get_block_number()
+        .await
+        .map_err(|e| eyre!("Failed to fetch the latest L2 block number: {:?}", e))?;
+    let number = partition_point(cfg.genesis.l2.number + 1, latest + 1, |number| {
+        let mut l2_cp = l2_cp.clone();
+        async move {
+            let block = l2_cp
+                .l2_block_info_by_number(number)
+                .await
+                .map_err(|e| eyre!("Failed to fetch L2 block {}: {:?}", number, e))?;
+            Ok(block.l1_origin.number < from)
+        }
+    })
+    .await?;
+    let mut cursor = l2_cp
+        .l2_block_info_by_number(number - 1)
+        .await
+        .map_err(|e| eyre!("Failed to fetch L2 block {}: {:?}", number - 1, e))?;
+
+    let mut cp = AlloyChainProvider::new_http(args.l1_rpc_url.clone());
+    let origin_number = match cursor.block_info.number <= cfg.genesis.l2.number {
+        true => cfg.genesis.l1.number,
+        false => cursor.l1_origin.number.saturating_sub(cfg.channel_timeout),
+    };
+    let origin = cp
+        .block_info_by_number(origin_number)
+        .await
+        .map_err(|e| eyre!("Failed to fetch L1 block {}: {:?}", origin_number, e))?;
+    info!(
+        "Deriving from L2 block {} and L1 origin {}, up to L1 block {}",
+        cursor.block_info.number, origin.number, to
+    );
+
+    let bp = args.online_blob_provider().await?;
+    let dap = HeraDataSource::new(cp.clone(), bp, &cfg, args.da_mode).with_alt_da(args.alt_da());
+    let mut pipeline = new_rollup_pipeline(cfg.clone(), cp, l2_cp.clone(), origin, dap);
+    let written = dump_pipeline(&mut pipeline, cursor, to, output, |number| {
+        let mut l2_cp = l2_cp.clone();
+        async move {
+            l2_cp
+                .l2_block_info_by_number(number)
+                .await
+                .map_err(|e| eyre!("Failed to fetch L2 block {}: {:?}", number, e))
+        }
+    })
+    .await?;
+    info!("Wrote the attributes of {} L2 blocks to {:?}", written, output);
+    Ok(written)
+}
+
+/// Steps the pipeline from the given L2 block until its origin moves past the L1 block
+/// `to`, writing the derived attributes to `output`. The cursor then follows the L2
+/// blocks returned by `block_by_number`, e.g. the canonical ones.
+async fn dump_pipeline<P, F, Fut>(
+    pipeline: &mut P,
+    mut cursor: L2BlockInfo,
+    to: u64,
+    output: &Path,
+    mut block_by_number: F,
+) -> Result<usize>
+where
+    P: Pipeline + Send,
+    F: FnMut(u64) -> Fut,
+    Fut: Future<Output = Result<L2BlockInfo>>,
+{
+    let (mut written, mut idle) = (0, 0);
+    loop {
+        match pipeline.step(cursor).await {
+            StepResult::PreparedAttributes => trace!("Prepared new attributes"),
+            StepResult::AdvancedOrigin => {
+                let origin = pipeline.origin().ok_or(eyre!("Pipeline has no L1 origin"))?;
+                if origin.number > to {
+                    break;
+                }
+                trace!("Advanced origin to L1 block {}", origin.number);
+            }
+            StepResult::OriginAdvanceErr(err) => {
+                bail!("Could not advance origin past {:?}: {:?}", pipeline.origin(), err)
+            }
+            StepResult::StepFailed(StageError::NotEnoughData) => {}
+            StepResult::StepFailed(err) => debug!("Pipeline step failed: {:?}", err),
+        }
+
+        let Some(derived) = pipeline.next() else {
+            idle += 1;
+            if idle > MAX_IDLE_STEPS {
+                bail!("No attributes derived after {} pipeline steps", MAX_IDLE_STEPS);
+            }
+            continue;
+        };
+        idle = 0;
+        let l2_block = derived.parent.block_info.number + 1;
+        let dumped = DumpedAttributes {
+            l2_block,
+            derived_from: pipeline.origin().ok_or(eyre!("Pipeline has no L1 origin"))?,
+            parent: derived.parent,
+            is_last_in_span: derived.is_last_in_span,
+            attributes: derived.attributes,
+        };
+        let path = output.join(format!("{}.json", l2_block));
+        fs::write(&path, serde_json::to_vec_pretty(&dumped)?)
+            .wrap_err_with(|| format!("Failed to write {:?}", path))?;
+        debug!("Wrote the attributes of L2 block {} to {:?}", l2_block, path);
+        written += 1;
+
+        cursor = block_by_number(l2_block).await?;
+    }
+    if written == 0 {
+        bail!("No attributes derived up to L1 block {}", to);
+    }
+    Ok(written)
+}
+
+/// Returns the first number of `lo..hi` for which the given predicate is false, the
+/// predicate being true then false over the range, or `hi` if it is always true.
+async fn partition_point<F, Fut>(mut lo: u64, mut hi: u64, mut pred: F) -> Result<u64>
+where
+    F: FnMut(u64) -> Fut,
+    Fut: Future<Output = Result<bool>>,
+{
+    while lo < hi {
+        let mid = lo + (hi - lo) / 2;
+        if pred(mid).await? {
+            lo = mid + 1;
+        } else {
+            hi = mid;
+        }
+    }
+    Ok(lo)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use clap::Parser;
+    use kona_primitives::L2AttributesWithParent;
+    use std::{
+        collections::{BTreeMap, VecDeque},
+        path::PathBuf,
+    };
+
+    #[derive(Parser)]
+    struct Cli {
+        #[clap(flatten)]
+        hera: HeraArgsExt,
+    }
+
+    /// A [Pipeline] deriving the given attributes from each L1 block, one per step.
+    #[derive(Debug, Default)]
+    struct MockPipeline {
+        /// The current L1 origin.
+        origin: BlockInfo,
+        /// The attributes left to derive from each L1 block.
+        batches: BTreeMap<u64, VecDeque<L2AttributesWithParent>>,
+        /// The attributes prepared by the last step.
+        prepared: Option<L2AttributesWithParent>,
+    }
+
+    impl MockPipeline {
+        /// Derives the L2 blocks following the given parent numbers from the given L1
+        /// block, the last of them ending a span batch.
+        fn with_batch(mut self, l1_block: u64, parents: &[u64]) -> Self {
+            let batch = parents.iter().enumerate().map(|(i, &number)| {
+                let parent = L2BlockInfo {
+                    block_info: BlockInfo { number, ..Default::default() },
+                    ..Default::default()
+                };
+                L2AttributesWithParent::new(Default::default(), parent, i + 1 == parents.len())
+            });
+            self.batches.entry(l1_block).or_default().extend(batch);
+            self
+        }
+    }
+
+    impl Iterator for MockPipeline {
+        type Item = L2AttributesWithParent;
+
+        fn next(&mut self) -> Option<Self::Item> {
+            self.prepared.take()
+        }
+    }
+
+    impl OriginProvider for MockPipeline {
+        fn origin(&self) -> Option<BlockInfo> {
+            Some(self.origin)
+        }
+    }
+
+    #[async_trait]
+    impl Pipeline for MockPipeline {
+        fn peek(&self) -> Option<&L2AttributesWithParent> {
+            self.prepared.as_ref()
+        }
+
+        async fn step(&mut self, _: L2BlockInfo) -> StepResult {
+            match self.batches.get_mut(&self.origin.number).and_then(VecDeque::pop_front) {
+                Some(attributes) => {
+                    self.prepared = Some(attributes);
+                    StepResult::PreparedAttributes
+                }
+                None => {
+                    self.origin.number += 1;
+                    StepResult::AdvancedOrigin
+                }
+            }
+        }
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("hera-{}-{}", name, std::process::id()));
+        _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// Dumps the attributes of the given pipeline up to the L1 block `to`.
+    async fn dump(pipeline: &mut MockPipeline, to: u64, output: &Path) -> Result<usize> {
+        let cursor = L2BlockInfo::default();
+        dump_pipeline(pipeline, cursor, to, output, |number| async move {
+            Ok(L2BlockInfo {
+                block_info: BlockInfo { number, ..Default::default() },
+                ..Default::default()
+            })
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_dump_pipeline() {
+        let dir = temp_dir("dump-range");
+        // The attributes derived past the range are not dumped.
+        let mut pipeline =
+            MockPipeline::default().with_batch(1, &[0]).with_batch(3, &[1, 2]).with_batch(5, &[3]);
+        assert_eq!(dump(&mut pipeline, 4, &dir).await.unwrap(), 3);
+        assert!(!dir.join("4.json").exists());
+
+        let json: serde_json::Value =
+            serde_json::from_slice(&fs::read(dir.join("3.json")).unwrap()).unwrap();
+        assert_eq!(json["l2_block"], 3);
+        assert_eq!(json["is_last_in_span"], true);
+        let parent = L2BlockInfo {
+            block_info: BlockInfo { number: 2, ..Default::default() },
+            ..Default::default()
+        };
+        assert_eq!(json["parent"], serde_json::to_value(parent).unwrap());
+        let derived_from = BlockInfo { number: 3, ..Default::default() };
+        assert_eq!(json["derived_from"], serde_json::to_value(derived_from).unwrap());
+        assert_eq!(
+            json["attributes"],
+            serde_json::to_value(L2PayloadAttributes::default()).unwrap()
+        );
+        let json: serde_json::Value =
+            serde_json::from_slice(&fs::read(dir.join("2.json")).unwrap()).unwrap();
+        assert_eq!(json["is_last_in_span"], false);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_dump_empty_range() {
+        let dir = temp_dir("dump-empty");
+        let mut pipeline = MockPipeline::default().with_batch(5, &[0]);
+        let err = dump(&mut pipeline, 4, &dir).await.unwrap_err();
+        assert!(err.to_string().contains("No attributes derived"));
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 0);
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_dump_reversed_range() {
+        let dir = std::env::temp_dir().join(format!("hera-dump-reversed-{}", std::process::id()));
+        let args = Cli::parse_from(["hera"]).hera;
+        let cfg = Arc::new(RollupConfig::default());
+        let err = dump_derivation(&args, cfg, 10, 9, &dir).await.unwrap_err();
+        assert!(err.to_string().contains("Invalid L1 range"));
+        assert!(!dir.exists());
+    }
+
+    #[tokio::test]
+    async fn test_partition_point() {
+        let point = |lo, hi, split| partition_point(lo, hi, move |n| async move { Ok(n < split) });
+        assert_eq!(point(1, 100, 42).await.unwrap(), 42);
+        assert_eq!(point(1, 100, 0).await.unwrap(), 1);
+        assert_eq!(point(1, 100, 200).await.unwrap(), 100);
+        assert_eq!(point(5, 5, 3).await.unwrap(), 5);
+
+        let err = partition_point(1, 10, |_| async { Err(eyre!("unavailable")) }).await;
+        assert!(err.is_err());
+    }
+}
@@ -0,0 +1,168 @@
+//! Authenticated engine API client
+
+use std::{collections::BTreeMap, time::Duration};
+
+use eyre::{bail, eyre, Result};
+use kona_primitives::RawTransaction;
+use reqwest::{
+    header::{AUTHORIZATION, CONTENT_TYPE},
+    Client, StatusCode,
+};
+use reth::rpc::types::engine::{Claims, ExecutionPayloadBodyV1, JwtSecret};
+use serde_json::Value;
+use tokio::sync::Mutex;
+use tracing::{debug, error, field, info_span, Instrument, Span};
+use url::Url;
+
+/// The default timeout of a single engine API request.
+pub const DEFAULT_ENGINE_API_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The default number of L2 block bodies fetched at once by [PayloadBodiesFetcher].
+///
+/// The engine API spec allows servers to reject ranges larger than 1024 blocks.
+pub const DEFAULT_PAYLOAD_BODIES_BATCH_SIZE: u64 = 64;
+
+/// EngineClient
+///
+/// A minimal JSON-RPC client for the authenticated engine API of an L2 execution client.
+#[derive(Debug, Clone)]
+pub struct EngineClient {
+    /// The engine API URL.
+    url: Url,
+    /// The reqwest client.
+    client: Client,
+    /// The JWT secret token for the engine API.
+    jwt_secret: JwtSecret,
+    /// The timeout of a single engine API request.
+    timeout: Duration,
+}
+
+impl EngineClient {
+    /// Creates a new [`EngineClient`] from the provided [Url] and [JwtSecret].
+    pub fn new_http(url: Url, jwt: JwtSecret) -> Self {
+        Self { url, client: Client::new(), jwt_secret: jwt, timeout: DEFAULT_ENGINE_API_TIMEOUT }
+    }
+
+    /// Sets the timeout of a single engine API request.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Sends an authenticated JSON-RPC request and returns the full response body.
+    pub async fn request(&self, method: &str, params: Value) -> Result<Value> {
+        let span = info_span!("engine_request", method, status = field::Empty);
+        self.send(method, params).instrument(span).await
+    }
+
+    /// Sends an authenticated JSON-RPC request, recording the response status in the
+    /// current span.
+    async fn send(&self, method: &str, params: Value) -> Result<Value> {
+        let request_body = serde_json::json!({
+            "id": 1,
+            "jsonrpc": "2.0",
+            "method": method,
+            "params": params
+        });
+
+        let claims = Claims::default();
+        let jwt = self.jwt_secret.encode(&claims)?;
+
+        let response = self
+            .client
+            .post(self.url.clone())
+            .timeout(self.timeout)
+            .header(CONTENT_TYPE, "application/json")
+            .header(AUTHORIZATION, format!("Bearer {}", jwt))
+            .json(&request_body)
+            .send()
+            .await?;
+
+        let status = response.status();
+        Span::current().record("status", status.as_u16());
+        let body = response.json::<Value>().await?;
+        match status {
+            StatusCode::OK => Ok(body),
+            _ => {
+                error!(?body, "Engine API returned status: {}", status);
+                bail!("Engine API returned status: {} and body: {:#?}", status, body);
+            }
+        }
+    }
+
+    /// Sends an authenticated JSON-RPC request and returns its result, failing if the
+    /// engine API returned an error.
+    pub async fn call(&self, method: &str, params: Value) -> Result<Value> {
+        let mut body = self.request(method, params).await?;
+        if let Some(err) = body.get("error") {
+            bail!("{} failed: {}", method, err);
+        }
+        body.get_mut("result").map(Value::take).ok_or(eyre!("Missing result"))
+    }
+
+    /// Fetches the bodies of `count` consecutive L2 blocks starting at `start` through
+    /// `engine_getPayloadBodiesByRangeV1`.
+    ///
+    /// Blocks unknown to the execution client are returned as `None`. The returned list
+    /// may be shorter than `count` if the range goes past the latest known block.
+    pub async fn get_payload_bodies_by_range(
+        &self,
+        start: u64,
+        count: u64,
+    ) -> Result<Vec<Option<ExecutionPayloadBodyV1>>> {
+        let params = serde_json::json!([format!("{:#x}", start), format!("{:#x}", count)]);
+        let result = self.call("engine_getPayloadBodiesByRangeV1", params).await?;
+        Ok(serde_json::from_value(result)?)
+    }
+}
+
+/// PayloadBodiesFetcher
+///
+/// Fetches the raw transactions of L2 blocks in bulk through
+/// `engine_getPayloadBodiesByRangeV1`, caching the bodies of the blocks
+/// following the requested one.
+///
+/// This is much faster than fetching every transaction separately when
+/// consolidating long ranges of already known unsafe L2 blocks.
+#[derive(Debug)]
+pub struct PayloadBodiesFetcher {
+    /// The engine API client.
+    engine: EngineClient,
+    /// The number of bodies to fetch at once.
+    batch_size: u64,
+    /// The prefetched transactions, by L2 block number.
+    cache: Mutex<BTreeMap<u64, Vec<RawTransaction>>>,
+}
+
+impl PayloadBodiesFetcher {
+    /// Creates a new [`PayloadBodiesFetcher`] fetching `batch_size` bodies at once.
+    pub fn new(engine: EngineClient, batch_size: u64) -> Self {
+        Self { engine, batch_size: batch_size.max(1), cache: Default::default() }
+    }
+
+    /// Returns the raw transactions of the given L2 block, or `None`
+    /// if the block is unknown to the execution client.
+    pub async fn transactions(&self, number: u64) -> Result<Option<Vec<RawTransaction>>> {
+        let mut cache = self.cache.lock().await;
+
+        // Blocks are consolidated in order, so older entries are never needed again.
+        *cache = cache.split_off(&number);
+        if let Some(txs) = cache.remove(&number) {
+            return Ok(Some(txs));
+        }
+
+        let bodies = self.engine.get_payload_bodies_by_range(number, self.batch_size).await?;
+        debug!("Fetched {} payload bodies starting at block {}", bodies.len(), number);
+
+        for (offset, body) in (0..).zip(bodies) {
+            let Some(body) = body else {
+                // Bodies past the first unknown block are not contiguous, don't cache them.
+                break;
+            };
+            let txs = body.transactions.into_iter().map(RawTransaction).collect();
+            cache.insert(number + offset, txs);
+        }
+
+        Ok(cache.remove(&number))
+    }
+}
@@ -0,0 +1,366 @@
+//! Forkchoice management through the engine API
+
+use alloy::primitives::{B256, U64};
+use eyre::{bail, eyre, Result};
+use kona_primitives::{BlockInfo, L2AttributesWithParent, L2PayloadAttributes};
+use serde::Deserialize;
+use serde_json::Value;
+use tracing::{debug, info};
+
+use crate::EngineClient;
+
+/// The status of a payload accepted by the execution client.
+const STATUS_VALID: &str = "VALID";
+
+/// EngineController
+///
+/// Inserts derived payload attributes into the L2 execution client through the
+/// authenticated engine API, and keeps its forkchoice in sync with the rollup node.
+///
+/// Each payload is built with `engine_forkchoiceUpdated` on top of its parent, fetched
+/// with `engine_getPayload`, imported with `engine_newPayload`, then made canonical
+/// with a last `engine_forkchoiceUpdated`. The V3 methods are used from Ecotone on,
+/// and the V2 methods before.
+#[derive(Debug)]
+pub struct EngineController {
+    /// The engine API client.
+    engine: EngineClient,
+    /// The Ecotone activation timestamp, if scheduled.
+    ecotone_time: Option<u64>,
+    /// The head of the L2 chain.
+    unsafe_head: BlockInfo,
+    /// The latest L2 block derived from L1 data.
+    safe_head: BlockInfo,
+    /// The latest L2 block derived from finalized L1 data.
+    finalized_head: BlockInfo,
+}
+
+/// The fields of an execution payload that identify its block.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PayloadHeader {
+    block_hash: B256,
+    parent_hash: B256,
+    block_number: U64,
+    timestamp: U64,
+}
+
+impl From<PayloadHeader> for BlockInfo {
+    fn from(header: PayloadHeader) -> Self {
+        Self {
+            hash: header.block_hash,
+            number: header.block_number.to(),
+            parent_hash: header.parent_hash,
+            timestamp: header.timestamp.to(),
+        }
+    }
+}
+
+impl EngineController {
+    /// Creates a new [EngineController], with all of its heads at the given block.
+    pub const fn new(engine: EngineClient, head: BlockInfo) -> Self {
+        Self {
+            engine,
+            ecotone_time: None,
+            unsafe_head: head,
+            safe_head: head,
+            finalized_head: head,
+        }
+    }
+
+    /// Sets the Ecotone activation timestamp, from which the V3 engine API methods are used.
+    pub const fn with_ecotone_time(mut self, ecotone_time: Option<u64>) -> Self {
+        self.ecotone_time = ecotone_time;
+        self
+    }
+
+    /// Returns the head of the L2 chain.
+    pub const fn unsafe_head(&self) -> BlockInfo {
+        self.unsafe_head
+    }
+
+    /// Returns the latest L2 block derived from L1 data.
+    pub const fn safe_head(&self) -> BlockInfo {
+        self.safe_head
+    }
+
+    /// Returns the latest L2 block derived from finalized L1 data.
+    pub const fn finalized_head(&self) -> BlockInfo {
+        self.finalized_head
+    }
+
+    /// Builds and imports the L2 block of the given derived payload attributes on top of
+    /// their parent, then makes it the new unsafe and safe head.
+    ///
+    /// If the parent is behind the current safe head, e.g. after the derivation pipeline
+    /// was restarted, the execution client is reorged back to it first.
+    pub async fn insert_attributes(
+        &mut self,
+        attributes: &L2AttributesWithParent,
+    ) -> Result<BlockInfo> {
+        let parent = attributes.parent.block_info;
+        if self.safe_head.number > parent.number {
+            info!(
+                "Rewinding the safe head from block {} to {}",
+                self.safe_head.number, parent.number
+            );
+            self.safe_head = parent;
+        }
+        let ecotone = self.is_ecotone(attributes.attributes.timestamp);
+
+        let payload_id = self
+            .forkchoice_updated(parent.hash, Some(&attributes.attributes), ecotone)
+            .await?
+            .ok_or(eyre!("Missing payload id for block {}", parent.number + 1))?;
+        let payload = self.get_payload(&payload_id, ecotone).await?;
+        let block: BlockInfo = serde_json::from_value::<PayloadHeader>(payload.clone())?.into();
+        if block.parent_hash != parent.hash {
+            bail!(
+                "Built block {} on {}, expected {}",
+                block.number,
+                block.parent_hash,
+                parent.hash
+            );
+        }
+
+        let root = attributes.attributes.parent_beacon_block_root;
+        self.new_payload(payload, ecotone.then_some(root.unwrap_or_default())).await?;
+
+        self.unsafe_head = block;
+        self.safe_head = block;
+        self.forkchoice_updated(block.hash, None, ecotone).await?;
+        metrics::gauge!("hera_engine_head", "head" => "unsafe").set(block.number as f64);
+        metrics::gauge!("hera_engine_head", "head" => "safe").set(block.number as f64);
+        debug!("Inserted block {} ({})", block.number, block.hash);
+        Ok(block)
+    }
+
+    /// Starts building a payload with the given attributes on top of the unsafe head,
+    /// e.g. as the sequencer, and returns the id of the payload being built.
+    pub async fn start_payload(&self, attributes: &L2PayloadAttributes) -> Result<String> {
+        let ecotone = self.is_ecotone(attributes.timestamp);
+        self.forkchoice_updated(self.unsafe_head.hash, Some(attributes), ecotone)
+            .await?
+            .ok_or(eyre!("Missing payload id for block {}", self.unsafe_head.number + 1))
+    }
+
+    /// Fetches and imports the payload being built under the given id, started with
+    /// [EngineController::start_payload], and makes it the new unsafe head.
+    ///
+    /// Returns the new block and its execution payload.
+    pub async fn seal_payload(
+        &mut self,
+        payload_id: &str,
+        attributes: &L2PayloadAttributes,
+    ) -> Result<(BlockInfo, Value)> {
+        let ecotone = self.is_ecotone(attributes.timestamp);
+        let payload = self.get_payload(payload_id, ecotone).await?;
+        let block: BlockInfo = serde_json::from_value::<PayloadHeader>(payload.clone())?.into();
+        if block.parent_hash != self.unsafe_head.hash {
+            bail!(
+                "Built block {} on {}, expected {}",
+                block.number,
+                block.parent_hash,
+                self.unsafe_head.hash
+            );
+        }
+
+        let root = attributes.parent_beacon_block_root;
+        self.new_payload(payload.clone(), ecotone.then_some(root.unwrap_or_default())).await?;
+
+        self.unsafe_head = block;
+        self.forkchoice_updated(block.hash, None, ecotone).await?;
+        metrics::gauge!("hera_engine_head", "head" => "unsafe").set(block.number as f64);
+        debug!("Sealed block {} ({})", block.number, block.hash);
+        Ok((block, payload))
+    }
+
+    /// Moves the unsafe head to the given block, e.g. when the sequencer takes over from
+    /// another one that built on top of ours. It is sent with the next forkchoice update.
+    pub fn set_unsafe_head(&mut self, head: BlockInfo) {
+        self.unsafe_head = head;
+    }
+
+    /// Moves the safe and finalized heads forward, e.g. to the blocks derived from L1 by
+    /// the rollup driver while sequencing. They are sent with the next forkchoice update.
+    pub fn update_safe_heads(&mut self, safe: BlockInfo, finalized: BlockInfo) {
+        if safe.number > self.safe_head.number && safe.number <= self.unsafe_head.number {
+            self.safe_head = safe;
+            metrics::gauge!("hera_engine_head", "head" => "safe").set(safe.number as f64);
+        }
+        if finalized.number > self.finalized_head.number &&
+            finalized.number <= self.safe_head.number
+        {
+            self.finalized_head = finalized;
+            metrics::gauge!("hera_engine_head", "head" => "finalized").set(finalized.number as f64);
+        }
+    }
+
+    /// Marks the given L2 block as finalized, and updates the forkchoice of the execution
+    /// client accordingly. Older blocks than the current finalized head are ignored.
+    pub async fn finalize(&mut self, block: BlockInfo) -> Result<()> {
+        if block.number <= self.finalized_head.number {
+            return Ok(());
+        }
+        self.finalized_head = block;
+        let ecotone = self.is_ecotone(self.unsafe_head.timestamp);
+        self.forkchoice_updated(self.unsafe_head.hash, None, ecotone).await?;
+        metrics::gauge!("hera_engine_head", "head" => "finalized").set(block.number as f64);
+        Ok(())
+    }
+
+    /// Returns whether the block with the given timestamp is past the Ecotone activation.
+    fn is_ecotone(&self, timestamp: u64) -> bool {
+        self.ecotone_time.map_or(false, |time| timestamp >= time)
+    }
+
+    /// Returns the forkchoice state with the given head, and the current safe and
+    /// finalized heads.
+    fn forkchoice_state(&self, head: B256) -> Value {
+        serde_json::json!({
+            "headBlockHash": head,
+            "safeBlockHash": self.safe_head.hash,
+            "finalizedBlockHash": self.finalized_head.hash,
+        })
+    }
+
+    /// Sends `engine_forkchoiceUpdated` with the given head, starting to build a payload
+    /// on top of it if attributes are given, and returns the id of the payload being built.
+    async fn forkchoice_updated(
+        &self,
+        head: B256,
+        attributes: Option<&L2PayloadAttributes>,
+        ecotone: bool,
+    ) -> Result<Option<String>> {
+        let method =
+            if ecotone { "engine_forkchoiceUpdatedV3" } else { "engine_forkchoiceUpdatedV2" };
+        let params = serde_json::json!([self.forkchoice_state(head), attributes]);
+        let result = self.engine.call(method, params).await?;
+        check_status(method, &result["payloadStatus"])?;
+        Ok(result["payloadId"].as_str().map(String::from))
+    }
+
+    /// Fetches the execution payload built under the given payload id.
+    async fn get_payload(&self, payload_id: &str, ecotone: bool) -> Result<Value> {
+        let method = if ecotone { "engine_getPayloadV3" } else { "engine_getPayloadV2" };
+        let mut result = self.engine.call(method, serde_json::json!([payload_id])).await?;
+        result.get_mut("executionPayload").map(Value::take).ok_or(eyre!("Missing payload"))
+    }
+
+    /// Imports the given execution payload, with the parent beacon block root from Ecotone on.
+    async fn new_payload(
+        &self,
+        payload: Value,
+        parent_beacon_block_root: Option<B256>,
+    ) -> Result<()> {
+        let (method, params) = new_payload_params(payload, parent_beacon_block_root);
+        let result = self.engine.call(method, params).await?;
+        check_status(method, &result)
+    }
+}
+
+/// Returns the method and parameters of the `engine_newPayload` request of the given payload.
+pub(crate) fn new_payload_params(
+    payload: Value,
+    parent_beacon_block_root: Option<B256>,
+) -> (&'static str, Value) {
+    match parent_beacon_block_root {
+        // L2 blocks have no blobs, so there are no versioned hashes to check.
+        Some(root) => ("engine_newPayloadV3", serde_json::json!([payload, [], root])),
+        None => ("engine_newPayloadV2", serde_json::json!([payload])),
+    }
+}
+
+/// Fails unless the given payload status is valid.
+fn check_status(method: &str, status: &Value) -> Result<()> {
+    match status["status"].as_str() {
+        Some(STATUS_VALID) => Ok(()),
+        Some(other) => {
+            metrics::counter!("hera_engine_invalid_payloads_total", "method" => method.to_string())
+                .increment(1);
+            bail!("{} returned status {}: {}", method, other, status["validationError"])
+        }
+        None => bail!("{} returned no payload status", method),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reth::rpc::types::engine::JwtSecret;
+
+    fn controller() -> EngineController {
+        let engine = EngineClient::new_http(
+            "http://localhost:8551".parse().unwrap(),
+            JwtSecret::from_hex("00".repeat(32)).unwrap(),
+        );
+        let genesis = BlockInfo { hash: B256::repeat_byte(1), ..Default::default() };
+        EngineController::new(engine, genesis).with_ecotone_time(Some(100))
+    }
+
+    #[test]
+    fn test_is_ecotone() {
+        let controller = controller();
+        assert!(!controller.is_ecotone(99));
+        assert!(controller.is_ecotone(100));
+        assert!(!controller.with_ecotone_time(None).is_ecotone(100));
+    }
+
+    #[test]
+    fn test_forkchoice_state() {
+        let state = controller().forkchoice_state(B256::repeat_byte(2));
+        assert_eq!(state["headBlockHash"], serde_json::json!(B256::repeat_byte(2)));
+        assert_eq!(state["safeBlockHash"], serde_json::json!(B256::repeat_byte(1)));
+        assert_eq!(state["finalizedBlockHash"], serde_json::json!(B256::repeat_byte(1)));
+    }
+
+    #[test]
+    fn test_update_safe_heads() {
+        let block = |number| BlockInfo { number, ..Default::default() };
+        let mut controller = controller();
+        controller.unsafe_head = block(10);
+        controller.update_safe_heads(block(12), block(4));
+        assert_eq!(controller.safe_head().number, 0);
+        assert_eq!(controller.finalized_head().number, 0);
+
+        controller.update_safe_heads(block(8), block(4));
+        assert_eq!(controller.safe_head().number, 8);
+        assert_eq!(controller.finalized_head().number, 4);
+        controller.update_safe_heads(block(6), block(9));
+        assert_eq!(controller.safe_head().number, 8);
+        assert_eq!(controller.finalized_head().number, 4);
+    }
+
+    #[test]
+    fn test_payload_header() {
+        let payload = serde_json::json!({
+            "blockHash": B256::repeat_byte(2),
+            "parentHash": B256::repeat_byte(1),
+            "blockNumber": "0x10",
+            "timestamp": "0x64",
+            "transactions": [],
+        });
+        let block: BlockInfo = serde_json::from_value::<PayloadHeader>(payload).unwrap().into();
+        assert_eq!(block.number, 16);
+        assert_eq!(block.timestamp, 100);
+        assert_eq!(block.parent_hash, B256::repeat_byte(1));
+    }
+
+    #[test]
+    fn test_new_payload_params() {
+        let (method, params) = new_payload_params(serde_json::json!({}), Some(B256::ZERO));
+        assert_eq!(method, "engine_newPayloadV3");
+        assert_eq!(params[1], serde_json::json!([]));
+        let (method, params) = new_payload_params(serde_json::json!({}), None);
+        assert_eq!(method, "engine_newPayloadV2");
+        assert_eq!(params.as_array().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_check_status() {
+        assert!(check_status("m", &serde_json::json!({ "status": "VALID" })).is_ok());
+        let err = check_status("m", &serde_json::json!({ "status": "INVALID" })).unwrap_err();
+        assert!(err.to_string().starts_with("m returned status INVALID"));
+        assert!(check_status("m", &Value::Null).is_err());
+    }
+}
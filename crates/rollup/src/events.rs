@@ -0,0 +1,188 @@
+//! Typed events of the rollup driver, for library users and the RPC server to follow
+//! derivation without parsing the logs.
+
+use std::{
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use alloy::primitives::B256;
+use kona_primitives::L2BlockInfo;
+use op_net::gossip::handler::ReceivedBlock;
+use serde::{Serialize, Serializer};
+use tokio::{
+    sync::broadcast::{self, error::RecvError},
+    task::JoinHandle,
+};
+use tracing::debug;
+
+/// The number of events buffered for each subscriber, past which lagging subscribers
+/// skip the oldest ones.
+pub const DRIVER_EVENT_CHANNEL_SIZE: usize = 256;
+
+/// How long the safe head can stay still before [DriverEvent::DerivationStalled] is
+/// emitted, which is well above the usual batch submission interval.
+pub const DEFAULT_STALL_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// An event of a running [crate::Driver], see [DriverEvents::subscribe].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "type")]
+pub enum DriverEvent {
+    /// An unsafe block was received over p2p gossip.
+    UnsafeBlockReceived {
+        /// The number of the block.
+        number: u64,
+        /// The hash of the block.
+        hash: B256,
+    },
+    /// A derived L2 block was validated, advancing the safe head.
+    SafeHeadAdvanced {
+        /// The new safe head.
+        safe_head: L2BlockInfo,
+        /// The L1 block the pipeline read up to when the block was derived.
+        derived_from: u64,
+    },
+    /// The safe head did not advance for longer than the stall timeout.
+    DerivationStalled {
+        /// The safe head derivation is stuck at.
+        safe_head: L2BlockInfo,
+        /// How long the safe head did not advance for.
+        #[serde(serialize_with = "as_secs")]
+        stalled_for: Duration,
+    },
+    /// An L1 reorg reverted the L1 origin of derived blocks, and derivation was reset.
+    ReorgDetected {
+        /// The first L1 block number that was reverted.
+        fork_number: u64,
+        /// The number of reverted L1 blocks.
+        depth: usize,
+        /// The latest derived L2 block before the reset.
+        from_l2: L2BlockInfo,
+        /// The L2 block derivation resumes from.
+        to_l2: L2BlockInfo,
+    },
+    /// A validator returned a verdict that differs from the one used for a block.
+    ValidatorDisagreement {
+        /// The number of the validated L2 block.
+        number: u64,
+        /// The name of the disagreeing validator.
+        validator: String,
+        /// The verdict of the disagreeing validator.
+        verdict: bool,
+        /// The verdict that was used.
+        outcome: bool,
+    },
+}
+
+/// Serializes a duration as a number of seconds.
+fn as_secs<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u64(duration.as_secs())
+}
+
+/// The bus the [DriverEvent]s are published on, shared by the driver and the components
+/// reporting events, e.g. the validators.
+#[derive(Debug, Clone)]
+pub struct DriverEvents {
+    /// The sender of the events, shared by the clones of the bus.
+    sender: Arc<RwLock<broadcast::Sender<DriverEvent>>>,
+}
+
+impl Default for DriverEvents {
+    fn default() -> Self {
+        let sender = broadcast::channel(DRIVER_EVENT_CHANNEL_SIZE).0;
+        Self { sender: Arc::new(RwLock::new(sender)) }
+    }
+}
+
+impl DriverEvents {
+    /// Returns a new receiver of the events published from now on.
+    ///
+    /// Subscribers falling behind by more than [DRIVER_EVENT_CHANNEL_SIZE] events skip
+    /// the oldest ones.
+    pub fn subscribe(&self) -> broadcast::Receiver<DriverEvent> {
+        self.sender.read().expect("lock poisoned").subscribe()
+    }
+
+    /// Publishes the event built by the given closure, if anyone is subscribed.
+    pub fn emit(&self, event: impl FnOnce() -> DriverEvent) {
+        let sender = self.sender.read().expect("lock poisoned");
+        if sender.receiver_count() > 0 {
+            _ = sender.send(event());
+        }
+    }
+
+    /// Publishes the events of this bus and of its clones on the given bus from now on,
+    /// e.g. one created before the driver to serve them over RPC.
+    ///
+    /// The existing subscribers of this bus stop receiving events.
+    pub fn publish_to(&self, other: &Self) {
+        let sender = other.sender.read().expect("lock poisoned").clone();
+        *self.sender.write().expect("lock poisoned") = sender;
+    }
+
+    /// Spawns a task publishing a [DriverEvent::UnsafeBlockReceived] for every block of
+    /// the given receiver, e.g. one of [op_net::driver::NetworkHandle::subscribe_unsafe_blocks].
+    pub fn forward_unsafe_blocks(
+        &self,
+        mut blocks: broadcast::Receiver<ReceivedBlock>,
+    ) -> JoinHandle<()> {
+        let events = self.clone();
+        tokio::spawn(async move {
+            loop {
+                match blocks.recv().await {
+                    Ok(block) => events.emit(|| DriverEvent::UnsafeBlockReceived {
+                        number: block.envelope.payload.block_number,
+                        hash: block.envelope.payload.block_hash,
+                    }),
+                    Err(RecvError::Lagged(skipped)) => {
+                        debug!("Skipped {} unsafe blocks for the driver events", skipped)
+                    }
+                    Err(RecvError::Closed) => return,
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_emit() {
+        let events = DriverEvents::default();
+        // Events without subscribers are not built.
+        events.emit(|| unreachable!());
+
+        let mut recv = events.subscribe();
+        let event = DriverEvent::ValidatorDisagreement {
+            number: 10,
+            validator: "engine-api".to_string(),
+            verdict: false,
+            outcome: true,
+        };
+        events.emit(|| event.clone());
+        assert_eq!(recv.try_recv().unwrap(), event);
+        assert!(recv.try_recv().is_err());
+
+        // The clones of a redirected bus publish on the new one.
+        let (clone, other) = (events.clone(), DriverEvents::default());
+        let mut other_recv = other.subscribe();
+        events.publish_to(&other);
+        clone.emit(|| event.clone());
+        assert_eq!(other_recv.try_recv().unwrap(), event);
+        assert!(recv.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_serialize_event() {
+        let event = DriverEvent::DerivationStalled {
+            safe_head: L2BlockInfo::default(),
+            stalled_for: Duration::from_millis(90_500),
+        };
+        let json = serde_json::to_value(&event).unwrap();
+        assert_eq!(json["type"], "DerivationStalled");
+        assert_eq!(json["stalled_for"], 90);
+        assert_eq!(json["safe_head"], serde_json::to_value(L2BlockInfo::default()).unwrap());
+    }
+}
@@ -0,0 +1,194 @@
+//! Finalization of the L2 blocks derived from finalized L1 data
+
+use std::{collections::VecDeque, sync::Arc, time::Duration};
+
+use alloy::{
+    eips::BlockNumberOrTag,
+    providers::{Provider, ReqwestProvider},
+};
+use eyre::{eyre, Result};
+use kona_primitives::L2BlockInfo;
+use tokio::{sync::watch, time::sleep};
+use tracing::{debug, trace, warn};
+use url::Url;
+
+use crate::Supervisor;
+
+/// The default interval at which the L1 finalized block is polled.
+pub const DEFAULT_L1_FINALIZED_POLL_INTERVAL: Duration = Duration::from_secs(12);
+
+/// The maximum number of L1 blocks whose derived L2 blocks wait for L1 finality.
+///
+/// L1 finalizes blocks about 2 epochs, i.e. 64 blocks, behind its head, so this only
+/// fills up if L1 finality stalls, in which case the oldest blocks are dropped.
+const FINALITY_QUEUE_CAPACITY: usize = 1024;
+
+/// Finalizer
+///
+/// Tracks the latest safe L2 block derived from each L1 block, i.e. from the batches
+/// read up to that L1 block. Once an L1 block is finalized, so is the latest L2 block
+/// derived from it or from an older L1 block, since its inputs can no longer be reorged.
+#[derive(Debug, Default)]
+pub struct Finalizer {
+    /// The L1 block numbers and the latest safe L2 block derived from them, oldest first.
+    derived: VecDeque<(u64, L2BlockInfo)>,
+    /// The latest finalized L2 block.
+    finalized: Option<L2BlockInfo>,
+}
+
+impl Finalizer {
+    /// Records the given safe L2 block, derived from the data read up to the given L1
+    /// block.
+    ///
+    /// The blocks recorded past it, e.g. before derivation was reset, are dropped.
+    pub fn on_safe(&mut self, derived_from: u64, block: L2BlockInfo) {
+        while self.derived.back().is_some_and(|(from, safe)| {
+            *from > derived_from || safe.block_info.number >= block.block_info.number
+        }) {
+            self.derived.pop_back();
+        }
+        match self.derived.back_mut() {
+            Some((from, safe)) if *from == derived_from => *safe = block,
+            _ => self.derived.push_back((derived_from, block)),
+        }
+        if self.derived.len() > FINALITY_QUEUE_CAPACITY {
+            self.derived.pop_front();
+        }
+    }
+
+    /// Drops the L2 blocks derived from the L1 blocks reverted by a reorg at the given
+    /// L1 block number.
+    pub fn on_l1_reorg(&mut self, fork_number: u64) {
+        self.derived.retain(|(from, _)| *from < fork_number);
+    }
+
+    /// Returns the latest L2 block derived from the L1 blocks up to the given finalized
+    /// one, if it is newer than the current finalized L2 block.
+    pub fn on_l1_finalized(&mut self, l1_finalized: u64) -> Option<L2BlockInfo> {
+        let mut finalized = None;
+        while let Some((from, block)) = self.derived.front() {
+            if *from > l1_finalized {
+                break;
+            }
+            finalized = Some(*block);
+            self.derived.pop_front();
+        }
+        let finalized = finalized.filter(|block| {
+            self.finalized.map_or(true, |f| block.block_info.number > f.block_info.number)
+        })?;
+        self.finalized = Some(finalized);
+        Some(finalized)
+    }
+
+    /// Returns the latest finalized L2 block, if any.
+    pub const fn finalized(&self) -> Option<L2BlockInfo> {
+        self.finalized
+    }
+}
+
+/// Tracks the finalized block of the L1 chain over RPC, for the standalone driver.
+///
+/// The ExEx driver reads the finalized block of its host node instead.
+#[derive(Debug, Clone)]
+pub struct L1FinalizedTracker {
+    /// The L1 RPC URL.
+    url: Url,
+    /// The interval at which the finalized block is polled.
+    poll_interval: Duration,
+}
+
+impl L1FinalizedTracker {
+    /// Creates a new [L1FinalizedTracker] for the given L1 RPC URL.
+    pub const fn new(url: Url, poll_interval: Duration) -> Self {
+        Self { url, poll_interval }
+    }
+
+    /// Spawns the tracking task under the given [Supervisor], returning a receiver of the
+    /// latest L1 finalized block number, once known.
+    pub fn supervise(self, supervisor: &mut Supervisor) -> watch::Receiver<Option<u64>> {
+        let sender = Arc::new(watch::channel(None).0);
+        let recv = sender.subscribe();
+        supervisor.spawn("l1_finalized_tracker", move || {
+            let tracker = self.clone();
+            let sender = sender.clone();
+            async move {
+                tracker.run(&sender).await;
+                Ok(())
+            }
+        });
+        recv
+    }
+
+    /// Polls the finalized block until the receiver is dropped.
+    async fn run(self, sender: &watch::Sender<Option<u64>>) {
+        let provider = ReqwestProvider::new_http(self.url.clone());
+        while !sender.is_closed() {
+            match finalized_block_number(&provider).await {
+                Ok(Some(number)) => {
+                    trace!("L1 finalized block: {}", number);
+                    sender.send_if_modified(|finalized| {
+                        let changed = *finalized != Some(number);
+                        *finalized = Some(number);
+                        changed
+                    });
+                }
+                // Pre-merge and dev chains may have no finalized block.
+                Ok(None) => {}
+                Err(err) => warn!(?err, "Failed to read the L1 finalized block"),
+            }
+            sleep(self.poll_interval).await;
+        }
+        debug!("L1 finalized block receiver dropped, stopping the tracker");
+    }
+}
+
+/// Returns the number of the finalized block of the given L1 provider, if any.
+async fn finalized_block_number(provider: &ReqwestProvider) -> Result<Option<u64>> {
+    let block = provider
+        .get_block_by_number(BlockNumberOrTag::Finalized, false)
+        .await
+        .map_err(|e| eyre!("Failed to fetch the L1 finalized block: {:?}", e))?;
+    Ok(block.and_then(|block| block.header.number))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use kona_primitives::BlockInfo;
+
+    fn block(number: u64) -> L2BlockInfo {
+        L2BlockInfo { block_info: BlockInfo { number, ..Default::default() }, ..Default::default() }
+    }
+
+    #[test]
+    fn test_finalizer() {
+        let mut finalizer = Finalizer::default();
+        finalizer.on_safe(10, block(1));
+        finalizer.on_safe(10, block(2));
+        finalizer.on_safe(11, block(3));
+        finalizer.on_safe(12, block(4));
+
+        assert_eq!(finalizer.on_l1_finalized(9), None);
+        assert_eq!(finalizer.on_l1_finalized(11), Some(block(3)));
+        assert_eq!(finalizer.on_l1_finalized(11), None);
+        assert_eq!(finalizer.finalized(), Some(block(3)));
+        assert_eq!(finalizer.on_l1_finalized(20), Some(block(4)));
+    }
+
+    #[test]
+    fn test_finalizer_reset() {
+        let mut finalizer = Finalizer::default();
+        finalizer.on_safe(10, block(1));
+        finalizer.on_safe(11, block(2));
+        finalizer.on_safe(12, block(3));
+
+        // Derivation restarted from block 2, from an older L1 block.
+        finalizer.on_safe(11, block(2));
+        assert_eq!(finalizer.on_l1_finalized(12), Some(block(2)));
+
+        finalizer.on_safe(13, block(3));
+        finalizer.on_safe(14, block(4));
+        finalizer.on_l1_reorg(14);
+        assert_eq!(finalizer.on_l1_finalized(14), Some(block(3)));
+    }
+}
@@ -0,0 +1,168 @@
+//! L1 head tracking for the standalone driver
+
+use std::{sync::Arc, time::Duration};
+
+use alloy::providers::{Provider, ProviderBuilder, WsConnect};
+use eyre::{eyre, Result};
+use tokio::{sync::watch, task::JoinHandle, time::sleep};
+use tracing::{debug, trace, warn};
+use url::Url;
+
+use crate::Supervisor;
+
+/// The default interval at which the L1 head is polled when subscriptions are unavailable.
+pub const DEFAULT_L1_POLL_INTERVAL: Duration = Duration::from_secs(4);
+
+/// Tracks the head of the L1 chain in the background.
+///
+/// For `ws://` and `wss://` endpoints, new heads are received as soon as they are
+/// produced via `eth_subscribe("newHeads")`. If the subscription cannot be established
+/// or is dropped, and for HTTP endpoints, the head is polled on a fixed interval instead.
+#[derive(Debug, Clone)]
+pub struct L1HeadTracker {
+    /// The L1 RPC URL.
+    url: Url,
+    /// The interval at which the head is polled.
+    poll_interval: Duration,
+}
+
+impl L1HeadTracker {
+    /// Creates a new [L1HeadTracker] for the given L1 RPC URL.
+    pub const fn new(url: Url, poll_interval: Duration) -> Self {
+        Self { url, poll_interval }
+    }
+
+    /// Spawns the tracking task, returning a receiver of the latest L1 head number.
+    ///
+    /// The receiver only holds the latest head, so a slow consumer skips straight to it.
+    /// The task stops once the receiver is dropped.
+    pub fn spawn(self) -> (watch::Receiver<u64>, JoinHandle<()>) {
+        let (sender, recv) = watch::channel(0);
+        let handle = tokio::spawn(async move { self.run(&sender).await });
+        (recv, handle)
+    }
+
+    /// Spawns the tracking task under the given [Supervisor], returning a receiver of
+    /// the latest L1 head number.
+    ///
+    /// The task is restarted if it panics, and the receiver keeps the last known head
+    /// across restarts.
+    pub fn supervise(self, supervisor: &mut Supervisor) -> watch::Receiver<u64> {
+        let sender = Arc::new(watch::channel(0).0);
+        let recv = sender.subscribe();
+        supervisor.spawn("l1_head_tracker", move || {
+            let tracker = self.clone();
+            let sender = sender.clone();
+            async move {
+                tracker.run(&sender).await;
+                Ok(())
+            }
+        });
+        recv
+    }
+
+    /// Runs the tracker, preferring subscriptions and falling back to polling.
+    async fn run(self, sender: &watch::Sender<u64>) {
+        while !sender.is_closed() {
+            if is_ws(&self.url) {
+                match self.subscribe(sender).await {
+                    Ok(()) => warn!("L1 newHeads subscription ended, polling instead"),
+                    Err(err) => warn!(?err, "L1 newHeads subscription failed, polling instead"),
+                }
+            }
+
+            // Polling only returns on errors, after which the subscription is retried.
+            if let Err(err) = self.poll(sender).await {
+                warn!(?err, "Failed to poll the L1 head");
+                sleep(self.poll_interval).await;
+            }
+        }
+        debug!("L1 head receiver dropped, stopping the tracker");
+    }
+
+    /// Receives new heads from an `eth_subscribe("newHeads")` subscription.
+    async fn subscribe(&self, sender: &watch::Sender<u64>) -> Result<()> {
+        let provider = ProviderBuilder::new()
+            .on_ws(WsConnect::new(self.url.as_str()))
+            .await
+            .map_err(|e| eyre!("Failed to connect to L1 websocket: {:?}", e))?;
+        let mut heads = provider
+            .subscribe_blocks()
+            .await
+            .map_err(|e| eyre!("Failed to subscribe to new L1 heads: {:?}", e))?;
+        debug!("Subscribed to new L1 heads at {}", self.url);
+
+        while let Ok(block) = heads.recv().await {
+            let Some(number) = block.header.number else {
+                continue;
+            };
+            if !update_head(sender, number) {
+                return Ok(());
+            }
+        }
+        Ok(())
+    }
+
+    /// Polls the L1 head on a fixed interval, until the receiver is dropped or an error occurs.
+    async fn poll(&self, sender: &watch::Sender<u64>) -> Result<()> {
+        let provider = ProviderBuilder::new()
+            .on_builtin(self.url.as_str())
+            .await
+            .map_err(|e| eyre!("Failed to connect to L1 RPC: {:?}", e))?;
+
+        loop {
+            let number = provider
+                .get_block_number()
+                .await
+                .map_err(|e| eyre!("Failed to fetch L1 block number: {:?}", e))?;
+            if !update_head(sender, number) {
+                return Ok(());
+            }
+            sleep(self.poll_interval).await;
+        }
+    }
+}
+
+/// Returns `true` if the given URL is a websocket endpoint.
+fn is_ws(url: &Url) -> bool {
+    matches!(url.scheme(), "ws" | "wss")
+}
+
+/// Publishes the given head if it changed.
+///
+/// Returns `false` if the receiver has been dropped.
+fn update_head(sender: &watch::Sender<u64>, number: u64) -> bool {
+    sender.send_if_modified(|head| {
+        let changed = *head != number;
+        *head = number;
+        changed
+    });
+    trace!("L1 head: {}", number);
+    !sender.is_closed()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_ws() {
+        assert!(is_ws(&Url::parse("ws://localhost:8546").unwrap()));
+        assert!(is_ws(&Url::parse("wss://eth.example.com").unwrap()));
+        assert!(!is_ws(&Url::parse("http://localhost:8545").unwrap()));
+    }
+
+    #[test]
+    fn test_update_head_only_notifies_changes() {
+        let (sender, mut recv) = watch::channel(0);
+        assert!(update_head(&sender, 1));
+        assert!(recv.has_changed().unwrap());
+        assert_eq!(*recv.borrow_and_update(), 1);
+
+        assert!(update_head(&sender, 1));
+        assert!(!recv.has_changed().unwrap());
+
+        drop(recv);
+        assert!(!update_head(&sender, 2));
+    }
+}
@@ -0,0 +1,820 @@
+//! Validation of the interop executing messages of the derived L2 blocks, as specified in
+//! the [interop messaging spec](https://specs.optimism.io/interop/messaging.html).
+
+use std::{collections::BTreeMap, fmt, path::Path, sync::Arc};
+
+use alloy::{
+    primitives::{address, keccak256, Address, Log, LogData, B256},
+    providers::{Provider, ReqwestProvider},
+    rpc::types::{BlockNumberOrTag, Filter},
+    sol,
+    sol_types::SolEvent,
+};
+use async_trait::async_trait;
+use eyre::{bail, eyre, Context, Result};
+use kona_derive::{online::AlloyL2ChainProvider, traits::L2ChainProvider};
+use kona_primitives::L2BlockInfo;
+use serde::{Deserialize, Serialize};
+use superchain_registry::RollupConfig;
+use tokio::sync::watch;
+use tracing::{debug, error, info, trace, warn};
+use url::Url;
+
+use crate::SyncStatus;
+
+sol! {
+    /// The identifier of an initiating message.
+    struct Identifier {
+        address origin;
+        uint256 blockNumber;
+        uint256 logIndex;
+        uint256 timestamp;
+        uint256 chainId;
+    }
+
+    /// Emitted by the `CrossL2Inbox` predeploy for every executed message.
+    event ExecutingMessage(bytes32 indexed msgHash, Identifier id);
+}
+
+/// The address of the `CrossL2Inbox` predeploy.
+pub const CROSS_L2_INBOX: Address = address!("4200000000000000000000000000000000000022");
+
+/// How long an initiating message can be executed for, in seconds.
+pub const MESSAGE_EXPIRY_WINDOW: u64 = 180 * 24 * 60 * 60;
+
+/// The chains whose initiating messages can be executed, loaded from a JSON file, e.g.:
+///
+/// ```json
+/// {
+///   "dependencies": {
+///     "10": { "activationTime": 1735689600, "rpcUrl": "http://localhost:9545" },
+///     "8453": { "activationTime": 1735689600, "rpcUrl": "http://localhost:10545" }
+///   }
+/// }
+/// ```
+///
+/// The other fields of op-supervisor's dependency set files are ignored, so they can be
+/// reused by adding the RPC URL of each chain.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DependencySet {
+    /// The chains of the set, by chain ID.
+    pub dependencies: BTreeMap<u64, ChainDependency>,
+}
+
+/// A chain of a [DependencySet].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChainDependency {
+    /// The timestamp from which the messages of the chain can be executed.
+    pub activation_time: u64,
+    /// The RPC URL of an execution client of the chain.
+    pub rpc_url: String,
+}
+
+impl DependencySet {
+    /// Loads the dependency set from the given JSON file.
+    pub fn load(path: &Path) -> Result<Self> {
+        let file = std::fs::read(path).wrap_err("Failed to open dependency set file")?;
+        serde_json::from_slice(&file).wrap_err("Failed to read dependency set file")
+    }
+}
+
+/// An executing message, read from an [ExecutingMessage] log of the `CrossL2Inbox`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InboxMessage {
+    /// The chain of the initiating message.
+    pub chain_id: u64,
+    /// The block of the initiating message.
+    pub block_number: u64,
+    /// The index of the initiating message log in its block.
+    pub log_index: u64,
+    /// The timestamp of the block of the initiating message.
+    pub timestamp: u64,
+    /// The address which emitted the initiating message.
+    pub origin: Address,
+    /// The hash of the payload of the initiating message, see [payload_hash].
+    pub msg_hash: B256,
+}
+
+impl InboxMessage {
+    /// Decodes the executing message of the given log, or returns `None` if it isn't an
+    /// [ExecutingMessage] log of the `CrossL2Inbox`.
+    pub fn from_log(log: &Log) -> Option<Result<Self, InvalidMessage>> {
+        if log.address != CROSS_L2_INBOX ||
+            log.data.topics().first() != Some(&ExecutingMessage::SIGNATURE_HASH)
+        {
+            return None;
+        }
+        let Ok(event) = ExecutingMessage::decode_log_data(&log.data, true) else {
+            return Some(Err(InvalidMessage::Malformed));
+        };
+        let id = event.id;
+        let fields = (
+            id.chainId.try_into(),
+            id.blockNumber.try_into(),
+            id.logIndex.try_into(),
+            id.timestamp.try_into(),
+        );
+        let (Ok(chain_id), Ok(block_number), Ok(log_index), Ok(timestamp)) = fields else {
+            return Some(Err(InvalidMessage::Malformed));
+        };
+        Some(Ok(Self {
+            chain_id,
+            block_number,
+            log_index,
+            timestamp,
+            origin: id.origin,
+            msg_hash: event.msgHash,
+        }))
+    }
+}
+
+/// Returns the hash of the payload of the given initiating message log: its topics and
+/// data, concatenated.
+pub fn payload_hash(log: &LogData) -> B256 {
+    let mut payload = Vec::with_capacity(log.topics().len() * 32 + log.data.len());
+    for topic in log.topics() {
+        payload.extend_from_slice(topic.as_slice());
+    }
+    payload.extend_from_slice(&log.data);
+    keccak256(payload)
+}
+
+/// Why an executing message is invalid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum InvalidMessage {
+    /// The `ExecutingMessage` log can't be decoded.
+    Malformed,
+    /// The initiating chain isn't in the dependency set.
+    UnknownChain,
+    /// The initiating message is newer than the executing block.
+    FutureTimestamp,
+    /// The initiating message is older than the [MESSAGE_EXPIRY_WINDOW].
+    Expired,
+    /// The initiating message predates the interop activation of its chain.
+    BeforeActivation,
+    /// The initiating block has a different timestamp.
+    TimestampMismatch,
+    /// The initiating block has no log at the index.
+    MissingLog,
+    /// The initiating log was emitted by a different address.
+    OriginMismatch,
+    /// The initiating log has a different payload hash.
+    HashMismatch,
+    /// The initiating log doesn't match the access list checksum.
+    ChecksumMismatch,
+}
+
+impl InvalidMessage {
+    /// Returns the metrics label of the reason.
+    pub const fn label(&self) -> &'static str {
+        match self {
+            Self::Malformed => "malformed",
+            Self::UnknownChain => "unknown_chain",
+            Self::FutureTimestamp => "future_timestamp",
+            Self::Expired => "expired",
+            Self::BeforeActivation => "before_activation",
+            Self::TimestampMismatch => "timestamp_mismatch",
+            Self::MissingLog => "missing_log",
+            Self::OriginMismatch => "origin_mismatch",
+            Self::HashMismatch => "hash_mismatch",
+            Self::ChecksumMismatch => "checksum_mismatch",
+        }
+    }
+}
+
+impl fmt::Display for InvalidMessage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Malformed => write!(f, "malformed executing message log"),
+            Self::UnknownChain => write!(f, "initiating chain not in the dependency set"),
+            Self::FutureTimestamp => write!(f, "initiating message newer than the block"),
+            Self::Expired => write!(f, "initiating message expired"),
+            Self::BeforeActivation => write!(f, "initiating message before interop activation"),
+            Self::TimestampMismatch => write!(f, "initiating block timestamp mismatch"),
+            Self::MissingLog => write!(f, "initiating log not found"),
+            Self::OriginMismatch => write!(f, "initiating log origin mismatch"),
+            Self::HashMismatch => write!(f, "initiating log payload hash mismatch"),
+            Self::ChecksumMismatch => write!(f, "initiating log checksum mismatch"),
+        }
+    }
+}
+
+/// The cross-chain safety of an executing message, or of a block as the lowest of its
+/// messages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SafetyLevel {
+    /// An executing message is invalid, so the block must be replaced.
+    Invalid(InvalidMessage),
+    /// An initiating message isn't known yet.
+    Unsafe,
+    /// The initiating messages are valid, but not all of them are safe on their chain.
+    CrossUnsafe,
+    /// The initiating messages are valid and safe on their chain.
+    CrossSafe,
+}
+
+/// The logs of a block of a chain of the dependency set.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BlockLogs {
+    /// The block timestamp.
+    pub timestamp: u64,
+    /// The logs of the block, in order.
+    pub logs: Vec<Log>,
+}
+
+/// Reads the initiating messages of the chains of the dependency set.
+#[async_trait]
+pub trait InteropProvider {
+    /// Returns the logs of the given block of the given chain, or `None` if the block
+    /// isn't known yet.
+    async fn block_logs(&self, chain_id: u64, number: u64) -> Result<Option<BlockLogs>>;
+
+    /// Returns the number of the safe block of the given chain.
+    async fn safe_head(&self, chain_id: u64) -> Result<u64>;
+}
+
+/// An [InteropProvider] reading from the RPC URLs of a [DependencySet].
+#[derive(Debug, Clone)]
+pub struct RpcInteropProvider {
+    /// The providers of the chains, by chain ID.
+    providers: BTreeMap<u64, ReqwestProvider>,
+}
+
+impl RpcInteropProvider {
+    /// Creates a new [RpcInteropProvider] for the chains of the given set.
+    pub fn new(deps: &DependencySet) -> Result<Self> {
+        let providers = deps
+            .dependencies
+            .iter()
+            .map(|(chain_id, dep)| {
+                let url: Url = dep
+                    .rpc_url
+                    .parse()
+                    .wrap_err_with(|| format!("Invalid RPC URL of chain {}", chain_id))?;
+                Ok((*chain_id, ReqwestProvider::new_http(url)))
+            })
+            .collect::<Result<_>>()?;
+        Ok(Self { providers })
+    }
+
+    /// Returns the provider of the given chain.
+    fn provider(&self, chain_id: u64) -> Result<&ReqwestProvider> {
+        self.providers.get(&chain_id).ok_or_else(|| eyre!("no RPC for chain {}", chain_id))
+    }
+}
+
+#[async_trait]
+impl InteropProvider for RpcInteropProvider {
+    async fn block_logs(&self, chain_id: u64, number: u64) -> Result<Option<BlockLogs>> {
+        let provider = self.provider(chain_id)?;
+        let Some(block) = provider.get_block_by_number(number.into(), false).await? else {
+            return Ok(None);
+        };
+        let hash = block.header.hash.ok_or_else(|| eyre!("block {} has no hash", number))?;
+        let logs = provider.get_logs(&Filter::new().at_block_hash(hash)).await?;
+        Ok(Some(BlockLogs {
+            timestamp: block.header.timestamp,
+            logs: logs.into_iter().map(|log| log.inner).collect(),
+        }))
+    }
+
+    async fn safe_head(&self, chain_id: u64) -> Result<u64> {
+        let block = self
+            .provider(chain_id)?
+            .get_block_by_number(BlockNumberOrTag::Safe, false)
+            .await?
+            .ok_or_else(|| eyre!("chain {} has no safe block", chain_id))?;
+        block.header.number.ok_or_else(|| eyre!("safe block of chain {} has no number", chain_id))
+    }
+}
+
+/// Checks executing messages against the initiating messages of a [DependencySet].
+///
+/// The initiating messages are checked to exist and to be safe on their chain, but their
+/// own blocks are not checked for cross-chain safety in turn.
+#[derive(Debug, Clone)]
+pub struct MessageValidator<P> {
+    /// The dependency set.
+    deps: DependencySet,
+    /// The provider of the initiating messages.
+    provider: P,
+}
+
+impl<P: InteropProvider + Send + Sync> MessageValidator<P> {
+    /// Creates a new [MessageValidator] reading the given set from the given provider.
+    pub const fn new(deps: DependencySet, provider: P) -> Self {
+        Self { deps, provider }
+    }
+
+    /// Returns the safety of the executing messages of the given block logs, at the given
+    /// block timestamp. Blocks without executing messages are [SafetyLevel::CrossSafe].
+    pub async fn check_block(&self, logs: &[Log], timestamp: u64) -> Result<SafetyLevel> {
+        let mut level = SafetyLevel::CrossSafe;
+        for message in logs.iter().filter_map(InboxMessage::from_log) {
+            let message_level = match message {
+                Ok(message) => self.check_message(&message, timestamp).await?,
+                Err(reason) => SafetyLevel::Invalid(reason),
+            };
+            level = level.min(message_level);
+            if matches!(level, SafetyLevel::Invalid(_)) {
+                break;
+            }
+        }
+        Ok(level)
+    }
+
+    /// Returns the safety of the given executing message, in a block of the given
+    /// timestamp.
+    pub async fn check_message(
+        &self,
+        message: &InboxMessage,
+        timestamp: u64,
+    ) -> Result<SafetyLevel> {
+        let lookup = MessageLookup {
+            chain_id: message.chain_id,
+            block_number: message.block_number,
+            log_index: message.log_index,
+            timestamp: message.timestamp,
+        };
+        let log = match self.lookup(&lookup, timestamp).await? {
+            Lookup::Found(log) => log,
+            Lookup::Ruled(level) => return Ok(level),
+        };
+        if log.address != message.origin {
+            return Ok(SafetyLevel::Invalid(InvalidMessage::OriginMismatch));
+        }
+        if payload_hash(&log.data) != message.msg_hash {
+            return Ok(SafetyLevel::Invalid(InvalidMessage::HashMismatch));
+        }
+        self.safety(&lookup).await
+    }
+
+    /// Returns the safety of the message of the given access list entry, in a block of
+    /// the given timestamp.
+    pub async fn check_access(&self, entry: &AccessEntry, timestamp: u64) -> Result<SafetyLevel> {
+        let lookup = MessageLookup {
+            chain_id: entry.chain_id,
+            block_number: entry.block_number,
+            log_index: entry.log_index,
+            timestamp: entry.timestamp,
+        };
+        let log = match self.lookup(&lookup, timestamp).await? {
+            Lookup::Found(log) => log,
+            Lookup::Ruled(level) => return Ok(level),
+        };
+        if entry.checksum_of(log.address, payload_hash(&log.data)) != entry.checksum {
+            return Ok(SafetyLevel::Invalid(InvalidMessage::ChecksumMismatch));
+        }
+        self.safety(&lookup).await
+    }
+
+    /// Checks the given message against the dependency set, and fetches its initiating
+    /// log.
+    async fn lookup(&self, message: &MessageLookup, timestamp: u64) -> Result<Lookup> {
+        use InvalidMessage::*;
+        use Lookup::Ruled;
+        use SafetyLevel::Invalid;
+
+        let Some(dep) = self.deps.dependencies.get(&message.chain_id) else {
+            return Ok(Ruled(Invalid(UnknownChain)));
+        };
+        if message.timestamp > timestamp {
+            return Ok(Ruled(Invalid(FutureTimestamp)));
+        }
+        if message.timestamp.saturating_add(MESSAGE_EXPIRY_WINDOW) < timestamp {
+            return Ok(Ruled(Invalid(Expired)));
+        }
+        if message.timestamp < dep.activation_time {
+            return Ok(Ruled(Invalid(BeforeActivation)));
+        }
+
+        let Some(block) = self.provider.block_logs(message.chain_id, message.block_number).await?
+        else {
+            return Ok(Ruled(SafetyLevel::Unsafe));
+        };
+        if block.timestamp != message.timestamp {
+            return Ok(Ruled(Invalid(TimestampMismatch)));
+        }
+        match block.logs.get(message.log_index as usize) {
+            Some(log) => Ok(Lookup::Found(log.clone())),
+            None => Ok(Ruled(Invalid(MissingLog))),
+        }
+    }
+
+    /// Returns the safety of the valid given message, from the safe head of its chain.
+    async fn safety(&self, message: &MessageLookup) -> Result<SafetyLevel> {
+        let safe_head = self.provider.safe_head(message.chain_id).await?;
+        Ok(match message.block_number <= safe_head {
+            true => SafetyLevel::CrossSafe,
+            false => SafetyLevel::CrossUnsafe,
+        })
+    }
+}
+
+/// The location of an initiating message, checked by a [MessageValidator].
+#[derive(Debug)]
+struct MessageLookup {
+    chain_id: u64,
+    block_number: u64,
+    log_index: u64,
+    timestamp: u64,
+}
+
+/// The result of a [MessageValidator] lookup.
+#[derive(Debug)]
+enum Lookup {
+    /// The initiating log was found, and has to be checked against the message.
+    Found(Log),
+    /// The message is ruled on without its initiating log.
+    Ruled(SafetyLevel),
+}
+
+/// An executing message declared in the access list of a transaction calling the
+/// `CrossL2Inbox`, as a lookup entry followed by a checksum entry.
+///
+/// See the [access list spec](https://specs.optimism.io/interop/predeploys.html#access-list).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AccessEntry {
+    /// The chain of the initiating message.
+    pub chain_id: u64,
+    /// The block of the initiating message.
+    pub block_number: u64,
+    /// The timestamp of the block of the initiating message.
+    pub timestamp: u64,
+    /// The index of the initiating message log in its block.
+    pub log_index: u64,
+    /// The checksum of the identifier and the payload hash of the message.
+    pub checksum: B256,
+}
+
+impl AccessEntry {
+    /// The type of the lookup entries.
+    pub const LOOKUP: u8 = 1;
+    /// The type of the chain ID extension entries, for chain IDs over 64 bits.
+    pub const CHAIN_ID_EXTENSION: u8 = 2;
+    /// The type of the checksum entries.
+    pub const CHECKSUM: u8 = 3;
+
+    /// Parses the messages of the given `CrossL2Inbox` access list entries.
+    pub fn parse_list(entries: &[B256]) -> Result<Vec<Self>> {
+        let mut messages = Vec::new();
+        let mut entries = entries.iter();
+        while let Some(lookup) = entries.next() {
+            match lookup[0] {
+                Self::LOOKUP => {}
+                Self::CHAIN_ID_EXTENSION => bail!("chain IDs over 64 bits are not supported"),
+                other => bail!("expected a lookup entry, got type {}", other),
+            }
+            if lookup[1..4] != [0; 3] {
+                bail!("invalid lookup entry: {}", lookup);
+            }
+            let Some(checksum) = entries.next().filter(|entry| entry[0] == Self::CHECKSUM) else {
+                bail!("missing checksum entry after {}", lookup);
+            };
+            let u64_at = |at: usize| u64::from_be_bytes(lookup[at..at + 8].try_into().unwrap());
+            messages.push(Self {
+                chain_id: u64_at(4),
+                block_number: u64_at(12),
+                timestamp: u64_at(20),
+                log_index: u32::from_be_bytes(lookup[28..32].try_into().unwrap()) as u64,
+                checksum: *checksum,
+            });
+        }
+        Ok(messages)
+    }
+
+    /// Returns the checksum of the message, if initiated by the given log origin with the
+    /// given payload hash.
+    pub fn checksum_of(&self, origin: Address, payload_hash: B256) -> B256 {
+        let log_hash = keccak256([origin.as_slice(), payload_hash.as_slice()].concat());
+        let mut id = [0u8; 32];
+        id[12..20].copy_from_slice(&self.block_number.to_be_bytes());
+        id[20..28].copy_from_slice(&self.timestamp.to_be_bytes());
+        id[28..32].copy_from_slice(&(self.log_index as u32).to_be_bytes());
+        let id_log_hash = keccak256([log_hash.as_slice(), &id].concat());
+        let chain_id = B256::left_padding_from(&self.chain_id.to_be_bytes());
+        let mut checksum = keccak256([id_log_hash.as_slice(), chain_id.as_slice()].concat());
+        checksum[0] = Self::CHECKSUM;
+        checksum
+    }
+}
+
+/// The cross-chain safe heads of an L2 chain, published by a [CrossSafetyTracker].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CrossSafety {
+    /// The latest block whose executing messages, and its ancestors', are valid.
+    pub cross_unsafe_l2: L2BlockInfo,
+    /// The latest safe block whose executing messages, and its ancestors', are valid and
+    /// safe on their chain.
+    pub cross_safe_l2: L2BlockInfo,
+}
+
+/// Tracks the cross-chain safety of the blocks derived by the driver, by checking their
+/// executing messages with a [MessageValidator].
+///
+/// The blocks up to the safe head of the driver when the tracker starts are assumed to be
+/// cross-safe. A block with an invalid executing message stops the cross heads until it
+/// is reorged out.
+#[derive(Debug)]
+pub struct CrossSafetyTracker {
+    /// The validator of the executing messages.
+    validator: MessageValidator<RpcInteropProvider>,
+    /// The provider of the derived L2 blocks.
+    l2_chain_provider: AlloyL2ChainProvider,
+    /// The provider of the logs of the derived L2 blocks.
+    l2_provider: ReqwestProvider,
+    /// The hash of the last block found invalid, to report it once.
+    invalid: Option<B256>,
+}
+
+impl CrossSafetyTracker {
+    /// Creates a new [CrossSafetyTracker] of the L2 chain at the given RPC URL, checking
+    /// its messages against the given dependency set.
+    pub fn new_http(l2_rpc_url: Url, cfg: Arc<RollupConfig>, deps: DependencySet) -> Result<Self> {
+        let provider = RpcInteropProvider::new(&deps)?;
+        Ok(Self {
+            validator: MessageValidator::new(deps, provider),
+            l2_chain_provider: AlloyL2ChainProvider::new_http(l2_rpc_url.clone(), cfg),
+            l2_provider: ReqwestProvider::new_http(l2_rpc_url),
+            invalid: None,
+        })
+    }
+
+    /// Returns a [MessageValidator] sharing the dependency set and providers of the
+    /// tracker.
+    pub fn validator(&self) -> MessageValidator<RpcInteropProvider> {
+        self.validator.clone()
+    }
+
+    /// Spawns the tracker following the given sync status of the driver, and returns a
+    /// receiver of the cross-chain safe heads.
+    ///
+    /// The task stops once the receiver or the sync status is dropped.
+    pub fn spawn(self, status: watch::Receiver<SyncStatus>) -> watch::Receiver<CrossSafety> {
+        let start = status.borrow().safe_l2;
+        let (sender, recv) =
+            watch::channel(CrossSafety { cross_unsafe_l2: start, cross_safe_l2: start });
+        tokio::spawn(async move { self.run(status, sender).await });
+        recv
+    }
+
+    /// Advances the cross heads on every change of the sync status.
+    async fn run(
+        mut self,
+        mut status: watch::Receiver<SyncStatus>,
+        sender: watch::Sender<CrossSafety>,
+    ) {
+        info!("Tracking the cross-chain safety of the L2 blocks");
+        let mut heads = *sender.borrow();
+        while status.changed().await.is_ok() && !sender.is_closed() {
+            let local = *status.borrow_and_update();
+            if let Err(err) = self.advance(&local, &mut heads).await {
+                warn!(?err, "Failed to check the executing messages of the L2 blocks");
+            }
+            sender.send_if_modified(|published| std::mem::replace(published, heads) != heads);
+        }
+        debug!("Sync status or cross safety receiver dropped, stopping the tracker");
+    }
+
+    /// Advances the given cross heads towards the given local heads.
+    async fn advance(&mut self, local: &SyncStatus, heads: &mut CrossSafety) -> Result<()> {
+        // The cross heads never pass the local heads, which move back on reorgs.
+        if heads.cross_safe_l2.block_info.number > local.safe_l2.block_info.number {
+            heads.cross_safe_l2 = local.safe_l2;
+        }
+        if heads.cross_unsafe_l2.block_info.number > local.unsafe_l2.block_info.number {
+            heads.cross_unsafe_l2 = heads.cross_safe_l2;
+        }
+
+        while heads.cross_unsafe_l2.block_info.number < local.unsafe_l2.block_info.number {
+            let (block, level) =
+                self.check_block(heads.cross_unsafe_l2.block_info.number + 1).await?;
+            if level < SafetyLevel::CrossUnsafe {
+                break;
+            }
+            heads.cross_unsafe_l2 = block;
+            metrics::gauge!("hera_interop_cross_unsafe_l2").set(block.block_info.number as f64);
+        }
+
+        // Blocks which were cross-unsafe may have become cross-safe since.
+        let target = local.safe_l2.block_info.number.min(heads.cross_unsafe_l2.block_info.number);
+        while heads.cross_safe_l2.block_info.number < target {
+            let (block, level) =
+                self.check_block(heads.cross_safe_l2.block_info.number + 1).await?;
+            if level < SafetyLevel::CrossSafe {
+                break;
+            }
+            heads.cross_safe_l2 = block;
+            metrics::gauge!("hera_interop_cross_safe_l2").set(block.block_info.number as f64);
+        }
+        Ok(())
+    }
+
+    /// Returns the given derived block and the safety of its executing messages.
+    async fn check_block(&mut self, number: u64) -> Result<(L2BlockInfo, SafetyLevel)> {
+        let block = self
+            .l2_chain_provider
+            .l2_block_info_by_number(number)
+            .await
+            .map_err(|e| eyre!("Failed to fetch L2 block {}: {:?}", number, e))?;
+        let filter = Filter::new().at_block_hash(block.block_info.hash);
+        let logs: Vec<_> =
+            self.l2_provider.get_logs(&filter).await?.into_iter().map(|log| log.inner).collect();
+        let level = self.validator.check_block(&logs, block.block_info.timestamp).await?;
+        trace!("L2 block {} is {:?}", number, level);
+        if let SafetyLevel::Invalid(reason) = level {
+            if self.invalid.replace(block.block_info.hash) != Some(block.block_info.hash) {
+                error!("L2 block {} has an invalid executing message: {}", number, reason);
+                metrics::counter!("hera_interop_invalid_messages_total", "reason" => reason.label())
+                    .increment(1);
+            }
+        }
+        Ok((block, level))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::{Bytes, U256};
+
+    /// An [InteropProvider] serving fixed blocks, with a fixed safe head.
+    struct MockProvider {
+        blocks: BTreeMap<(u64, u64), BlockLogs>,
+        safe_head: u64,
+    }
+
+    #[async_trait]
+    impl InteropProvider for MockProvider {
+        async fn block_logs(&self, chain_id: u64, number: u64) -> Result<Option<BlockLogs>> {
+            Ok(self.blocks.get(&(chain_id, number)).cloned())
+        }
+
+        async fn safe_head(&self, _chain_id: u64) -> Result<u64> {
+            Ok(self.safe_head)
+        }
+    }
+
+    fn initiating_log() -> Log {
+        Log::new_unchecked(
+            Address::repeat_byte(0xaa),
+            vec![B256::repeat_byte(1)],
+            Bytes::from_static(b"hello"),
+        )
+    }
+
+    fn executing_log(msg_hash: B256, block_number: u64, timestamp: u64) -> Log {
+        let event = ExecutingMessage {
+            msgHash: msg_hash,
+            id: Identifier {
+                origin: Address::repeat_byte(0xaa),
+                blockNumber: U256::from(block_number),
+                logIndex: U256::from(1),
+                timestamp: U256::from(timestamp),
+                chainId: U256::from(10),
+            },
+        };
+        Log { address: CROSS_L2_INBOX, data: event.encode_log_data() }
+    }
+
+    fn validator(safe_head: u64) -> MessageValidator<MockProvider> {
+        let mut deps = DependencySet::default();
+        deps.dependencies
+            .insert(10, ChainDependency { activation_time: 100, ..Default::default() });
+        let block = BlockLogs { timestamp: 1000, logs: vec![Log::default(), initiating_log()] };
+        let provider = MockProvider { blocks: BTreeMap::from([((10, 5), block)]), safe_head };
+        MessageValidator::new(deps, provider)
+    }
+
+    #[test]
+    fn test_inbox_message() {
+        let hash = payload_hash(&initiating_log().data);
+        let message = InboxMessage::from_log(&executing_log(hash, 5, 1000)).unwrap().unwrap();
+        assert_eq!(message.chain_id, 10);
+        assert_eq!(message.block_number, 5);
+        assert_eq!(message.log_index, 1);
+        assert_eq!(message.origin, Address::repeat_byte(0xaa));
+        assert_eq!(message.msg_hash, hash);
+
+        assert!(InboxMessage::from_log(&initiating_log()).is_none());
+        let mut log = executing_log(hash, 5, 1000);
+        log.address = Address::ZERO;
+        assert!(InboxMessage::from_log(&log).is_none());
+    }
+
+    #[test]
+    fn test_parse_dependency_set() {
+        let deps: DependencySet = serde_json::from_str(
+            r#"{"dependencies":{"10":{"chainIndex":10,"activationTime":100,"historyMinTime":0,"rpcUrl":"http://localhost:9545"}}}"#,
+        )
+        .unwrap();
+        assert_eq!(deps.dependencies[&10].activation_time, 100);
+        assert!(RpcInteropProvider::new(&deps).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_check_message() {
+        let hash = payload_hash(&initiating_log().data);
+        let message = InboxMessage::from_log(&executing_log(hash, 5, 1000)).unwrap().unwrap();
+        assert_eq!(
+            validator(5).check_message(&message, 1002).await.unwrap(),
+            SafetyLevel::CrossSafe
+        );
+        assert_eq!(
+            validator(4).check_message(&message, 1002).await.unwrap(),
+            SafetyLevel::CrossUnsafe
+        );
+
+        let invalid = |reason| Ok(SafetyLevel::Invalid(reason));
+        let check = |message: InboxMessage, timestamp| async move {
+            validator(5).check_message(&message, timestamp).await.map_err(|_| ())
+        };
+        assert_eq!(check(message, 999).await, invalid(InvalidMessage::FutureTimestamp));
+        assert_eq!(
+            check(message, 1001 + MESSAGE_EXPIRY_WINDOW).await,
+            invalid(InvalidMessage::Expired)
+        );
+        assert_eq!(
+            check(InboxMessage { chain_id: 8453, ..message }, 1002).await,
+            invalid(InvalidMessage::UnknownChain)
+        );
+        assert_eq!(
+            check(InboxMessage { timestamp: 99, ..message }, 1002).await,
+            invalid(InvalidMessage::BeforeActivation)
+        );
+        assert_eq!(
+            check(InboxMessage { timestamp: 1001, ..message }, 1002).await,
+            invalid(InvalidMessage::TimestampMismatch)
+        );
+        assert_eq!(
+            check(InboxMessage { log_index: 2, ..message }, 1002).await,
+            invalid(InvalidMessage::MissingLog)
+        );
+        assert_eq!(
+            check(InboxMessage { origin: Address::ZERO, ..message }, 1002).await,
+            invalid(InvalidMessage::OriginMismatch)
+        );
+        assert_eq!(
+            check(InboxMessage { msg_hash: B256::ZERO, ..message }, 1002).await,
+            invalid(InvalidMessage::HashMismatch)
+        );
+        assert_eq!(
+            check(InboxMessage { block_number: 6, ..message }, 1002).await,
+            Ok(SafetyLevel::Unsafe)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_check_access() {
+        let mut lookup = B256::ZERO;
+        lookup[0] = AccessEntry::LOOKUP;
+        lookup[4..12].copy_from_slice(&10u64.to_be_bytes());
+        lookup[12..20].copy_from_slice(&5u64.to_be_bytes());
+        lookup[20..28].copy_from_slice(&1000u64.to_be_bytes());
+        lookup[28..32].copy_from_slice(&1u32.to_be_bytes());
+        let entry = AccessEntry {
+            chain_id: 10,
+            block_number: 5,
+            timestamp: 1000,
+            log_index: 1,
+            checksum: B256::ZERO,
+        };
+        let log = initiating_log();
+        let checksum = entry.checksum_of(log.address, payload_hash(&log.data));
+        assert_eq!(checksum[0], AccessEntry::CHECKSUM);
+
+        let entries = AccessEntry::parse_list(&[lookup, checksum]).unwrap();
+        assert_eq!(entries, vec![AccessEntry { checksum, ..entry }]);
+        assert!(AccessEntry::parse_list(&[lookup]).is_err());
+        assert!(AccessEntry::parse_list(&[checksum]).is_err());
+
+        let validator = validator(5);
+        assert_eq!(
+            validator.check_access(&entries[0], 1000).await.unwrap(),
+            SafetyLevel::CrossSafe
+        );
+        let mut wrong = entries[0];
+        wrong.checksum[31] ^= 1;
+        assert_eq!(
+            validator.check_access(&wrong, 1000).await.unwrap(),
+            SafetyLevel::Invalid(InvalidMessage::ChecksumMismatch)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_check_block() {
+        let hash = payload_hash(&initiating_log().data);
+        let validator = validator(4);
+        assert_eq!(validator.check_block(&[], 1000).await.unwrap(), SafetyLevel::CrossSafe);
+
+        let logs = vec![initiating_log(), executing_log(hash, 5, 1000)];
+        assert_eq!(validator.check_block(&logs, 1000).await.unwrap(), SafetyLevel::CrossUnsafe);
+
+        let logs = vec![executing_log(hash, 6, 1000), executing_log(B256::ZERO, 5, 1000)];
+        assert_eq!(
+            validator.check_block(&logs, 1000).await.unwrap(),
+            SafetyLevel::Invalid(InvalidMessage::HashMismatch)
+        );
+    }
+}
@@ -5,19 +5,158 @@
 #![cfg_attr(not(test), warn(unused_crate_dependencies))]
 
 mod driver;
-pub use driver::Driver;
+pub use driver::{Driver, DriverContext, ExExDriverContext, StandaloneContext};
+
+mod events;
+pub use events::{DriverEvent, DriverEvents, DEFAULT_STALL_TIMEOUT, DRIVER_EVENT_CHANNEL_SIZE};
+
+mod head_tracker;
+pub use head_tracker::{L1HeadTracker, DEFAULT_L1_POLL_INTERVAL};
+
+mod finality;
+pub use finality::{Finalizer, L1FinalizedTracker, DEFAULT_L1_FINALIZED_POLL_INTERVAL};
+
+mod beacon_events;
+pub use beacon_events::BeaconEventTracker;
+
+mod unsafe_signer;
+pub use unsafe_signer::{UnsafeSignerWatcher, DEFAULT_SIGNER_POLL_INTERVAL};
+
+mod system_config;
+pub use system_config::{
+    LiveSystemConfig, SystemConfigHistory, SystemConfigTracker, SystemConfigUpdate,
+    SystemConfigValidator, DEFAULT_SYSTEM_CONFIG_POLL_INTERVAL,
+};
+
+mod pacing;
+pub use pacing::{StepPacer, DEFAULT_STEP_BURST, DEFAULT_TIP_DISTANCE};
+
+mod prefetch;
+pub use prefetch::{Prefetcher, DEFAULT_PREFETCH_DEPTH};
+
+mod rollup_config;
+pub use rollup_config::{check_l2_genesis, load_rollup_config, validate_rollup_config};
+
+mod superchain;
+pub use superchain::{chain_name, resolve_chain_id};
+
+mod checkpoint;
+pub use checkpoint::{Checkpoint, CheckpointStore};
+
+mod reorg;
+pub use reorg::{L1Reorg, ReorgWatcher, DEFAULT_REORG_WATCH_DEPTH};
+
+mod supervisor;
+pub use supervisor::{Backoff, Supervisor, DEFAULT_INITIAL_BACKOFF, DEFAULT_MAX_BACKOFF};
+
+mod interop;
+pub use interop::{
+    payload_hash, AccessEntry, BlockLogs, ChainDependency, CrossSafety, CrossSafetyTracker,
+    DependencySet, InboxMessage, InteropProvider, InvalidMessage, MessageValidator,
+    RpcInteropProvider, SafetyLevel, CROSS_L2_INBOX, MESSAGE_EXPIRY_WINDOW,
+};
+
+mod multi;
+pub use multi::{ChainConfig, ChainSpec, ChainsConfig, MultiDriver};
 
 mod cli;
-pub use cli::HeraArgsExt;
+pub use cli::{HeraArgsExt, P2PArgs};
+
+mod rpc;
+pub use rpc::{
+    DerivedIdPair, ExecutingDescriptor, HeraAdminApiServer, HeraAdminRpc, HeraApiServer,
+    HeraP2PApiServer, HeraRpc, SupervisorApiServer, SupervisorBlockId, SupervisorRpc,
+    SupervisorSafetyLevel, HERA_VERSION,
+};
+
+mod status;
+pub use status::SyncStatus;
+
+mod output;
+pub use output::{output_root, OutputFetcher, OutputResponse, L2_TO_L1_MESSAGE_PASSER};
 
 mod validator;
-pub use validator::AttributesValidator;
+pub use validator::{
+    AttributesValidator, EngineApiValidator, MultiValidator, QuorumPolicy, SampledValidator,
+    TrustedValidator, ValidationTimeout,
+};
+
+mod engine;
+pub use engine::{EngineClient, PayloadBodiesFetcher};
+
+mod engine_controller;
+pub use engine_controller::EngineController;
+
+mod circuit_breaker;
+pub use circuit_breaker::CircuitBreaker;
+
+mod altda;
+pub use altda::{
+    AltDa, ChallengeStatus, ChallengeTracker, DaClient, DaCommitment, ALTDA_DERIVATION_VERSION,
+    DEFAULT_CHALLENGE_WINDOW, DEFAULT_RESOLVE_WINDOW,
+};
+
+mod data_source;
+pub use data_source::{DaMode, HeraDataIter, HeraDataSource};
+
+mod sequencer;
+pub use sequencer::{
+    select_origin, SequencerCommand, SequencerControl, SequencerDriver, DEFAULT_SEQUENCER_L1_CONFS,
+};
+
+mod tx_manager;
+pub use tx_manager::{
+    TxFees, TxManager, DEFAULT_FEE_BUMP_PERCENT, DEFAULT_MAX_RESUBMISSIONS,
+    DEFAULT_RESUBMISSION_TIMEOUT,
+};
+
+mod batcher;
+pub use batcher::{
+    batcher_tx_data, single_batch, BatchSubmitter, DEFAULT_BATCHER_POLL_INTERVAL,
+    DEFAULT_MAX_CHANNEL_DURATION, DEFAULT_SUB_SAFETY_MARGIN,
+};
+
+mod proposer;
+pub use proposer::{
+    first_game_block, OutputProposer, ProposalTarget, DEFAULT_PROPOSAL_INTERVAL,
+    DEFAULT_PROPOSER_POLL_INTERVAL,
+};
 
 mod pipeline;
 pub use pipeline::{new_rollup_pipeline, RollupPipeline};
 
+mod prove;
+pub use prove::{generate_prestate, BootInfo, PrestateWitness};
+
+mod dump;
+pub use dump::{dump_derivation, DumpedAttributes};
+
 mod telemetry;
-pub use telemetry::init_telemetry_stack;
+pub use telemetry::{
+    check_log_filter, init_telemetry_stack, reset_log_filter, set_log_filter, shutdown_telemetry,
+    LogConfig, LogFormat, LogRotation, MetricsStyle, OtlpConfig, DEFAULT_OTLP_FILTER,
+    DEFAULT_OTLP_SERVICE_NAME,
+};
+
+mod drops;
+pub(crate) use drops::SharedChannelTracker;
+pub use drops::{record_drop, ChannelTracker, DropKind, DropReason};
+
+mod derivation_metrics;
+pub(crate) use derivation_metrics::DerivationMetrics;
+
+mod span_batch;
+pub use span_batch::SpanBatchConfig;
+pub(crate) use span_batch::{SpanBatchTracker, SpanPosition};
+
+mod reload;
+pub use reload::{ConfigReloader, ReloadableConfig, ReloadableValidator};
+
+mod metrics_compat;
+pub use metrics_compat::{
+    op_node_metric_key, op_node_metric_name, OpNodeCompatRecorder, OpNodeMetric, HERA_ONLY_METRICS,
+    OP_NODE_METRICS,
+};
 
 /// The identifier of the Hera Execution Extension.
 pub const HERA_EXEX_ID: &str = "hera";
@@ -0,0 +1,413 @@
+//! op-node compatible metric names
+
+use metrics::{
+    Counter, Gauge, Histogram, Key, KeyName, Label, Metadata, Recorder, SharedString, Unit,
+};
+
+/// The prefix of all metrics emitted by Hera.
+pub const HERA_METRICS_PREFIX: &str = "hera_";
+
+/// The prefix of all metrics emitted by op-node (namespace `op_node`, subsystem `default`).
+pub const OP_NODE_METRICS_PREFIX: &str = "op_node_default_";
+
+/// The default histogram buckets used by op-node.
+///
+/// These are the default buckets of the Prometheus Go client (`prometheus.DefBuckets`).
+pub const OP_NODE_HISTOGRAM_BUCKETS: &[f64] =
+    &[0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+/// The op-node metric a Hera metric, or one of its series, is exported as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OpNodeMetric {
+    /// The Hera metric name.
+    pub hera: &'static str,
+    /// The Hera label selecting the series, which is replaced by the op-node labels.
+    pub series: Option<(&'static str, &'static str)>,
+    /// The op-node metric name.
+    pub name: &'static str,
+    /// The labels op-node sets on the series.
+    pub labels: &'static [(&'static str, &'static str)],
+}
+
+impl OpNodeMetric {
+    /// Exports the given Hera metric under the given op-node name.
+    const fn renamed(hera: &'static str, name: &'static str) -> Self {
+        Self { hera, series: None, name, labels: &[] }
+    }
+
+    /// Exports the given Hera metric as the op-node `refs_number` series of the given
+    /// `layer` and `type`.
+    const fn ref_number(
+        hera: &'static str,
+        series: Option<(&'static str, &'static str)>,
+        labels: &'static [(&'static str, &'static str)],
+    ) -> Self {
+        Self { hera, series, name: "op_node_default_refs_number", labels }
+    }
+
+    /// Returns `true` if the given Hera metric series is exported as this metric.
+    fn matches(&self, key: &Key) -> bool {
+        self.hera == key.name() &&
+            self.series.map_or(true, |(label, value)| {
+                key.labels().any(|l| l.key() == label && l.value() == value)
+            })
+    }
+}
+
+/// The Hera metrics with an op-node equivalent, and the op-node names and labels they
+/// are exported with.
+pub const OP_NODE_METRICS: &[OpNodeMetric] = &[
+    OpNodeMetric::renamed("hera_up", "op_node_default_up"),
+    OpNodeMetric::renamed("hera_p2p_peers_connected", "op_node_default_peer_count"),
+    OpNodeMetric::renamed("hera_derivation_batches_total", "op_node_default_derived_batches_total"),
+    OpNodeMetric::renamed("hera_l1_reorg_depth", "op_node_default_l1_reorg_depth"),
+    OpNodeMetric::renamed("hera_unsafe_buffer_size", "op_node_default_unsafe_payloads_buffer_len"),
+    OpNodeMetric::renamed(
+        "hera_sequencer_failures_total",
+        "op_node_default_sequencing_errors_total",
+    ),
+    OpNodeMetric::ref_number(
+        "hera_derivation_l1_origin",
+        None,
+        &[("layer", "l1"), ("type", "l1_derived")],
+    ),
+    OpNodeMetric::ref_number(
+        "hera_exex_l1_finalized",
+        None,
+        &[("layer", "l1"), ("type", "l1_finalized")],
+    ),
+    OpNodeMetric::ref_number(
+        "hera_engine_head",
+        Some(("head", "unsafe")),
+        &[("layer", "l2"), ("type", "l2_unsafe")],
+    ),
+    OpNodeMetric::ref_number(
+        "hera_engine_head",
+        Some(("head", "safe")),
+        &[("layer", "l2"), ("type", "l2_safe")],
+    ),
+    OpNodeMetric::ref_number(
+        "hera_engine_head",
+        Some(("head", "finalized")),
+        &[("layer", "l2"), ("type", "l2_finalized")],
+    ),
+    OpNodeMetric::ref_number(
+        "hera_finalized_l2_block",
+        None,
+        &[("layer", "l2"), ("type", "l2_finalized")],
+    ),
+];
+
+/// The Hera metrics without an op-node equivalent, which are exported with their
+/// [HERA_METRICS_PREFIX] replaced by the [OP_NODE_METRICS_PREFIX].
+pub const HERA_ONLY_METRICS: &[&str] = &[
+    "hera_batcher_blobs_total",
+    "hera_batcher_blocks_total",
+    "hera_batcher_channels_total",
+    "hera_batcher_failures_total",
+    "hera_batcher_frames_total",
+    "hera_batcher_pending_blocks",
+    "hera_batcher_txs_total",
+    "hera_beacon_events_total",
+    "hera_blob_archive_requests_total",
+    "hera_blob_cache_pruned_blocks_total",
+    "hera_blob_cache_size_bytes",
+    "hera_blob_verification_failures_total",
+    "hera_chain_store_blocks",
+    "hera_chain_store_pruned_blocks_total",
+    "hera_deposits_only_blocks_total",
+    "hera_deposits_only_mode",
+    "hera_derivation_attributes_per_second",
+    "hera_derivation_attributes_total",
+    "hera_derivation_channels_opened_total",
+    "hera_derivation_channels_timed_out_total",
+    "hera_derivation_dropped_total",
+    "hera_derivation_frames_total",
+    "hera_derivation_l1_origin_lag_blocks",
+    "hera_derivation_origin_advances_total",
+    "hera_derivation_stalls_total",
+    "hera_derivation_steps_total",
+    "hera_discovery_bytes_received_total",
+    "hera_discovery_bytes_sent_total",
+    "hera_discovery_connected_peers",
+    "hera_discovery_enr_publishes_total",
+    "hera_discovery_enr_seq",
+    "hera_discovery_enr_updates_total",
+    "hera_discovery_lookup_interval_seconds",
+    "hera_discovery_lookup_timeouts_total",
+    "hera_discovery_lookups_total",
+    "hera_discovery_opstack_peers",
+    "hera_discovery_peers_found_total",
+    "hera_discovery_rejected_nodes_total",
+    "hera_discovery_rejected_peers",
+    "hera_discovery_sessions_total",
+    "hera_discovery_table_size",
+    "hera_engine_api_failovers_total",
+    "hera_engine_api_healthy",
+    "hera_engine_api_secondary_healthy",
+    "hera_engine_invalid_payloads_total",
+    "hera_exex_beacon_l1_finalized",
+    "hera_exex_finalized_l1_reorgs_total",
+    "hera_exex_l1_lag_blocks",
+    "hera_exex_l1_pickup_latency_seconds",
+    "hera_exex_notification_backlog",
+    "hera_gossip_banned_peers_total",
+    "hera_gossip_blocks_received_total",
+    "hera_gossip_decode_failures_total",
+    "hera_gossip_equivocations_total",
+    "hera_gossip_invalid_payloads_total",
+    "hera_gossip_invalid_signatures_total",
+    "hera_gossip_oversized_messages_total",
+    "hera_gossip_signature_batch_size",
+    "hera_gossip_signature_cache_hits_total",
+    "hera_gossip_signature_cache_misses_total",
+    "hera_gossip_validation_cache_hits_total",
+    "hera_interop_cross_safe_l2",
+    "hera_interop_cross_unsafe_l2",
+    "hera_interop_invalid_messages_total",
+    "hera_l1_cache_blocks",
+    "hera_l1_cache_evictions_total",
+    "hera_l1_cache_lookups_total",
+    "hera_l1_reorgs_total",
+    "hera_l1_rpc_retries_total",
+    "hera_p2p_connections_denied_total",
+    "hera_p2p_dial_failures_total",
+    "hera_p2p_nat_public",
+    "hera_prefetch_failures_total",
+    "hera_prefetched_l1_blocks_total",
+    "hera_proposer_failures_total",
+    "hera_proposer_last_proposed_block",
+    "hera_proposer_proposals_total",
+    "hera_sequencer_active",
+    "hera_sequencer_blocks_total",
+    "hera_span_batch_blocks",
+    "hera_span_batches_invalid_total",
+    "hera_span_batches_rejected_total",
+    "hera_span_batches_total",
+    "hera_span_batches_validated_total",
+    "hera_sync_requests_served_total",
+    "hera_system_config_mismatches_total",
+    "hera_system_config_updates_total",
+    "hera_task_crashes_total",
+    "hera_txmgr_fee_bumps_total",
+    "hera_txmgr_included_total",
+    "hera_txmgr_reverted_total",
+    "hera_txmgr_sent_total",
+    "hera_unsafe_blocks_dropped_total",
+    "hera_unsafe_buffer_dropped_total",
+    "hera_unsafe_signer_rotations_total",
+    "hera_validation_skipped_total",
+    "hera_validation_timeouts_total",
+    "hera_validator_disagreements_total",
+    "hera_validator_latency_seconds",
+];
+
+/// Returns the op-node name of the given Hera metric name.
+///
+/// The metrics of [OP_NODE_METRICS] take their op-node name, and the other Hera
+/// metrics have their [HERA_METRICS_PREFIX] replaced by the [OP_NODE_METRICS_PREFIX].
+/// Metrics without the Hera prefix are exported unchanged.
+pub fn op_node_metric_name(name: &str) -> String {
+    if let Some(metric) = OP_NODE_METRICS.iter().find(|metric| metric.hera == name) {
+        return metric.name.to_string();
+    }
+
+    match name.strip_prefix(HERA_METRICS_PREFIX) {
+        Some(rest) => format!("{}{}", OP_NODE_METRICS_PREFIX, rest),
+        None => name.to_string(),
+    }
+}
+
+/// Returns the op-node equivalent of the given Hera metric series: its op-node name,
+/// and its labels with the ones set by op-node in place of the selecting Hera label.
+pub fn op_node_metric_key(key: &Key) -> Key {
+    let Some(metric) = OP_NODE_METRICS.iter().find(|metric| metric.matches(key)) else {
+        return Key::from_parts(
+            op_node_metric_name(key.name()),
+            key.labels().cloned().collect::<Vec<_>>(),
+        );
+    };
+
+    let labels = key
+        .labels()
+        .filter(|label| metric.series.map_or(true, |(name, _)| label.key() != name))
+        .cloned()
+        .chain(metric.labels.iter().map(|&(name, value)| Label::new(name, value)))
+        .collect::<Vec<_>>();
+    Key::from_parts(metric.name, labels)
+}
+
+/// A [Recorder] that renames every metric to its op-node equivalent
+/// before forwarding it to the inner recorder.
+///
+/// This allows existing op-node Grafana dashboards and alert rules
+/// to keep working when swapping op-node for Hera.
+#[derive(Debug)]
+pub struct OpNodeCompatRecorder<R> {
+    /// The recorder that receives the renamed metrics.
+    inner: R,
+}
+
+impl<R> OpNodeCompatRecorder<R> {
+    /// Creates a new [OpNodeCompatRecorder] wrapping the given recorder.
+    pub const fn new(inner: R) -> Self {
+        Self { inner }
+    }
+
+    /// Returns the op-node equivalent of the given [KeyName].
+    fn key_name(name: KeyName) -> KeyName {
+        KeyName::from(op_node_metric_name(name.as_str()))
+    }
+
+    /// Returns the op-node equivalent of the given [Key], see [op_node_metric_key].
+    fn key(key: &Key) -> Key {
+        op_node_metric_key(key)
+    }
+}
+
+impl<R: Recorder> Recorder for OpNodeCompatRecorder<R> {
+    fn describe_counter(&self, key: KeyName, unit: Option<Unit>, description: SharedString) {
+        self.inner.describe_counter(Self::key_name(key), unit, description)
+    }
+
+    fn describe_gauge(&self, key: KeyName, unit: Option<Unit>, description: SharedString) {
+        self.inner.describe_gauge(Self::key_name(key), unit, description)
+    }
+
+    fn describe_histogram(&self, key: KeyName, unit: Option<Unit>, description: SharedString) {
+        self.inner.describe_histogram(Self::key_name(key), unit, description)
+    }
+
+    fn register_counter(&self, key: &Key, metadata: &Metadata<'_>) -> Counter {
+        self.inner.register_counter(&Self::key(key), metadata)
+    }
+
+    fn register_gauge(&self, key: &Key, metadata: &Metadata<'_>) -> Gauge {
+        self.inner.register_gauge(&Self::key(key), metadata)
+    }
+
+    fn register_histogram(&self, key: &Key, metadata: &Metadata<'_>) -> Histogram {
+        self.inner.register_histogram(&Self::key(key), metadata)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_op_node_metric_name_prefix_swap() {
+        assert_eq!(op_node_metric_name("hera_up"), "op_node_default_up");
+        assert_eq!(op_node_metric_name("hera_refs_number"), "op_node_default_refs_number");
+    }
+
+    #[test]
+    fn test_op_node_metric_name_mapped() {
+        assert_eq!(op_node_metric_name("hera_p2p_peers_connected"), "op_node_default_peer_count");
+        assert_eq!(
+            op_node_metric_name("hera_derivation_batches_total"),
+            "op_node_default_derived_batches_total"
+        );
+    }
+
+    #[test]
+    fn test_op_node_metric_key_labels() {
+        let key = Key::from_parts("hera_engine_head", vec![Label::new("head", "safe")]);
+        let expected = Key::from_parts(
+            "op_node_default_refs_number",
+            vec![Label::new("layer", "l2"), Label::new("type", "l2_safe")],
+        );
+        assert_eq!(op_node_metric_key(&key), expected);
+
+        let key = Key::from_parts("hera_derivation_steps_total", vec![Label::new("result", "ok")]);
+        let expected = Key::from_parts(
+            "op_node_default_derivation_steps_total",
+            vec![Label::new("result", "ok")],
+        );
+        assert_eq!(op_node_metric_key(&key), expected);
+    }
+
+    #[test]
+    fn test_op_node_metrics_mapping() {
+        let mapping: Vec<_> = OP_NODE_METRICS
+            .iter()
+            .map(|metric| {
+                let series = metric.series.map(|(label, value)| vec![Label::new(label, value)]);
+                let key = Key::from_parts(metric.hera, series.unwrap_or_default());
+                let key = op_node_metric_key(&key);
+                let labels: Vec<_> =
+                    key.labels().map(|l| format!("{}={}", l.key(), l.value())).collect();
+                format!("{} -> {}{{{}}}", metric.hera, key.name(), labels.join(","))
+            })
+            .collect();
+        assert_eq!(
+            mapping,
+            [
+                "hera_up -> op_node_default_up{}",
+                "hera_p2p_peers_connected -> op_node_default_peer_count{}",
+                "hera_derivation_batches_total -> op_node_default_derived_batches_total{}",
+                "hera_l1_reorg_depth -> op_node_default_l1_reorg_depth{}",
+                "hera_unsafe_buffer_size -> op_node_default_unsafe_payloads_buffer_len{}",
+                "hera_sequencer_failures_total -> op_node_default_sequencing_errors_total{}",
+                "hera_derivation_l1_origin -> op_node_default_refs_number{layer=l1,type=l1_derived}",
+                "hera_exex_l1_finalized -> op_node_default_refs_number{layer=l1,type=l1_finalized}",
+                "hera_engine_head -> op_node_default_refs_number{layer=l2,type=l2_unsafe}",
+                "hera_engine_head -> op_node_default_refs_number{layer=l2,type=l2_safe}",
+                "hera_engine_head -> op_node_default_refs_number{layer=l2,type=l2_finalized}",
+                "hera_finalized_l2_block -> op_node_default_refs_number{layer=l2,type=l2_finalized}",
+            ]
+        );
+
+        for name in HERA_ONLY_METRICS {
+            let expected = name.replacen(HERA_METRICS_PREFIX, OP_NODE_METRICS_PREFIX, 1);
+            assert_eq!(op_node_metric_name(name), expected);
+        }
+    }
+
+    /// Collects the `hera_*` string literals of the Rust sources in the given directory.
+    fn hera_metric_literals(dir: &std::path::Path, names: &mut Vec<String>) {
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                hera_metric_literals(&path, names);
+            } else if path.extension().is_some_and(|ext| ext == "rs") &&
+                !path.ends_with("metrics_compat.rs")
+            {
+                let source = std::fs::read_to_string(&path).unwrap();
+                for (_, rest) in
+                    source.match_indices("\"hera_").map(|(i, _)| source.split_at(i + 1))
+                {
+                    let name: String = rest
+                        .chars()
+                        .take_while(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || *c == '_')
+                        .collect();
+                    if rest[name.len()..].starts_with('"') {
+                        names.push(name);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_every_metric_is_mapped() {
+        let root = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("../..");
+        let mut names = Vec::new();
+        hera_metric_literals(&root.join("crates"), &mut names);
+        hera_metric_literals(&root.join("bin"), &mut names);
+        assert!(names.contains(&"hera_up".to_string()));
+        for name in names {
+            assert!(
+                OP_NODE_METRICS.iter().any(|metric| metric.hera == name) ||
+                    HERA_ONLY_METRICS.contains(&name.as_str()),
+                "{} is not mapped to an op-node metric",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn test_op_node_metric_name_foreign_metric() {
+        assert_eq!(op_node_metric_name("reth_sync_checkpoint"), "reth_sync_checkpoint");
+    }
+}
@@ -0,0 +1,263 @@
+//! Derivation of several chains in one process
+
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use alloy::primitives::Address;
+use eyre::{bail, eyre, Context, Result};
+use kona_derive::online::AlloyL2ChainProvider;
+use kona_providers::{LayeredBlobProvider, OnlineChainProvider};
+use serde::Deserialize;
+use superchain_registry::RollupConfig;
+use tokio::{sync::watch, task::JoinSet};
+use tracing::{info, info_span, Instrument};
+
+use crate::{chain_name, Driver, DriverEvents, HeraArgsExt, StandaloneContext, SyncStatus};
+
+/// The chains of a multi-chain node, loaded from a TOML file listing them, e.g.:
+///
+/// ```toml
+/// [[chain]]
+/// chain = "op-mainnet"
+/// l2-rpc-url = "http://localhost:9545"
+///
+/// [[chain]]
+/// chain = "base"
+/// l2-rpc-url = "http://localhost:10545"
+/// rpc-port = 9546
+/// ```
+///
+/// The settings of each chain replace the command line flags of the same name, and
+/// every other flag applies to all the chains.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ChainsConfig {
+    /// The chains, the first one being the chain of the p2p network identity.
+    #[serde(rename = "chain", default)]
+    pub chains: Vec<ChainConfig>,
+}
+
+/// The settings of a single chain of a [ChainsConfig].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct ChainConfig {
+    /// The name or chain ID of the chain in the superchain registry.
+    pub chain: Option<String>,
+    /// An op-node `rollup.json` file, for chains outside the superchain registry.
+    pub rollup_config: Option<PathBuf>,
+    /// The RPC URL of the L2 execution client of the chain.
+    pub l2_rpc_url: String,
+    /// The engine API URL of the L2 execution client of the chain.
+    pub l2_engine_api_url: Option<String>,
+    /// The JWT secret file of the engine API of the chain.
+    pub l2_engine_jwt_secret: Option<PathBuf>,
+    /// The unsafe block signer of the chain, whose blocks are only gossiped if set.
+    /// Defaults to `--p2p.unsafe-block-signer` for the first chain.
+    pub unsafe_block_signer: Option<String>,
+    /// The port to serve the `optimism_*` RPC methods of the chain on.
+    pub rpc_port: Option<u16>,
+    /// The file recording the latest validated L2 block of the chain.
+    pub checkpoint_file: Option<PathBuf>,
+}
+
+/// A chain of a multi-chain node, with its node arguments and rollup config.
+#[derive(Debug, Clone)]
+pub struct ChainSpec {
+    /// The name of the chain, for logs.
+    pub name: String,
+    /// The node arguments of the chain.
+    pub args: HeraArgsExt,
+    /// The rollup config of the chain.
+    pub cfg: Arc<RollupConfig>,
+}
+
+impl ChainsConfig {
+    /// Loads the chains from the given TOML file.
+    pub fn load(path: &Path) -> Result<Self> {
+        let file = std::fs::read_to_string(path).wrap_err("Failed to open chains file")?;
+        toml::from_str(&file).wrap_err("Failed to read chains file")
+    }
+
+    /// Resolves the node arguments and rollup config of each chain, on top of the given
+    /// shared arguments.
+    pub fn resolve(&self, shared: &HeraArgsExt) -> Result<Vec<ChainSpec>> {
+        if self.chains.is_empty() {
+            bail!("No chain listed in the chains file");
+        }
+        let mut ids = HashSet::new();
+        self.chains
+            .iter()
+            .enumerate()
+            .map(|(i, chain)| {
+                let mut args = chain.args(shared)?;
+                if i > 0 && chain.unsafe_block_signer.is_none() {
+                    // The unsafe block signer flag only applies to the first chain.
+                    args.p2p.unsafe_block_signer = None;
+                }
+                let cfg = args.rollup_config()?;
+                if !ids.insert(cfg.l2_chain_id) {
+                    bail!("Chain ID {} is listed twice", cfg.l2_chain_id);
+                }
+                let name =
+                    chain_name(cfg.l2_chain_id).unwrap_or_else(|| cfg.l2_chain_id.to_string());
+                Ok(ChainSpec { name, args, cfg })
+            })
+            .collect()
+    }
+}
+
+impl ChainConfig {
+    /// Returns the node arguments of the chain: the given shared arguments, with the
+    /// settings of the chain.
+    ///
+    /// The RPC port and the checkpoint file are never shared, since the chains would
+    /// conflict over them.
+    pub fn args(&self, shared: &HeraArgsExt) -> Result<HeraArgsExt> {
+        if self.chain.is_none() && self.rollup_config.is_none() {
+            bail!("Chain entries need a chain name or a rollup config file");
+        }
+        let mut args = shared.clone();
+        args.chain = self.chain.clone();
+        args.l2_config_file = self.rollup_config.clone();
+        args.l2_rpc_url = self.l2_rpc_url.parse().wrap_err("Invalid l2-rpc-url")?;
+        if let Some(url) = &self.l2_engine_api_url {
+            args.l2_engine_api_url = Some(url.parse().wrap_err("Invalid l2-engine-api-url")?);
+        }
+        if let Some(path) = &self.l2_engine_jwt_secret {
+            args.l2_engine_jwt_secret = Some(path.clone());
+        }
+        if let Some(signer) = &self.unsafe_block_signer {
+            let signer = signer.parse::<Address>();
+            args.p2p.unsafe_block_signer =
+                Some(signer.map_err(|e| eyre!("Invalid unsafe-block-signer: {}", e))?);
+        }
+        args.rpc_port = self.rpc_port;
+        args.checkpoint_file = self.checkpoint_file.clone();
+        Ok(args)
+    }
+}
+
+/// The type of the standalone drivers of a [MultiDriver].
+type StandaloneDriver =
+    Driver<StandaloneContext, OnlineChainProvider, LayeredBlobProvider, AlloyL2ChainProvider>;
+
+/// Runs the standalone drivers of several chains, which follow the L1 chain of a shared
+/// [StandaloneContext].
+#[derive(Debug)]
+pub struct MultiDriver {
+    /// The drivers, by chain name.
+    drivers: Vec<(String, StandaloneDriver)>,
+}
+
+impl MultiDriver {
+    /// Creates the standalone drivers of the given chains, following the L1 chain of the
+    /// given shared arguments.
+    pub async fn standalone(shared: &HeraArgsExt, chains: Vec<ChainSpec>) -> Result<Self> {
+        let ctx = StandaloneContext::from_args(shared);
+        let mut drivers = Vec::with_capacity(chains.len());
+        for ChainSpec { name, args, cfg } in chains {
+            let driver = Driver::standalone_with_context(args, cfg, ctx.clone())
+                .await
+                .wrap_err_with(|| format!("Failed to create the driver of {}", name))?;
+            drivers.push((name, driver));
+        }
+        Ok(Self { drivers })
+    }
+
+    /// Returns a receiver of the sync status of the given chain, if it is run.
+    pub fn sync_status(&self, name: &str) -> Option<watch::Receiver<SyncStatus>> {
+        self.drivers.iter().find(|(n, _)| n == name).map(|(_, driver)| driver.sync_status())
+    }
+
+    /// Returns the bus the events of the given chain are published on, if it is run.
+    pub fn events(&self, name: &str) -> Option<DriverEvents> {
+        self.drivers.iter().find(|(n, _)| n == name).map(|(_, driver)| driver.events())
+    }
+
+    /// Runs the drivers until one of them returns, in which case the others are stopped.
+    pub async fn start(self) -> Result<()> {
+        let mut tasks = JoinSet::new();
+        for (name, driver) in self.drivers {
+            info!("Starting the driver of {}", name);
+            let span = info_span!("chain", %name);
+            tasks.spawn(
+                async move {
+                    driver.start().await.wrap_err_with(|| format!("Driver of {} failed", name))
+                }
+                .instrument(span),
+            );
+        }
+        match tasks.join_next().await {
+            Some(result) => result.map_err(|e| eyre!("Driver task failed: {}", e))?,
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    #[derive(Parser)]
+    struct Cli {
+        #[clap(flatten)]
+        hera: HeraArgsExt,
+    }
+
+    #[test]
+    fn test_parse_chains_config() {
+        let config: ChainsConfig = toml::from_str(
+            r#"
+            [[chain]]
+            chain = "op-mainnet"
+            l2-rpc-url = "http://localhost:9545"
+
+            [[chain]]
+            rollup-config = "devnet.json"
+            l2-rpc-url = "http://localhost:10545"
+            unsafe-block-signer = "0xAf6E19BE0F9cE7f8afd49a1824851023A8249e8a"
+            rpc-port = 9546
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.chains.len(), 2);
+        assert_eq!(config.chains[0].chain.as_deref(), Some("op-mainnet"));
+        assert_eq!(config.chains[1].rollup_config, Some(PathBuf::from("devnet.json")));
+        assert_eq!(config.chains[1].rpc_port, Some(9546));
+
+        assert!(toml::from_str::<ChainsConfig>("[[chain]]\nl2-rpc = \"x\"").is_err());
+    }
+
+    #[test]
+    fn test_chain_args() {
+        let shared = Cli::parse_from([
+            "hera",
+            "--hera.rpc.port",
+            "9545",
+            "--hera.checkpoint-file",
+            "checkpoint.json",
+        ])
+        .hera;
+        let chain = ChainConfig {
+            chain: Some("base".to_string()),
+            l2_rpc_url: "http://localhost:10545".to_string(),
+            unsafe_block_signer: Some("0xAf6E19BE0F9cE7f8afd49a1824851023A8249e8a".to_string()),
+            ..Default::default()
+        };
+        let args = chain.args(&shared).unwrap();
+        assert_eq!(args.chain.as_deref(), Some("base"));
+        assert_eq!(args.l2_rpc_url.as_str(), "http://localhost:10545/");
+        assert!(args.p2p.unsafe_block_signer.is_some());
+        assert_eq!(args.rpc_port, None);
+        assert_eq!(args.checkpoint_file, None);
+        assert_eq!(args.l1_rpc_url, shared.l1_rpc_url);
+
+        let chain =
+            ChainConfig { l2_rpc_url: "http://localhost".to_string(), ..Default::default() };
+        assert!(chain.args(&shared).is_err());
+    }
+}
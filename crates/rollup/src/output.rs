@@ -0,0 +1,144 @@
+//! L2 output roots
+
+use std::sync::Arc;
+
+use alloy::{
+    primitives::{address, keccak256, Address, B256},
+    providers::{network::primitives::BlockTransactionsKind, Provider, ReqwestProvider},
+};
+use eyre::{eyre, Result};
+use kona_derive::{online::AlloyL2ChainProvider, traits::L2ChainProvider};
+use kona_primitives::L2BlockInfo;
+use serde::{Deserialize, Serialize};
+use superchain_registry::RollupConfig;
+use tokio::sync::Mutex;
+use url::Url;
+
+use crate::SyncStatus;
+
+/// The address of the `L2ToL1MessagePasser` predeploy, whose storage root is
+/// committed to in the output roots.
+pub const L2_TO_L1_MESSAGE_PASSER: Address = address!("4200000000000000000000000000000000000016");
+
+/// The version of the output roots computed by [output_root].
+pub const OUTPUT_VERSION_V0: B256 = B256::ZERO;
+
+/// The output of an L2 block, as returned by `optimism_outputAtBlock`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OutputResponse {
+    /// The version of the output root.
+    pub version: B256,
+    /// The output root of the block.
+    pub output_root: B256,
+    /// The block the output root commits to.
+    pub block_ref: L2BlockInfo,
+    /// The storage root of the `L2ToL1MessagePasser` at the block.
+    pub withdrawal_storage_root: B256,
+    /// The state root of the block.
+    pub state_root: B256,
+    /// The sync status of the node when the output was computed.
+    pub sync_status: SyncStatus,
+}
+
+/// Computes the V0 output root of an L2 block from its state root, the storage root
+/// of the `L2ToL1MessagePasser` and its hash.
+pub fn output_root(state_root: B256, withdrawal_storage_root: B256, block_hash: B256) -> B256 {
+    let mut preimage = [0u8; 128];
+    preimage[..32].copy_from_slice(OUTPUT_VERSION_V0.as_slice());
+    preimage[32..64].copy_from_slice(state_root.as_slice());
+    preimage[64..96].copy_from_slice(withdrawal_storage_root.as_slice());
+    preimage[96..].copy_from_slice(block_hash.as_slice());
+    keccak256(preimage)
+}
+
+/// Computes the outputs of L2 blocks from an L2 execution client.
+#[derive(Debug, Clone)]
+pub struct OutputFetcher {
+    /// The L2 RPC provider.
+    provider: ReqwestProvider,
+    /// The L2 chain provider, resolving the L1 origins of the blocks.
+    l2_chain_provider: Arc<Mutex<AlloyL2ChainProvider>>,
+}
+
+impl OutputFetcher {
+    /// Creates a new [OutputFetcher] from the provided L2 RPC [Url].
+    pub fn new_http(url: Url, cfg: Arc<RollupConfig>) -> Self {
+        let l2_chain_provider = AlloyL2ChainProvider::new_http(url.clone(), cfg);
+        Self {
+            provider: ReqwestProvider::new_http(url),
+            l2_chain_provider: Arc::new(Mutex::new(l2_chain_provider)),
+        }
+    }
+
+    /// Computes the output of the given L2 block.
+    pub async fn output_at_block(
+        &self,
+        number: u64,
+        sync_status: SyncStatus,
+    ) -> Result<OutputResponse> {
+        let block_ref =
+            self.l2_chain_provider.lock().await.l2_block_info_by_number(number).await.map_err(
+                |e| eyre!("Failed to fetch L2 block info for block {}: {:?}", number, e),
+            )?;
+        let block = self
+            .provider
+            .get_block(number.into(), BlockTransactionsKind::Hashes)
+            .await?
+            .ok_or(eyre!("Block {} not found", number))?;
+        let proof = self
+            .provider
+            .get_proof(L2_TO_L1_MESSAGE_PASSER, vec![])
+            .block_id(number.into())
+            .await?;
+
+        let state_root = block.header.state_root;
+        let withdrawal_storage_root = proof.storage_hash;
+        Ok(OutputResponse {
+            version: OUTPUT_VERSION_V0,
+            output_root: output_root(
+                state_root,
+                withdrawal_storage_root,
+                block_ref.block_info.hash,
+            ),
+            block_ref,
+            withdrawal_storage_root,
+            state_root,
+            sync_status,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_output_root() {
+        let root = output_root(B256::repeat_byte(1), B256::repeat_byte(2), B256::repeat_byte(3));
+        let mut preimage = vec![0u8; 32];
+        for byte in 1..=3 {
+            preimage.extend_from_slice(&[byte; 32]);
+        }
+        assert_eq!(root, keccak256(preimage));
+        assert_ne!(root, output_root(B256::ZERO, B256::repeat_byte(2), B256::repeat_byte(3)));
+    }
+
+    #[test]
+    fn test_output_response_fields() {
+        let response = OutputResponse {
+            version: OUTPUT_VERSION_V0,
+            output_root: B256::ZERO,
+            block_ref: Default::default(),
+            withdrawal_storage_root: B256::ZERO,
+            state_root: B256::ZERO,
+            sync_status: Default::default(),
+        };
+        let json = serde_json::to_value(&response).unwrap();
+        for field in ["outputRoot", "blockRef", "withdrawalStorageRoot", "stateRoot", "syncStatus"]
+        {
+            assert!(json.get(field).is_some(), "missing {}", field);
+        }
+        assert!(json["syncStatus"].get("safe_l2").is_some());
+    }
+}
@@ -0,0 +1,128 @@
+//! Derivation step pacing
+
+use std::time::Duration;
+
+use tokio::time::{sleep, Instant};
+
+/// The default number of pipeline steps that can run back-to-back near the L1 tip.
+pub const DEFAULT_STEP_BURST: u32 = 64;
+
+/// The default distance in L1 blocks from the L1 head under which the pipeline
+/// is considered near the tip.
+pub const DEFAULT_TIP_DISTANCE: u64 = 8;
+
+/// Limits the rate of derivation pipeline steps, and thus the RPC load they cause.
+///
+/// While catching up, steps are spaced evenly at the configured rate. Near the L1 tip,
+/// up to a burst of steps can run back-to-back, so that new L1 blocks are derived
+/// without delay.
+#[derive(Debug)]
+pub struct StepPacer {
+    /// The sustained number of steps per second.
+    rate: f64,
+    /// The number of steps that can run back-to-back near the tip.
+    burst: u32,
+    /// The distance in L1 blocks from the head under which the pipeline is near the tip.
+    tip_distance: u64,
+    /// The steps that can run without waiting. Negative while steps are delayed.
+    tokens: f64,
+    /// The last time the tokens were refilled.
+    refilled_at: Instant,
+}
+
+impl StepPacer {
+    /// Creates a new [StepPacer] allowing `steps_per_second` pipeline steps per second.
+    pub fn new(steps_per_second: u32) -> Self {
+        Self {
+            rate: steps_per_second.max(1) as f64,
+            burst: DEFAULT_STEP_BURST,
+            tip_distance: DEFAULT_TIP_DISTANCE,
+            tokens: 1.0,
+            refilled_at: Instant::now(),
+        }
+    }
+
+    /// Sets the number of steps that can run back-to-back near the tip.
+    pub fn with_burst(mut self, burst: u32) -> Self {
+        self.burst = burst.max(1);
+        self
+    }
+
+    /// Sets the distance in L1 blocks from the head under which the pipeline is near the tip.
+    pub const fn with_tip_distance(mut self, blocks: u64) -> Self {
+        self.tip_distance = blocks;
+        self
+    }
+
+    /// Returns `true` if the given pipeline origin is close enough to the L1 head
+    /// to allow bursts. The pipeline is catching up while the head is unknown.
+    pub fn is_near_tip(&self, origin: Option<u64>, head: Option<u64>) -> bool {
+        match (origin, head) {
+            (Some(origin), Some(head)) => origin.saturating_add(self.tip_distance) >= head,
+            _ => false,
+        }
+    }
+
+    /// Waits until the next pipeline step is allowed to run.
+    pub async fn pace(&mut self, near_tip: bool) {
+        let delay = self.delay(near_tip, Instant::now());
+        if !delay.is_zero() {
+            sleep(delay).await;
+        }
+    }
+
+    /// Takes a step at `now` and returns how long to wait before running it.
+    fn delay(&mut self, near_tip: bool, now: Instant) -> Duration {
+        let capacity = if near_tip { self.burst as f64 } else { 1.0 };
+        let elapsed = now.saturating_duration_since(self.refilled_at).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(capacity);
+        self.refilled_at = now;
+
+        self.tokens -= 1.0;
+        if self.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.tokens / self.rate)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_catch_up_is_paced() {
+        let mut pacer = StepPacer::new(10);
+        let now = pacer.refilled_at;
+
+        assert_eq!(pacer.delay(false, now), Duration::ZERO);
+        assert_eq!(pacer.delay(false, now), Duration::from_millis(100));
+        assert_eq!(pacer.delay(false, now), Duration::from_millis(200));
+
+        // Idle time does not accumulate more than a single step while catching up.
+        let later = now + Duration::from_secs(10);
+        assert_eq!(pacer.delay(false, later), Duration::ZERO);
+        assert_eq!(pacer.delay(false, later), Duration::from_millis(100));
+    }
+
+    #[test]
+    fn test_burst_near_tip() {
+        let mut pacer = StepPacer::new(10).with_burst(3);
+        let now = pacer.refilled_at + Duration::from_secs(1);
+
+        for _ in 0..3 {
+            assert_eq!(pacer.delay(true, now), Duration::ZERO);
+        }
+        assert_eq!(pacer.delay(true, now), Duration::from_millis(100));
+    }
+
+    #[test]
+    fn test_is_near_tip() {
+        let pacer = StepPacer::new(10).with_tip_distance(8);
+        assert!(pacer.is_near_tip(Some(92), Some(100)));
+        assert!(!pacer.is_near_tip(Some(91), Some(100)));
+        assert!(!pacer.is_near_tip(Some(100), None));
+        assert!(!pacer.is_near_tip(None, Some(100)));
+    }
+}
@@ -3,7 +3,7 @@
 use std::{fmt::Debug, sync::Arc};
 
 use kona_derive::{
-    online::{DerivationPipeline, EthereumDataSource, PipelineBuilder},
+    online::{DerivationPipeline, PipelineBuilder},
     stages::{
         AttributesQueue, BatchQueue, ChannelBank, ChannelReader, FrameQueue, L1Retrieval,
         L1Traversal, StatefulAttributesBuilder,
@@ -12,9 +12,11 @@ use kona_derive::{
 };
 use kona_primitives::{BlockInfo, RollupConfig};
 
+use crate::HeraDataSource;
+
 /// A [FrameQueue] stage implementation that takes the outputs of the [L1Retrieval] stage and
 /// parses it into frames, using the [L1Traversal] stage to fetch block info for each frame.
-type L1FrameQueue<CP, BP> = FrameQueue<L1Retrieval<EthereumDataSource<CP, BP>, L1Traversal<CP>>>;
+type L1FrameQueue<CP, BP> = FrameQueue<L1Retrieval<HeraDataSource<CP, BP>, L1Traversal<CP>>>;
 
 /// A concrete [NextAttributes](kona_derive::traits::NextAttributes) stage implementation that
 /// accepts batches from the [BatchQueue] stage and transforms them into payload attributes.
@@ -32,21 +34,21 @@ type L1AttributesQueue<CP, BP, L2CP> = AttributesQueue<
 /// and transforms them into [L2PayloadAttributes](kona_primitives::L2PayloadAttributes).
 pub type RollupPipeline<CP, BP, L2CP> = DerivationPipeline<L1AttributesQueue<CP, BP, L2CP>, L2CP>;
 
-/// Creates a new [RollupPipeline] from the given components.
+/// Creates a new [RollupPipeline] from the given components, reading the batcher data
+/// from the given [HeraDataSource].
 #[allow(unused)]
 pub fn new_rollup_pipeline<CP, BP, L2CP>(
     cfg: Arc<RollupConfig>,
     chain_provider: CP,
-    blob_provider: BP,
     l2_chain_provider: L2CP,
     origin: BlockInfo,
+    dap: HeraDataSource<CP, BP>,
 ) -> RollupPipeline<CP, BP, L2CP>
 where
     CP: ChainProvider + Send + Sync + Clone + Debug,
     BP: BlobProvider + Send + Sync + Clone + Debug,
     L2CP: L2ChainProvider + Send + Sync + Clone + Debug,
 {
-    let dap = EthereumDataSource::new(chain_provider.clone(), blob_provider.clone(), &cfg.clone());
     let attributes = StatefulAttributesBuilder::new(
         cfg.clone(),
         l2_chain_provider.clone(),
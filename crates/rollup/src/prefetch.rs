@@ -0,0 +1,211 @@
+//! Prefetching of the L1 data ahead of derivation
+
+use std::{ops::Range, sync::Arc};
+
+use alloy::consensus::{TxEip4844Variant, TxEnvelope};
+use eyre::{eyre, Result};
+use kona_derive::traits::{BlobProvider, ChainProvider};
+use kona_primitives::{BlockInfo, IndexedBlobHash};
+use superchain_registry::RollupConfig;
+use tokio::{sync::watch, task::JoinSet};
+use tracing::{debug, trace};
+
+/// The default number of L1 blocks prefetched ahead of the derivation pipeline origin.
+pub const DEFAULT_PREFETCH_DEPTH: u64 = 8;
+
+/// Prefetches the L1 data the derivation pipeline will need next.
+///
+/// Whenever the pipeline origin advances, the headers, transactions and receipts of
+/// the next L1 blocks, and the blobs of their batcher transactions, are fetched
+/// concurrently in the background. They land in the caches that the chain and blob
+/// providers share with their clones, so that the pipeline reads them from memory
+/// instead of waiting on sequential RPC requests.
+#[derive(Debug)]
+pub struct Prefetcher {
+    /// The latest origin of the derivation pipeline.
+    origin: watch::Sender<Option<BlockInfo>>,
+}
+
+impl Prefetcher {
+    /// Spawns a task prefetching up to `depth` L1 blocks ahead of the pipeline origin,
+    /// with clones of the given providers. The task stops when the [Prefetcher] is dropped.
+    pub fn spawn<CP, BP>(
+        cfg: Arc<RollupConfig>,
+        chain_provider: CP,
+        blob_provider: BP,
+        depth: u64,
+    ) -> Self
+    where
+        CP: ChainProvider + Clone + Send + Sync + 'static,
+        BP: BlobProvider + Clone + Send + Sync + 'static,
+    {
+        let (origin, rx) = watch::channel(None);
+        tokio::spawn(prefetch(cfg, chain_provider, blob_provider, depth, rx));
+        Self { origin }
+    }
+
+    /// Notifies the prefetcher that the pipeline advanced to the given origin.
+    pub fn advance(&self, origin: &BlockInfo) {
+        self.origin.send_replace(Some(*origin));
+    }
+}
+
+/// Prefetches the L1 blocks ahead of each new origin, until the sender is dropped.
+async fn prefetch<CP, BP>(
+    cfg: Arc<RollupConfig>,
+    chain_provider: CP,
+    blob_provider: BP,
+    depth: u64,
+    mut origin: watch::Receiver<Option<BlockInfo>>,
+) where
+    CP: ChainProvider + Clone + Send + Sync + 'static,
+    BP: BlobProvider + Clone + Send + Sync + 'static,
+{
+    let mut next = 0;
+    while origin.changed().await.is_ok() {
+        let Some(current) = *origin.borrow_and_update() else {
+            continue;
+        };
+        let range = prefetch_range(current.number, next, depth);
+        if range.is_empty() {
+            continue;
+        }
+        trace!("Prefetching L1 blocks {:?}", range);
+
+        let mut tasks = JoinSet::new();
+        for number in range.clone() {
+            let (cfg, cp, bp) = (cfg.clone(), chain_provider.clone(), blob_provider.clone());
+            tasks.spawn(async move { (number, prefetch_block(&cfg, cp, bp, number).await) });
+        }
+        // Blocks that failed, usually because they are past the L1 head, are retried
+        // with the next origin.
+        next = range.end;
+        while let Some(joined) = tasks.join_next().await {
+            let Ok((number, result)) = joined else {
+                continue;
+            };
+            match result {
+                Ok(()) => metrics::counter!("hera_prefetched_l1_blocks_total").increment(1),
+                Err(err) => {
+                    debug!(?err, "Failed to prefetch L1 block {}", number);
+                    metrics::counter!("hera_prefetch_failures_total").increment(1);
+                    next = next.min(number);
+                }
+            }
+        }
+    }
+}
+
+/// Returns the range of L1 blocks to prefetch for the given origin, skipping those
+/// already prefetched, up to `next`. A range of prefetched blocks that doesn't follow
+/// the origin, e.g. after a reset, is ignored.
+fn prefetch_range(origin: u64, next: u64, depth: u64) -> Range<u64> {
+    let (start, end) = (origin + 1, origin + 1 + depth);
+    if next > start && next <= end {
+        next..end
+    } else {
+        start..end
+    }
+}
+
+/// Fetches the header, transactions and receipts of the given L1 block, and the blobs
+/// of the transactions sent to the batch inbox.
+async fn prefetch_block<CP, BP>(
+    cfg: &RollupConfig,
+    mut chain_provider: CP,
+    mut blob_provider: BP,
+    number: u64,
+) -> Result<()>
+where
+    CP: ChainProvider + Send,
+    BP: BlobProvider + Send,
+{
+    let block = chain_provider
+        .block_info_by_number(number)
+        .await
+        .map_err(|e| eyre!("Failed to fetch block: {:?}", e))?;
+    let (_, txs) = chain_provider
+        .block_info_and_transactions_by_hash(block.hash)
+        .await
+        .map_err(|e| eyre!("Failed to fetch transactions: {:?}", e))?;
+    chain_provider
+        .receipts_by_hash(block.hash)
+        .await
+        .map_err(|e| eyre!("Failed to fetch receipts: {:?}", e))?;
+
+    let hashes = batcher_blob_hashes(cfg, &txs);
+    if !hashes.is_empty() {
+        blob_provider
+            .get_blobs(&block, &hashes)
+            .await
+            .map_err(|e| eyre!("Failed to fetch blobs: {:?}", e))?;
+    }
+    Ok(())
+}
+
+/// Returns the hashes of the blobs of the transactions sent to the batch inbox, indexed
+/// among all the blobs of the block as the pipeline's blob source does.
+///
+/// The sender of the transactions is not checked against the batcher address, which
+/// may only prefetch a few blobs the pipeline won't read.
+fn batcher_blob_hashes(cfg: &RollupConfig, txs: &[TxEnvelope]) -> Vec<IndexedBlobHash> {
+    let mut hashes = Vec::new();
+    let mut index = 0;
+    for tx in txs {
+        let TxEnvelope::Eip4844(tx) = tx else {
+            continue;
+        };
+        let tx = match tx.tx() {
+            TxEip4844Variant::TxEip4844(tx) => tx,
+            TxEip4844Variant::TxEip4844WithSidecar(tx) => &tx.tx,
+        };
+        if tx.to != cfg.batch_inbox_address {
+            index += tx.blob_versioned_hashes.len();
+            continue;
+        }
+        for hash in &tx.blob_versioned_hashes {
+            hashes.push(IndexedBlobHash { index, hash: *hash });
+            index += 1;
+        }
+    }
+    hashes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::{
+        consensus::{Signed, TxEip4844},
+        primitives::{Address, Signature, B256, U256},
+    };
+
+    fn blob_tx(to: Address, blobs: u8) -> TxEnvelope {
+        let tx = TxEip4844 {
+            to,
+            blob_versioned_hashes: (0..blobs).map(B256::repeat_byte).collect(),
+            ..Default::default()
+        };
+        let signature = Signature::from_rs_and_parity(U256::ZERO, U256::ZERO, false).unwrap();
+        TxEnvelope::Eip4844(Signed::new_unchecked(tx.into(), signature, B256::ZERO))
+    }
+
+    #[test]
+    fn test_prefetch_range() {
+        assert_eq!(prefetch_range(10, 0, 4), 11..15);
+        assert_eq!(prefetch_range(11, 15, 4), 15..16);
+        assert_eq!(prefetch_range(11, 16, 4), 16..16);
+        // The pipeline was reset behind the prefetched blocks.
+        assert_eq!(prefetch_range(2, 16, 4), 3..7);
+    }
+
+    #[test]
+    fn test_batcher_blob_hashes() {
+        let inbox = Address::repeat_byte(0xff);
+        let cfg = RollupConfig { batch_inbox_address: inbox, ..Default::default() };
+        let txs = [blob_tx(Address::ZERO, 2), blob_tx(inbox, 1)];
+        assert_eq!(
+            batcher_blob_hashes(&cfg, &txs),
+            vec![IndexedBlobHash { index: 2, hash: B256::repeat_byte(0) }]
+        );
+    }
+}
@@ -0,0 +1,219 @@
+//! Proposal of the L2 output roots to L1
+
+use std::{sync::Arc, time::Duration};
+
+use alloy::{
+    primitives::{Address, Bytes, TxKind, U256},
+    rpc::types::{TransactionInput, TransactionRequest},
+    signers::local::PrivateKeySigner,
+    sol,
+    sol_types::SolCall,
+};
+use eyre::{eyre, Context, Result};
+use superchain_registry::RollupConfig;
+use tokio::{sync::watch, time::sleep};
+use tracing::{debug, info, warn};
+
+use crate::{HeraArgsExt, OutputFetcher, SyncStatus, TxManager};
+
+/// The default interval at which the proposer checks for a new output to propose.
+pub const DEFAULT_PROPOSER_POLL_INTERVAL: Duration = Duration::from_secs(12);
+
+/// The default number of L2 blocks between two dispute games created by the proposer.
+pub const DEFAULT_PROPOSAL_INTERVAL: u64 = 1800;
+
+sol! {
+    /// The functions of the `L2OutputOracle` used by the proposer.
+    interface L2OutputOracle {
+        function nextBlockNumber() external view returns (uint256);
+        function proposeL2Output(
+            bytes32 _outputRoot,
+            uint256 _l2BlockNumber,
+            bytes32 _l1BlockHash,
+            uint256 _l1BlockNumber
+        ) external payable;
+    }
+
+    /// The functions of the `DisputeGameFactory` used by the proposer.
+    interface DisputeGameFactory {
+        function initBonds(uint32 _gameType) external view returns (uint256);
+        function create(
+            uint32 _gameType,
+            bytes32 _rootClaim,
+            bytes _extraData
+        ) external payable returns (address);
+    }
+}
+
+/// Where the output roots are proposed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProposalTarget {
+    /// The `L2OutputOracle` at the given address, which sets the next block to propose.
+    OutputOracle(Address),
+    /// The `DisputeGameFactory` at the given address, creating a game of the given type
+    /// every `interval` L2 blocks.
+    DisputeGameFactory {
+        /// The address of the factory
+        address: Address,
+        /// The type of the created games
+        game_type: u32,
+        /// The number of L2 blocks between two games
+        interval: u64,
+    },
+}
+
+/// Returns the first L2 block to propose to a `DisputeGameFactory`, given the current
+/// proposable head: the latest multiple of the interval, excluding the genesis.
+pub fn first_game_block(head: u64, interval: u64) -> u64 {
+    let interval = interval.max(1);
+    (head / interval * interval).max(interval)
+}
+
+/// OutputProposer
+///
+/// Proposes the output roots of the finalized L2 blocks to L1, as the proposer of the
+/// chain.
+///
+/// The finalized head, or the safe head if non-finalized proposals are allowed, is
+/// followed through the sync status of the rollup driver. With an `L2OutputOracle`, the
+/// block it expects next is proposed once the head reaches it. With a
+/// `DisputeGameFactory`, a game is created every proposal interval, with the init bond
+/// of its game type and the L2 block number as extra data.
+#[derive(Debug)]
+pub struct OutputProposer {
+    /// The fetcher of the output roots
+    outputs: OutputFetcher,
+    /// The manager of the proposal transactions
+    txs: TxManager,
+    /// The contract the outputs are proposed to
+    target: ProposalTarget,
+    /// The sync status of the rollup driver, for the proposable head
+    sync_status: watch::Receiver<SyncStatus>,
+    /// Whether the outputs of the safe blocks are proposed before they are finalized
+    allow_non_finalized: bool,
+    /// The interval at which a new output to propose is checked for
+    poll_interval: Duration,
+    /// The next L2 block to create a dispute game for, once started
+    next_game_block: Option<u64>,
+}
+
+impl OutputProposer {
+    /// Creates a new standalone [OutputProposer], following the heads of the given sync
+    /// status.
+    pub fn standalone(
+        args: &HeraArgsExt,
+        cfg: Arc<RollupConfig>,
+        sync_status: watch::Receiver<SyncStatus>,
+    ) -> Result<Self> {
+        let key = args.proposer_private_key.ok_or(eyre!("Missing proposer private key"))?;
+        let signer = PrivateKeySigner::from_bytes(&key).wrap_err("Invalid proposer key")?;
+        let target = args
+            .proposal_target()
+            .ok_or(eyre!("Missing L2OutputOracle or DisputeGameFactory address"))?;
+        let txs = TxManager::new(args.l1_rpc_url.clone(), signer, cfg.l1_chain_id);
+        Ok(Self {
+            outputs: OutputFetcher::new_http(args.l2_rpc_url.clone(), cfg),
+            txs,
+            target,
+            sync_status,
+            allow_non_finalized: args.proposer_allow_non_finalized,
+            poll_interval: Duration::from_secs(args.proposer_poll_interval),
+            next_game_block: None,
+        })
+    }
+
+    /// Starts the proposal loop. It only returns on an unrecoverable error.
+    pub async fn start(mut self) -> Result<()> {
+        info!("Starting proposer, proposing to {:?} from {}", self.target, self.txs.address());
+        loop {
+            if let Err(err) = self.tick().await {
+                warn!("Failed to propose output: {:?}", err);
+                metrics::counter!("hera_proposer_failures_total").increment(1);
+            }
+            sleep(self.poll_interval).await;
+        }
+    }
+
+    /// Proposes the next output, if the proposable head has reached it.
+    async fn tick(&mut self) -> Result<()> {
+        let status = *self.sync_status.borrow();
+        let head = match self.allow_non_finalized {
+            true => status.safe_l2,
+            false => status.finalized_l2,
+        };
+        let head = head.block_info.number;
+
+        let number = match self.target {
+            ProposalTarget::OutputOracle(address) => {
+                let call = L2OutputOracle::nextBlockNumberCall {};
+                let output = self.txs.call(call_request(address, call.abi_encode())).await?;
+                let next = L2OutputOracle::nextBlockNumberCall::abi_decode_returns(&output, true)?;
+                next._0.saturating_to::<u64>()
+            }
+            ProposalTarget::DisputeGameFactory { interval, .. } => {
+                *self.next_game_block.get_or_insert_with(|| first_game_block(head, interval))
+            }
+        };
+        if number > head {
+            debug!("Waiting for L2 block {} to propose, head is {}", number, head);
+            return Ok(());
+        }
+
+        let output = self.outputs.output_at_block(number, status).await?;
+        let tx = match self.target {
+            ProposalTarget::OutputOracle(address) => {
+                let call = L2OutputOracle::proposeL2OutputCall {
+                    _outputRoot: output.output_root,
+                    _l2BlockNumber: U256::from(number),
+                    _l1BlockHash: status.current_l1.hash,
+                    _l1BlockNumber: U256::from(status.current_l1.number),
+                };
+                call_request(address, call.abi_encode())
+            }
+            ProposalTarget::DisputeGameFactory { address, game_type, .. } => {
+                let call = DisputeGameFactory::initBondsCall { _gameType: game_type };
+                let bond = self.txs.call(call_request(address, call.abi_encode())).await?;
+                let bond = DisputeGameFactory::initBondsCall::abi_decode_returns(&bond, true)?._0;
+                let call = DisputeGameFactory::createCall {
+                    _gameType: game_type,
+                    _rootClaim: output.output_root,
+                    _extraData: Bytes::from(U256::from(number).to_be_bytes::<32>()),
+                };
+                let mut tx = call_request(address, call.abi_encode());
+                tx.value = Some(bond);
+                tx
+            }
+        };
+        self.txs.send(tx).await?;
+
+        if let ProposalTarget::DisputeGameFactory { interval, .. } = self.target {
+            self.next_game_block = Some(number + interval);
+        }
+        metrics::counter!("hera_proposer_proposals_total").increment(1);
+        metrics::gauge!("hera_proposer_last_proposed_block").set(number as f64);
+        info!("Proposed output root {} of L2 block {}", output.output_root, number);
+        Ok(())
+    }
+}
+
+/// Returns a transaction request calling the given contract with the given calldata.
+fn call_request(to: Address, input: Vec<u8>) -> TransactionRequest {
+    TransactionRequest {
+        to: Some(TxKind::Call(to)),
+        input: TransactionInput::new(input.into()),
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_first_game_block() {
+        assert_eq!(first_game_block(0, 1800), 1800);
+        assert_eq!(first_game_block(3599, 1800), 1800);
+        assert_eq!(first_game_block(3600, 1800), 3600);
+        assert_eq!(first_game_block(5, 0), 5);
+    }
+}
@@ -0,0 +1,229 @@
+//! Fault proof prestate witness generation
+
+use std::sync::Arc;
+
+use alloy::{
+    eips::BlockNumberOrTag,
+    primitives::{address, keccak256, Address, Bytes, B256},
+    providers::{network::primitives::BlockTransactionsKind, Provider, ReqwestProvider},
+};
+use eyre::{bail, eyre, Result};
+use kona_derive::{
+    errors::StageError,
+    online::AlloyL2ChainProvider,
+    traits::{ChainProvider, L2ChainProvider, OriginProvider, Pipeline, StepResult},
+};
+use kona_primitives::L2AttributesWithParent;
+use kona_providers::{
+    witness::{local_preimage_key, WitnessBundle, WitnessStore},
+    AlloyChainProvider, RecordingBlobProvider, RecordingChainProvider,
+};
+use serde::{Deserialize, Serialize};
+use superchain_registry::RollupConfig;
+use tracing::{debug, info, trace};
+
+use crate::{new_rollup_pipeline, DriverEvents, HeraArgsExt, HeraDataSource};
+
+/// The local preimage key of the L1 head hash.
+const L1_HEAD_KEY: u64 = 1;
+/// The local preimage key of the agreed upon L2 output root.
+const L2_OUTPUT_ROOT_KEY: u64 = 2;
+/// The local preimage key of the claimed L2 output root.
+const L2_CLAIM_KEY: u64 = 3;
+/// The local preimage key of the claimed L2 block number.
+const L2_CLAIM_BLOCK_NUMBER_KEY: u64 = 4;
+/// The local preimage key of the L2 chain ID.
+const L2_CHAIN_ID_KEY: u64 = 5;
+/// The local preimage key of the JSON encoded rollup configuration.
+const L2_ROLLUP_CONFIG_KEY: u64 = 6;
+
+/// The address of the `L2ToL1MessagePasser` predeploy, whose storage root
+/// is committed to in the output root.
+const L2_TO_L1_MESSAGE_PASSER: Address = address!("4200000000000000000000000000000000000016");
+
+/// The maximum number of pipeline steps to derive a single L2 block.
+const MAX_PIPELINE_STEPS: usize = 100_000;
+
+/// The boot information of the fault proof program.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BootInfo {
+    /// The L1 head hash containing all data needed to derive the claimed block.
+    pub l1_head: B256,
+    /// The agreed upon output root of the parent of the claimed block.
+    pub agreed_l2_output_root: B256,
+    /// The claimed output root of the claimed block.
+    pub claimed_l2_output_root: B256,
+    /// The number of the claimed L2 block.
+    pub claimed_l2_block_number: u64,
+    /// The L2 chain ID.
+    pub chain_id: u64,
+}
+
+/// The witness needed to re-execute the derivation of a single
+/// L2 block inside the fault proof program.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PrestateWitness {
+    /// The boot information of the program.
+    pub boot_info: BootInfo,
+    /// All preimages fetched during derivation, including the boot information.
+    pub witness: WitnessBundle,
+}
+
+/// Derives the given L2 block and records the preimage witness and boot information
+/// needed to re-execute its derivation inside the fault proof program.
+///
+/// The derived payload attributes are checked against the canonical block
+/// with the configured validator before the witness is returned.
+pub async fn generate_prestate(
+    args: &HeraArgsExt,
+    cfg: Arc<RollupConfig>,
+    l2_block: u64,
+) -> Result<PrestateWitness> {
+    if l2_block <= cfg.genesis.l2.number {
+        bail!("Cannot prove block {} at or before the L2 genesis", l2_block);
+    }
+
+    let l2_provider = ReqwestProvider::new_http(args.l2_rpc_url.clone());
+    let mut l2_cp = AlloyL2ChainProvider::new_http(args.l2_rpc_url.clone(), cfg.clone());
+    let parent = l2_cp
+        .l2_block_info_by_number(l2_block - 1)
+        .await
+        .map_err(|e| eyre!("Failed to fetch L2 block {}: {:?}", l2_block - 1, e))?;
+
+    let witness = WitnessStore::default();
+    let mut cp = RecordingChainProvider::new(
+        AlloyChainProvider::new_http(args.l1_rpc_url.clone()),
+        witness.clone(),
+    );
+    let online = args.online_blob_provider().await?;
+    let bp = RecordingBlobProvider::new(online, witness.clone());
+
+    // Start far enough back for the pipeline to see every channel that may contain the batch.
+    let origin_number = parent.l1_origin.number.saturating_sub(cfg.channel_timeout);
+    let origin = cp
+        .block_info_by_number(origin_number)
+        .await
+        .map_err(|e| eyre!("Failed to fetch L1 block {}: {:?}", origin_number, e))?;
+    info!("Deriving L2 block {} from L1 origin {}", l2_block, origin.number);
+
+    let dap = HeraDataSource::new(cp.clone(), bp, &cfg, args.da_mode).with_alt_da(args.alt_da());
+    let mut pipeline = new_rollup_pipeline(cfg.clone(), cp, l2_cp, origin, dap);
+    let mut attributes: Option<L2AttributesWithParent> = None;
+    for _ in 0..MAX_PIPELINE_STEPS {
+        match pipeline.step(parent).await {
+            StepResult::PreparedAttributes => trace!("Prepared new attributes"),
+            StepResult::AdvancedOrigin => trace!("Advanced origin"),
+            StepResult::OriginAdvanceErr(err) => bail!("Could not advance origin: {:?}", err),
+            StepResult::StepFailed(StageError::NotEnoughData) => continue,
+            StepResult::StepFailed(err) => debug!("Pipeline step failed: {:?}", err),
+        }
+
+        if let Some(derived) = pipeline.next() {
+            if derived.parent.block_info.number + 1 == l2_block {
+                attributes = Some(derived);
+                break;
+            }
+        }
+    }
+    let attributes = attributes.ok_or(eyre!("Failed to derive L2 block {}", l2_block))?;
+    let l1_head = pipeline.origin().ok_or(eyre!("Pipeline has no L1 origin"))?.hash;
+
+    if !args.validator(&cfg, &DriverEvents::default())?.validate(&attributes).await? {
+        bail!("Derived attributes do not match the canonical L2 block {}", l2_block);
+    }
+
+    // The program starts from the agreed output root, and walks back to the parent header.
+    let agreed_l2_output_root = record_output_root(&l2_provider, &witness, l2_block - 1).await?;
+    let claimed_l2_output_root = record_output_root(&l2_provider, &witness, l2_block).await?;
+    let raw_header: Bytes = l2_provider
+        .raw_request("debug_getRawHeader".into(), [format!("{:#x}", l2_block - 1)])
+        .await
+        .map_err(|e| eyre!("Failed to fetch raw L2 header: {:?}", e))?;
+    witness.insert_keccak256(raw_header);
+
+    let boot_info = BootInfo {
+        l1_head,
+        agreed_l2_output_root,
+        claimed_l2_output_root,
+        claimed_l2_block_number: l2_block,
+        chain_id: cfg.l2_chain_id,
+    };
+    record_boot_info(&witness, &boot_info, &cfg)?;
+    info!("Recorded {} preimages for L2 block {}", witness.len(), l2_block);
+
+    Ok(PrestateWitness { boot_info, witness: witness.bundle() })
+}
+
+/// Computes the output root of the given L2 block and records its preimage.
+async fn record_output_root(
+    provider: &ReqwestProvider,
+    witness: &WitnessStore,
+    number: u64,
+) -> Result<B256> {
+    let block = provider
+        .get_block(BlockNumberOrTag::from(number).into(), BlockTransactionsKind::Hashes)
+        .await
+        .map_err(|e| eyre!("Failed to fetch L2 block {}: {:?}", number, e))?
+        .ok_or(eyre!("L2 block {} not found", number))?;
+    let proof = provider
+        .get_proof(L2_TO_L1_MESSAGE_PASSER, vec![])
+        .block_id(BlockNumberOrTag::from(number).into())
+        .await
+        .map_err(|e| eyre!("Failed to fetch message passer proof: {:?}", e))?;
+    let hash = block.header.hash.ok_or(eyre!("L2 block {} has no hash", number))?;
+
+    // Output root version 0: keccak256(version ++ state_root ++ storage_root ++ block_hash)
+    let mut preimage = [0u8; 128];
+    preimage[32..64].copy_from_slice(block.header.state_root.as_slice());
+    preimage[64..96].copy_from_slice(proof.storage_hash.as_slice());
+    preimage[96..].copy_from_slice(hash.as_slice());
+
+    witness.insert_keccak256(Bytes::copy_from_slice(&preimage));
+    Ok(keccak256(preimage))
+}
+
+/// Records the boot information as local preimages.
+fn record_boot_info(witness: &WitnessStore, boot: &BootInfo, cfg: &RollupConfig) -> Result<()> {
+    let local = |key: u64, value: &[u8]| {
+        witness.insert(local_preimage_key(key), Bytes::copy_from_slice(value));
+    };
+
+    local(L1_HEAD_KEY, boot.l1_head.as_slice());
+    local(L2_OUTPUT_ROOT_KEY, boot.agreed_l2_output_root.as_slice());
+    local(L2_CLAIM_KEY, boot.claimed_l2_output_root.as_slice());
+    local(L2_CLAIM_BLOCK_NUMBER_KEY, &boot.claimed_l2_block_number.to_be_bytes());
+    local(L2_CHAIN_ID_KEY, &boot.chain_id.to_be_bytes());
+    local(L2_ROLLUP_CONFIG_KEY, &serde_json::to_vec(cfg)?);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use kona_providers::witness::PreimageKeyType;
+
+    #[test]
+    fn test_record_boot_info() {
+        let witness = WitnessStore::default();
+        let boot = BootInfo {
+            l1_head: B256::repeat_byte(1),
+            agreed_l2_output_root: B256::repeat_byte(2),
+            claimed_l2_output_root: B256::repeat_byte(3),
+            claimed_l2_block_number: 42,
+            chain_id: 10,
+        };
+        record_boot_info(&witness, &boot, &RollupConfig::default()).unwrap();
+
+        assert_eq!(witness.len(), 6);
+        assert_eq!(
+            witness.get(&local_preimage_key(L1_HEAD_KEY)),
+            Some(Bytes::copy_from_slice(boot.l1_head.as_slice()))
+        );
+        assert_eq!(
+            witness.get(&local_preimage_key(L2_CLAIM_BLOCK_NUMBER_KEY)),
+            Some(Bytes::copy_from_slice(&42u64.to_be_bytes()))
+        );
+        assert_eq!(local_preimage_key(L1_HEAD_KEY)[0], PreimageKeyType::Local as u8);
+    }
+}
@@ -0,0 +1,390 @@
+//! Configuration hot-reloading on SIGHUP
+
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+};
+
+use async_trait::async_trait;
+use eyre::{bail, Context, Result};
+use kona_primitives::L2AttributesWithParent;
+use libp2p::{multiaddr::Protocol, Multiaddr, PeerId};
+use op_net::gossip::peers::PeerManager;
+use serde::Deserialize;
+use superchain_registry::RollupConfig;
+use tokio::{
+    signal::unix::{signal, SignalKind},
+    task::JoinHandle,
+};
+use tracing::{debug, error, info, warn};
+use url::Url;
+
+use crate::{
+    telemetry::{check_log_filter, reset_log_filter, set_log_filter},
+    AttributesValidator, DriverEvents, HeraArgsExt,
+};
+
+/// The settings of the reloadable configuration file.
+///
+/// Only the known fields can be applied while the node is running. Any other setting
+/// is kept as-is, so that changing it can be reported as requiring a restart.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReloadableConfig {
+    /// The log filter directives, e.g. `hera=debug`.
+    pub log_level: Option<String>,
+    /// The peer multiaddrs that are always dialed.
+    #[serde(default)]
+    pub static_peers: Vec<String>,
+    /// The RPC URL of the trusted L2 execution client.
+    pub l2_rpc_url: Option<String>,
+    /// The URL of the L2 engine API.
+    pub l2_engine_api_url: Option<String>,
+    /// All other settings, which require a restart to be applied.
+    #[serde(flatten)]
+    pub other: BTreeMap<String, serde_json::Value>,
+}
+
+impl ReloadableConfig {
+    /// Loads the configuration from the given JSON file.
+    pub fn load(path: &Path) -> Result<Self> {
+        let file = File::open(path).wrap_err("Failed to open config file")?;
+        serde_json::from_reader(file).wrap_err("Failed to read config file")
+    }
+
+    /// Returns the names of the settings that differ in `new`, split between
+    /// those that can be applied live and those that require a restart.
+    pub fn diff(&self, new: &Self) -> (Vec<String>, Vec<String>) {
+        let mut changed = Vec::new();
+        if self.log_level != new.log_level {
+            changed.push("logLevel".to_string());
+        }
+        if self.static_peers != new.static_peers {
+            changed.push("staticPeers".to_string());
+        }
+        if self.l2_rpc_url != new.l2_rpc_url {
+            changed.push("l2RpcUrl".to_string());
+        }
+        if self.l2_engine_api_url != new.l2_engine_api_url {
+            changed.push("l2EngineApiUrl".to_string());
+        }
+
+        let mut restart = Vec::new();
+        for key in self.other.keys().chain(new.other.keys()) {
+            if self.other.get(key) != new.other.get(key) && !restart.contains(key) {
+                restart.push(key.clone());
+            }
+        }
+        (changed, restart)
+    }
+
+    /// Parses the static peer multiaddrs.
+    fn parse_static_peers(&self) -> Result<Vec<Multiaddr>> {
+        self.static_peers
+            .iter()
+            .map(|peer| peer.parse().wrap_err_with(|| format!("Invalid static peer {}", peer)))
+            .collect()
+    }
+
+    /// Applies the validator endpoints to the given arguments.
+    fn apply_endpoints(&self, args: &mut HeraArgsExt) -> Result<()> {
+        if let Some(url) = &self.l2_rpc_url {
+            args.l2_rpc_url = Url::parse(url)?;
+        }
+        if let Some(url) = &self.l2_engine_api_url {
+            args.l2_engine_api_url = Some(Url::parse(url)?);
+        }
+        Ok(())
+    }
+}
+
+/// An [AttributesValidator] that can be replaced while the node is running.
+#[derive(Debug)]
+pub struct ReloadableValidator {
+    /// The current validator.
+    inner: RwLock<Arc<dyn AttributesValidator + Send + Sync>>,
+}
+
+impl ReloadableValidator {
+    /// Creates a new [ReloadableValidator] wrapping the given validator.
+    pub fn new(validator: Arc<dyn AttributesValidator + Send + Sync>) -> Self {
+        Self { inner: RwLock::new(validator) }
+    }
+
+    /// Replaces the validator. Validations that already started are not affected.
+    pub fn replace(&self, validator: Arc<dyn AttributesValidator + Send + Sync>) {
+        *self.inner.write().expect("lock poisoned") = validator;
+    }
+
+    /// Returns the current validator.
+    fn current(&self) -> Arc<dyn AttributesValidator + Send + Sync> {
+        self.inner.read().expect("lock poisoned").clone()
+    }
+}
+
+#[async_trait]
+impl AttributesValidator for ReloadableValidator {
+    async fn validate(&self, attributes: &L2AttributesWithParent) -> Result<bool> {
+        self.current().validate(attributes).await
+    }
+}
+
+/// Reloads the configuration file on SIGHUP.
+///
+/// The log level, static peers and validator endpoints are applied without restarting
+/// the node. Every reload logs which settings changed, and which of them only take
+/// effect after a restart. The new configuration is validated as a whole before any
+/// of it is applied, so an invalid file leaves the node as it was.
+///
+/// The static peers added to the file are dialed, and the connections of the removed
+/// ones are closed, if their address includes their peer ID. The static peers given on
+/// the command line are redialed by discovery, and are not affected. In allowlist-only
+/// mode, the added static peers must be in `--p2p.allowlist` to connect.
+///
+/// Alert webhooks are deliberately not reloaded: Hera doesn't send alerts itself, and
+/// leaves alerting to rules on its Prometheus metrics. An `alertWebhooks` setting is
+/// reported like any other setting that is not applied live.
+#[derive(Debug)]
+pub struct ConfigReloader {
+    /// The path of the configuration file.
+    path: PathBuf,
+    /// The configuration that is currently applied.
+    current: ReloadableConfig,
+    /// The node arguments, with the reloaded settings applied.
+    args: HeraArgsExt,
+    /// The rollup configuration.
+    cfg: Arc<RollupConfig>,
+    /// The validator used by the driver.
+    validator: Arc<ReloadableValidator>,
+    /// The bus the disagreements of the validators are published on.
+    events: DriverEvents,
+    /// Dials and disconnects the static peers, if p2p networking is enabled.
+    peers: Option<PeerManager>,
+}
+
+impl ConfigReloader {
+    /// Creates a new [ConfigReloader], applying the configuration file on top of the
+    /// given arguments, whose validators publish their disagreements on the given
+    /// [DriverEvents].
+    pub fn new(
+        path: PathBuf,
+        mut args: HeraArgsExt,
+        cfg: Arc<RollupConfig>,
+        events: DriverEvents,
+    ) -> Result<Self> {
+        let current = ReloadableConfig::load(&path)?;
+        current.apply_endpoints(&mut args)?;
+        if let Some(directives) = &current.log_level {
+            if let Err(err) = set_log_filter(directives) {
+                warn!(?err, "Failed to apply the configured log level");
+            }
+        }
+        let validator = Arc::new(ReloadableValidator::new(args.validator(&cfg, &events)?));
+
+        Ok(Self { path, current, args, cfg, validator, events, peers: None })
+    }
+
+    /// Dials and disconnects the static peers that change on reload with the given
+    /// [PeerManager].
+    pub fn with_peers(mut self, peers: PeerManager) -> Self {
+        self.peers = Some(peers);
+        self
+    }
+
+    /// Returns the validator whose endpoints are reloaded.
+    pub fn validator(&self) -> Arc<dyn AttributesValidator + Send + Sync> {
+        self.validator.clone()
+    }
+
+    /// Spawns the task reloading the configuration on every SIGHUP.
+    pub fn spawn(mut self) -> Result<JoinHandle<()>> {
+        let mut hangup = signal(SignalKind::hangup())?;
+        Ok(tokio::spawn(async move {
+            while hangup.recv().await.is_some() {
+                if let Err(err) = self.reload().await {
+                    error!(?err, "Failed to reload configuration from {:?}", self.path);
+                }
+            }
+        }))
+    }
+
+    /// Reloads the configuration file and applies the settings that changed.
+    ///
+    /// Nothing is applied if any of the new settings is invalid.
+    async fn reload(&mut self) -> Result<()> {
+        let new = ReloadableConfig::load(&self.path)?;
+        let (changed, restart) = self.current.diff(&new);
+        if changed.is_empty() && restart.is_empty() {
+            info!("Reloaded configuration: nothing changed");
+            return Ok(());
+        }
+
+        // Validate all the new settings before applying any of them.
+        let log_level_changed = new.log_level != self.current.log_level;
+        if let (true, Some(directives)) = (log_level_changed, &new.log_level) {
+            check_log_filter(directives)?;
+        }
+        let (added, removed) = if new.static_peers != self.current.static_peers {
+            if self.peers.is_none() {
+                bail!("P2P networking is disabled");
+            }
+            let old = self.current.parse_static_peers()?;
+            let new = new.parse_static_peers()?;
+            let added = new.iter().filter(|p| !old.contains(p)).cloned().collect::<Vec<_>>();
+            let removed = old.iter().filter(|p| !new.contains(p)).cloned().collect::<Vec<_>>();
+            (added, removed)
+        } else {
+            (None, Vec::new(), Vec::new())
+        };
+        let validator = if new.l2_rpc_url != self.current.l2_rpc_url ||
+            new.l2_engine_api_url != self.current.l2_engine_api_url
+        {
+            let mut args = self.args.clone();
+            new.apply_endpoints(&mut args)?;
+            let validator = args.validator(&self.cfg, &self.events)?;
+            Some((args, validator))
+        } else {
+            None
+        };
+
+        if log_level_changed {
+            match &new.log_level {
+                Some(directives) => set_log_filter(directives)?,
+                None => reset_log_filter()?,
+            }
+        }
+        if let Some(peers) = self.peers.as_ref() {
+            for addr in added {
+                if let Err(err) = peers.connect(addr.clone()).await {
+                    warn!(?err, "Failed to dial static peer {}", addr);
+                }
+            }
+            for addr in removed {
+                let Some(peer) = peer_id(&addr) else {
+                    warn!("Static peer {} has no peer ID, its connections are kept", addr);
+                    continue;
+                };
+                if let Err(err) = peers.disconnect(peer).await {
+                    debug!(?err, "Failed to disconnect static peer {}", addr);
+                }
+            }
+        }
+        if let Some((args, validator)) = validator {
+            self.args = args;
+            self.validator.replace(validator);
+        }
+
+        info!(?changed, requires_restart = ?restart, "Reloaded configuration");
+        self.current = new;
+        Ok(())
+    }
+}
+
+/// Returns the peer ID of the given multiaddr, if it includes one.
+fn peer_id(addr: &Multiaddr) -> Option<PeerId> {
+    addr.iter().find_map(|protocol| match protocol {
+        Protocol::P2p(id) => Some(id),
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+    use op_net::gossip::peers::PeerCommand;
+    use tokio::sync::mpsc;
+
+    #[derive(Parser)]
+    struct Cli {
+        #[clap(flatten)]
+        hera: HeraArgsExt,
+    }
+
+    /// Creates a [ConfigReloader] of the given configuration file, with a [PeerManager]
+    /// whose commands are acknowledged and sent back as strings.
+    fn reloader(path: PathBuf) -> (ConfigReloader, mpsc::UnboundedReceiver<String>) {
+        let (commands, mut command_recv) = mpsc::unbounded_channel();
+        let (sender, recv) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            while let Some(command) = command_recv.recv().await {
+                let (command, reply) = match command {
+                    PeerCommand::Connect(addr, reply) => (format!("connect {}", addr), reply),
+                    PeerCommand::Disconnect(peer, reply) => (format!("disconnect {}", peer), reply),
+                    _ => continue,
+                };
+                _ = reply.send(Ok(()));
+                _ = sender.send(command);
+            }
+        });
+        let args = Cli::parse_from(["hera"]).hera;
+        let cfg = Arc::new(RollupConfig::default());
+        let reloader = ConfigReloader::new(path, args, cfg, DriverEvents::default())
+            .unwrap()
+            .with_peers(PeerManager::new(commands));
+        (reloader, recv)
+    }
+
+    #[tokio::test]
+    async fn test_reload_invalid_config() {
+        let path = std::env::temp_dir().join(format!("hera-reload-invalid-{}", std::process::id()));
+        std::fs::write(&path, r#"{"staticPeers":["/ip4/10.0.0.1/tcp/9222"]}"#).unwrap();
+        let (mut reloader, mut commands) = reloader(path.clone());
+        let current = reloader.current.clone();
+
+        // The added peer is not dialed, since the URL that follows it is invalid.
+        std::fs::write(
+            &path,
+            r#"{"staticPeers":["/ip4/10.0.0.1/tcp/9222","/ip4/10.0.0.2/tcp/9222"],"l2RpcUrl":"not a url"}"#,
+        )
+        .unwrap();
+        assert!(reloader.reload().await.is_err());
+        assert_eq!(reloader.current, current);
+        tokio::task::yield_now().await;
+        assert!(commands.try_recv().is_err());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_reload_static_peers() {
+        let path = std::env::temp_dir().join(format!("hera-reload-peers-{}", std::process::id()));
+        let peer = PeerId::random();
+        let removed = format!("/ip4/10.0.0.1/tcp/9222/p2p/{}", peer);
+        std::fs::write(&path, format!(r#"{{"staticPeers":["{}"]}}"#, removed)).unwrap();
+        let (mut reloader, mut commands) = reloader(path.clone());
+
+        // The added peer is dialed, and the removed one disconnected.
+        std::fs::write(&path, r#"{"staticPeers":["/ip4/10.0.0.2/tcp/9222"]}"#).unwrap();
+        reloader.reload().await.unwrap();
+        assert_eq!(reloader.current.static_peers, vec!["/ip4/10.0.0.2/tcp/9222"]);
+        assert_eq!(commands.recv().await.unwrap(), "connect /ip4/10.0.0.2/tcp/9222");
+        assert_eq!(commands.recv().await.unwrap(), format!("disconnect {}", peer));
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_diff() {
+        let old: ReloadableConfig = serde_json::from_str(
+            r#"{"logLevel":"hera=info","staticPeers":[],"l2ChainId":10,"validationMode":"trusted"}"#,
+        )
+        .unwrap();
+        let new: ReloadableConfig = serde_json::from_str(
+            r#"{"logLevel":"hera=debug","staticPeers":["/ip4/10.0.0.1/tcp/9222"],"l2ChainId":8453}"#,
+        )
+        .unwrap();
+
+        let (changed, restart) = old.diff(&new);
+        assert_eq!(changed, vec!["logLevel", "staticPeers"]);
+        assert_eq!(restart, vec!["l2ChainId", "validationMode"]);
+        assert_eq!(old.diff(&old), (Vec::<String>::new(), Vec::<String>::new()));
+
+        // Alert webhooks are not applied live.
+        let webhooks: ReloadableConfig =
+            serde_json::from_str(r#"{"alertWebhooks":["https://alerts.example.com"]}"#).unwrap();
+        let (changed, restart) = ReloadableConfig::default().diff(&webhooks);
+        assert!(changed.is_empty());
+        assert_eq!(restart, vec!["alertWebhooks"]);
+    }
+}
@@ -0,0 +1,156 @@
+//! L1 reorg detection
+
+use std::collections::VecDeque;
+
+use kona_primitives::BlockInfo;
+
+/// The default number of L1 blocks tracked by a [ReorgWatcher].
+pub const DEFAULT_REORG_WATCH_DEPTH: usize = 256;
+
+/// An L1 reorg, detected by a [ReorgWatcher].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct L1Reorg {
+    /// The tracked L1 blocks that are no longer canonical, in ascending order.
+    pub reverted: Vec<BlockInfo>,
+    /// The new canonical L1 tip.
+    pub new_tip: BlockInfo,
+}
+
+impl L1Reorg {
+    /// Returns the number of the first L1 block that is no longer canonical.
+    pub fn fork_number(&self) -> u64 {
+        self.reverted.first().map_or(self.new_tip.number, |block| block.number)
+    }
+
+    /// Returns the number of reverted L1 blocks.
+    pub fn depth(&self) -> usize {
+        self.reverted.len()
+    }
+
+    /// Returns the L1 tip before the reorg.
+    pub fn old_tip(&self) -> Option<BlockInfo> {
+        self.reverted.last().copied()
+    }
+
+    /// Merges a later reorg into this one, e.g. when several reorgs happen before the
+    /// driver handles them.
+    pub fn merge(mut self, later: Self) -> Self {
+        self.reverted.extend(later.reverted);
+        self.reverted.sort_by_key(|block| block.number);
+        self.reverted.dedup();
+        self.new_tip = later.new_tip;
+        self
+    }
+}
+
+/// Tracks the hashes of the latest L1 blocks delivered to the driver, and detects
+/// reorgs when newly delivered blocks don't extend them.
+#[derive(Debug, Clone)]
+pub struct ReorgWatcher {
+    /// The tracked L1 blocks, in ascending order.
+    blocks: VecDeque<BlockInfo>,
+    /// The maximum number of tracked L1 blocks.
+    depth: usize,
+}
+
+impl Default for ReorgWatcher {
+    fn default() -> Self {
+        Self::new(DEFAULT_REORG_WATCH_DEPTH)
+    }
+}
+
+impl ReorgWatcher {
+    /// Creates a new [ReorgWatcher], tracking the given number of L1 blocks.
+    pub fn new(depth: usize) -> Self {
+        Self { blocks: VecDeque::with_capacity(depth), depth: depth.max(1) }
+    }
+
+    /// Tracks the given chain of newly delivered L1 blocks, in ascending order, and
+    /// returns the reorg it reveals, if any.
+    ///
+    /// Tracked blocks at the height of a new block with a different hash are reverted.
+    /// If the parent of the new chain is tracked under another hash, e.g. because a
+    /// notification was missed, the tracked parent is reverted too.
+    pub fn observe(&mut self, chain: &[BlockInfo]) -> Option<L1Reorg> {
+        let (first, tip) = (chain.first()?, chain.last()?);
+        let mut fork = self
+            .blocks
+            .iter()
+            .position(|block| block.number >= first.number)
+            .unwrap_or(self.blocks.len());
+        if let Some(parent) = fork.checked_sub(1).map(|i| self.blocks[i]) {
+            if parent.number + 1 == first.number && parent.hash != first.parent_hash {
+                fork -= 1;
+            }
+        }
+
+        let reverted: Vec<_> =
+            self.blocks.drain(fork..).filter(|block| !chain.contains(block)).collect();
+        self.blocks.extend(chain);
+        while self.blocks.len() > self.depth {
+            self.blocks.pop_front();
+        }
+
+        (!reverted.is_empty()).then_some(L1Reorg { reverted, new_tip: *tip })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::B256;
+
+    fn block(number: u64, fork: u8) -> BlockInfo {
+        BlockInfo {
+            number,
+            hash: B256::with_last_byte(number as u8 + fork),
+            parent_hash: B256::with_last_byte((number as u8).wrapping_sub(1) + fork),
+            timestamp: number * 12,
+        }
+    }
+
+    #[test]
+    fn test_observe_extending_chain() {
+        let mut watcher = ReorgWatcher::new(2);
+        assert_eq!(watcher.observe(&[block(1, 0), block(2, 0)]), None);
+        assert_eq!(watcher.observe(&[block(3, 0)]), None);
+        // Re-delivered blocks are not a reorg.
+        assert_eq!(watcher.observe(&[block(3, 0)]), None);
+        assert_eq!(watcher.blocks, vec![block(2, 0), block(3, 0)]);
+    }
+
+    #[test]
+    fn test_observe_reorg() {
+        let mut watcher = ReorgWatcher::default();
+        watcher.observe(&[block(1, 0), block(2, 0), block(3, 0)]);
+
+        let mut replacement = block(2, 100);
+        replacement.parent_hash = block(1, 0).hash;
+        let reorg = watcher.observe(&[replacement, block(3, 100)]).unwrap();
+        assert_eq!(reorg.fork_number(), 2);
+        assert_eq!(reorg.depth(), 2);
+        assert_eq!(reorg.old_tip(), Some(block(3, 0)));
+        assert_eq!(reorg.new_tip, block(3, 100));
+    }
+
+    #[test]
+    fn test_observe_unlinked_chain() {
+        let mut watcher = ReorgWatcher::default();
+        watcher.observe(&[block(1, 0), block(2, 0)]);
+
+        // Block 3 does not build on the tracked block 2.
+        let reorg = watcher.observe(&[block(3, 100)]).unwrap();
+        assert_eq!(reorg.fork_number(), 2);
+        assert_eq!(reorg.depth(), 1);
+    }
+
+    #[test]
+    fn test_merge() {
+        let first = L1Reorg { reverted: vec![block(5, 0), block(6, 0)], new_tip: block(6, 1) };
+        let later = L1Reorg { reverted: vec![block(4, 0)], new_tip: block(5, 2) };
+        let merged = first.merge(later);
+        assert_eq!(merged.fork_number(), 4);
+        assert_eq!(merged.depth(), 3);
+        assert_eq!(merged.new_tip, block(5, 2));
+    }
+}
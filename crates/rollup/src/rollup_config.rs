@@ -0,0 +1,212 @@
+//! Loading and validation of custom rollup configs
+
+use std::{fs, path::Path};
+
+use alloy::{
+    primitives::{Address, B256},
+    providers::{Provider, ReqwestProvider},
+};
+use eyre::{bail, eyre, Result, WrapErr};
+use superchain_registry::{RollupConfig, ROLLUP_CONFIGS};
+use url::Url;
+
+/// Loads the op-node compatible rollup config JSON file at the given path, and checks
+/// that its fields are consistent with [validate_rollup_config].
+pub fn load_rollup_config(path: &Path) -> Result<RollupConfig> {
+    let data =
+        fs::read(path).wrap_err_with(|| format!("Failed to open rollup config {:?}", path))?;
+    let cfg: RollupConfig = serde_json::from_slice(&data)
+        .map_err(|e| eyre!("Failed to parse rollup config {:?}: {}", path, e))?;
+    validate_rollup_config(&cfg).wrap_err_with(|| format!("Invalid rollup config {:?}", path))?;
+    Ok(cfg)
+}
+
+/// Checks that the fields of a rollup config are consistent:
+///
+/// - the chain IDs, block time, sequencing window and channel timeout are set,
+/// - the genesis blocks and system config are set, with a batcher address,
+/// - the batch inbox, deposit contract and system config addresses are set,
+/// - the hardforks are scheduled in activation order, without skipping any,
+/// - the genesis blocks match the superchain registry's, if the chain is registered.
+pub fn validate_rollup_config(cfg: &RollupConfig) -> Result<()> {
+    if cfg.l1_chain_id == 0 || cfg.l2_chain_id == 0 {
+        bail!("l1_chain_id and l2_chain_id must be set");
+    }
+    if cfg.l1_chain_id == cfg.l2_chain_id {
+        bail!("l1_chain_id and l2_chain_id must differ, both are {}", cfg.l1_chain_id);
+    }
+    for (field, value) in [
+        ("block_time", cfg.block_time),
+        ("seq_window_size", cfg.seq_window_size),
+        ("channel_timeout", cfg.channel_timeout),
+    ] {
+        if value == 0 {
+            bail!("{} must be greater than 0", field);
+        }
+    }
+
+    if cfg.genesis.l1.hash == B256::ZERO {
+        bail!("genesis.l1.hash must be set");
+    }
+    if cfg.genesis.l2.hash == B256::ZERO {
+        bail!("genesis.l2.hash must be set");
+    }
+    let Some(system_config) = &cfg.genesis.system_config else {
+        bail!("genesis.system_config must be set");
+    };
+    if system_config.batcher_address == Address::ZERO {
+        bail!("genesis.system_config.batcherAddr must be set");
+    }
+
+    for (field, address) in [
+        ("batch_inbox_address", cfg.batch_inbox_address),
+        ("deposit_contract_address", cfg.deposit_contract_address),
+        ("l1_system_config_address", cfg.l1_system_config_address),
+    ] {
+        if address == Address::ZERO {
+            bail!("{} must be set", field);
+        }
+    }
+
+    check_fork_order(&[
+        ("regolith_time", cfg.regolith_time),
+        ("canyon_time", cfg.canyon_time),
+        ("delta_time", cfg.delta_time),
+        ("ecotone_time", cfg.ecotone_time),
+        ("fjord_time", cfg.fjord_time),
+        ("granite_time", cfg.granite_time),
+    ])?;
+
+    if let Some(registered) = ROLLUP_CONFIGS.get(&cfg.l2_chain_id) {
+        if registered.genesis.l1 != cfg.genesis.l1 || registered.genesis.l2 != cfg.genesis.l2 {
+            bail!(
+                "genesis blocks do not match the superchain registry's for chain ID {}: \
+                 expected L1 {} and L2 {}",
+                cfg.l2_chain_id,
+                registered.genesis.l1.hash,
+                registered.genesis.l2.hash
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Checks that the L2 execution client at the given URL runs the chain of the given
+/// rollup config, i.e. that it has the same chain ID and L2 genesis block.
+///
+/// A client initialized from the wrong genesis only fails much later, on the first
+/// derived block, so this is a quick check for operators setting up a node.
+pub async fn check_l2_genesis(cfg: &RollupConfig, l2_rpc_url: Url) -> Result<()> {
+    let provider = ReqwestProvider::new_http(l2_rpc_url);
+    let chain_id = provider
+        .get_chain_id()
+        .await
+        .map_err(|e| eyre!("Failed to fetch the L2 chain ID: {:?}", e))?;
+    let number = cfg.genesis.l2.number;
+    let block = provider
+        .get_block_by_number(number.into(), false)
+        .await
+        .map_err(|e| eyre!("Failed to fetch L2 block {}: {:?}", number, e))?
+        .ok_or_else(|| eyre!("L2 genesis block {} not found", number))?;
+    let hash = block.header.hash.ok_or_else(|| eyre!("L2 block {} has no hash", number))?;
+    check_genesis_match(cfg, chain_id, hash)
+}
+
+/// Checks that the given L2 chain ID and genesis block hash match the rollup config.
+fn check_genesis_match(cfg: &RollupConfig, chain_id: u64, genesis_hash: B256) -> Result<()> {
+    if chain_id != cfg.l2_chain_id {
+        bail!("L2 chain ID is {}, expected {}", chain_id, cfg.l2_chain_id);
+    }
+    if genesis_hash != cfg.genesis.l2.hash {
+        bail!(
+            "L2 genesis block {} is {}, expected {}",
+            cfg.genesis.l2.number,
+            genesis_hash,
+            cfg.genesis.l2.hash
+        );
+    }
+    Ok(())
+}
+
+/// Checks that the given hardforks, in activation order, are scheduled in that order,
+/// and that no hardfork is scheduled without the previous ones.
+fn check_fork_order(forks: &[(&str, Option<u64>)]) -> Result<()> {
+    for pair in forks.windows(2) {
+        let [(previous, previous_time), (next, next_time)] = pair else {
+            continue;
+        };
+        match (previous_time, next_time) {
+            (None, Some(_)) => bail!("{} is set, but {} is not", next, previous),
+            (Some(previous_time), Some(next_time)) if next_time < previous_time => {
+                bail!(
+                    "{} ({}) must not be before {} ({})",
+                    next,
+                    next_time,
+                    previous,
+                    previous_time
+                )
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registry_configs_are_valid() {
+        let cfg = ROLLUP_CONFIGS.get(&10).unwrap();
+        validate_rollup_config(cfg).unwrap();
+    }
+
+    #[test]
+    fn test_validate_rollup_config() {
+        let mut cfg = ROLLUP_CONFIGS.get(&10).unwrap().clone();
+        cfg.genesis.l2.hash = B256::repeat_byte(1);
+        let err = validate_rollup_config(&cfg).unwrap_err();
+        assert!(err.to_string().starts_with("genesis blocks do not match"));
+
+        cfg.batch_inbox_address = Address::ZERO;
+        let err = validate_rollup_config(&cfg).unwrap_err();
+        assert_eq!(err.to_string(), "batch_inbox_address must be set");
+    }
+
+    #[test]
+    fn test_check_genesis_match() {
+        let cfg = ROLLUP_CONFIGS.get(&10).unwrap();
+        assert!(check_genesis_match(cfg, 10, cfg.genesis.l2.hash).is_ok());
+        let err = check_genesis_match(cfg, 8453, cfg.genesis.l2.hash).unwrap_err();
+        assert_eq!(err.to_string(), "L2 chain ID is 8453, expected 10");
+        let err = check_genesis_match(cfg, 10, B256::ZERO).unwrap_err();
+        assert!(err.to_string().starts_with(&format!(
+            "L2 genesis block {} is {}",
+            cfg.genesis.l2.number,
+            B256::ZERO
+        )));
+    }
+
+    #[test]
+    fn test_check_fork_order() {
+        assert!(check_fork_order(&[("a", Some(0)), ("b", Some(10)), ("c", None)]).is_ok());
+        let err = check_fork_order(&[("a", None), ("b", Some(10))]).unwrap_err();
+        assert_eq!(err.to_string(), "b is set, but a is not");
+        let err = check_fork_order(&[("a", Some(10)), ("b", Some(5))]).unwrap_err();
+        assert_eq!(err.to_string(), "b (5) must not be before a (10)");
+    }
+
+    #[test]
+    fn test_load_rollup_config() {
+        let path =
+            std::env::temp_dir().join(format!("hera-rollup-config-{}.json", std::process::id()));
+        fs::write(&path, serde_json::to_vec(ROLLUP_CONFIGS.get(&10).unwrap()).unwrap()).unwrap();
+        assert_eq!(load_rollup_config(&path).unwrap().l2_chain_id, 10);
+
+        fs::write(&path, b"{ not json").unwrap();
+        let err = load_rollup_config(&path).unwrap_err();
+        assert!(err.to_string().starts_with("Failed to parse rollup config"));
+        fs::remove_file(path).unwrap();
+    }
+}
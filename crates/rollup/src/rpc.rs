@@ -0,0 +1,839 @@
+//! Hera RPC API
+
+use std::{collections::BTreeMap, net::SocketAddr, sync::Arc};
+
+use alloy::primitives::{Bytes, B256, U64};
+use async_trait::async_trait;
+use eyre::Result;
+use jsonrpsee::{
+    core::{RpcResult, SubscriptionResult},
+    proc_macros::rpc,
+    server::{RpcModule, Server, ServerHandle},
+    types::{
+        error::{INTERNAL_ERROR_CODE, INVALID_PARAMS_CODE},
+        ErrorObjectOwned,
+    },
+    PendingSubscriptionSink, SubscriptionMessage,
+};
+use kona_primitives::L2BlockInfo;
+use libp2p::{Multiaddr, PeerId};
+use op_net::{
+    gossip::{
+        injector::PayloadInjector,
+        peers::{Connectedness, PeerDump, PeerInfo, PeerManager, PeerStats},
+    },
+    types::node_info::NodeInfo,
+};
+use serde::{Deserialize, Serialize};
+use superchain_registry::RollupConfig;
+use tokio::sync::{broadcast::error::RecvError, watch};
+use tracing::{debug, info};
+
+use crate::{
+    AccessEntry, CrossSafety, DriverEvent, DriverEvents, InvalidMessage, LiveSystemConfig,
+    MessageValidator, OutputFetcher, OutputResponse, RpcInteropProvider, SafetyLevel,
+    SequencerControl, SyncStatus, SystemConfigHistory, MESSAGE_EXPIRY_WINDOW,
+};
+
+/// The version of the node returned by `optimism_version`.
+pub const HERA_VERSION: &str = concat!("v", env!("CARGO_PKG_VERSION"));
+
+/// The `optimism` namespace of the Hera RPC API.
+///
+/// The methods shared with op-node return the same results, so that existing
+/// tooling such as the proposer or monitoring can point at Hera.
+#[rpc(server, namespace = "optimism")]
+pub trait HeraApi {
+    /// Returns the peer ID, ENR, listen addresses, chain ID and enabled
+    /// networking features of the node.
+    #[method(name = "nodeInfo")]
+    async fn node_info(&self) -> RpcResult<NodeInfo>;
+
+    /// Returns the current L1 and L2 heads of the node.
+    #[method(name = "syncStatus")]
+    async fn sync_status(&self) -> RpcResult<SyncStatus>;
+
+    /// Returns the rollup configuration of the node.
+    #[method(name = "rollupConfig")]
+    async fn rollup_config(&self) -> RpcResult<RollupConfig>;
+
+    /// Returns the output root of the given L2 block.
+    #[method(name = "outputAtBlock")]
+    async fn output_at_block(&self, number: U64) -> RpcResult<OutputResponse>;
+
+    /// Returns the latest L1 `SystemConfig` tracked from its `ConfigUpdate` logs.
+    #[method(name = "systemConfig")]
+    async fn system_config(&self) -> RpcResult<LiveSystemConfig>;
+
+    /// Returns the version of the node.
+    #[method(name = "version")]
+    async fn version(&self) -> RpcResult<String>;
+
+    /// Subscribes to the [DriverEvent]s published from now on, over WebSocket.
+    #[subscription(
+        name = "subscribeDriverEvents" => "driverEvent",
+        unsubscribe = "unsubscribeDriverEvents",
+        item = DriverEvent
+    )]
+    async fn subscribe_driver_events(&self) -> SubscriptionResult;
+}
+
+/// The server implementation of the [HeraApiServer].
+#[derive(Debug, Clone)]
+pub struct HeraRpc {
+    /// The node information, if p2p networking is enabled.
+    node_info: Option<NodeInfo>,
+    /// The sync status published by the driver.
+    sync_status: Option<watch::Receiver<SyncStatus>>,
+    /// The rollup configuration.
+    rollup_config: Option<Arc<RollupConfig>>,
+    /// The fetcher of the L2 outputs.
+    outputs: Option<OutputFetcher>,
+    /// The tracked `SystemConfig`.
+    system_config: Option<SystemConfigHistory>,
+    /// The cross-chain safe heads, if interop messages are checked.
+    cross_safety: Option<watch::Receiver<CrossSafety>>,
+    /// The `supervisor` namespace served along, if interop messages are checked.
+    supervisor: Option<SupervisorRpc>,
+    /// The bus the driver events are published on.
+    events: Option<DriverEvents>,
+}
+
+impl HeraRpc {
+    /// Creates a new [HeraRpc] for a node with the given networking information.
+    pub const fn new(node_info: Option<NodeInfo>) -> Self {
+        Self {
+            node_info,
+            sync_status: None,
+            rollup_config: None,
+            outputs: None,
+            system_config: None,
+            cross_safety: None,
+            supervisor: None,
+            events: None,
+        }
+    }
+
+    /// Serves the sync status published by the driver.
+    pub fn with_sync_status(mut self, sync_status: watch::Receiver<SyncStatus>) -> Self {
+        self.sync_status = Some(sync_status);
+        self
+    }
+
+    /// Serves the given rollup configuration.
+    pub fn with_rollup_config(mut self, rollup_config: Arc<RollupConfig>) -> Self {
+        self.rollup_config = Some(rollup_config);
+        self
+    }
+
+    /// Serves the L2 outputs computed by the given [OutputFetcher].
+    pub fn with_outputs(mut self, outputs: OutputFetcher) -> Self {
+        self.outputs = Some(outputs);
+        self
+    }
+
+    /// Serves the latest config of the given tracked `SystemConfig`.
+    pub fn with_system_config(mut self, system_config: SystemConfigHistory) -> Self {
+        self.system_config = Some(system_config);
+        self
+    }
+
+    /// Serves the cross-chain safe heads published by a
+    /// [CrossSafetyTracker](crate::CrossSafetyTracker) in the sync status, instead of the
+    /// local heads.
+    pub fn with_cross_safety(mut self, cross_safety: watch::Receiver<CrossSafety>) -> Self {
+        self.cross_safety = Some(cross_safety);
+        self
+    }
+
+    /// Serves the driver events published on the given bus to subscribers.
+    pub fn with_events(mut self, events: DriverEvents) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    /// Serves the given `supervisor` namespace along with the `optimism` one.
+    pub fn with_supervisor(mut self, supervisor: SupervisorRpc) -> Self {
+        self.supervisor = Some(supervisor);
+        self
+    }
+
+    /// Returns the `optimism` namespace, merged with the `supervisor` one if set.
+    pub fn into_module(mut self) -> Result<RpcModule<()>> {
+        let supervisor = self.supervisor.take();
+        let mut module = self.into_rpc().remove_context();
+        if let Some(supervisor) = supervisor {
+            module.merge(supervisor.into_rpc())?;
+        }
+        Ok(module)
+    }
+
+    /// Serves the `optimism` namespace, and the `supervisor` one if set, over HTTP and
+    /// WebSocket at the given address.
+    pub async fn serve(self, addr: SocketAddr) -> Result<ServerHandle> {
+        let server = Server::builder().build(addr).await?;
+        info!("Serving RPC on {}", server.local_addr()?);
+        Ok(server.start(self.into_module()?))
+    }
+
+    /// Returns the latest sync status published by the driver.
+    fn current_sync_status(&self) -> RpcResult<SyncStatus> {
+        let status = self.sync_status.as_ref().ok_or_else(|| unavailable("sync status"))?;
+        Ok(with_cross_safety(*status.borrow(), self.cross_safety.as_ref()))
+    }
+}
+
+/// Returns the given sync status with the given cross-chain safe heads, or with the local
+/// heads if interop messages aren't checked.
+fn with_cross_safety(
+    mut status: SyncStatus,
+    cross_safety: Option<&watch::Receiver<CrossSafety>>,
+) -> SyncStatus {
+    match cross_safety {
+        Some(cross_safety) => {
+            let cross_safety = *cross_safety.borrow();
+            status.cross_unsafe_l2 = cross_safety.cross_unsafe_l2;
+            status.cross_safe_l2 = cross_safety.cross_safe_l2;
+        }
+        None => {
+            status.cross_unsafe_l2 = status.unsafe_l2;
+            status.cross_safe_l2 = status.safe_l2;
+        }
+    }
+    status
+}
+
+#[async_trait]
+impl HeraApiServer for HeraRpc {
+    async fn node_info(&self) -> RpcResult<NodeInfo> {
+        self.node_info.clone().ok_or_else(|| {
+            ErrorObjectOwned::owned(INTERNAL_ERROR_CODE, "p2p networking is disabled", None::<()>)
+        })
+    }
+
+    async fn sync_status(&self) -> RpcResult<SyncStatus> {
+        self.current_sync_status()
+    }
+
+    async fn rollup_config(&self) -> RpcResult<RollupConfig> {
+        let cfg = self.rollup_config.as_ref().ok_or_else(|| unavailable("rollup config"))?;
+        Ok(cfg.as_ref().clone())
+    }
+
+    async fn output_at_block(&self, number: U64) -> RpcResult<OutputResponse> {
+        let outputs = self.outputs.as_ref().ok_or_else(|| unavailable("L2 outputs"))?;
+        let sync_status = self.current_sync_status()?;
+        outputs
+            .output_at_block(number.to(), sync_status)
+            .await
+            .map_err(|e| ErrorObjectOwned::owned(INTERNAL_ERROR_CODE, e.to_string(), None::<()>))
+    }
+
+    async fn system_config(&self) -> RpcResult<LiveSystemConfig> {
+        let history = self.system_config.as_ref().ok_or_else(|| unavailable("system config"))?;
+        Ok(history.latest())
+    }
+
+    async fn version(&self) -> RpcResult<String> {
+        Ok(HERA_VERSION.to_string())
+    }
+
+    async fn subscribe_driver_events(
+        &self,
+        pending: PendingSubscriptionSink,
+    ) -> SubscriptionResult {
+        let Some(events) = &self.events else {
+            pending.reject(unavailable("driver events")).await;
+            return Ok(());
+        };
+        let mut events = events.subscribe();
+        let sink = pending.accept().await?;
+        loop {
+            let event = tokio::select! {
+                event = events.recv() => event,
+                _ = sink.closed() => return Ok(()),
+            };
+            match event {
+                Ok(event) => {
+                    if sink.send(SubscriptionMessage::from_json(&event)?).await.is_err() {
+                        return Ok(());
+                    }
+                }
+                Err(RecvError::Lagged(skipped)) => {
+                    debug!("Driver event subscriber skipped {} events", skipped)
+                }
+                Err(RecvError::Closed) => return Ok(()),
+            }
+        }
+    }
+}
+
+/// Returns the error of a method whose data is not served by this node.
+fn unavailable(what: &str) -> ErrorObjectOwned {
+    ErrorObjectOwned::owned(INTERNAL_ERROR_CODE, format!("{} is unavailable", what), None::<()>)
+}
+
+/// The safety levels of the OP supervisor API.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SupervisorSafetyLevel {
+    /// The message is invalid.
+    Invalid,
+    /// The message may not exist yet.
+    Unsafe,
+    /// The message is valid, but its chain may not have derived it yet.
+    CrossUnsafe,
+    /// The message was derived by its chain, whose own dependencies may not be safe.
+    LocalSafe,
+    /// The message and its dependencies were derived from L1.
+    Safe,
+    /// The message was derived from finalized L1 data.
+    Finalized,
+}
+
+/// The executing side of the messages checked by `supervisor_checkAccessList`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExecutingDescriptor {
+    /// The timestamp of the executing block.
+    pub timestamp: U64,
+    /// For how many seconds after the timestamp the messages must stay valid.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<U64>,
+}
+
+/// A block of the OP supervisor API.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SupervisorBlockId {
+    /// The block hash.
+    pub hash: B256,
+    /// The block number.
+    pub number: u64,
+}
+
+impl From<L2BlockInfo> for SupervisorBlockId {
+    fn from(block: L2BlockInfo) -> Self {
+        Self { hash: block.block_info.hash, number: block.block_info.number }
+    }
+}
+
+/// An L2 block and the L1 block it was derived from.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DerivedIdPair {
+    /// The L1 block.
+    pub source: SupervisorBlockId,
+    /// The L2 block.
+    pub derived: SupervisorBlockId,
+}
+
+impl From<L2BlockInfo> for DerivedIdPair {
+    fn from(block: L2BlockInfo) -> Self {
+        let source =
+            SupervisorBlockId { hash: block.l1_origin.hash, number: block.l1_origin.number };
+        Self { source, derived: block.into() }
+    }
+}
+
+/// The `supervisor` namespace, matching the methods of the OP supervisor API served from
+/// the interop checks of the node, for the components built against it.
+#[rpc(server, namespace = "supervisor")]
+pub trait SupervisorApi {
+    /// Checks the executing messages of the given `CrossL2Inbox` access list entries, at
+    /// the given executing block, against the given minimum safety. Returns an error if
+    /// any message is invalid or less safe.
+    #[method(name = "checkAccessList")]
+    async fn check_access_list(
+        &self,
+        inbox_entries: Vec<B256>,
+        min_safety: SupervisorSafetyLevel,
+        executing_descriptor: ExecutingDescriptor,
+    ) -> RpcResult<()>;
+
+    /// Returns the latest derived L2 block of the given chain.
+    #[method(name = "localUnsafe")]
+    async fn local_unsafe(&self, chain_id: U64) -> RpcResult<SupervisorBlockId>;
+
+    /// Returns the latest validated L2 block of the given chain, and its L1 origin.
+    #[method(name = "localSafe")]
+    async fn local_safe(&self, chain_id: U64) -> RpcResult<DerivedIdPair>;
+
+    /// Returns the latest cross-safe L2 block of the given chain, and its L1 origin.
+    #[method(name = "crossSafe")]
+    async fn cross_safe(&self, chain_id: U64) -> RpcResult<DerivedIdPair>;
+
+    /// Returns the latest finalized L2 block of the given chain.
+    #[method(name = "finalized")]
+    async fn finalized(&self, chain_id: U64) -> RpcResult<SupervisorBlockId>;
+}
+
+/// The server implementation of the [SupervisorApiServer].
+///
+/// The heads are served for the chains run by this node, and the messages are checked
+/// by a [MessageValidator], without the finalized level.
+#[derive(Debug, Clone)]
+pub struct SupervisorRpc {
+    /// The validator of the executing messages.
+    validator: MessageValidator<RpcInteropProvider>,
+    /// The sync status and cross-chain safe heads of the chains, by chain ID.
+    chains: BTreeMap<u64, (watch::Receiver<SyncStatus>, Option<watch::Receiver<CrossSafety>>)>,
+}
+
+impl SupervisorRpc {
+    /// Creates a new [SupervisorRpc] checking messages with the given validator.
+    pub const fn new(validator: MessageValidator<RpcInteropProvider>) -> Self {
+        Self { validator, chains: BTreeMap::new() }
+    }
+
+    /// Serves the heads of the given chain, from its sync status and cross-chain safe
+    /// heads.
+    pub fn with_chain(
+        mut self,
+        chain_id: u64,
+        sync_status: watch::Receiver<SyncStatus>,
+        cross_safety: Option<watch::Receiver<CrossSafety>>,
+    ) -> Self {
+        self.chains.insert(chain_id, (sync_status, cross_safety));
+        self
+    }
+
+    /// Returns the sync status of the given chain.
+    fn chain_status(&self, chain_id: U64) -> RpcResult<SyncStatus> {
+        let (status, cross_safety) = self.chains.get(&chain_id.to()).ok_or_else(|| {
+            ErrorObjectOwned::owned(
+                INVALID_PARAMS_CODE,
+                format!("unknown chain: {}", chain_id),
+                None::<()>,
+            )
+        })?;
+        Ok(with_cross_safety(*status.borrow(), cross_safety.as_ref()))
+    }
+}
+
+#[async_trait]
+impl SupervisorApiServer for SupervisorRpc {
+    async fn check_access_list(
+        &self,
+        inbox_entries: Vec<B256>,
+        min_safety: SupervisorSafetyLevel,
+        executing_descriptor: ExecutingDescriptor,
+    ) -> RpcResult<()> {
+        let required = match min_safety {
+            SupervisorSafetyLevel::Invalid |
+            SupervisorSafetyLevel::Unsafe |
+            SupervisorSafetyLevel::CrossUnsafe => SafetyLevel::CrossUnsafe,
+            SupervisorSafetyLevel::LocalSafe | SupervisorSafetyLevel::Safe => {
+                SafetyLevel::CrossSafe
+            }
+            SupervisorSafetyLevel::Finalized => {
+                return Err(ErrorObjectOwned::owned(
+                    INVALID_PARAMS_CODE,
+                    "finalized safety is not supported",
+                    None::<()>,
+                ))
+            }
+        };
+        let entries = AccessEntry::parse_list(&inbox_entries)
+            .map_err(|e| ErrorObjectOwned::owned(INVALID_PARAMS_CODE, e.to_string(), None::<()>))?;
+        let timestamp = executing_descriptor.timestamp.to::<u64>();
+        let deadline = timestamp.saturating_add(executing_descriptor.timeout.map_or(0, |t| t.to()));
+        for entry in &entries {
+            let level =
+                self.validator.check_access(entry, timestamp).await.map_err(internal_error)?;
+            let level = match level {
+                // The message must not expire before the deadline.
+                SafetyLevel::CrossUnsafe | SafetyLevel::CrossSafe
+                    if entry.timestamp.saturating_add(MESSAGE_EXPIRY_WINDOW) < deadline =>
+                {
+                    SafetyLevel::Invalid(InvalidMessage::Expired)
+                }
+                level => level,
+            };
+            if level < required {
+                let message = match level {
+                    SafetyLevel::Invalid(reason) => format!("invalid message: {}", reason),
+                    level => format!("message is {:?}, below {:?}", level, min_safety),
+                };
+                return Err(ErrorObjectOwned::owned(INVALID_PARAMS_CODE, message, None::<()>));
+            }
+        }
+        Ok(())
+    }
+
+    async fn local_unsafe(&self, chain_id: U64) -> RpcResult<SupervisorBlockId> {
+        Ok(self.chain_status(chain_id)?.unsafe_l2.into())
+    }
+
+    async fn local_safe(&self, chain_id: U64) -> RpcResult<DerivedIdPair> {
+        Ok(self.chain_status(chain_id)?.safe_l2.into())
+    }
+
+    async fn cross_safe(&self, chain_id: U64) -> RpcResult<DerivedIdPair> {
+        Ok(self.chain_status(chain_id)?.cross_safe_l2.into())
+    }
+
+    async fn finalized(&self, chain_id: U64) -> RpcResult<SupervisorBlockId> {
+        Ok(self.chain_status(chain_id)?.finalized_l2.into())
+    }
+}
+
+/// The `admin` namespace of the Hera RPC API.
+///
+/// These methods are only served on the authenticated RPC endpoint.
+#[rpc(server, namespace = "admin")]
+pub trait HeraAdminApi {
+    /// Injects a signed unsafe payload into the same path as gossiped blocks.
+    ///
+    /// The payload is encoded as a gossip message of the given blocks topic version
+    /// (`0` for v1, `1` for v2, ...). If `republish` is set, the payload is also
+    /// published on the blocks topic of its version.
+    #[method(name = "postUnsafePayload")]
+    async fn post_unsafe_payload(
+        &self,
+        version: u8,
+        payload: Bytes,
+        republish: Option<bool>,
+    ) -> RpcResult<()>;
+
+    /// Starts the sequencer on top of the unsafe head with the given hash.
+    #[method(name = "startSequencer")]
+    async fn start_sequencer(&self, unsafe_head: B256) -> RpcResult<()>;
+
+    /// Stops the sequencer, and returns the hash of its last block.
+    #[method(name = "stopSequencer")]
+    async fn stop_sequencer(&self) -> RpcResult<B256>;
+
+    /// Returns whether the sequencer is active.
+    #[method(name = "sequencerActive")]
+    async fn sequencer_active(&self) -> RpcResult<bool>;
+}
+
+/// The `opp2p` namespace of the Hera RPC API, matching op-node's peer management methods.
+///
+/// These methods are served along with the `admin` namespace.
+#[rpc(server, namespace = "opp2p")]
+pub trait HeraP2PApi {
+    /// Returns the local node as a peer.
+    #[method(name = "self")]
+    async fn self_info(&self) -> RpcResult<PeerInfo>;
+
+    /// Returns the known peers, or only the connected ones.
+    #[method(name = "peers")]
+    async fn peers(&self, connected: bool) -> RpcResult<PeerDump>;
+
+    /// Returns the peer statistics.
+    #[method(name = "peerStats")]
+    async fn peer_stats(&self) -> RpcResult<PeerStats>;
+
+    /// Dials the peer at the given multiaddress.
+    #[method(name = "connectPeer")]
+    async fn connect_peer(&self, addr: String) -> RpcResult<()>;
+
+    /// Closes the connections to the given peer.
+    #[method(name = "disconnectPeer")]
+    async fn disconnect_peer(&self, peer: String) -> RpcResult<()>;
+
+    /// Disconnects the given peer and refuses its connections until the node restarts.
+    #[method(name = "blockPeer")]
+    async fn block_peer(&self, peer: String) -> RpcResult<()>;
+}
+
+/// The server implementation of the [HeraAdminApiServer] and [HeraP2PApiServer].
+#[derive(Debug, Clone)]
+pub struct HeraAdminRpc {
+    /// The unsafe payload injector, if p2p networking is enabled.
+    injector: Option<PayloadInjector>,
+    /// Whether payloads are republished when the request does not say.
+    republish: bool,
+    /// The peer manager and the identity of the node, if p2p networking is enabled.
+    peers: Option<(PeerManager, NodeInfo)>,
+    /// Starts and stops the sequencer, if the node sequences.
+    sequencer: Option<SequencerControl>,
+}
+
+impl HeraAdminRpc {
+    /// Creates a new [HeraAdminRpc] injecting payloads with the given injector.
+    pub const fn new(injector: Option<PayloadInjector>) -> Self {
+        Self { injector, republish: false, peers: None, sequencer: None }
+    }
+
+    /// Republishes injected payloads on their blocks topic by default.
+    pub const fn with_republish(mut self, republish: bool) -> Self {
+        self.republish = republish;
+        self
+    }
+
+    /// Serves the `opp2p` methods with the given [PeerManager] of the node.
+    pub fn with_peers(mut self, peers: PeerManager, node_info: NodeInfo) -> Self {
+        self.peers = Some((peers, node_info));
+        self
+    }
+
+    /// Serves the sequencer controls with the given [SequencerControl].
+    pub fn with_sequencer(mut self, sequencer: SequencerControl) -> Self {
+        self.sequencer = Some(sequencer);
+        self
+    }
+
+    /// Serves the `admin` and `opp2p` namespaces on a trusted, unauthenticated HTTP
+    /// endpoint at the given address, e.g. for a sequencer using this node as its
+    /// gossip ingress.
+    pub async fn serve(self, addr: SocketAddr) -> Result<ServerHandle> {
+        let server = Server::builder().build(addr).await?;
+        info!("Serving admin RPC on {}", server.local_addr()?);
+        let mut module = HeraAdminApiServer::into_rpc(self.clone());
+        module.merge(HeraP2PApiServer::into_rpc(self))?;
+        Ok(server.start(module))
+    }
+
+    /// Returns the [SequencerControl], or an error if the node doesn't sequence.
+    fn sequencer_control(&self) -> RpcResult<&SequencerControl> {
+        self.sequencer.as_ref().ok_or_else(|| {
+            ErrorObjectOwned::owned(INTERNAL_ERROR_CODE, "sequencer is disabled", None::<()>)
+        })
+    }
+
+    /// Returns the [PeerManager] of the node, or an error if p2p networking is disabled.
+    fn peer_manager(&self) -> RpcResult<&(PeerManager, NodeInfo)> {
+        self.peers.as_ref().ok_or_else(|| {
+            ErrorObjectOwned::owned(INTERNAL_ERROR_CODE, "p2p networking is disabled", None::<()>)
+        })
+    }
+}
+
+/// Converts an error of the network driver into an RPC error.
+fn internal_error(err: eyre::Report) -> ErrorObjectOwned {
+    ErrorObjectOwned::owned(INTERNAL_ERROR_CODE, err.to_string(), None::<()>)
+}
+
+/// Parses a [PeerId] parameter.
+fn parse_peer_id(peer: &str) -> RpcResult<PeerId> {
+    peer.parse().map_err(|e| {
+        ErrorObjectOwned::owned(INVALID_PARAMS_CODE, format!("invalid peer ID: {}", e), None::<()>)
+    })
+}
+
+#[async_trait]
+impl HeraAdminApiServer for HeraAdminRpc {
+    async fn post_unsafe_payload(
+        &self,
+        version: u8,
+        payload: Bytes,
+        republish: Option<bool>,
+    ) -> RpcResult<()> {
+        let injector = self.injector.as_ref().ok_or_else(|| {
+            ErrorObjectOwned::owned(INTERNAL_ERROR_CODE, "p2p networking is disabled", None::<()>)
+        })?;
+        injector
+            .inject(version, payload.to_vec(), republish.unwrap_or(self.republish))
+            .await
+            .map_err(|e| ErrorObjectOwned::owned(INVALID_PARAMS_CODE, e.to_string(), None::<()>))?;
+        Ok(())
+    }
+
+    async fn start_sequencer(&self, unsafe_head: B256) -> RpcResult<()> {
+        self.sequencer_control()?.start(unsafe_head).await.map_err(internal_error)
+    }
+
+    async fn stop_sequencer(&self) -> RpcResult<B256> {
+        self.sequencer_control()?.stop().await.map_err(internal_error)
+    }
+
+    async fn sequencer_active(&self) -> RpcResult<bool> {
+        self.sequencer_control()?.active().await.map_err(internal_error)
+    }
+}
+
+#[async_trait]
+impl HeraP2PApiServer for HeraAdminRpc {
+    async fn self_info(&self) -> RpcResult<PeerInfo> {
+        let (peers, node_info) = self.peer_manager()?;
+        let enr = peers.local_enr().await.map_err(internal_error)?;
+        Ok(PeerInfo {
+            peer_id: node_info.peer_id.clone(),
+            enr: enr.or_else(|| node_info.enr.clone()).unwrap_or_default(),
+            addresses: node_info.listen_addrs.clone(),
+            connectedness: Connectedness::Connected,
+            chain_id: node_info.chain_id,
+            gossip_blocks: true,
+            ..Default::default()
+        })
+    }
+
+    async fn peers(&self, connected: bool) -> RpcResult<PeerDump> {
+        self.peer_manager()?.0.peers(connected).await.map_err(internal_error)
+    }
+
+    async fn peer_stats(&self) -> RpcResult<PeerStats> {
+        self.peer_manager()?.0.stats().await.map_err(internal_error)
+    }
+
+    async fn connect_peer(&self, addr: String) -> RpcResult<()> {
+        let addr: Multiaddr = addr.parse().map_err(|e| {
+            ErrorObjectOwned::owned(
+                INVALID_PARAMS_CODE,
+                format!("invalid multiaddress: {}", e),
+                None::<()>,
+            )
+        })?;
+        self.peer_manager()?.0.connect(addr).await.map_err(internal_error)
+    }
+
+    async fn disconnect_peer(&self, peer: String) -> RpcResult<()> {
+        let peer = parse_peer_id(&peer)?;
+        self.peer_manager()?.0.disconnect(peer).await.map_err(internal_error)
+    }
+
+    async fn block_peer(&self, peer: String) -> RpcResult<()> {
+        let peer = parse_peer_id(&peer)?;
+        self.peer_manager()?.0.block(peer).await.map_err(internal_error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use kona_primitives::{BlockInfo, L2BlockInfo};
+
+    #[tokio::test]
+    async fn test_node_info() {
+        let info = NodeInfo {
+            peer_id: "16Uiu2HAm".to_string(),
+            enr: None,
+            listen_addrs: vec!["/ip4/127.0.0.1/tcp/9222".to_string()],
+            chain_id: 10,
+            features: vec!["static-peers".to_string()],
+        };
+        assert_eq!(HeraRpc::new(Some(info.clone())).node_info().await.unwrap(), info);
+        assert!(HeraRpc::new(None).node_info().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_sync_status() {
+        assert!(HeraRpc::new(None).sync_status().await.is_err());
+
+        let status = SyncStatus {
+            head_l1: BlockInfo { number: 10, ..Default::default() },
+            ..Default::default()
+        };
+        let (_sender, receiver) = watch::channel(status);
+        let rpc = HeraRpc::new(None).with_sync_status(receiver);
+        assert_eq!(rpc.sync_status().await.unwrap(), status);
+        assert!(rpc.output_at_block(U64::from(1)).await.is_err());
+        assert_eq!(rpc.version().await.unwrap(), HERA_VERSION);
+
+        let cross_safety = CrossSafety {
+            cross_unsafe_l2: L2BlockInfo {
+                block_info: BlockInfo { number: 3, ..Default::default() },
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let (_sender, cross_receiver) = watch::channel(cross_safety);
+        let rpc = rpc.with_cross_safety(cross_receiver);
+        let status = rpc.sync_status().await.unwrap();
+        assert_eq!(status.cross_unsafe_l2, cross_safety.cross_unsafe_l2);
+        assert_eq!(status.head_l1.number, 10);
+    }
+
+    #[tokio::test]
+    async fn test_sync_status_without_interop() {
+        let status = SyncStatus {
+            unsafe_l2: L2BlockInfo {
+                block_info: BlockInfo { number: 7, ..Default::default() },
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let (_sender, receiver) = watch::channel(status);
+        let rpc = HeraRpc::new(None).with_sync_status(receiver);
+        let status = rpc.sync_status().await.unwrap();
+        assert_eq!(status.cross_unsafe_l2, status.unsafe_l2);
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_driver_events() {
+        use jsonrpsee::core::EmptyServerParams;
+
+        let events = DriverEvents::default();
+        let module = HeraRpc::new(None).with_events(events.clone()).into_module().unwrap();
+        let mut sub = module
+            .subscribe_unbounded("optimism_subscribeDriverEvents", EmptyServerParams::new())
+            .await
+            .unwrap();
+        let event = DriverEvent::UnsafeBlockReceived { number: 1, hash: B256::repeat_byte(1) };
+        events.emit(|| event.clone());
+        let (json, _) = sub.next::<serde_json::Value>().await.unwrap().unwrap();
+        assert_eq!(json, serde_json::to_value(&event).unwrap());
+
+        let module = HeraRpc::new(None).into_module().unwrap();
+        let sub = module
+            .subscribe_unbounded("optimism_subscribeDriverEvents", EmptyServerParams::new())
+            .await;
+        assert!(sub.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_supervisor() {
+        let validator = MessageValidator::new(
+            Default::default(),
+            RpcInteropProvider::new(&Default::default()).unwrap(),
+        );
+        let safe = L2BlockInfo {
+            block_info: BlockInfo { number: 5, ..Default::default() },
+            ..Default::default()
+        };
+        let (_sender, receiver) =
+            watch::channel(SyncStatus { safe_l2: safe, ..Default::default() });
+        let rpc = SupervisorRpc::new(validator).with_chain(10, receiver, None);
+        assert_eq!(rpc.local_safe(U64::from(10)).await.unwrap().derived.number, 5);
+        assert_eq!(rpc.cross_safe(U64::from(10)).await.unwrap().derived.number, 5);
+        assert!(rpc.local_unsafe(U64::from(8453)).await.is_err());
+
+        let descriptor = ExecutingDescriptor { timestamp: U64::from(1000), timeout: None };
+        let level = SupervisorSafetyLevel::CrossUnsafe;
+        assert!(rpc.check_access_list(Vec::new(), level, descriptor).await.is_ok());
+        assert!(rpc.check_access_list(vec![B256::ZERO], level, descriptor).await.is_err());
+        let level = SupervisorSafetyLevel::Finalized;
+        assert!(rpc.check_access_list(Vec::new(), level, descriptor).await.is_err());
+
+        let level: SupervisorSafetyLevel = serde_json::from_str("\"cross-unsafe\"").unwrap();
+        assert_eq!(level, SupervisorSafetyLevel::CrossUnsafe);
+    }
+
+    #[tokio::test]
+    async fn test_system_config() {
+        assert!(HeraRpc::new(None).system_config().await.is_err());
+
+        let config = LiveSystemConfig { l1_block: 10, gas_limit: 30_000_000, ..Default::default() };
+        let rpc = HeraRpc::new(None).with_system_config(SystemConfigHistory::fixed(config));
+        assert_eq!(rpc.system_config().await.unwrap(), config);
+    }
+
+    #[tokio::test]
+    async fn test_post_unsafe_payload_without_network() {
+        let rpc = HeraAdminRpc::new(None);
+        assert!(rpc.post_unsafe_payload(2, Bytes::new(), None).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_sequencer_methods_without_sequencer() {
+        let rpc = HeraAdminRpc::new(None);
+        assert!(rpc.sequencer_active().await.is_err());
+        assert!(rpc.start_sequencer(B256::ZERO).await.is_err());
+        assert!(rpc.stop_sequencer().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_p2p_methods_without_network() {
+        let rpc = HeraAdminRpc::new(None);
+        assert!(rpc.self_info().await.is_err());
+        assert!(rpc.peer_stats().await.is_err());
+        assert!(rpc.block_peer("not a peer id".to_string()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_serve_admin_rpc() {
+        let handle = HeraAdminRpc::new(None).serve("127.0.0.1:0".parse().unwrap()).await.unwrap();
+        handle.stop().unwrap();
+        handle.stopped().await;
+    }
+}
@@ -0,0 +1,517 @@
+//! Sequencer block building through the engine API
+
+use std::{
+    fmt::Debug,
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use alloy::{
+    primitives::{Address, Bloom, Bytes, Signature, B256, U256, U64},
+    providers::{Provider, ReqwestProvider},
+};
+use eyre::{bail, eyre, Result};
+use kona_derive::{
+    online::AlloyL2ChainProvider,
+    stages::{AttributesBuilder, StatefulAttributesBuilder},
+    traits::{ChainProvider, L2ChainProvider},
+};
+use kona_primitives::{BlockID, BlockInfo, L2BlockInfo, L2ExecutionPayload, L2PayloadAttributes};
+use kona_providers::AlloyChainProvider;
+use op_net::{
+    gossip::injector::PublishRequest,
+    types::{envelope::ExecutionPayloadEnvelope, payload::PayloadHash},
+};
+use serde::Deserialize;
+use serde_json::Value;
+use superchain_registry::RollupConfig;
+use tokio::{
+    sync::{mpsc, oneshot, watch},
+    time::sleep,
+};
+use tracing::{debug, info, warn};
+
+use crate::{EngineController, HeraArgsExt, SyncStatus};
+
+/// The default number of L1 blocks a new L1 origin must be behind the L1 head.
+pub const DEFAULT_SEQUENCER_L1_CONFS: u64 = 4;
+
+/// The delay before retrying to build a block after a failure.
+const RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// A command to a running [SequencerDriver], with a channel to send its result back on.
+#[derive(Debug)]
+pub enum SequencerCommand {
+    /// Starts sequencing on top of the unsafe head with the given hash.
+    Start(B256, oneshot::Sender<Result<()>>),
+    /// Stops sequencing, and sends back the hash of the last sequenced block.
+    Stop(oneshot::Sender<Result<B256>>),
+    /// Sends back whether the sequencer is active.
+    Active(oneshot::Sender<bool>),
+}
+
+/// Starts and stops a running [SequencerDriver], e.g. from a conductor through the
+/// admin RPC, to fail over between sequencer instances.
+#[derive(Debug, Clone)]
+pub struct SequencerControl(mpsc::UnboundedSender<SequencerCommand>);
+
+impl SequencerControl {
+    /// Starts sequencing on top of the unsafe head with the given hash.
+    ///
+    /// Fails if the sequencer is already active, or if its unsafe head doesn't match.
+    pub async fn start(&self, hash: B256) -> Result<()> {
+        self.request(|reply| SequencerCommand::Start(hash, reply)).await?
+    }
+
+    /// Stops sequencing once the block being built is sealed, and returns the hash of
+    /// the last sequenced block, to start the next sequencer on.
+    pub async fn stop(&self) -> Result<B256> {
+        self.request(SequencerCommand::Stop).await?
+    }
+
+    /// Returns whether the sequencer is active.
+    pub async fn active(&self) -> Result<bool> {
+        self.request(SequencerCommand::Active).await
+    }
+
+    /// Sends a command to the sequencer and waits for its result.
+    async fn request<T>(
+        &self,
+        command: impl FnOnce(oneshot::Sender<T>) -> SequencerCommand,
+    ) -> Result<T> {
+        let (sender, recv) = oneshot::channel();
+        self.0.send(command(sender)).map_err(|_| eyre!("sequencer is not running"))?;
+        recv.await.map_err(|_| eyre!("sequencer is not running"))
+    }
+}
+
+/// Selects the L1 origin of the next L2 block, with the given timestamp, following the
+/// origin selection rules of the sequencer.
+///
+/// The next L1 block, if known, is adopted as soon as the L2 block is not older than it.
+/// Past the max sequencer drift of the current origin, the next L1 block must be known.
+pub fn select_origin(
+    timestamp: u64,
+    current: BlockInfo,
+    next: Option<BlockInfo>,
+    max_sequencer_drift: u64,
+) -> Result<BlockInfo> {
+    match next {
+        Some(next) if timestamp >= next.timestamp => Ok(next),
+        Some(_) => Ok(current),
+        None if timestamp > current.timestamp + max_sequencer_drift => {
+            bail!("Past the sequencer drift of L1 origin {}, and no next origin", current.number)
+        }
+        None => Ok(current),
+    }
+}
+
+/// Returns the current unix timestamp, in seconds.
+fn unix_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs()
+}
+
+/// SequencerDriver
+///
+/// Builds the L2 blocks of a sequencer at the chain's block time, on top of the unsafe
+/// head of the L2 execution client, and gossips them.
+///
+/// Each block is started with `engine_forkchoiceUpdated` right after the previous one,
+/// giving the execution client until the block's timestamp to include transactions from
+/// its pool, then sealed with `engine_getPayload` and imported. Past the max sequencer
+/// drift, blocks are built without pool transactions until the L1 origin catches up.
+#[derive(Debug)]
+pub struct SequencerDriver<CP, L2CP>
+where
+    CP: ChainProvider + Send + Sync + Clone + Debug,
+    L2CP: L2ChainProvider + Send + Sync + Clone + Debug,
+{
+    /// The rollup configuration
+    cfg: Arc<RollupConfig>,
+    /// The controller building the blocks through the engine API
+    engine: EngineController,
+    /// The L1 chain provider, for the L1 origins
+    chain_provider: CP,
+    /// The builder of the payload attributes of the new blocks
+    builder: StatefulAttributesBuilder<CP, L2CP>,
+    /// The unsafe head, which the next block is built on
+    head: L2BlockInfo,
+    /// The number of L1 blocks a new L1 origin must be behind the L1 head
+    l1_confs: u64,
+    /// Requests the gossip event loop to sign and publish the built blocks, if enabled
+    publisher: Option<mpsc::UnboundedSender<PublishRequest>>,
+    /// The sync status of the rollup driver, for the safe and finalized heads
+    sync_status: Option<watch::Receiver<SyncStatus>>,
+    /// Whether blocks are being built, or the sequencer waits to be started
+    active: bool,
+    /// Receives the commands of the [SequencerControl], if any
+    commands: Option<mpsc::UnboundedReceiver<SequencerCommand>>,
+}
+
+impl SequencerDriver<AlloyChainProvider, AlloyL2ChainProvider> {
+    /// Creates a new standalone [SequencerDriver], building on the latest block of the
+    /// L2 execution client.
+    pub async fn standalone(args: &HeraArgsExt, cfg: Arc<RollupConfig>) -> Result<Self> {
+        let engine = args.engine_client()?.ok_or(eyre!("Missing engine API URL or JWT secret"))?;
+        let cp = AlloyChainProvider::new_http(args.l1_rpc_url.clone());
+        let mut l2_cp = AlloyL2ChainProvider::new_http(args.l2_rpc_url.clone(), cfg.clone());
+
+        let latest = ReqwestProvider::new_http(args.l2_rpc_url.clone()).get_block_number().await?;
+        let head = l2_cp
+            .l2_block_info_by_number(latest)
+            .await
+            .map_err(|e| eyre!("Failed to fetch L2 block {}: {:?}", latest, e))?;
+        info!(
+            "Sequencing on top of L2 block {} ({})",
+            head.block_info.number, head.block_info.hash
+        );
+
+        let engine =
+            EngineController::new(engine, head.block_info).with_ecotone_time(cfg.ecotone_time);
+        let builder = StatefulAttributesBuilder::new(cfg.clone(), l2_cp, cp.clone());
+        Ok(Self {
+            cfg,
+            engine,
+            chain_provider: cp,
+            builder,
+            head,
+            l1_confs: args.sequencer_l1_confs,
+            publisher: None,
+            sync_status: None,
+            active: !args.sequencer_stopped,
+            commands: None,
+        })
+    }
+}
+
+impl<CP, L2CP> SequencerDriver<CP, L2CP>
+where
+    CP: ChainProvider + Send + Sync + Clone + Debug,
+    L2CP: L2ChainProvider + Send + Sync + Clone + Debug,
+{
+    /// Gossips the built blocks through the given publish channel, e.g.
+    /// `NetworkHandle::sequencer`.
+    pub fn with_publisher(mut self, publisher: mpsc::UnboundedSender<PublishRequest>) -> Self {
+        self.publisher = Some(publisher);
+        self
+    }
+
+    /// Follows the safe and finalized heads of the given sync status, e.g. the one of the
+    /// rollup driver deriving the chain from L1.
+    pub fn with_sync_status(mut self, status: watch::Receiver<SyncStatus>) -> Self {
+        self.sync_status = Some(status);
+        self
+    }
+
+    /// Returns a [SequencerControl] to start and stop the sequencer once it runs.
+    ///
+    /// Replaces any previously returned control.
+    pub fn control(&mut self) -> SequencerControl {
+        let (sender, recv) = mpsc::unbounded_channel();
+        self.commands = Some(recv);
+        SequencerControl(sender)
+    }
+
+    /// Starts the sequencing loop. It only returns on an unrecoverable error.
+    pub async fn start(mut self) -> Result<()> {
+        metrics::gauge!("hera_sequencer_active").set(self.active as u8 as f64);
+        if !self.active {
+            info!("Sequencer is stopped, waiting for admin_startSequencer");
+        }
+        loop {
+            self.handle_commands().await;
+            if let Err(err) = self.build_next().await {
+                warn!("Failed to build L2 block {}: {:?}", self.head.block_info.number + 1, err);
+                metrics::counter!("hera_sequencer_failures_total").increment(1);
+                sleep(RETRY_DELAY).await;
+            }
+        }
+    }
+
+    /// Handles the pending [SequencerCommand]s, or waits for the sequencer to be
+    /// started if it is stopped.
+    async fn handle_commands(&mut self) {
+        let Some(mut commands) = self.commands.take() else {
+            return;
+        };
+        loop {
+            let command = if self.active {
+                match commands.try_recv() {
+                    Ok(command) => command,
+                    Err(_) => break,
+                }
+            } else {
+                match commands.recv().await {
+                    Some(command) => command,
+                    None => {
+                        warn!("Sequencer control dropped while stopped, it won't be restarted");
+                        std::future::pending().await
+                    }
+                }
+            };
+            self.handle_command(command);
+        }
+        self.commands = Some(commands);
+    }
+
+    /// Handles a [SequencerCommand] and sends its result back.
+    fn handle_command(&mut self, command: SequencerCommand) {
+        match command {
+            SequencerCommand::Start(hash, reply) => {
+                let result = if self.active {
+                    Err(eyre!("sequencer already running"))
+                } else {
+                    let unsafe_l2 = self.sync_status.as_ref().map(|s| s.borrow().unsafe_l2);
+                    start_head(hash, self.head, unsafe_l2).map(|head| {
+                        self.head = head;
+                        self.engine.set_unsafe_head(head.block_info);
+                        self.active = true;
+                        info!("Sequencer started on block {} ({})", head.block_info.number, hash);
+                    })
+                };
+                _ = reply.send(result);
+            }
+            SequencerCommand::Stop(reply) => {
+                let result = if self.active {
+                    self.active = false;
+                    info!("Sequencer stopped at block {}", self.head.block_info.number);
+                    Ok(self.head.block_info.hash)
+                } else {
+                    Err(eyre!("sequencer not running"))
+                };
+                _ = reply.send(result);
+            }
+            SequencerCommand::Active(reply) => {
+                _ = reply.send(self.active);
+            }
+        }
+        metrics::gauge!("hera_sequencer_active").set(self.active as u8 as f64);
+    }
+
+    /// Builds, seals and publishes the next L2 block.
+    async fn build_next(&mut self) -> Result<()> {
+        if let Some(status) = &self.sync_status {
+            let status = *status.borrow();
+            self.engine
+                .update_safe_heads(status.safe_l2.block_info, status.finalized_l2.block_info);
+        }
+
+        let timestamp = self.head.block_info.timestamp + self.cfg.block_time;
+        let origin = self.next_origin(timestamp).await?;
+        let epoch = BlockID { hash: origin.hash, number: origin.number };
+        let mut attributes = self
+            .builder
+            .prepare_payload_attributes(self.head, epoch)
+            .await
+            .map_err(|e| eyre!("Failed to prepare payload attributes: {:?}", e))?;
+        attributes.no_tx_pool = timestamp > origin.timestamp + self.cfg.max_sequencer_drift;
+        if attributes.no_tx_pool {
+            let number = self.head.block_info.number + 1;
+            warn!("Past the sequencer drift at block {}, excluding pool transactions", number);
+        }
+
+        let payload_id = self.engine.start_payload(&attributes).await?;
+        let now = unix_now();
+        if timestamp > now {
+            sleep(Duration::from_secs(timestamp - now)).await;
+        }
+        let (block, payload) = self.engine.seal_payload(&payload_id, &attributes).await?;
+
+        let seq_num =
+            if origin.number == self.head.l1_origin.number { self.head.seq_num + 1 } else { 0 };
+        self.head = L2BlockInfo { block_info: block, l1_origin: epoch, seq_num };
+        metrics::counter!("hera_sequencer_blocks_total").increment(1);
+        info!("Sequenced block {} ({}) with L1 origin {}", block.number, block.hash, origin.number);
+
+        self.publish(payload, &attributes).await;
+        Ok(())
+    }
+
+    /// Returns the L1 origin of the next L2 block, with the given timestamp.
+    ///
+    /// The next L1 block is only considered once it has the configured confirmations.
+    async fn next_origin(&mut self, timestamp: u64) -> Result<BlockInfo> {
+        let current = self
+            .chain_provider
+            .block_info_by_number(self.head.l1_origin.number)
+            .await
+            .map_err(|e| eyre!("Failed to fetch L1 origin: {:?}", e))?;
+        let next_number = current.number + 1;
+        let confirmed =
+            self.chain_provider.block_info_by_number(next_number + self.l1_confs).await.is_ok();
+        let next = match confirmed {
+            true => self.chain_provider.block_info_by_number(next_number).await.ok(),
+            false => None,
+        };
+        select_origin(timestamp, current, next, self.cfg.max_sequencer_drift)
+    }
+
+    /// Signs and gossips the given execution payload, if publishing is enabled.
+    async fn publish(&self, payload: Value, attributes: &L2PayloadAttributes) {
+        let Some(publisher) = &self.publisher else {
+            return;
+        };
+        let result = async {
+            let envelope = payload_envelope(payload, attributes.parent_beacon_block_root)?;
+            let (sender, recv) = oneshot::channel();
+            publisher.send((envelope, sender)).map_err(|_| eyre!("network is not running"))?;
+            recv.await.map_err(|_| eyre!("network is not running"))?
+        };
+        match result.await {
+            Ok(id) => debug!("Published sequenced block, message id {}", id),
+            Err(err) => warn!("Failed to publish sequenced block: {:?}", err),
+        }
+    }
+}
+
+/// Returns the unsafe head with the given hash to start sequencing on: the last block of
+/// this sequencer, or the unsafe head of the rollup driver, e.g. once the blocks of the
+/// previous sequencer were received.
+fn start_head(
+    hash: B256,
+    head: L2BlockInfo,
+    unsafe_l2: Option<L2BlockInfo>,
+) -> Result<L2BlockInfo> {
+    if head.block_info.hash == hash {
+        return Ok(head);
+    }
+    match unsafe_l2 {
+        Some(unsafe_l2) if unsafe_l2.block_info.hash == hash => Ok(unsafe_l2),
+        _ => bail!(
+            "block hash does not match: unsafe head is {}, not {}",
+            head.block_info.hash,
+            hash
+        ),
+    }
+}
+
+/// The execution payload returned by `engine_getPayload`.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ExecutionPayloadJson {
+    parent_hash: B256,
+    fee_recipient: Address,
+    state_root: B256,
+    receipts_root: B256,
+    logs_bloom: Bloom,
+    prev_randao: B256,
+    block_number: U64,
+    gas_limit: U64,
+    gas_used: U64,
+    timestamp: U64,
+    extra_data: Bytes,
+    base_fee_per_gas: U256,
+    block_hash: B256,
+    transactions: Vec<Bytes>,
+    withdrawals: Option<Vec<Value>>,
+    blob_gas_used: Option<U64>,
+    excess_blob_gas: Option<U64>,
+}
+
+impl From<ExecutionPayloadJson> for L2ExecutionPayload {
+    fn from(payload: ExecutionPayloadJson) -> Self {
+        Self {
+            parent_hash: payload.parent_hash,
+            fee_recipient: payload.fee_recipient,
+            state_root: payload.state_root,
+            receipts_root: payload.receipts_root,
+            logs_bloom: payload.logs_bloom,
+            prev_randao: payload.prev_randao,
+            block_number: payload.block_number.to(),
+            gas_limit: payload.gas_limit.to(),
+            gas_used: payload.gas_used.to(),
+            timestamp: payload.timestamp.to(),
+            extra_data: payload.extra_data,
+            base_fee_per_gas: Some(payload.base_fee_per_gas.saturating_to()),
+            block_hash: payload.block_hash,
+            transactions: payload.transactions,
+            deserialized_transactions: Vec::default(),
+            // L2 blocks never contain withdrawals, the list only exists from Canyon on.
+            withdrawals: payload.withdrawals.map(|_| Vec::new()),
+            blob_gas_used: payload.blob_gas_used.map(|gas| gas.to()),
+            excess_blob_gas: payload.excess_blob_gas.map(|gas| gas.to()),
+        }
+    }
+}
+
+/// Wraps the given execution payload in an unsigned envelope, to be signed on publication.
+fn payload_envelope(
+    payload: Value,
+    parent_beacon_block_root: Option<B256>,
+) -> Result<ExecutionPayloadEnvelope> {
+    let payload = serde_json::from_value::<ExecutionPayloadJson>(payload)?.into();
+    Ok(ExecutionPayloadEnvelope {
+        payload,
+        signature: Signature::from_rs_and_parity(U256::ZERO, U256::ZERO, false)?,
+        hash: PayloadHash::default(),
+        parent_beacon_block_root,
+        withdrawals_root: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block(number: u64, timestamp: u64) -> BlockInfo {
+        BlockInfo { number, timestamp, ..Default::default() }
+    }
+
+    #[test]
+    fn test_select_origin() {
+        let (current, next) = (block(10, 100), block(11, 112));
+        assert_eq!(select_origin(110, current, Some(next), 600).unwrap(), current);
+        assert_eq!(select_origin(112, current, Some(next), 600).unwrap(), next);
+        assert_eq!(select_origin(700, current, None, 600).unwrap(), current);
+        assert!(select_origin(701, current, None, 600).is_err());
+    }
+
+    #[test]
+    fn test_start_head() {
+        let l2 = |number, hash| L2BlockInfo {
+            block_info: BlockInfo { number, hash, ..Default::default() },
+            ..Default::default()
+        };
+        let (head, unsafe_l2) = (l2(10, B256::repeat_byte(1)), l2(12, B256::repeat_byte(2)));
+        assert_eq!(start_head(B256::repeat_byte(1), head, Some(unsafe_l2)).unwrap(), head);
+        assert_eq!(start_head(B256::repeat_byte(2), head, Some(unsafe_l2)).unwrap(), unsafe_l2);
+        assert!(start_head(B256::repeat_byte(2), head, None).is_err());
+        assert!(start_head(B256::repeat_byte(3), head, Some(unsafe_l2)).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_control_without_sequencer() {
+        let (sender, recv) = mpsc::unbounded_channel();
+        drop(recv);
+        let err = SequencerControl(sender).active().await.unwrap_err();
+        assert_eq!(err.to_string(), "sequencer is not running");
+    }
+
+    #[test]
+    fn test_payload_envelope() {
+        let payload = serde_json::json!({
+            "parentHash": B256::repeat_byte(1),
+            "feeRecipient": Address::ZERO,
+            "stateRoot": B256::ZERO,
+            "receiptsRoot": B256::ZERO,
+            "logsBloom": Bloom::ZERO,
+            "prevRandao": B256::ZERO,
+            "blockNumber": "0x10",
+            "gasLimit": "0x1c9c380",
+            "gasUsed": "0x0",
+            "timestamp": "0x64",
+            "extraData": "0x",
+            "baseFeePerGas": "0x7",
+            "blockHash": B256::repeat_byte(2),
+            "transactions": ["0x7e01"],
+            "withdrawals": [],
+            "blobGasUsed": "0x0",
+            "excessBlobGas": "0x0",
+        });
+        let envelope = payload_envelope(payload, Some(B256::ZERO)).unwrap();
+        assert_eq!(envelope.payload.block_number, 16);
+        assert_eq!(envelope.payload.base_fee_per_gas, Some(7));
+        assert_eq!(envelope.payload.withdrawals, Some(Vec::new()));
+        assert_eq!(envelope.payload.transactions.len(), 1);
+    }
+}
@@ -0,0 +1,122 @@
+//! Limits and metrics of the span batches
+
+use eyre::{bail, Result};
+use kona_primitives::L2AttributesWithParent;
+
+/// The span batch configuration of the driver.
+///
+/// Whether span batches are accepted at all is decided by the Delta activation of the
+/// rollup configuration, which `--hera.span-batch-activation` and
+/// `--hera.disable-span-batches` override.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SpanBatchConfig {
+    /// The max number of L2 blocks of a span batch, past which it is rejected
+    pub max_blocks: Option<u64>,
+}
+
+/// The position of a derived block in the batch it comes from.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct SpanPosition {
+    /// The index of the block in its batch
+    pub(crate) index: u64,
+    /// Whether the block is the last of its batch
+    pub(crate) is_last: bool,
+}
+
+impl SpanPosition {
+    /// Returns `true` if the block comes from a span batch of several blocks.
+    pub(crate) const fn in_span_batch(&self) -> bool {
+        self.index > 0 || !self.is_last
+    }
+
+    /// Records the validation result of the block, once per span batch: on the first
+    /// invalid block, or on the last block if all of them are valid.
+    pub(crate) fn record_validation(&self, valid: bool) {
+        if !self.in_span_batch() {
+            return;
+        }
+        if !valid {
+            metrics::counter!("hera_span_batches_invalid_total").increment(1);
+        } else if self.is_last {
+            metrics::counter!("hera_span_batches_validated_total").increment(1);
+        }
+    }
+}
+
+/// Tracks the span batches the derived payload attributes come from.
+///
+/// The attributes of a span batch are derived one block at a time, the last one being
+/// marked as such. A singular batch is a span of one block.
+#[derive(Debug, Default)]
+pub(crate) struct SpanBatchTracker {
+    /// The configured limits
+    config: SpanBatchConfig,
+    /// The number of blocks of the current span batch derived so far
+    blocks: u64,
+}
+
+impl SpanBatchTracker {
+    /// Creates a new [SpanBatchTracker] enforcing the given configuration.
+    pub(crate) const fn new(config: SpanBatchConfig) -> Self {
+        Self { config, blocks: 0 }
+    }
+
+    /// Observes the given payload attributes, and returns the position of their block in
+    /// its batch.
+    ///
+    /// Fails if the span batch exceeds the max number of blocks.
+    pub(crate) fn observe(&mut self, attributes: &L2AttributesWithParent) -> Result<SpanPosition> {
+        let position = SpanPosition { index: self.blocks, is_last: attributes.is_last_in_span };
+        self.blocks += 1;
+        if let Some(max) = self.config.max_blocks {
+            if self.blocks > max {
+                let number = attributes.parent.block_info.number + 1;
+                self.blocks = 0;
+                metrics::counter!("hera_span_batches_rejected_total").increment(1);
+                bail!("Span batch exceeds {} blocks at block {}", max, number);
+            }
+        }
+        if position.is_last {
+            let blocks = std::mem::take(&mut self.blocks);
+            if blocks > 1 {
+                metrics::counter!("hera_span_batches_total").increment(1);
+                metrics::histogram!("hera_span_batch_blocks").record(blocks as f64);
+            }
+        }
+        Ok(position)
+    }
+
+    /// Forgets the current span batch, when derivation restarts.
+    pub(crate) fn reset(&mut self) {
+        self.blocks = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn attributes(is_last_in_span: bool) -> L2AttributesWithParent {
+        L2AttributesWithParent::new(Default::default(), Default::default(), is_last_in_span)
+    }
+
+    #[test]
+    fn test_span_batch_tracker() {
+        let mut tracker = SpanBatchTracker::new(SpanBatchConfig { max_blocks: Some(3) });
+        let singular = tracker.observe(&attributes(true)).unwrap();
+        assert!(!singular.in_span_batch());
+
+        let first = tracker.observe(&attributes(false)).unwrap();
+        assert_eq!(first, SpanPosition { index: 0, is_last: false });
+        assert!(first.in_span_batch());
+        let last = tracker.observe(&attributes(true)).unwrap();
+        assert_eq!(last, SpanPosition { index: 1, is_last: true });
+        assert!(last.in_span_batch());
+
+        for _ in 0..3 {
+            tracker.observe(&attributes(false)).unwrap();
+        }
+        assert!(tracker.observe(&attributes(true)).is_err());
+        assert_eq!(tracker.observe(&attributes(true)).unwrap().index, 0);
+    }
+}
@@ -0,0 +1,45 @@
+//! Sync status of the rollup node
+
+use kona_primitives::{BlockInfo, L2BlockInfo};
+use serde::{Deserialize, Serialize};
+
+/// The sync status of the rollup node, as returned by `optimism_syncStatus`.
+///
+/// The fields are named after op-node's, so that existing tooling can read them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SyncStatus {
+    /// The L1 block the derivation pipeline is currently reading from.
+    pub current_l1: BlockInfo,
+    /// The latest known L1 block.
+    pub head_l1: BlockInfo,
+    /// The latest finalized L1 block, once known.
+    pub finalized_l1: BlockInfo,
+    /// The latest derived L2 block.
+    pub unsafe_l2: L2BlockInfo,
+    /// The latest validated L2 block.
+    pub safe_l2: L2BlockInfo,
+    /// The latest L2 block derived from finalized L1 data.
+    pub finalized_l2: L2BlockInfo,
+    /// The latest derived L2 block whose interop executing messages are valid. The same
+    /// as `unsafe_l2` unless interop messages are checked.
+    #[serde(default)]
+    pub cross_unsafe_l2: L2BlockInfo,
+    /// The latest validated L2 block whose interop executing messages are valid and safe
+    /// on their chain. The same as `safe_l2` unless interop messages are checked.
+    #[serde(default)]
+    pub cross_safe_l2: L2BlockInfo,
+}
+
+impl SyncStatus {
+    /// Creates the sync status of a node starting from the given L2 block.
+    pub fn starting_at(head: L2BlockInfo) -> Self {
+        Self {
+            unsafe_l2: head,
+            safe_l2: head,
+            finalized_l2: head,
+            cross_unsafe_l2: head,
+            cross_safe_l2: head,
+            ..Default::default()
+        }
+    }
+}
@@ -0,0 +1,53 @@
+//! Chains of the superchain registry
+
+use eyre::{bail, eyre, Result};
+use superchain_registry::{OPCHAINS, ROLLUP_CONFIGS};
+
+/// Resolves an L2 chain of the superchain registry from its name or chain ID.
+///
+/// Names are matched case-insensitively, with dashes in place of spaces and the
+/// `-testnet` suffix being optional, e.g. `op-mainnet`, `base` or `op-sepolia`.
+pub fn resolve_chain_id(chain: &str) -> Result<u64> {
+    if let Ok(chain_id) = chain.parse::<u64>() {
+        if !ROLLUP_CONFIGS.contains_key(&chain_id) {
+            bail!("Chain ID {} is not in the superchain registry", chain_id);
+        }
+        return Ok(chain_id);
+    }
+
+    let slug = chain.to_lowercase();
+    OPCHAINS
+        .values()
+        .find(|config| {
+            let name = chain_slug(&config.name);
+            name == slug || name.strip_suffix("-testnet") == Some(slug.as_str())
+        })
+        .map(|config| config.chain_id)
+        .ok_or_else(|| eyre!("Chain {} is not in the superchain registry", chain))
+}
+
+/// Returns the name of the chain with the given ID in the superchain registry, if any.
+pub fn chain_name(chain_id: u64) -> Option<String> {
+    OPCHAINS.get(&chain_id).map(|config| config.name.clone())
+}
+
+/// Returns the slug of a chain name of the registry, e.g. `op-sepolia-testnet`.
+fn chain_slug(name: &str) -> String {
+    name.to_lowercase().replace(' ', "-")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_chain_id() {
+        assert_eq!(resolve_chain_id("op-mainnet").unwrap(), 10);
+        assert_eq!(resolve_chain_id("Base").unwrap(), 8453);
+        assert_eq!(resolve_chain_id("op-sepolia").unwrap(), 11155420);
+        assert_eq!(resolve_chain_id("op-sepolia-testnet").unwrap(), 11155420);
+        assert_eq!(resolve_chain_id("10").unwrap(), 10);
+        assert!(resolve_chain_id("1").is_err());
+        assert!(resolve_chain_id("unknown").is_err());
+    }
+}
@@ -0,0 +1,208 @@
+//! Supervised background tasks
+
+use std::{future::Future, time::Duration};
+
+use eyre::Result;
+use tokio::{
+    select,
+    sync::watch,
+    task::JoinHandle,
+    time::{sleep, Instant},
+};
+use tracing::{error, info, warn};
+
+/// The default delay before restarting a task for the first time.
+pub const DEFAULT_INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+
+/// The default maximum delay before restarting a task.
+pub const DEFAULT_MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// An exponential backoff between restarts.
+#[derive(Debug, Clone)]
+pub struct Backoff {
+    /// The first delay.
+    initial: Duration,
+    /// The maximum delay.
+    max: Duration,
+    /// The next delay.
+    current: Duration,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self::new(DEFAULT_INITIAL_BACKOFF, DEFAULT_MAX_BACKOFF)
+    }
+}
+
+impl Backoff {
+    /// Creates a new [Backoff] doubling from `initial` up to `max`.
+    pub fn new(initial: Duration, max: Duration) -> Self {
+        Self { initial, max: max.max(initial), current: initial }
+    }
+
+    /// Returns the next delay, and doubles the following one.
+    pub fn next_delay(&mut self) -> Duration {
+        let delay = self.current;
+        self.current = (self.current * 2).min(self.max);
+        delay
+    }
+
+    /// Resets the delay to its initial value.
+    pub fn reset(&mut self) {
+        self.current = self.initial;
+    }
+
+    /// Records that a task exited after running for `uptime`, and returns the
+    /// delay before restarting it.
+    ///
+    /// Tasks that ran for longer than the maximum delay are considered to have
+    /// been healthy, so the backoff starts over.
+    pub fn on_exit(&mut self, uptime: Duration) -> Duration {
+        if uptime > self.max {
+            self.reset();
+        }
+        self.next_delay()
+    }
+}
+
+/// Supervisor
+///
+/// Runs background tasks that are restarted with an exponential [Backoff] whenever
+/// they exit, return an error or panic, so that a single failing component can't
+/// leave the node half-alive. Every restart increments the `hera_task_crashes_total`
+/// counter of the task.
+///
+/// All tasks are stopped when the supervisor is shut down or dropped.
+#[derive(Debug)]
+pub struct Supervisor {
+    /// Signals the supervised tasks to shut down.
+    shutdown: watch::Sender<bool>,
+    /// The backoff applied to restarts.
+    backoff: Backoff,
+    /// The handles of the supervision loops.
+    tasks: Vec<JoinHandle<()>>,
+}
+
+impl Default for Supervisor {
+    fn default() -> Self {
+        Self::new(Backoff::default())
+    }
+}
+
+impl Supervisor {
+    /// Creates a new [Supervisor] restarting tasks with the given [Backoff].
+    pub fn new(backoff: Backoff) -> Self {
+        Self { shutdown: watch::channel(false).0, backoff, tasks: Vec::new() }
+    }
+
+    /// Spawns a supervised task named `name`.
+    ///
+    /// The `task` function is called to create a new instance of the task
+    /// every time it needs to be (re)started.
+    pub fn spawn<F, Fut>(&mut self, name: &'static str, task: F)
+    where
+        F: Fn() -> Fut + Send + 'static,
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        let mut shutdown = self.shutdown.subscribe();
+        let mut backoff = self.backoff.clone();
+
+        self.tasks.push(tokio::spawn(async move {
+            loop {
+                let started = Instant::now();
+                let mut handle = tokio::spawn(task());
+                let result = select! {
+                    result = &mut handle => result,
+                    _ = shutdown.changed() => {
+                        handle.abort();
+                        return;
+                    }
+                };
+
+                match result {
+                    Ok(Ok(())) => warn!("Task {} exited", name),
+                    Ok(Err(err)) => error!(?err, "Task {} failed", name),
+                    Err(err) if err.is_panic() => error!(?err, "Task {} panicked", name),
+                    Err(_) => {
+                        info!("Task {} was cancelled", name);
+                        return;
+                    }
+                }
+                metrics::counter!("hera_task_crashes_total", "task" => name).increment(1);
+
+                let delay = backoff.on_exit(started.elapsed());
+                warn!("Restarting task {} in {:?}", name, delay);
+                select! {
+                    _ = sleep(delay) => {},
+                    _ = shutdown.changed() => return,
+                }
+            }
+        }));
+    }
+
+    /// Returns the number of supervised tasks that are still running.
+    pub fn running(&self) -> usize {
+        self.tasks.iter().filter(|task| !task.is_finished()).count()
+    }
+
+    /// Stops all supervised tasks.
+    pub fn shutdown(&self) {
+        _ = self.shutdown.send(true);
+    }
+}
+
+impl Drop for Supervisor {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    };
+
+    #[test]
+    fn test_backoff() {
+        let mut backoff = Backoff::new(Duration::from_secs(1), Duration::from_secs(5));
+        assert_eq!(backoff.next_delay(), Duration::from_secs(1));
+        assert_eq!(backoff.next_delay(), Duration::from_secs(2));
+        assert_eq!(backoff.next_delay(), Duration::from_secs(4));
+        assert_eq!(backoff.next_delay(), Duration::from_secs(5));
+
+        // A task that ran for long enough starts over.
+        assert_eq!(backoff.on_exit(Duration::from_secs(10)), Duration::from_secs(1));
+        assert_eq!(backoff.on_exit(Duration::ZERO), Duration::from_secs(2));
+    }
+
+    #[tokio::test]
+    async fn test_restart_panicking_task() {
+        let backoff = Backoff::new(Duration::from_millis(1), Duration::from_millis(1));
+        let mut supervisor = Supervisor::new(backoff);
+        let runs = Arc::new(AtomicUsize::new(0));
+
+        let counter = runs.clone();
+        supervisor.spawn("test", move || {
+            let counter = counter.clone();
+            async move {
+                if counter.fetch_add(1, Ordering::SeqCst) < 2 {
+                    panic!("task failed");
+                }
+                std::future::pending().await
+            }
+        });
+
+        while runs.load(Ordering::SeqCst) < 3 {
+            sleep(Duration::from_millis(1)).await;
+        }
+        assert_eq!(supervisor.running(), 1);
+
+        supervisor.shutdown();
+        while supervisor.running() > 0 {
+            sleep(Duration::from_millis(1)).await;
+        }
+    }
+}
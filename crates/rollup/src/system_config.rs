@@ -0,0 +1,539 @@
+//! Tracking of the live L1 `SystemConfig`
+
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use alloy::{
+    primitives::{Address, TxKind, B256, U256},
+    providers::{Provider, ReqwestProvider},
+    rpc::types::{BlockId, Filter, Log, TransactionInput, TransactionRequest},
+    sol,
+    sol_types::{SolCall, SolEvent, SolValue},
+};
+use async_trait::async_trait;
+use eyre::{bail, eyre, Result};
+use kona_primitives::L2AttributesWithParent;
+use serde::{Deserialize, Serialize};
+use superchain_registry::RollupConfig;
+use tokio::{sync::watch, time::sleep};
+use tracing::{debug, info, warn};
+use url::Url;
+
+use crate::AttributesValidator;
+
+sol! {
+    /// The parts of the L1 `SystemConfig` contract tracked by the node.
+    interface SystemConfig {
+        function batcherHash() external view returns (bytes32);
+        function gasLimit() external view returns (uint64);
+        function overhead() external view returns (uint256);
+        function scalar() external view returns (uint256);
+        function unsafeBlockSigner() external view returns (address);
+        function eip1559Denominator() external view returns (uint32);
+        function eip1559Elasticity() external view returns (uint32);
+
+        event ConfigUpdate(uint256 indexed version, uint8 indexed updateType, bytes data);
+    }
+}
+
+/// The default interval at which the `SystemConfig` logs are polled.
+pub const DEFAULT_SYSTEM_CONFIG_POLL_INTERVAL: Duration = Duration::from_secs(12);
+
+/// The number of L1 blocks with a config change kept in a [SystemConfigHistory].
+const HISTORY_CAPACITY: usize = 256;
+
+/// An update of the `SystemConfig`, emitted as a `ConfigUpdate` event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SystemConfigUpdate {
+    /// The batcher address changed.
+    Batcher(Address),
+    /// The L1 fee overhead and scalar changed. From Ecotone on, the scalar packs the
+    /// base fee and blob base fee scalars.
+    FeeScalars {
+        /// The L1 fee overhead.
+        overhead: U256,
+        /// The L1 fee scalar.
+        scalar: U256,
+    },
+    /// The L2 block gas limit changed.
+    GasLimit(u64),
+    /// The unsafe block signer changed.
+    UnsafeBlockSigner(Address),
+    /// The EIP-1559 parameters of the L2 chain changed, from Holocene on.
+    Eip1559Params {
+        /// The base fee max change denominator.
+        denominator: u32,
+        /// The elasticity multiplier.
+        elasticity: u32,
+    },
+}
+
+impl SystemConfigUpdate {
+    /// The `updateType` of the batcher address updates.
+    pub const BATCHER: u8 = 0;
+    /// The `updateType` of the fee scalars updates.
+    pub const FEE_SCALARS: u8 = 1;
+    /// The `updateType` of the gas limit updates.
+    pub const GAS_LIMIT: u8 = 2;
+    /// The `updateType` of the unsafe block signer updates.
+    pub const UNSAFE_BLOCK_SIGNER: u8 = 3;
+    /// The `updateType` of the EIP-1559 parameters updates.
+    pub const EIP_1559_PARAMS: u8 = 4;
+
+    /// Decodes the update of a `ConfigUpdate` log.
+    pub fn from_log(log: &Log) -> Result<Self> {
+        let event = SystemConfig::ConfigUpdate::decode_log_data(log.data(), true)?;
+        if event.version != U256::ZERO {
+            bail!("unsupported config update version {}", event.version);
+        }
+        let data = &event.data;
+        Ok(match event.updateType {
+            Self::BATCHER => Self::Batcher(Address::from_word(B256::abi_decode(data, true)?)),
+            Self::FEE_SCALARS => {
+                let (overhead, scalar) = <(U256, U256)>::abi_decode_params(data, true)?;
+                Self::FeeScalars { overhead, scalar }
+            }
+            Self::GAS_LIMIT => Self::GasLimit(U256::abi_decode(data, true)?.saturating_to()),
+            Self::UNSAFE_BLOCK_SIGNER => Self::UnsafeBlockSigner(Address::abi_decode(data, true)?),
+            Self::EIP_1559_PARAMS => {
+                let params = U256::abi_decode(data, true)?.saturating_to::<u64>();
+                Self::Eip1559Params {
+                    denominator: (params >> 32) as u32,
+                    elasticity: params as u32,
+                }
+            }
+            other => bail!("unknown config update type {}", other),
+        })
+    }
+}
+
+/// The `SystemConfig` as of an L1 block, as returned by `optimism_systemConfig`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LiveSystemConfig {
+    /// The L1 block the config last changed at, or was read from the contract at.
+    pub l1_block: u64,
+    /// The address the batcher transactions must be sent from.
+    pub batcher_address: Address,
+    /// The L1 fee overhead.
+    pub overhead: U256,
+    /// The L1 fee scalar.
+    pub scalar: U256,
+    /// The L2 block gas limit.
+    pub gas_limit: u64,
+    /// The unsafe block signer, if read from the contract.
+    pub unsafe_block_signer: Option<Address>,
+    /// The EIP-1559 base fee max change denominator, from Holocene on.
+    pub eip1559_denominator: Option<u32>,
+    /// The EIP-1559 elasticity multiplier, from Holocene on.
+    pub eip1559_elasticity: Option<u32>,
+}
+
+impl LiveSystemConfig {
+    /// Returns the genesis config of the given rollup config, if it has one.
+    pub fn from_genesis(cfg: &RollupConfig) -> Option<Self> {
+        let genesis = cfg.genesis.system_config.as_ref()?;
+        Some(Self {
+            l1_block: cfg.genesis.l1.number,
+            batcher_address: genesis.batcher_address,
+            overhead: genesis.overhead,
+            scalar: genesis.scalar,
+            gas_limit: genesis.gas_limit,
+            ..Default::default()
+        })
+    }
+
+    /// Applies the given update.
+    pub fn apply(&mut self, update: SystemConfigUpdate) {
+        match update {
+            SystemConfigUpdate::Batcher(address) => self.batcher_address = address,
+            SystemConfigUpdate::FeeScalars { overhead, scalar } => {
+                self.overhead = overhead;
+                self.scalar = scalar;
+            }
+            SystemConfigUpdate::GasLimit(gas_limit) => self.gas_limit = gas_limit,
+            SystemConfigUpdate::UnsafeBlockSigner(address) => {
+                self.unsafe_block_signer = Some(address)
+            }
+            SystemConfigUpdate::Eip1559Params { denominator, elasticity } => {
+                self.eip1559_denominator = Some(denominator);
+                self.eip1559_elasticity = Some(elasticity);
+            }
+        }
+    }
+
+    /// Returns the base fee and blob base fee scalars packed in the Ecotone scalar, or
+    /// `None` if it has the pre-Ecotone encoding.
+    pub fn ecotone_scalars(&self) -> Option<(u32, u32)> {
+        let bytes = self.scalar.to_be_bytes::<32>();
+        if bytes[0] != 1 {
+            return None;
+        }
+        let base_fee = u32::from_be_bytes(bytes[28..32].try_into().ok()?);
+        let blob_base_fee = u32::from_be_bytes(bytes[24..28].try_into().ok()?);
+        Some((base_fee, blob_base_fee))
+    }
+}
+
+/// The recent history of the `SystemConfig`, shared by its clones.
+///
+/// Holds the config as of every L1 block where it changed since the tracker started, so
+/// that the config of the L1 blocks being derived, behind the L1 head, can be looked up.
+#[derive(Debug, Clone)]
+pub struct SystemConfigHistory {
+    /// The configs by the L1 block they changed at.
+    configs: Arc<RwLock<BTreeMap<u64, LiveSystemConfig>>>,
+    /// The latest config.
+    latest: watch::Receiver<LiveSystemConfig>,
+}
+
+impl SystemConfigHistory {
+    /// Creates a history holding only the given config, which is never updated.
+    pub fn fixed(config: LiveSystemConfig) -> Self {
+        let (writer, history) = HistoryWriter::new(config);
+        writer.reset(config);
+        history
+    }
+
+    /// Returns the latest config.
+    pub fn latest(&self) -> LiveSystemConfig {
+        *self.latest.borrow()
+    }
+
+    /// Returns a receiver of the latest config.
+    pub fn subscribe(&self) -> watch::Receiver<LiveSystemConfig> {
+        self.latest.clone()
+    }
+
+    /// Returns the config as of the given L1 block, or `None` if the block is older than
+    /// the tracked history.
+    pub fn at(&self, l1_block: u64) -> Option<LiveSystemConfig> {
+        let configs = self.configs.read().ok()?;
+        let (first, _) = configs.first_key_value()?;
+        if l1_block < *first {
+            return None;
+        }
+        configs.range(..=l1_block).next_back().map(|(_, config)| *config)
+    }
+}
+
+/// Publishes the configs of a [SystemConfigHistory].
+#[derive(Debug)]
+struct HistoryWriter {
+    /// The configs by the L1 block they changed at.
+    configs: Arc<RwLock<BTreeMap<u64, LiveSystemConfig>>>,
+    /// The latest config.
+    latest: watch::Sender<LiveSystemConfig>,
+}
+
+impl HistoryWriter {
+    /// Creates a new empty history, whose latest config is the given one until the
+    /// contract is read.
+    fn new(initial: LiveSystemConfig) -> (Self, SystemConfigHistory) {
+        let configs = Arc::new(RwLock::new(BTreeMap::new()));
+        let (latest, recv) = watch::channel(initial);
+        (Self { configs: configs.clone(), latest }, SystemConfigHistory { configs, latest: recv })
+    }
+
+    /// Restarts the history from the given config, read from the contract.
+    fn reset(&self, config: LiveSystemConfig) {
+        if let Ok(mut configs) = self.configs.write() {
+            configs.clear();
+        }
+        self.push(config);
+    }
+
+    /// Records the given config, which changed at its L1 block.
+    fn push(&self, config: LiveSystemConfig) {
+        if let Ok(mut configs) = self.configs.write() {
+            configs.insert(config.l1_block, config);
+            while configs.len() > HISTORY_CAPACITY {
+                configs.pop_first();
+            }
+        }
+        self.latest.send_replace(config);
+    }
+}
+
+/// Tracks the live `SystemConfig` from the L1 logs.
+///
+/// On start, the config is read from the contract at the L1 head. Then its
+/// `ConfigUpdate` events are polled, and every change is recorded in a
+/// [SystemConfigHistory]. Updates of reorged L1 blocks are not rolled back.
+#[derive(Debug, Clone)]
+pub struct SystemConfigTracker {
+    /// The L1 provider.
+    provider: ReqwestProvider,
+    /// The address of the `SystemConfig` contract.
+    address: Address,
+    /// The config the history starts with until the contract is read.
+    genesis: LiveSystemConfig,
+    /// The interval at which the logs are polled.
+    poll_interval: Duration,
+}
+
+impl SystemConfigTracker {
+    /// Creates a new [SystemConfigTracker] of the `SystemConfig` of the given rollup
+    /// config over HTTP, or `None` if the rollup config has no `SystemConfig`.
+    pub fn new_http(url: Url, cfg: &RollupConfig) -> Option<Self> {
+        if cfg.l1_system_config_address == Address::ZERO {
+            return None;
+        }
+        Some(Self {
+            provider: ReqwestProvider::new_http(url),
+            address: cfg.l1_system_config_address,
+            genesis: LiveSystemConfig::from_genesis(cfg)?,
+            poll_interval: DEFAULT_SYSTEM_CONFIG_POLL_INTERVAL,
+        })
+    }
+
+    /// Sets the interval at which the logs are polled.
+    pub const fn with_poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+
+    /// Spawns the tracker, and returns the [SystemConfigHistory] it records.
+    ///
+    /// The task stops once all the clones of the history are dropped.
+    pub fn spawn(self) -> SystemConfigHistory {
+        let (writer, history) = HistoryWriter::new(self.genesis);
+        tokio::spawn(async move { self.run(writer).await });
+        history
+    }
+
+    /// Reads the current config, then polls the config updates until the history is
+    /// dropped.
+    async fn run(self, writer: HistoryWriter) {
+        let mut config = loop {
+            match self.read_config().await {
+                Ok(config) => break config,
+                Err(err) => {
+                    warn!(?err, "Failed to read the system config");
+                    sleep(self.poll_interval).await;
+                }
+            }
+        };
+        info!("Tracking the system config from L1 block {}", config.l1_block);
+        writer.reset(config);
+        let mut next = config.l1_block + 1;
+        while !writer.latest.is_closed() {
+            sleep(self.poll_interval).await;
+            match self.poll(next, &mut config, &writer).await {
+                Ok(polled) => next = polled,
+                Err(err) => warn!(?err, "Failed to poll the system config updates"),
+            }
+        }
+        debug!("System config history dropped, stopping the tracker");
+    }
+
+    /// Reads the config from the contract at the L1 head.
+    async fn read_config(&self) -> Result<LiveSystemConfig> {
+        let number = self.provider.get_block_number().await?;
+        let block = BlockId::from(number);
+        let batcher = self.call(SystemConfig::batcherHashCall {}, block).await?._0;
+        let gas_limit = self.call(SystemConfig::gasLimitCall {}, block).await?._0;
+        let overhead = self.call(SystemConfig::overheadCall {}, block).await?._0;
+        let scalar = self.call(SystemConfig::scalarCall {}, block).await?._0;
+        let signer = self.call(SystemConfig::unsafeBlockSignerCall {}, block).await?._0;
+        // Only the Holocene contracts have the EIP-1559 parameters.
+        let denominator = self.call(SystemConfig::eip1559DenominatorCall {}, block).await.ok();
+        let elasticity = self.call(SystemConfig::eip1559ElasticityCall {}, block).await.ok();
+        Ok(LiveSystemConfig {
+            l1_block: number,
+            batcher_address: Address::from_word(batcher),
+            overhead,
+            scalar,
+            gas_limit,
+            unsafe_block_signer: Some(signer),
+            eip1559_denominator: denominator.map(|d| d._0),
+            eip1559_elasticity: elasticity.map(|e| e._0),
+        })
+    }
+
+    /// Calls a view function of the contract at the given block.
+    async fn call<C: SolCall>(&self, call: C, block: BlockId) -> Result<C::Return> {
+        let tx = TransactionRequest {
+            to: Some(TxKind::Call(self.address)),
+            input: TransactionInput::new(call.abi_encode().into()),
+            ..Default::default()
+        };
+        let output = self.provider.call(&tx).block_id(block).await?;
+        C::abi_decode_returns(&output, true)
+            .map_err(|e| eyre!("Failed to decode {}: {}", C::SIGNATURE, e))
+    }
+
+    /// Applies the config updates from block `from` to the L1 head to the given config,
+    /// and returns the next block to poll from.
+    async fn poll(
+        &self,
+        from: u64,
+        config: &mut LiveSystemConfig,
+        writer: &HistoryWriter,
+    ) -> Result<u64> {
+        let head = self.provider.get_block_number().await?;
+        if head < from {
+            return Ok(from);
+        }
+        let filter = Filter::new()
+            .address(self.address)
+            .event_signature(SystemConfig::ConfigUpdate::SIGNATURE_HASH)
+            .from_block(from)
+            .to_block(head);
+        for log in self.provider.get_logs(&filter).await? {
+            let Some(number) = log.block_number.filter(|_| !log.removed) else {
+                continue;
+            };
+            match SystemConfigUpdate::from_log(&log) {
+                Ok(update) => {
+                    info!("System config updated at L1 block {}: {:?}", number, update);
+                    metrics::counter!("hera_system_config_updates_total").increment(1);
+                    config.apply(update);
+                    config.l1_block = number;
+                    writer.push(*config);
+                }
+                Err(err) => warn!(?err, "Invalid system config update at L1 block {}", number),
+            }
+        }
+        Ok(head + 1)
+    }
+}
+
+/// Checks the gas limit of the derived payload attributes against the tracked
+/// [SystemConfigHistory], before validating them with the inner validator.
+///
+/// The attributes of a block use the config of its L1 origin, which is either the origin
+/// of its parent or the next L1 block. Blocks whose origin is older than the tracked
+/// history are only validated by the inner validator.
+#[derive(Debug, Clone)]
+pub struct SystemConfigValidator {
+    /// The validator of the attributes with a matching config.
+    inner: Arc<dyn AttributesValidator + Send + Sync>,
+    /// The tracked history of the config.
+    history: SystemConfigHistory,
+}
+
+impl SystemConfigValidator {
+    /// Creates a new [SystemConfigValidator] wrapping the given validator.
+    pub fn new(
+        inner: Arc<dyn AttributesValidator + Send + Sync>,
+        history: SystemConfigHistory,
+    ) -> Self {
+        Self { inner, history }
+    }
+
+    /// Returns `false` if the gas limit of the attributes matches none of the configs
+    /// of their possible L1 origins.
+    fn check_gas_limit(&self, attributes: &L2AttributesWithParent) -> bool {
+        let Some(gas_limit) = attributes.attributes.gas_limit else {
+            return true;
+        };
+        let origin = attributes.parent.l1_origin.number;
+        let configs = [self.history.at(origin), self.history.at(origin + 1)];
+        if configs.iter().any(Option::is_none) {
+            return true;
+        }
+        configs.iter().flatten().any(|config| config.gas_limit == gas_limit)
+    }
+}
+
+#[async_trait]
+impl AttributesValidator for SystemConfigValidator {
+    async fn validate(&self, attributes: &L2AttributesWithParent) -> Result<bool> {
+        if !self.check_gas_limit(attributes) {
+            warn!(
+                "Gas limit of block {} doesn't match the system config",
+                attributes.parent.block_info.number + 1
+            );
+            metrics::counter!("hera_system_config_mismatches_total", "field" => "gas_limit")
+                .increment(1);
+            return Ok(false);
+        }
+        self.inner.validate(attributes).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::Bytes;
+
+    fn config_update(update_type: u8, data: Vec<u8>) -> Log {
+        let event = SystemConfig::ConfigUpdate {
+            version: U256::ZERO,
+            updateType: update_type,
+            data: Bytes::from(data),
+        };
+        Log {
+            inner: alloy::primitives::Log { address: Address::ZERO, data: event.encode_log_data() },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_config_updates() {
+        let batcher = Address::repeat_byte(0xbb);
+        let log = config_update(0, batcher.into_word().abi_encode());
+        assert_eq!(
+            SystemConfigUpdate::from_log(&log).unwrap(),
+            SystemConfigUpdate::Batcher(batcher)
+        );
+
+        let log = config_update(1, (U256::from(188), U256::from(684000)).abi_encode_params());
+        assert_eq!(
+            SystemConfigUpdate::from_log(&log).unwrap(),
+            SystemConfigUpdate::FeeScalars {
+                overhead: U256::from(188),
+                scalar: U256::from(684000)
+            }
+        );
+
+        let log = config_update(2, U256::from(30_000_000).abi_encode());
+        assert_eq!(
+            SystemConfigUpdate::from_log(&log).unwrap(),
+            SystemConfigUpdate::GasLimit(30_000_000)
+        );
+
+        let params = U256::from((250u64 << 32) | 6);
+        let log = config_update(4, params.abi_encode());
+        assert_eq!(
+            SystemConfigUpdate::from_log(&log).unwrap(),
+            SystemConfigUpdate::Eip1559Params { denominator: 250, elasticity: 6 }
+        );
+
+        assert!(SystemConfigUpdate::from_log(&config_update(9, Vec::new())).is_err());
+    }
+
+    #[test]
+    fn test_ecotone_scalars() {
+        let mut bytes = [0u8; 32];
+        bytes[0] = 1;
+        bytes[24..28].copy_from_slice(&810949u32.to_be_bytes());
+        bytes[28..32].copy_from_slice(&1368u32.to_be_bytes());
+        let config = LiveSystemConfig { scalar: U256::from_be_bytes(bytes), ..Default::default() };
+        assert_eq!(config.ecotone_scalars(), Some((1368, 810949)));
+
+        let config = LiveSystemConfig { scalar: U256::from(684000), ..Default::default() };
+        assert_eq!(config.ecotone_scalars(), None);
+    }
+
+    #[test]
+    fn test_history() {
+        let genesis = LiveSystemConfig { l1_block: 1, gas_limit: 15, ..Default::default() };
+        let (writer, history) = HistoryWriter::new(genesis);
+        assert_eq!(history.at(100), None);
+        assert_eq!(history.latest(), genesis);
+
+        let config = LiveSystemConfig { l1_block: 100, gas_limit: 30, ..Default::default() };
+        writer.reset(config);
+        writer.push(LiveSystemConfig { l1_block: 110, gas_limit: 60, ..config });
+        assert_eq!(history.at(99), None);
+        assert_eq!(history.at(100).unwrap().gas_limit, 30);
+        assert_eq!(history.at(109).unwrap().gas_limit, 30);
+        assert_eq!(history.at(200).unwrap().gas_limit, 60);
+        assert_eq!(history.latest().gas_limit, 60);
+    }
+}
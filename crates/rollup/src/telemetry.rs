@@ -1,17 +1,303 @@
-use std::{io::IsTerminal, net::SocketAddr};
+use std::{
+    io::IsTerminal,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+    thread,
+};
 
-use eyre::{bail, Result};
+use eyre::{bail, eyre, Result};
 use metrics_exporter_prometheus::PrometheusBuilder;
-use tracing::{info, Level};
+use opentelemetry::{trace::TracerProvider as _, KeyValue};
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::{
+    trace::{self, Sampler},
+    Resource,
+};
+use opentelemetry_semantic_conventions::resource::SERVICE_NAME;
+use tokio::runtime;
+use tracing::{error, info, Level};
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
 use tracing_subscriber::{
-    fmt::Layer as FmtLayer, layer::SubscriberExt, util::SubscriberInitExt, EnvFilter, Layer,
+    fmt::{Layer as FmtLayer, MakeWriter},
+    layer::SubscriberExt,
+    reload,
+    util::SubscriberInitExt,
+    EnvFilter, Layer, Registry,
 };
+use url::Url;
+
+use crate::metrics_compat::{OpNodeCompatRecorder, OP_NODE_HISTOGRAM_BUCKETS};
+
+/// The naming scheme used when exporting metrics.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MetricsStyle {
+    /// Export metrics under Hera's own names (`hera_*`).
+    #[default]
+    Hera,
+    /// Export metrics under op-node's names, labels and histogram buckets (`op_node_default_*`),
+    /// so that existing op-node dashboards and alert rules keep working.
+    OpNode,
+}
+
+impl std::str::FromStr for MetricsStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "hera" => Ok(MetricsStyle::Hera),
+            "op-node" => Ok(MetricsStyle::OpNode),
+            _ => Err(format!("Invalid metrics style: {}", s)),
+        }
+    }
+}
+
+/// The format of the log lines.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LogFormat {
+    /// Human readable lines, colored on terminals.
+    #[default]
+    Pretty,
+    /// One JSON object per line, e.g. for Loki or Elasticsearch.
+    Json,
+}
+
+impl std::str::FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "pretty" => Ok(LogFormat::Pretty),
+            "json" => Ok(LogFormat::Json),
+            _ => Err(format!("Invalid log format: {}", s)),
+        }
+    }
+}
+
+/// How often the log file is rotated.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LogRotation {
+    /// A new file every minute.
+    Minutely,
+    /// A new file every hour.
+    Hourly,
+    /// A new file every day.
+    #[default]
+    Daily,
+    /// A single file, never rotated.
+    Never,
+}
+
+impl std::str::FromStr for LogRotation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "minutely" => Ok(LogRotation::Minutely),
+            "hourly" => Ok(LogRotation::Hourly),
+            "daily" => Ok(LogRotation::Daily),
+            "never" => Ok(LogRotation::Never),
+            _ => Err(format!("Invalid log rotation: {}", s)),
+        }
+    }
+}
+
+impl From<LogRotation> for Rotation {
+    fn from(rotation: LogRotation) -> Self {
+        match rotation {
+            LogRotation::Minutely => Rotation::MINUTELY,
+            LogRotation::Hourly => Rotation::HOURLY,
+            LogRotation::Daily => Rotation::DAILY,
+            LogRotation::Never => Rotation::NEVER,
+        }
+    }
+}
+
+/// The logging configuration of the tracing stack.
+#[derive(Debug, Clone, Default)]
+pub struct LogConfig {
+    /// The format of the log lines.
+    pub format: LogFormat,
+    /// The log filter directives, e.g. `hera=debug,op_net=info`.
+    /// Defaults to `RUST_LOG`, or `hera=info` if unset.
+    pub filter: Option<String>,
+    /// The file the logs are also written to, suffixed with the date once rotated.
+    pub file: Option<PathBuf>,
+    /// How often the log file is rotated.
+    pub rotation: LogRotation,
+    /// The number of rotated log files kept, or all of them if unset.
+    pub max_files: Option<usize>,
+}
+
+impl LogConfig {
+    /// Returns the log filter directives the tracing stack starts with.
+    fn directives(&self) -> String {
+        self.filter
+            .clone()
+            .or_else(|| std::env::var(EnvFilter::DEFAULT_ENV).ok())
+            .unwrap_or_else(|| DEFAULT_LOG_FILTER.to_string())
+    }
+
+    /// Creates the appender of the log file, if enabled.
+    fn file_appender(&self) -> Result<Option<RollingFileAppender>> {
+        let Some(path) = &self.file else {
+            return Ok(None);
+        };
+        let prefix = path.file_name().ok_or(eyre!("Invalid log file: {:?}", path))?;
+        let dir = path.parent().filter(|dir| !dir.as_os_str().is_empty());
+        let dir = dir.unwrap_or(Path::new("."));
+        let mut builder = RollingFileAppender::builder()
+            .rotation(self.rotation.into())
+            .filename_prefix(prefix.to_string_lossy());
+        if let Some(max_files) = self.max_files {
+            builder = builder.max_log_files(max_files);
+        }
+        Ok(Some(builder.build(dir)?))
+    }
+}
+
+/// The log filter directives used when none are configured.
+const DEFAULT_LOG_FILTER: &str = "hera=info";
+
+/// The handles to reload the log filter of each output of the tracing stack,
+/// once initialized.
+static LOG_FILTER: OnceLock<Vec<reload::Handle<EnvFilter, Registry>>> = OnceLock::new();
+
+/// The log filter directives the tracing stack was initialized with.
+static INITIAL_LOG_FILTER: OnceLock<String> = OnceLock::new();
+
+/// Replaces the log filter of the tracing stack with the given directives,
+/// e.g. `hera=debug,op_net=info`.
+pub fn set_log_filter(directives: &str) -> Result<()> {
+    let handles = LOG_FILTER.get().ok_or(eyre!("Tracing stack is not initialized"))?;
+    for handle in handles {
+        let filter = EnvFilter::builder().parse(directives)?;
+        handle.reload(filter).map_err(|e| eyre!("Failed to reload log filter: {}", e))?;
+    }
+    Ok(())
+}
+
+/// Checks that the given log filter directives are valid, without applying them.
+pub fn check_log_filter(directives: &str) -> Result<()> {
+    EnvFilter::builder().parse(directives)?;
+    Ok(())
+}
+
+/// Restores the log filter the tracing stack was initialized with.
+pub fn reset_log_filter() -> Result<()> {
+    set_log_filter(INITIAL_LOG_FILTER.get().map_or(DEFAULT_LOG_FILTER, String::as_str))
+}
+
+/// Parses the given log filter directives, ignoring the invalid ones.
+fn env_filter(directives: &str) -> Result<EnvFilter> {
+    Ok(EnvFilter::builder()
+        .with_default_directive(DEFAULT_LOG_FILTER.parse()?)
+        .parse_lossy(directives))
+}
+
+/// Creates a layer formatting the logs to the given writer, with a reloadable filter.
+fn fmt_layer<W>(
+    format: LogFormat,
+    ansi: bool,
+    show_target: bool,
+    writer: W,
+    filter: reload::Layer<EnvFilter, Registry>,
+) -> Box<dyn Layer<Registry> + Send + Sync>
+where
+    W: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+{
+    match format {
+        LogFormat::Pretty => FmtLayer::new()
+            .with_ansi(ansi)
+            .with_target(show_target)
+            .with_writer(writer)
+            .with_filter(filter)
+            .boxed(),
+        LogFormat::Json => FmtLayer::new()
+            .json()
+            .with_current_span(true)
+            .with_span_list(false)
+            .with_writer(writer)
+            .with_filter(filter)
+            .boxed(),
+    }
+}
+
+/// The default service name of the exported traces.
+pub const DEFAULT_OTLP_SERVICE_NAME: &str = "hera";
+
+/// The default filter of the spans exported over OTLP.
+pub const DEFAULT_OTLP_FILTER: &str = "hera=info,rollup=info,op_net=info";
+
+/// The configuration of the OpenTelemetry trace export.
+#[derive(Debug, Clone)]
+pub struct OtlpConfig {
+    /// The gRPC endpoint of the OTLP collector, e.g. `http://localhost:4317`.
+    pub endpoint: Url,
+    /// The ratio of the traces that are sampled, between 0 and 1.
+    pub sample_ratio: f64,
+    /// The service name the traces are exported under.
+    pub service_name: String,
+    /// The filter directives of the exported spans.
+    pub filter: String,
+}
+
+impl OtlpConfig {
+    /// Creates a new [OtlpConfig] exporting all the traces to the given endpoint.
+    pub fn new(endpoint: Url) -> Self {
+        Self {
+            endpoint,
+            sample_ratio: 1.0,
+            service_name: DEFAULT_OTLP_SERVICE_NAME.to_string(),
+            filter: DEFAULT_OTLP_FILTER.to_string(),
+        }
+    }
+
+    /// Creates a layer exporting the spans to the OTLP collector in batches.
+    ///
+    /// Root spans are sampled with the configured ratio, and child spans follow the
+    /// decision of their parent, so that the traces are exported whole.
+    fn layer(&self) -> Result<Box<dyn Layer<Registry> + Send + Sync>> {
+        let exporter =
+            opentelemetry_otlp::new_exporter().tonic().with_endpoint(self.endpoint.as_str());
+        let sampler = Sampler::ParentBased(Box::new(Sampler::TraceIdRatioBased(self.sample_ratio)));
+        let resource = Resource::new([KeyValue::new(SERVICE_NAME, self.service_name.clone())]);
+        let provider = opentelemetry_otlp::new_pipeline()
+            .tracing()
+            .with_exporter(exporter)
+            .with_trace_config(
+                trace::Config::default().with_sampler(sampler).with_resource(resource),
+            )
+            .install_batch(opentelemetry_sdk::runtime::Tokio)?;
+        let tracer = provider.tracer(self.service_name.clone());
+        opentelemetry::global::set_tracer_provider(provider);
+
+        let filter = EnvFilter::builder().parse(&self.filter)?;
+        Ok(tracing_opentelemetry::layer().with_tracer(tracer).with_filter(filter).boxed())
+    }
+}
+
+/// Flushes the traces that are not exported yet, if OTLP export is enabled.
+///
+/// This function should be called before the program exits.
+pub fn shutdown_telemetry() {
+    opentelemetry::global::shutdown_tracer_provider();
+}
 
 /// Initialize the tracing stack and Prometheus metrics recorder.
 ///
-/// This function should be called at the beginning of the program.
-pub fn init_telemetry_stack(metrics_port: u16) -> Result<()> {
-    let filter = EnvFilter::builder().with_default_directive("hera=info".parse()?).from_env_lossy();
+/// The logs are written to stdout, and to a rotated file if one is configured, in the
+/// configured format. The spans are exported to an OTLP collector if one is configured.
+/// This function should be called at the beginning of the program, within the Tokio
+/// runtime.
+pub fn init_telemetry_stack(
+    metrics_port: u16,
+    metrics_style: MetricsStyle,
+    log: &LogConfig,
+    otlp: Option<&OtlpConfig>,
+) -> Result<()> {
+    let directives = log.directives();
+    let filter = env_filter(&directives)?;
 
     // Whether to use ANSI formatting and colors in the console output.
     // If unset, always use colors if stdout is a tty.
@@ -29,22 +315,66 @@ pub fn init_telemetry_stack(metrics_port: u16) -> Result<()> {
         Err(_) => filter.max_level_hint().map_or(true, |max_level| max_level > Level::INFO),
     };
 
-    let std_layer = FmtLayer::new()
-        .with_ansi(should_use_colors)
-        .with_target(should_show_target)
-        .with_writer(std::io::stdout)
-        .with_filter(filter);
+    let (filter, handle) = reload::Layer::new(filter);
+    let mut handles = vec![handle];
+    let mut layers =
+        vec![fmt_layer(log.format, should_use_colors, should_show_target, std::io::stdout, filter)];
+    if let Some(appender) = log.file_appender()? {
+        let (filter, handle) = reload::Layer::new(env_filter(&directives)?);
+        handles.push(handle);
+        layers.push(fmt_layer(log.format, false, should_show_target, appender, filter));
+    }
+    if let Some(otlp) = otlp {
+        layers.push(otlp.layer()?);
+    }
 
-    tracing_subscriber::registry().with(std_layer).try_init()?;
+    tracing_subscriber::registry().with(layers).try_init()?;
+    _ = LOG_FILTER.set(handles);
+    _ = INITIAL_LOG_FILTER.set(directives);
 
     let prometheus_addr = SocketAddr::from(([0, 0, 0, 0], metrics_port));
     let builder = PrometheusBuilder::new().with_http_listener(prometheus_addr);
 
-    if let Err(e) = builder.install() {
+    let installed = match metrics_style {
+        MetricsStyle::Hera => builder.install().map_err(|e| eyre!(e)),
+        MetricsStyle::OpNode => install_op_node_compat(builder),
+    };
+
+    if let Err(e) = installed {
         bail!("failed to install Prometheus recorder: {:?}", e);
     } else {
         info!("Telemetry initialized. Serving Prometheus metrics at: http://{}", prometheus_addr);
     }
+    if let Some(otlp) = otlp {
+        info!("Exporting traces to the OTLP collector at: {}", otlp.endpoint);
+    }
+
+    op_net::telemetry::describe_metrics();
+    metrics::gauge!("hera_up").set(1.0);
 
     Ok(())
 }
+
+/// Installs the Prometheus recorder behind an [OpNodeCompatRecorder], using op-node's
+/// histogram buckets.
+///
+/// The exporter is driven by a dedicated single-threaded runtime on a background
+/// thread, mirroring what [PrometheusBuilder::install] does internally.
+fn install_op_node_compat(builder: PrometheusBuilder) -> Result<()> {
+    let builder = builder.set_buckets(OP_NODE_HISTOGRAM_BUCKETS)?;
+
+    let runtime = runtime::Builder::new_current_thread().enable_all().build()?;
+    let (recorder, exporter) = {
+        let _guard = runtime.enter();
+        builder.build()?
+    };
+
+    thread::Builder::new().name("hera-metrics-exporter".to_string()).spawn(move || {
+        if let Err(e) = runtime.block_on(exporter) {
+            error!("Prometheus exporter stopped: {:?}", e);
+        }
+    })?;
+
+    metrics::set_global_recorder(OpNodeCompatRecorder::new(recorder))
+        .map_err(|e| eyre!("failed to set global recorder: {}", e))
+}
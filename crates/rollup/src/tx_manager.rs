@@ -0,0 +1,269 @@
+//! Submission of L1 transactions, with nonce management and fee bumping
+
+use std::time::Duration;
+
+use alloy::{
+    eips::eip2718::Encodable2718,
+    primitives::{Address, Bytes, B256},
+    providers::{
+        network::{EthereumWallet, TransactionBuilder},
+        Provider, ReqwestProvider,
+    },
+    rpc::types::{TransactionReceipt, TransactionRequest},
+    signers::local::PrivateKeySigner,
+};
+use eyre::{bail, eyre, Result};
+use tokio::time::{sleep, Instant};
+use tracing::{debug, info, warn};
+use url::Url;
+
+/// The default percentage by which the fees of a stuck transaction are bumped.
+///
+/// Execution clients only accept a replacement transaction if its fees are at least
+/// 10% higher, or 100% higher for blob transactions.
+pub const DEFAULT_FEE_BUMP_PERCENT: u64 = 10;
+
+/// The minimum fee bump of a blob transaction accepted by execution clients.
+const BLOB_FEE_BUMP_PERCENT: u64 = 100;
+
+/// The default time after which a transaction that is not included is resubmitted
+/// with bumped fees.
+pub const DEFAULT_RESUBMISSION_TIMEOUT: Duration = Duration::from_secs(48);
+
+/// The default number of times a transaction is resubmitted before giving up.
+pub const DEFAULT_MAX_RESUBMISSIONS: usize = 10;
+
+/// The interval at which the receipts of the submitted transactions are polled.
+const RECEIPT_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// The fees of a transaction.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TxFees {
+    /// The max fee per gas.
+    pub max_fee_per_gas: u128,
+    /// The max priority fee per gas.
+    pub max_priority_fee_per_gas: u128,
+    /// The max fee per blob gas, for blob transactions.
+    pub max_fee_per_blob_gas: Option<u128>,
+}
+
+impl TxFees {
+    /// Returns the fees bumped by the given percentage, and by at least one wei.
+    pub fn bump(self, percent: u64) -> Self {
+        let bump = |fee: u128| (fee * (100 + percent as u128) / 100).max(fee + 1);
+        Self {
+            max_fee_per_gas: bump(self.max_fee_per_gas),
+            max_priority_fee_per_gas: bump(self.max_priority_fee_per_gas),
+            max_fee_per_blob_gas: self.max_fee_per_blob_gas.map(bump),
+        }
+    }
+
+    /// Sets the fees of the given transaction request.
+    fn apply(&self, mut tx: TransactionRequest) -> TransactionRequest {
+        tx.max_fee_per_gas = Some(self.max_fee_per_gas);
+        tx.max_priority_fee_per_gas = Some(self.max_priority_fee_per_gas);
+        tx.max_fee_per_blob_gas = self.max_fee_per_blob_gas;
+        tx
+    }
+}
+
+/// TxManager
+///
+/// Signs and submits L1 transactions from a single account, one at a time, until they
+/// are included.
+///
+/// Nonces are tracked locally after being fetched once, so that consecutive
+/// transactions don't wait for the pending state of the L1 node. A transaction that is
+/// not included within the resubmission timeout is replaced by the same transaction
+/// with bumped fees.
+#[derive(Debug)]
+pub struct TxManager {
+    /// The L1 provider.
+    provider: ReqwestProvider,
+    /// The wallet signing the transactions.
+    wallet: EthereumWallet,
+    /// The address of the signer.
+    from: Address,
+    /// The L1 chain ID.
+    chain_id: u64,
+    /// The nonce of the next transaction, once fetched.
+    nonce: Option<u64>,
+    /// The percentage by which the fees of a stuck transaction are bumped.
+    fee_bump_percent: u64,
+    /// The time after which a transaction that is not included is resubmitted.
+    resubmission_timeout: Duration,
+    /// The number of times a transaction is resubmitted before giving up.
+    max_resubmissions: usize,
+}
+
+impl TxManager {
+    /// Creates a new [TxManager] submitting transactions signed by the given signer
+    /// to the L1 node at the given URL.
+    pub fn new(l1_rpc_url: Url, signer: PrivateKeySigner, chain_id: u64) -> Self {
+        Self {
+            provider: ReqwestProvider::new_http(l1_rpc_url),
+            from: signer.address(),
+            wallet: EthereumWallet::from(signer),
+            chain_id,
+            nonce: None,
+            fee_bump_percent: DEFAULT_FEE_BUMP_PERCENT,
+            resubmission_timeout: DEFAULT_RESUBMISSION_TIMEOUT,
+            max_resubmissions: DEFAULT_MAX_RESUBMISSIONS,
+        }
+    }
+
+    /// Sets the percentage by which the fees of a stuck transaction are bumped.
+    pub const fn with_fee_bump_percent(mut self, percent: u64) -> Self {
+        self.fee_bump_percent = percent;
+        self
+    }
+
+    /// Sets the time after which a transaction that is not included is resubmitted.
+    pub const fn with_resubmission_timeout(mut self, timeout: Duration) -> Self {
+        self.resubmission_timeout = timeout;
+        self
+    }
+
+    /// Returns the address transactions are sent from.
+    pub const fn address(&self) -> Address {
+        self.from
+    }
+
+    /// Returns the number of the latest L1 block.
+    pub async fn l1_block_number(&self) -> Result<u64> {
+        Ok(self.provider.get_block_number().await?)
+    }
+
+    /// Executes the given call against the latest L1 block, and returns its output.
+    pub async fn call(&self, tx: TransactionRequest) -> Result<Bytes> {
+        Ok(self.provider.call(&tx.with_from(self.from)).await?)
+    }
+
+    /// Signs and submits the given transaction, resubmitting it with bumped fees until
+    /// it is included, and returns its receipt.
+    pub async fn send(&mut self, tx: TransactionRequest) -> Result<TransactionReceipt> {
+        let nonce = self.next_nonce().await?;
+        let mut tx = tx.with_from(self.from).with_nonce(nonce).with_chain_id(self.chain_id);
+        let is_blob = tx.sidecar.is_some();
+        let mut fees = self.estimate_fees(is_blob).await?;
+        tx = fees.apply(tx);
+        let gas = self.provider.estimate_gas(&tx).await?;
+        tx = tx.with_gas_limit(gas);
+        let bump_percent = match is_blob {
+            true => self.fee_bump_percent.max(BLOB_FEE_BUMP_PERCENT),
+            false => self.fee_bump_percent,
+        };
+
+        let mut sent: Vec<B256> = Vec::new();
+        for attempt in 0..=self.max_resubmissions {
+            let envelope = fees
+                .apply(tx.clone())
+                .build(&self.wallet)
+                .await
+                .map_err(|e| eyre!("Failed to sign transaction: {:?}", e))?;
+            let hash = *envelope.tx_hash();
+            match self.provider.send_raw_transaction(&envelope.encoded_2718()).await {
+                Ok(_) => {
+                    debug!("Sent transaction {} with nonce {} (attempt {})", hash, nonce, attempt);
+                    metrics::counter!("hera_txmgr_sent_total").increment(1);
+                    sent.push(hash);
+                }
+                Err(err) if err.to_string().to_lowercase().contains("nonce too low") => {
+                    // A previous attempt may have been included in the meantime.
+                    if let Some(receipt) = self.find_receipt(&sent).await? {
+                        return self.included(receipt);
+                    }
+                    self.nonce = None;
+                    bail!("Nonce {} is too low: {}", nonce, err);
+                }
+                Err(err) => warn!("Failed to send transaction {}: {}", hash, err),
+            }
+
+            let deadline = Instant::now() + self.resubmission_timeout;
+            while Instant::now() < deadline {
+                if let Some(receipt) = self.find_receipt(&sent).await? {
+                    return self.included(receipt);
+                }
+                sleep(RECEIPT_POLL_INTERVAL).await;
+            }
+            fees = fees.bump(bump_percent);
+            metrics::counter!("hera_txmgr_fee_bumps_total").increment(1);
+            info!("Transaction with nonce {} not included yet, bumping fees", nonce);
+        }
+
+        // The nonce may still be used by one of the pending transactions.
+        self.nonce = None;
+        bail!("Transaction with nonce {} not included after {} attempts", nonce, sent.len())
+    }
+
+    /// Returns the nonce of the next transaction, fetching it on first use.
+    async fn next_nonce(&mut self) -> Result<u64> {
+        let nonce = match self.nonce {
+            Some(nonce) => nonce,
+            None => self.provider.get_transaction_count(self.from).pending().await?,
+        };
+        self.nonce = Some(nonce);
+        Ok(nonce)
+    }
+
+    /// Estimates the fees of a new transaction.
+    async fn estimate_fees(&self, is_blob: bool) -> Result<TxFees> {
+        let estimate = self.provider.estimate_eip1559_fees(None).await?;
+        let max_fee_per_blob_gas = match is_blob {
+            // Leave room for the blob base fee to rise until inclusion.
+            true => Some(self.provider.get_blob_base_fee().await?.max(1) * 2),
+            false => None,
+        };
+        Ok(TxFees {
+            max_fee_per_gas: estimate.max_fee_per_gas,
+            max_priority_fee_per_gas: estimate.max_priority_fee_per_gas,
+            max_fee_per_blob_gas,
+        })
+    }
+
+    /// Returns the receipt of any of the given transactions, if one was included.
+    async fn find_receipt(&self, hashes: &[B256]) -> Result<Option<TransactionReceipt>> {
+        for hash in hashes {
+            if let Some(receipt) = self.provider.get_transaction_receipt(*hash).await? {
+                return Ok(Some(receipt));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Consumes the nonce of an included transaction, and fails if it reverted.
+    fn included(&mut self, receipt: TransactionReceipt) -> Result<TransactionReceipt> {
+        self.nonce = self.nonce.map(|nonce| nonce + 1);
+        if !receipt.status() {
+            metrics::counter!("hera_txmgr_reverted_total").increment(1);
+            bail!("Transaction {} reverted", receipt.transaction_hash);
+        }
+        metrics::counter!("hera_txmgr_included_total").increment(1);
+        info!(
+            "Transaction {} included in L1 block {:?}",
+            receipt.transaction_hash, receipt.block_number
+        );
+        Ok(receipt)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bump_fees() {
+        let fees = TxFees {
+            max_fee_per_gas: 100,
+            max_priority_fee_per_gas: 1,
+            max_fee_per_blob_gas: None,
+        };
+        let bumped = fees.bump(10);
+        assert_eq!(bumped.max_fee_per_gas, 110);
+        assert_eq!(bumped.max_priority_fee_per_gas, 2);
+        assert_eq!(bumped.max_fee_per_blob_gas, None);
+
+        let blob = TxFees { max_fee_per_blob_gas: Some(50), ..fees }.bump(100);
+        assert_eq!(blob.max_fee_per_blob_gas, Some(100));
+    }
+}
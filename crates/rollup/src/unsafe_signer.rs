@@ -0,0 +1,194 @@
+//! Rotation of the unsafe block signer from the L1 `SystemConfig`
+
+use std::time::Duration;
+
+use alloy::{
+    primitives::{Address, TxKind, B256, U256},
+    providers::{Provider, ReqwestProvider},
+    rpc::types::{Filter, Log, TransactionInput, TransactionRequest},
+    sol_types::{SolCall, SolEvent},
+};
+use eyre::{eyre, Result};
+use tokio::{sync::watch, task::JoinHandle, time::sleep};
+use tracing::{debug, info, warn};
+use url::Url;
+
+use crate::system_config::{SystemConfig, SystemConfigUpdate};
+
+/// The default interval at which the `SystemConfig` logs are polled.
+pub const DEFAULT_SIGNER_POLL_INTERVAL: Duration = Duration::from_secs(12);
+
+/// Watches the L1 `SystemConfig` for unsafe block signer rotations.
+///
+/// On start, the signer is read from the contract, in case it changed while the node was
+/// down. Then the `ConfigUpdate` events of the unsafe block signer are polled, and every
+/// new signer is pushed to the gossip handler through the given sender, e.g.
+/// `NetworkHandle::unsafe_block_signer`, so that key rotations don't require a restart.
+#[derive(Debug, Clone)]
+pub struct UnsafeSignerWatcher {
+    /// The L1 provider.
+    provider: ReqwestProvider,
+    /// The address of the `SystemConfig` contract.
+    system_config: Address,
+    /// The interval at which the logs are polled.
+    poll_interval: Duration,
+}
+
+impl UnsafeSignerWatcher {
+    /// Creates a new [UnsafeSignerWatcher] of the given `SystemConfig` over HTTP.
+    pub fn new_http(url: Url, system_config: Address) -> Self {
+        Self {
+            provider: ReqwestProvider::new_http(url),
+            system_config,
+            poll_interval: DEFAULT_SIGNER_POLL_INTERVAL,
+        }
+    }
+
+    /// Sets the interval at which the logs are polled.
+    pub const fn with_poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+
+    /// Spawns the watcher, pushing the new signers to the given sender.
+    ///
+    /// The task stops once all the receivers of the sender are dropped.
+    pub fn spawn(self, signer: watch::Sender<Address>) -> JoinHandle<()> {
+        tokio::spawn(async move { self.run(&signer).await })
+    }
+
+    /// Reads the current signer, then polls the signer updates until the receivers are
+    /// dropped.
+    async fn run(self, signer: &watch::Sender<Address>) {
+        let mut next = loop {
+            match self.current_signer().await {
+                Ok((number, address)) => {
+                    update_signer(signer, address);
+                    break number + 1;
+                }
+                Err(err) => {
+                    warn!(?err, "Failed to read the unsafe block signer");
+                    sleep(self.poll_interval).await;
+                }
+            }
+        };
+        while !signer.is_closed() {
+            sleep(self.poll_interval).await;
+            match self.poll(next, signer).await {
+                Ok(polled) => next = polled,
+                Err(err) => warn!(?err, "Failed to poll the unsafe block signer updates"),
+            }
+        }
+        debug!("Unsafe block signer receivers dropped, stopping the watcher");
+    }
+
+    /// Returns the latest L1 block number and the unsafe block signer at that block.
+    async fn current_signer(&self) -> Result<(u64, Address)> {
+        let number = self.provider.get_block_number().await?;
+        let tx = TransactionRequest {
+            to: Some(TxKind::Call(self.system_config)),
+            input: TransactionInput::new(
+                SystemConfig::unsafeBlockSignerCall {}.abi_encode().into(),
+            ),
+            ..Default::default()
+        };
+        let output = self.provider.call(&tx).block_id(number.into()).await?;
+        let signer = SystemConfig::unsafeBlockSignerCall::abi_decode_returns(&output, true)?._0;
+        Ok((number, signer))
+    }
+
+    /// Applies the signer updates from block `from` to the L1 head, and returns the next
+    /// block to poll from.
+    async fn poll(&self, from: u64, signer: &watch::Sender<Address>) -> Result<u64> {
+        let head = self.provider.get_block_number().await?;
+        if head < from {
+            return Ok(from);
+        }
+        let filter = Filter::new()
+            .address(self.system_config)
+            .event_signature(SystemConfig::ConfigUpdate::SIGNATURE_HASH)
+            .topic2(B256::from(U256::from(SystemConfigUpdate::UNSAFE_BLOCK_SIGNER)))
+            .from_block(from)
+            .to_block(head);
+        for log in self.provider.get_logs(&filter).await? {
+            if log.removed {
+                continue;
+            }
+            match parse_signer_update(&log) {
+                Ok(address) => update_signer(signer, address),
+                Err(err) => warn!(?err, "Invalid unsafe block signer update"),
+            }
+        }
+        Ok(head + 1)
+    }
+}
+
+/// Pushes the given signer to the gossip handler, if it changed.
+fn update_signer(signer: &watch::Sender<Address>, address: Address) {
+    let previous = signer.send_replace(address);
+    if previous != address {
+        info!("Unsafe block signer rotated from {} to {}", previous, address);
+        metrics::counter!("hera_unsafe_signer_rotations_total").increment(1);
+    }
+}
+
+/// Returns the new signer of a `ConfigUpdate` log of the unsafe block signer.
+fn parse_signer_update(log: &Log) -> Result<Address> {
+    match SystemConfigUpdate::from_log(log)? {
+        SystemConfigUpdate::UnsafeBlockSigner(address) => Ok(address),
+        update => Err(eyre!("unexpected update {:?}", update)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::{
+        primitives::{keccak256, Bytes, LogData},
+        sol_types::SolValue,
+    };
+
+    fn config_update(update_type: u8, data: Vec<u8>) -> Log {
+        let event = SystemConfig::ConfigUpdate {
+            version: U256::ZERO,
+            updateType: update_type,
+            data: Bytes::from(data),
+        };
+        Log {
+            inner: alloy::primitives::Log { address: Address::ZERO, data: event.encode_log_data() },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_config_update_topic() {
+        assert_eq!(
+            SystemConfig::ConfigUpdate::SIGNATURE_HASH,
+            keccak256("ConfigUpdate(uint256,uint8,bytes)")
+        );
+    }
+
+    #[test]
+    fn test_parse_signer_update() {
+        let signer = Address::repeat_byte(0xaa);
+        let log = config_update(SystemConfigUpdate::UNSAFE_BLOCK_SIGNER, signer.abi_encode());
+        assert_eq!(parse_signer_update(&log).unwrap(), signer);
+
+        // A batcher address update.
+        let log = config_update(0, signer.abi_encode());
+        assert!(parse_signer_update(&log).is_err());
+
+        let log = Log {
+            inner: alloy::primitives::Log { address: Address::ZERO, data: LogData::default() },
+            ..Default::default()
+        };
+        assert!(parse_signer_update(&log).is_err());
+    }
+
+    #[test]
+    fn test_update_signer() {
+        let (signer, recv) = watch::channel(Address::ZERO);
+        update_signer(&signer, Address::repeat_byte(1));
+        assert_eq!(*recv.borrow(), Address::repeat_byte(1));
+    }
+}
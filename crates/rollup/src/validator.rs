@@ -1,25 +1,81 @@
 //! Attributes validator for the rollup node
 
-use std::fmt::Debug;
+use std::{
+    fmt::{self, Debug},
+    future::Future,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
 
 use alloy::{
-    eips::BlockNumberOrTag,
+    consensus::TxEnvelope,
+    eips::{eip2718::Encodable2718, BlockNumberOrTag},
+    primitives::{Bytes, TxKind, B256, U128},
     providers::{network::primitives::BlockTransactionsKind, Provider, ReqwestProvider},
+    rpc::types::{BlockTransactions, Transaction},
 };
+use alloy_rlp::{Encodable, Header as RlpHeader};
 use async_trait::async_trait;
-use eyre::{bail, eyre, Result};
+use eyre::{bail, eyre, Result, WrapErr};
 use kona_primitives::{L2AttributesWithParent, L2PayloadAttributes, RawTransaction};
-use reqwest::{
-    header::{AUTHORIZATION, CONTENT_TYPE},
-    Client, StatusCode,
-};
-use reth::rpc::types::{
-    engine::{Claims, JwtSecret},
-    Header,
-};
-use tracing::error;
+use reth::rpc::types::{engine::JwtSecret, Header};
+use superchain_registry::RollupConfig;
+use tokio::time::timeout;
+use tracing::{error, warn};
 use url::Url;
 
+use crate::{
+    circuit_breaker::CircuitBreaker,
+    driver::DEPOSIT_TX_TYPE,
+    engine::{EngineClient, PayloadBodiesFetcher},
+    engine_controller::new_payload_params,
+    DriverEvent, DriverEvents,
+};
+
+/// The default timeout of a single trusted L2 RPC call.
+pub const DEFAULT_TRUSTED_RPC_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The default deadline to validate a single block against the trusted L2 RPC.
+pub const DEFAULT_VALIDATION_DEADLINE: Duration = Duration::from_secs(60);
+
+/// An error returned when a validation takes too long.
+///
+/// It can be told apart from other validation errors by downcasting the
+/// returned [eyre::Report], e.g. to retry instead of halting derivation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValidationTimeout {
+    /// A single RPC call did not complete in time.
+    Call {
+        /// The RPC method that timed out.
+        method: &'static str,
+        /// The timeout of the call.
+        timeout: Duration,
+    },
+    /// The validation of a block did not complete in time.
+    Deadline {
+        /// The number of the block being validated.
+        block: u64,
+        /// The validation deadline.
+        deadline: Duration,
+    },
+}
+
+impl fmt::Display for ValidationTimeout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Call { method, timeout } => write!(f, "{} timed out after {:?}", method, timeout),
+            Self::Deadline { block, deadline } => {
+                write!(f, "validation of block {} exceeded its {:?} deadline", block, deadline)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ValidationTimeout {}
+
 /// AttributesValidator
 ///
 /// A trait that defines the interface for validating newly derived L2 attributes.
@@ -34,48 +90,149 @@ pub trait AttributesValidator: Debug {
 ///
 /// Validates the [`L2AttributesWithParent`] by fetching the associated L2 block from
 /// a trusted L2 RPC and constructing the L2 Attributes from the block.
+///
+/// The attributes are reconstructed with the rules of the hardforks active at the
+/// block, and trusted blocks that don't follow them are rejected, e.g. if the trusted
+/// node runs with a different hardfork schedule.
 #[derive(Debug, Clone)]
 pub struct TrustedValidator {
     /// The L2 provider.
     provider: ReqwestProvider,
-    /// The canyon activation timestamp.
-    canyon_activation: u64,
+    /// The rollup config, with the hardfork activation timestamps.
+    cfg: Arc<RollupConfig>,
+    /// Optional bulk fetcher of block bodies from the engine API.
+    bodies: Option<Arc<PayloadBodiesFetcher>>,
+    /// The timeout of a single RPC call.
+    call_timeout: Duration,
+    /// The deadline to validate a single block.
+    deadline: Duration,
+    /// Whether the provider supports `debug_getRawBlock`.
+    raw_block_supported: Arc<AtomicBool>,
+    /// Whether the provider exposes the `debug_*` namespace.
+    debug_namespace: bool,
 }
 
 impl TrustedValidator {
     /// Creates a new [`TrustedValidator`].
-    pub fn new(provider: ReqwestProvider, canyon_activation: u64) -> Self {
-        Self { provider, canyon_activation }
+    pub fn new(provider: ReqwestProvider, cfg: Arc<RollupConfig>) -> Self {
+        Self {
+            provider,
+            cfg,
+            bodies: None,
+            call_timeout: DEFAULT_TRUSTED_RPC_TIMEOUT,
+            deadline: DEFAULT_VALIDATION_DEADLINE,
+            raw_block_supported: Arc::new(AtomicBool::new(true)),
+            debug_namespace: true,
+        }
     }
 
     /// Creates a new [`TrustedValidator`] from the provided [Url].
-    #[allow(unused)]
-    pub fn new_http(url: Url, canyon_activation: u64) -> Self {
+    pub fn new_http(url: Url, cfg: Arc<RollupConfig>) -> Self {
         let inner = ReqwestProvider::new_http(url);
-        Self::new(inner, canyon_activation)
+        Self::new(inner, cfg)
+    }
+
+    /// Fetches block transactions in bulk from the engine API with the given
+    /// [PayloadBodiesFetcher], instead of one `debug_getRawTransaction` call per transaction.
+    pub fn with_payload_bodies(mut self, bodies: PayloadBodiesFetcher) -> Self {
+        self.bodies = Some(Arc::new(bodies));
+        self
+    }
+
+    /// Sets the timeout of a single RPC call.
+    pub fn with_call_timeout(mut self, call_timeout: Duration) -> Self {
+        self.call_timeout = call_timeout;
+        self
+    }
+
+    /// Sets the deadline to validate a single block, across all of its RPC calls.
+    pub fn with_deadline(mut self, deadline: Duration) -> Self {
+        self.deadline = deadline;
+        self
+    }
+
+    /// Re-encodes the raw transactions from hydrated blocks instead of fetching them
+    /// from the `debug_*` namespace, which most hosted RPC providers don't expose.
+    pub fn without_debug_namespace(mut self) -> Self {
+        self.debug_namespace = false;
+        self
+    }
+
+    /// Runs the given RPC call, failing with [ValidationTimeout::Call] if it doesn't
+    /// complete within the call timeout.
+    async fn call<T>(
+        &self,
+        method: &'static str,
+        fut: impl Future<Output = Result<T>>,
+    ) -> Result<T> {
+        match timeout(self.call_timeout, fut).await {
+            Ok(result) => result,
+            Err(_) => Err(ValidationTimeout::Call { method, timeout: self.call_timeout }.into()),
+        }
     }
 
     /// Fetches a block [Header] and a list of raw RLP encoded transactions from the L2 provider.
     ///
-    /// This method needs to fetch the non-hydrated block and then
-    /// fetch the raw transactions using the `debug_*` namespace.
+    /// This method needs to fetch the non-hydrated block and then fetch the raw
+    /// transactions using the `debug_*` namespace, unless a [PayloadBodiesFetcher] is
+    /// configured and knows the block. The whole raw block is fetched at once with
+    /// `debug_getRawBlock`, falling back to one `debug_getRawTransaction` call per
+    /// transaction if it fails.
     pub async fn get_block(&self, tag: BlockNumberOrTag) -> Result<(Header, Vec<RawTransaction>)> {
+        if !self.debug_namespace {
+            return self.get_hydrated_block(tag).await;
+        }
+
         // Don't hydrate the block so we only get a list of transaction hashes.
         let block = self
-            .provider
-            .get_block(tag.into(), BlockTransactionsKind::Hashes)
-            .await
-            .map_err(|e| eyre!(format!("Failed to fetch block: {:?}", e)))?
+            .call("eth_getBlockByNumber", async {
+                self.provider
+                    .get_block(tag.into(), BlockTransactionsKind::Hashes)
+                    .await
+                    .map_err(|e| eyre!(format!("Failed to fetch block: {:?}", e)))
+            })
+            .await?
             .ok_or(eyre!("Block not found"))?;
 
+        if let (Some(bodies), Some(number)) = (&self.bodies, block.header.number) {
+            let fetched =
+                self.call("engine_getPayloadBodiesByRangeV1", bodies.transactions(number)).await;
+            match fetched {
+                Ok(Some(txs)) if txs.len() == block.transactions.len() => {
+                    return Ok((block.header, txs));
+                }
+                Ok(_) => warn!("Payload body for block {} unavailable, falling back", number),
+                Err(err) => warn!(?err, "Failed to fetch payload bodies, falling back"),
+            }
+        }
+
+        // Fetch all transactions at once from the raw block, if the provider supports it.
+        if let Some(number) = block.header.number {
+            if self.raw_block_supported.load(Ordering::Relaxed) {
+                match self.get_raw_block_transactions(number).await {
+                    Ok(txs) if txs.len() == block.transactions.len() => {
+                        return Ok((block.header, txs));
+                    }
+                    Ok(_) => warn!("Raw block {} transaction count mismatch, falling back", number),
+                    Err(err) => warn!(?err, "Failed to fetch raw block {}, falling back", number),
+                }
+            }
+        }
+
         // For each transaction hash, fetch the raw transaction RLP.
         let mut txs = vec![];
         for tx in block.transactions.hashes() {
-            match self.provider.raw_request("debug_getRawTransaction".into(), [tx]).await {
+            let raw = self.call("debug_getRawTransaction", async {
+                self.provider
+                    .raw_request("debug_getRawTransaction".into(), [tx])
+                    .await
+                    .map_err(|e| eyre!("Failed to fetch transaction: {:?}", e))
+            });
+            match raw.await {
                 Ok(tx) => txs.push(tx),
                 Err(err) => {
                     error!(?err, "Failed to fetch RLP transaction");
-                    bail!("Failed to fetch transaction");
+                    return Err(err);
                 }
             }
         }
@@ -88,16 +245,64 @@ impl TrustedValidator {
         Ok((block.header, txs))
     }
 
+    /// Fetches a block [Header] and re-encodes its raw transactions from the
+    /// hydrated block, without using the `debug_*` namespace.
+    async fn get_hydrated_block(
+        &self,
+        tag: BlockNumberOrTag,
+    ) -> Result<(Header, Vec<RawTransaction>)> {
+        let block = self
+            .call("eth_getBlockByNumber", async {
+                self.provider
+                    .get_block(tag.into(), BlockTransactionsKind::Full)
+                    .await
+                    .map_err(|e| eyre!(format!("Failed to fetch block: {:?}", e)))
+            })
+            .await?
+            .ok_or(eyre!("Block not found"))?;
+
+        let BlockTransactions::Full(transactions) = &block.transactions else {
+            bail!("Block transactions are not hydrated");
+        };
+        let txs = transactions.iter().map(encode_transaction).collect::<Result<Vec<_>>>()?;
+
+        Ok((block.header, txs))
+    }
+
+    /// Fetches the raw transactions of the given block with a single `debug_getRawBlock` call.
+    ///
+    /// If the provider rejects the method, it is not used again.
+    async fn get_raw_block_transactions(&self, number: u64) -> Result<Vec<RawTransaction>> {
+        let raw: Bytes = self
+            .call("debug_getRawBlock", async {
+                self.provider
+                    .raw_request("debug_getRawBlock".into(), [format!("{:#x}", number)])
+                    .await
+                    .map_err(|e| {
+                        if e.as_error_resp().is_some() {
+                            warn!(
+                                "debug_getRawBlock unsupported, fetching transactions one by one"
+                            );
+                            self.raw_block_supported.store(false, Ordering::Relaxed);
+                        }
+                        eyre!("Failed to fetch raw block: {:?}", e)
+                    })
+            })
+            .await?;
+        raw_block_transactions(&raw)
+    }
+
     /// Gets the payload for the specified [BlockNumberOrTag].
     pub async fn get_payload(&self, tag: BlockNumberOrTag) -> Result<L2PayloadAttributes> {
         let (header, transactions) = self.get_block(tag).await?;
+        check_fork_rules(&self.cfg, &header)?;
 
         Ok(L2PayloadAttributes {
             timestamp: header.timestamp,
             prev_randao: header.mix_hash.unwrap_or_default(),
             fee_recipient: header.miner,
             // Withdrawals on optimism are always empty, *after* canyon (Shanghai) activation
-            withdrawals: (header.timestamp >= self.canyon_activation).then_some(Vec::default()),
+            withdrawals: self.cfg.is_canyon_active(header.timestamp).then_some(Vec::default()),
             parent_beacon_block_root: header.parent_beacon_block_root,
             transactions,
             no_tx_pool: true,
@@ -106,17 +311,170 @@ impl TrustedValidator {
     }
 }
 
+/// Checks that the header of a trusted block carries the fields of the hardforks active
+/// at its timestamp: withdrawals from Canyon on, and the parent beacon block root from
+/// Ecotone on.
+fn check_fork_rules(cfg: &RollupConfig, header: &Header) -> Result<()> {
+    let canyon = cfg.is_canyon_active(header.timestamp);
+    if canyon != header.withdrawals_root.is_some() {
+        bail!(
+            "Block at timestamp {} {} a withdrawals root, but Canyon is {}active",
+            header.timestamp,
+            if canyon { "has no" } else { "has" },
+            if canyon { "" } else { "not " }
+        );
+    }
+    let ecotone = cfg.is_ecotone_active(header.timestamp);
+    if ecotone != header.parent_beacon_block_root.is_some() {
+        bail!(
+            "Block at timestamp {} {} a parent beacon block root, but Ecotone is {}active",
+            header.timestamp,
+            if ecotone { "has no" } else { "has" },
+            if ecotone { "" } else { "not " }
+        );
+    }
+    Ok(())
+}
+
+/// Returns the name of the hardfork activated by the L2 block with the given timestamp,
+/// if any.
+///
+/// Activation blocks are the first built with the new rules, and the Ecotone and Fjord
+/// ones also carry the upgrade transactions, so a mismatch is worth telling apart.
+fn activated_fork(cfg: &RollupConfig, timestamp: u64) -> Option<&'static str> {
+    let parent = timestamp.saturating_sub(cfg.block_time);
+    [
+        ("Canyon", cfg.canyon_time),
+        ("Delta", cfg.delta_time),
+        ("Ecotone", cfg.ecotone_time),
+        ("Fjord", cfg.fjord_time),
+        ("Granite", cfg.granite_time),
+    ]
+    .into_iter()
+    .rev()
+    .find(|(_, time)| time.map_or(false, |time| parent < time && time <= timestamp))
+    .map(|(name, _)| name)
+}
+
+/// Extracts the EIP-2718 encoded transactions from an RLP encoded block.
+///
+/// Block transactions are encoded as RLP lists for legacy transactions, and as RLP
+/// strings wrapping their EIP-2718 encoding for typed transactions.
+fn raw_block_transactions(raw: &[u8]) -> Result<Vec<RawTransaction>> {
+    let buf = &mut &raw[..];
+    let block = RlpHeader::decode(buf)?;
+    if !block.list {
+        bail!("Raw block is not an RLP list");
+    }
+
+    // Skip the block header.
+    let header = RlpHeader::decode(buf)?;
+    advance(buf, header.payload_length)?;
+
+    let list = RlpHeader::decode(buf)?;
+    if !list.list {
+        bail!("Raw block transactions are not an RLP list");
+    }
+    let mut txs_buf = &buf[..list.payload_length.min(buf.len())];
+    if txs_buf.len() != list.payload_length {
+        bail!("Raw block transactions are truncated");
+    }
+
+    let mut txs = vec![];
+    while !txs_buf.is_empty() {
+        let start = txs_buf;
+        let tx = RlpHeader::decode(&mut txs_buf)?;
+        let header_length = start.len() - txs_buf.len();
+        advance(&mut txs_buf, tx.payload_length)?;
+        let encoded = if tx.list {
+            // Legacy transactions are included as is.
+            &start[..header_length + tx.payload_length]
+        } else {
+            &start[header_length..header_length + tx.payload_length]
+        };
+        txs.push(RawTransaction(Bytes::copy_from_slice(encoded)));
+    }
+
+    Ok(txs)
+}
+
+/// Returns the EIP-2718 encoding of a transaction from a hydrated block.
+fn encode_transaction(tx: &Transaction) -> Result<RawTransaction> {
+    if tx.transaction_type == Some(DEPOSIT_TX_TYPE) {
+        return encode_deposit(tx);
+    }
+    let envelope = TxEnvelope::try_from(tx.clone())
+        .map_err(|e| eyre!("Failed to convert transaction {}: {:?}", tx.hash, e))?;
+    Ok(RawTransaction(envelope.encoded_2718().into()))
+}
+
+/// Returns the EIP-2718 encoding of a deposit transaction from a hydrated block.
+///
+/// The deposit specific fields are not part of the standard transaction object,
+/// so they are read from its additional fields.
+fn encode_deposit(tx: &Transaction) -> Result<RawTransaction> {
+    let source_hash: B256 = tx
+        .other
+        .get_deserialized("sourceHash")
+        .ok_or(eyre!("Deposit {} has no source hash", tx.hash))??;
+    let mint: U128 = tx.other.get_deserialized("mint").transpose()?.unwrap_or_default();
+    let is_system_tx: bool =
+        tx.other.get_deserialized("isSystemTx").transpose()?.unwrap_or_default();
+
+    let mut payload = vec![];
+    source_hash.encode(&mut payload);
+    tx.from.encode(&mut payload);
+    TxKind::from(tx.to).encode(&mut payload);
+    mint.encode(&mut payload);
+    tx.value.encode(&mut payload);
+    tx.gas.encode(&mut payload);
+    is_system_tx.encode(&mut payload);
+    tx.input.encode(&mut payload);
+
+    let mut out = vec![DEPOSIT_TX_TYPE];
+    RlpHeader { list: true, payload_length: payload.len() }.encode(&mut out);
+    out.extend_from_slice(&payload);
+    Ok(RawTransaction(out.into()))
+}
+
+/// Advances the buffer by the given number of bytes.
+fn advance(buf: &mut &[u8], len: usize) -> Result<()> {
+    if buf.len() < len {
+        bail!("Unexpected end of RLP input");
+    }
+    *buf = &buf[len..];
+    Ok(())
+}
+
 #[async_trait]
 impl AttributesValidator for TrustedValidator {
     async fn validate(&self, attributes: &L2AttributesWithParent) -> Result<bool> {
         let expected = attributes.parent.block_info.number + 1;
         let tag = BlockNumberOrTag::from(expected);
 
-        match self.get_payload(tag).await {
-            Ok(payload) => Ok(attributes.attributes == payload),
+        let payload = match timeout(self.deadline, self.get_payload(tag)).await {
+            Ok(payload) => payload,
+            Err(_) => {
+                Err(ValidationTimeout::Deadline { block: expected, deadline: self.deadline }.into())
+            }
+        };
+
+        match payload {
+            Ok(payload) => {
+                let valid = attributes.attributes == payload;
+                if !valid {
+                    if let Some(fork) = activated_fork(&self.cfg, payload.timestamp) {
+                        warn!("Attributes mismatch at the {} activation block {}", fork, expected);
+                    }
+                }
+                Ok(valid)
+            }
             Err(err) => {
+                if err.downcast_ref::<ValidationTimeout>().is_some() {
+                    metrics::counter!("hera_validation_timeouts_total").increment(1);
+                }
                 error!(?err, "Failed to fetch payload for block {}", expected);
-                bail!("Failed to fetch payload for block {}: {:?}", expected, err);
+                Err(err.wrap_err(format!("Failed to fetch payload for block {}", expected)))
             }
         }
     }
@@ -126,57 +484,564 @@ impl AttributesValidator for TrustedValidator {
 ///
 /// Validates the [`L2AttributesWithParent`] by sending the attributes to an L2 engine API.
 /// The engine API will return a `VALID` or `INVALID` response.
+///
+/// Every request is bounded by a timeout, and a [CircuitBreaker] marks the engine as
+/// unhealthy after consecutive failures so that validation fails fast instead of
+/// hanging on an unresponsive engine.
+///
+/// Requests can be mirrored to a secondary engine, whose response is used when the
+/// primary engine fails or is unhealthy, e.g. while it is down for maintenance.
+///
+/// `engine_newPayloadV3` is used from Ecotone on if a rollup config is set, and
+/// `engine_newPayloadV2` otherwise.
 #[derive(Debug, Clone)]
 pub struct EngineApiValidator {
-    /// The engine API URL.
-    url: Url,
-    /// The reqwest client.
-    client: Client,
-    /// The JWT secret token for the engine API.
-    jwt_secret: JwtSecret,
+    /// The engine API client.
+    engine: EngineClient,
+    /// The rollup config, with the hardfork activation timestamps.
+    cfg: Option<Arc<RollupConfig>>,
+    /// The circuit breaker tracking the health of the engine API.
+    breaker: CircuitBreaker,
+    /// The secondary engine API that requests are mirrored to, if any.
+    secondary: Option<Box<EngineApiValidator>>,
+    /// The bus the disagreements with the secondary engine API are published on.
+    events: DriverEvents,
 }
 
 impl EngineApiValidator {
     /// Creates a new [`EngineApiValidator`] from the provided [Url] and [JwtSecret].
-    #[allow(unused)]
     pub fn new_http(url: Url, jwt: JwtSecret) -> Self {
-        Self { url, client: Client::new(), jwt_secret: jwt }
+        Self {
+            engine: EngineClient::new_http(url, jwt),
+            cfg: None,
+            breaker: CircuitBreaker::default(),
+            secondary: None,
+            events: DriverEvents::default(),
+        }
+    }
+
+    /// Mirrors every request to the given secondary engine API, and fails over to it
+    /// when the primary one fails.
+    ///
+    /// The secondary's own secondary engine, if any, is ignored.
+    pub fn with_secondary(mut self, secondary: EngineApiValidator) -> Self {
+        self.secondary = Some(Box::new(secondary));
+        self
+    }
+
+    /// Publishes the disagreements with the secondary engine API on the given bus.
+    pub fn with_events(mut self, events: DriverEvents) -> Self {
+        self.events = events;
+        self
+    }
+
+    /// Sets the rollup config, used to pick the engine API version of each payload.
+    pub fn with_rollup_config(mut self, cfg: Arc<RollupConfig>) -> Self {
+        self.cfg = Some(cfg);
+        self
+    }
+
+    /// Sets the timeout of a single engine API request.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.engine = self.engine.with_timeout(timeout);
+        self
+    }
+
+    /// Sets the [CircuitBreaker] tracking the health of the engine API.
+    pub fn with_circuit_breaker(mut self, breaker: CircuitBreaker) -> Self {
+        self.breaker = breaker;
+        self
+    }
+
+    /// Returns the [CircuitBreaker] tracking the health of the engine API.
+    pub const fn circuit_breaker(&self) -> &CircuitBreaker {
+        &self.breaker
+    }
+
+    /// Returns whether the engine API is considered healthy.
+    pub fn is_healthy(&self) -> bool {
+        self.breaker.is_healthy()
+    }
+
+    /// Sends the `engine_newPayload` request through the circuit breaker.
+    async fn checked_new_payload(&self, attributes: &L2AttributesWithParent) -> Result<bool> {
+        if !self.breaker.allow_request() {
+            bail!("Engine API is unhealthy, skipping request");
+        }
+
+        let result = self.new_payload(attributes).await;
+        match &result {
+            Ok(_) => self.breaker.record_success(),
+            Err(err) => {
+                self.breaker.record_failure();
+                if !self.breaker.is_healthy() {
+                    warn!(?err, "Engine API marked unhealthy after consecutive failures");
+                }
+            }
+        }
+        result
+    }
+
+    /// Sends the `engine_newPayload` request of the hardfork active at the payload, and
+    /// returns whether the payload is valid.
+    async fn new_payload(&self, attributes: &L2AttributesWithParent) -> Result<bool> {
+        let payload = &attributes.attributes;
+        let ecotone =
+            self.cfg.as_ref().map_or(false, |cfg| cfg.is_ecotone_active(payload.timestamp));
+        let root = ecotone.then(|| payload.parent_beacon_block_root.unwrap_or_default());
+        let (method, params) = new_payload_params(serde_json::to_value(payload)?, root);
+        let body = self.engine.request(method, params).await?;
+
+        Ok(body
+            .pointer("/result/status")
+            .and_then(|status| status.as_str())
+            .map_or(false, |status| status == "VALID"))
     }
 }
 
 #[async_trait]
 impl AttributesValidator for EngineApiValidator {
     async fn validate(&self, attributes: &L2AttributesWithParent) -> Result<bool> {
-        let request_body = serde_json::json!({
-            "id": 1,
-            "jsonrpc": "2.0",
-            "method": "engine_newPayloadV2",
-            "params": [attributes.attributes]
-        });
-
-        let claims = Claims::default();
-        let jwt = self.jwt_secret.encode(&claims)?;
-
-        let response = self
-            .client
-            .post(self.url.clone())
-            .header(CONTENT_TYPE, "application/json")
-            .header(AUTHORIZATION, format!("Bearer {}", jwt))
-            .json(&request_body)
-            .send()
-            .await?;
+        let Some(secondary) = &self.secondary else {
+            let result = self.checked_new_payload(attributes).await;
+            metrics::gauge!("hera_engine_api_healthy").set(health(&self.breaker));
+            return result;
+        };
+
+        let (result, mirrored) = tokio::join!(
+            self.checked_new_payload(attributes),
+            secondary.checked_new_payload(attributes)
+        );
+        metrics::gauge!("hera_engine_api_healthy").set(health(&self.breaker));
+        metrics::gauge!("hera_engine_api_secondary_healthy").set(health(&secondary.breaker));
 
-        let status = response.status();
-        let body = response.json::<serde_json::Value>().await?;
-        match status {
-            StatusCode::OK => Ok(body
-                .pointer("/result/status")
-                .and_then(|status| status.as_str())
-                .map_or(false, |status| status == "VALID")),
-            _ => {
-                error!(?body, "Engine API returned status: {}", status);
-                bail!("Engine API returned status: {} and body: {:#?}", status, body);
+        match (result, mirrored) {
+            (Ok(valid), Ok(mirrored)) if valid != mirrored => {
+                warn!(valid, mirrored, "Primary and secondary engine APIs disagree on payload");
+                self.events.emit(|| DriverEvent::ValidatorDisagreement {
+                    number: attributes.parent.block_info.number + 1,
+                    validator: "secondary-engine-api".to_string(),
+                    verdict: mirrored,
+                    outcome: valid,
+                });
+                Ok(valid)
             }
+            (Ok(valid), _) => Ok(valid),
+            (Err(err), mirrored) => {
+                warn!(?err, "Primary engine API failed, failing over to the secondary");
+                metrics::counter!("hera_engine_api_failovers_total").increment(1);
+                mirrored
+            }
+        }
+    }
+}
+
+/// Returns the value of the health gauge of an engine API with the given breaker.
+fn health(breaker: &CircuitBreaker) -> f64 {
+    if breaker.is_healthy() {
+        1.0
+    } else {
+        0.0
+    }
+}
+
+/// The policy deciding the outcome of a [MultiValidator] from the verdicts of its
+/// validators.
+///
+/// Validators that fail to return a verdict, e.g. because their endpoint is down,
+/// don't vote. If the policy can't be decided from the votes, validation fails.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum QuorumPolicy {
+    /// Every validator must find the attributes valid.
+    #[default]
+    All,
+    /// A single validator finding the attributes valid is enough.
+    Any,
+    /// Most validators must vote, and most votes must find the attributes valid.
+    Majority,
+}
+
+impl QuorumPolicy {
+    /// Decides the outcome of a validation from the verdicts of all validators, `None`
+    /// standing for a validator that failed. Returns `None` if it can't be decided.
+    pub fn decide(&self, verdicts: &[Option<bool>]) -> Option<bool> {
+        let valid = verdicts.iter().filter(|v| **v == Some(true)).count();
+        let invalid = verdicts.iter().filter(|v| **v == Some(false)).count();
+        match self {
+            Self::All if invalid > 0 => Some(false),
+            Self::All => (valid == verdicts.len()).then_some(true),
+            Self::Any if valid > 0 => Some(true),
+            Self::Any => (invalid > 0).then_some(false),
+            Self::Majority => {
+                let votes = valid + invalid;
+                (votes * 2 > verdicts.len()).then_some(valid * 2 > votes)
+            }
+        }
+    }
+}
+
+impl std::str::FromStr for QuorumPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "all" => Ok(Self::All),
+            "any" => Ok(Self::Any),
+            "majority" => Ok(Self::Majority),
+            _ => Err(format!("Invalid quorum policy: {}", s)),
+        }
+    }
+}
+
+/// An [AttributesValidator] running a set of validators concurrently, and deciding the
+/// outcome from their verdicts with a [QuorumPolicy].
+///
+/// The latency of each validator is recorded, along with how often its verdict
+/// disagrees with the outcome.
+#[derive(Debug)]
+pub struct MultiValidator {
+    /// The validators, with the name they are reported under.
+    validators: Vec<(String, Arc<dyn AttributesValidator + Send + Sync>)>,
+    /// The policy deciding the outcome.
+    policy: QuorumPolicy,
+    /// The bus the disagreements with the outcome are published on.
+    events: DriverEvents,
+}
+
+impl MultiValidator {
+    /// Creates a new [MultiValidator] without validators, deciding with the given policy.
+    pub fn new(policy: QuorumPolicy) -> Self {
+        Self { validators: Vec::new(), policy, events: DriverEvents::default() }
+    }
+
+    /// Publishes the disagreements with the outcome on the given bus.
+    pub fn with_events(mut self, events: DriverEvents) -> Self {
+        self.events = events;
+        self
+    }
+
+    /// Adds a validator, reported under the given name.
+    pub fn with_validator(
+        mut self,
+        name: impl Into<String>,
+        validator: Arc<dyn AttributesValidator + Send + Sync>,
+    ) -> Self {
+        self.validators.push((name.into(), validator));
+        self
+    }
+}
+
+#[async_trait]
+impl AttributesValidator for MultiValidator {
+    async fn validate(&self, attributes: &L2AttributesWithParent) -> Result<bool> {
+        let mut tasks = tokio::task::JoinSet::new();
+        for (index, (_, validator)) in self.validators.iter().enumerate() {
+            let (validator, attributes) = (validator.clone(), attributes.clone());
+            tasks.spawn(async move {
+                let started = std::time::Instant::now();
+                let result = validator.validate(&attributes).await;
+                (index, started.elapsed(), result)
+            });
+        }
+
+        let mut verdicts = vec![None; self.validators.len()];
+        let mut first_err = None;
+        while let Some(joined) = tasks.join_next().await {
+            let (index, elapsed, result) =
+                joined.map_err(|e| eyre!("Validation task failed: {:?}", e))?;
+            let name = self.validators[index].0.clone();
+            metrics::histogram!("hera_validator_latency_seconds", "validator" => name.clone())
+                .record(elapsed.as_secs_f64());
+            match result {
+                Ok(valid) => verdicts[index] = Some(valid),
+                Err(err) => {
+                    warn!(?err, validator = %name, "Validator failed to return a verdict");
+                    first_err.get_or_insert(err);
+                }
+            }
+        }
+
+        let Some(valid) = self.policy.decide(&verdicts) else {
+            let err = first_err.unwrap_or_else(|| eyre!("No validator configured"));
+            return Err(err.wrap_err(format!("No {:?} quorum of validators", self.policy)));
+        };
+        for ((name, _), verdict) in self.validators.iter().zip(&verdicts) {
+            if verdict.is_some_and(|verdict| verdict != valid) {
+                warn!(validator = %name, valid, "Validator disagrees with the quorum");
+                metrics::counter!("hera_validator_disagreements_total", "validator" => name.clone())
+                    .increment(1);
+                self.events.emit(|| DriverEvent::ValidatorDisagreement {
+                    number: attributes.parent.block_info.number + 1,
+                    validator: name.clone(),
+                    verdict: !valid,
+                    outcome: valid,
+                });
+            }
+        }
+        Ok(valid)
+    }
+}
+
+/// An [AttributesValidator] that only validates 1-in-N derived attribute sets.
+///
+/// Skipped attributes are considered valid. Attributes are always validated after
+/// a failed validation, and after a reorg or a restart of the pipeline, i.e. when
+/// they don't build on the block following the previous ones.
+#[derive(Debug)]
+pub struct SampledValidator {
+    /// The validator of the sampled attributes.
+    inner: Arc<dyn AttributesValidator + Send + Sync>,
+    /// Decides which attributes are validated.
+    sampler: Mutex<Sampler>,
+}
+
+impl SampledValidator {
+    /// Creates a new [SampledValidator] validating 1-in-`rate` attribute sets with `inner`.
+    pub fn new(inner: Arc<dyn AttributesValidator + Send + Sync>, rate: u64) -> Self {
+        Self { inner, sampler: Mutex::new(Sampler::new(rate)) }
+    }
+}
+
+#[async_trait]
+impl AttributesValidator for SampledValidator {
+    async fn validate(&self, attributes: &L2AttributesWithParent) -> Result<bool> {
+        let parent = attributes.parent.block_info.number;
+        if !self.sampler.lock().expect("lock poisoned").should_validate(parent) {
+            metrics::counter!("hera_validation_skipped_total").increment(1);
+            return Ok(true);
         }
+
+        let result = self.inner.validate(attributes).await;
+        let valid = matches!(result, Ok(true));
+        self.sampler.lock().expect("lock poisoned").on_result(valid);
+        result
+    }
+}
+
+/// The sampling state of a [SampledValidator].
+#[derive(Debug)]
+struct Sampler {
+    /// One in `rate` attribute sets is validated.
+    rate: u64,
+    /// The number of attribute sets skipped since the last validated one.
+    skipped: u64,
+    /// The parent number of the previous attributes.
+    last_parent: Option<u64>,
+    /// Whether the next attributes must be validated.
+    force: bool,
+}
+
+impl Sampler {
+    /// Creates a new [Sampler] selecting 1-in-`rate` attribute sets.
+    fn new(rate: u64) -> Self {
+        Self { rate: rate.max(1), skipped: 0, last_parent: None, force: false }
+    }
+
+    /// Returns `true` if the attributes built on the given parent must be validated.
+    fn should_validate(&mut self, parent: u64) -> bool {
+        let contiguous = self.last_parent.is_some_and(|last| parent == last + 1);
+        self.last_parent = Some(parent);
+
+        if self.force || !contiguous || self.skipped + 1 >= self.rate {
+            self.skipped = 0;
+            self.force = false;
+            return true;
+        }
+        self.skipped += 1;
+        false
+    }
+
+    /// Records the outcome of a validation, forcing the next one after a failure.
+    fn on_result(&mut self, valid: bool) {
+        if !valid {
+            self.force = true;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy_rlp::Decodable;
+
+    #[test]
+    fn test_raw_block_transactions() {
+        let legacy = vec![0xc3, 0x01, 0x02, 0x03];
+        let typed = vec![0x02, 0xc2, 0x05, 0x06];
+
+        // block = [header, [legacy, typed], ommers]
+        let mut txs = vec![];
+        txs.extend_from_slice(&legacy);
+        txs.push(0x80 + typed.len() as u8);
+        txs.extend_from_slice(&typed);
+        let mut payload = vec![0xc1, 0x80];
+        payload.push(0xc0 + txs.len() as u8);
+        payload.extend_from_slice(&txs);
+        payload.push(0xc0);
+        let mut raw = vec![0xc0 + payload.len() as u8];
+        raw.extend_from_slice(&payload);
+
+        let decoded = raw_block_transactions(&raw).unwrap();
+        assert_eq!(decoded, vec![RawTransaction(legacy.into()), RawTransaction(typed.into())]);
+    }
+
+    #[test]
+    fn test_raw_block_transactions_truncated() {
+        assert!(raw_block_transactions(&[0xc4, 0xc1, 0x80, 0xc5, 0x01]).is_err());
+    }
+
+    #[test]
+    fn test_encode_deposit() {
+        let mut tx = Transaction { transaction_type: Some(DEPOSIT_TX_TYPE), ..Default::default() };
+        tx.other.insert("sourceHash".into(), serde_json::json!(B256::repeat_byte(1)));
+        tx.other.insert("mint".into(), serde_json::json!("0x10"));
+        tx.other.insert("isSystemTx".into(), serde_json::json!(false));
+
+        let RawTransaction(encoded) = encode_transaction(&tx).unwrap();
+        assert_eq!(encoded[0], DEPOSIT_TX_TYPE);
+
+        let buf = &mut &encoded[1..];
+        let header = RlpHeader::decode(buf).unwrap();
+        assert!(header.list);
+        assert_eq!(header.payload_length, buf.len());
+        assert_eq!(B256::decode(buf).unwrap(), B256::repeat_byte(1));
+    }
+
+    #[test]
+    fn test_encode_deposit_missing_source_hash() {
+        let tx = Transaction { transaction_type: Some(DEPOSIT_TX_TYPE), ..Default::default() };
+        assert!(encode_transaction(&tx).is_err());
+    }
+
+    fn fork_config() -> RollupConfig {
+        RollupConfig {
+            block_time: 2,
+            canyon_time: Some(10),
+            delta_time: Some(10),
+            ecotone_time: Some(20),
+            fjord_time: Some(30),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_check_fork_rules() {
+        let cfg = fork_config();
+        let header = Header { timestamp: 8, ..Default::default() };
+        assert!(check_fork_rules(&cfg, &header).is_ok());
+
+        let mut header = Header { timestamp: 10, ..Default::default() };
+        assert!(check_fork_rules(&cfg, &header).is_err());
+        header.withdrawals_root = Some(B256::ZERO);
+        assert!(check_fork_rules(&cfg, &header).is_ok());
+        header.parent_beacon_block_root = Some(B256::ZERO);
+        assert!(check_fork_rules(&cfg, &header).is_err());
+
+        header.timestamp = 20;
+        assert!(check_fork_rules(&cfg, &header).is_ok());
+        header.parent_beacon_block_root = None;
+        let err = check_fork_rules(&cfg, &header).unwrap_err();
+        assert!(err.to_string().contains("Ecotone is active"));
+    }
+
+    #[test]
+    fn test_activated_fork() {
+        let cfg = fork_config();
+        // Delta activates in the same block as Canyon, and the latest fork is reported.
+        assert_eq!(activated_fork(&cfg, 10), Some("Delta"));
+        assert_eq!(activated_fork(&cfg, 12), None);
+        assert_eq!(activated_fork(&cfg, 20), Some("Ecotone"));
+        assert_eq!(activated_fork(&cfg, 31), Some("Fjord"));
+        assert_eq!(activated_fork(&cfg, 32), None);
+    }
+
+    #[test]
+    fn test_quorum_policy() {
+        let verdicts = [Some(true), Some(false), None];
+        assert_eq!(QuorumPolicy::All.decide(&verdicts), Some(false));
+        assert_eq!(QuorumPolicy::Any.decide(&verdicts), Some(true));
+        assert_eq!(QuorumPolicy::Majority.decide(&verdicts), Some(false));
+
+        assert_eq!(QuorumPolicy::All.decide(&[Some(true), None]), None);
+        assert_eq!(QuorumPolicy::Any.decide(&[None, None]), None);
+        assert_eq!(QuorumPolicy::Majority.decide(&[Some(true), None, None]), None);
+        assert_eq!(QuorumPolicy::Majority.decide(&[Some(true), Some(true), None]), Some(true));
+    }
+
+    /// A validator returning a fixed verdict, or failing if there is none.
+    #[derive(Debug)]
+    struct FixedValidator(Option<bool>);
+
+    #[async_trait]
+    impl AttributesValidator for FixedValidator {
+        async fn validate(&self, _: &L2AttributesWithParent) -> Result<bool> {
+            self.0.ok_or_else(|| eyre!("unavailable"))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_multi_validator() {
+        let attributes =
+            L2AttributesWithParent::new(L2PayloadAttributes::default(), Default::default(), false);
+        let validator = MultiValidator::new(QuorumPolicy::Majority)
+            .with_validator("a", Arc::new(FixedValidator(Some(true))))
+            .with_validator("b", Arc::new(FixedValidator(Some(true))))
+            .with_validator("c", Arc::new(FixedValidator(Some(false))));
+        let mut events = validator.events.subscribe();
+        assert!(validator.validate(&attributes).await.unwrap());
+        assert_eq!(
+            events.try_recv().unwrap(),
+            DriverEvent::ValidatorDisagreement {
+                number: 1,
+                validator: "c".to_string(),
+                verdict: false,
+                outcome: true,
+            }
+        );
+
+        let validator = MultiValidator::new(QuorumPolicy::All)
+            .with_validator("a", Arc::new(FixedValidator(Some(true))))
+            .with_validator("b", Arc::new(FixedValidator(None)));
+        let err = validator.validate(&attributes).await.unwrap_err();
+        assert_eq!(err.to_string(), "No All quorum of validators");
+    }
+
+    #[test]
+    fn test_sampler() {
+        let mut sampler = Sampler::new(3);
+        let sampled: Vec<_> = (0..7).map(|parent| sampler.should_validate(parent)).collect();
+        assert_eq!(sampled, vec![true, false, false, true, false, false, true]);
+        sampler.on_result(true);
+
+        // A failed validation forces the next one.
+        assert!(!sampler.should_validate(7));
+        sampler.on_result(false);
+        assert!(sampler.should_validate(8));
+        sampler.on_result(true);
+        assert!(!sampler.should_validate(9));
+
+        // A rewind or a gap always validates.
+        assert!(sampler.should_validate(5));
+        assert!(sampler.should_validate(12));
+    }
+
+    #[tokio::test]
+    async fn test_call_timeout() {
+        let cfg = Arc::new(RollupConfig::default());
+        let validator = TrustedValidator::new_http(Url::parse("http://localhost:1").unwrap(), cfg)
+            .with_call_timeout(Duration::from_millis(10));
+        let err = validator
+            .call("eth_chainId", std::future::pending::<Result<()>>())
+            .await
+            .unwrap_err()
+            .wrap_err("context");
+
+        assert_eq!(
+            err.downcast_ref::<ValidationTimeout>(),
+            Some(&ValidationTimeout::Call {
+                method: "eth_chainId",
+                timeout: Duration::from_millis(10)
+            })
+        );
     }
 }
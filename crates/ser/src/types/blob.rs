@@ -0,0 +1,125 @@
+//! Encoding of batcher data into blobs.
+
+use alloc::vec::Vec;
+use eyre::{bail, Result};
+
+/// The size of a blob, in bytes.
+pub const BLOB_SIZE: usize = 4096 * 32;
+
+/// The maximum size of the data encoded in a single blob.
+pub const MAX_BLOB_DATA_SIZE: usize = (4 * 31 + 3) * 1024 - 4;
+
+/// The version of the blob encoding.
+const BLOB_ENCODING_VERSION: u8 = 0;
+
+/// The number of encoding rounds, each filling 4 field elements.
+const BLOB_ENCODING_ROUNDS: usize = 1024;
+
+/// Encodes the given data into a blob, as specified for the OP Stack batcher.
+///
+/// The data is prefixed with the encoding version and its length, then spread over the
+/// field elements of the blob so that the first byte of each is below the BLS modulus:
+/// every 4 field elements hold 4 * 31 bytes in their lower bytes, plus 3 bytes split
+/// into the 6 low bits of their first bytes. Decoding is done by kona's `BlobData`.
+pub fn encode_blob(data: &[u8]) -> Result<Vec<u8>> {
+    if data.len() > MAX_BLOB_DATA_SIZE {
+        bail!("Data of {} bytes exceeds the max blob data size", data.len());
+    }
+
+    let mut blob = alloc::vec![0u8; BLOB_SIZE];
+    let mut reader = Reader { data, offset: 0 };
+    let mut offset = 0;
+    let mut write = |first: u8, chunk: &[u8; 31]| {
+        blob[offset] = first;
+        blob[offset + 1..offset + 32].copy_from_slice(chunk);
+        offset += 32;
+    };
+
+    for round in 0..BLOB_ENCODING_ROUNDS {
+        if reader.offset >= data.len() {
+            break;
+        }
+        let chunk = if round == 0 {
+            let mut chunk = [0u8; 31];
+            chunk[0] = BLOB_ENCODING_VERSION;
+            chunk[1..4].copy_from_slice(&(data.len() as u32).to_be_bytes()[1..]);
+            let n = data.len().min(27);
+            chunk[4..4 + n].copy_from_slice(&data[..n]);
+            reader.offset = n;
+            chunk
+        } else {
+            reader.read31()
+        };
+
+        let x = reader.read1();
+        write(x & 0b0011_1111, &chunk);
+
+        let chunk = reader.read31();
+        let y = reader.read1();
+        write((y & 0b0000_1111) | ((x & 0b1100_0000) >> 2), &chunk);
+
+        let chunk = reader.read31();
+        let z = reader.read1();
+        write(z & 0b0011_1111, &chunk);
+
+        let chunk = reader.read31();
+        write(((z & 0b1100_0000) >> 2) | ((y & 0b1111_0000) >> 4), &chunk);
+    }
+
+    Ok(blob)
+}
+
+/// Reads the data to encode, padding it with zeros.
+struct Reader<'a> {
+    /// The data to encode.
+    data: &'a [u8],
+    /// The number of bytes read.
+    offset: usize,
+}
+
+impl Reader<'_> {
+    /// Reads the next byte.
+    fn read1(&mut self) -> u8 {
+        let byte = self.data.get(self.offset).copied().unwrap_or_default();
+        self.offset += 1;
+        byte
+    }
+
+    /// Reads the next 31 bytes.
+    fn read31(&mut self) -> [u8; 31] {
+        let mut chunk = [0u8; 31];
+        let start = self.offset.min(self.data.len());
+        let n = (self.data.len() - start).min(31);
+        chunk[..n].copy_from_slice(&self.data[start..start + n]);
+        self.offset += 31;
+        chunk
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_blob_layout() {
+        let data: Vec<u8> = (0..1000).map(|i| (i % 251) as u8).collect();
+        let encoded = encode_blob(&data).unwrap();
+        assert_eq!(encoded.len(), BLOB_SIZE);
+        assert_eq!(encoded[1], BLOB_ENCODING_VERSION);
+        assert_eq!(encoded[2..5], [0, 0x03, 0xe8]);
+        assert_eq!(encoded[5..32], data[..27]);
+        assert_eq!(encoded[0], data[27] & 0b0011_1111);
+        assert_eq!(encoded[33..64], data[28..59]);
+        assert!(encoded[1024..].iter().all(|b| *b == 0));
+
+        assert!(encode_blob(&[]).unwrap().iter().all(|b| *b == 0));
+        assert!(encode_blob(&[0; MAX_BLOB_DATA_SIZE]).is_ok());
+        assert!(encode_blob(&[0; MAX_BLOB_DATA_SIZE + 1]).is_err());
+    }
+
+    #[test]
+    fn test_encode_blob_field_elements() {
+        let encoded = encode_blob(&[0xff; 1000]).unwrap();
+        assert!(encoded.chunks(32).all(|element| element[0] & 0b1100_0000 == 0));
+    }
+}
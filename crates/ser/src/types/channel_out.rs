@@ -17,7 +17,10 @@ use crate::traits::Compressor;
 /// in the [Frame Format][ff] specs: 16 + 2 + 4 + 1 = 23 bytes.
 ///
 /// [ff]: https://github.com/ethereum-optimism/specs/blob/main/specs/protocol/derivation.md#frame-format
-pub(crate) const FRAME_V0_OVERHEAD_SIZE: u64 = 23;
+pub const FRAME_V0_OVERHEAD_SIZE: u64 = 23;
+
+/// The default max size of a frame, fitting in a calldata batcher transaction.
+pub const DEFAULT_MAX_FRAME_SIZE: u64 = 120_000;
 
 /// The channel output type.
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -44,6 +47,8 @@ pub struct ChannelOut<C: Compressor> {
     pub channel_timeout: u64,
     /// The sub safety margin.
     pub sub_safety_margin: u64,
+    /// If the last frame was output.
+    pub last_output: bool,
 }
 
 impl<C: Compressor> ChannelOut<C> {
@@ -59,18 +64,27 @@ impl<C: Compressor> ChannelOut<C> {
             frame: 0,
             rlp_length: 0,
             closed: false,
-            max_frame_size: 0,
+            max_frame_size: DEFAULT_MAX_FRAME_SIZE,
             rollup_config: cfg,
             compressor: c,
             sequencer_window_timeout: epoch_num + sequencer_window_size - sub_safety_margin,
             channel_timeout: epoch_num + max_channel_duration,
             sub_safety_margin,
+            last_output: false,
         }
     }
 
-    /// Returns the max channel duration.
-    pub fn max_channel_duration(_cfg: &RollupConfig) -> u64 {
-        unimplemented!()
+    /// Sets the max size of the output frames, including their overhead.
+    pub fn with_max_frame_size(mut self, max_frame_size: u64) -> Self {
+        self.max_frame_size = max_frame_size.max(FRAME_V0_OVERHEAD_SIZE + 1);
+        self
+    }
+
+    /// Returns the max channel duration, in L1 blocks.
+    ///
+    /// The channel must be fully submitted before it times out on L1.
+    pub const fn max_channel_duration(cfg: &RollupConfig) -> u64 {
+        cfg.channel_timeout
     }
 
     /// Returns the ready bytes from the channel.
@@ -92,15 +106,21 @@ impl<C: Compressor> ChannelOut<C> {
     pub fn check_timed_out(&mut self, l1_block_num: u64) {
         if self.sequencer_window_timeout < l1_block_num || self.channel_timeout < l1_block_num {
             warn!(target: "channel-out", "Batch is timed out. Closing channel: {:?}", self.id);
-            self.closed = true;
+            if let Err(e) = self.close() {
+                error!(target: "channel-out", "Error closing compressor: {:?}", e);
+            }
         }
     }
 
     /// Adds a batch to the [ChannelOut].
-    pub fn add_batch(&mut self, batch: SingleBatch) {
+    ///
+    /// Fails if the channel is closed, or if the batch would exceed the max RLP bytes
+    /// per channel, in which case the channel is closed. The channel is closed once the
+    /// compressor is full.
+    pub fn add_batch(&mut self, batch: SingleBatch) -> Result<()> {
         if self.closed {
             warn!(target: "channel-out", "Channel is closed. Not adding batch: {:?}", self.id);
-            return;
+            bail!("Channel is closed");
         }
 
         // RLP encode the batch
@@ -110,8 +130,8 @@ impl<C: Compressor> ChannelOut<C> {
         let max_per_channel = self.max_rlp_bytes_per_channel(&batch);
         if self.rlp_length + buf.len() as u64 > max_per_channel {
             warn!(target: "channel-out", "Batch exceeds max RLP bytes per channel ({}). Closing channel: {:?}", max_per_channel, self.id);
-            self.closed = true;
-            return;
+            self.close()?;
+            bail!("Batch exceeds max RLP bytes per channel");
         }
 
         self.rlp_length += buf.len() as u64;
@@ -120,8 +140,20 @@ impl<C: Compressor> ChannelOut<C> {
             Ok(n) => trace!(target: "channel-out", "Wrote {} bytes to compressor", n),
             Err(e) => {
                 error!(target: "channel-out", "Error writing batch to compressor: {:?}", e);
+                bail!("Error writing batch to compressor: {:?}", e);
             }
         }
+        if self.compressor.is_full() {
+            self.close()?;
+        }
+        Ok(())
+    }
+
+    /// Closes the channel, so that its remaining data is output in frames, the last one
+    /// being marked as such.
+    pub fn close(&mut self) -> Result<()> {
+        self.closed = true;
+        self.compressor.close()
     }
 
     /// Updates the channel timeout when a frame is published.
@@ -143,9 +175,10 @@ impl<C: Compressor> ChannelOut<C> {
         self.compressor.flush()
     }
 
-    /// Compresses the channel and reads the compressed data.
+    /// Compresses the channel and reads the compressed data of the next frame.
     pub fn compress(&mut self) -> Result<Vec<u8>> {
-        let mut buf = vec![0; self.ready_bytes()];
+        let max_data_size = (self.max_frame_size - FRAME_V0_OVERHEAD_SIZE) as usize;
+        let mut buf = vec![0; self.ready_bytes().min(max_data_size)];
         match self.compressor.read(&mut buf) {
             Ok(n) => trace!(target: "channel-out", "Read {} bytes from compressor", n),
             Err(e) => {
@@ -157,8 +190,10 @@ impl<C: Compressor> ChannelOut<C> {
     }
 
     /// Outputs the next frame if available.
+    ///
+    /// Once the channel is closed, frames are output until the last one.
     pub fn output_frame(&mut self) -> Option<Frame> {
-        if !self.frame_ready() {
+        if self.is_done() || (!self.closed && !self.frame_ready()) {
             return None;
         }
 
@@ -170,8 +205,10 @@ impl<C: Compressor> ChannelOut<C> {
             }
         };
 
-        let frame = Frame { id: self.id, number: self.frame, data, is_last: false };
+        let is_last = self.closed && self.ready_bytes() == 0;
+        let frame = Frame { id: self.id, number: self.frame, data, is_last };
         self.frame += 1;
+        self.last_output = is_last;
 
         // If the max frame number is reached,
         // the channel must be closed.
@@ -182,4 +219,9 @@ impl<C: Compressor> ChannelOut<C> {
 
         Some(frame)
     }
+
+    /// Returns `true` once the last frame of the channel was output.
+    pub const fn is_done(&self) -> bool {
+        self.last_output
+    }
 }
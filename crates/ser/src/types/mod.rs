@@ -1,4 +1,10 @@
 //! Types for compressing OP Types.
 
 mod channel_out;
-pub use channel_out::ChannelOut;
+pub use channel_out::{ChannelOut, DEFAULT_MAX_FRAME_SIZE, FRAME_V0_OVERHEAD_SIZE};
+
+mod zlib_compressor;
+pub use zlib_compressor::{ZlibCompressor, DEFAULT_APPROX_COMPRESSION_RATIO};
+
+mod blob;
+pub use blob::{encode_blob, BLOB_SIZE, MAX_BLOB_DATA_SIZE};
@@ -0,0 +1,131 @@
+//! A zlib [Compressor] for channel data.
+
+use alloc::vec::Vec;
+use eyre::{bail, Result};
+use miniz_oxide::deflate::compress_to_vec_zlib;
+
+use crate::traits::Compressor;
+
+/// The default estimated ratio of the compressed size to the uncompressed size of
+/// channel data, as used by op-batcher's ratio compressor.
+pub const DEFAULT_APPROX_COMPRESSION_RATIO: f64 = 0.6;
+
+/// The zlib compression level, the best one.
+const COMPRESSION_LEVEL: u8 = 9;
+
+/// A [Compressor] that buffers the channel data and zlib compresses it on close.
+///
+/// The compressed size is estimated with a fixed compression ratio, to decide when the
+/// compressor is full. The compressed data is only available once closed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ZlibCompressor {
+    /// The uncompressed data.
+    input: Vec<u8>,
+    /// The compressed data, once closed.
+    output: Vec<u8>,
+    /// The number of compressed bytes already read.
+    read: usize,
+    /// Whether the compressor is closed.
+    closed: bool,
+    /// The target size of the compressed data.
+    target_size: usize,
+    /// The estimated compression ratio.
+    approx_ratio: f64,
+}
+
+impl ZlibCompressor {
+    /// Creates a new [ZlibCompressor], full once the compressed data is estimated to
+    /// reach the given target size.
+    pub const fn new(target_size: usize) -> Self {
+        Self {
+            input: Vec::new(),
+            output: Vec::new(),
+            read: 0,
+            closed: false,
+            target_size,
+            approx_ratio: DEFAULT_APPROX_COMPRESSION_RATIO,
+        }
+    }
+
+    /// Sets the estimated ratio of the compressed size to the uncompressed size.
+    pub const fn with_approx_ratio(mut self, approx_ratio: f64) -> Self {
+        self.approx_ratio = approx_ratio;
+        self
+    }
+
+    /// Returns the estimated size of the compressed data.
+    fn estimated_size(&self) -> usize {
+        (self.input.len() as f64 * self.approx_ratio) as usize
+    }
+}
+
+impl Compressor for ZlibCompressor {
+    fn is_empty(&self) -> bool {
+        self.input.is_empty()
+    }
+
+    /// Returns the number of compressed bytes left to read, which is zero until closed.
+    fn len(&self) -> usize {
+        self.output.len() - self.read
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        // The data is compressed at once on close, so there is nothing to flush.
+        Ok(())
+    }
+
+    fn is_full(&self) -> bool {
+        self.estimated_size() >= self.target_size
+    }
+
+    fn write(&mut self, data: &[u8]) -> Result<usize> {
+        if self.closed {
+            bail!("Compressor is closed");
+        }
+        self.input.extend_from_slice(data);
+        Ok(data.len())
+    }
+
+    fn read(&mut self, data: &mut [u8]) -> Result<usize> {
+        let n = data.len().min(self.len());
+        data[..n].copy_from_slice(&self.output[self.read..self.read + n]);
+        self.read += n;
+        Ok(n)
+    }
+
+    fn close(&mut self) -> Result<()> {
+        if !self.closed {
+            self.output = compress_to_vec_zlib(&self.input, COMPRESSION_LEVEL);
+            self.closed = true;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use miniz_oxide::inflate::decompress_to_vec_zlib;
+
+    #[test]
+    fn test_compress_roundtrip() {
+        let mut compressor = ZlibCompressor::new(60);
+        assert!(compressor.is_empty());
+        compressor.write(&[7; 50]).unwrap();
+        assert!(!compressor.is_full());
+        compressor.write(&[8; 50]).unwrap();
+        assert!(compressor.is_full());
+        assert_eq!(compressor.len(), 0);
+
+        compressor.close().unwrap();
+        assert!(compressor.write(&[1]).is_err());
+        let mut first = vec![0; 4];
+        assert_eq!(compressor.read(&mut first).unwrap(), 4);
+        let mut rest = vec![0; compressor.len()];
+        compressor.read(&mut rest).unwrap();
+        assert_eq!(compressor.len(), 0);
+
+        let data = decompress_to_vec_zlib(&[first, rest].concat()).unwrap();
+        assert_eq!(data, [[7; 50], [8; 50]].concat());
+    }
+}